@@ -7,27 +7,29 @@ use std::{
     sync::atomic::{AtomicU64, Ordering},
 };
 
-use crate::graphics::{colors::Rgb, display::Canvas};
+use crate::graphics::{codec::PngImage, colors::Rgb, display::Canvas};
 
 type ExternalResult<T> = Result<T, Box<dyn Error>>;
 
 static TEMP_PPM_COUNTER: AtomicU64 = AtomicU64::new(0);
 
-/// Converts an image to a [`Canvas`], converting non-PPM images to a sibling `.ppm` file first.
+/// Converts an image to a [`Canvas`].
 ///
 /// # Arguments
 /// * `file_name` - The name of the file to load.
 /// * `pos_glitch` - Whether to swap the parsed canvas dimensions after loading.
 ///
 /// # Note
-/// Non-PPM inputs are converted through a temporary `.ppm` file that is removed after parsing.
+/// PPM and PNG inputs are decoded natively; PNG alpha is discarded. Other formats are converted
+/// through a temporary `.ppm` file with `ImageMagick` that is removed after parsing.
 ///
 /// # Errors
 /// Returns an error if `file_name` does not exist, has no valid extension, or cannot be read.
-/// Non-PPM inputs also return an error if `ImageMagick`'s `magick` command cannot convert the image
-/// to PPM. PPM parsing returns an error for malformed headers or pixel tokens, unsupported magic
-/// values other than `P3` and `P6`, unsupported `maxval` 0, channel values greater than `maxval`,
-/// oversized image dimensions or byte counts, missing binary separators, and truncated pixel data.
+/// PNG inputs return an error if the PNG stream is malformed. Other non-PPM inputs return an error
+/// if `ImageMagick`'s `magick` command cannot convert the image to PPM. PPM parsing returns an
+/// error for malformed headers or pixel tokens, unsupported magic values other than `P3` and `P6`,
+/// unsupported `maxval` 0, channel values greater than `maxval`, oversized image dimensions or byte
+/// counts, missing binary separators, and truncated pixel data.
 ///
 /// # Examples
 ///
@@ -66,6 +68,8 @@ pub fn ppmify(file_name: &str, pos_glitch: bool) -> ExternalResult<Canvas> {
 
     let canvas = if ext == "ppm" {
         parse_ppm(path)?
+    } else if ext == "png" {
+        PngImage::open(path)?.to_canvas()
     } else {
         let converted = temp_ppm_path(path)?;
         let status = Command::new("magick").arg(path).arg(&converted).status()?;
@@ -268,7 +272,7 @@ fn parse_ppm(path: &Path) -> Result<Canvas, Box<dyn Error>> {
 #[cfg(test)]
 mod tests {
     use super::{ppmify, temp_ppm_path};
-    use crate::graphics::colors::Rgb;
+    use crate::graphics::{colors::Rgb, display::Canvas};
     use std::{
         fs,
         path::{Path, PathBuf},
@@ -346,6 +350,21 @@ mod tests {
         let _ = fs::remove_file(path);
     }
 
+    #[test]
+    fn parses_png_without_imagemagick() {
+        let path = temp_file("native-png", "png");
+        let mut canvas = Canvas::new(2, 1, Rgb::BLACK);
+        canvas.fill_canvas(vec![Rgb::new(1, 2, 3), Rgb::new(250, 128, 0)]);
+        canvas
+            .save_extension(path.to_str().expect("utf8 path"))
+            .expect("write png");
+
+        let parsed = ppmify(path.to_str().expect("utf8 path"), false).expect("parse png");
+
+        assert_eq!(parsed.pixels(), canvas.pixels());
+        let _ = fs::remove_file(path);
+    }
+
     #[test]
     fn temp_ppm_paths_are_unique_per_call() {
         let path = Path::new("/tmp/source.png");
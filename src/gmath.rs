@@ -7,9 +7,13 @@ pub mod edge_matrix;
 pub mod geometry;
 /// Hosts various helpers to make math easier.
 pub mod helpers;
+/// Scrambled Sobol, Halton, and blue-noise low-discrepancy sequences.
+pub mod low_discrepancy;
 /// Includes the [`matrix::Matrix`] struct with a surrounding mini matrix library
 /// to make it easier for a user to draw onto the Canvas.
 pub mod matrix;
+/// Keyframed transforms decomposed into translation, rotation, and scale for motion blur.
+pub mod motion;
 /// Hosts the [`parametric::Parametric`] struct.
 pub mod parametric;
 /// Deterministic Perlin noise for procedural textures.
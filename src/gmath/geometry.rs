@@ -5,11 +5,17 @@ use super::{
     polygon_matrix::Bounds3,
     vector::{Point, Vector},
 };
+use std::f64::consts::PI;
 
 const TRIANGLE_BOUNDS_EPSILON: f64 = 1e-10;
 const TRIANGLE_HIT_EPSILON: f64 = 1e-10;
 const QUAD_BOUNDS_EPSILON: f64 = 1e-4;
 const QUAD_HIT_EPSILON: f64 = 1e-8;
+const CURVED_BOUNDS_EPSILON: f64 = 1e-4;
+const CURVED_HIT_EPSILON: f64 = 1e-12;
+const POLYNOMIAL_EPSILON: f64 = 1e-9;
+const QUARTIC_NEWTON_STEPS: usize = 2;
+const MAX_CURVE_DEPTH: u32 = 10;
 
 /// Orthonormal camera frame derived from an eye point, target point, and view-up vector.
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -159,6 +165,12 @@ impl OrthonormalBasis {
     pub fn local(self, local: Vector) -> Vector {
         local.x() * self.u + local.y() * self.v + local.z() * self.w
     }
+
+    /// Converts a world vector into this basis' local coordinates.
+    #[must_use]
+    pub fn to_local(self, world: Vector) -> Vector {
+        Vector::new(self.u.dot(world), self.v.dot(world), self.w.dot(world))
+    }
 }
 
 /// Analytic triangle geometry shared by raster and ray-tracing paths.
@@ -538,6 +550,1274 @@ impl MovingSphereGeometry {
     }
 }
 
+/// Surface data for a ray hit on a curved analytic shape.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShapeHit {
+    /// Ray parameter at the hit.
+    pub t: f64,
+    /// Unit-length outward normal at the hit point.
+    pub normal: Vector,
+    /// Horizontal texture coordinate at the hit point.
+    pub u: f64,
+    /// Vertical texture coordinate at the hit point.
+    pub v: f64,
+}
+
+/// Analytic disk geometry centered on `center` and facing along its normal.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DiskGeometry {
+    center: Point,
+    basis: OrthonormalBasis,
+    radius: f64,
+}
+
+impl DiskGeometry {
+    /// Creates a disk centered on `center` whose front face points along `normal`.
+    ///
+    /// A zero `normal` produces a degenerate disk that is never hit.
+    #[must_use]
+    pub fn new(center: Point, normal: Vector, radius: f64) -> Self {
+        let (basis, length) = axis_frame(normal);
+        Self {
+            center,
+            basis,
+            radius: if length > 0.0 { radius.max(0.0) } else { 0.0 },
+        }
+    }
+
+    /// Returns the disk center.
+    #[must_use]
+    pub const fn center(self) -> Point {
+        self.center
+    }
+
+    /// Returns the unit front-face normal.
+    #[must_use]
+    pub const fn normal(self) -> Vector {
+        self.basis.w
+    }
+
+    /// Returns the local frame whose `w` axis is the disk normal.
+    #[must_use]
+    pub const fn basis(self) -> OrthonormalBasis {
+        self.basis
+    }
+
+    /// Returns the disk radius.
+    #[must_use]
+    pub const fn radius(self) -> f64 {
+        self.radius
+    }
+
+    /// Returns the disk area.
+    #[must_use]
+    pub fn area(self) -> f64 {
+        PI * self.radius * self.radius
+    }
+
+    /// Returns padded axis-aligned bounds for the disk.
+    #[must_use]
+    pub fn bounds(self) -> Bounds3 {
+        circle_bounds(self.center, self.basis.w, self.radius).padded(CURVED_BOUNDS_EPSILON)
+    }
+
+    /// Returns ray hit data for a two-sided ray/disk intersection.
+    ///
+    /// Texture coordinates map the disk onto the unit square along the basis `u` and `v` axes.
+    #[must_use]
+    pub fn hit_ray(self, ray: &Ray, t_min: f64, t_max: f64) -> Option<ShapeHit> {
+        if self.radius <= 0.0 {
+            return None;
+        }
+
+        let (origin, direction) = local_ray(self.basis, self.center, ray);
+        let (t, u, v) = hit_cap(origin, direction, 0.0, self.radius, t_min, t_max)?;
+        Some(ShapeHit {
+            t,
+            normal: self.basis.w,
+            u,
+            v,
+        })
+    }
+}
+
+/// Analytic cylinder geometry running from `base` along an axis vector.
+///
+/// Cylinders are capped by default, so they bound a closed volume and can be used as media
+/// boundaries. Use [`Self::with_caps`] for an open tube.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CylinderGeometry {
+    base: Point,
+    basis: OrthonormalBasis,
+    height: f64,
+    radius: f64,
+    capped: bool,
+}
+
+impl CylinderGeometry {
+    /// Creates a capped cylinder from its base center, base-to-top `axis`, and radius.
+    #[must_use]
+    pub fn new(base: Point, axis: Vector, radius: f64) -> Self {
+        let (basis, height) = axis_frame(axis);
+        Self {
+            base,
+            basis,
+            height,
+            radius: radius.max(0.0),
+            capped: true,
+        }
+    }
+
+    /// Returns this cylinder with or without its two end caps.
+    #[must_use]
+    pub const fn with_caps(self, capped: bool) -> Self {
+        Self { capped, ..self }
+    }
+
+    /// Returns the base center.
+    #[must_use]
+    pub const fn base(self) -> Point {
+        self.base
+    }
+
+    /// Returns the top center.
+    #[must_use]
+    pub fn top(self) -> Point {
+        self.base + self.height * self.basis.w
+    }
+
+    /// Returns the local frame whose `w` axis runs from base to top.
+    #[must_use]
+    pub const fn basis(self) -> OrthonormalBasis {
+        self.basis
+    }
+
+    /// Returns the distance from base to top.
+    #[must_use]
+    pub const fn height(self) -> f64 {
+        self.height
+    }
+
+    /// Returns the cylinder radius.
+    #[must_use]
+    pub const fn radius(self) -> f64 {
+        self.radius
+    }
+
+    /// Returns true when the end caps are part of the surface.
+    #[must_use]
+    pub const fn is_capped(self) -> bool {
+        self.capped
+    }
+
+    /// Returns the surface area of the side, plus both caps when capped.
+    #[must_use]
+    pub fn area(self) -> f64 {
+        self.side_area()
+            + if self.capped {
+                2.0 * self.cap_area()
+            } else {
+                0.0
+            }
+    }
+
+    /// Returns the area of the curved side.
+    #[must_use]
+    pub fn side_area(self) -> f64 {
+        2.0 * PI * self.radius * self.height
+    }
+
+    /// Returns the area of one end cap.
+    #[must_use]
+    pub fn cap_area(self) -> f64 {
+        PI * self.radius * self.radius
+    }
+
+    /// Returns padded axis-aligned bounds for the cylinder.
+    #[must_use]
+    pub fn bounds(self) -> Bounds3 {
+        circle_bounds(self.base, self.basis.w, self.radius)
+            .union(circle_bounds(self.top(), self.basis.w, self.radius))
+            .padded(CURVED_BOUNDS_EPSILON)
+    }
+
+    /// Returns the closest ray hit on the side or caps.
+    ///
+    /// Side texture coordinates are `(angle, height)` fractions; caps map onto the unit square.
+    #[must_use]
+    pub fn hit_ray(self, ray: &Ray, t_min: f64, mut t_max: f64) -> Option<ShapeHit> {
+        if self.radius <= 0.0 || self.height <= 0.0 {
+            return None;
+        }
+
+        let (origin, direction) = local_ray(self.basis, self.base, ray);
+        let mut closest = None;
+        let a = direction.x() * direction.x() + direction.y() * direction.y();
+        let half_b = origin.x() * direction.x() + origin.y() * direction.y();
+        let c = origin.x() * origin.x() + origin.y() * origin.y() - self.radius * self.radius;
+        for t in quadratic_roots(a, half_b, c).iter() {
+            let point = origin + t * direction;
+            if t_min < t && t < t_max && (0.0..=self.height).contains(&point.z()) {
+                let normal = Vector::new(point.x() / self.radius, point.y() / self.radius, 0.0);
+                closest = Some(ShapeHit {
+                    t,
+                    normal: self.basis.local(normal),
+                    u: azimuth_fraction(point.x(), point.y()),
+                    v: point.z() / self.height,
+                });
+                t_max = t;
+                break;
+            }
+        }
+
+        if self.capped {
+            for (z, side) in [(0.0, -1.0), (self.height, 1.0)] {
+                if let Some((t, u, v)) = hit_cap(origin, direction, z, self.radius, t_min, t_max) {
+                    closest = Some(ShapeHit {
+                        t,
+                        normal: side * self.basis.w,
+                        u,
+                        v,
+                    });
+                    t_max = t;
+                }
+            }
+        }
+
+        closest
+    }
+}
+
+/// Analytic cone geometry with its base disk at `base` and its apex along an axis vector.
+///
+/// Cones are capped by default, so they bound a closed volume and can be used as media
+/// boundaries. Use [`Self::with_caps`] to drop the base disk.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ConeGeometry {
+    base: Point,
+    basis: OrthonormalBasis,
+    height: f64,
+    radius: f64,
+    capped: bool,
+}
+
+impl ConeGeometry {
+    /// Creates a capped cone from its base center, base-to-apex `axis`, and base radius.
+    #[must_use]
+    pub fn new(base: Point, axis: Vector, radius: f64) -> Self {
+        let (basis, height) = axis_frame(axis);
+        Self {
+            base,
+            basis,
+            height,
+            radius: radius.max(0.0),
+            capped: true,
+        }
+    }
+
+    /// Returns this cone with or without its base disk.
+    #[must_use]
+    pub const fn with_caps(self, capped: bool) -> Self {
+        Self { capped, ..self }
+    }
+
+    /// Returns the base center.
+    #[must_use]
+    pub const fn base(self) -> Point {
+        self.base
+    }
+
+    /// Returns the apex.
+    #[must_use]
+    pub fn apex(self) -> Point {
+        self.base + self.height * self.basis.w
+    }
+
+    /// Returns the local frame whose `w` axis runs from base to apex.
+    #[must_use]
+    pub const fn basis(self) -> OrthonormalBasis {
+        self.basis
+    }
+
+    /// Returns the distance from base to apex.
+    #[must_use]
+    pub const fn height(self) -> f64 {
+        self.height
+    }
+
+    /// Returns the base radius.
+    #[must_use]
+    pub const fn radius(self) -> f64 {
+        self.radius
+    }
+
+    /// Returns true when the base disk is part of the surface.
+    #[must_use]
+    pub const fn is_capped(self) -> bool {
+        self.capped
+    }
+
+    /// Returns the surface area of the slanted side, plus the base disk when capped.
+    #[must_use]
+    pub fn area(self) -> f64 {
+        self.side_area() + if self.capped { self.cap_area() } else { 0.0 }
+    }
+
+    /// Returns the area of the slanted side.
+    #[must_use]
+    pub fn side_area(self) -> f64 {
+        PI * self.radius * self.radius.hypot(self.height)
+    }
+
+    /// Returns the area of the base disk.
+    #[must_use]
+    pub fn cap_area(self) -> f64 {
+        PI * self.radius * self.radius
+    }
+
+    /// Returns the unit outward side normal at azimuth `phi` around the local frame.
+    #[must_use]
+    pub fn side_normal(self, phi: f64) -> Vector {
+        let (sin_phi, cos_phi) = phi.sin_cos();
+        let slope = self.radius / self.height;
+        self.basis
+            .local(Vector::new(cos_phi, sin_phi, slope) / slope.hypot(1.0))
+    }
+
+    /// Returns padded axis-aligned bounds for the cone.
+    #[must_use]
+    pub fn bounds(self) -> Bounds3 {
+        circle_bounds(self.base, self.basis.w, self.radius)
+            .union_point(self.apex())
+            .padded(CURVED_BOUNDS_EPSILON)
+    }
+
+    /// Returns the closest ray hit on the side or base.
+    ///
+    /// Side texture coordinates are `(angle, height)` fractions; the base maps onto the unit
+    /// square.
+    #[must_use]
+    pub fn hit_ray(self, ray: &Ray, t_min: f64, mut t_max: f64) -> Option<ShapeHit> {
+        if self.radius <= 0.0 || self.height <= 0.0 {
+            return None;
+        }
+
+        let (origin, direction) = local_ray(self.basis, self.base, ray);
+        let slope = self.radius / self.height;
+        let slope_squared = slope * slope;
+        let to_apex = self.height - origin.z();
+        let a = direction.x() * direction.x() + direction.y() * direction.y()
+            - slope_squared * direction.z() * direction.z();
+        let half_b = origin.x() * direction.x()
+            + origin.y() * direction.y()
+            + slope_squared * to_apex * direction.z();
+        let c =
+            origin.x() * origin.x() + origin.y() * origin.y() - slope_squared * to_apex * to_apex;
+        let mut closest = None;
+        for t in quadratic_roots(a, half_b, c).iter() {
+            let point = origin + t * direction;
+            if t_min < t && t < t_max && (0.0..=self.height).contains(&point.z()) {
+                let phi = point.y().atan2(point.x());
+                closest = Some(ShapeHit {
+                    t,
+                    normal: self.side_normal(phi),
+                    u: azimuth_fraction(point.x(), point.y()),
+                    v: point.z() / self.height,
+                });
+                t_max = t;
+                break;
+            }
+        }
+
+        if self.capped
+            && let Some((t, u, v)) = hit_cap(origin, direction, 0.0, self.radius, t_min, t_max)
+        {
+            closest = Some(ShapeHit {
+                t,
+                normal: -self.basis.w,
+                u,
+                v,
+            });
+        }
+
+        closest
+    }
+}
+
+/// Analytic torus geometry around `center`, with its ring in the plane normal to an axis.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TorusGeometry {
+    center: Point,
+    basis: OrthonormalBasis,
+    major_radius: f64,
+    minor_radius: f64,
+}
+
+impl TorusGeometry {
+    /// Creates a torus from its center, ring axis, ring (major) radius, and tube (minor) radius.
+    ///
+    /// The tube radius is clamped to the ring radius so the surface never self-intersects. A zero
+    /// `axis` produces a degenerate torus that is never hit.
+    #[must_use]
+    pub fn new(center: Point, axis: Vector, major_radius: f64, minor_radius: f64) -> Self {
+        let (basis, length) = axis_frame(axis);
+        let major_radius = if length > 0.0 {
+            major_radius.max(0.0)
+        } else {
+            0.0
+        };
+        Self {
+            center,
+            basis,
+            major_radius,
+            minor_radius: minor_radius.clamp(0.0, major_radius),
+        }
+    }
+
+    /// Returns the torus center.
+    #[must_use]
+    pub const fn center(self) -> Point {
+        self.center
+    }
+
+    /// Returns the unit ring axis.
+    #[must_use]
+    pub const fn axis(self) -> Vector {
+        self.basis.w
+    }
+
+    /// Returns the local frame whose `w` axis is the ring axis.
+    #[must_use]
+    pub const fn basis(self) -> OrthonormalBasis {
+        self.basis
+    }
+
+    /// Returns the distance from the center to the middle of the tube.
+    #[must_use]
+    pub const fn major_radius(self) -> f64 {
+        self.major_radius
+    }
+
+    /// Returns the tube radius.
+    #[must_use]
+    pub const fn minor_radius(self) -> f64 {
+        self.minor_radius
+    }
+
+    /// Returns the torus surface area.
+    #[must_use]
+    pub fn area(self) -> f64 {
+        4.0 * PI * PI * self.major_radius * self.minor_radius
+    }
+
+    /// Returns the surface point and unit outward normal at ring angle `phi` and tube angle
+    /// `theta`.
+    #[must_use]
+    pub fn surface_point(self, phi: f64, theta: f64) -> (Point, Vector) {
+        let (sin_phi, cos_phi) = phi.sin_cos();
+        let (sin_theta, cos_theta) = theta.sin_cos();
+        let normal = self.basis.local(Vector::new(
+            cos_theta * cos_phi,
+            cos_theta * sin_phi,
+            sin_theta,
+        ));
+        let ring = self
+            .basis
+            .local(Vector::new(cos_phi, sin_phi, 0.0) * self.major_radius);
+        (self.center + ring + self.minor_radius * normal, normal)
+    }
+
+    /// Returns padded axis-aligned bounds for the torus.
+    #[must_use]
+    pub fn bounds(self) -> Bounds3 {
+        let tube = Vector::new(self.minor_radius, self.minor_radius, self.minor_radius);
+        let ring = circle_bounds(self.center, self.basis.w, self.major_radius);
+        Bounds3::from_points(
+            Point::new(ring.min.0, ring.min.1, ring.min.2) - tube,
+            Point::new(ring.max.0, ring.max.1, ring.max.2) + tube,
+        )
+        .padded(CURVED_BOUNDS_EPSILON)
+    }
+
+    /// Returns the closest ray hit by solving the torus quartic.
+    ///
+    /// Texture coordinates are `(ring angle, tube angle)` fractions.
+    #[must_use]
+    pub fn hit_ray(self, ray: &Ray, t_min: f64, t_max: f64) -> Option<ShapeHit> {
+        if self.minor_radius <= 0.0 {
+            return None;
+        }
+
+        // Solve in units of the bounding radius along a unit direction, starting near the
+        // bounding sphere, so the quartic coefficients stay well conditioned.
+        let scale = self.major_radius + self.minor_radius;
+        let (origin, direction) = local_ray(self.basis, self.center, ray);
+        let speed = direction.length();
+        if speed <= f64::EPSILON {
+            return None;
+        }
+        let origin = origin / scale;
+        let direction = direction / speed;
+        let to_center = origin.dot(direction);
+        let discriminant = to_center * to_center - (origin.length_squared() - 1.0);
+        if discriminant < 0.0 {
+            return None;
+        }
+        let root = discriminant.sqrt();
+        let (enter, exit) = (-to_center - root, -to_center + root);
+        let (s_min, s_max) = (t_min * speed / scale, t_max * speed / scale);
+        if exit <= s_min || enter >= s_max {
+            return None;
+        }
+
+        let shift = enter.max(0.0);
+        let origin = origin + shift * direction;
+        let major = self.major_radius / scale;
+        let minor = self.minor_radius / scale;
+        let major_squared = major * major;
+        let along = origin.dot(direction);
+        let offset = origin.length_squared() + major_squared - minor * minor;
+        let planar_origin = origin.x() * origin.x() + origin.y() * origin.y();
+        let planar_cross = origin.x() * direction.x() + origin.y() * direction.y();
+        let planar_direction = direction.x() * direction.x() + direction.y() * direction.y();
+        let coefficients = [
+            4.0 * along,
+            4.0 * along * along + 2.0 * offset - 4.0 * major_squared * planar_direction,
+            4.0 * along * offset - 8.0 * major_squared * planar_cross,
+            offset * offset - 4.0 * major_squared * planar_origin,
+        ];
+
+        let s = solve_quartic(coefficients)
+            .iter()
+            .map(|root| polish_quartic_root(coefficients, root))
+            .filter(|&root| s_min < root + shift && root + shift < s_max)
+            .min_by(f64::total_cmp)?;
+        let point = origin + s * direction;
+        let planar = point.x().hypot(point.y());
+        let (ring_x, ring_y) = if planar > f64::EPSILON {
+            (point.x() / planar, point.y() / planar)
+        } else {
+            (1.0, 0.0)
+        };
+        let normal = Vector::new(
+            point.x() - major * ring_x,
+            point.y() - major * ring_y,
+            point.z(),
+        )
+        .normalized();
+        Some(ShapeHit {
+            t: (s + shift) * scale / speed,
+            normal: self.basis.local(normal),
+            u: azimuth_fraction(point.x(), point.y()),
+            v: azimuth_fraction(planar - major, point.z()),
+        })
+    }
+}
+
+/// Cross-section model used when intersecting a [`CurveGeometry`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CurveKind {
+    /// A flat strip that always faces the incoming ray, shaded with a flat normal.
+    Flat,
+    /// A ray-facing strip whose normals sweep around the axis like a thin tube.
+    Cylinder,
+    /// A strip with a fixed orientation whose normal blends between one normal per end.
+    Ribbon([Vector; 2]),
+}
+
+/// Surface data for a ray hit on a [`CurveGeometry`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CurveHit {
+    /// Ray parameter at the hit.
+    pub t: f64,
+    /// Unit-length normal at the hit point, facing the incoming ray.
+    pub normal: Vector,
+    /// Unit-length curve tangent at the hit point.
+    pub tangent: Vector,
+    /// Unit-length direction across the curve width, toward increasing `v`.
+    pub bitangent: Vector,
+    /// Curve parameter at the hit, mapped into the curve's `u` range.
+    pub u: f64,
+    /// Position across the curve width, from `0.0` on one edge to `1.0` on the other.
+    pub v: f64,
+}
+
+/// Cubic Bézier curve with a varying width, for hair, fur, and grass.
+///
+/// Curves are intersected as ray-facing strips by recursively subdividing the control polygon in
+/// a ray-aligned frame, so they stay smooth at any distance without tessellation. Widths are full
+/// widths at the two ends and vary linearly along the curve parameter.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CurveGeometry {
+    control_points: [Point; 4],
+    widths: [f64; 2],
+    kind: CurveKind,
+    u_range: [f64; 2],
+}
+
+impl CurveGeometry {
+    /// Creates a cylinder-shaded curve from four Bézier control points and end widths.
+    ///
+    /// Negative or non-finite widths are treated as zero, which produces a curve that is never hit.
+    #[must_use]
+    pub fn new(control_points: [Point; 4], widths: [f64; 2]) -> Self {
+        Self {
+            control_points,
+            widths: widths.map(|width| {
+                if width.is_finite() {
+                    width.max(0.0)
+                } else {
+                    0.0
+                }
+            }),
+            kind: CurveKind::Cylinder,
+            u_range: [0.0, 1.0],
+        }
+    }
+
+    /// Creates a curve from Hermite end points and end tangents.
+    #[must_use]
+    pub fn from_hermite(
+        p0: Point,
+        p1: Point,
+        tangent0: Vector,
+        tangent1: Vector,
+        widths: [f64; 2],
+    ) -> Self {
+        Self::new([p0, p0 + tangent0 / 3.0, p1 - tangent1 / 3.0, p1], widths)
+    }
+
+    /// Creates a smooth Catmull-Rom chain through `points`, one Bézier curve per span.
+    ///
+    /// Widths taper linearly from `widths[0]` at the first point to `widths[1]` at the last, and
+    /// each span's `u` range covers its share of `0.0..=1.0`. Fewer than two points produce no
+    /// curves.
+    #[must_use]
+    pub fn catmull_rom_chain(points: &[Point], widths: [f64; 2]) -> Vec<Self> {
+        if points.len() < 2 {
+            return Vec::new();
+        }
+        let last = points.len() - 1;
+        let tangent = |index: usize| {
+            let previous = points[index.saturating_sub(1)];
+            let next = points[(index + 1).min(last)];
+            (next - previous)
+                * if index == 0 || index == last {
+                    1.0
+                } else {
+                    0.5
+                }
+        };
+        #[allow(clippy::cast_precision_loss)]
+        let spans = last as f64;
+
+        (0..last)
+            .map(|index| {
+                #[allow(clippy::cast_precision_loss)]
+                let u_range = [index as f64 / spans, (index + 1) as f64 / spans];
+                let width_at = |u: f64| widths[0] + (widths[1] - widths[0]) * u;
+                Self::from_hermite(
+                    points[index],
+                    points[index + 1],
+                    tangent(index),
+                    tangent(index + 1),
+                    [width_at(u_range[0]), width_at(u_range[1])],
+                )
+                .with_u_range(u_range)
+            })
+            .collect()
+    }
+
+    /// Returns this curve with a ray-facing flat cross-section.
+    #[must_use]
+    pub const fn flat(mut self) -> Self {
+        self.kind = CurveKind::Flat;
+        self
+    }
+
+    /// Returns this curve as an oriented ribbon whose normal blends from `start` to `end`.
+    ///
+    /// Zero normals fall back to the cylinder cross-section.
+    #[must_use]
+    pub fn with_ribbon_normals(mut self, start: Vector, end: Vector) -> Self {
+        self.kind = if start.length_squared() > f64::EPSILON && end.length_squared() > f64::EPSILON
+        {
+            CurveKind::Ribbon([start.normalized(), end.normalized()])
+        } else {
+            CurveKind::Cylinder
+        };
+        self
+    }
+
+    /// Returns this curve with its parameter reported over `u_range` instead of `0.0..=1.0`.
+    #[must_use]
+    pub const fn with_u_range(mut self, u_range: [f64; 2]) -> Self {
+        self.u_range = u_range;
+        self
+    }
+
+    /// Returns the four Bézier control points.
+    #[must_use]
+    pub const fn control_points(self) -> [Point; 4] {
+        self.control_points
+    }
+
+    /// Returns the full widths at the start and end of the curve.
+    #[must_use]
+    pub const fn widths(self) -> [f64; 2] {
+        self.widths
+    }
+
+    /// Returns the cross-section model.
+    #[must_use]
+    pub const fn kind(self) -> CurveKind {
+        self.kind
+    }
+
+    /// Returns the parameter range reported by hits.
+    #[must_use]
+    pub const fn u_range(self) -> [f64; 2] {
+        self.u_range
+    }
+
+    /// Returns the curve point at local parameter `u` in `0.0..=1.0`.
+    #[must_use]
+    pub fn point(self, u: f64) -> Point {
+        let [p0, p1, p2, p3] = self.control_points.map(point_to_vector);
+        let point = bezier_point([p0, p1, p2, p3], u);
+        Point::new(point.x(), point.y(), point.z())
+    }
+
+    /// Returns the derivative with respect to local parameter `u`.
+    #[must_use]
+    pub fn derivative(self, u: f64) -> Vector {
+        bezier_derivative(self.control_points.map(point_to_vector), u)
+    }
+
+    /// Returns the full width at local parameter `u`.
+    #[must_use]
+    pub fn width(self, u: f64) -> f64 {
+        self.widths[0] + (self.widths[1] - self.widths[0]) * u
+    }
+
+    /// Returns the part of the curve between local parameters `u0` and `u1` as its own curve.
+    #[must_use]
+    pub fn segment(self, u0: f64, u1: f64) -> Self {
+        let points = self.control_points.map(point_to_vector);
+        let blossom = |a: f64, b: f64, c: f64| {
+            let point = bezier_blossom(points, a, b, c);
+            Point::new(point.x(), point.y(), point.z())
+        };
+        let kind = match self.kind {
+            CurveKind::Ribbon(normals) => CurveKind::Ribbon([
+                slerp_unit(normals[0], normals[1], u0),
+                slerp_unit(normals[0], normals[1], u1),
+            ]),
+            kind => kind,
+        };
+        let u_at = |u: f64| self.u_range[0] + (self.u_range[1] - self.u_range[0]) * u;
+        Self {
+            control_points: [
+                blossom(u0, u0, u0),
+                blossom(u0, u0, u1),
+                blossom(u0, u1, u1),
+                blossom(u1, u1, u1),
+            ],
+            widths: [self.width(u0), self.width(u1)],
+            kind,
+            u_range: [u_at(u0), u_at(u1)],
+        }
+    }
+
+    /// Splits the curve into `count` segments of equal parameter length.
+    #[must_use]
+    pub fn split(self, count: usize) -> Vec<Self> {
+        let count = count.max(1);
+        #[allow(clippy::cast_precision_loss)]
+        let step = 1.0 / count as f64;
+        (0..count)
+            .map(|index| {
+                #[allow(clippy::cast_precision_loss)]
+                let u0 = index as f64 * step;
+                self.segment(u0, if index + 1 == count { 1.0 } else { u0 + step })
+            })
+            .collect()
+    }
+
+    /// Returns the length of the control polygon, an upper bound on the curve length.
+    #[must_use]
+    pub fn control_polygon_length(self) -> f64 {
+        self.control_points
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]).length())
+            .sum()
+    }
+
+    /// Returns padded axis-aligned bounds covering the control hull widened by half the width.
+    #[must_use]
+    pub fn bounds(self) -> Bounds3 {
+        let radius = 0.5 * self.widths[0].max(self.widths[1]);
+        let [first, rest @ ..] = self.control_points;
+        let hull = rest
+            .iter()
+            .fold(Bounds3::from_points(first, first), |bounds, point| {
+                bounds.union_point(*point)
+            });
+        Bounds3::new(
+            (
+                hull.min.0 - radius,
+                hull.min.1 - radius,
+                hull.min.2 - radius,
+            ),
+            (
+                hull.max.0 + radius,
+                hull.max.1 + radius,
+                hull.max.2 + radius,
+            ),
+        )
+        .padded(CURVED_BOUNDS_EPSILON)
+    }
+
+    /// Returns the closest ray hit on the curve strip inside `t_min..=t_max`.
+    ///
+    /// The control polygon is moved into a frame looking down the ray and subdivided until each
+    /// piece is nearly straight relative to the curve width, then each piece is tested as a
+    /// segment of the given width.
+    #[must_use]
+    pub fn hit_ray(self, ray: &Ray, t_min: f64, t_max: f64) -> Option<CurveHit> {
+        let max_width = self.widths[0].max(self.widths[1]);
+        if max_width <= 0.0 {
+            return None;
+        }
+        let speed = ray.direction().length();
+        let basis = OrthonormalBasis::from_w(*ray.direction())?;
+        let origin = *ray.origin();
+        let local = self
+            .control_points
+            .map(|point| basis.to_local(point - origin));
+
+        // Depth needed for the subdivided pieces to deviate from straight by about 5% of the width.
+        let curvature = (0..2)
+            .flat_map(|i| (0..3).map(move |axis| (i, axis)))
+            .map(|(i, axis)| (local[i][axis] - 2.0 * local[i + 1][axis] + local[i + 2][axis]).abs())
+            .fold(0.0, f64::max);
+        let epsilon = 0.05 * max_width;
+        let depth = (std::f64::consts::SQRT_2 * 6.0 * curvature / (8.0 * epsilon))
+            .log2()
+            .max(0.0)
+            * 0.5;
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let depth = depth.min(f64::from(MAX_CURVE_DEPTH)) as u32;
+
+        let mut search = CurveSearch {
+            curve: self,
+            direction: basis.w(),
+            z_min: t_min * speed,
+            z_max: t_max * speed,
+            best: None,
+        };
+        search.recurse(local, 0.0, 1.0, depth);
+        let (z, u, v, hit_width) = search.best?;
+
+        let tangent = self.derivative(u);
+        let tangent = if tangent.length_squared() > f64::EPSILON {
+            tangent.normalized()
+        } else {
+            (self.control_points[3] - self.control_points[0]).normalized()
+        };
+        let direction = basis.w();
+        // The ray frame is left-handed, so the positive edge-function side is `tangent x direction`.
+        let side = tangent.cross(direction);
+        if side.length_squared() <= f64::EPSILON || hit_width <= 0.0 {
+            return None;
+        }
+        let side = side.normalized();
+        let facing = tangent.cross(side);
+        let (normal, bitangent) = match self.kind {
+            CurveKind::Flat => (facing, side),
+            CurveKind::Cylinder => {
+                let offset = (2.0 * v - 1.0).clamp(-1.0, 1.0);
+                (
+                    side * offset + facing * (1.0 - offset * offset).sqrt(),
+                    side,
+                )
+            }
+            CurveKind::Ribbon([n0, n1]) => {
+                let ribbon = slerp_unit(n0, n1, u);
+                let normal = ribbon - tangent * ribbon.dot(tangent);
+                if normal.length_squared() <= f64::EPSILON {
+                    (facing, side)
+                } else {
+                    let normal = normal.normalized();
+                    let across = normal.cross(tangent);
+                    let across = if across.dot(side) < 0.0 {
+                        -across
+                    } else {
+                        across
+                    };
+                    let normal = if normal.dot(direction) > 0.0 {
+                        -normal
+                    } else {
+                        normal
+                    };
+                    (normal, across)
+                }
+            }
+        };
+
+        Some(CurveHit {
+            t: z / speed,
+            normal,
+            tangent,
+            bitangent,
+            u: self.u_range[0] + (self.u_range[1] - self.u_range[0]) * u,
+            v,
+        })
+    }
+}
+
+/// Recursion state for [`CurveGeometry::hit_ray`] in the ray-aligned frame.
+struct CurveSearch {
+    curve: CurveGeometry,
+    direction: Vector,
+    z_min: f64,
+    z_max: f64,
+    best: Option<(f64, f64, f64, f64)>,
+}
+
+impl CurveSearch {
+    fn recurse(&mut self, points: [Vector; 4], u0: f64, u1: f64, depth: u32) {
+        let half_width = 0.5 * self.curve.width(u0).max(self.curve.width(u1));
+        let z_limit = self.best.map_or(self.z_max, |best| best.0);
+        let (mut min, mut max) = (points[0], points[0]);
+        for point in &points[1..] {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(point[axis]);
+                max[axis] = max[axis].max(point[axis]);
+            }
+        }
+        if min.x() - half_width > 0.0
+            || max.x() + half_width < 0.0
+            || min.y() - half_width > 0.0
+            || max.y() + half_width < 0.0
+            || max.z() + half_width < self.z_min
+            || min.z() - half_width > z_limit
+        {
+            return;
+        }
+
+        if depth > 0 {
+            let (left, right) = split_bezier(points);
+            let middle = 0.5 * (u0 + u1);
+            self.recurse(left, u0, middle, depth - 1);
+            self.recurse(right, middle, u1, depth - 1);
+            return;
+        }
+
+        // Reject hits beyond the perpendicular planes through each end of the piece.
+        let [p0, p1, p2, p3] = points;
+        if (p1.y() - p0.y()) * -p0.y() + p0.x() * (p0.x() - p1.x()) < 0.0
+            || (p2.y() - p3.y()) * -p3.y() + p3.x() * (p3.x() - p2.x()) < 0.0
+        {
+            return;
+        }
+        let segment_x = p3.x() - p0.x();
+        let segment_y = p3.y() - p0.y();
+        let denominator = segment_x * segment_x + segment_y * segment_y;
+        if denominator <= 0.0 {
+            return;
+        }
+        let w = (-p0.x() * segment_x - p0.y() * segment_y) / denominator;
+        let u = (u0 + (u1 - u0) * w).clamp(u0, u1);
+        let mut hit_width = self.curve.width(u);
+        if let CurveKind::Ribbon([n0, n1]) = self.curve.kind {
+            hit_width *= slerp_unit(n0, n1, u).dot(self.direction).abs();
+        }
+        let w = w.clamp(0.0, 1.0);
+        let center = bezier_point(points, w);
+        let distance_squared = center.x() * center.x() + center.y() * center.y();
+        if distance_squared > 0.25 * hit_width * hit_width
+            || center.z() < self.z_min
+            || center.z() > z_limit
+        {
+            return;
+        }
+
+        let derivative = bezier_derivative(points, w);
+        let side = derivative.x() * -center.y() + center.x() * derivative.y();
+        let offset = distance_squared.sqrt() / hit_width;
+        let v = if side > 0.0 {
+            0.5 + offset
+        } else {
+            0.5 - offset
+        };
+        self.best = Some((center.z(), u, v, hit_width));
+    }
+}
+
+fn bezier_point([p0, p1, p2, p3]: [Vector; 4], u: f64) -> Vector {
+    let s = 1.0 - u;
+    p0 * (s * s * s) + p1 * (3.0 * s * s * u) + p2 * (3.0 * s * u * u) + p3 * (u * u * u)
+}
+
+fn bezier_derivative([p0, p1, p2, p3]: [Vector; 4], u: f64) -> Vector {
+    let s = 1.0 - u;
+    (p1 - p0) * (3.0 * s * s) + (p2 - p1) * (6.0 * s * u) + (p3 - p2) * (3.0 * u * u)
+}
+
+fn bezier_blossom(points: [Vector; 4], u0: f64, u1: f64, u2: f64) -> Vector {
+    let lerp = |t: f64, a: Vector, b: Vector| a + (b - a) * t;
+    let a = [
+        lerp(u0, points[0], points[1]),
+        lerp(u0, points[1], points[2]),
+        lerp(u0, points[2], points[3]),
+    ];
+    let b = [lerp(u1, a[0], a[1]), lerp(u1, a[1], a[2])];
+    lerp(u2, b[0], b[1])
+}
+
+fn split_bezier(points: [Vector; 4]) -> ([Vector; 4], [Vector; 4]) {
+    let [p0, p1, p2, p3] = points;
+    let first = [(p0 + p1) * 0.5, (p1 + p2) * 0.5, (p2 + p3) * 0.5];
+    let second = [(first[0] + first[1]) * 0.5, (first[1] + first[2]) * 0.5];
+    let middle = (second[0] + second[1]) * 0.5;
+    (
+        [p0, first[0], second[0], middle],
+        [middle, second[1], first[2], p3],
+    )
+}
+
+/// Spherically interpolates between two unit vectors.
+fn slerp_unit(start: Vector, end: Vector, t: f64) -> Vector {
+    let cos_theta = start.dot(end).clamp(-1.0, 1.0);
+    let theta = cos_theta.acos();
+    let sin_theta = theta.sin();
+    if sin_theta.abs() <= POLYNOMIAL_EPSILON {
+        return (start + (end - start) * t).normalized();
+    }
+    (start * ((1.0 - t) * theta).sin() + end * (t * theta).sin()) / sin_theta
+}
+
+fn axis_frame(axis: Vector) -> (OrthonormalBasis, f64) {
+    OrthonormalBasis::from_w(axis).map_or(
+        (
+            OrthonormalBasis {
+                u: Vector::new(1.0, 0.0, 0.0),
+                v: Vector::new(0.0, 1.0, 0.0),
+                w: Vector::new(0.0, 0.0, 1.0),
+            },
+            0.0,
+        ),
+        |basis| (basis, axis.length()),
+    )
+}
+
+fn local_ray(basis: OrthonormalBasis, origin: Point, ray: &Ray) -> (Vector, Vector) {
+    (
+        basis.to_local(*ray.origin() - origin),
+        basis.to_local(*ray.direction()),
+    )
+}
+
+/// Returns the tight bounds of a circle of `radius` around `center` in the plane normal to `axis`.
+fn circle_bounds(center: Point, axis: Vector, radius: f64) -> Bounds3 {
+    let extent = Vector::new(
+        radius * (1.0 - axis.x() * axis.x()).max(0.0).sqrt(),
+        radius * (1.0 - axis.y() * axis.y()).max(0.0).sqrt(),
+        radius * (1.0 - axis.z() * axis.z()).max(0.0).sqrt(),
+    );
+    Bounds3::from_points(center - extent, center + extent)
+}
+
+/// Intersects a local-frame ray with the disk of `radius` centered on the axis at height `z`.
+fn hit_cap(
+    origin: Vector,
+    direction: Vector,
+    z: f64,
+    radius: f64,
+    t_min: f64,
+    t_max: f64,
+) -> Option<(f64, f64, f64)> {
+    if direction.z().abs() < CURVED_HIT_EPSILON {
+        return None;
+    }
+
+    let t = (z - origin.z()) / direction.z();
+    if !(t_min < t && t < t_max) {
+        return None;
+    }
+
+    let x = origin.x() + t * direction.x();
+    let y = origin.y() + t * direction.y();
+    if x * x + y * y > radius * radius {
+        return None;
+    }
+
+    Some((t, 0.5 + 0.5 * x / radius, 0.5 + 0.5 * y / radius))
+}
+
+/// Returns the angle of `(x, y)` as a fraction of a full turn in `[0, 1)`.
+fn azimuth_fraction(x: f64, y: f64) -> f64 {
+    y.atan2(x).rem_euclid(2.0 * PI) / (2.0 * PI)
+}
+
+/// Up to four real polynomial roots stored inline.
+#[derive(Clone, Copy, Debug, Default)]
+struct Roots {
+    values: [f64; 4],
+    len: usize,
+}
+
+impl Roots {
+    fn push(&mut self, root: f64) {
+        if self.len < self.values.len() {
+            self.values[self.len] = root;
+            self.len += 1;
+        }
+    }
+
+    fn extend(&mut self, roots: Self) {
+        for root in roots.iter() {
+            self.push(root);
+        }
+    }
+
+    fn first(&self) -> Option<f64> {
+        self.iter().next()
+    }
+
+    fn shifted(mut self, offset: f64) -> Self {
+        for root in &mut self.values[..self.len] {
+            *root += offset;
+        }
+        self
+    }
+
+    fn iter(&self) -> impl Iterator<Item = f64> + '_ {
+        self.values[..self.len].iter().copied()
+    }
+}
+
+/// Returns the ascending real roots of `a t^2 + 2 half_b t + c`.
+fn quadratic_roots(a: f64, half_b: f64, c: f64) -> Roots {
+    let mut roots = Roots::default();
+    if a.abs() <= CURVED_HIT_EPSILON {
+        if half_b.abs() > CURVED_HIT_EPSILON {
+            roots.push(-c / (2.0 * half_b));
+        }
+        return roots;
+    }
+
+    let discriminant = half_b * half_b - a * c;
+    if discriminant >= 0.0 {
+        let root = discriminant.sqrt();
+        let first = (-half_b - root) / a;
+        let second = (-half_b + root) / a;
+        roots.push(first.min(second));
+        roots.push(first.max(second));
+    }
+    roots
+}
+
+/// Returns the real roots of the monic quartic `x^4 + c[0] x^3 + c[1] x^2 + c[2] x + c[3]`.
+///
+/// Uses Ferrari's method through a resolvent cubic.
+#[allow(clippy::many_single_char_names)]
+fn solve_quartic([a, b, c, d]: [f64; 4]) -> Roots {
+    // Depressed quartic y^4 + p y^2 + q y + r with x = y - a / 4.
+    let a_squared = a * a;
+    let p = -3.0 / 8.0 * a_squared + b;
+    let q = a_squared * a / 8.0 - 0.5 * a * b + c;
+    let r = -3.0 / 256.0 * a_squared * a_squared + a_squared * b / 16.0 - 0.25 * a * c + d;
+
+    let mut roots = Roots::default();
+    if r.abs() < POLYNOMIAL_EPSILON {
+        roots.push(0.0);
+        roots.extend(solve_cubic([0.0, p, q]));
+    } else {
+        let Some(z) = solve_cubic([-0.5 * p, -r, 0.5 * r * p - 0.125 * q * q]).first() else {
+            return roots;
+        };
+        let (Some(u), Some(v)) = (non_negative_sqrt(z * z - r), non_negative_sqrt(2.0 * z - p))
+        else {
+            return roots;
+        };
+        let v = if q < 0.0 { -v } else { v };
+        roots.extend(monic_quadratic_roots(v, z - u));
+        roots.extend(monic_quadratic_roots(-v, z + u));
+    }
+    roots.shifted(-0.25 * a)
+}
+
+/// Returns the real roots of the monic cubic `x^3 + c[0] x^2 + c[1] x + c[2]`.
+#[allow(clippy::many_single_char_names)]
+fn solve_cubic([a, b, c]: [f64; 3]) -> Roots {
+    // Depressed cubic y^3 + 3 p y + 2 q with x = y - a / 3.
+    let a_squared = a * a;
+    let p = (b - a_squared / 3.0) / 3.0;
+    let q = 0.5 * (2.0 / 27.0 * a * a_squared - a * b / 3.0 + c);
+    let p_cubed = p * p * p;
+    let discriminant = q * q + p_cubed;
+
+    let mut roots = Roots::default();
+    if discriminant.abs() < POLYNOMIAL_EPSILON {
+        if q.abs() < POLYNOMIAL_EPSILON {
+            roots.push(0.0);
+        } else {
+            let u = (-q).cbrt();
+            roots.push(2.0 * u);
+            roots.push(-u);
+        }
+    } else if discriminant < 0.0 {
+        let phi = (-q / (-p_cubed).sqrt()).clamp(-1.0, 1.0).acos() / 3.0;
+        let scale = 2.0 * (-p).sqrt();
+        roots.push(scale * phi.cos());
+        roots.push(-scale * (phi + PI / 3.0).cos());
+        roots.push(-scale * (phi - PI / 3.0).cos());
+    } else {
+        let root = discriminant.sqrt();
+        roots.push((root - q).cbrt() - (root + q).cbrt());
+    }
+    roots.shifted(-a / 3.0)
+}
+
+/// Returns the real roots of `x^2 + b x + c`.
+fn monic_quadratic_roots(b: f64, c: f64) -> Roots {
+    let mut roots = Roots::default();
+    let discriminant = 0.25 * b * b - c;
+    if discriminant.abs() < POLYNOMIAL_EPSILON {
+        roots.push(-0.5 * b);
+    } else if discriminant > 0.0 {
+        let root = discriminant.sqrt();
+        roots.push(-0.5 * b - root);
+        roots.push(-0.5 * b + root);
+    }
+    roots
+}
+
+fn non_negative_sqrt(value: f64) -> Option<f64> {
+    if value.abs() < POLYNOMIAL_EPSILON {
+        Some(0.0)
+    } else if value > 0.0 {
+        Some(value.sqrt())
+    } else {
+        None
+    }
+}
+
+/// Refines a quartic root with a few Newton steps to undo cancellation in Ferrari's method.
+fn polish_quartic_root([a, b, c, d]: [f64; 4], mut root: f64) -> f64 {
+    for _ in 0..QUARTIC_NEWTON_STEPS {
+        let value = (((root + a) * root + b) * root + c) * root + d;
+        let slope = ((4.0 * root + 3.0 * a) * root + 2.0 * b) * root + c;
+        if slope.abs() <= f64::EPSILON {
+            break;
+        }
+        root -= value / slope;
+    }
+    root
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -728,4 +2008,176 @@ mod tests {
 
         assert_close(sphere.radius(), 0.0);
     }
+
+    #[test]
+    fn cylinder_geometry_hits_side_and_caps() {
+        let cylinder =
+            CylinderGeometry::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 2.0), 0.5);
+
+        let side = Ray::new(Point::new(-3.0, 0.0, 1.0), Vector::new(1.0, 0.0, 0.0));
+        let hit = cylinder
+            .hit_ray(&side, 0.0, f64::INFINITY)
+            .expect("side hit");
+        assert_close(hit.t, 2.5);
+        assert_close(hit.normal.x(), -1.0);
+        assert_close(hit.v, 0.5);
+
+        let cap = Ray::new(Point::new(0.25, 0.0, 5.0), Vector::new(0.0, 0.0, -1.0));
+        let hit = cylinder
+            .hit_ray(&cap, 0.0, f64::INFINITY)
+            .expect("top cap hit");
+        assert_close(hit.t, 3.0);
+        assert_close(hit.normal.z(), 1.0);
+        let open = cylinder.with_caps(false);
+        let hit = open.hit_ray(&cap, 0.0, f64::INFINITY);
+        assert!(hit.is_none());
+
+        assert_close(cylinder.area(), 2.0 * PI * 0.5 * 2.0 + 2.0 * PI * 0.25);
+        let bounds = cylinder.bounds();
+        assert!((bounds.max.2 - 2.0).abs() < 1e-3 && (bounds.max.0 - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn cone_geometry_hits_slanted_side_and_base() {
+        let cone = ConeGeometry::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0), 1.0);
+
+        let side = Ray::new(Point::new(0.0, 0.5, -3.0), Vector::new(0.0, 0.0, 1.0));
+        let hit = cone.hit_ray(&side, 0.0, f64::INFINITY).expect("side hit");
+        assert_close(hit.t, 2.5);
+        let expected = Vector::new(0.0, 1.0, -1.0).normalized();
+        assert!((hit.normal - expected).length() < 1e-10);
+
+        let base = Ray::new(Point::new(0.2, -2.0, 0.1), Vector::new(0.0, 1.0, 0.0));
+        let hit = cone.hit_ray(&base, 0.0, f64::INFINITY).expect("base hit");
+        assert_close(hit.t, 2.0);
+        assert_close(hit.normal.y(), -1.0);
+
+        let above_apex = Ray::new(Point::new(0.5, 1.5, -3.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(cone.hit_ray(&above_apex, 0.0, f64::INFINITY).is_none());
+        assert_close(cone.area(), PI * 2.0_f64.sqrt() + PI);
+    }
+
+    #[test]
+    fn torus_geometry_hit_ray_finds_every_crossing_in_order() {
+        let torus = TorusGeometry::new(
+            Point::new(1.0, 0.0, 0.0),
+            Vector::new(0.0, 0.0, 3.0),
+            2.0,
+            0.5,
+        );
+        let ray = Ray::new(Point::new(-4.0, 0.0, 0.0), Vector::new(2.0, 0.0, 0.0));
+
+        let mut t_min = 0.0;
+        let mut crossings = Vec::new();
+        while let Some(hit) = torus.hit_ray(&ray, t_min, f64::INFINITY) {
+            crossings.push(hit.t);
+            t_min = hit.t + 1e-6;
+        }
+        assert_eq!(crossings.len(), 4);
+        for (actual, expected) in crossings.into_iter().zip([1.25, 1.75, 3.25, 3.75]) {
+            assert!((actual - expected).abs() < 1e-9, "{actual} != {expected}");
+        }
+
+        let hit = torus.hit_ray(&ray, 0.0, f64::INFINITY).expect("outer hit");
+        assert_close(hit.normal.x(), -1.0);
+        let through_hole = Ray::new(Point::new(1.0, 0.0, 5.0), Vector::new(0.0, 0.0, -1.0));
+        assert!(torus.hit_ray(&through_hole, 0.0, f64::INFINITY).is_none());
+        let top = Ray::new(Point::new(3.0, 0.0, 5.0), Vector::new(0.0, 0.0, -1.0));
+        let hit = torus
+            .hit_ray(&top, 0.0, f64::INFINITY)
+            .expect("tube top hit");
+        assert_close(hit.t, 4.5);
+        assert_close(hit.normal.z(), 1.0);
+        assert_close(hit.v, 0.25);
+    }
+
+    #[test]
+    fn disk_geometry_hits_inside_radius_only() {
+        let disk = DiskGeometry::new(Point::new(0.0, 0.0, -1.0), Vector::new(0.0, 0.0, 1.0), 1.0);
+        let ray = Ray::new(Point::new(0.5, 0.0, 0.0), Vector::new(0.0, 0.0, -1.0));
+
+        let hit = disk.hit_ray(&ray, 0.0, f64::INFINITY).expect("disk hit");
+        assert_close(hit.t, 1.0);
+        assert_close(hit.normal.z(), 1.0);
+        let outside = Ray::new(Point::new(0.8, 0.8, 0.0), Vector::new(0.0, 0.0, -1.0));
+        assert!(disk.hit_ray(&outside, 0.0, f64::INFINITY).is_none());
+        let degenerate = DiskGeometry::new(Point::new(0.0, 0.0, -1.0), Vector::default(), 1.0);
+        assert!(degenerate.hit_ray(&ray, 0.0, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn curve_geometry_hits_a_straight_tube_across_its_width() {
+        let curve = CurveGeometry::new(
+            [
+                Point::new(-1.0, 0.0, 0.0),
+                Point::new(-1.0 / 3.0, 0.0, 0.0),
+                Point::new(1.0 / 3.0, 0.0, 0.0),
+                Point::new(1.0, 0.0, 0.0),
+            ],
+            [0.2, 0.2],
+        );
+        let center = Ray::new(Point::new(0.0, 0.0, 5.0), Vector::new(0.0, 0.0, -1.0));
+        let hit = curve
+            .hit_ray(&center, 0.0, f64::INFINITY)
+            .expect("center hit");
+        assert!((hit.t - 5.0).abs() < 1e-3);
+        assert!((hit.u - 0.5).abs() < 1e-2);
+        assert!((hit.v - 0.5).abs() < 1e-2);
+        assert!(hit.normal.z() > 0.99);
+        assert!(hit.tangent.x().abs() > 0.99);
+
+        let offset = Ray::new(Point::new(0.25, 0.05, 5.0), Vector::new(0.0, 0.0, -1.0));
+        let hit = curve
+            .hit_ray(&offset, 0.0, f64::INFINITY)
+            .expect("offset hit");
+        let across = hit.bitangent.y().signum() * (2.0 * hit.v - 1.0);
+        assert!((across - 0.5).abs() < 0.05, "{hit:?}");
+        assert!(hit.normal.y() > 0.4 && hit.normal.z() > 0.8, "{hit:?}");
+
+        let miss = Ray::new(Point::new(0.0, 0.2, 5.0), Vector::new(0.0, 0.0, -1.0));
+        assert!(curve.hit_ray(&miss, 0.0, f64::INFINITY).is_none());
+        let flat = curve
+            .flat()
+            .hit_ray(&offset, 0.0, f64::INFINITY)
+            .expect("flat hit");
+        assert!(flat.normal.z() > 0.99);
+    }
+
+    #[test]
+    fn curve_geometry_segments_stay_on_the_parent_curve() {
+        let curve = CurveGeometry::from_hermite(
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(2.0, 1.0, 0.0),
+            Vector::new(3.0, 0.0, 0.0),
+            Vector::new(0.0, 3.0, 1.0),
+            [0.4, 0.1],
+        );
+        let pieces = curve.split(4);
+        assert_eq!(pieces.len(), 4);
+        for (index, piece) in pieces.iter().enumerate() {
+            #[allow(clippy::cast_precision_loss)]
+            let start = index as f64 / 4.0;
+            for local in [0.0, 0.3, 1.0] {
+                let u = start + local / 4.0;
+                let expected = curve.point(u);
+                let actual = piece.point(local);
+                assert!((actual - expected).length() < 1e-9);
+                assert!((piece.width(local) - curve.width(u)).abs() < 1e-9);
+            }
+            assert!((piece.u_range()[0] - start).abs() < 1e-12);
+        }
+
+        let chain = CurveGeometry::catmull_rom_chain(
+            &[
+                Point::new(0.0, 0.0, 0.0),
+                Point::new(1.0, 1.0, 0.0),
+                Point::new(2.0, 0.0, 0.0),
+            ],
+            [0.3, 0.1],
+        );
+        assert_eq!(chain.len(), 2);
+        assert!((chain[0].point(1.0) - chain[1].point(0.0)).length() < 1e-12);
+        assert!((chain[0].widths()[1] - chain[1].widths()[0]).abs() < 1e-12);
+        assert!((chain[0].derivative(1.0) - chain[1].derivative(0.0)).length() < 1e-9);
+    }
 }
@@ -0,0 +1,430 @@
+//! Scrambled low-discrepancy sequences for quasi-Monte Carlo sampling.
+//!
+//! These sequences back [`SampleRng::from_sequence`](crate::gmath::random::SampleRng::from_sequence):
+//! each real number drawn from such a generator is the next dimension of one sequence point, so
+//! the successive decisions along a path read well-stratified, correlated coordinates instead of
+//! independent pseudo-random values. Every point is randomized (Owen scrambling or a per-digit
+//! random permutation), which keeps Monte Carlo estimates unbiased.
+
+use super::random::SampleRng;
+use std::sync::OnceLock;
+
+/// Number of prime bases available to [`LowDiscrepancySequence::Halton`].
+pub const HALTON_MAX_DIMENSIONS: u32 = 64;
+
+const HALTON_PRIMES: [u32; HALTON_MAX_DIMENSIONS as usize] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191, 193,
+    197, 199, 211, 223, 227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283, 293, 307,
+    311,
+];
+const ONE_MINUS_EPSILON: f64 = 1.0 - f64::EPSILON / 2.0;
+const BLUE_NOISE_SIZE: usize = 64;
+const BLUE_NOISE_SIGMA: f64 = 1.5;
+const BLUE_NOISE_SEED: u64 = 0x5eed_b10e;
+
+/// Low-discrepancy sequence that drives a [`SampleRng`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LowDiscrepancySequence {
+    /// Owen-scrambled Sobol points, padded to any dimension with independently shuffled 2D sets.
+    Sobol,
+    /// Owen-scrambled Halton points using one prime base per dimension.
+    ///
+    /// Dimensions past [`HALTON_MAX_DIMENSIONS`] fall back to pseudo-random values.
+    Halton,
+    /// Sobol points shared by neighbouring pixels and toroidally shifted by a blue-noise mask.
+    ///
+    /// Each pixel still integrates an unbiased, well-stratified point set, but the error left in
+    /// neighbouring pixels is anti-correlated, so residual noise looks like fine grain rather
+    /// than blotches.
+    BlueNoiseSobol,
+}
+
+/// One sequence point whose dimensions are handed out in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SequencePoint {
+    sequence: LowDiscrepancySequence,
+    seed: u64,
+    pixel: (u32, u32),
+    index: u32,
+    dimension: u32,
+}
+
+impl SequencePoint {
+    pub(crate) fn new(
+        sequence: LowDiscrepancySequence,
+        seed: u64,
+        pixel: (u32, u32),
+        index: u32,
+    ) -> Self {
+        // Sobol and Halton scramble each pixel independently; blue noise shares one scramble and
+        // relies on the dither mask to decorrelate pixels.
+        let seed = match sequence {
+            LowDiscrepancySequence::Sobol | LowDiscrepancySequence::Halton => {
+                mix_bits(seed ^ mix_bits(u64::from(pixel.0) | (u64::from(pixel.1) << 32)))
+            }
+            LowDiscrepancySequence::BlueNoiseSobol => mix_bits(seed),
+        };
+        Self {
+            sequence,
+            seed,
+            pixel,
+            index,
+            dimension: 0,
+        }
+    }
+
+    /// Returns the next dimension of this point, or `None` once the sequence has no more
+    /// dimensions.
+    pub(crate) fn next_sample(&mut self) -> Option<f64> {
+        let value = sample_dimension(
+            self.sequence,
+            self.seed,
+            self.pixel,
+            self.index,
+            self.dimension,
+        )?;
+        self.dimension += 1;
+        Some(value)
+    }
+
+    /// Seed for the pseudo-random stream that covers integer draws and exhausted dimensions.
+    pub(crate) fn fallback_seed(self) -> u64 {
+        mix_bits(self.seed ^ mix_bits(u64::from(self.index).wrapping_add(0x9e37_79b9)))
+    }
+}
+
+/// Returns dimension `dimension` of point `index` of a scrambled sequence.
+#[allow(clippy::cast_possible_truncation)]
+fn sample_dimension(
+    sequence: LowDiscrepancySequence,
+    seed: u64,
+    pixel: (u32, u32),
+    index: u32,
+    dimension: u32,
+) -> Option<f64> {
+    match sequence {
+        LowDiscrepancySequence::Sobol => Some(padded_sobol(index, dimension, seed)),
+        LowDiscrepancySequence::Halton => {
+            let base = *HALTON_PRIMES.get(usize::try_from(dimension).ok()?)?;
+            Some(scrambled_radical_inverse(
+                base,
+                u64::from(index),
+                mix_bits(seed ^ u64::from(dimension)),
+            ))
+        }
+        LowDiscrepancySequence::BlueNoiseSobol => {
+            let value = padded_sobol(index, dimension, seed);
+            let offset = mix_bits(seed ^ 0xb10e ^ u64::from(dimension));
+            let shift = blue_noise_value(
+                pixel.0.wrapping_add(offset as u32),
+                pixel.1.wrapping_add((offset >> 32) as u32),
+            );
+            let shifted = value + shift;
+            Some(
+                if shifted >= 1.0 {
+                    shifted - 1.0
+                } else {
+                    shifted
+                }
+                .min(ONE_MINUS_EPSILON),
+            )
+        }
+    }
+}
+
+/// Dimension `dimension` of an Owen-scrambled Sobol point, padded with shuffled 2D sets.
+#[allow(clippy::cast_possible_truncation)]
+fn padded_sobol(index: u32, dimension: u32, seed: u64) -> f64 {
+    let pair_seed = mix_bits(seed ^ u64::from(dimension / 2).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    let shuffled = nested_uniform_scramble(index, pair_seed as u32);
+    let (x, y) = sobol_2d(shuffled);
+    let (bits, coordinate_seed) = if dimension.is_multiple_of(2) {
+        (x, (pair_seed >> 32) as u32)
+    } else {
+        (y, mix_bits(pair_seed) as u32)
+    };
+    unit_from_bits(nested_uniform_scramble(bits, coordinate_seed))
+}
+
+/// The first two Sobol dimensions, as 32-bit fixed-point values.
+fn sobol_2d(index: u32) -> (u32, u32) {
+    let x = index.reverse_bits();
+    let mut y = 0;
+    let mut direction = 1_u32 << 31;
+    let mut remaining = index;
+    while remaining != 0 {
+        if remaining & 1 != 0 {
+            y ^= direction;
+        }
+        remaining >>= 1;
+        direction ^= direction >> 1;
+    }
+    (x, y)
+}
+
+/// Owen scrambling of a 32-bit fixed-point value with a hash-based nested permutation.
+fn nested_uniform_scramble(value: u32, seed: u32) -> u32 {
+    laine_karras_permutation(value.reverse_bits(), seed).reverse_bits()
+}
+
+fn laine_karras_permutation(mut value: u32, seed: u32) -> u32 {
+    value = value.wrapping_add(seed);
+    value ^= value.wrapping_mul(0x6c50_b47c);
+    value ^= value.wrapping_mul(0xb82f_1e52);
+    value ^= value.wrapping_mul(0xc7af_e638);
+    value ^= value.wrapping_mul(0x8d22_f6e6);
+    value
+}
+
+/// Radical inverse in `base` with every digit permuted by a hash of the digits above it.
+#[allow(clippy::cast_precision_loss)]
+fn scrambled_radical_inverse(base: u32, mut index: u64, seed: u64) -> f64 {
+    let wide_base = u64::from(base);
+    let inverse_base = 1.0 / f64::from(base);
+    let mut inverse_base_power = 1.0;
+    let mut value = 0.0;
+    let mut prefix = 0_u64;
+    let mut depth = 0_u64;
+    while 1.0 - f64::from(base - 1) * inverse_base_power < 1.0 {
+        let next = index / wide_base;
+        let digit = index - next * wide_base;
+        let digit_hash = mix_bits(seed ^ prefix ^ (depth << 56));
+        let digit = (digit + digit_hash % wide_base) % wide_base;
+        inverse_base_power *= inverse_base;
+        value += digit as f64 * inverse_base_power;
+        prefix = prefix.wrapping_mul(wide_base).wrapping_add(digit);
+        index = next;
+        depth += 1;
+    }
+    f64::min(value, ONE_MINUS_EPSILON)
+}
+
+fn unit_from_bits(bits: u32) -> f64 {
+    f64::from(bits) * (1.0 / 4_294_967_296.0)
+}
+
+fn mix_bits(mut value: u64) -> u64 {
+    value ^= value >> 31;
+    value = value.wrapping_mul(0x7fb5_d329_728e_a185);
+    value ^= value >> 27;
+    value = value.wrapping_mul(0x81da_def4_bc2d_d44d);
+    value ^ (value >> 33)
+}
+
+/// Value of the tiled blue-noise dither mask at a pixel, in `[0, 1)`.
+#[allow(clippy::cast_possible_truncation)]
+fn blue_noise_value(x: u32, y: u32) -> f64 {
+    static MASK: OnceLock<Vec<f64>> = OnceLock::new();
+    let mask = MASK.get_or_init(build_blue_noise_mask);
+    let size = BLUE_NOISE_SIZE as u32;
+    mask[(y % size) as usize * BLUE_NOISE_SIZE + (x % size) as usize]
+}
+
+/// Builds a toroidal blue-noise threshold mask with Ulichney's void-and-cluster method.
+#[allow(clippy::cast_precision_loss)]
+fn build_blue_noise_mask() -> Vec<f64> {
+    let cell_count = BLUE_NOISE_SIZE * BLUE_NOISE_SIZE;
+    let kernel: Vec<f64> = (0..cell_count)
+        .map(|cell| {
+            let dx = (cell % BLUE_NOISE_SIZE).min(BLUE_NOISE_SIZE - cell % BLUE_NOISE_SIZE);
+            let dy = (cell / BLUE_NOISE_SIZE).min(BLUE_NOISE_SIZE - cell / BLUE_NOISE_SIZE);
+            let distance_squared = (dx * dx + dy * dy) as f64;
+            (-distance_squared / (2.0 * BLUE_NOISE_SIGMA * BLUE_NOISE_SIGMA)).exp()
+        })
+        .collect();
+    let mut pattern = DitherPattern {
+        occupied: vec![false; cell_count],
+        energy: vec![0.0; cell_count],
+        kernel: &kernel,
+    };
+
+    let initial_count = cell_count / 10;
+    let mut rng = SampleRng::new(BLUE_NOISE_SEED);
+    let mut placed = 0;
+    while placed < initial_count {
+        let cell = rng.random_index(cell_count).expect("mask is not empty");
+        if !pattern.occupied[cell] {
+            pattern.toggle(cell);
+            placed += 1;
+        }
+    }
+    // Move points from the tightest cluster to the largest void until the pattern is stable.
+    loop {
+        let cluster = pattern.tightest_cluster();
+        pattern.toggle(cluster);
+        let void = pattern.largest_void();
+        pattern.toggle(void);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut ranks = vec![0_usize; cell_count];
+    let mut removal = DitherPattern {
+        occupied: pattern.occupied.clone(),
+        energy: pattern.energy.clone(),
+        kernel: &kernel,
+    };
+    for rank in (0..initial_count).rev() {
+        let cluster = removal.tightest_cluster();
+        removal.toggle(cluster);
+        ranks[cluster] = rank;
+    }
+    for rank in initial_count..cell_count {
+        let void = pattern.largest_void();
+        pattern.toggle(void);
+        ranks[void] = rank;
+    }
+
+    ranks
+        .into_iter()
+        .map(|rank| (rank as f64 + 0.5) / cell_count as f64)
+        .collect()
+}
+
+struct DitherPattern<'a> {
+    occupied: Vec<bool>,
+    energy: Vec<f64>,
+    kernel: &'a [f64],
+}
+
+impl DitherPattern<'_> {
+    fn toggle(&mut self, cell: usize) {
+        self.occupied[cell] = !self.occupied[cell];
+        let sign = if self.occupied[cell] { 1.0 } else { -1.0 };
+        let (cell_x, cell_y) = (cell % BLUE_NOISE_SIZE, cell / BLUE_NOISE_SIZE);
+        for y in 0..BLUE_NOISE_SIZE {
+            let dy = (y + BLUE_NOISE_SIZE - cell_y) % BLUE_NOISE_SIZE;
+            for x in 0..BLUE_NOISE_SIZE {
+                let dx = (x + BLUE_NOISE_SIZE - cell_x) % BLUE_NOISE_SIZE;
+                self.energy[y * BLUE_NOISE_SIZE + x] +=
+                    sign * self.kernel[dy * BLUE_NOISE_SIZE + dx];
+            }
+        }
+    }
+
+    fn tightest_cluster(&self) -> usize {
+        self.extreme_cell(true, |candidate, best| candidate > best)
+    }
+
+    fn largest_void(&self) -> usize {
+        self.extreme_cell(false, |candidate, best| candidate < best)
+    }
+
+    fn extreme_cell(&self, occupied: bool, better: impl Fn(f64, f64) -> bool) -> usize {
+        let mut best: Option<usize> = None;
+        for (cell, &energy) in self.energy.iter().enumerate() {
+            if self.occupied[cell] == occupied
+                && best.is_none_or(|best| better(energy, self.energy[best]))
+            {
+                best = Some(cell);
+            }
+        }
+        best.expect("dither pattern has a matching cell")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEQUENCES: [LowDiscrepancySequence; 3] = [
+        LowDiscrepancySequence::Sobol,
+        LowDiscrepancySequence::Halton,
+        LowDiscrepancySequence::BlueNoiseSobol,
+    ];
+
+    #[test]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn sequence_dimensions_are_stratified_over_power_of_two_prefixes() {
+        for sequence in SEQUENCES {
+            for dimension in 0..8 {
+                let mut strata = [false; 16];
+                for index in 0..16 {
+                    let value = sample_dimension(sequence, 7, (3, 5), index, dimension)
+                        .expect("dimension is available");
+                    assert!((0.0..1.0).contains(&value));
+                    strata[(value * 16.0) as usize] = true;
+                }
+                let filled = strata.iter().filter(|filled| **filled).count();
+                // Sobol is exact; Halton bases other than two stratify over their own powers, and
+                // the blue-noise shift rotates strata off the power-of-two grid.
+                let expected = match sequence {
+                    LowDiscrepancySequence::Sobol => 16,
+                    LowDiscrepancySequence::Halton if dimension == 0 => 16,
+                    LowDiscrepancySequence::Halton | LowDiscrepancySequence::BlueNoiseSobol => 10,
+                };
+                assert!(
+                    filled >= expected,
+                    "{sequence:?} dimension {dimension}: {filled}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn padded_sobol_pairs_are_two_dimensionally_stratified() {
+        for pair in 0..4 {
+            let mut cells = [false; 16];
+            for index in 0..16 {
+                let x = padded_sobol(index, 2 * pair, 11);
+                let y = padded_sobol(index, 2 * pair + 1, 11);
+                cells[(y * 4.0) as usize * 4 + (x * 4.0) as usize] = true;
+            }
+            assert!(cells.iter().all(|filled| *filled), "pair {pair}");
+        }
+    }
+
+    #[test]
+    fn sequence_points_are_deterministic_and_seeded() {
+        for sequence in SEQUENCES {
+            let mut first = SequencePoint::new(sequence, 3, (1, 2), 9);
+            let mut second = SequencePoint::new(sequence, 3, (1, 2), 9);
+            let mut reseeded = SequencePoint::new(sequence, 4, (1, 2), 9);
+            let mut differs = false;
+            for _ in 0..16 {
+                let value = first.next_sample();
+                assert_eq!(value, second.next_sample());
+                differs |= value != reseeded.next_sample();
+            }
+            assert!(differs, "{sequence:?}");
+        }
+    }
+
+    #[test]
+    fn halton_runs_out_of_prime_bases() {
+        let mut point = SequencePoint::new(LowDiscrepancySequence::Halton, 1, (0, 0), 3);
+        for _ in 0..HALTON_MAX_DIMENSIONS {
+            assert!(point.next_sample().is_some());
+        }
+        assert!(point.next_sample().is_none());
+    }
+
+    #[test]
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )]
+    fn blue_noise_mask_is_a_permutation_with_dissimilar_neighbours() {
+        let cell_count = BLUE_NOISE_SIZE * BLUE_NOISE_SIZE;
+        let size = BLUE_NOISE_SIZE as u32;
+        let mut seen = vec![false; cell_count];
+        let mut neighbour_difference = 0.0;
+        for y in 0..size {
+            for x in 0..size {
+                let value = blue_noise_value(x, y);
+                let rank = (value * cell_count as f64) as usize;
+                assert!(!seen[rank]);
+                seen[rank] = true;
+                neighbour_difference += (value - blue_noise_value(x + 1, y)).abs();
+            }
+        }
+        neighbour_difference /= cell_count as f64;
+
+        // Independent uniform values differ by 1/3 on average; blue noise pushes neighbours apart.
+        assert!(neighbour_difference > 0.4, "{neighbour_difference}");
+    }
+}
@@ -0,0 +1,516 @@
+//! Keyframed rigid-body and scale transforms for motion blur.
+//!
+//! A [`DecomposedTransform`] splits an affine object-to-world matrix into translation, rotation,
+//! and per-axis scale so that keyed poses can be interpolated without the shearing and shrinking
+//! that blending raw matrix entries produces. [`AnimatedTransform`] strings several decomposed
+//! keys together over time and computes bounds that cover the whole motion.
+
+use super::{
+    matrix::Matrix,
+    polygon_matrix::Bounds3,
+    quaternion::Quaternion,
+    vector::{Point, Vector},
+};
+use std::f64::consts::PI;
+
+const DECOMPOSITION_EPSILON: f64 = 1e-9;
+const RIGID_EPSILON: f64 = 1e-8;
+/// Largest rotation covered by one bounds sample; finer steps keep the padding tight.
+const MAX_BOUNDS_STEP_ANGLE: f64 = PI / 8.0;
+
+/// An affine transform split into scale, then rotation, then translation.
+///
+/// Points map to world space as `translation + rotation * (scale * point)`. A mirrored transform
+/// is represented by a negative x scale.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecomposedTransform {
+    /// The world-space translation.
+    pub translation: Vector,
+    /// The unit rotation quaternion.
+    pub rotation: Quaternion,
+    /// The per-axis scale applied before rotating.
+    pub scale: Vector,
+}
+
+impl Default for DecomposedTransform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl DecomposedTransform {
+    /// Creates a transform from its translation, rotation, and scale parts.
+    ///
+    /// The rotation is normalized.
+    #[must_use]
+    pub fn new(translation: Vector, rotation: Quaternion, scale: Vector) -> Self {
+        Self {
+            translation,
+            rotation: rotation.normalized(),
+            scale,
+        }
+    }
+
+    /// Returns the identity transform.
+    #[must_use]
+    pub fn identity() -> Self {
+        Self {
+            translation: Vector::new(0.0, 0.0, 0.0),
+            rotation: Quaternion::IDENTITY,
+            scale: Vector::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    /// Decomposes a 4x4 object-to-world matrix.
+    ///
+    /// Returns `None` for matrices that are not 4x4, are projective, contain non-finite values,
+    /// collapse an axis, or shear. Those transforms cannot be interpolated as a pose.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use gartus::gmath::{matrix::Matrix, motion::DecomposedTransform};
+    /// let matrix = Matrix::translate(1.0, 2.0, 3.0) * Matrix::scale(2.0, 2.0, 2.0);
+    /// let pose = DecomposedTransform::from_matrix(&matrix).unwrap();
+    /// assert!((pose.translation.y() - 2.0).abs() < 1e-12);
+    /// assert!((pose.scale.x() - 2.0).abs() < 1e-12);
+    /// ```
+    #[must_use]
+    pub fn from_matrix(matrix: &Matrix) -> Option<Self> {
+        if matrix.rows() != 4 || matrix.cols() != 4 {
+            return None;
+        }
+        let column = |index: usize| {
+            let mut basis = [0.0; 4];
+            basis[index] = 1.0;
+            matrix.transform_homogeneous_point(&basis)
+        };
+        let origin = column(3);
+        let axes = [column(0), column(1), column(2)];
+        if (origin[3] - 1.0).abs() > DECOMPOSITION_EPSILON
+            || axes
+                .iter()
+                .any(|axis| axis[3].abs() > DECOMPOSITION_EPSILON)
+        {
+            return None;
+        }
+
+        let mut axes = axes.map(|axis| Vector::new(axis[0], axis[1], axis[2]));
+        if !axes.iter().all(|axis| axis.is_finite()) || !origin.iter().all(|v| v.is_finite()) {
+            return None;
+        }
+        let mut scale = axes.map(Vector::length);
+        if scale.iter().any(|length| *length <= DECOMPOSITION_EPSILON) {
+            return None;
+        }
+        for (axis, length) in axes.iter_mut().zip(scale) {
+            *axis /= length;
+        }
+        if axes[0].dot(axes[1]).abs() > DECOMPOSITION_EPSILON
+            || axes[0].dot(axes[2]).abs() > DECOMPOSITION_EPSILON
+            || axes[1].dot(axes[2]).abs() > DECOMPOSITION_EPSILON
+        {
+            return None;
+        }
+        if axes[0].cross(axes[1]).dot(axes[2]) < 0.0 {
+            scale[0] = -scale[0];
+            axes[0] = -axes[0];
+        }
+
+        let rotation = [
+            [axes[0].x(), axes[1].x(), axes[2].x()],
+            [axes[0].y(), axes[1].y(), axes[2].y()],
+            [axes[0].z(), axes[1].z(), axes[2].z()],
+        ];
+        Some(Self {
+            translation: Vector::new(origin[0], origin[1], origin[2]),
+            rotation: Quaternion::from_rotation_matrix(rotation),
+            scale: Vector::new(scale[0], scale[1], scale[2]),
+        })
+    }
+
+    /// Recomposes the transform as a 4x4 object-to-world matrix.
+    pub fn to_matrix(&self) -> Matrix {
+        let rotation = self.rotation.to_rotation_matrix();
+        let mut matrix = Matrix::identity_matrix(4);
+        for (row, values) in rotation.iter().enumerate() {
+            for (col, value) in values.iter().enumerate() {
+                matrix.set(row, col, value * self.scale[col]);
+            }
+            matrix.set(row, 3, self.translation[row]);
+        }
+        matrix
+    }
+
+    /// Interpolates toward `other`: translation and scale linearly, rotation by slerp.
+    #[must_use]
+    pub fn interpolate(&self, other: &Self, t: f64) -> Self {
+        Self {
+            translation: self.translation + (other.translation - self.translation) * t,
+            rotation: self.rotation.slerp(&other.rotation, t),
+            scale: self.scale + (other.scale - self.scale) * t,
+        }
+    }
+
+    /// Maps an object-space point to world space.
+    #[must_use]
+    pub fn transform_point(&self, point: Point) -> Point {
+        let offset = self.transform_vector(Vector::new(point.x(), point.y(), point.z()));
+        Point::new(
+            offset.x() + self.translation.x(),
+            offset.y() + self.translation.y(),
+            offset.z() + self.translation.z(),
+        )
+    }
+
+    /// Maps an object-space direction to world space.
+    #[must_use]
+    pub fn transform_vector(&self, vector: Vector) -> Vector {
+        self.rotate(Vector::new(
+            vector.x() * self.scale.x(),
+            vector.y() * self.scale.y(),
+            vector.z() * self.scale.z(),
+        ))
+    }
+
+    /// Maps an object-space normal to world space with the inverse-transpose transform.
+    ///
+    /// The result is not normalized.
+    #[must_use]
+    pub fn transform_normal(&self, normal: Vector) -> Vector {
+        self.rotate(Vector::new(
+            normal.x() / self.scale.x(),
+            normal.y() / self.scale.y(),
+            normal.z() / self.scale.z(),
+        ))
+    }
+
+    /// Maps a world-space point back to object space.
+    #[must_use]
+    pub fn inverse_transform_point(&self, point: Point) -> Point {
+        let local = self.inverse_transform_vector(Vector::new(
+            point.x() - self.translation.x(),
+            point.y() - self.translation.y(),
+            point.z() - self.translation.z(),
+        ));
+        Point::new(local.x(), local.y(), local.z())
+    }
+
+    /// Maps a world-space direction back to object space.
+    #[must_use]
+    pub fn inverse_transform_vector(&self, vector: Vector) -> Vector {
+        let [x, y, z] =
+            self.rotation
+                .conjugate()
+                .rotate_vector([vector.x(), vector.y(), vector.z()]);
+        Vector::new(x / self.scale.x(), y / self.scale.y(), z / self.scale.z())
+    }
+
+    /// Returns whether the transform preserves lengths, so areas and solid angles carry over.
+    #[must_use]
+    pub fn is_rigid(&self) -> bool {
+        [self.scale.x(), self.scale.y(), self.scale.z()]
+            .into_iter()
+            .all(|scale| (scale.abs() - 1.0).abs() <= RIGID_EPSILON)
+    }
+
+    fn rotate(&self, vector: Vector) -> Vector {
+        let [x, y, z] = self
+            .rotation
+            .rotate_vector([vector.x(), vector.y(), vector.z()]);
+        Vector::new(x, y, z)
+    }
+}
+
+/// A transform keyed at two or more times and interpolated in between.
+///
+/// Times before the first key or after the last key clamp to the nearest key, so a shutter that
+/// extends past the animation holds the end poses.
+/// Rotations between neighbouring keys follow the shorter arc, so spins of half a turn or more
+/// need intermediate keys.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimatedTransform {
+    keys: Vec<(f64, DecomposedTransform)>,
+}
+
+impl AnimatedTransform {
+    /// Creates an animated transform from `(time, pose)` keys.
+    ///
+    /// Keys are sorted by time. Returns `None` when there are no keys, a time is not finite, or
+    /// two keys share a time.
+    #[must_use]
+    pub fn new(keys: impl IntoIterator<Item = (f64, DecomposedTransform)>) -> Option<Self> {
+        let mut keys: Vec<_> = keys.into_iter().collect();
+        if keys.is_empty() || keys.iter().any(|(time, _)| !time.is_finite()) {
+            return None;
+        }
+        keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+        if keys.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+            return None;
+        }
+        Some(Self { keys })
+    }
+
+    /// Creates an animated transform from `(time, matrix)` keys.
+    ///
+    /// Returns `None` when any matrix cannot be decomposed or the key times are invalid.
+    #[must_use]
+    pub fn from_matrices<'m>(keys: impl IntoIterator<Item = (f64, &'m Matrix)>) -> Option<Self> {
+        let keys = keys
+            .into_iter()
+            .map(|(time, matrix)| Some((time, DecomposedTransform::from_matrix(matrix)?)))
+            .collect::<Option<Vec<_>>>()?;
+        Self::new(keys)
+    }
+
+    /// Returns the time-sorted keys.
+    #[must_use]
+    pub fn keys(&self) -> &[(f64, DecomposedTransform)] {
+        &self.keys
+    }
+
+    /// Returns whether the pose changes between keys.
+    #[must_use]
+    pub fn is_animated(&self) -> bool {
+        self.keys.windows(2).any(|pair| pair[0].1 != pair[1].1)
+    }
+
+    /// Returns whether every key preserves lengths.
+    #[must_use]
+    pub fn is_rigid(&self) -> bool {
+        self.keys.iter().all(|(_, pose)| pose.is_rigid())
+    }
+
+    /// Returns the interpolated pose at `time`.
+    #[must_use]
+    pub fn at(&self, time: f64) -> DecomposedTransform {
+        let next = self.keys.partition_point(|(key_time, _)| *key_time <= time);
+        if next == 0 {
+            return self.keys[0].1;
+        }
+        if next == self.keys.len() {
+            return self.keys[next - 1].1;
+        }
+        let (start_time, start) = self.keys[next - 1];
+        let (end_time, end) = self.keys[next];
+        start.interpolate(&end, (time - start_time) / (end_time - start_time))
+    }
+
+    /// Returns world-space bounds covering `bounds` at every time between the first and last key.
+    ///
+    /// Each segment is sampled finely enough that no step rotates more than `PI / 8`, and the
+    /// sampled corner boxes are padded by the largest distance a corner's curved path can stray
+    /// from the straight chord between samples.
+    #[must_use]
+    pub fn motion_bounds(&self, bounds: Bounds3) -> Bounds3 {
+        let corners = bounds_corners(bounds);
+        let mut motion_bounds = transformed_corner_bounds(&corners, &self.keys[0].1);
+
+        for pair in self.keys.windows(2) {
+            let (start, end) = (pair[0].1, pair[1].1);
+            let angle = start.rotation.angle_to(&end.rotation);
+            // Keys are at most half a turn apart, so this is at most eight steps.
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let steps = (angle / MAX_BOUNDS_STEP_ANGLE).ceil().max(1.0) as u32;
+            let step_angle = angle / f64::from(steps);
+
+            let mut padding: f64 = 0.0;
+            for step in 1..=steps {
+                let t = f64::from(step) / f64::from(steps);
+                let previous_t = f64::from(step - 1) / f64::from(steps);
+                let pose = start.interpolate(&end, t);
+                let previous = start.interpolate(&end, previous_t);
+                motion_bounds = motion_bounds.union(transformed_corner_bounds(&corners, &pose));
+
+                // A corner follows R(t) * S(t) * p with S linear, so its distance from the chord is
+                // at most (angle^2 * radius + 2 * angle * scale change) / 8 over one step.
+                for corner in &corners {
+                    let scaled = |pose: &DecomposedTransform| {
+                        Vector::new(
+                            corner.x() * pose.scale.x(),
+                            corner.y() * pose.scale.y(),
+                            corner.z() * pose.scale.z(),
+                        )
+                    };
+                    let (from, to) = (scaled(&previous), scaled(&pose));
+                    let radius = from.length().max(to.length());
+                    let stretch = (to - from).length();
+                    padding = padding
+                        .max((step_angle * step_angle * radius + 2.0 * step_angle * stretch) / 8.0);
+                }
+            }
+            motion_bounds = pad_bounds(motion_bounds, padding);
+        }
+        motion_bounds
+    }
+}
+
+fn bounds_corners(bounds: Bounds3) -> [Point; 8] {
+    let mut corners = [Point::new(0.0, 0.0, 0.0); 8];
+    for (index, corner) in corners.iter_mut().enumerate() {
+        *corner = Point::new(
+            if index & 1 == 0 {
+                bounds.min.0
+            } else {
+                bounds.max.0
+            },
+            if index & 2 == 0 {
+                bounds.min.1
+            } else {
+                bounds.max.1
+            },
+            if index & 4 == 0 {
+                bounds.min.2
+            } else {
+                bounds.max.2
+            },
+        );
+    }
+    corners
+}
+
+fn transformed_corner_bounds(corners: &[Point; 8], pose: &DecomposedTransform) -> Bounds3 {
+    let first = pose.transform_point(corners[0]);
+    corners[1..]
+        .iter()
+        .fold(Bounds3::from_points(first, first), |bounds, corner| {
+            bounds.union_point(pose.transform_point(*corner))
+        })
+}
+
+fn pad_bounds(bounds: Bounds3, padding: f64) -> Bounds3 {
+    Bounds3::new(
+        (
+            bounds.min.0 - padding,
+            bounds.min.1 - padding,
+            bounds.min.2 - padding,
+        ),
+        (
+            bounds.max.0 + padding,
+            bounds.max.1 + padding,
+            bounds.max.2 + padding,
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matrices_round_trip_through_decomposition() {
+        let matrix = Matrix::translate(1.0, -2.0, 0.5)
+            * Matrix::rotate_y(35.0)
+            * Matrix::rotate_x(-20.0)
+            * Matrix::scale(2.0, 0.5, 3.0);
+        let pose = DecomposedTransform::from_matrix(&matrix).expect("affine matrix decomposes");
+        let recomposed = pose.to_matrix();
+
+        for point in [
+            [0.0, 0.0, 0.0, 1.0],
+            [1.0, 2.0, -3.0, 1.0],
+            [0.3, -0.7, 0.1, 0.0],
+        ] {
+            let expected = matrix.transform_homogeneous_point(&point);
+            let actual = recomposed.transform_homogeneous_point(&point);
+            for (a, b) in actual.iter().zip(expected) {
+                assert!((a - b).abs() < 1e-9);
+            }
+        }
+
+        let world = pose.transform_point(Point::new(1.0, 2.0, -3.0));
+        let local = pose.inverse_transform_point(world);
+        assert!((local.x() - 1.0).abs() < 1e-9);
+        assert!((local.y() - 2.0).abs() < 1e-9);
+        assert!((local.z() + 3.0).abs() < 1e-9);
+        assert!(!pose.is_rigid());
+
+        let shear = Matrix::new(
+            4,
+            4,
+            vec![
+                1.0, 0.0, 0.0, 0.0, 0.5, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
+            ],
+        );
+        assert!(DecomposedTransform::from_matrix(&shear).is_none());
+    }
+
+    #[test]
+    fn mirrored_matrices_keep_their_handedness() {
+        let matrix = Matrix::rotate_z(30.0) * Matrix::scale(1.0, -1.0, 1.0);
+        let pose = DecomposedTransform::from_matrix(&matrix).expect("mirror decomposes");
+        let recomposed = pose.to_matrix();
+        let expected = matrix.transform_homogeneous_point(&[0.2, 0.9, -0.4, 1.0]);
+        let actual = recomposed.transform_homogeneous_point(&[0.2, 0.9, -0.4, 1.0]);
+        for (a, b) in actual.iter().zip(expected) {
+            assert!((a - b).abs() < 1e-9);
+        }
+        assert!(pose.is_rigid());
+    }
+
+    #[test]
+    fn animated_transform_interpolates_keys_and_clamps_outside_them() {
+        let animation = AnimatedTransform::from_matrices([
+            (1.0, &Matrix::rotate_y(90.0)),
+            (0.0, &Matrix::identity_matrix(4)),
+            (2.0, &Matrix::translate(0.0, 4.0, 0.0)),
+        ])
+        .expect("valid keys");
+        assert!((animation.keys()[0].0).abs() < f64::EPSILON);
+        assert!(animation.is_animated());
+
+        let halfway = animation.at(0.5).transform_point(Point::new(1.0, 0.0, 0.0));
+        let expected = std::f64::consts::FRAC_1_SQRT_2;
+        assert!((halfway.x() - expected).abs() < 1e-9);
+        assert!((halfway.z().abs() - expected).abs() < 1e-9);
+
+        let late = animation.at(5.0).transform_point(Point::new(0.0, 0.0, 0.0));
+        assert!((late.y() - 4.0).abs() < 1e-12);
+        let early = animation
+            .at(-1.0)
+            .transform_point(Point::new(1.0, 0.0, 0.0));
+        assert!((early.x() - 1.0).abs() < 1e-12);
+
+        assert!(
+            AnimatedTransform::new([
+                (0.0, DecomposedTransform::identity()),
+                (0.0, DecomposedTransform::identity()),
+            ])
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn motion_bounds_cover_every_pose_during_a_spin() {
+        // Half-turn keys: slerp takes the shorter arc, so wider spins need intermediate keys.
+        let animation = AnimatedTransform::new((0..3).map(|key| {
+            let key = f64::from(key);
+            (
+                0.5 * key,
+                DecomposedTransform::new(
+                    Vector::new(0.0, 0.5 * key, 0.0),
+                    Quaternion::from_axis_angle(0.9 * PI * key, [0.0, 1.0, 0.0]),
+                    Vector::new(1.0, 1.0 + 0.5 * key, 1.0),
+                ),
+            )
+        }))
+        .expect("valid keys");
+        let object = Bounds3::new((2.0, -0.5, -0.5), (3.0, 0.5, 0.5));
+        let bounds = animation.motion_bounds(object);
+
+        for step in 0..=200 {
+            let pose = animation.at(f64::from(step) / 200.0);
+            for corner in bounds_corners(object) {
+                let point = pose.transform_point(corner);
+                assert!(point.x() >= bounds.min.0 && point.x() <= bounds.max.0);
+                assert!(point.y() >= bounds.min.1 && point.y() <= bounds.max.1);
+                assert!(point.z() >= bounds.min.2 && point.z() <= bounds.max.2);
+            }
+        }
+        // The sweep reaches around the y axis instead of staying near the key boxes.
+        assert!(bounds.min.0 < -2.9 && bounds.min.2 < -2.9 && bounds.max.2 > 2.9);
+        assert!(bounds.max.0 < 3.6);
+    }
+}
@@ -1,5 +1,5 @@
 /// Represents a quaternion for 3D rotations.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Quaternion {
     /// The real component of the quaternion.
     pub w: f64,
@@ -100,6 +100,157 @@ impl Quaternion {
         let rotated_qv = *self * qv * self.conjugate();
         [rotated_qv.x, rotated_qv.y, rotated_qv.z]
     }
+
+    /// The identity rotation.
+    pub const IDENTITY: Self = Quaternion {
+        w: 1.0,
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+    };
+
+    #[must_use]
+    /// Returns the four-dimensional dot product of two quaternions.
+    pub fn dot(&self, other: &Self) -> f64 {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    #[must_use]
+    /// Returns a unit-length copy of the quaternion, or the identity when it is zero.
+    pub fn normalized(&self) -> Self {
+        let mag = self.magnitude();
+        if mag <= f64::EPSILON || !mag.is_finite() {
+            return Self::IDENTITY;
+        }
+        Quaternion {
+            w: self.w / mag,
+            x: self.x / mag,
+            y: self.y / mag,
+            z: self.z / mag,
+        }
+    }
+
+    #[must_use]
+    /// Returns the angle in radians of the shortest rotation between two unit quaternions.
+    pub fn angle_to(&self, other: &Self) -> f64 {
+        2.0 * self.dot(other).abs().min(1.0).acos()
+    }
+
+    #[must_use]
+    /// Spherically interpolates between two unit quaternions along the shortest arc.
+    ///
+    /// `t = 0` returns `self` and `t = 1` returns `other` (or its negation, which is the same
+    /// rotation).
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use gartus::gmath::quaternion::Quaternion;
+    /// let start = Quaternion::IDENTITY;
+    /// let end = Quaternion::from_axis_angle(std::f64::consts::PI, [0.0, 1.0, 0.0]);
+    /// let halfway = start.slerp(&end, 0.5);
+    /// let rotated = halfway.rotate_vector([1.0, 0.0, 0.0]);
+    /// assert!(rotated[2].abs() > 0.999);
+    /// ```
+    pub fn slerp(&self, other: &Self, t: f64) -> Self {
+        let mut cos_theta = self.dot(other);
+        let mut end = *other;
+        if cos_theta < 0.0 {
+            cos_theta = -cos_theta;
+            end = Quaternion {
+                w: -end.w,
+                x: -end.x,
+                y: -end.y,
+                z: -end.z,
+            };
+        }
+
+        let (start_weight, end_weight) = if cos_theta > 1.0 - 1e-9 {
+            // Nearly identical rotations: normalized linear interpolation is exact enough.
+            (1.0 - t, t)
+        } else {
+            let theta = cos_theta.acos();
+            let sin_theta = theta.sin();
+            (
+                ((1.0 - t) * theta).sin() / sin_theta,
+                (t * theta).sin() / sin_theta,
+            )
+        };
+        Quaternion {
+            w: start_weight * self.w + end_weight * end.w,
+            x: start_weight * self.x + end_weight * end.x,
+            y: start_weight * self.y + end_weight * end.y,
+            z: start_weight * self.z + end_weight * end.z,
+        }
+        .normalized()
+    }
+
+    #[must_use]
+    /// Builds a unit quaternion from a row-major 3x3 rotation matrix.
+    ///
+    /// The matrix is expected to be orthonormal with a positive determinant.
+    pub fn from_rotation_matrix(m: [[f64; 3]; 3]) -> Self {
+        let trace = m[0][0] + m[1][1] + m[2][2];
+        let quaternion = if trace > 0.0 {
+            let s = 2.0 * (trace + 1.0).sqrt();
+            Quaternion {
+                w: 0.25 * s,
+                x: (m[2][1] - m[1][2]) / s,
+                y: (m[0][2] - m[2][0]) / s,
+                z: (m[1][0] - m[0][1]) / s,
+            }
+        } else if m[0][0] > m[1][1] && m[0][0] > m[2][2] {
+            let s = 2.0 * (1.0 + m[0][0] - m[1][1] - m[2][2]).sqrt();
+            Quaternion {
+                w: (m[2][1] - m[1][2]) / s,
+                x: 0.25 * s,
+                y: (m[0][1] + m[1][0]) / s,
+                z: (m[0][2] + m[2][0]) / s,
+            }
+        } else if m[1][1] > m[2][2] {
+            let s = 2.0 * (1.0 + m[1][1] - m[0][0] - m[2][2]).sqrt();
+            Quaternion {
+                w: (m[0][2] - m[2][0]) / s,
+                x: (m[0][1] + m[1][0]) / s,
+                y: 0.25 * s,
+                z: (m[1][2] + m[2][1]) / s,
+            }
+        } else {
+            let s = 2.0 * (1.0 + m[2][2] - m[0][0] - m[1][1]).sqrt();
+            Quaternion {
+                w: (m[1][0] - m[0][1]) / s,
+                x: (m[0][2] + m[2][0]) / s,
+                y: (m[1][2] + m[2][1]) / s,
+                z: 0.25 * s,
+            }
+        };
+        quaternion.normalized()
+    }
+
+    #[must_use]
+    /// Returns the row-major 3x3 rotation matrix for a unit quaternion.
+    pub fn to_rotation_matrix(&self) -> [[f64; 3]; 3] {
+        let Quaternion { w, x, y, z } = *self;
+        [
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - w * z),
+                2.0 * (x * z + w * y),
+            ],
+            [
+                2.0 * (x * y + w * z),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - w * x),
+            ],
+            [
+                2.0 * (x * z - w * y),
+                2.0 * (y * z + w * x),
+                1.0 - 2.0 * (x * x + y * y),
+            ],
+        ]
+    }
 }
 
 // Define quaternion multiplication.
@@ -115,3 +266,42 @@ impl std::ops::Mul<Quaternion> for Quaternion {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotation_matrix_round_trips_through_quaternion() {
+        let quaternion = Quaternion::from_axis_angle(2.3, [0.6, 0.0, 0.8]);
+        let matrix = quaternion.to_rotation_matrix();
+        let recovered = Quaternion::from_rotation_matrix(matrix);
+
+        assert!((quaternion.dot(&recovered).abs() - 1.0).abs() < 1e-12);
+        let rotated = quaternion.rotate_vector([1.0, 2.0, 3.0]);
+        for (row, expected) in matrix.iter().zip(rotated) {
+            let actual = row[0] + 2.0 * row[1] + 3.0 * row[2];
+            assert!((actual - expected).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn slerp_follows_the_shortest_arc_at_constant_speed() {
+        let start = Quaternion::from_axis_angle(0.2, [0.0, 0.0, 1.0]);
+        let end = Quaternion::from_axis_angle(1.4, [0.0, 0.0, 1.0]);
+        // The negated end quaternion is the same rotation and must take the same path.
+        let negated_end = Quaternion {
+            w: -end.w,
+            x: -end.x,
+            y: -end.y,
+            z: -end.z,
+        };
+
+        for t in [0.0, 0.25, 0.5, 1.0] {
+            let expected = Quaternion::from_axis_angle(0.2 + 1.2 * t, [0.0, 0.0, 1.0]);
+            assert!((start.slerp(&end, t).dot(&expected) - 1.0).abs() < 1e-12);
+            assert!((start.slerp(&negated_end, t).dot(&expected).abs() - 1.0).abs() < 1e-12);
+        }
+        assert!((start.angle_to(&end) - 1.2).abs() < 1e-12);
+    }
+}
@@ -1,23 +1,63 @@
 //! Deterministic random sampling helpers for graphics algorithms.
 
-use super::vector::Vector;
+use super::{
+    low_discrepancy::{LowDiscrepancySequence, SequencePoint},
+    vector::Vector,
+};
 
 const TAU: f64 = std::f64::consts::TAU;
 
 /// Small deterministic random-number generator for graphics samples.
 ///
 /// This uses a fixed `SplitMix64` stream so seeded renders are stable across
-/// dependency and platform changes.
+/// dependency and platform changes. A generator created with [`Self::from_sequence`] instead
+/// draws its real numbers from one point of a scrambled low-discrepancy sequence.
 #[derive(Clone, Copy, Debug)]
 pub struct SampleRng {
     state: u64,
+    sequence: Option<SequencePoint>,
 }
 
 impl SampleRng {
     /// Creates a sample RNG from a seed.
     #[must_use]
     pub fn new(seed: u64) -> Self {
-        Self { state: seed }
+        Self {
+            state: seed,
+            sequence: None,
+        }
+    }
+
+    /// Creates a sampler that hands out the dimensions of one low-discrepancy point in order.
+    ///
+    /// Every real number drawn afterwards (lens, time, BSDF, light, and Russian-roulette
+    /// decisions alike) is the next dimension of point `sample_index`, so consecutive samples of
+    /// a pixel stratify each decision along the path. `seed` randomizes the scramble and `pixel`
+    /// selects the per-pixel scramble or blue-noise dither. Integer draws from
+    /// [`Self::random_index`] and dimensions past the end of a finite sequence use an
+    /// independent `SplitMix64` stream.
+    #[must_use]
+    pub fn from_sequence(
+        sequence: LowDiscrepancySequence,
+        seed: u64,
+        pixel: (u32, u32),
+        sample_index: u32,
+    ) -> Self {
+        let point = SequencePoint::new(sequence, seed, pixel, sample_index);
+        Self {
+            state: point.fallback_seed(),
+            sequence: Some(point),
+        }
+    }
+
+    /// Returns the current generator state.
+    ///
+    /// Passing it to [`Self::new`] continues the same sequence, which lets long renders save and
+    /// resume their random streams. For a generator from [`Self::from_sequence`] this is only the
+    /// state of its fallback stream.
+    #[must_use]
+    pub const fn state(&self) -> u64 {
+        self.state
     }
 
     fn next_u64(&mut self) -> u64 {
@@ -54,6 +94,9 @@ impl SampleRng {
     #[must_use]
     #[allow(clippy::cast_precision_loss)]
     pub fn random_double(&mut self) -> f64 {
+        if let Some(value) = self.sequence.as_mut().and_then(SequencePoint::next_sample) {
+            return value;
+        }
         let bits = self.next_u64() >> 11;
         bits as f64 * (1.0 / ((1_u64 << 53) as f64))
     }
@@ -178,6 +221,22 @@ mod tests {
         }
     }
 
+    #[test]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn sequence_samplers_stratify_each_dimension_across_sample_indices() {
+        let mut strata = [[false; 8]; 3];
+        for sample_index in 0..8 {
+            let mut rng =
+                SampleRng::from_sequence(LowDiscrepancySequence::Sobol, 5, (2, 9), sample_index);
+            for dimension in &mut strata {
+                dimension[(rng.random_double() * 8.0) as usize] = true;
+            }
+            assert!(rng.random_index(4).is_some_and(|index| index < 4));
+        }
+
+        assert!(strata.iter().flatten().all(|filled| *filled));
+    }
+
     #[test]
     fn random_index_returns_values_below_upper_bound() {
         let mut rng = SampleRng::new(5);
@@ -5,6 +5,8 @@
 pub mod animation;
 /// Perspective projection helpers for simple 3D scenes.
 pub mod camera;
+/// Native image file encoders and decoders.
+pub mod codec;
 /// Includes RGB and HSL color types.
 pub mod colors;
 /// Includes the [`display::Canvas`] struct, which represents your drawing board.
@@ -1,12 +1,17 @@
 use crate::{
     gmath::{edge_matrix::EdgeMatrix, matrix::Matrix},
-    graphics::display::Canvas,
+    graphics::{
+        codec::{GifEncoder, GifOptions},
+        display::Canvas,
+        texture::load_ppm_canvas,
+    },
 };
 use std::{
     error::Error,
-    fmt, fs, io,
+    fmt,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
     path::{Path, PathBuf},
-    process::Command,
     time::{SystemTime, UNIX_EPOCH},
 };
 
@@ -16,6 +21,9 @@ use rayon::prelude::*;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 /// Explicit frame recorder for animations.
+///
+/// A recorder either writes each frame as a PPM file for a later [`FrameRecorder::encode_gif`],
+/// or, when created with [`FrameRecorder::streaming_gif`], encodes frames straight into the GIF.
 #[derive(Debug)]
 pub struct FrameRecorder {
     dir: PathBuf,
@@ -23,6 +31,17 @@ pub struct FrameRecorder {
     delay_cs: u16,
     frame_index: usize,
     captured_paths: Vec<PathBuf>,
+    frame_delays: Vec<u16>,
+    gif_options: GifOptions,
+    stream: Option<GifStream>,
+}
+
+/// Output state for a recorder that encodes frames as they are captured.
+#[derive(Debug)]
+struct GifStream {
+    output: PathBuf,
+    encoder: Option<GifEncoder<BufWriter<File>>>,
+    created: bool,
 }
 
 /// Error returned while rendering and encoding an animation.
@@ -66,6 +85,8 @@ pub struct AnimationRenderOptions {
     output: PathBuf,
     delay_cs: u16,
     preview: Option<(usize, PathBuf)>,
+    gif_options: GifOptions,
+    stream_frames: bool,
     cleanup_frames: bool,
     clear_existing_frames: bool,
     unique_frame_dir: bool,
@@ -88,6 +109,8 @@ impl AnimationRenderOptions {
             output: output.into(),
             delay_cs: 2,
             preview: None,
+            gif_options: GifOptions::default(),
+            stream_frames: true,
             cleanup_frames: true,
             clear_existing_frames: true,
             unique_frame_dir: false,
@@ -109,6 +132,23 @@ impl AnimationRenderOptions {
         self
     }
 
+    /// Sets the quantization, dithering, and looping options for the GIF encoder.
+    #[must_use]
+    pub fn gif_options(mut self, gif_options: GifOptions) -> Self {
+        self.gif_options = gif_options;
+        self
+    }
+
+    /// Sets whether frames are encoded directly into the GIF as they are rendered.
+    ///
+    /// Streaming is the default. When disabled, frames are first written as PPM files under the
+    /// frame directory and encoded once every frame exists.
+    #[must_use]
+    pub fn stream_frames(mut self, stream_frames: bool) -> Self {
+        self.stream_frames = stream_frames;
+        self
+    }
+
     /// Sets whether generated PPM frames are deleted after a successful GIF encode.
     #[must_use]
     pub fn cleanup_frames(mut self, cleanup_frames: bool) -> Self {
@@ -154,9 +194,27 @@ impl FrameRecorder {
             delay_cs: 2,
             frame_index: 0,
             captured_paths: Vec::new(),
+            frame_delays: Vec::new(),
+            gif_options: GifOptions::default(),
+            stream: None,
         }
     }
 
+    /// Creates a recorder that encodes frames directly into the GIF at `output`.
+    ///
+    /// No frame files are written. The GIF is created on the first capture and completed by
+    /// [`FrameRecorder::finish_gif`].
+    #[must_use]
+    pub fn streaming_gif(output: impl Into<PathBuf>) -> Self {
+        let mut recorder = Self::new(PathBuf::new(), "frame-");
+        recorder.stream = Some(GifStream {
+            output: output.into(),
+            encoder: None,
+            created: false,
+        });
+        recorder
+    }
+
     /// Sets the GIF delay in centiseconds.
     #[must_use]
     pub fn with_delay(mut self, delay_cs: u16) -> Self {
@@ -164,6 +222,19 @@ impl FrameRecorder {
         self
     }
 
+    /// Sets the quantization, dithering, and looping options for the GIF encoder.
+    #[must_use]
+    pub fn with_gif_options(mut self, gif_options: GifOptions) -> Self {
+        self.gif_options = gif_options;
+        self
+    }
+
+    /// Returns whether frames are encoded directly into the GIF instead of frame files.
+    #[must_use]
+    pub fn is_streaming(&self) -> bool {
+        self.stream.is_some()
+    }
+
     /// Returns the next frame index.
     #[must_use]
     pub fn frame_index(&self) -> usize {
@@ -172,18 +243,41 @@ impl FrameRecorder {
 
     /// Captures the current canvas as the next frame.
     ///
+    /// Returns the written frame file, or the GIF path for a streaming recorder.
+    ///
     /// # Errors
-    /// Returns `Err` if the directory cannot be created or the PPM file cannot be written.
+    /// Returns `Err` if the directory cannot be created or the PPM file cannot be written. A
+    /// streaming recorder returns `Err` if the GIF cannot be created or the frame does not match
+    /// the first frame's size; the partial GIF is then removed.
     pub fn capture(&mut self, canvas: &Canvas) -> io::Result<PathBuf> {
-        fs::create_dir_all(&self.dir)?;
-        let path = self.frame_path(self.frame_index);
-        canvas.save_binary(path.to_str().ok_or_else(|| {
-            io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "animation path is not valid UTF-8",
-            )
-        })?)?;
-        self.captured_paths.push(path.clone());
+        self.capture_with_delay(canvas, self.delay_cs)
+    }
+
+    /// Captures the current canvas as the next frame, shown for `delay_cs` centiseconds.
+    ///
+    /// # Errors
+    /// Returns `Err` under the same conditions as [`FrameRecorder::capture`].
+    pub fn capture_with_delay(&mut self, canvas: &Canvas, delay_cs: u16) -> io::Result<PathBuf> {
+        let path = if let Some(stream) = &mut self.stream {
+            let result = stream.add_frame(canvas, delay_cs, self.gif_options);
+            if result.is_err() {
+                stream.discard();
+            }
+            result?;
+            stream.output.clone()
+        } else {
+            fs::create_dir_all(&self.dir)?;
+            let path = self.frame_path(self.frame_index);
+            canvas.save_binary(path.to_str().ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "animation path is not valid UTF-8",
+                )
+            })?)?;
+            self.captured_paths.push(path.clone());
+            path
+        };
+        self.frame_delays.push(delay_cs);
         self.frame_index += 1;
         Ok(path)
     }
@@ -243,59 +337,55 @@ impl FrameRecorder {
         Ok(removed)
     }
 
-    /// Encodes captured frames to a GIF using `ImageMagick` `magick`.
+    /// Encodes the captured PPM frames to a GIF at `output`.
+    ///
+    /// Frames are read back one at a time, so only the frame being encoded is held in memory.
     ///
     /// # Errors
-    /// Returns `Err` if no frames have been captured, if `magick` cannot be spawned, or if it exits with a failure status.
+    /// Returns `Err` if no frames have been captured, if this is a streaming recorder, or if a
+    /// frame cannot be read or the GIF cannot be written. A partially written GIF is removed.
     pub fn encode_gif(&self, output: impl AsRef<Path>) -> io::Result<()> {
-        if self.frame_index == 0 {
+        if self.is_streaming() {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
-                "cannot encode animation with no captured frames",
+                "streaming recorders encode while capturing; call `finish_gif` instead",
             ));
         }
+        encode_frame_files(
+            self.captured_paths
+                .iter()
+                .zip(self.frame_delays.iter().copied()),
+            self.gif_options,
+            output.as_ref(),
+        )
+    }
 
-        let output = output.as_ref();
-        let run_encoder = |binary: &str| {
-            let mut command = Command::new(binary);
-            command
-                .arg("-delay")
-                .arg(self.delay_cs.to_string())
-                .arg("-loop")
-                .arg("0");
-            for path in &self.captured_paths {
-                command.arg(path);
-            }
-            command.arg(output);
-            command.status()
+    /// Completes the GIF written by a streaming recorder and returns its path.
+    ///
+    /// # Errors
+    /// Returns `Err` if this recorder is not streaming, if no frames have been captured, or if
+    /// the GIF trailer cannot be written. A partially written GIF is removed.
+    pub fn finish_gif(&mut self) -> io::Result<PathBuf> {
+        let Some(stream) = &mut self.stream else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "only streaming recorders can finish a GIF; call `encode_gif` instead",
+            ));
         };
-
-        let (binary, status) = match run_encoder("magick") {
-            Ok(status) => ("magick", status),
-            Err(error) if error.kind() == io::ErrorKind::NotFound => {
-                ("convert", run_encoder("convert").map_err(|convert_error| {
-                    io::Error::new(
-                        convert_error.kind(),
-                        format!(
-                            "failed to run ImageMagick `magick` or `convert`; is ImageMagick installed and in PATH? {convert_error}"
-                        ),
-                    )
-                })?)
-            }
-            Err(error) => {
-                return Err(io::Error::new(
-                    error.kind(),
-                    format!("failed to run ImageMagick `magick`: {error}"),
-                ));
-            }
+        let Some(encoder) = stream.encoder.take() else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "cannot encode animation with no captured frames",
+            ));
         };
-
-        if !status.success() {
-            return Err(io::Error::other(format!(
-                "ImageMagick `{binary}` failed with status {status} while encoding animation"
-            )));
+        let result = encoder
+            .finish()
+            .and_then(|mut out| out.flush())
+            .map(|()| stream.output.clone());
+        if result.is_err() {
+            stream.discard();
         }
-        Ok(())
+        result
     }
 
     /// Encodes captured frames to a GIF and then removes the generated PPM frames.
@@ -422,6 +512,8 @@ impl FrameRecorder {
             output,
             delay_cs,
             preview,
+            gif_options,
+            stream_frames,
             cleanup_frames,
             clear_existing_frames,
             unique_frame_dir,
@@ -442,18 +534,9 @@ impl FrameRecorder {
             )));
         }
 
-        if let Some(parent) = output
-            .parent()
-            .filter(|parent| !parent.as_os_str().is_empty())
-        {
-            fs::create_dir_all(parent).map_err(AnimationError::Io)?;
-        }
-        if let Some((_, ref preview_output)) = preview
-            && let Some(parent) = preview_output
-                .parent()
-                .filter(|parent| !parent.as_os_str().is_empty())
-        {
-            fs::create_dir_all(parent).map_err(AnimationError::Io)?;
+        create_parent_dir(&output).map_err(AnimationError::Io)?;
+        if let Some((_, ref preview_output)) = preview {
+            create_parent_dir(preview_output).map_err(AnimationError::Io)?;
         }
 
         let prefix = sanitize_prefix(&prefix);
@@ -463,8 +546,13 @@ impl FrameRecorder {
             dir
         };
 
-        let mut recorder = Self::new(frame_dir, prefix).with_delay(delay_cs);
-        if clear_existing_frames {
+        let recorder = if stream_frames {
+            Self::streaming_gif(&output)
+        } else {
+            Self::new(frame_dir, prefix)
+        };
+        let mut recorder = recorder.with_delay(delay_cs).with_gif_options(gif_options);
+        if clear_existing_frames && !stream_frames {
             recorder
                 .clear_existing_frames()
                 .map_err(AnimationError::Io)?;
@@ -483,13 +571,22 @@ impl FrameRecorder {
                     eprintln!("Wrote frame {}/{}", frame + 1, frames);
                 }
             }
+            if stream_frames {
+                return recorder.finish_gif().map(drop).map_err(AnimationError::Io);
+            }
             if show_progress {
                 eprintln!("Encoding GIF...");
             }
             recorder.encode_gif(&output).map_err(AnimationError::Io)
         })();
 
-        if cleanup_frames {
+        if result.is_err()
+            && let Some(stream) = &mut recorder.stream
+        {
+            stream.discard();
+        }
+
+        if cleanup_frames && !stream_frames {
             let cleanup_result = recorder
                 .clear_captured_frames()
                 .and_then(|_| {
@@ -517,6 +614,92 @@ impl FrameRecorder {
     }
 }
 
+impl GifStream {
+    fn add_frame(&mut self, canvas: &Canvas, delay_cs: u16, options: GifOptions) -> io::Result<()> {
+        if let Some(encoder) = &mut self.encoder {
+            return encoder.add_frame(canvas, delay_cs);
+        }
+        create_parent_dir(&self.output)?;
+        let out = BufWriter::new(File::create(&self.output)?);
+        self.created = true;
+        self.encoder
+            .insert(GifEncoder::new(
+                out,
+                canvas.width(),
+                canvas.height(),
+                options,
+            )?)
+            .add_frame(canvas, delay_cs)
+    }
+
+    /// Drops the in-progress encoder and removes whatever part of the GIF this stream wrote.
+    fn discard(&mut self) {
+        self.encoder = None;
+        if std::mem::take(&mut self.created) {
+            let _ = fs::remove_file(&self.output);
+        }
+    }
+}
+
+/// Encodes PPM frame files, each paired with its delay in centiseconds, into a GIF at `output`.
+pub(crate) fn encode_frame_files<'a>(
+    frames: impl IntoIterator<Item = (&'a PathBuf, u16)>,
+    options: GifOptions,
+    output: &Path,
+) -> io::Result<()> {
+    let mut frames = frames.into_iter().peekable();
+    if frames.peek().is_none() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "cannot encode animation with no captured frames",
+        ));
+    }
+
+    let mut created = false;
+    let result = (|| {
+        let mut encoder = None;
+        for (path, delay_cs) in frames {
+            let canvas = load_ppm_canvas(path).map_err(|error| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("failed to read frame {}: {error}", path.display()),
+                )
+            })?;
+            if encoder.is_none() {
+                let out = BufWriter::new(File::create(output)?);
+                created = true;
+                encoder = Some(GifEncoder::new(
+                    out,
+                    canvas.width(),
+                    canvas.height(),
+                    options,
+                )?);
+            }
+            if let Some(encoder) = &mut encoder {
+                encoder.add_frame(&canvas, delay_cs)?;
+            }
+        }
+        match encoder {
+            Some(encoder) => encoder.finish()?.flush(),
+            None => Ok(()),
+        }
+    })();
+    if result.is_err() && created {
+        let _ = fs::remove_file(output);
+    }
+    result
+}
+
+fn create_parent_dir(path: &Path) -> io::Result<()> {
+    match path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        Some(parent) => fs::create_dir_all(parent),
+        None => Ok(()),
+    }
+}
+
 fn save_preview(canvas: &Canvas, preview_output: &Path) -> io::Result<()> {
     canvas.save_extension(preview_output.to_str().ok_or_else(|| {
         io::Error::new(
@@ -1,16 +1,23 @@
 use super::colors::Rgb;
-use crate::gmath::random::SampleRng;
 use crate::gmath::ray::Ray;
 use crate::gmath::{
     geometry::CameraPose,
     vector::{Point, Vector},
 };
+use crate::gmath::{low_discrepancy::LowDiscrepancySequence, random::SampleRng};
+#[cfg(feature = "spectral")]
+use crate::graphics::raytracing::ScatterRecord;
 use crate::graphics::raytracing::{
-    EnvironmentLight, HitRecord, Hittable, INFINITY, Interval, LinearColor, MaterialPdf,
-    PdfContext, ScatterRecord, component_mul, degrees_to_radians,
+    BsdfFlags, BsdfSample, EnvironmentLight, HitRecord, Hittable, INFINITY, Interval, LinearColor,
+    PdfContext, component_mul, degrees_to_radians,
 };
 use crate::graphics::raytracing::{
-    HittablePdf, MixturePdf, Pdf, SHADOW_ACNE_EPSILON, scenes::normal_scene_color,
+    HittablePdf, Pdf, SHADOW_ACNE_EPSILON,
+    bdpt::{BidirectionalCamera, BidirectionalIntegrator, FilmSplat},
+    checkpoint::{CheckpointEstimator, RenderCheckpoint},
+    denoise::{Denoiser, luminance},
+    photon::{PhotonGrid, PhotonMapIntegrator, PhotonMappingOptions, PhotonPixel},
+    scenes::normal_scene_color,
 };
 #[cfg(feature = "spectral")]
 use crate::graphics::raytracing::{
@@ -19,7 +26,10 @@ use crate::graphics::raytracing::{
 };
 use crate::{
     gmath::{edge_matrix::EdgeMatrix, matrix::Matrix, polygon_matrix::PolygonMatrix},
-    graphics::display::{Canvas, HdrImage},
+    graphics::{
+        codec::{ExrCompression, ExrError, ExrImage, ExrPixelType},
+        display::{Canvas, HdrImage},
+    },
 };
 #[cfg(feature = "rayon")]
 use rayon::prelude::*;
@@ -53,6 +63,30 @@ pub enum PixelSampleMode {
         /// Number of strata along each pixel axis.
         grid_width: u32,
     },
+    /// Draw every path dimension from an Owen-scrambled Sobol sequence.
+    ///
+    /// Unlike the stratified modes, this stratifies the lens, time, and every scattering and
+    /// light-sampling decision along the path, and any sample count works.
+    Sobol,
+    /// Draw every path dimension from an Owen-scrambled Halton sequence.
+    Halton,
+    /// Draw Sobol points shared across pixels and dithered with a blue-noise mask, so the
+    /// remaining error appears as fine-grained noise.
+    BlueNoise,
+}
+
+impl PixelSampleMode {
+    /// Returns the low-discrepancy sequence driving each pixel sample, or `None` for independent
+    /// random and jittered-grid modes.
+    #[must_use]
+    pub const fn low_discrepancy_sequence(self) -> Option<LowDiscrepancySequence> {
+        match self {
+            Self::Random | Self::Stratified | Self::StratifiedGrid { .. } => None,
+            Self::Sobol => Some(LowDiscrepancySequence::Sobol),
+            Self::Halton => Some(LowDiscrepancySequence::Halton),
+            Self::BlueNoise => Some(LowDiscrepancySequence::BlueNoiseSobol),
+        }
+    }
 }
 
 /// Direct-lighting strategy used when rendering with an explicit light target set.
@@ -61,7 +95,14 @@ pub enum PixelSampleMode {
 /// bounces sample a mixture of the material PDF and the light-target PDF, then collect light if
 /// that continuation ray reaches an emitter. [`Self::NextEventEstimation`] samples one light
 /// direction immediately, casts a visibility ray through the world, then continues the path with
-/// the material PDF only.
+/// the material's BSDF sampling. Light samples and BSDF-sampled rays that reach an emitter or the
+/// environment are combined with the power heuristic, so neither strategy's noise dominates.
+/// Only materials reporting a diffuse or glossy
+/// [`BsdfFlags`](crate::graphics::raytracing::BsdfFlags) lobe take explicit light samples.
+/// [`Self::Bidirectional`] also traces a subpath from a point sampled on the light targets and
+/// connects every camera and light vertex pair, splatting light-subpath vertices that see the lens
+/// directly onto the film. Renders that only estimate isolated pixels (progressive, adaptive,
+/// checkpointed, AOV, environment, and spectral renders) fall back to next-event estimation.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum DirectLightingMode {
     /// Mix light-target sampling into the ordinary path-continuation direction.
@@ -69,6 +110,8 @@ pub enum DirectLightingMode {
     CurrentPathContinuation,
     /// Add direct lighting with an explicit shadow ray at each diffuse or volume scattering event.
     NextEventEstimation,
+    /// Connect camera subpaths to light subpaths with multiple importance sampling.
+    Bidirectional,
 }
 
 /// Transport backend used by the default Canvas/HDR render entrypoints.
@@ -113,12 +156,22 @@ impl SamplingStrategy {
         }
     }
 
+    /// Creates a bidirectional path-tracing strategy.
+    #[must_use]
+    pub const fn bidirectional() -> Self {
+        Self {
+            direct_lighting_mode: DirectLightingMode::Bidirectional,
+            light_pdf_weight: 0.0,
+        }
+    }
+
     /// Creates the strategy corresponding to the legacy direct-lighting mode.
     #[must_use]
     pub const fn from_direct_lighting_mode(mode: DirectLightingMode) -> Self {
         match mode {
             DirectLightingMode::CurrentPathContinuation => Self::current_path_continuation(),
             DirectLightingMode::NextEventEstimation => Self::next_event_estimation(),
+            DirectLightingMode::Bidirectional => Self::bidirectional(),
         }
     }
 
@@ -158,40 +211,62 @@ impl SamplingStrategy {
     fn uses_next_event_estimation(self) -> bool {
         matches!(
             self.direct_lighting_mode,
-            DirectLightingMode::NextEventEstimation
+            DirectLightingMode::NextEventEstimation | DirectLightingMode::Bidirectional
         )
     }
 
+    fn uses_bidirectional(self) -> bool {
+        self.direct_lighting_mode == DirectLightingMode::Bidirectional
+    }
+
+    /// Samples the next path direction at a scattering hit.
+    ///
+    /// Materials with an evaluable lobe mix in light-target sampling for current-path
+    /// continuation, or mark the sample for power-heuristic weighting against the explicit light
+    /// samples taken by next-event estimation.
     fn continuation_sample(
         self,
         ray: &Ray,
         hit: &HitRecord<'_>,
-        material_pdf: MaterialPdf,
         lights: Option<&dyn Hittable>,
         environment: Option<&EnvironmentLight>,
         rng: &mut SampleRng,
-    ) -> ContinuationSample {
+    ) -> Option<ContinuationSample> {
+        let material = hit.material;
+        let evaluable = material.bsdf_flags(hit).has_non_delta_lobe();
         if self.direct_lighting_mode == DirectLightingMode::CurrentPathContinuation
+            && evaluable
             && let Some(lights) = lights
         {
             let light_pdf = HittablePdf::new(lights, PdfContext::new(hit.point, ray.time()));
-            let mixture_pdf = MixturePdf::weighted(light_pdf, material_pdf, self.light_pdf_weight);
-            let direction = mixture_pdf.generate(rng);
-            return ContinuationSample {
+            let direction = if rng.random_double() < self.light_pdf_weight {
+                light_pdf.generate(rng)
+            } else {
+                let sample = material.sample_bsdf(ray, hit, rng)?;
+                if sample.is_delta() {
+                    return Some(ContinuationSample::unweighted(sample));
+                }
+                sample.direction
+            };
+            let scattered = Ray::with_time(hit.point, direction, ray.time());
+            return Some(ContinuationSample {
                 direction,
-                pdf_value: mixture_pdf.value(direction),
-                suppress_next_emission: false,
+                value: material.eval_bsdf(ray, hit, &scattered),
+                pdf_value: self.light_pdf_weight * light_pdf.value(direction)
+                    + (1.0 - self.light_pdf_weight) * material.bsdf_pdf(ray, hit, &scattered),
+                weight_light_emission: false,
                 weight_environment_miss: false,
-            };
+            });
         }
 
-        let direction = material_pdf.generate(rng);
-        ContinuationSample {
-            direction,
-            pdf_value: material_pdf.value(direction),
-            suppress_next_emission: self.uses_next_event_estimation() && lights.is_some(),
-            weight_environment_miss: self.uses_next_event_estimation() && environment.is_some(),
-        }
+        let sample = material.sample_bsdf(ray, hit, rng)?;
+        let explicit_lighting =
+            self.uses_next_event_estimation() && evaluable && !sample.is_delta();
+        Some(ContinuationSample {
+            weight_light_emission: explicit_lighting && lights.is_some(),
+            weight_environment_miss: explicit_lighting && environment.is_some(),
+            ..ContinuationSample::unweighted(sample)
+        })
     }
 }
 
@@ -354,15 +429,43 @@ impl RayBackgroundContext<'_> {
 #[derive(Debug, Clone, Copy, PartialEq)]
 struct ContinuationSample {
     direction: Vector,
+    value: LinearColor,
     pdf_value: f64,
-    suppress_next_emission: bool,
+    weight_light_emission: bool,
     weight_environment_miss: bool,
 }
 
+impl ContinuationSample {
+    const fn unweighted(sample: BsdfSample) -> Self {
+        Self {
+            direction: sample.direction,
+            value: sample.value,
+            pdf_value: sample.pdf,
+            weight_light_emission: false,
+            weight_environment_miss: false,
+        }
+    }
+
+    fn light_mis_vertex(&self, origin: Point, time: f64) -> Option<LightMisVertex> {
+        self.weight_light_emission.then(|| LightMisVertex {
+            context: PdfContext::new(origin, time),
+            bsdf_pdf: self.pdf_value,
+        })
+    }
+}
+
+/// Path vertex whose BSDF-sampled continuation competes with an explicit light sample.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct LightMisVertex {
+    context: PdfContext,
+    bsdf_pdf: f64,
+}
+
 /// Per-pixel adaptive sampling settings for random world renders.
 ///
-/// Adaptive sampling is only applied to [`PixelSampleMode::Random`]. Stratified modes keep their
-/// exact grid sample count so jittered comparisons and final renders stay deterministic.
+/// Adaptive sampling is applied to [`PixelSampleMode::Random`] and the low-discrepancy sequence
+/// modes. Stratified modes keep their exact grid sample count so jittered comparisons and final
+/// renders stay deterministic.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct AdaptiveSampling {
     /// Minimum samples to take before checking convergence.
@@ -501,6 +604,11 @@ pub struct DenoisingAovs {
     pub albedo_linear: Vec<LinearColor>,
     /// Linear floating-point first-hit normal samples encoded in `[0, 1]`, row-major.
     pub normal_linear: Vec<LinearColor>,
+    /// Per-pixel variance of the beauty luminance mean, row-major.
+    ///
+    /// Empty when the producer did not track sample statistics. Pixels with fewer than two
+    /// accepted samples hold `NaN`.
+    pub variance: Vec<f64>,
 }
 
 impl DenoisingAovs {
@@ -521,7 +629,71 @@ impl DenoisingAovs {
             beauty_linear,
             albedo_linear,
             normal_linear,
+            variance: Vec::new(),
+        }
+    }
+
+    /// Attaches a per-pixel beauty variance buffer.
+    #[must_use]
+    pub fn with_variance(mut self, variance: Vec<f64>) -> Self {
+        self.variance = variance;
+        self
+    }
+
+    /// Denoises the beauty pass with default [`Denoiser`] settings.
+    #[must_use]
+    pub fn denoise(&self) -> HdrImage {
+        Denoiser::default().denoise(self)
+    }
+
+    /// Builds a multi-layer `OpenEXR` image from the linear AOVs.
+    ///
+    /// The beauty pass is the default `R`, `G`, `B` layer, followed by `albedo.*` and
+    /// `normal.*` layers. Normals are decoded back to `[-1, 1]`. A non-empty variance buffer is
+    /// written as a float `variance.Y` channel.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if a linear buffer does not match the beauty canvas size.
+    pub fn to_exr_image(&self, pixel_type: ExrPixelType) -> Result<ExrImage, ExrError> {
+        let normals: Vec<LinearColor> = self
+            .normal_linear
+            .iter()
+            .map(|normal| {
+                LinearColor::new(
+                    normal.red.mul_add(2.0, -1.0),
+                    normal.green.mul_add(2.0, -1.0),
+                    normal.blue.mul_add(2.0, -1.0),
+                )
+            })
+            .collect();
+        let mut image = ExrImage::new(self.beauty.width(), self.beauty.height());
+        image.push_rgb_layer("", &self.beauty_linear, pixel_type)?;
+        image.push_rgb_layer("albedo", &self.albedo_linear, pixel_type)?;
+        image.push_rgb_layer("normal", &normals, pixel_type)?;
+        if !self.variance.is_empty() {
+            #[allow(clippy::cast_possible_truncation)]
+            let variance = self.variance.iter().map(|value| *value as f32).collect();
+            image.push_channel("variance.Y", ExrPixelType::Float, variance)?;
         }
+        Ok(image)
+    }
+
+    /// Saves the AOVs as one multi-layer `OpenEXR` file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if a linear buffer does not match the beauty canvas size or the underlying
+    /// I/O fails.
+    pub fn save_exr(
+        &self,
+        file_name: &str,
+        pixel_type: ExrPixelType,
+        compression: ExrCompression,
+    ) -> io::Result<()> {
+        self.to_exr_image(pixel_type)?
+            .save(file_name, compression)?;
+        Ok(())
     }
 }
 
@@ -686,6 +858,36 @@ struct PixelRenderContext<'a> {
     background: RayBackgroundContext<'a>,
 }
 
+/// Running luminance mean and squared deviation of finite pixel samples.
+#[derive(Clone, Copy, Debug, Default)]
+struct LuminanceMoments {
+    count: u32,
+    mean: f64,
+    m2: f64,
+}
+
+impl LuminanceMoments {
+    fn add(&mut self, sample: LinearColor) {
+        if !sample.is_finite() {
+            return;
+        }
+        self.count += 1;
+        let value = luminance(sample);
+        let delta = value - self.mean;
+        self.mean += delta / f64::from(self.count);
+        self.m2 += delta * (value - self.mean);
+    }
+
+    /// Returns the variance of the pixel mean, or `NaN` below two samples.
+    fn variance_of_mean(self) -> f64 {
+        if self.count < 2 {
+            return f64::NAN;
+        }
+        let count = f64::from(self.count);
+        (self.m2 / (count - 1.0) / count).max(0.0)
+    }
+}
+
 #[cfg(feature = "spectral")]
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
 struct SpectralPixel {
@@ -697,6 +899,26 @@ impl RayColorContext<'_> {
     fn miss_radiance(&self, direction: Vector) -> LinearColor {
         self.background.radiance(direction)
     }
+
+    /// Environment lights evaluate misses spectrally; other backgrounds are lifted from RGB.
+    #[cfg(feature = "spectral")]
+    fn miss_spectral_radiance(&self, direction: Vector, wavelength: SampledWavelength) -> f64 {
+        self.environment.map_or_else(
+            || RayCamera::sample_spectrum(self.miss_radiance(direction), wavelength),
+            |environment| environment.spectral_radiance(direction, wavelength),
+        )
+    }
+
+    /// Power-heuristic weight for emission reached along `direction` from `vertex`.
+    fn light_emission_weight(&self, vertex: Option<LightMisVertex>, direction: Vector) -> f64 {
+        match (self.lights, vertex) {
+            (Some(lights), Some(vertex)) => RayCamera::power_heuristic(
+                vertex.bsdf_pdf,
+                lights.pdf_value(vertex.context, direction),
+            ),
+            _ => 1.0,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -1246,6 +1468,7 @@ impl RayCamera {
     /// The camera takes at least `min_samples`, up to `max_samples`, and stops early when the
     /// largest channel standard error of the mean drops below `error_threshold`. Adaptive sampling
     /// is ignored for stratified modes because those modes promise an exact jittered grid.
+    /// Sequence modes stay progressive, so stopping early keeps their stratification.
     ///
     /// # Panics
     ///
@@ -1546,7 +1769,10 @@ impl RayCamera {
     #[must_use]
     pub fn effective_samples_per_pixel(self) -> u32 {
         match self.pixel_sample_mode {
-            PixelSampleMode::Random => self.samples_per_pixel,
+            PixelSampleMode::Random
+            | PixelSampleMode::Sobol
+            | PixelSampleMode::Halton
+            | PixelSampleMode::BlueNoise => self.samples_per_pixel,
             PixelSampleMode::Stratified => {
                 let sqrt_spp = Self::stratified_grid_width(self.samples_per_pixel);
                 sqrt_spp * sqrt_spp
@@ -1691,6 +1917,14 @@ impl RayCamera {
         Ray::with_time(ray_origin, pixel_sample - ray_origin, ray_time)
     }
 
+    /// Restarts `rng` at point `sample_index` of the pixel's low-discrepancy sequence; independent
+    /// random sampling keeps drawing from the pixel's stream.
+    fn begin_pixel_sample(self, rng: &mut SampleRng, x: u32, y: u32, sample_index: u32) {
+        if let Some(sequence) = self.pixel_sample_mode.low_discrepancy_sequence() {
+            *rng = SampleRng::from_sequence(sequence, self.rng_seed, (x, y), sample_index);
+        }
+    }
+
     fn sample_square(rng: &mut SampleRng) -> Vector {
         Vector::new(rng.random_double() - 0.5, rng.random_double() - 0.5, 0.0)
     }
@@ -1726,7 +1960,6 @@ impl RayCamera {
         z ^ (z >> 31)
     }
 
-    #[allow(clippy::too_many_lines)]
     fn ray_color(
         ray: &Ray,
         depth: u32,
@@ -1736,7 +1969,7 @@ impl RayCamera {
         let mut current_ray = *ray;
         let mut attenuation = LinearColor::new(1.0, 1.0, 1.0);
         let mut color = LinearColor::default();
-        let mut allow_emitted = true;
+        let mut light_vertex = None;
         let mut miss_radiance_weight = 1.0;
 
         for bounce_index in 0..depth {
@@ -1749,112 +1982,79 @@ impl RayCamera {
                 return color + component_mul(attenuation, miss);
             };
 
-            if allow_emitted {
-                color += component_mul(attenuation, Self::emitted_at(&current_ray, &record));
+            let emitted = Self::emitted_at(&current_ray, &record);
+            if emitted != LinearColor::default() {
+                let weight = context.light_emission_weight(light_vertex, *current_ray.direction());
+                color += component_mul(attenuation, emitted) * weight;
             }
 
-            let Some(scatter) = record.material.scatter(&current_ray, &record, rng) else {
-                return color;
-            };
-
-            match scatter {
-                ScatterRecord::Specular {
-                    ray,
-                    attenuation: scatter_attenuation,
-                } => {
-                    attenuation = component_mul(attenuation, scatter_attenuation);
-                    current_ray = ray;
-                    allow_emitted = true;
-                    miss_radiance_weight = 1.0;
-                    if !Self::russian_roulette_survives(
-                        bounce_index,
-                        context.russian_roulette_min_depth,
-                        &mut attenuation,
+            if context.sampling_strategy.uses_next_event_estimation()
+                && record.material.bsdf_flags(&record).has_non_delta_lobe()
+            {
+                if let Some(lights) = context.lights {
+                    let direct = Self::estimate_direct_lighting(
+                        &current_ray,
+                        &record,
+                        context.world,
+                        lights,
                         rng,
-                    ) {
-                        return color;
-                    }
+                    );
+                    color += component_mul(attenuation, direct);
                 }
-                ScatterRecord::Scattering {
-                    attenuation: scatter_attenuation,
-                    pdf: material_pdf,
-                } => {
-                    if context.sampling_strategy.uses_next_event_estimation() {
-                        if let Some(lights) = context.lights {
-                            let direct = Self::estimate_direct_lighting(
-                                &current_ray,
-                                &record,
-                                context.world,
-                                lights,
-                                scatter_attenuation,
-                                rng,
-                            );
-                            color += component_mul(attenuation, direct);
-                        }
-                        if let Some(environment) = context.environment {
-                            let direct = Self::estimate_environment_lighting(
-                                &current_ray,
-                                &record,
-                                context.world,
-                                environment,
-                                scatter_attenuation,
-                                rng,
-                            );
-                            color += component_mul(attenuation, direct);
-                        }
-                    }
-
-                    let continuation = context.sampling_strategy.continuation_sample(
+                if let Some(environment) = context.environment {
+                    let direct = Self::estimate_environment_lighting(
                         &current_ray,
                         &record,
-                        material_pdf,
-                        context.lights,
-                        context.environment,
+                        context.world,
+                        environment,
                         rng,
                     );
+                    color += component_mul(attenuation, direct);
+                }
+            }
 
-                    if !continuation.pdf_value.is_finite() || continuation.pdf_value <= f64::EPSILON
-                    {
-                        return color;
-                    }
-
-                    let scattered_ray =
-                        Ray::with_time(record.point, continuation.direction, current_ray.time());
-                    let scattering_pdf =
-                        record
-                            .material
-                            .scattering_pdf(&current_ray, &record, &scattered_ray);
-                    if !scattering_pdf.is_finite() || scattering_pdf <= 0.0 {
-                        return color;
-                    }
+            let Some(continuation) = context.sampling_strategy.continuation_sample(
+                &current_ray,
+                &record,
+                context.lights,
+                context.environment,
+                rng,
+            ) else {
+                return color;
+            };
 
-                    let next_miss_radiance_weight = if continuation.weight_environment_miss {
-                        context.environment.map_or(1.0, |environment| {
-                            Self::power_heuristic(
-                                continuation.pdf_value,
-                                environment.pdf_value(continuation.direction),
-                            )
-                        })
-                    } else {
-                        1.0
-                    };
+            if !continuation.pdf_value.is_finite() || continuation.pdf_value <= f64::EPSILON {
+                return color;
+            }
+            let throughput = continuation.value * (1.0 / continuation.pdf_value);
+            if !throughput.is_finite() || throughput == LinearColor::default() {
+                return color;
+            }
 
-                    attenuation = component_mul(
-                        attenuation,
-                        scatter_attenuation * (scattering_pdf / continuation.pdf_value),
-                    );
-                    current_ray = scattered_ray;
-                    allow_emitted = !continuation.suppress_next_emission;
-                    miss_radiance_weight = next_miss_radiance_weight;
-                    if !Self::russian_roulette_survives(
-                        bounce_index,
-                        context.russian_roulette_min_depth,
-                        &mut attenuation,
-                        rng,
-                    ) {
-                        return color;
-                    }
-                }
+            // Light samples from the last scattering vertex see through index-matched medium
+            // boundaries, so emission found past one keeps that vertex's MIS weights.
+            if !record.material.bsdf_flags(&record).is_index_matched() {
+                miss_radiance_weight = if continuation.weight_environment_miss {
+                    context.environment.map_or(1.0, |environment| {
+                        Self::power_heuristic(
+                            continuation.pdf_value,
+                            environment.pdf_value(continuation.direction),
+                        )
+                    })
+                } else {
+                    1.0
+                };
+                light_vertex = continuation.light_mis_vertex(record.point, current_ray.time());
+            }
+            attenuation = component_mul(attenuation, throughput);
+            current_ray = Ray::with_time(record.point, continuation.direction, current_ray.time());
+            if !Self::russian_roulette_survives(
+                bounce_index,
+                context.russian_roulette_min_depth,
+                &mut attenuation,
+                rng,
+            ) {
+                return color;
             }
         }
 
@@ -1873,7 +2073,7 @@ impl RayCamera {
         let mut current_ray = *ray;
         let mut attenuation = 1.0;
         let mut radiance = 0.0;
-        let mut allow_emitted = true;
+        let mut light_vertex = None;
         let mut miss_radiance_weight = 1.0;
 
         for bounce_index in 0..depth {
@@ -1882,16 +2082,16 @@ impl RayCamera {
                 Interval::new(SHADOW_ACNE_EPSILON, INFINITY),
                 rng,
             ) else {
-                let miss = Self::sample_spectrum(
-                    context.miss_radiance(*current_ray.direction()),
-                    wavelength,
-                ) * miss_radiance_weight;
+                let miss = context.miss_spectral_radiance(*current_ray.direction(), wavelength)
+                    * miss_radiance_weight;
                 return radiance + attenuation * miss;
             };
 
-            if allow_emitted {
-                radiance +=
-                    attenuation * Self::spectral_emitted_at(&current_ray, &record, wavelength);
+            let emitted = Self::spectral_emitted_at(&current_ray, &record, wavelength);
+            if emitted != 0.0 {
+                radiance += attenuation
+                    * emitted
+                    * context.light_emission_weight(light_vertex, *current_ray.direction());
             }
 
             let Some(scatter) =
@@ -1910,8 +2110,10 @@ impl RayCamera {
                     attenuation *=
                         Self::sample_material_spectrum(&record, scatter_attenuation, wavelength);
                     current_ray = ray;
-                    allow_emitted = true;
-                    miss_radiance_weight = 1.0;
+                    if !record.material.bsdf_flags(&record).is_index_matched() {
+                        light_vertex = None;
+                        miss_radiance_weight = 1.0;
+                    }
                     if !Self::russian_roulette_survives_scalar(
                         bounce_index,
                         context.russian_roulette_min_depth,
@@ -1923,9 +2125,11 @@ impl RayCamera {
                 }
                 ScatterRecord::Scattering {
                     attenuation: scatter_attenuation,
-                    pdf: material_pdf,
+                    ..
                 } => {
-                    if context.sampling_strategy.uses_next_event_estimation() {
+                    if context.sampling_strategy.uses_next_event_estimation()
+                        && record.material.bsdf_flags(&record).has_non_delta_lobe()
+                    {
                         if let Some(lights) = context.lights {
                             radiance += attenuation
                                 * Self::estimate_direct_lighting_spectral(
@@ -1952,14 +2156,15 @@ impl RayCamera {
                         }
                     }
 
-                    let continuation = context.sampling_strategy.continuation_sample(
+                    let Some(continuation) = context.sampling_strategy.continuation_sample(
                         &current_ray,
                         &record,
-                        material_pdf,
                         context.lights,
                         context.environment,
                         rng,
-                    );
+                    ) else {
+                        return radiance;
+                    };
 
                     if !continuation.pdf_value.is_finite() || continuation.pdf_value <= f64::EPSILON
                     {
@@ -1991,7 +2196,7 @@ impl RayCamera {
                         Self::sample_material_spectrum(&record, scatter_attenuation, wavelength)
                             * (scattering_pdf / continuation.pdf_value);
                     current_ray = scattered_ray;
-                    allow_emitted = !continuation.suppress_next_emission;
+                    light_vertex = continuation.light_mis_vertex(record.point, current_ray.time());
                     miss_radiance_weight = next_miss_radiance_weight;
                     if !Self::russian_roulette_survives_scalar(
                         bounce_index,
@@ -2021,7 +2226,7 @@ impl RayCamera {
         let mut current_frame = PolarizationFrame::from_direction(*current_ray.direction());
         let mut throughput = StokesVector::unpolarized(1.0);
         let mut radiance = StokesVector::default();
-        let mut allow_emitted = true;
+        let mut light_vertex = None;
         let mut miss_radiance_weight = 1.0;
 
         for bounce_index in 0..depth {
@@ -2030,16 +2235,16 @@ impl RayCamera {
                 Interval::new(SHADOW_ACNE_EPSILON, INFINITY),
                 rng,
             ) else {
-                let miss = Self::sample_spectrum(
-                    context.miss_radiance(*current_ray.direction()),
-                    wavelength,
-                ) * miss_radiance_weight;
+                let miss = context.miss_spectral_radiance(*current_ray.direction(), wavelength)
+                    * miss_radiance_weight;
                 return radiance + throughput * miss;
             };
 
-            if allow_emitted {
-                radiance +=
-                    throughput * Self::spectral_emitted_at(&current_ray, &record, wavelength);
+            let emitted = Self::spectral_emitted_at(&current_ray, &record, wavelength);
+            if emitted != 0.0 {
+                radiance += throughput
+                    * (emitted
+                        * context.light_emission_weight(light_vertex, *current_ray.direction()));
             }
 
             let Some(scatter) =
@@ -2068,8 +2273,10 @@ impl RayCamera {
                         * Self::sample_material_spectrum(&record, scatter_attenuation, wavelength);
                     current_ray = ray;
                     current_frame = outgoing_frame;
-                    allow_emitted = true;
-                    miss_radiance_weight = 1.0;
+                    if !record.material.bsdf_flags(&record).is_index_matched() {
+                        light_vertex = None;
+                        miss_radiance_weight = 1.0;
+                    }
                     if !Self::russian_roulette_survives_stokes(
                         bounce_index,
                         context.russian_roulette_min_depth,
@@ -2081,9 +2288,11 @@ impl RayCamera {
                 }
                 ScatterRecord::Scattering {
                     attenuation: scatter_attenuation,
-                    pdf: material_pdf,
+                    ..
                 } => {
-                    if context.sampling_strategy.uses_next_event_estimation() {
+                    if context.sampling_strategy.uses_next_event_estimation()
+                        && record.material.bsdf_flags(&record).has_non_delta_lobe()
+                    {
                         if let Some(lights) = context.lights {
                             radiance += Self::estimate_direct_lighting_polarized(
                                 &current_ray,
@@ -2112,14 +2321,15 @@ impl RayCamera {
                         }
                     }
 
-                    let continuation = context.sampling_strategy.continuation_sample(
+                    let Some(continuation) = context.sampling_strategy.continuation_sample(
                         &current_ray,
                         &record,
-                        material_pdf,
                         context.lights,
                         context.environment,
                         rng,
-                    );
+                    ) else {
+                        return radiance;
+                    };
 
                     if !continuation.pdf_value.is_finite() || continuation.pdf_value <= f64::EPSILON
                     {
@@ -2163,7 +2373,7 @@ impl RayCamera {
                     throughput = mueller.apply(throughput) * scalar_weight;
                     current_ray = scattered_ray;
                     current_frame = outgoing_frame;
-                    allow_emitted = !continuation.suppress_next_emission;
+                    light_vertex = continuation.light_mis_vertex(record.point, current_ray.time());
                     miss_radiance_weight = next_miss_radiance_weight;
                     if !Self::russian_roulette_survives_stokes(
                         bounce_index,
@@ -2201,6 +2411,31 @@ impl RayCamera {
         hit.material.emitted(ray, hit, hit.u, hit.v, hit.point)
     }
 
+    /// Finds the first surface along a shadow ray and the transmittance of the media before it.
+    ///
+    /// Medium collisions and index-matched medium boundaries are stepped over and replaced by
+    /// [`Hittable::transmittance_with_rng`], so thin smoke dims a light instead of randomly
+    /// blocking it.
+    fn shadow_surface_hit<'w>(
+        world: &'w dyn Hittable,
+        shadow_ray: &Ray,
+        rng: &mut SampleRng,
+    ) -> Option<(HitRecord<'w>, LinearColor)> {
+        let mut t_min = SHADOW_ACNE_EPSILON;
+        loop {
+            let hit = world.hit_with_rng(shadow_ray, Interval::new(t_min, INFINITY), rng)?;
+            if !hit.material.bsdf_flags(&hit).contains(BsdfFlags::MEDIUM) {
+                let transmittance = world.transmittance_with_rng(
+                    shadow_ray,
+                    Interval::new(SHADOW_ACNE_EPSILON, hit.t - SHADOW_ACNE_EPSILON),
+                    rng,
+                );
+                return Some((hit, transmittance));
+            }
+            t_min = hit.t + SHADOW_ACNE_EPSILON;
+        }
+    }
+
     #[cfg(feature = "spectral")]
     fn sample_spectrum(color: LinearColor, wavelength: SampledWavelength) -> f64 {
         Spectrum::from_linear_rgb(color).sample(wavelength)
@@ -2227,7 +2462,6 @@ impl RayCamera {
         hit: &HitRecord<'_>,
         world: &dyn Hittable,
         lights: &dyn Hittable,
-        scatter_attenuation: LinearColor,
         rng: &mut SampleRng,
     ) -> LinearColor {
         let context = PdfContext::new(hit.point, ray_in.time());
@@ -2240,25 +2474,26 @@ impl RayCamera {
         }
 
         let shadow_ray = Ray::with_time(hit.point, direction_to_light, ray_in.time());
-        let scattering_pdf = hit.material.scattering_pdf(ray_in, hit, &shadow_ray);
-        if !scattering_pdf.is_finite() || scattering_pdf <= 0.0 {
+        let bsdf = hit.material.eval_bsdf(ray_in, hit, &shadow_ray);
+        if !bsdf.is_finite() || bsdf == LinearColor::default() {
             return LinearColor::default();
         }
+        let mis_weight = Self::power_heuristic(
+            light_pdf_value,
+            hit.material.bsdf_pdf(ray_in, hit, &shadow_ray),
+        );
 
-        let Some(light_hit) = world.hit_with_rng(
-            &shadow_ray,
-            Interval::new(SHADOW_ACNE_EPSILON, INFINITY),
-            rng,
-        ) else {
+        let Some((light_hit, transmittance)) = Self::shadow_surface_hit(world, &shadow_ray, rng)
+        else {
             return LinearColor::default();
         };
 
-        let emitted = Self::emitted_at(&shadow_ray, &light_hit);
+        let emitted = component_mul(Self::emitted_at(&shadow_ray, &light_hit), transmittance);
         if !emitted.is_finite() {
             return LinearColor::default();
         }
 
-        component_mul(scatter_attenuation, emitted) * (scattering_pdf / light_pdf_value)
+        component_mul(bsdf, emitted) * (mis_weight / light_pdf_value)
     }
 
     fn estimate_environment_lighting(
@@ -2266,7 +2501,6 @@ impl RayCamera {
         hit: &HitRecord<'_>,
         world: &dyn Hittable,
         environment: &EnvironmentLight,
-        scatter_attenuation: LinearColor,
         rng: &mut SampleRng,
     ) -> LinearColor {
         let (direction, environment_pdf) = environment.sample_direction(rng);
@@ -2275,25 +2509,26 @@ impl RayCamera {
         }
 
         let shadow_ray = Ray::with_time(hit.point, direction, ray_in.time());
-        let scattering_pdf = hit.material.scattering_pdf(ray_in, hit, &shadow_ray);
-        if !scattering_pdf.is_finite() || scattering_pdf <= 0.0 {
+        let bsdf = hit.material.eval_bsdf(ray_in, hit, &shadow_ray);
+        if !bsdf.is_finite() || bsdf == LinearColor::default() {
             return LinearColor::default();
         }
-        let mis_weight = Self::power_heuristic(environment_pdf, scattering_pdf);
+        let mis_weight = Self::power_heuristic(
+            environment_pdf,
+            hit.material.bsdf_pdf(ray_in, hit, &shadow_ray),
+        );
 
-        if world
-            .hit_with_rng(
-                &shadow_ray,
-                Interval::new(SHADOW_ACNE_EPSILON, INFINITY),
-                rng,
-            )
-            .is_some()
-        {
+        let transmittance = world.transmittance_with_rng(
+            &shadow_ray,
+            Interval::new(SHADOW_ACNE_EPSILON, INFINITY),
+            rng,
+        );
+        if transmittance == LinearColor::default() {
             return LinearColor::default();
         }
 
-        component_mul(scatter_attenuation, environment.radiance(direction))
-            * (mis_weight * scattering_pdf / environment_pdf)
+        let radiance = component_mul(environment.radiance(direction), transmittance);
+        component_mul(bsdf, radiance) * (mis_weight / environment_pdf)
     }
 
     #[cfg(feature = "spectral")]
@@ -2320,23 +2555,25 @@ impl RayCamera {
         if !scattering_pdf.is_finite() || scattering_pdf <= 0.0 {
             return 0.0;
         }
+        let mis_weight = Self::power_heuristic(
+            light_pdf_value,
+            hit.material.bsdf_pdf(ray_in, hit, &shadow_ray),
+        );
 
-        let Some(light_hit) = world.hit_with_rng(
-            &shadow_ray,
-            Interval::new(SHADOW_ACNE_EPSILON, INFINITY),
-            rng,
-        ) else {
+        let Some((light_hit, transmittance)) = Self::shadow_surface_hit(world, &shadow_ray, rng)
+        else {
             return 0.0;
         };
 
-        let emitted = Self::spectral_emitted_at(&shadow_ray, &light_hit, wavelength);
+        let emitted = Self::spectral_emitted_at(&shadow_ray, &light_hit, wavelength)
+            * Self::sample_spectrum(transmittance, wavelength);
         if !emitted.is_finite() {
             return 0.0;
         }
 
         Self::sample_material_spectrum(hit, scatter_attenuation, wavelength)
             * emitted
-            * (scattering_pdf / light_pdf_value)
+            * (mis_weight * scattering_pdf / light_pdf_value)
     }
 
     #[cfg(feature = "spectral")]
@@ -2359,21 +2596,23 @@ impl RayCamera {
         if !scattering_pdf.is_finite() || scattering_pdf <= 0.0 {
             return 0.0;
         }
-        let mis_weight = Self::power_heuristic(environment_pdf, scattering_pdf);
+        let mis_weight = Self::power_heuristic(
+            environment_pdf,
+            hit.material.bsdf_pdf(ray_in, hit, &shadow_ray),
+        );
 
-        if world
-            .hit_with_rng(
-                &shadow_ray,
-                Interval::new(SHADOW_ACNE_EPSILON, INFINITY),
-                rng,
-            )
-            .is_some()
-        {
+        let transmittance = world.transmittance_with_rng(
+            &shadow_ray,
+            Interval::new(SHADOW_ACNE_EPSILON, INFINITY),
+            rng,
+        );
+        if transmittance == LinearColor::default() {
             return 0.0;
         }
 
         Self::sample_material_spectrum(hit, scatter_attenuation, wavelength)
-            * Self::sample_spectrum(environment.radiance(direction), wavelength)
+            * environment.spectral_radiance(direction, wavelength)
+            * Self::sample_spectrum(transmittance, wavelength)
             * (mis_weight * scattering_pdf / environment_pdf)
     }
 
@@ -2404,16 +2643,18 @@ impl RayCamera {
         if !scattering_pdf.is_finite() || scattering_pdf <= 0.0 {
             return StokesVector::default();
         }
+        let mis_weight = Self::power_heuristic(
+            light_pdf_value,
+            hit.material.bsdf_pdf(ray_in, hit, &shadow_ray),
+        );
 
-        let Some(light_hit) = world.hit_with_rng(
-            &shadow_ray,
-            Interval::new(SHADOW_ACNE_EPSILON, INFINITY),
-            rng,
-        ) else {
+        let Some((light_hit, transmittance)) = Self::shadow_surface_hit(world, &shadow_ray, rng)
+        else {
             return StokesVector::default();
         };
 
-        let emitted = Self::spectral_emitted_at(&shadow_ray, &light_hit, wavelength);
+        let emitted = Self::spectral_emitted_at(&shadow_ray, &light_hit, wavelength)
+            * Self::sample_spectrum(transmittance, wavelength);
         if !emitted.is_finite() {
             return StokesVector::default();
         }
@@ -2428,7 +2669,7 @@ impl RayCamera {
             wavelength,
         );
         let scatter_weight = Self::sample_material_spectrum(hit, scatter_attenuation, wavelength)
-            * (scattering_pdf / light_pdf_value);
+            * (mis_weight * scattering_pdf / light_pdf_value);
         mueller.apply(throughput) * (scatter_weight * emitted)
     }
 
@@ -2455,16 +2696,17 @@ impl RayCamera {
         if !scattering_pdf.is_finite() || scattering_pdf <= 0.0 {
             return StokesVector::default();
         }
-        let mis_weight = Self::power_heuristic(environment_pdf, scattering_pdf);
+        let mis_weight = Self::power_heuristic(
+            environment_pdf,
+            hit.material.bsdf_pdf(ray_in, hit, &shadow_ray),
+        );
 
-        if world
-            .hit_with_rng(
-                &shadow_ray,
-                Interval::new(SHADOW_ACNE_EPSILON, INFINITY),
-                rng,
-            )
-            .is_some()
-        {
+        let transmittance = world.transmittance_with_rng(
+            &shadow_ray,
+            Interval::new(SHADOW_ACNE_EPSILON, INFINITY),
+            rng,
+        );
+        if transmittance == LinearColor::default() {
             return StokesVector::default();
         }
 
@@ -2479,7 +2721,8 @@ impl RayCamera {
         );
         let scatter_weight = Self::sample_material_spectrum(hit, scatter_attenuation, wavelength)
             * (mis_weight * scattering_pdf / environment_pdf);
-        let emitted = Self::sample_spectrum(environment.radiance(direction), wavelength);
+        let emitted = environment.spectral_radiance(direction, wavelength)
+            * Self::sample_spectrum(transmittance, wavelength);
         mueller.apply(throughput) * (scatter_weight * emitted)
     }
 
@@ -2666,26 +2909,53 @@ impl RayCamera {
         environment: Option<&EnvironmentLight>,
         background: RayBackgroundContext<'_>,
     ) -> LinearColor {
-        let mut rng = SampleRng::new(Self::pixel_seed(self.rng_seed, x, y));
-        let mut pixel_color = LinearColor::default();
-        let sample_count = self.effective_samples_per_pixel();
         let pixel_context = PixelRenderContext {
             world,
             lights,
             environment,
             background,
         };
+        self.render_world_linear_pixel_estimate(x, y, pixel_context, None)
+    }
+
+    /// Renders one pixel, optionally recording luminance moments of the accepted samples.
+    fn render_world_linear_pixel_estimate(
+        self,
+        x: u32,
+        y: u32,
+        pixel_context: PixelRenderContext<'_>,
+        mut moments: Option<&mut LuminanceMoments>,
+    ) -> LinearColor {
+        let mut rng = SampleRng::new(Self::pixel_seed(self.rng_seed, x, y));
+        let mut pixel_color = LinearColor::default();
+        let sample_count = self.effective_samples_per_pixel();
+        let mut record = |sample: LinearColor| {
+            if let Some(moments) = moments.as_deref_mut() {
+                moments.add(sample);
+            }
+            sample
+        };
 
         match self.pixel_sample_mode {
-            PixelSampleMode::Random => {
+            PixelSampleMode::Random
+            | PixelSampleMode::Sobol
+            | PixelSampleMode::Halton
+            | PixelSampleMode::BlueNoise => {
                 if let Some(settings) = self.adaptive_sampling {
-                    pixel_color = self.render_world_pixel_adaptive(x, y, pixel_context, settings);
+                    pixel_color = self.render_world_pixel_adaptive(
+                        x,
+                        y,
+                        pixel_context,
+                        settings,
+                        &mut record,
+                    );
                 } else {
                     let mut accepted_samples = 0;
-                    for _ in 0..sample_count {
+                    for sample_index in 0..sample_count {
+                        self.begin_pixel_sample(&mut rng, x, y, sample_index);
                         accepted_samples += u32::from(Self::add_finite_sample(
                             &mut pixel_color,
-                            self.sample_world_color(x, y, pixel_context, &mut rng),
+                            record(self.sample_world_color(x, y, pixel_context, &mut rng)),
                         ));
                     }
                     pixel_color = Self::average_accepted_samples(pixel_color, accepted_samples);
@@ -2701,7 +2971,7 @@ impl RayCamera {
                         );
                         accepted_samples += u32::from(Self::add_finite_sample(
                             &mut pixel_color,
-                            Self::ray_color(
+                            record(Self::ray_color(
                                 &ray,
                                 self.max_depth,
                                 self.ray_color_context_with_background(
@@ -2711,7 +2981,7 @@ impl RayCamera {
                                     pixel_context.background,
                                 ),
                                 &mut rng,
-                            ),
+                            )),
                         ));
                     }
                 }
@@ -2722,6 +2992,80 @@ impl RayCamera {
         pixel_color
     }
 
+    /// Adds `samples` random samples to every pixel of `checkpoint`, continuing each pixel's saved
+    /// random stream.
+    ///
+    /// Checkpointed renders always use independent random pixel samples with RGB transport, so
+    /// stratified, adaptive, and spectral settings do not apply.
+    pub(crate) fn accumulate_checkpoint_samples(
+        self,
+        world: &dyn Hittable,
+        lights: Option<&dyn Hittable>,
+        environment: Option<&EnvironmentLight>,
+        checkpoint: &mut RenderCheckpoint,
+        samples: u32,
+        tile_size: u32,
+    ) {
+        let camera = if environment.is_some() {
+            self.environment_importance_camera()
+        } else {
+            self
+        }
+        .initialize();
+        let image_width =
+            usize::try_from(camera.image_width).expect("image width should fit usize");
+        let pixel_context = PixelRenderContext {
+            world,
+            lights,
+            environment,
+            background: environment.map_or(
+                RayBackgroundContext::BuiltIn(camera.background),
+                |environment| RayBackgroundContext::Borrowed(environment),
+            ),
+        };
+        let previous = &checkpoint.pixels;
+        let pixels = Self::render_values_tiled(
+            camera.image_width,
+            camera.image_height,
+            tile_size,
+            |x, y| {
+                let index = usize::try_from(y).expect("pixel y should fit usize") * image_width
+                    + usize::try_from(x).expect("pixel x should fit usize");
+                let mut pixel = previous[index];
+                let mut rng = SampleRng::new(pixel.rng_state);
+                for _ in 0..samples {
+                    pixel.accepted_samples += u32::from(Self::add_finite_sample(
+                        &mut pixel.sum,
+                        camera.sample_world_color(x, y, pixel_context, &mut rng),
+                    ));
+                }
+                pixel.rng_state = rng.state();
+                pixel
+            },
+        );
+        checkpoint.pixels = pixels;
+        checkpoint.add_samples_per_pixel(samples);
+    }
+
+    /// Starts an empty checkpoint whose pixel streams match an ordinary render with this camera.
+    ///
+    /// `lights` and `environment` record which estimator the render uses, so a resume can reject
+    /// a checkpoint from a different one.
+    pub(crate) fn new_render_checkpoint(self, lights: bool, environment: bool) -> RenderCheckpoint {
+        let camera = self.initialize();
+        RenderCheckpoint::new(
+            camera.image_width,
+            camera.image_height,
+            camera.rng_seed,
+            CheckpointEstimator {
+                max_depth: camera.max_depth,
+                lights,
+                environment,
+            },
+            |x, y| Self::pixel_seed(camera.rng_seed, x, y),
+        )
+    }
+
     #[cfg(feature = "spectral")]
     fn render_world_spectral_pixel(
         self,
@@ -2767,9 +3111,13 @@ impl RayCamera {
         let sample_count = self.effective_samples_per_pixel();
 
         match self.pixel_sample_mode {
-            PixelSampleMode::Random => {
+            PixelSampleMode::Random
+            | PixelSampleMode::Sobol
+            | PixelSampleMode::Halton
+            | PixelSampleMode::BlueNoise => {
                 let mut accepted_samples = 0;
-                for _ in 0..sample_count {
+                for sample_index in 0..sample_count {
+                    self.begin_pixel_sample(&mut rng, x, y, sample_index);
                     let ray = self.ray_for_pixel_sample(x, y, &mut rng);
                     let wavelength = SampledWavelength::sample_visible(&mut rng);
                     let radiance = Self::ray_sampled_wavelength_radiance(
@@ -2844,9 +3192,13 @@ impl RayCamera {
         let sample_count = self.effective_samples_per_pixel();
 
         match self.pixel_sample_mode {
-            PixelSampleMode::Random => {
+            PixelSampleMode::Random
+            | PixelSampleMode::Sobol
+            | PixelSampleMode::Halton
+            | PixelSampleMode::BlueNoise => {
                 let mut accepted_samples = 0;
-                for _ in 0..sample_count {
+                for sample_index in 0..sample_count {
+                    self.begin_pixel_sample(&mut rng, x, y, sample_index);
                     let ray = self.ray_for_pixel_sample(x, y, &mut rng);
                     let wavelength = SampledWavelength::sample_visible(&mut rng);
                     let stokes = Self::ray_sampled_wavelength_stokes(
@@ -2940,14 +3292,16 @@ impl RayCamera {
         y: u32,
         context: PixelRenderContext<'_>,
         settings: AdaptiveSampling,
+        mut record: impl FnMut(LinearColor) -> LinearColor,
     ) -> LinearColor {
         let mut rng = SampleRng::new(Self::pixel_seed(self.rng_seed, x, y));
         let mut mean = LinearColor::default();
         let mut m2 = LinearColor::default();
         let mut accepted_samples = 0;
 
-        for _ in 0..settings.max_samples {
-            let sample = self.sample_world_color(x, y, context, &mut rng);
+        for sample_index in 0..settings.max_samples {
+            self.begin_pixel_sample(&mut rng, x, y, sample_index);
+            let sample = record(self.sample_world_color(x, y, context, &mut rng));
             if !sample.is_finite() {
                 continue;
             }
@@ -3027,7 +3381,10 @@ impl RayCamera {
 
     fn active_stratified_grid_width(self) -> u32 {
         match self.pixel_sample_mode {
-            PixelSampleMode::Random => 1,
+            PixelSampleMode::Random
+            | PixelSampleMode::Sobol
+            | PixelSampleMode::Halton
+            | PixelSampleMode::BlueNoise => 1,
             PixelSampleMode::Stratified => Self::stratified_grid_width(self.samples_per_pixel),
             PixelSampleMode::StratifiedGrid { grid_width } => grid_width.max(1),
         }
@@ -3039,8 +3396,12 @@ impl RayCamera {
         let sample_count = self.effective_samples_per_pixel();
 
         match self.pixel_sample_mode {
-            PixelSampleMode::Random => {
-                for _ in 0..sample_count {
+            PixelSampleMode::Random
+            | PixelSampleMode::Sobol
+            | PixelSampleMode::Halton
+            | PixelSampleMode::BlueNoise => {
+                for sample_index in 0..sample_count {
+                    self.begin_pixel_sample(&mut rng, x, y, sample_index);
                     let ray = self.ray_for_pixel_sample(x, y, &mut rng);
                     pixel_color += normal_scene_color(&ray, world);
                 }
@@ -3073,8 +3434,12 @@ impl RayCamera {
         let sample_count = self.effective_samples_per_pixel();
 
         match self.pixel_sample_mode {
-            PixelSampleMode::Random => {
-                for _ in 0..sample_count {
+            PixelSampleMode::Random
+            | PixelSampleMode::Sobol
+            | PixelSampleMode::Halton
+            | PixelSampleMode::BlueNoise => {
+                for sample_index in 0..sample_count {
+                    self.begin_pixel_sample(&mut rng, x, y, sample_index);
                     let ray = self.ray_for_pixel_sample(x, y, &mut rng);
                     pixel_color += Self::denoising_aov_sample(&ray, world, kind, &mut rng);
                 }
@@ -4006,7 +4371,8 @@ impl RayCamera {
     /// Renders `world` with explicit next-event light connections.
     ///
     /// This forces next-event estimation for callers that built a camera with
-    /// [`DirectLightingMode::CurrentPathContinuation`]. It is not a bidirectional path tracer.
+    /// [`DirectLightingMode::CurrentPathContinuation`]. Use [`Self::render_world_bidirectional`]
+    /// to also trace light subpaths.
     pub fn render_world_with_light_connections(
         self,
         world: &dyn Hittable,
@@ -4027,6 +4393,135 @@ impl RayCamera {
             .render_world_with_lights_tiled(world, lights, tile_size)
     }
 
+    /// Renders `world` with bidirectional path tracing from the camera and from `lights`.
+    ///
+    /// Every light target must report emitter surface samples through
+    /// [`Hittable::sample_surface`]; targets that cannot are only reached from the camera.
+    /// Stratified and adaptive pixel sampling fall back to independent random samples.
+    pub fn render_world_bidirectional(self, world: &dyn Hittable, lights: &dyn Hittable) -> Canvas {
+        self.bidirectional_render_camera()
+            .render_world_with_lights(world, lights)
+    }
+
+    /// Renders `world` with bidirectional path tracing and explicit tile size.
+    pub fn render_world_bidirectional_tiled(
+        self,
+        world: &dyn Hittable,
+        lights: &dyn Hittable,
+        tile_size: u32,
+    ) -> Canvas {
+        self.bidirectional_render_camera()
+            .render_world_with_lights_tiled(world, lights, tile_size)
+    }
+
+    /// Renders `world` with bidirectional path tracing to linear floating-point HDR samples.
+    #[must_use]
+    pub fn render_world_bidirectional_hdr_image(
+        self,
+        world: &dyn Hittable,
+        lights: &dyn Hittable,
+    ) -> HdrImage {
+        self.bidirectional_render_camera()
+            .render_world_with_lights_hdr_image(world, lights)
+    }
+
+    /// Renders `world` with stochastic progressive photon mapping from `lights`.
+    ///
+    /// Each sample per pixel is one pass: photons are emitted from surface points sampled on
+    /// `lights`, then one camera ray per pixel follows delta lobes to a diffuse or glossy surface,
+    /// takes a next-event direct-lighting sample there, and gathers photons inside a radius that
+    /// shrinks every pass. Caustics cast through glass converge far faster than with camera paths.
+    /// Light targets must support [`Hittable::sample_surface`].
+    pub fn render_world_photon_mapped(
+        self,
+        world: &dyn Hittable,
+        lights: &dyn Hittable,
+        options: PhotonMappingOptions,
+    ) -> Canvas {
+        self.render_world_photon_mapped_hdr_image(world, lights, options)
+            .to_canvas()
+    }
+
+    /// Renders `world` with stochastic progressive photon mapping to linear HDR samples.
+    #[must_use]
+    pub fn render_world_photon_mapped_hdr_image(
+        self,
+        world: &dyn Hittable,
+        lights: &dyn Hittable,
+        options: PhotonMappingOptions,
+    ) -> HdrImage {
+        self.render_world_photon_mapped_hdr_image_tiled(
+            world,
+            lights,
+            options,
+            DEFAULT_RENDER_TILE_SIZE,
+        )
+    }
+
+    /// Renders `world` with stochastic progressive photon mapping and explicit tile size.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the image size does not fit in `usize`.
+    #[must_use]
+    pub fn render_world_photon_mapped_hdr_image_tiled(
+        self,
+        world: &dyn Hittable,
+        lights: &dyn Hittable,
+        options: PhotonMappingOptions,
+        tile_size: u32,
+    ) -> HdrImage {
+        let camera = self.initialize();
+        let integrator = PhotonMapIntegrator {
+            world,
+            lights,
+            background: camera.background,
+            max_depth: camera.max_depth,
+            shutter: (camera.shutter_start, camera.shutter_end),
+            alpha: options.alpha_value(),
+        };
+        let image_width =
+            usize::try_from(camera.image_width).expect("image width should fit usize");
+        let passes = camera.effective_samples_per_pixel().max(1);
+        let mut pixels = vec![
+            PhotonPixel::new(options.initial_radius_for(world));
+            Canvas::pixel_count(camera.image_width, camera.image_height)
+        ];
+
+        for pass in 0..passes {
+            let pass_seed = Self::pixel_seed(camera.rng_seed, pass, u32::MAX);
+            let photons = integrator.trace_photons(options.photons_per_pass_count(), |chunk| {
+                Self::pixel_seed(pass_seed, chunk, u32::MAX)
+            });
+            let max_radius = pixels.iter().map(|pixel| pixel.radius).fold(0.0, f64::max);
+            let grid = PhotonGrid::new(photons, max_radius);
+            let previous = &pixels;
+            pixels = Self::render_values_tiled(
+                camera.image_width,
+                camera.image_height,
+                tile_size,
+                |x, y| {
+                    let index = usize::try_from(y).expect("pixel y should fit usize") * image_width
+                        + usize::try_from(x).expect("pixel x should fit usize");
+                    let mut rng = SampleRng::new(Self::pixel_seed(pass_seed, x, y));
+                    camera.begin_pixel_sample(&mut rng, x, y, pass);
+                    let ray = camera.ray_for_pixel_sample(x, y, &mut rng);
+                    integrator.update_pixel(previous[index], &ray, &grid, &mut rng)
+                },
+            );
+        }
+
+        let emitted_photons = f64::from(passes) * f64::from(options.photons_per_pass_count());
+        HdrImage::from_pixels(
+            camera.image_width,
+            camera.image_height,
+            pixels
+                .into_iter()
+                .map(|pixel| pixel.radiance(passes, emitted_photons))
+                .collect(),
+        )
+    }
+
     /// Renders a lit hittable world with the feature-gated sampled-wavelength prototype.
     #[cfg(feature = "spectral")]
     pub fn render_world_with_lights_spectral(
@@ -4168,6 +4663,10 @@ impl RayCamera {
         self.with_direct_lighting_mode(DirectLightingMode::NextEventEstimation)
     }
 
+    fn bidirectional_render_camera(self) -> Self {
+        self.with_sampling_strategy(SamplingStrategy::bidirectional())
+    }
+
     fn environment_importance_camera(self) -> Self {
         self.with_direct_lighting_mode(DirectLightingMode::NextEventEstimation)
     }
@@ -4191,12 +4690,24 @@ impl RayCamera {
         tile_size: u32,
     ) -> DenoisingAovs {
         let camera = self.initialize();
-        let beauty_linear = Self::render_values_tiled(
+        let beauty: Vec<(LinearColor, f64)> = Self::render_values_tiled(
             camera.image_width,
             camera.image_height,
             tile_size,
-            |x, y| camera.render_world_linear_pixel(x, y, world, lights),
+            |x, y| {
+                let mut moments = LuminanceMoments::default();
+                let context = PixelRenderContext {
+                    world,
+                    lights,
+                    environment: None,
+                    background: RayBackgroundContext::BuiltIn(camera.background),
+                };
+                let color =
+                    camera.render_world_linear_pixel_estimate(x, y, context, Some(&mut moments));
+                (color, moments.variance_of_mean())
+            },
         );
+        let (beauty_linear, variance): (Vec<_>, Vec<_>) = beauty.into_iter().unzip();
         let albedo_linear = Self::render_values_tiled(
             camera.image_width,
             camera.image_height,
@@ -4241,6 +4752,7 @@ impl RayCamera {
             albedo_linear,
             normal_linear,
         )
+        .with_variance(variance)
     }
 
     fn render_world_with_optional_lights_hdr_image_tiled(
@@ -4259,6 +4771,12 @@ impl RayCamera {
         }
 
         let camera = self.initialize();
+        if camera.sampling_strategy.uses_bidirectional()
+            && let Some(lights) = lights
+        {
+            return camera.render_bidirectional_hdr_image(world, lights, tile_size);
+        }
+
         let pixels = Self::render_values_tiled(
             camera.image_width,
             camera.image_height,
@@ -4268,6 +4786,99 @@ impl RayCamera {
         HdrImage::from_pixels(camera.image_width, camera.image_height, pixels)
     }
 
+    /// Renders an initialized camera with bidirectional path tracing.
+    ///
+    /// Light-tracing splats can land in any pixel, so tiles render in parallel batches and their
+    /// splats are accumulated serially in tile order, keeping the image deterministic for a seed.
+    fn render_bidirectional_hdr_image(
+        self,
+        world: &dyn Hittable,
+        lights: &dyn Hittable,
+        tile_size: u32,
+    ) -> HdrImage {
+        let integrator = BidirectionalIntegrator {
+            camera: self.bidirectional_camera(),
+            world,
+            lights,
+            background: self.background,
+            max_depth: self.max_depth,
+        };
+        let pixel_count = Canvas::pixel_count(self.image_width, self.image_height);
+        let image_width = usize::try_from(self.image_width).expect("image width should fit usize");
+        let mut pixels = vec![LinearColor::default(); pixel_count];
+        let mut splat_sums = vec![LinearColor::default(); pixel_count];
+        let mut add_tile =
+            |(rendered_tile, splats): (RenderedValueTile<LinearColor>, Vec<FilmSplat>)| {
+                Self::copy_tile_values(&mut pixels, image_width, &rendered_tile);
+                for splat in splats {
+                    splat_sums[splat.index] += splat.color;
+                }
+            };
+        let tiles = Self::render_tiles(self.image_width, self.image_height, tile_size);
+
+        #[cfg(feature = "rayon")]
+        for batch in tiles.chunks(rayon::current_num_threads().max(1)) {
+            let rendered_tiles: Vec<_> = batch
+                .par_iter()
+                .map(|&tile| self.render_bidirectional_tile(tile, &integrator))
+                .collect();
+            rendered_tiles.into_iter().for_each(&mut add_tile);
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        for tile in tiles {
+            add_tile(self.render_bidirectional_tile(tile, &integrator));
+        }
+
+        let splat_scale = 1.0 / f64::from(self.effective_samples_per_pixel().max(1));
+        for (pixel, splat_sum) in pixels.iter_mut().zip(splat_sums) {
+            *pixel += splat_sum * splat_scale;
+        }
+        HdrImage::from_pixels(self.image_width, self.image_height, pixels)
+    }
+
+    fn render_bidirectional_tile(
+        self,
+        tile: RenderTile,
+        integrator: &BidirectionalIntegrator<'_>,
+    ) -> (RenderedValueTile<LinearColor>, Vec<FilmSplat>) {
+        let mut splats = Vec::new();
+        let mut values = Vec::with_capacity(tile.pixel_count());
+        for y in tile.y..tile.y_end() {
+            for x in tile.x..tile.x_end() {
+                let mut rng = SampleRng::new(Self::pixel_seed(self.rng_seed, x, y));
+                let mut pixel_color = LinearColor::default();
+                let mut accepted_samples = 0;
+                for sample_index in 0..self.effective_samples_per_pixel() {
+                    self.begin_pixel_sample(&mut rng, x, y, sample_index);
+                    let ray = self.ray_for_pixel_sample(x, y, &mut rng);
+                    accepted_samples += u32::from(Self::add_finite_sample(
+                        &mut pixel_color,
+                        integrator.sample(&ray, &mut rng, &mut splats),
+                    ));
+                }
+                values.push(Self::average_accepted_samples(
+                    pixel_color,
+                    accepted_samples,
+                ));
+            }
+        }
+        (RenderedValueTile { tile, values }, splats)
+    }
+
+    fn bidirectional_camera(self) -> BidirectionalCamera {
+        BidirectionalCamera {
+            center: self.camera_center,
+            pixel00_loc: self.pixel00_loc,
+            pixel_delta_u: self.pixel_delta_u,
+            pixel_delta_v: self.pixel_delta_v,
+            defocus_disk_u: self.defocus_disk_u,
+            defocus_disk_v: self.defocus_disk_v,
+            image_width: self.image_width,
+            image_height: self.image_height,
+        }
+    }
+
     fn render_world_with_optional_lights_and_environment_hdr_image_tiled(
         self,
         world: &dyn Hittable,
@@ -4702,7 +5313,7 @@ impl Canvas {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::graphics::raytracing::{HitRecord, Lambertian, Material, SpherePdf, SurfaceHit};
+    use crate::graphics::raytracing::{HitRecord, Lambertian, Material, SurfaceHit};
     use std::sync::atomic::{AtomicU32, Ordering};
 
     fn assert_close(actual: f64, expected: f64) {
@@ -5044,6 +5655,91 @@ mod tests {
         }
     }
 
+    fn sequence_test_world() -> crate::graphics::raytracing::HittableList {
+        use crate::graphics::raytracing::{HittableList, Lambertian, Sphere};
+
+        let mut world = HittableList::new();
+        world.add(Sphere::with_material(
+            Point::new(0.0, -100.5, -1.0),
+            100.0,
+            Lambertian::new(LinearColor::new(0.5, 0.5, 0.5)),
+        ));
+        world.add(Sphere::with_material(
+            Point::new(0.0, 0.0, -1.0),
+            0.5,
+            Lambertian::new(LinearColor::new(0.7, 0.3, 0.3)),
+        ));
+        world
+    }
+
+    #[test]
+    fn ray_camera_sequence_sample_modes_are_reproducible_across_tiles() {
+        let world = sequence_test_world();
+        for mode in [
+            PixelSampleMode::Sobol,
+            PixelSampleMode::Halton,
+            PixelSampleMode::BlueNoise,
+        ] {
+            let camera = RayCamera::new(6, 1.0)
+                .with_samples_per_pixel(5)
+                .with_pixel_sample_mode(mode)
+                .with_max_depth(4)
+                .with_background(LinearColor::new(1.0, 1.0, 1.0))
+                .with_rng_seed(3);
+
+            let first = camera.render_world_hdr_image_tiled(&world, 2);
+            let second = camera.render_world_hdr_image_tiled(&world, 5);
+            let random = camera
+                .with_pixel_sample_mode(PixelSampleMode::Random)
+                .render_world_hdr_image_tiled(&world, 2);
+
+            assert_eq!(camera.effective_samples_per_pixel(), 5);
+            assert_eq!(first.pixels(), second.pixels(), "{mode:?}");
+            assert_ne!(first.pixels(), random.pixels(), "{mode:?}");
+        }
+    }
+
+    #[test]
+    fn ray_camera_sequence_sample_modes_reduce_error_against_random_sampling() {
+        let world = sequence_test_world();
+        let camera = RayCamera::new(4, 1.0)
+            .with_vertical_fov(60.0)
+            .with_max_depth(3)
+            .with_background(LinearColor::new(1.0, 1.0, 1.0));
+        let reference = camera
+            .with_samples_per_pixel(8192)
+            .with_pixel_sample_mode(PixelSampleMode::Sobol)
+            .with_rng_seed(1)
+            .render_world_hdr_image(&world);
+        let squared_error = |mode: PixelSampleMode| {
+            (0..8_u64)
+                .map(|seed| {
+                    let image = camera
+                        .with_samples_per_pixel(32)
+                        .with_pixel_sample_mode(mode)
+                        .with_rng_seed(100 + seed)
+                        .render_world_hdr_image(&world);
+                    image
+                        .pixels()
+                        .iter()
+                        .zip(reference.pixels())
+                        .map(|(pixel, expected)| (luminance(*pixel) - luminance(*expected)).powi(2))
+                        .sum::<f64>()
+                })
+                .sum::<f64>()
+        };
+
+        let random = squared_error(PixelSampleMode::Random);
+        for mode in [
+            PixelSampleMode::Sobol,
+            PixelSampleMode::Halton,
+            PixelSampleMode::BlueNoise,
+        ] {
+            let error = squared_error(mode);
+            assert!(error < 0.7 * random, "{mode:?}: {error} vs {random}");
+        }
+    }
+
     #[test]
     fn russian_roulette_skips_until_configured_depth() {
         let mut rng = SampleRng::new(41);
@@ -5131,6 +5827,442 @@ mod tests {
         assert!(canvas.pixels().iter().any(|pixel| *pixel != Rgb::BLACK));
     }
 
+    #[test]
+    fn ray_camera_physical_sky_lights_ground_without_fireflies() {
+        use crate::graphics::raytracing::{
+            EnvironmentLight, HittableList, Lambertian, PhysicalSky, Quad,
+        };
+
+        let sky = PhysicalSky::new(50.0, 30.0).with_ground_albedo(LinearColor::new(1.0, 1.0, 1.0));
+        let environment = EnvironmentLight::from_physical_sky(sky);
+        let mut world = HittableList::new();
+        world.add(Quad::with_material(
+            Point::new(-100.0, 0.0, -100.0),
+            Vector::new(0.0, 0.0, 200.0),
+            Vector::new(200.0, 0.0, 0.0),
+            Lambertian::new(LinearColor::new(0.5, 0.5, 0.5)),
+        ));
+        let camera = RayCamera::new(4, 1.0)
+            .with_look_at(Point::new(0.0, 1.0, 1.0), Point::new(0.0, 0.0, 0.0))
+            .with_samples_per_pixel(256)
+            .with_max_depth(2)
+            .with_rng_seed(9);
+
+        let image = camera.render_world_with_environment_hdr_image(&world, &environment);
+
+        // A white ground reflects E / pi, so the half-albedo plane should see half of that.
+        let expected = luminance(sky.radiance(Vector::new(0.0, -1.0, 0.0))) * 0.5;
+        let values = image
+            .pixels()
+            .iter()
+            .map(|pixel| luminance(*pixel))
+            .collect::<Vec<_>>();
+        let mean = values.iter().sum::<f64>() / 16.0;
+        let brightest = values.iter().copied().fold(0.0, f64::max);
+
+        assert!(
+            (mean - expected).abs() < 0.08 * expected,
+            "{mean} vs {expected}"
+        );
+        assert!(brightest < 1.5 * expected, "{brightest} vs {expected}");
+    }
+
+    #[test]
+    fn ray_camera_next_event_estimation_mis_matches_path_continuation_on_glossy_floor() {
+        use crate::graphics::raytracing::{DiffuseLight, GgxMicrofacet, HittableList, Quad};
+
+        let light_corner = Point::new(-0.25, 1.0, -0.75);
+        let light_u = Vector::new(0.5, 0.0, 0.0);
+        let light_v = Vector::new(0.0, 0.0, 0.5);
+        let mut world = HittableList::new();
+        world.add(Quad::with_material(
+            Point::new(-5.0, 0.0, -5.0),
+            Vector::new(0.0, 0.0, 10.0),
+            Vector::new(10.0, 0.0, 0.0),
+            GgxMicrofacet::new(LinearColor::new(0.8, 0.8, 0.8), 0.3),
+        ));
+        world.add(Quad::with_material(
+            light_corner,
+            light_u,
+            light_v,
+            DiffuseLight::new(LinearColor::new(8.0, 8.0, 8.0)),
+        ));
+        let mut lights = HittableList::new();
+        lights.add(Quad::new(light_corner, light_u, light_v));
+        let camera = RayCamera::new(4, 1.0)
+            .with_look_at(Point::new(0.0, 0.5, 1.0), Point::new(0.0, 0.0, -0.5))
+            .with_samples_per_pixel(1024)
+            .with_max_depth(3)
+            .with_background(LinearColor::default())
+            .with_rng_seed(11);
+        let mean = |mode| {
+            camera
+                .with_direct_lighting_mode(mode)
+                .render_world_with_lights_hdr_image(&world, &lights)
+                .pixels()
+                .iter()
+                .map(|pixel| luminance(*pixel))
+                .sum::<f64>()
+                / 16.0
+        };
+
+        let continuation = mean(DirectLightingMode::CurrentPathContinuation);
+        let next_event = mean(DirectLightingMode::NextEventEstimation);
+
+        assert!(continuation > 0.01, "{continuation}");
+        assert!(
+            (next_event - continuation).abs() < 0.05 * continuation,
+            "{next_event} vs {continuation}"
+        );
+    }
+
+    fn bidirectional_test_room() -> (
+        crate::graphics::raytracing::HittableList,
+        crate::graphics::raytracing::HittableList,
+    ) {
+        use crate::graphics::raytracing::{DiffuseLight, HittableList, Lambertian, Quad};
+
+        let light_corner = Point::new(-0.25, 1.0, -0.75);
+        let light_u = Vector::new(0.5, 0.0, 0.0);
+        let light_v = Vector::new(0.0, 0.0, 0.5);
+        let mut world = HittableList::new();
+        world.add(Quad::with_material(
+            Point::new(-5.0, 0.0, -5.0),
+            Vector::new(0.0, 0.0, 10.0),
+            Vector::new(10.0, 0.0, 0.0),
+            Lambertian::new(LinearColor::new(0.7, 0.7, 0.7)),
+        ));
+        world.add(Quad::with_material(
+            Point::new(-5.0, 0.0, -1.5),
+            Vector::new(10.0, 0.0, 0.0),
+            Vector::new(0.0, 3.0, 0.0),
+            Lambertian::new(LinearColor::new(0.5, 0.6, 0.4)),
+        ));
+        world.add(Quad::with_material(
+            light_corner,
+            light_u,
+            light_v,
+            DiffuseLight::new(LinearColor::new(8.0, 8.0, 8.0)),
+        ));
+        let mut lights = HittableList::new();
+        lights.add(Quad::new(light_corner, light_u, light_v));
+        (world, lights)
+    }
+
+    #[test]
+    fn ray_camera_bidirectional_matches_next_event_estimation_on_diffuse_room() {
+        let (world, lights) = bidirectional_test_room();
+        let camera = RayCamera::new(4, 1.0)
+            .with_look_at(Point::new(0.0, 0.5, 1.0), Point::new(0.0, 0.0, -0.5))
+            .with_vertical_fov(40.0)
+            .with_samples_per_pixel(1024)
+            .with_max_depth(4)
+            .with_background(LinearColor::default())
+            .with_rng_seed(5);
+        let mean = |image: HdrImage| {
+            image
+                .pixels()
+                .iter()
+                .map(|pixel| luminance(*pixel))
+                .sum::<f64>()
+                / 16.0
+        };
+
+        let next_event = mean(
+            camera
+                .with_direct_lighting_mode(DirectLightingMode::NextEventEstimation)
+                .render_world_with_lights_hdr_image(&world, &lights),
+        );
+        let bidirectional = mean(camera.render_world_bidirectional_hdr_image(&world, &lights));
+
+        assert!(next_event > 0.01, "{next_event}");
+        assert!(
+            (bidirectional - next_event).abs() < 0.05 * next_event,
+            "{bidirectional} vs {next_event}"
+        );
+    }
+
+    #[test]
+    fn ray_camera_bidirectional_matches_next_event_estimation_through_glass() {
+        use crate::graphics::raytracing::{Dielectric, Sphere};
+
+        let (mut world, lights) = bidirectional_test_room();
+        world.add(Sphere::with_material(
+            Point::new(0.0, 0.35, -0.5),
+            0.3,
+            Dielectric::new(crate::graphics::lighting::RefractiveIndex::GLASS),
+        ));
+        let camera = RayCamera::new(4, 1.0)
+            .with_look_at(Point::new(0.0, 0.8, 1.0), Point::new(0.0, 0.0, -0.5))
+            .with_vertical_fov(40.0)
+            .with_samples_per_pixel(2048)
+            .with_max_depth(6)
+            .with_background(LinearColor::default())
+            .with_rng_seed(17);
+        let mean = |image: HdrImage| {
+            image
+                .pixels()
+                .iter()
+                .map(|pixel| luminance(*pixel))
+                .sum::<f64>()
+                / 16.0
+        };
+
+        let next_event = mean(
+            camera
+                .with_direct_lighting_mode(DirectLightingMode::NextEventEstimation)
+                .render_world_with_lights_hdr_image(&world, &lights),
+        );
+        let bidirectional = mean(camera.render_world_bidirectional_hdr_image(&world, &lights));
+
+        assert!(
+            (bidirectional - next_event).abs() < 0.08 * next_event,
+            "{bidirectional} vs {next_event}"
+        );
+    }
+
+    #[test]
+    fn ray_camera_light_samples_see_through_index_matched_media_without_double_counting() {
+        use crate::graphics::raytracing::{ChromaticMedium, MediumCoefficients, Sphere};
+
+        let (mut world, lights) = bidirectional_test_room();
+        world.add(ChromaticMedium::new(
+            Sphere::new(Point::new(0.0, 0.5, -0.5), 0.4),
+            MediumCoefficients::new(
+                LinearColor::new(0.2, 1.0, 3.0),
+                LinearColor::new(0.5, 0.5, 0.5),
+            ),
+        ));
+        let mut rng = SampleRng::new(4);
+        let shadow_ray = Ray::new(Point::new(0.0, 0.0, -0.5), Vector::new(0.0, 1.0, 0.0));
+        let (light_hit, transmittance) =
+            RayCamera::shadow_surface_hit(&world, &shadow_ray, &mut rng)
+                .expect("shadow ray should reach the light");
+        assert!((light_hit.t - 1.0).abs() < 1e-9, "{}", light_hit.t);
+        assert!(transmittance.blue > 0.0 && transmittance.blue < transmittance.red);
+
+        let camera = RayCamera::new(4, 1.0)
+            .with_look_at(Point::new(0.0, 0.5, 1.0), Point::new(0.0, 0.0, -0.5))
+            .with_vertical_fov(40.0)
+            .with_samples_per_pixel(2048)
+            .with_max_depth(8)
+            .with_background(LinearColor::default())
+            .with_rng_seed(29);
+        let mean = |image: HdrImage| {
+            image
+                .pixels()
+                .iter()
+                .map(|pixel| luminance(*pixel))
+                .sum::<f64>()
+                / 16.0
+        };
+        let render = |mode| {
+            mean(
+                camera
+                    .with_direct_lighting_mode(mode)
+                    .render_world_with_lights_hdr_image(&world, &lights),
+            )
+        };
+
+        let next_event = render(DirectLightingMode::NextEventEstimation);
+        let continuation = render(DirectLightingMode::CurrentPathContinuation);
+        let bidirectional = mean(camera.render_world_bidirectional_hdr_image(&world, &lights));
+
+        assert!(next_event > 0.01, "{next_event}");
+        assert!(
+            (continuation - next_event).abs() < 0.06 * next_event,
+            "{continuation} vs {next_event}"
+        );
+        assert!(
+            (bidirectional - next_event).abs() < 0.06 * next_event,
+            "{bidirectional} vs {next_event}"
+        );
+    }
+
+    #[test]
+    fn ray_camera_bidirectional_render_is_deterministic_for_a_seed() {
+        let (world, lights) = bidirectional_test_room();
+        let camera = RayCamera::new(6, 1.0)
+            .with_look_at(Point::new(0.0, 0.5, 1.0), Point::new(0.0, 0.3, -0.5))
+            .with_samples_per_pixel(4)
+            .with_max_depth(3)
+            .with_rng_seed(3);
+
+        let first = camera.render_world_bidirectional_tiled(&world, &lights, 2);
+        let second = camera.render_world_bidirectional_tiled(&world, &lights, 2);
+
+        assert_eq!(first.pixels(), second.pixels());
+    }
+
+    #[test]
+    fn ray_camera_photon_mapping_matches_next_event_estimation_on_diffuse_room() {
+        let (world, lights) = bidirectional_test_room();
+        let camera = RayCamera::new(4, 1.0)
+            .with_look_at(Point::new(0.0, 0.5, 1.0), Point::new(0.0, 0.0, -0.5))
+            .with_vertical_fov(40.0)
+            .with_samples_per_pixel(256)
+            .with_max_depth(4)
+            .with_background(LinearColor::default())
+            .with_rng_seed(9);
+        let mean = |image: HdrImage| {
+            image
+                .pixels()
+                .iter()
+                .map(|pixel| luminance(*pixel))
+                .sum::<f64>()
+                / 16.0
+        };
+
+        let next_event = mean(
+            camera
+                .with_samples_per_pixel(1024)
+                .with_direct_lighting_mode(DirectLightingMode::NextEventEstimation)
+                .render_world_with_lights_hdr_image(&world, &lights),
+        );
+        let photon_mapped = mean(
+            camera.render_world_photon_mapped_hdr_image(
+                &world,
+                &lights,
+                PhotonMappingOptions::new()
+                    .photons_per_pass(2000)
+                    .initial_radius(0.2),
+            ),
+        );
+
+        assert!(next_event > 0.01, "{next_event}");
+        assert!(
+            (photon_mapped - next_event).abs() < 0.08 * next_event,
+            "{photon_mapped} vs {next_event}"
+        );
+    }
+
+    #[test]
+    fn ray_camera_photon_mapping_matches_bidirectional_under_glass() {
+        use crate::graphics::raytracing::{Dielectric, Sphere};
+
+        let (mut world, lights) = bidirectional_test_room();
+        world.add(Sphere::with_material(
+            Point::new(0.0, 0.45, -0.5),
+            0.3,
+            Dielectric::new(crate::graphics::lighting::RefractiveIndex::GLASS),
+        ));
+        // Looks down at the floor under the sphere, where the caustic lands.
+        let camera = RayCamera::new(4, 1.0)
+            .with_look_at(Point::new(0.9, 0.6, -0.5), Point::new(0.0, 0.0, -0.5))
+            .with_vertical_fov(20.0)
+            .with_max_depth(6)
+            .with_background(LinearColor::default())
+            .with_rng_seed(21);
+        let mean = |image: HdrImage| {
+            image
+                .pixels()
+                .iter()
+                .map(|pixel| luminance(*pixel))
+                .sum::<f64>()
+                / 16.0
+        };
+
+        let bidirectional = mean(
+            camera
+                .with_samples_per_pixel(2048)
+                .render_world_bidirectional_hdr_image(&world, &lights),
+        );
+        let photon_mapped = mean(
+            camera
+                .with_samples_per_pixel(128)
+                .render_world_photon_mapped_hdr_image(
+                    &world,
+                    &lights,
+                    PhotonMappingOptions::new()
+                        .photons_per_pass(4000)
+                        .initial_radius(0.1),
+                ),
+        );
+
+        assert!(
+            (photon_mapped - bidirectional).abs() < 0.1 * bidirectional,
+            "{photon_mapped} vs {bidirectional}"
+        );
+    }
+
+    #[test]
+    fn ray_camera_photon_mapped_render_is_deterministic_for_a_seed() {
+        let (world, lights) = bidirectional_test_room();
+        let camera = RayCamera::new(6, 1.0)
+            .with_look_at(Point::new(0.0, 0.5, 1.0), Point::new(0.0, 0.3, -0.5))
+            .with_samples_per_pixel(3)
+            .with_max_depth(3)
+            .with_rng_seed(3);
+        let options = PhotonMappingOptions::new().photons_per_pass(5000);
+
+        let first = camera.render_world_photon_mapped_hdr_image_tiled(&world, &lights, options, 2);
+        let second = camera.render_world_photon_mapped_hdr_image_tiled(&world, &lights, options, 5);
+
+        assert_eq!(first.pixels(), second.pixels());
+    }
+
+    #[test]
+    fn ray_camera_light_tree_matches_uniform_light_list_with_many_lights() {
+        use crate::graphics::raytracing::{
+            DiffuseLight, HittableList, Lambertian, LightTree, Quad, SamplingTargetList, Sphere,
+        };
+
+        let mut world = HittableList::new();
+        world.add(Quad::with_material(
+            Point::new(-5.0, 0.0, -5.0),
+            Vector::new(0.0, 0.0, 10.0),
+            Vector::new(10.0, 0.0, 0.0),
+            Lambertian::new(LinearColor::new(0.7, 0.7, 0.7)),
+        ));
+        let mut tree = LightTree::new();
+        let mut uniform = SamplingTargetList::new();
+        for index in 0..16 {
+            let (column, row) = (f64::from(index % 4), f64::from(index / 4));
+            let center = Point::new(column - 1.5, 0.4, row - 2.5);
+            let radius = 0.1;
+            let radiance = 1.0 + 3.0 * f64::from(index % 3);
+            world.add(Sphere::with_material(
+                center,
+                radius,
+                DiffuseLight::new(LinearColor::new(radiance, radiance, radiance)),
+            ));
+            tree.add_sphere(center, radius, radiance * radius * radius);
+            uniform.add_sphere(center, radius);
+        }
+        let camera = RayCamera::new(4, 1.0)
+            .with_look_at(Point::new(0.0, 2.0, 1.0), Point::new(0.0, 0.0, -1.0))
+            .with_vertical_fov(50.0)
+            .with_max_depth(2)
+            .with_background(LinearColor::default())
+            .with_direct_lighting_mode(DirectLightingMode::NextEventEstimation)
+            .with_rng_seed(17);
+        let mean = |image: HdrImage| {
+            image
+                .pixels()
+                .iter()
+                .map(|pixel| luminance(*pixel))
+                .sum::<f64>()
+                / 16.0
+        };
+
+        let uniform = mean(
+            camera
+                .with_samples_per_pixel(8192)
+                .render_world_with_lights_hdr_image(&world, &uniform),
+        );
+        let tree = mean(
+            camera
+                .with_samples_per_pixel(4096)
+                .render_world_with_lights_hdr_image(&world, &tree),
+        );
+
+        assert!(uniform > 0.01, "{uniform}");
+        assert!(
+            (tree - uniform).abs() < 0.05 * uniform,
+            "{tree} vs {uniform}"
+        );
+    }
+
     fn test_background_fn(_direction: Vector) -> LinearColor {
         LinearColor::new(0.25, 0.0, 0.0)
     }
@@ -5204,7 +6336,6 @@ mod tests {
     fn sampling_strategy_light_pdf_weight_controls_continuation_pdf() {
         let material = Lambertian::new(LinearColor::new(0.5, 0.5, 0.5));
         let (ray, hit) = sampling_strategy_hit(&material);
-        let material_pdf = MaterialPdf::Sphere(SpherePdf);
         let light = FixedPdfTarget {
             direction: Vector::new(1.0, 0.0, 0.0),
             pdf_value: 0.75,
@@ -5214,19 +6345,15 @@ mod tests {
             let strategy =
                 SamplingStrategy::current_path_continuation().with_light_pdf_weight(weight);
             let mut rng = SampleRng::new(100 + seed_offset);
-            let sample = strategy.continuation_sample(
-                &ray,
-                &hit,
-                material_pdf,
-                Some(&light),
-                None,
-                &mut rng,
-            );
-            let material_pdf_value = material_pdf.value(sample.direction);
+            let sample = strategy
+                .continuation_sample(&ray, &hit, Some(&light), None, &mut rng)
+                .expect("Lambertian continuation should sample a direction");
+            let scattered = Ray::new(hit.point, sample.direction);
+            let material_pdf_value = material.bsdf_pdf(&ray, &hit, &scattered);
             let expected_pdf = (1.0 - weight) * material_pdf_value + weight * light.pdf_value;
 
             assert_close(sample.pdf_value, expected_pdf);
-            assert!(!sample.suppress_next_emission);
+            assert!(!sample.weight_light_emission);
             assert!(!sample.weight_environment_miss);
         }
     }
@@ -5263,6 +6390,52 @@ mod tests {
         );
     }
 
+    #[test]
+    fn ray_camera_denoising_aovs_track_beauty_variance() {
+        let world = crate::graphics::raytracing::scenes::normal_sphere_world();
+        let camera = RayCamera::new(4, 1.0).with_background(LinearColor::new(0.5, 0.7, 1.0));
+
+        let single = camera
+            .with_samples_per_pixel(1)
+            .render_world_denoising_aovs(&world);
+        let aovs = camera
+            .with_samples_per_pixel(8)
+            .render_world_denoising_aovs(&world);
+        let denoised = aovs.denoise();
+
+        assert!(single.variance.iter().all(|variance| variance.is_nan()));
+        assert_eq!(aovs.variance.len(), 16);
+        assert!(
+            aovs.variance
+                .iter()
+                .all(|variance| variance.is_finite() && *variance >= 0.0)
+        );
+        assert_eq!((denoised.width(), denoised.height()), (4, 4));
+        assert!(denoised.pixels().iter().all(|pixel| pixel.is_finite()));
+    }
+
+    #[test]
+    fn denoising_aovs_export_named_exr_layers() {
+        let world = crate::graphics::raytracing::scenes::normal_sphere_world();
+        let aovs = RayCamera::new(4, 1.0)
+            .with_samples_per_pixel(1)
+            .with_background(LinearColor::default())
+            .render_world_denoising_aovs(&world);
+
+        let image = aovs
+            .to_exr_image(ExrPixelType::Float)
+            .expect("aov buffers match the canvas size");
+
+        assert_eq!(image.layers(), vec!["", "albedo", "normal", "variance"]);
+        let normals = image.rgb_layer("normal").expect("normal layer");
+        for (decoded, encoded) in normals.iter().zip(&aovs.normal_linear) {
+            assert!((decoded.red - encoded.red.mul_add(2.0, -1.0)).abs() < 1.0e-6);
+        }
+        let mut short = aovs;
+        short.albedo_linear.pop();
+        assert!(short.to_exr_image(ExrPixelType::Half).is_err());
+    }
+
     #[test]
     fn ray_camera_vertical_fov_controls_ray_spread() {
         let wide = RayCamera::new(101, 1.0).with_vertical_fov(90.0);
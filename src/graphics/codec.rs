@@ -0,0 +1,16 @@
+//! Dependency-free image codecs.
+//!
+//! [`Canvas::save_extension`](crate::graphics::display::Canvas::save_extension) and the external
+//! image loaders use these encoders and decoders directly, so common formats work without
+//! `ImageMagick` installed.
+
+pub mod exr;
+pub mod gif;
+pub mod hdr;
+pub mod png;
+pub mod zlib;
+
+pub use exr::{ExrChannel, ExrCompression, ExrError, ExrImage, ExrPixelType};
+pub use gif::{GifDithering, GifEncoder, GifOptions, GifQuantizer};
+pub use png::{PngError, PngImage};
+pub use zlib::Compression;
@@ -0,0 +1,1239 @@
+//! Native `OpenEXR` scanline encoding and decoding.
+//!
+//! The writer emits single-part scanline files with half or float channels and no, ZIP, or PIZ
+//! compression. The reader also accepts RLE and single-line ZIP blocks and 32-bit unsigned
+//! channels. Tiled, deep, and multi-part files are rejected.
+//!
+//! Channels named `R`, `G`, and `B` form the default RGB layer; other layers use the usual
+//! `layer.R` naming so compositing tools group them.
+
+mod piz;
+
+use std::{
+    error::Error,
+    fmt, fs,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use super::zlib::{self, Compression};
+use crate::graphics::{colors::LinearRgb, display::HdrImage};
+
+const MAGIC: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+const TILED_FLAG: u8 = 0x02;
+const LONG_NAMES_FLAG: u8 = 0x04;
+const NON_IMAGE_FLAG: u8 = 0x08;
+const MULTI_PART_FLAG: u8 = 0x10;
+const MAX_NAME_LENGTH: usize = 255;
+const SHORT_NAME_LENGTH: usize = 31;
+/// Largest decoded image accepted by the reader, in samples across all channels.
+const MAX_SAMPLES: u64 = 1 << 30;
+
+/// Error returned while reading or writing `OpenEXR` data.
+#[derive(Debug)]
+pub enum ExrError {
+    /// Reading or writing the underlying file failed.
+    Io(io::Error),
+    /// The EXR stream or the image being written is malformed.
+    Invalid(String),
+    /// The EXR stream is valid but uses a feature this codec does not handle.
+    Unsupported(String),
+}
+
+impl ExrError {
+    fn invalid(message: impl Into<String>) -> Self {
+        Self::Invalid(message.into())
+    }
+
+    fn unsupported(message: impl Into<String>) -> Self {
+        Self::Unsupported(message.into())
+    }
+}
+
+impl fmt::Display for ExrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "EXR I/O error: {error}"),
+            Self::Invalid(message) => write!(f, "invalid EXR: {message}"),
+            Self::Unsupported(message) => write!(f, "unsupported EXR: {message}"),
+        }
+    }
+}
+
+impl Error for ExrError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Invalid(_) | Self::Unsupported(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for ExrError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<zlib::InflateError> for ExrError {
+    fn from(error: zlib::InflateError) -> Self {
+        Self::Invalid(error.to_string())
+    }
+}
+
+impl From<ExrError> for io::Error {
+    fn from(error: ExrError) -> Self {
+        match error {
+            ExrError::Io(error) => error,
+            ExrError::Invalid(_) => io::Error::new(io::ErrorKind::InvalidData, error),
+            ExrError::Unsupported(_) => io::Error::new(io::ErrorKind::Unsupported, error),
+        }
+    }
+}
+
+/// Storage type of one EXR channel.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ExrPixelType {
+    /// 16-bit IEEE half floats.
+    #[default]
+    Half,
+    /// 32-bit IEEE floats.
+    Float,
+}
+
+impl ExrPixelType {
+    const fn code(self) -> i32 {
+        match self {
+            Self::Half => 1,
+            Self::Float => 2,
+        }
+    }
+
+    const fn byte_size(self) -> usize {
+        match self {
+            Self::Half => 2,
+            Self::Float => 4,
+        }
+    }
+}
+
+/// Block compression used when writing an EXR file.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ExrCompression {
+    /// Uncompressed scanlines.
+    None,
+    /// Lossless zlib compression of 16-line blocks.
+    #[default]
+    Zip,
+    /// Lossless wavelet and Huffman compression of 32-line blocks, best for noisy renders.
+    Piz,
+}
+
+/// Compression methods the reader understands.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum BlockCompression {
+    None,
+    Rle,
+    Zips,
+    Zip,
+    Piz,
+}
+
+impl BlockCompression {
+    fn from_code(code: u8) -> Result<Self, ExrError> {
+        match code {
+            0 => Ok(Self::None),
+            1 => Ok(Self::Rle),
+            2 => Ok(Self::Zips),
+            3 => Ok(Self::Zip),
+            4 => Ok(Self::Piz),
+            5 => Err(ExrError::unsupported("PXR24 compression")),
+            6 | 7 => Err(ExrError::unsupported("B44 compression")),
+            8 | 9 => Err(ExrError::unsupported("DWA compression")),
+            other => Err(ExrError::invalid(format!("unknown compression {other}"))),
+        }
+    }
+
+    const fn code(self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Rle => 1,
+            Self::Zips => 2,
+            Self::Zip => 3,
+            Self::Piz => 4,
+        }
+    }
+
+    const fn lines_per_block(self) -> usize {
+        match self {
+            Self::None | Self::Rle | Self::Zips => 1,
+            Self::Zip => 16,
+            Self::Piz => 32,
+        }
+    }
+}
+
+impl From<ExrCompression> for BlockCompression {
+    fn from(compression: ExrCompression) -> Self {
+        match compression {
+            ExrCompression::None => Self::None,
+            ExrCompression::Zip => Self::Zip,
+            ExrCompression::Piz => Self::Piz,
+        }
+    }
+}
+
+/// One named channel of samples, stored row-major with row 0 at the top.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExrChannel {
+    name: String,
+    pixel_type: ExrPixelType,
+    samples: Vec<f32>,
+}
+
+impl ExrChannel {
+    /// Returns the full channel name, such as `R` or `albedo.G`.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the storage type used when the channel is written.
+    #[must_use]
+    pub const fn pixel_type(&self) -> ExrPixelType {
+        self.pixel_type
+    }
+
+    /// Returns the channel samples.
+    #[must_use]
+    pub fn samples(&self) -> &[f32] {
+        &self.samples
+    }
+}
+
+/// A scanline `OpenEXR` image made of named float channels.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExrImage {
+    width: u32,
+    height: u32,
+    channels: Vec<ExrChannel>,
+}
+
+impl ExrImage {
+    /// Creates an image with no channels.
+    #[must_use]
+    pub const fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            channels: Vec::new(),
+        }
+    }
+
+    /// Creates an image whose default `R`, `G`, `B` layer holds `image`.
+    #[must_use]
+    pub fn from_hdr_image(image: &HdrImage, pixel_type: ExrPixelType) -> Self {
+        let mut exr = Self::new(image.width(), image.height());
+        exr.push_rgb_layer_unchecked("", image.pixels(), pixel_type);
+        exr
+    }
+
+    /// Adds a channel.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ExrError::Invalid`] if the name is empty, longer than 255 bytes, contains a
+    /// NUL byte, or is already used, or if `samples.len()` is not `width * height`.
+    pub fn push_channel(
+        &mut self,
+        name: impl Into<String>,
+        pixel_type: ExrPixelType,
+        samples: Vec<f32>,
+    ) -> Result<(), ExrError> {
+        let name = name.into();
+        if name.is_empty() || name.len() > MAX_NAME_LENGTH || name.contains('\0') {
+            return Err(ExrError::invalid(format!("bad channel name `{name}`")));
+        }
+        if self.channel(&name).is_some() {
+            return Err(ExrError::invalid(format!("duplicate channel `{name}`")));
+        }
+        let expected = u64::from(self.width) * u64::from(self.height);
+        if samples.len() as u64 != expected {
+            return Err(ExrError::invalid(format!(
+                "channel `{name}` has {} samples, expected {expected}",
+                samples.len()
+            )));
+        }
+        self.channels.push(ExrChannel {
+            name,
+            pixel_type,
+            samples,
+        });
+        Ok(())
+    }
+
+    /// Adds `layer.R`, `layer.G`, and `layer.B` channels, or `R`, `G`, `B` for an empty layer.
+    ///
+    /// Non-finite components are written as zero.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from [`Self::push_channel`].
+    pub fn push_rgb_layer(
+        &mut self,
+        layer: &str,
+        pixels: &[LinearRgb],
+        pixel_type: ExrPixelType,
+    ) -> Result<(), ExrError> {
+        for (suffix, samples) in rgb_channels(pixels) {
+            self.push_channel(layer_channel_name(layer, suffix), pixel_type, samples)?;
+        }
+        Ok(())
+    }
+
+    fn push_rgb_layer_unchecked(
+        &mut self,
+        layer: &str,
+        pixels: &[LinearRgb],
+        pixel_type: ExrPixelType,
+    ) {
+        for (suffix, samples) in rgb_channels(pixels) {
+            self.channels.push(ExrChannel {
+                name: layer_channel_name(layer, suffix),
+                pixel_type,
+                samples,
+            });
+        }
+    }
+
+    /// Returns the image width.
+    #[must_use]
+    pub const fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the image height.
+    #[must_use]
+    pub const fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns every channel in insertion order, or file order for decoded images.
+    #[must_use]
+    pub fn channels(&self) -> &[ExrChannel] {
+        &self.channels
+    }
+
+    /// Returns the channel with the exact `name`.
+    #[must_use]
+    pub fn channel(&self, name: &str) -> Option<&ExrChannel> {
+        self.channels.iter().find(|channel| channel.name == name)
+    }
+
+    /// Returns the distinct layer names, with `""` for channels without a layer prefix.
+    #[must_use]
+    pub fn layers(&self) -> Vec<&str> {
+        let mut layers = Vec::new();
+        for channel in &self.channels {
+            let layer = channel.name.rsplit_once('.').map_or("", |(layer, _)| layer);
+            if !layers.contains(&layer) {
+                layers.push(layer);
+            }
+        }
+        layers
+    }
+
+    /// Reads the `R`, `G`, `B` channels of `layer` as linear colors.
+    ///
+    /// A layer with only a `Y` luminance channel is expanded to gray. Returns `None` if the
+    /// layer has neither.
+    #[must_use]
+    pub fn rgb_layer(&self, layer: &str) -> Option<Vec<LinearRgb>> {
+        let channel = |suffix| self.channel(&layer_channel_name(layer, suffix));
+        if let (Some(red), Some(green), Some(blue)) = (channel("R"), channel("G"), channel("B")) {
+            return Some(
+                red.samples
+                    .iter()
+                    .zip(&green.samples)
+                    .zip(&blue.samples)
+                    .map(|((red, green), blue)| {
+                        LinearRgb::new(f64::from(*red), f64::from(*green), f64::from(*blue))
+                    })
+                    .collect(),
+            );
+        }
+        channel("Y").map(|luminance| {
+            luminance
+                .samples
+                .iter()
+                .map(|value| {
+                    let value = f64::from(*value);
+                    LinearRgb::new(value, value, value)
+                })
+                .collect()
+        })
+    }
+
+    /// Converts the default RGB layer to an [`HdrImage`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ExrError::Invalid`] if the image has no default `R`, `G`, `B` or `Y` channels.
+    pub fn to_hdr_image(&self) -> Result<HdrImage, ExrError> {
+        self.layer_to_hdr_image("")
+    }
+
+    /// Converts one RGB layer to an [`HdrImage`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ExrError::Invalid`] if the layer has no `R`, `G`, `B` or `Y` channels.
+    pub fn layer_to_hdr_image(&self, layer: &str) -> Result<HdrImage, ExrError> {
+        let pixels = self
+            .rgb_layer(layer)
+            .ok_or_else(|| ExrError::invalid(format!("no RGB channels in layer `{layer}`")))?;
+        Ok(HdrImage::from_pixels(self.width, self.height, pixels))
+    }
+
+    /// Decodes an `OpenEXR` byte stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ExrError::Invalid`] for malformed headers or blocks, and
+    /// [`ExrError::Unsupported`] for tiled, deep, or multi-part files, subsampled channels, and
+    /// lossy compression methods.
+    pub fn decode(bytes: &[u8]) -> Result<Self, ExrError> {
+        decode(bytes)
+    }
+
+    /// Reads and decodes an `OpenEXR` file.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ExrError::Io`] if the file cannot be read, or any error from [`Self::decode`].
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ExrError> {
+        Self::decode(&fs::read(path)?)
+    }
+
+    /// Encodes the image as a single-part scanline `OpenEXR` stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ExrError::Invalid`] if the image is empty, has no channels, or is too large.
+    pub fn encode(&self, compression: ExrCompression) -> Result<Vec<u8>, ExrError> {
+        let mut out = Vec::new();
+        self.write_to(&mut out, compression)?;
+        Ok(out)
+    }
+
+    /// Encodes the image and writes it to `out`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ExrError::Io`] if writing fails, or any validation error from [`Self::encode`].
+    pub fn write_to<W: Write>(
+        &self,
+        mut out: W,
+        compression: ExrCompression,
+    ) -> Result<(), ExrError> {
+        encode(self, compression.into(), &mut out)
+    }
+
+    /// Encodes the image to a file.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ExrError::Io`] if the file cannot be written, or any error from [`Self::encode`].
+    pub fn save(
+        &self,
+        path: impl AsRef<Path>,
+        compression: ExrCompression,
+    ) -> Result<(), ExrError> {
+        let mut out = BufWriter::new(fs::File::create(path)?);
+        self.write_to(&mut out, compression)?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+/// Returns the channel suffix and finite float samples of each RGB component.
+fn rgb_channels(pixels: &[LinearRgb]) -> [(&'static str, Vec<f32>); 3] {
+    let channel = |component: fn(&LinearRgb) -> f64| {
+        pixels
+            .iter()
+            .map(|pixel| finite_f32(component(pixel)))
+            .collect()
+    };
+    [
+        ("R", channel(|pixel| pixel.red)),
+        ("G", channel(|pixel| pixel.green)),
+        ("B", channel(|pixel| pixel.blue)),
+    ]
+}
+
+fn layer_channel_name(layer: &str, suffix: &str) -> String {
+    if layer.is_empty() {
+        suffix.to_string()
+    } else {
+        format!("{layer}.{suffix}")
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn finite_f32(value: f64) -> f32 {
+    if value.is_finite() { value as f32 } else { 0.0 }
+}
+
+/// Converts a float to the nearest half float, rounding ties to even.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_possible_wrap,
+    clippy::cast_sign_loss
+)]
+pub(crate) fn f32_to_half(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x007f_ffff;
+
+    if exponent == 0xff {
+        let nan = if mantissa == 0 {
+            0
+        } else {
+            0x0200 | (mantissa >> 13) as u16
+        };
+        return sign | 0x7c00 | nan;
+    }
+
+    let half_exponent = exponent - 127 + 15;
+    if half_exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    let round = |value: u32, shift: u32| {
+        let kept = value >> shift;
+        let remainder = value & ((1 << shift) - 1);
+        let halfway = 1 << (shift - 1);
+        if remainder > halfway || (remainder == halfway && kept & 1 == 1) {
+            kept + 1
+        } else {
+            kept
+        }
+    };
+    if half_exponent <= 0 {
+        if half_exponent < -10 {
+            return sign;
+        }
+        let shift = (14 - half_exponent) as u32;
+        return sign | round(mantissa | 0x0080_0000, shift) as u16;
+    }
+    sign | round(((half_exponent as u32) << 23) | mantissa, 13) as u16
+}
+
+/// Converts a half float to a float exactly.
+pub(crate) fn half_to_f32(half: u16) -> f32 {
+    let sign = u32::from(half & 0x8000) << 16;
+    let exponent = u32::from((half >> 10) & 0x1f);
+    let mantissa = u32::from(half & 0x03ff);
+    match exponent {
+        0 => {
+            let magnitude = f32::from(half & 0x03ff) * f32::powi(2.0, -24);
+            if sign == 0 { magnitude } else { -magnitude }
+        }
+        0x1f => f32::from_bits(sign | 0x7f80_0000 | (mantissa << 13)),
+        _ => f32::from_bits(sign | ((exponent + 112) << 23) | (mantissa << 13)),
+    }
+}
+
+fn write_attribute(out: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    out.extend_from_slice(name.as_bytes());
+    out.push(0);
+    out.extend_from_slice(kind.as_bytes());
+    out.push(0);
+    out.extend_from_slice(&u32::try_from(value.len()).unwrap_or(u32::MAX).to_le_bytes());
+    out.extend_from_slice(value);
+}
+
+/// Builds the magic number, version, and required header attributes.
+fn encode_header(
+    channels: &[&ExrChannel],
+    compression: BlockCompression,
+    max_x: i32,
+    max_y: i32,
+) -> Vec<u8> {
+    let mut header = MAGIC.to_vec();
+    let long_names = channels
+        .iter()
+        .any(|channel| channel.name.len() > SHORT_NAME_LENGTH);
+    header.extend_from_slice(&[2, if long_names { LONG_NAMES_FLAG } else { 0 }, 0, 0]);
+
+    let mut channel_list = Vec::new();
+    for channel in channels {
+        channel_list.extend_from_slice(channel.name.as_bytes());
+        channel_list.push(0);
+        channel_list.extend_from_slice(&channel.pixel_type.code().to_le_bytes());
+        channel_list.extend_from_slice(&[0, 0, 0, 0]);
+        channel_list.extend_from_slice(&1_i32.to_le_bytes());
+        channel_list.extend_from_slice(&1_i32.to_le_bytes());
+    }
+    channel_list.push(0);
+    let mut window = Vec::with_capacity(16);
+    for value in [0, 0, max_x, max_y] {
+        window.extend_from_slice(&value.to_le_bytes());
+    }
+    write_attribute(&mut header, "channels", "chlist", &channel_list);
+    write_attribute(
+        &mut header,
+        "compression",
+        "compression",
+        &[compression.code()],
+    );
+    write_attribute(&mut header, "dataWindow", "box2i", &window);
+    write_attribute(&mut header, "displayWindow", "box2i", &window);
+    write_attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    write_attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1.0_f32.to_le_bytes(),
+    );
+    write_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    write_attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1.0_f32.to_le_bytes(),
+    );
+    header.push(0);
+    header
+}
+
+/// Appends scanlines `rows` of every channel in file order, as stored in an uncompressed block.
+fn append_scanlines(
+    channels: &[&ExrChannel],
+    width: usize,
+    rows: std::ops::Range<usize>,
+    raw: &mut Vec<u8>,
+) {
+    for y in rows {
+        for channel in channels {
+            let row = &channel.samples[y * width..(y + 1) * width];
+            match channel.pixel_type {
+                ExrPixelType::Half => {
+                    for sample in row {
+                        raw.extend_from_slice(&f32_to_half(*sample).to_le_bytes());
+                    }
+                }
+                ExrPixelType::Float => {
+                    for sample in row {
+                        raw.extend_from_slice(&sample.to_le_bytes());
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns the uncompressed size of one scanline across all channels.
+fn scanline_bytes(channels: &[&ExrChannel], width: usize) -> usize {
+    channels
+        .iter()
+        .map(|channel| channel.pixel_type.byte_size() * width)
+        .sum()
+}
+
+#[allow(clippy::cast_possible_wrap, clippy::cast_possible_truncation)]
+fn encode<W: Write>(
+    image: &ExrImage,
+    compression: BlockCompression,
+    out: &mut W,
+) -> Result<(), ExrError> {
+    if image.width == 0 || image.height == 0 {
+        return Err(ExrError::invalid("image dimensions must be non-zero"));
+    }
+    if image.channels.is_empty() {
+        return Err(ExrError::invalid("image has no channels"));
+    }
+    let max_coordinate = |value: u32| {
+        i32::try_from(value - 1).map_err(|_| ExrError::invalid("image dimensions are too large"))
+    };
+    let (max_x, max_y) = (max_coordinate(image.width)?, max_coordinate(image.height)?);
+    let width = usize::try_from(image.width).map_err(|_| ExrError::invalid("image too large"))?;
+    let height = usize::try_from(image.height).map_err(|_| ExrError::invalid("image too large"))?;
+
+    let mut channels = image.channels.iter().collect::<Vec<_>>();
+    channels.sort_by(|a, b| a.name.as_bytes().cmp(b.name.as_bytes()));
+
+    let mut header = encode_header(&channels, compression, max_x, max_y);
+
+    let lines_per_block = compression.lines_per_block();
+    let block_count = height.div_ceil(lines_per_block);
+    let channel_sizes = channels
+        .iter()
+        .map(|channel| channel.pixel_type.byte_size() / 2)
+        .collect::<Vec<_>>();
+
+    let mut blocks = Vec::with_capacity(block_count);
+    let mut raw = Vec::with_capacity(scanline_bytes(&channels, width) * lines_per_block);
+    for block in 0..block_count {
+        let first_line = block * lines_per_block;
+        let lines = lines_per_block.min(height - first_line);
+        raw.clear();
+        append_scanlines(&channels, width, first_line..first_line + lines, &mut raw);
+        let compressed = match compression {
+            BlockCompression::None => None,
+            BlockCompression::Zip | BlockCompression::Zips => Some(zip_compress(&raw)),
+            BlockCompression::Rle => Some(rle_compress(&raw)),
+            BlockCompression::Piz => Some(piz::compress(&raw, width, lines, &channel_sizes)),
+        };
+        let data = match compressed {
+            Some(compressed) if compressed.len() < raw.len() => compressed,
+            _ => raw.clone(),
+        };
+        blocks.push((first_line as i32, data));
+    }
+
+    let mut offset = (header.len() + block_count * 8) as u64;
+    for (_, data) in &blocks {
+        header.extend_from_slice(&offset.to_le_bytes());
+        offset += 8 + data.len() as u64;
+    }
+    out.write_all(&header)?;
+    for (y, data) in blocks {
+        out.write_all(&y.to_le_bytes())?;
+        let size =
+            u32::try_from(data.len()).map_err(|_| ExrError::invalid("block is too large"))?;
+        out.write_all(&size.to_le_bytes())?;
+        out.write_all(&data)?;
+    }
+    Ok(())
+}
+
+/// Splits even and odd bytes apart and delta-encodes them, as `OpenEXR` does before zlib and RLE.
+fn predict_and_interleave(raw: &[u8]) -> Vec<u8> {
+    let half = raw.len().div_ceil(2);
+    let mut reordered = vec![0_u8; raw.len()];
+    for (index, byte) in raw.iter().enumerate() {
+        let target = if index % 2 == 0 {
+            index / 2
+        } else {
+            half + index / 2
+        };
+        reordered[target] = *byte;
+    }
+    let mut previous = reordered.first().copied().unwrap_or(0);
+    for byte in reordered.iter_mut().skip(1) {
+        let current = *byte;
+        *byte = current.wrapping_sub(previous).wrapping_add(128);
+        previous = current;
+    }
+    reordered
+}
+
+/// Inverts [`predict_and_interleave`].
+fn reconstruct_and_deinterleave(mut data: Vec<u8>) -> Vec<u8> {
+    for index in 1..data.len() {
+        data[index] = data[index - 1].wrapping_add(data[index]).wrapping_sub(128);
+    }
+    let half = data.len().div_ceil(2);
+    let (even, odd) = data.split_at(half);
+    let mut raw = Vec::with_capacity(data.len());
+    for (index, byte) in even.iter().enumerate() {
+        raw.push(*byte);
+        if let Some(byte) = odd.get(index) {
+            raw.push(*byte);
+        }
+    }
+    raw
+}
+
+fn zip_compress(raw: &[u8]) -> Vec<u8> {
+    zlib::compress(&predict_and_interleave(raw), Compression::Default)
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn rle_compress(raw: &[u8]) -> Vec<u8> {
+    let data = predict_and_interleave(raw);
+    let mut out = Vec::new();
+    let mut start = 0;
+    while start < data.len() {
+        let mut run = 1;
+        while start + run < data.len() && data[start + run] == data[start] && run < 128 {
+            run += 1;
+        }
+        if run >= 3 {
+            out.push((run - 1) as u8);
+            out.push(data[start]);
+            start += run;
+            continue;
+        }
+        let mut end = start + 1;
+        while end < data.len()
+            && end - start < 127
+            && !(end + 2 < data.len() && data[end] == data[end + 1] && data[end] == data[end + 2])
+        {
+            end += 1;
+        }
+        out.push(((end - start) as u8).wrapping_neg());
+        out.extend_from_slice(&data[start..end]);
+        start = end;
+    }
+    out
+}
+
+#[allow(clippy::cast_sign_loss)]
+fn rle_decompress(data: &[u8], expected: usize) -> Result<Vec<u8>, ExrError> {
+    let mut out = Vec::with_capacity(expected);
+    let mut cursor = 0;
+    while cursor < data.len() {
+        let count = data[cursor].cast_signed();
+        cursor += 1;
+        if count < 0 {
+            let length = usize::from(count.unsigned_abs());
+            let literal = data
+                .get(cursor..cursor + length)
+                .ok_or_else(|| ExrError::invalid("truncated RLE block"))?;
+            out.extend_from_slice(literal);
+            cursor += length;
+        } else {
+            let value = *data
+                .get(cursor)
+                .ok_or_else(|| ExrError::invalid("truncated RLE block"))?;
+            cursor += 1;
+            out.extend(std::iter::repeat_n(value, count as usize + 1));
+        }
+        if out.len() > expected {
+            return Err(ExrError::invalid("RLE block decodes to too many bytes"));
+        }
+    }
+    Ok(reconstruct_and_deinterleave(out))
+}
+
+/// Sequential little-endian reader over the file bytes.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    cursor: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], ExrError> {
+        let slice = self
+            .bytes
+            .get(self.cursor..self.cursor.saturating_add(length))
+            .ok_or_else(|| ExrError::invalid("unexpected end of file"))?;
+        self.cursor += length;
+        Ok(slice)
+    }
+
+    fn i32(&mut self) -> Result<i32, ExrError> {
+        let bytes = self.take(4)?;
+        Ok(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn u64(&mut self) -> Result<u64, ExrError> {
+        let bytes = self.take(8)?;
+        let mut value = [0; 8];
+        value.copy_from_slice(bytes);
+        Ok(u64::from_le_bytes(value))
+    }
+
+    fn name(&mut self, max_length: usize) -> Result<&'a str, ExrError> {
+        let rest = &self.bytes[self.cursor.min(self.bytes.len())..];
+        let length = rest
+            .iter()
+            .position(|byte| *byte == 0)
+            .ok_or_else(|| ExrError::invalid("unterminated name"))?;
+        if length > max_length {
+            return Err(ExrError::invalid("name is too long"));
+        }
+        let name = std::str::from_utf8(&rest[..length])
+            .map_err(|_| ExrError::invalid("name is not UTF-8"))?;
+        self.cursor += length + 1;
+        Ok(name)
+    }
+}
+
+/// Channel description parsed from the header.
+struct ChannelInfo {
+    name: String,
+    pixel_type: u32,
+}
+
+impl ChannelInfo {
+    const fn byte_size(&self) -> usize {
+        if self.pixel_type == 1 { 2 } else { 4 }
+    }
+}
+
+fn parse_channels(value: &[u8], max_name_length: usize) -> Result<Vec<ChannelInfo>, ExrError> {
+    let mut reader = Reader {
+        bytes: value,
+        cursor: 0,
+    };
+    let mut channels = Vec::new();
+    loop {
+        let name = reader.name(max_name_length)?;
+        if name.is_empty() {
+            return Ok(channels);
+        }
+        let pixel_type = u32::try_from(reader.i32()?)
+            .ok()
+            .filter(|pixel_type| *pixel_type <= 2)
+            .ok_or_else(|| ExrError::invalid(format!("channel `{name}` has a bad pixel type")))?;
+        reader.take(4)?;
+        let x_sampling = reader.i32()?;
+        let y_sampling = reader.i32()?;
+        if x_sampling != 1 || y_sampling != 1 {
+            return Err(ExrError::unsupported(format!(
+                "channel `{name}` is subsampled"
+            )));
+        }
+        channels.push(ChannelInfo {
+            name: name.to_string(),
+            pixel_type,
+        });
+    }
+}
+
+#[allow(clippy::too_many_lines, clippy::cast_precision_loss)]
+fn decode(bytes: &[u8]) -> Result<ExrImage, ExrError> {
+    let mut reader = Reader { bytes, cursor: 0 };
+    if reader.take(4)? != MAGIC {
+        return Err(ExrError::invalid("bad magic number"));
+    }
+    let version = reader.take(4)?;
+    if version[0] != 2 {
+        return Err(ExrError::unsupported(format!(
+            "file version {}",
+            version[0]
+        )));
+    }
+    let flags = version[1];
+    if flags & TILED_FLAG != 0 {
+        return Err(ExrError::unsupported("tiled images"));
+    }
+    if flags & (NON_IMAGE_FLAG | MULTI_PART_FLAG) != 0 {
+        return Err(ExrError::unsupported("deep or multi-part images"));
+    }
+    let max_name_length = if flags & LONG_NAMES_FLAG == 0 {
+        SHORT_NAME_LENGTH
+    } else {
+        MAX_NAME_LENGTH
+    };
+
+    let mut channels = None;
+    let mut compression = None;
+    let mut data_window = None;
+    loop {
+        let name = reader.name(max_name_length)?;
+        if name.is_empty() {
+            break;
+        }
+        let _kind = reader.name(max_name_length)?;
+        let size = usize::try_from(reader.i32()?)
+            .map_err(|_| ExrError::invalid(format!("attribute `{name}` has a negative size")))?;
+        let value = reader.take(size)?;
+        match name {
+            "channels" => channels = Some(parse_channels(value, max_name_length)?),
+            "compression" => {
+                let code = *value
+                    .first()
+                    .ok_or_else(|| ExrError::invalid("empty compression attribute"))?;
+                compression = Some(BlockCompression::from_code(code)?);
+            }
+            "dataWindow" => {
+                if value.len() != 16 {
+                    return Err(ExrError::invalid("dataWindow must be a box2i"));
+                }
+                let mut window = Reader {
+                    bytes: value,
+                    cursor: 0,
+                };
+                data_window = Some([window.i32()?, window.i32()?, window.i32()?, window.i32()?]);
+            }
+            _ => {}
+        }
+    }
+
+    let channels = channels.ok_or_else(|| ExrError::invalid("missing channels attribute"))?;
+    let compression =
+        compression.ok_or_else(|| ExrError::invalid("missing compression attribute"))?;
+    let [min_x, min_y, max_x, max_y] =
+        data_window.ok_or_else(|| ExrError::invalid("missing dataWindow attribute"))?;
+    let extent = |min: i32, max: i32| {
+        u32::try_from(i64::from(max) - i64::from(min) + 1)
+            .ok()
+            .filter(|extent| *extent > 0)
+            .ok_or_else(|| ExrError::invalid("empty or inverted dataWindow"))
+    };
+    let (width, height) = (extent(min_x, max_x)?, extent(min_y, max_y)?);
+    let samples_per_channel = u64::from(width) * u64::from(height);
+    if samples_per_channel.saturating_mul(channels.len() as u64) > MAX_SAMPLES {
+        return Err(ExrError::unsupported("image is too large"));
+    }
+    let width_usize = width as usize;
+    let height_usize = height as usize;
+
+    let lines_per_block = compression.lines_per_block();
+    let block_count = height_usize.div_ceil(lines_per_block);
+    let mut offsets = Vec::with_capacity(block_count);
+    for _ in 0..block_count {
+        offsets.push(reader.u64()?);
+    }
+
+    let mut samples = channels
+        .iter()
+        .map(|_| vec![0.0_f32; width_usize * height_usize])
+        .collect::<Vec<_>>();
+    let line_bytes = channels
+        .iter()
+        .map(|channel| channel.byte_size() * width_usize)
+        .sum::<usize>();
+    let channel_sizes = channels
+        .iter()
+        .map(|channel| channel.byte_size() / 2)
+        .collect::<Vec<_>>();
+
+    for offset in offsets {
+        let mut block = Reader {
+            bytes,
+            cursor: usize::try_from(offset).map_err(|_| ExrError::invalid("bad block offset"))?,
+        };
+        let block_y = i64::from(block.i32()?) - i64::from(min_y);
+        let first_line = usize::try_from(block_y)
+            .ok()
+            .filter(|line| *line < height_usize && line % lines_per_block == 0)
+            .ok_or_else(|| ExrError::invalid("block y coordinate is out of range"))?;
+        let size =
+            usize::try_from(block.i32()?).map_err(|_| ExrError::invalid("negative block size"))?;
+        let data = block.take(size)?;
+        let lines = lines_per_block.min(height_usize - first_line);
+        let expected = line_bytes * lines;
+
+        let raw = if size == expected {
+            data.to_vec()
+        } else {
+            match compression {
+                BlockCompression::None => {
+                    return Err(ExrError::invalid("uncompressed block has the wrong size"));
+                }
+                BlockCompression::Rle => rle_decompress(data, expected)?,
+                BlockCompression::Zips | BlockCompression::Zip => {
+                    reconstruct_and_deinterleave(zlib::decompress(data)?)
+                }
+                BlockCompression::Piz => piz::decompress(data, width_usize, lines, &channel_sizes)
+                    .map_err(ExrError::Invalid)?,
+            }
+        };
+        if raw.len() != expected {
+            return Err(ExrError::invalid("block decodes to the wrong size"));
+        }
+
+        let mut cursor = 0;
+        for y in first_line..first_line + lines {
+            for (channel, target) in channels.iter().zip(&mut samples) {
+                let row = &mut target[y * width_usize..(y + 1) * width_usize];
+                let size = channel.byte_size();
+                for (sample, bytes) in row
+                    .iter_mut()
+                    .zip(raw[cursor..cursor + size * width_usize].chunks_exact(size))
+                {
+                    *sample = match channel.pixel_type {
+                        0 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32,
+                        1 => half_to_f32(u16::from_le_bytes([bytes[0], bytes[1]])),
+                        _ => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+                    };
+                }
+                cursor += size * width_usize;
+            }
+        }
+    }
+
+    Ok(ExrImage {
+        width,
+        height,
+        channels: channels
+            .into_iter()
+            .zip(samples)
+            .map(|(channel, samples)| ExrChannel {
+                name: channel.name,
+                pixel_type: if channel.pixel_type == 1 {
+                    ExrPixelType::Half
+                } else {
+                    ExrPixelType::Float
+                },
+                samples,
+            })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        BlockCompression, ExrCompression, ExrError, ExrImage, ExrPixelType, encode, f32_to_half,
+        finite_f32, half_to_f32,
+    };
+    use crate::graphics::{colors::LinearRgb, display::HdrImage};
+
+    fn gradient(width: u32, height: u32) -> HdrImage {
+        HdrImage::from_pixels(
+            width,
+            height,
+            (0..width * height)
+                .map(|index| {
+                    let value = f64::from(index);
+                    LinearRgb::new(
+                        value * 0.25,
+                        1.0 / (value + 1.0),
+                        (value * 0.37).sin() * 40.0,
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn half_conversion_matches_reference_values() {
+        for (value, bits) in [
+            (0.0_f32, 0x0000_u16),
+            (-0.0, 0x8000),
+            (1.0, 0x3c00),
+            (-2.0, 0xc000),
+            (65504.0, 0x7bff),
+            (1.0e6, 0x7c00),
+            (f32::INFINITY, 0x7c00),
+            (f32::powi(2.0, -24), 0x0001),
+            (f32::powi(2.0, -14), 0x0400),
+            (0.333_333_34, 0x3555),
+            (1.000_488_3, 0x3c00),
+        ] {
+            assert_eq!(f32_to_half(value), bits, "{value}");
+        }
+        assert!(half_to_f32(f32_to_half(f32::NAN)).is_nan());
+        for bits in (0..=0xffff_u16).filter(|bits| bits & 0x7c00 != 0x7c00) {
+            assert_eq!(f32_to_half(half_to_f32(bits)), bits);
+        }
+    }
+
+    #[test]
+    fn float_images_round_trip_exactly_for_every_compression() {
+        let image = gradient(37, 41);
+        for compression in [
+            ExrCompression::None,
+            ExrCompression::Zip,
+            ExrCompression::Piz,
+        ] {
+            let bytes = ExrImage::from_hdr_image(&image, ExrPixelType::Float)
+                .encode(compression)
+                .expect("encode");
+            let decoded = ExrImage::decode(&bytes).expect("decode");
+
+            let restored = decoded.to_hdr_image().expect("rgb layer");
+            assert_eq!(decoded.channels()[0].name(), "B");
+            for (restored, original) in restored.pixels().iter().zip(image.pixels()) {
+                assert_eq!(
+                    restored.red.to_bits(),
+                    f64::from(finite_f32(original.red)).to_bits()
+                );
+                assert_eq!(
+                    restored.green.to_bits(),
+                    f64::from(finite_f32(original.green)).to_bits()
+                );
+                assert_eq!(
+                    restored.blue.to_bits(),
+                    f64::from(finite_f32(original.blue)).to_bits()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn half_images_round_trip_to_half_precision() {
+        let image = gradient(19, 70);
+        for compression in [ExrCompression::Zip, ExrCompression::Piz] {
+            let bytes = ExrImage::from_hdr_image(&image, ExrPixelType::Half)
+                .encode(compression)
+                .expect("encode");
+            let restored = ExrImage::decode(&bytes)
+                .expect("decode")
+                .to_hdr_image()
+                .expect("rgb layer");
+            for (restored, original) in restored.pixels().iter().zip(image.pixels()) {
+                let expected = half_to_f32(f32_to_half(finite_f32(original.blue)));
+                assert_eq!(restored.blue.to_bits(), f64::from(expected).to_bits());
+            }
+        }
+    }
+
+    #[test]
+    fn other_block_compressions_decode() {
+        let image = ExrImage::from_hdr_image(&gradient(5, 4), ExrPixelType::Half);
+        let expected =
+            ExrImage::decode(&image.encode(ExrCompression::None).expect("encode")).expect("decode");
+        for compression in [BlockCompression::Rle, BlockCompression::Zips] {
+            let mut bytes = Vec::new();
+            encode(&image, compression, &mut bytes).expect("encode");
+            assert_eq!(ExrImage::decode(&bytes).expect("decode"), expected);
+        }
+    }
+
+    #[test]
+    fn named_layers_round_trip() {
+        let pixels = vec![LinearRgb::new(0.5, 0.25, 2.0); 6];
+        let mut image = ExrImage::new(3, 2);
+        image
+            .push_rgb_layer("", &pixels, ExrPixelType::Half)
+            .expect("beauty");
+        image
+            .push_rgb_layer("albedo", &pixels, ExrPixelType::Float)
+            .expect("albedo");
+        image
+            .push_channel("depth", ExrPixelType::Float, vec![7.0; 6])
+            .expect("depth");
+
+        let decoded =
+            ExrImage::decode(&image.encode(ExrCompression::Piz).expect("encode")).expect("decode");
+
+        assert_eq!(decoded.layers(), vec!["", "albedo"]);
+        assert_eq!(decoded.rgb_layer("albedo").expect("albedo layer"), pixels);
+        assert_eq!(
+            decoded.channel("depth").expect("depth").samples(),
+            &[7.0; 6]
+        );
+        assert!(decoded.rgb_layer("missing").is_none());
+    }
+
+    #[test]
+    fn rejects_bad_channels_and_streams() {
+        let mut image = ExrImage::new(2, 2);
+        assert!(matches!(
+            image.push_channel("R", ExrPixelType::Half, vec![0.0; 3]),
+            Err(ExrError::Invalid(_))
+        ));
+        image
+            .push_channel("R", ExrPixelType::Half, vec![0.0; 4])
+            .expect("channel");
+        assert!(
+            image
+                .push_channel("R", ExrPixelType::Half, vec![0.0; 4])
+                .is_err()
+        );
+        assert!(ExrImage::new(2, 2).encode(ExrCompression::Zip).is_err());
+
+        let bytes = image.encode(ExrCompression::Zip).expect("encode");
+        assert!(matches!(
+            ExrImage::decode(&bytes[..bytes.len() - 2]),
+            Err(ExrError::Invalid(_))
+        ));
+        assert!(matches!(
+            ExrImage::decode(b"not an exr"),
+            Err(ExrError::Invalid(_))
+        ));
+        let mut tiled = bytes;
+        tiled[5] |= 0x02;
+        assert!(matches!(
+            ExrImage::decode(&tiled),
+            Err(ExrError::Unsupported(_))
+        ));
+    }
+}
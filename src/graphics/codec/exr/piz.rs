@@ -0,0 +1,757 @@
+//! PIZ block compression.
+//!
+//! Each channel's 16-bit words are remapped through a dense lookup table, run through a 2D Haar
+//! wavelet, and Huffman coded with a run-length escape symbol. The bit layout follows the `OpenEXR`
+//! reference implementation so files interoperate with other readers and writers.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+const USHORT_RANGE: usize = 1 << 16;
+const BITMAP_SIZE: usize = USHORT_RANGE >> 3;
+const HUF_ENCSIZE: usize = USHORT_RANGE + 1;
+const MAX_CODE_LENGTH: usize = 58;
+const SHORT_ZEROCODE_RUN: u64 = 59;
+const LONG_ZEROCODE_RUN: u64 = 63;
+const SHORTEST_LONG_RUN: usize = 6;
+const LONGEST_LONG_RUN: usize = 255 + SHORTEST_LONG_RUN;
+const A_OFFSET: i32 = 1 << 15;
+const MOD_MASK: i32 = (1 << 16) - 1;
+
+/// Compresses one uncompressed scanline block.
+///
+/// `channel_sizes` holds each channel's sample size in 16-bit words, in file channel order.
+pub(super) fn compress(raw: &[u8], width: usize, lines: usize, channel_sizes: &[usize]) -> Vec<u8> {
+    let (mut words, starts) = gather_channels(raw, width, lines, channel_sizes);
+
+    let mut bitmap = vec![0_u8; BITMAP_SIZE];
+    for &word in &words {
+        bitmap[usize::from(word >> 3)] |= 1 << (word & 7);
+    }
+    bitmap[0] &= !1;
+    let min_non_zero = bitmap.iter().position(|byte| *byte != 0);
+    let max_non_zero = bitmap.iter().rposition(|byte| *byte != 0);
+
+    let mut lut = vec![0_u16; USHORT_RANGE];
+    let mut next = 0_u16;
+    for (value, entry) in lut.iter_mut().enumerate() {
+        if value == 0 || bitmap[value >> 3] & (1 << (value & 7)) != 0 {
+            *entry = next;
+            next = next.wrapping_add(1);
+        }
+    }
+    let max_value = next.wrapping_sub(1);
+    for word in &mut words {
+        *word = lut[usize::from(*word)];
+    }
+
+    let mut out = Vec::new();
+    if let (Some(min), Some(max)) = (min_non_zero, max_non_zero) {
+        out.extend_from_slice(&word_at(min).to_le_bytes());
+        out.extend_from_slice(&word_at(max).to_le_bytes());
+        out.extend_from_slice(&bitmap[min..=max]);
+    } else {
+        out.extend_from_slice(&word_at(BITMAP_SIZE - 1).to_le_bytes());
+        out.extend_from_slice(&0_u16.to_le_bytes());
+    }
+
+    for_each_wavelet_plane(&starts, width, lines, channel_sizes, |start, size| {
+        wav2_encode(
+            &mut words[start..],
+            width,
+            size,
+            lines,
+            width * size,
+            max_value,
+        );
+    });
+
+    let length_at = out.len();
+    out.extend_from_slice(&[0; 4]);
+    huffman_compress(&words, &mut out);
+    let length = u32::try_from(out.len() - length_at - 4).unwrap_or(u32::MAX);
+    out[length_at..length_at + 4].copy_from_slice(&length.to_le_bytes());
+    out
+}
+
+/// Decompresses one PIZ block into `width * lines` samples per channel.
+pub(super) fn decompress(
+    data: &[u8],
+    width: usize,
+    lines: usize,
+    channel_sizes: &[usize],
+) -> Result<Vec<u8>, String> {
+    let read_u16 = |offset: usize| {
+        data.get(offset..offset + 2)
+            .map(|bytes| usize::from(u16::from_le_bytes([bytes[0], bytes[1]])))
+            .ok_or_else(|| "truncated PIZ block".to_string())
+    };
+    let min_non_zero = read_u16(0)?;
+    let max_non_zero = read_u16(2)?;
+    if max_non_zero >= BITMAP_SIZE {
+        return Err("PIZ bitmap range is out of bounds".to_string());
+    }
+
+    let mut bitmap = vec![0_u8; BITMAP_SIZE];
+    let mut cursor = 4;
+    if min_non_zero <= max_non_zero {
+        let length = max_non_zero - min_non_zero + 1;
+        let bytes = data
+            .get(cursor..cursor + length)
+            .ok_or("truncated PIZ bitmap")?;
+        bitmap[min_non_zero..=max_non_zero].copy_from_slice(bytes);
+        cursor += length;
+    }
+
+    let mut lut = vec![0_u16; USHORT_RANGE];
+    let mut count = 0;
+    for value in 0..USHORT_RANGE {
+        if value == 0 || bitmap[value >> 3] & (1 << (value & 7)) != 0 {
+            lut[count] = word_at(value);
+            count += 1;
+        }
+    }
+    let max_value = word_at(count - 1);
+
+    let length = data
+        .get(cursor..cursor + 4)
+        .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or("truncated PIZ block")?;
+    cursor += 4;
+    let length = usize::try_from(length).map_err(|_| "PIZ block is too large")?;
+    let compressed = data
+        .get(cursor..cursor + length)
+        .ok_or("truncated PIZ Huffman data")?;
+
+    let total = width * lines * channel_sizes.iter().sum::<usize>();
+    let mut words = huffman_decompress(compressed, total)?;
+
+    let starts = channel_starts(width, lines, channel_sizes);
+    for_each_wavelet_plane(&starts, width, lines, channel_sizes, |start, size| {
+        wav2_decode(
+            &mut words[start..],
+            width,
+            size,
+            lines,
+            width * size,
+            max_value,
+        );
+    });
+    for word in &mut words {
+        *word = lut[usize::from(*word)];
+    }
+
+    let mut raw = Vec::with_capacity(total * 2);
+    let mut cursors = starts;
+    for _ in 0..lines {
+        for (cursor, size) in cursors.iter_mut().zip(channel_sizes) {
+            let count = width * size;
+            for word in &words[*cursor..*cursor + count] {
+                raw.extend_from_slice(&word.to_le_bytes());
+            }
+            *cursor += count;
+        }
+    }
+    Ok(raw)
+}
+
+/// Converts an index that is known to be below `USHORT_RANGE` to a 16-bit word.
+fn word_at(index: usize) -> u16 {
+    u16::try_from(index).unwrap_or(u16::MAX)
+}
+
+fn channel_starts(width: usize, lines: usize, channel_sizes: &[usize]) -> Vec<usize> {
+    let mut offset = 0;
+    channel_sizes
+        .iter()
+        .map(|size| {
+            let start = offset;
+            offset += width * lines * size;
+            start
+        })
+        .collect()
+}
+
+/// Splits scanline-interleaved words into one contiguous plane per channel.
+fn gather_channels(
+    raw: &[u8],
+    width: usize,
+    lines: usize,
+    channel_sizes: &[usize],
+) -> (Vec<u16>, Vec<usize>) {
+    let starts = channel_starts(width, lines, channel_sizes);
+    let total = width * lines * channel_sizes.iter().sum::<usize>();
+    let mut words = vec![0_u16; total];
+    let mut source = raw
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]));
+    let mut cursors = starts.clone();
+    for _ in 0..lines {
+        for (cursor, size) in cursors.iter_mut().zip(channel_sizes) {
+            for word in &mut words[*cursor..*cursor + width * size] {
+                *word = source.next().unwrap_or(0);
+            }
+            *cursor += width * size;
+        }
+    }
+    (words, starts)
+}
+
+/// Calls `apply(start, size)` for each interleaved 16-bit plane of every channel.
+fn for_each_wavelet_plane(
+    starts: &[usize],
+    width: usize,
+    lines: usize,
+    channel_sizes: &[usize],
+    mut apply: impl FnMut(usize, usize),
+) {
+    if width == 0 || lines == 0 {
+        return;
+    }
+    for (start, &size) in starts.iter().zip(channel_sizes) {
+        for plane in 0..size {
+            apply(start + plane, size);
+        }
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn wenc14(a: u16, b: u16) -> (u16, u16) {
+    let a = i32::from(a.cast_signed());
+    let b = i32::from(b.cast_signed());
+    let mean = ((a + b) >> 1) as i16;
+    let difference = (a - b) as i16;
+    (mean.cast_unsigned(), difference.cast_unsigned())
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn wdec14(low: u16, high: u16) -> (u16, u16) {
+    let low = i32::from(low.cast_signed());
+    let high = i32::from(high.cast_signed());
+    let a = low + (high & 1) + (high >> 1);
+    (
+        (a as i16).cast_unsigned(),
+        ((a - high) as i16).cast_unsigned(),
+    )
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn wenc16(a: u16, b: u16) -> (u16, u16) {
+    let offset_a = (i32::from(a) + A_OFFSET) & MOD_MASK;
+    let mut mean = (offset_a + i32::from(b)) >> 1;
+    let difference = offset_a - i32::from(b);
+    if difference < 0 {
+        mean = (mean + A_OFFSET) & MOD_MASK;
+    }
+    (mean as u16, (difference & MOD_MASK) as u16)
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn wdec16(low: u16, high: u16) -> (u16, u16) {
+    let mean = i32::from(low);
+    let difference = i32::from(high);
+    let b = (mean - (difference >> 1)) & MOD_MASK;
+    let a = (difference + b - A_OFFSET) & MOD_MASK;
+    (a as u16, b as u16)
+}
+
+/// In-place 2D Haar wavelet encode of an `nx` by `ny` plane with strides `ox` and `oy`.
+fn wav2_encode(data: &mut [u16], nx: usize, ox: usize, ny: usize, oy: usize, max_value: u16) {
+    let encode = if max_value < (1 << 14) {
+        wenc14
+    } else {
+        wenc16
+    };
+    let n = nx.min(ny);
+    let mut p = 1;
+    let mut p2 = 2;
+    while p2 <= n {
+        let (oy1, oy2, ox1, ox2) = (oy * p, oy * p2, ox * p, ox * p2);
+        let end_y = oy * (ny - p2);
+        let mut py = 0;
+        while py <= end_y {
+            let end_x = py + ox * (nx - p2);
+            let mut px = py;
+            while px <= end_x {
+                let (p01, p10) = (px + ox1, px + oy1);
+                let p11 = p10 + ox1;
+                let (i00, i01) = encode(data[px], data[p01]);
+                let (i10, i11) = encode(data[p10], data[p11]);
+                (data[px], data[p10]) = encode(i00, i10);
+                (data[p01], data[p11]) = encode(i01, i11);
+                px += ox2;
+            }
+            if nx & p != 0 {
+                let p10 = px + oy1;
+                (data[px], data[p10]) = encode(data[px], data[p10]);
+            }
+            py += oy2;
+        }
+        if ny & p != 0 {
+            let end_x = py + ox * (nx - p2);
+            let mut px = py;
+            while px <= end_x {
+                let p01 = px + ox1;
+                (data[px], data[p01]) = encode(data[px], data[p01]);
+                px += ox2;
+            }
+        }
+        p = p2;
+        p2 <<= 1;
+    }
+}
+
+/// Inverts [`wav2_encode`].
+fn wav2_decode(data: &mut [u16], nx: usize, ox: usize, ny: usize, oy: usize, max_value: u16) {
+    let decode = if max_value < (1 << 14) {
+        wdec14
+    } else {
+        wdec16
+    };
+    let n = nx.min(ny);
+    let mut p = 1;
+    while p <= n {
+        p <<= 1;
+    }
+    p >>= 1;
+    let mut p2 = p;
+    p >>= 1;
+    while p >= 1 {
+        let (oy1, oy2, ox1, ox2) = (oy * p, oy * p2, ox * p, ox * p2);
+        let end_y = oy * (ny - p2);
+        let mut py = 0;
+        while py <= end_y {
+            let end_x = py + ox * (nx - p2);
+            let mut px = py;
+            while px <= end_x {
+                let (p01, p10) = (px + ox1, px + oy1);
+                let p11 = p10 + ox1;
+                let (i00, i10) = decode(data[px], data[p10]);
+                let (i01, i11) = decode(data[p01], data[p11]);
+                (data[px], data[p01]) = decode(i00, i01);
+                (data[p10], data[p11]) = decode(i10, i11);
+                px += ox2;
+            }
+            if nx & p != 0 {
+                let p10 = px + oy1;
+                (data[px], data[p10]) = decode(data[px], data[p10]);
+            }
+            py += oy2;
+        }
+        if ny & p != 0 {
+            let end_x = py + ox * (nx - p2);
+            let mut px = py;
+            while px <= end_x {
+                let p01 = px + ox1;
+                (data[px], data[p01]) = decode(data[px], data[p01]);
+                px += ox2;
+            }
+        }
+        p2 = p;
+        p >>= 1;
+    }
+}
+
+/// Most-significant-bit-first bit packer.
+struct BitWriter<'a> {
+    out: &'a mut Vec<u8>,
+    pending: u64,
+    pending_bits: u32,
+    written_bits: u64,
+}
+
+impl<'a> BitWriter<'a> {
+    fn new(out: &'a mut Vec<u8>) -> Self {
+        Self {
+            out,
+            pending: 0,
+            pending_bits: 0,
+            written_bits: 0,
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn write(&mut self, count: u32, value: u64) {
+        self.written_bits += u64::from(count);
+        let mut remaining = count;
+        while remaining > 0 {
+            let take = remaining.min(32);
+            remaining -= take;
+            let chunk = (value >> remaining) & ((1 << take) - 1);
+            self.pending = (self.pending << take) | chunk;
+            self.pending_bits += take;
+            while self.pending_bits >= 8 {
+                self.pending_bits -= 8;
+                self.out.push((self.pending >> self.pending_bits) as u8);
+            }
+            self.pending &= (1 << self.pending_bits) - 1;
+        }
+    }
+
+    fn write_code(&mut self, code: u64) {
+        self.write(code_length(code), code >> 6);
+    }
+
+    /// Flushes the final partial byte and returns the number of meaningful bits written.
+    #[allow(clippy::cast_possible_truncation)]
+    fn finish(self) -> u64 {
+        if self.pending_bits > 0 {
+            self.out
+                .push((self.pending << (8 - self.pending_bits)) as u8);
+        }
+        self.written_bits
+    }
+}
+
+/// Most-significant-bit-first bit reader.
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl BitReader<'_> {
+    fn read(&mut self, count: u32) -> Result<u64, String> {
+        let mut value = 0;
+        for _ in 0..count {
+            let byte = self
+                .data
+                .get(self.position >> 3)
+                .ok_or("truncated PIZ Huffman data")?;
+            value = (value << 1) | u64::from((byte >> (7 - (self.position & 7))) & 1);
+            self.position += 1;
+        }
+        Ok(value)
+    }
+
+    fn byte_position(&self) -> usize {
+        self.position.div_ceil(8)
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn code_length(code: u64) -> u32 {
+    (code & 63) as u32
+}
+
+/// Computes Huffman code lengths for every symbol with a non-zero frequency.
+fn code_lengths(frequencies: &[u64]) -> Vec<u64> {
+    let mut heap = BinaryHeap::new();
+    let mut parents = Vec::new();
+    let mut leaves = Vec::new();
+    for (symbol, &frequency) in frequencies.iter().enumerate() {
+        if frequency > 0 {
+            heap.push(Reverse((frequency, parents.len())));
+            leaves.push((symbol, parents.len()));
+            parents.push(usize::MAX);
+        }
+    }
+    while heap.len() > 1 {
+        let Some(Reverse((first_frequency, first))) = heap.pop() else {
+            break;
+        };
+        let Some(Reverse((second_frequency, second))) = heap.pop() else {
+            break;
+        };
+        let node = parents.len();
+        parents.push(usize::MAX);
+        parents[first] = node;
+        parents[second] = node;
+        heap.push(Reverse((first_frequency + second_frequency, node)));
+    }
+
+    let mut lengths = vec![0_u64; frequencies.len()];
+    for (symbol, leaf) in leaves {
+        let mut depth = 0;
+        let mut node = leaf;
+        while parents[node] != usize::MAX {
+            node = parents[node];
+            depth += 1;
+        }
+        lengths[symbol] = depth;
+    }
+    lengths
+}
+
+/// Replaces code lengths with `(code << 6) | length` canonical codes.
+///
+/// Shorter codes are numerically higher than longer codes, and codes of one length increase with
+/// the symbol value. Returns the first code assigned to each length.
+#[allow(clippy::cast_possible_truncation)]
+fn canonical_codes(codes: &mut [u64]) -> [u64; MAX_CODE_LENGTH + 1] {
+    let mut counts = [0_u64; MAX_CODE_LENGTH + 1];
+    for &length in codes.iter() {
+        counts[length as usize] += 1;
+    }
+    let mut next = [0_u64; MAX_CODE_LENGTH + 1];
+    let mut code = 0;
+    for length in (1..=MAX_CODE_LENGTH).rev() {
+        next[length] = code;
+        code = (code + counts[length]) >> 1;
+    }
+    let first = next;
+    for entry in codes.iter_mut() {
+        let length = *entry;
+        if length > 0 {
+            *entry = length | (next[length as usize] << 6);
+            next[length as usize] += 1;
+        }
+    }
+    first
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn huffman_compress(words: &[u16], out: &mut Vec<u8>) {
+    if words.is_empty() {
+        return;
+    }
+    let mut frequencies = vec![0_u64; HUF_ENCSIZE];
+    for &word in words {
+        frequencies[usize::from(word)] += 1;
+    }
+    let min_symbol = frequencies
+        .iter()
+        .position(|frequency| *frequency > 0)
+        .unwrap_or(0);
+    let run_symbol = frequencies
+        .iter()
+        .rposition(|frequency| *frequency > 0)
+        .unwrap_or(0)
+        + 1;
+    frequencies[run_symbol] = 1;
+
+    let mut codes = code_lengths(&frequencies);
+    canonical_codes(&mut codes);
+
+    let header_at = out.len();
+    out.extend_from_slice(&[0; 20]);
+    let table_at = out.len();
+    let mut table = BitWriter::new(out);
+    let mut symbol = min_symbol;
+    while symbol <= run_symbol {
+        let length = u64::from(code_length(codes[symbol]));
+        if length == 0 {
+            let mut run = 1;
+            while symbol < run_symbol
+                && run < LONGEST_LONG_RUN
+                && code_length(codes[symbol + 1]) == 0
+            {
+                symbol += 1;
+                run += 1;
+            }
+            if run >= SHORTEST_LONG_RUN {
+                table.write(6, LONG_ZEROCODE_RUN);
+                table.write(8, (run - SHORTEST_LONG_RUN) as u64);
+                symbol += 1;
+                continue;
+            }
+            if run >= 2 {
+                table.write(6, SHORT_ZEROCODE_RUN + run as u64 - 2);
+                symbol += 1;
+                continue;
+            }
+        }
+        table.write(6, length);
+        symbol += 1;
+    }
+    table.finish();
+    let table_length = out.len() - table_at;
+
+    let run_code = codes[run_symbol];
+    let mut data = BitWriter::new(out);
+    let mut send = |symbol: u16, repeats: u32| {
+        let code = codes[usize::from(symbol)];
+        if code_length(code) + code_length(run_code) + 8 < code_length(code) * repeats {
+            data.write_code(code);
+            data.write_code(run_code);
+            data.write(8, u64::from(repeats));
+        } else {
+            for _ in 0..=repeats {
+                data.write_code(code);
+            }
+        }
+    };
+    let mut current = words[0];
+    let mut repeats = 0;
+    for &word in &words[1..] {
+        if word == current && repeats < 255 {
+            repeats += 1;
+        } else {
+            send(current, repeats);
+            repeats = 0;
+        }
+        current = word;
+    }
+    send(current, repeats);
+    let bit_count = data.finish();
+
+    let header = [
+        min_symbol as u32,
+        run_symbol as u32,
+        table_length as u32,
+        bit_count as u32,
+        0,
+    ];
+    for (slot, value) in out[header_at..header_at + 20]
+        .chunks_exact_mut(4)
+        .zip(header)
+    {
+        slot.copy_from_slice(&value.to_le_bytes());
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn huffman_decompress(data: &[u8], expected: usize) -> Result<Vec<u16>, String> {
+    if data.is_empty() {
+        return if expected == 0 {
+            Ok(Vec::new())
+        } else {
+            Err("missing PIZ Huffman data".to_string())
+        };
+    }
+    let header = |index: usize| {
+        data.get(index * 4..index * 4 + 4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+            .ok_or_else(|| "truncated PIZ Huffman header".to_string())
+    };
+    let min_symbol = header(0)?;
+    let run_symbol = header(1)?;
+    let bit_count = header(3)?;
+    if min_symbol >= HUF_ENCSIZE || run_symbol >= HUF_ENCSIZE || min_symbol > run_symbol {
+        return Err("invalid PIZ Huffman table range".to_string());
+    }
+
+    let mut reader = BitReader {
+        data: &data[20..],
+        position: 0,
+    };
+    let mut codes = vec![0_u64; HUF_ENCSIZE];
+    let mut symbol = min_symbol;
+    while symbol <= run_symbol {
+        let length = reader.read(6)?;
+        let run = if length == LONG_ZEROCODE_RUN {
+            reader.read(8)? as usize + SHORTEST_LONG_RUN
+        } else if length >= SHORT_ZEROCODE_RUN {
+            (length - SHORT_ZEROCODE_RUN) as usize + 2
+        } else {
+            codes[symbol] = length;
+            symbol += 1;
+            continue;
+        };
+        if symbol + run > run_symbol + 1 {
+            return Err("PIZ Huffman table is too long".to_string());
+        }
+        symbol += run;
+    }
+    let first = canonical_codes(&mut codes);
+
+    let mut symbols_by_length = vec![Vec::new(); MAX_CODE_LENGTH + 1];
+    for (symbol, &code) in codes.iter().enumerate() {
+        let length = code_length(code) as usize;
+        if length > 0 {
+            symbols_by_length[length].push(symbol);
+        }
+    }
+
+    let body = &data[20 + reader.byte_position()..];
+    if bit_count.div_ceil(8) > body.len() {
+        return Err("truncated PIZ Huffman data".to_string());
+    }
+    let mut reader = BitReader {
+        data: body,
+        position: 0,
+    };
+    let mut words: Vec<u16> = Vec::with_capacity(expected);
+    while reader.position < bit_count {
+        let mut code = 0;
+        let mut length = 0;
+        let symbol = loop {
+            if reader.position >= bit_count || length == MAX_CODE_LENGTH {
+                return Err("invalid PIZ Huffman code".to_string());
+            }
+            code = (code << 1) | reader.read(1)?;
+            length += 1;
+            let symbols = &symbols_by_length[length];
+            if !symbols.is_empty() && code >= first[length] {
+                let index = (code - first[length]) as usize;
+                break *symbols.get(index).ok_or("invalid PIZ Huffman code")?;
+            }
+        };
+        if symbol == run_symbol {
+            let repeats = reader.read(8)? as usize;
+            let previous = *words.last().ok_or("PIZ run without a previous value")?;
+            if words.len() + repeats > expected {
+                return Err("PIZ Huffman data decodes to too many values".to_string());
+            }
+            words.extend(std::iter::repeat_n(previous, repeats));
+        } else if words.len() < expected {
+            words.push(symbol as u16);
+        } else {
+            return Err("PIZ Huffman data decodes to too many values".to_string());
+        }
+    }
+    if words.len() != expected {
+        return Err("PIZ Huffman data decodes to too few values".to_string());
+    }
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compress, decompress, wav2_decode, wav2_encode};
+
+    #[test]
+    fn wavelet_round_trips_both_value_ranges() {
+        for max_value in [1000_u16, u16::MAX] {
+            let original = (0_u16..35)
+                .map(|value| value.wrapping_mul(7919) % max_value.max(1))
+                .collect::<Vec<_>>();
+            let mut data = original.clone();
+            wav2_encode(&mut data, 7, 1, 5, 7, max_value);
+            assert_ne!(data, original);
+            wav2_decode(&mut data, 7, 1, 5, 7, max_value);
+            assert_eq!(data, original);
+        }
+    }
+
+    #[test]
+    #[allow(clippy::cast_possible_truncation)]
+    fn blocks_round_trip_with_mixed_channel_sizes() {
+        let (width, lines, sizes) = (9, 6, [1, 2, 1]);
+        let raw = (0..width * lines * 4 * 2)
+            .map(|index| {
+                if index % 5 == 0 {
+                    0
+                } else {
+                    (index * 37 % 251) as u8
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let compressed = compress(&raw, width, lines, &sizes);
+        let restored = decompress(&compressed, width, lines, &sizes).expect("decompress");
+
+        assert_eq!(restored, raw);
+    }
+
+    #[test]
+    fn constant_blocks_use_run_length_codes() {
+        let raw = vec![0x3c; 64 * 32 * 2];
+
+        let compressed = compress(&raw, 64, 32, &[1]);
+
+        assert!(compressed.len() < 64, "{} bytes", compressed.len());
+        assert_eq!(
+            decompress(&compressed, 64, 32, &[1]).expect("decompress"),
+            raw
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_blocks() {
+        let raw = (0..200_u8).collect::<Vec<_>>();
+        let compressed = compress(&raw, 10, 10, &[1]);
+
+        assert!(decompress(&compressed[..compressed.len() - 3], 10, 10, &[1]).is_err());
+        assert!(decompress(&[0, 0], 10, 10, &[1]).is_err());
+    }
+}
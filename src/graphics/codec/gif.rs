@@ -0,0 +1,943 @@
+//! Streaming `GIF89a` animation encoder.
+//!
+//! Every frame gets its own quantized local color table. After the first frame, only the bounding
+//! box of pixels that changed is written, and unchanged pixels inside that box are emitted as a
+//! transparent index so the previous frame shows through.
+
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+};
+
+use crate::graphics::{colors::Rgb, display::Canvas};
+
+const MAX_CODE: u16 = 4095;
+
+/// Palette reduction algorithm used for each frame.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum GifQuantizer {
+    /// Recursively splits the color box with the widest channel at its population median.
+    #[default]
+    MedianCut,
+    /// Builds an eight-level color octree and merges the least populated leaves.
+    Octree,
+}
+
+/// Error diffusion applied when mapping frame pixels to the palette.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum GifDithering {
+    /// Maps every pixel to its nearest palette entry.
+    #[default]
+    None,
+    /// Diffuses quantization error with the Floyd-Steinberg kernel.
+    FloydSteinberg,
+}
+
+/// Options for [`GifEncoder`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GifOptions {
+    loop_count: Option<u16>,
+    quantizer: GifQuantizer,
+    dithering: GifDithering,
+    max_colors: u16,
+    crop_unchanged: bool,
+}
+
+impl Default for GifOptions {
+    fn default() -> Self {
+        Self {
+            loop_count: Some(0),
+            quantizer: GifQuantizer::default(),
+            dithering: GifDithering::default(),
+            max_colors: 256,
+            crop_unchanged: true,
+        }
+    }
+}
+
+impl GifOptions {
+    /// Creates options that loop forever with median-cut quantization and frame-diff cropping.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the NETSCAPE loop count: `Some(0)` loops forever, `Some(n)` repeats `n` times, and
+    /// `None` omits the loop extension so viewers play the animation once.
+    #[must_use]
+    pub const fn loop_count(mut self, loop_count: Option<u16>) -> Self {
+        self.loop_count = loop_count;
+        self
+    }
+
+    /// Sets the palette quantizer.
+    #[must_use]
+    pub const fn quantizer(mut self, quantizer: GifQuantizer) -> Self {
+        self.quantizer = quantizer;
+        self
+    }
+
+    /// Sets the dithering mode.
+    #[must_use]
+    pub const fn dithering(mut self, dithering: GifDithering) -> Self {
+        self.dithering = dithering;
+        self
+    }
+
+    /// Sets the maximum palette size per frame, clamped to `2..=256`.
+    ///
+    /// Cropped frames reserve one entry for transparency.
+    #[must_use]
+    pub fn max_colors(mut self, max_colors: u16) -> Self {
+        self.max_colors = max_colors.clamp(2, 256);
+        self
+    }
+
+    /// Sets whether frames after the first only store the region that changed.
+    #[must_use]
+    pub const fn crop_unchanged(mut self, crop_unchanged: bool) -> Self {
+        self.crop_unchanged = crop_unchanged;
+        self
+    }
+}
+
+/// Streaming `GIF89a` writer that accepts one [`Canvas`] per frame.
+#[derive(Debug)]
+pub struct GifEncoder<W: Write> {
+    out: W,
+    width: u16,
+    height: u16,
+    options: GifOptions,
+    previous: Option<Vec<Rgb>>,
+    frames: usize,
+}
+
+impl<W: Write> GifEncoder<W> {
+    /// Writes the GIF header and loop extension for a `width` by `height` animation.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if a dimension is zero or larger than `u16::MAX`, or if writing fails.
+    pub fn new(mut out: W, width: u32, height: u32, options: GifOptions) -> io::Result<Self> {
+        let dimension = |value: u32| {
+            u16::try_from(value)
+                .ok()
+                .filter(|value| *value > 0)
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("GIF dimensions must be 1..=65535, got {width}x{height}"),
+                    )
+                })
+        };
+        let width = dimension(width)?;
+        let height = dimension(height)?;
+
+        out.write_all(b"GIF89a")?;
+        out.write_all(&width.to_le_bytes())?;
+        out.write_all(&height.to_le_bytes())?;
+        out.write_all(&[0, 0, 0])?;
+        if let Some(loop_count) = options.loop_count {
+            out.write_all(&[0x21, 0xff, 11])?;
+            out.write_all(b"NETSCAPE2.0")?;
+            out.write_all(&[3, 1])?;
+            out.write_all(&loop_count.to_le_bytes())?;
+            out.write_all(&[0])?;
+        }
+        Ok(Self {
+            out,
+            width,
+            height,
+            options,
+            previous: None,
+            frames: 0,
+        })
+    }
+
+    /// Returns the number of frames written so far.
+    #[must_use]
+    pub const fn frame_count(&self) -> usize {
+        self.frames
+    }
+
+    /// Quantizes and appends `canvas` as the next frame, shown for `delay_cs` centiseconds.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the canvas size differs from the animation size or writing fails.
+    pub fn add_frame(&mut self, canvas: &Canvas, delay_cs: u16) -> io::Result<()> {
+        if canvas.width() != u32::from(self.width) || canvas.height() != u32::from(self.height) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "GIF frame is {}x{} but the animation is {}x{}",
+                    canvas.width(),
+                    canvas.height(),
+                    self.width,
+                    self.height
+                ),
+            ));
+        }
+        let pixels = canvas.pixels();
+        let width = usize::from(self.width);
+        let region = match (&self.previous, self.options.crop_unchanged) {
+            (Some(previous), true) => changed_region(previous, pixels, width),
+            _ => Region {
+                left: 0,
+                top: 0,
+                width,
+                height: usize::from(self.height),
+            },
+        };
+        let transparent = self.options.crop_unchanged && self.previous.is_some();
+        let mask = match self.previous.as_ref().filter(|_| transparent) {
+            Some(previous) => region
+                .rows(pixels, width)
+                .zip(region.rows(previous, width))
+                .map(|(current, previous)| current != previous)
+                .collect::<Vec<_>>(),
+            None => vec![true; region.width * region.height],
+        };
+
+        let crop = region
+            .rows(pixels, width)
+            .enumerate()
+            .filter(|(index, _)| !transparent || mask[*index])
+            .map(|(_, pixel)| *pixel)
+            .collect::<Vec<_>>();
+        let colors = usize::from(self.options.max_colors) - usize::from(transparent);
+        let mut palette = match self.options.quantizer {
+            GifQuantizer::MedianCut => median_cut(&crop, colors),
+            GifQuantizer::Octree => octree(&crop, colors),
+        };
+        if palette.is_empty() {
+            palette.push(Rgb::BLACK);
+        }
+        let transparent_index = transparent.then(|| {
+            palette.push(Rgb::BLACK);
+            u8::try_from(palette.len() - 1).unwrap_or(u8::MAX)
+        });
+
+        let indices = map_to_palette(
+            &region.rows(pixels, width).copied().collect::<Vec<_>>(),
+            region.width,
+            &palette,
+            self.options.dithering,
+            transparent_index.map(|index| (index, mask.as_slice())),
+        );
+        self.write_frame(&region, &palette, &indices, transparent_index, delay_cs)?;
+        self.previous = Some(pixels.to_vec());
+        self.frames += 1;
+        Ok(())
+    }
+
+    fn write_frame(
+        &mut self,
+        region: &Region,
+        palette: &[Rgb],
+        indices: &[u8],
+        transparent_index: Option<u8>,
+        delay_cs: u16,
+    ) -> io::Result<()> {
+        let table_bits = palette_bits(palette.len());
+        let packed_gce = (1 << 2) | u8::from(transparent_index.is_some());
+        self.out.write_all(&[0x21, 0xf9, 4, packed_gce])?;
+        self.out.write_all(&delay_cs.to_le_bytes())?;
+        self.out
+            .write_all(&[transparent_index.unwrap_or(0), 0, 0x2c])?;
+        for value in [region.left, region.top, region.width, region.height] {
+            let value = u16::try_from(value).expect("frame region fits GIF dimensions");
+            self.out.write_all(&value.to_le_bytes())?;
+        }
+        self.out.write_all(&[0x80 | (table_bits - 1)])?;
+        for index in 0..1_usize << table_bits {
+            let color = palette.get(index).copied().unwrap_or(Rgb::BLACK);
+            self.out.write_all(&[color.red, color.green, color.blue])?;
+        }
+
+        let min_code_size = table_bits.max(2);
+        self.out.write_all(&[min_code_size])?;
+        for block in lzw_encode(indices, min_code_size).chunks(255) {
+            self.out
+                .write_all(&[u8::try_from(block.len()).expect("sub-block fits u8")])?;
+            self.out.write_all(block)?;
+        }
+        self.out.write_all(&[0])
+    }
+
+    /// Writes the GIF trailer and returns the underlying writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if no frames were added or writing fails.
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.frames == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "cannot finish a GIF with no frames",
+            ));
+        }
+        self.out.write_all(&[0x3b])?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Region {
+    left: usize,
+    top: usize,
+    width: usize,
+    height: usize,
+}
+
+impl Region {
+    fn rows<'a>(&self, pixels: &'a [Rgb], stride: usize) -> impl Iterator<Item = &'a Rgb> + 'a {
+        let Self {
+            left,
+            top,
+            width,
+            height,
+        } = *self;
+        (top..top + height).flat_map(move |y| &pixels[y * stride + left..y * stride + left + width])
+    }
+}
+
+fn changed_region(previous: &[Rgb], current: &[Rgb], width: usize) -> Region {
+    let mut min_x = usize::MAX;
+    let mut min_y = usize::MAX;
+    let mut max_x = 0;
+    let mut max_y = 0;
+    for (index, (old, new)) in previous.iter().zip(current).enumerate() {
+        if old != new {
+            let (x, y) = (index % width, index / width);
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+    }
+    if min_x == usize::MAX {
+        // Nothing changed; a single transparent pixel keeps the frame's delay.
+        return Region {
+            left: 0,
+            top: 0,
+            width: 1,
+            height: 1,
+        };
+    }
+    Region {
+        left: min_x,
+        top: min_y,
+        width: max_x - min_x + 1,
+        height: max_y - min_y + 1,
+    }
+}
+
+fn palette_bits(len: usize) -> u8 {
+    let mut bits = 1;
+    while (1 << bits) < len {
+        bits += 1;
+    }
+    bits
+}
+
+#[derive(Clone, Copy, Debug)]
+struct ColorBin {
+    color: [u8; 3],
+    count: u32,
+}
+
+fn histogram(pixels: &[Rgb]) -> Vec<ColorBin> {
+    let mut counts = HashMap::<[u8; 3], u32>::new();
+    for pixel in pixels {
+        *counts
+            .entry([pixel.red, pixel.green, pixel.blue])
+            .or_default() += 1;
+    }
+    let mut bins = counts
+        .into_iter()
+        .map(|(color, count)| ColorBin { color, count })
+        .collect::<Vec<_>>();
+    bins.sort_unstable_by_key(|bin| bin.color);
+    bins
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn weighted_average(bins: &[ColorBin]) -> Rgb {
+    let mut sums = [0_u64; 3];
+    let mut total = 0_u64;
+    for bin in bins {
+        for (sum, channel) in sums.iter_mut().zip(bin.color) {
+            *sum += u64::from(channel) * u64::from(bin.count);
+        }
+        total += u64::from(bin.count);
+    }
+    let total = total.max(1);
+    let channel = |sum: u64| ((sum + total / 2) / total) as u8;
+    Rgb::new(channel(sums[0]), channel(sums[1]), channel(sums[2]))
+}
+
+fn median_cut(pixels: &[Rgb], max_colors: usize) -> Vec<Rgb> {
+    let bins = histogram(pixels);
+    if bins.len() <= max_colors {
+        return bins
+            .iter()
+            .map(|bin| Rgb::new(bin.color[0], bin.color[1], bin.color[2]))
+            .collect();
+    }
+
+    let range = |bins: &[ColorBin]| {
+        (0..3)
+            .map(|channel| {
+                let (min, max) = bins.iter().fold((u8::MAX, 0), |(min, max), bin| {
+                    (min.min(bin.color[channel]), max.max(bin.color[channel]))
+                });
+                (max - min, channel)
+            })
+            .max()
+            .unwrap_or((0, 0))
+    };
+    let mut boxes = vec![bins];
+    while boxes.len() < max_colors {
+        let Some((index, channel)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, bins)| bins.len() > 1)
+            .map(|(index, bins)| {
+                let (spread, channel) = range(bins);
+                let population = bins.iter().map(|bin| u64::from(bin.count)).sum::<u64>();
+                (u64::from(spread) * population, index, channel)
+            })
+            .max()
+            .map(|(_, index, channel)| (index, channel))
+        else {
+            break;
+        };
+        let mut bins = boxes.swap_remove(index);
+        bins.sort_unstable_by_key(|bin| bin.color[channel]);
+        let half = bins.iter().map(|bin| u64::from(bin.count)).sum::<u64>() / 2;
+        let mut running = 0;
+        let split = bins
+            .iter()
+            .position(|bin| {
+                running += u64::from(bin.count);
+                running >= half
+            })
+            .map_or(1, |position| position + 1)
+            .clamp(1, bins.len() - 1);
+        let upper = bins.split_off(split);
+        boxes.push(bins);
+        boxes.push(upper);
+    }
+    boxes.iter().map(|bins| weighted_average(bins)).collect()
+}
+
+#[derive(Clone, Debug, Default)]
+struct OctreeNode {
+    children: [Option<usize>; 8],
+    sums: [u64; 3],
+    count: u64,
+    leaf: bool,
+}
+
+fn octree(pixels: &[Rgb], max_colors: usize) -> Vec<Rgb> {
+    const DEPTH: usize = 8;
+    let mut nodes = vec![OctreeNode::default()];
+    let mut levels = vec![Vec::<usize>::new(); DEPTH];
+    levels[0].push(0);
+    let mut leaves = 0_usize;
+    for bin in histogram(pixels) {
+        let mut node = 0;
+        for level in 0..DEPTH {
+            let shift = 7 - level;
+            let child = usize::from((bin.color[0] >> shift) & 1) << 2
+                | usize::from((bin.color[1] >> shift) & 1) << 1
+                | usize::from((bin.color[2] >> shift) & 1);
+            node = if let Some(next) = nodes[node].children[child] {
+                next
+            } else {
+                let next = nodes.len();
+                nodes.push(OctreeNode {
+                    leaf: level + 1 == DEPTH,
+                    ..OctreeNode::default()
+                });
+                nodes[node].children[child] = Some(next);
+                if level + 1 == DEPTH {
+                    leaves += 1;
+                } else {
+                    levels[level + 1].push(next);
+                }
+                next
+            };
+        }
+        for (sum, channel) in nodes[node].sums.iter_mut().zip(bin.color) {
+            *sum += u64::from(channel) * u64::from(bin.count);
+        }
+        nodes[node].count += u64::from(bin.count);
+    }
+
+    // Merge the least populated deepest internal nodes until the leaf count fits. Every node at
+    // `level` only has leaf children once all deeper levels have been processed.
+    for level in (0..DEPTH).rev() {
+        if leaves <= max_colors {
+            break;
+        }
+        let mut candidates = levels[level]
+            .iter()
+            .copied()
+            .filter(|&node| !nodes[node].leaf)
+            .map(|node| (subtree_count(&nodes, node), node))
+            .collect::<Vec<_>>();
+        candidates.sort_unstable();
+        for (_, node) in candidates {
+            if leaves <= max_colors {
+                break;
+            }
+            let children = nodes[node].children;
+            let mut merged = 0;
+            for child in children.into_iter().flatten() {
+                let child_node = nodes[child].clone();
+                for (sum, child_sum) in nodes[node].sums.iter_mut().zip(child_node.sums) {
+                    *sum += child_sum;
+                }
+                nodes[node].count += child_node.count;
+                merged += 1;
+            }
+            nodes[node].children = [None; 8];
+            nodes[node].leaf = true;
+            leaves = leaves + 1 - merged;
+        }
+    }
+
+    let mut palette = Vec::with_capacity(leaves);
+    let mut stack = vec![0];
+    while let Some(node) = stack.pop() {
+        let current = &nodes[node];
+        if current.leaf {
+            let count = current.count.max(1);
+            let channel = |sum: u64| u8::try_from((sum + count / 2) / count).unwrap_or(u8::MAX);
+            palette.push(Rgb::new(
+                channel(current.sums[0]),
+                channel(current.sums[1]),
+                channel(current.sums[2]),
+            ));
+        } else {
+            stack.extend(current.children.iter().flatten());
+        }
+    }
+    palette.truncate(max_colors);
+    palette
+}
+
+/// Total pixel count held by a node's direct children.
+fn subtree_count(nodes: &[OctreeNode], node: usize) -> u64 {
+    nodes[node]
+        .children
+        .iter()
+        .flatten()
+        .map(|&child| nodes[child].count)
+        .sum()
+}
+
+fn nearest(palette: &[Rgb], red: i32, green: i32, blue: i32) -> u8 {
+    let mut best = (i32::MAX, 0);
+    for (index, color) in palette.iter().enumerate() {
+        let dr = red - i32::from(color.red);
+        let dg = green - i32::from(color.green);
+        let db = blue - i32::from(color.blue);
+        let distance = 2 * dr * dr + 4 * dg * dg + 3 * db * db;
+        if distance < best.0 {
+            best = (distance, index);
+        }
+    }
+    u8::try_from(best.1).expect("palette fits u8 indices")
+}
+
+fn map_to_palette(
+    pixels: &[Rgb],
+    width: usize,
+    palette: &[Rgb],
+    dithering: GifDithering,
+    transparency: Option<(u8, &[bool])>,
+) -> Vec<u8> {
+    // The transparent entry is appended last and must never be chosen for a visible pixel.
+    let visible = &palette[..palette.len() - usize::from(transparency.is_some())];
+    let mut cache = HashMap::<(i32, i32, i32), u8>::new();
+    let mut lookup = |red: i32, green: i32, blue: i32| {
+        *cache
+            .entry((red, green, blue))
+            .or_insert_with(|| nearest(visible, red, green, blue))
+    };
+    let is_transparent = |index: usize| transparency.is_some_and(|(_, mask)| !mask[index]);
+    let transparent_index = transparency.map_or(0, |(index, _)| index);
+
+    match dithering {
+        GifDithering::None => pixels
+            .iter()
+            .enumerate()
+            .map(|(index, pixel)| {
+                if is_transparent(index) {
+                    transparent_index
+                } else {
+                    lookup(
+                        i32::from(pixel.red),
+                        i32::from(pixel.green),
+                        i32::from(pixel.blue),
+                    )
+                }
+            })
+            .collect(),
+        GifDithering::FloydSteinberg => {
+            let mut error = vec![[0_i32; 3]; (width + 2) * 2];
+            let mut indices = Vec::with_capacity(pixels.len());
+            for (row_index, row) in pixels.chunks(width).enumerate() {
+                let (current, next) = error.split_at_mut(width + 2);
+                for (x, pixel) in row.iter().enumerate() {
+                    let index = row_index * width + x;
+                    if is_transparent(index) {
+                        indices.push(transparent_index);
+                        continue;
+                    }
+                    let target = [
+                        (i32::from(pixel.red) + current[x + 1][0] / 16).clamp(0, 255),
+                        (i32::from(pixel.green) + current[x + 1][1] / 16).clamp(0, 255),
+                        (i32::from(pixel.blue) + current[x + 1][2] / 16).clamp(0, 255),
+                    ];
+                    let chosen = lookup(target[0] & !3, target[1] & !3, target[2] & !3);
+                    indices.push(chosen);
+                    let color = visible[usize::from(chosen)];
+                    let residual = [
+                        target[0] - i32::from(color.red),
+                        target[1] - i32::from(color.green),
+                        target[2] - i32::from(color.blue),
+                    ];
+                    for channel in 0..3 {
+                        current[x + 2][channel] += residual[channel] * 7;
+                        next[x][channel] += residual[channel] * 3;
+                        next[x + 1][channel] += residual[channel] * 5;
+                        next[x + 2][channel] += residual[channel];
+                    }
+                }
+                current.copy_from_slice(next);
+                next.fill([0; 3]);
+            }
+            indices
+        }
+    }
+}
+
+/// Packs variable-width LZW codes least-significant bit first.
+struct CodeWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    count: u8,
+}
+
+impl CodeWriter {
+    fn write(&mut self, code: u16, width: u8) {
+        self.buffer |= u32::from(code) << self.count;
+        self.count += width;
+        while self.count >= 8 {
+            self.bytes.push(self.buffer.to_le_bytes()[0]);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.buffer.to_le_bytes()[0]);
+        }
+        self.bytes
+    }
+}
+
+fn lzw_encode(indices: &[u8], min_code_size: u8) -> Vec<u8> {
+    let clear = 1_u16 << min_code_size;
+    let end = clear + 1;
+    let mut writer = CodeWriter {
+        bytes: Vec::new(),
+        buffer: 0,
+        count: 0,
+    };
+    let mut table = HashMap::<(u16, u8), u16>::with_capacity(4096);
+    let mut width = min_code_size + 1;
+    let mut next_code = end + 1;
+    writer.write(clear, width);
+
+    let mut iter = indices.iter();
+    let Some(&first) = iter.next() else {
+        writer.write(end, width);
+        return writer.finish();
+    };
+    let mut prefix = u16::from(first);
+    for &index in iter {
+        if let Some(&code) = table.get(&(prefix, index)) {
+            prefix = code;
+            continue;
+        }
+        writer.write(prefix, width);
+        if next_code <= MAX_CODE {
+            table.insert((prefix, index), next_code);
+            if next_code == 1 << width && width < 12 {
+                width += 1;
+            }
+            next_code += 1;
+        } else {
+            writer.write(clear, width);
+            table.clear();
+            width = min_code_size + 1;
+            next_code = end + 1;
+        }
+        prefix = u16::from(index);
+    }
+    writer.write(prefix, width);
+    writer.write(end, width);
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GifDithering, GifEncoder, GifOptions, GifQuantizer, lzw_encode};
+    use crate::graphics::{colors::Rgb, display::Canvas};
+
+    /// Minimal GIF reader used to check the encoder: returns composited RGB frames and delays.
+    fn decode_frames(bytes: &[u8]) -> (Option<u16>, Vec<(u16, Vec<Rgb>)>) {
+        assert_eq!(&bytes[..6], b"GIF89a");
+        let width = usize::from(u16::from_le_bytes([bytes[6], bytes[7]]));
+        let height = usize::from(u16::from_le_bytes([bytes[8], bytes[9]]));
+        assert_eq!(bytes[10] & 0x80, 0, "encoder writes no global color table");
+        let mut cursor = 13;
+        let mut canvas = vec![Rgb::BLACK; width * height];
+        let mut frames = Vec::new();
+        let mut loop_count = None;
+        let mut delay = 0;
+        let mut transparent = None;
+        loop {
+            match bytes[cursor] {
+                0x21 => {
+                    let label = bytes[cursor + 1];
+                    cursor += 2;
+                    if label == 0xf9 {
+                        delay = u16::from_le_bytes([bytes[cursor + 2], bytes[cursor + 3]]);
+                        transparent = (bytes[cursor + 1] & 1 == 1).then_some(bytes[cursor + 4]);
+                    } else if label == 0xff && &bytes[cursor + 1..cursor + 12] == b"NETSCAPE2.0" {
+                        loop_count =
+                            Some(u16::from_le_bytes([bytes[cursor + 14], bytes[cursor + 15]]));
+                    }
+                    while bytes[cursor] != 0 {
+                        cursor += usize::from(bytes[cursor]) + 1;
+                    }
+                    cursor += 1;
+                }
+                0x2c => {
+                    let field = |offset: usize| {
+                        usize::from(u16::from_le_bytes([
+                            bytes[cursor + offset],
+                            bytes[cursor + offset + 1],
+                        ]))
+                    };
+                    let (left, top, frame_width, frame_height) =
+                        (field(1), field(3), field(5), field(7));
+                    let packed = bytes[cursor + 9];
+                    assert_eq!(packed & 0x80, 0x80, "frames carry a local color table");
+                    cursor += 10;
+                    let table_len = 1 << ((packed & 7) + 1);
+                    let palette = bytes[cursor..cursor + table_len * 3]
+                        .chunks_exact(3)
+                        .map(|rgb| Rgb::new(rgb[0], rgb[1], rgb[2]))
+                        .collect::<Vec<_>>();
+                    cursor += table_len * 3;
+                    let min_code_size = bytes[cursor];
+                    cursor += 1;
+                    let mut data = Vec::new();
+                    while bytes[cursor] != 0 {
+                        let len = usize::from(bytes[cursor]);
+                        data.extend_from_slice(&bytes[cursor + 1..cursor + 1 + len]);
+                        cursor += len + 1;
+                    }
+                    cursor += 1;
+                    let indices = lzw_decode(&data, min_code_size);
+                    assert_eq!(indices.len(), frame_width * frame_height);
+                    for (offset, &index) in indices.iter().enumerate() {
+                        if Some(index) == transparent {
+                            continue;
+                        }
+                        let x = left + offset % frame_width;
+                        let y = top + offset / frame_width;
+                        canvas[y * width + x] = palette[usize::from(index)];
+                    }
+                    frames.push((delay, canvas.clone()));
+                }
+                0x3b => return (loop_count, frames),
+                other => panic!("unexpected GIF block {other:#x}"),
+            }
+        }
+    }
+
+    fn lzw_decode(data: &[u8], min_code_size: u8) -> Vec<u8> {
+        let clear = 1_usize << min_code_size;
+        let end = clear + 1;
+        let mut width = u32::from(min_code_size) + 1;
+        let mut table: Vec<Vec<u8>> = Vec::new();
+        let reset = |table: &mut Vec<Vec<u8>>| {
+            table.clear();
+            table.extend((0..clear).map(|index| vec![u8::try_from(index).unwrap()]));
+            table.push(Vec::new());
+            table.push(Vec::new());
+        };
+        reset(&mut table);
+        let mut out = Vec::new();
+        let mut previous: Option<Vec<u8>> = None;
+        let mut bit = 0_usize;
+        loop {
+            let mut code = 0_usize;
+            for offset in 0..width as usize {
+                let position = bit + offset;
+                if data[position / 8] >> (position % 8) & 1 == 1 {
+                    code |= 1 << offset;
+                }
+            }
+            bit += width as usize;
+            if code == clear {
+                reset(&mut table);
+                width = u32::from(min_code_size) + 1;
+                previous = None;
+                continue;
+            }
+            if code == end {
+                return out;
+            }
+            let entry = if code < table.len() {
+                table[code].clone()
+            } else {
+                let mut entry = previous.clone().expect("KwKwK code needs a previous entry");
+                entry.push(entry[0]);
+                entry
+            };
+            out.extend_from_slice(&entry);
+            if let Some(mut previous) = previous {
+                previous.push(entry[0]);
+                table.push(previous);
+                if table.len() == 1 << width && width < 12 {
+                    width += 1;
+                }
+            }
+            previous = Some(entry);
+        }
+    }
+
+    fn gradient(width: u32, height: u32, shift: u32) -> Canvas {
+        Canvas::from_fn(width, height, |x, y| {
+            Rgb::new(
+                u8::try_from((x * 255 / width + shift) % 256).unwrap(),
+                u8::try_from(y * 255 / height).unwrap(),
+                u8::try_from((x + y) % 4 * 20).unwrap(),
+            )
+        })
+    }
+
+    fn encode(frames: &[Canvas], options: GifOptions) -> Vec<u8> {
+        let mut encoder =
+            GifEncoder::new(Vec::new(), frames[0].width(), frames[0].height(), options).unwrap();
+        for (index, frame) in frames.iter().enumerate() {
+            encoder
+                .add_frame(frame, u16::try_from(index + 2).unwrap())
+                .unwrap();
+        }
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn lzw_round_trips_long_and_repetitive_streams() {
+        let mut indices = Vec::new();
+        for index in 0..20_000_u32 {
+            indices.push(u8::try_from(index * 7 % 13).unwrap());
+            indices.push(3);
+        }
+        assert_eq!(lzw_decode(&lzw_encode(&indices, 4), 4), indices);
+        assert_eq!(lzw_decode(&lzw_encode(&[1, 1, 1, 1, 1], 2), 2), vec![1; 5]);
+    }
+
+    #[test]
+    fn few_color_frames_round_trip_exactly_with_delays_and_loop() {
+        let first = Canvas::from_fn(9, 7, |x, y| {
+            if (x + y) % 2 == 0 {
+                Rgb::RED
+            } else {
+                Rgb::BLUE
+            }
+        });
+        let mut second = first.clone();
+        second.plot(&Rgb::GREEN, 3, 2);
+        second.plot(&Rgb::WHITE, 5, 4);
+
+        for quantizer in [GifQuantizer::MedianCut, GifQuantizer::Octree] {
+            let (loop_count, frames) = decode_frames(&encode(
+                &[first.clone(), second.clone(), second.clone()],
+                GifOptions::new().loop_count(Some(3)).quantizer(quantizer),
+            ));
+
+            assert_eq!(loop_count, Some(3));
+            assert_eq!(frames.len(), 3);
+            assert_eq!(frames[0], (2, first.pixels().to_vec()));
+            assert_eq!(frames[1], (3, second.pixels().to_vec()));
+            assert_eq!(frames[2], (4, second.pixels().to_vec()));
+        }
+    }
+
+    #[test]
+    fn unchanged_regions_are_cropped_after_the_first_frame() {
+        let first = gradient(64, 48, 0);
+        let mut second = first.clone();
+        second.plot(&Rgb::WHITE, 10, 10);
+
+        let cropped = encode(&[first.clone(), second.clone()], GifOptions::new());
+        let full = encode(&[first, second], GifOptions::new().crop_unchanged(false));
+
+        assert!(cropped.len() < full.len() * 3 / 4);
+    }
+
+    #[test]
+    fn quantizers_and_dithering_stay_close_to_the_source() {
+        let frames = [gradient(80, 40, 0), gradient(80, 40, 40)];
+        for quantizer in [GifQuantizer::MedianCut, GifQuantizer::Octree] {
+            for dithering in [GifDithering::None, GifDithering::FloydSteinberg] {
+                let options = GifOptions::new()
+                    .quantizer(quantizer)
+                    .dithering(dithering)
+                    .max_colors(64)
+                    .loop_count(None);
+                let (loop_count, decoded) = decode_frames(&encode(&frames, options));
+                assert_eq!(loop_count, None);
+                for ((_, pixels), source) in decoded.iter().zip(&frames) {
+                    let error = pixels
+                        .iter()
+                        .zip(source.pixels())
+                        .map(|(a, b)| {
+                            u64::from(a.red.abs_diff(b.red))
+                                + u64::from(a.green.abs_diff(b.green))
+                                + u64::from(a.blue.abs_diff(b.blue))
+                        })
+                        .sum::<u64>()
+                        / pixels.len() as u64;
+                    assert!(error < 50, "{quantizer:?}/{dithering:?} mean error {error}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_mismatched_frames_and_empty_animations() {
+        let mut encoder = GifEncoder::new(Vec::new(), 4, 4, GifOptions::new()).unwrap();
+        assert!(
+            encoder
+                .add_frame(&Canvas::new(3, 4, Rgb::BLACK), 2)
+                .is_err()
+        );
+        assert!(encoder.finish().is_err());
+        assert!(GifEncoder::new(Vec::new(), 0, 4, GifOptions::new()).is_err());
+        assert!(GifEncoder::new(Vec::new(), 70_000, 4, GifOptions::new()).is_err());
+    }
+}
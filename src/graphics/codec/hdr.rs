@@ -0,0 +1,426 @@
+//! Native Radiance RGBE (`.hdr`) and portable float-map (`.pfm`) decoding.
+//!
+//! Both readers produce an [`HdrImage`] with unclamped linear radiance and the top row first.
+//! The Radiance reader accepts flat, old-style, and adaptive run-length encoded scanlines in the
+//! standard `-Y height +X width` or bottom-up `+Y height +X width` orientation. The PFM reader
+//! accepts color (`PF`) and grayscale (`Pf`) maps in either byte order. The matching writers are
+//! [`HdrImage::save_radiance_hdr`] and [`HdrImage::save_pfm`].
+
+use std::{fs, io, path::Path};
+
+use crate::graphics::{
+    colors::LinearRgb,
+    display::{Canvas, HdrImage},
+};
+
+/// Largest decoded image accepted by the readers, in pixels.
+const MAX_PIXELS: u64 = 1 << 28;
+
+/// Reads a Radiance RGBE `.hdr` file.
+///
+/// # Errors
+///
+/// Returns an I/O error if the file cannot be read, [`io::ErrorKind::InvalidData`] if it is
+/// malformed, or [`io::ErrorKind::Unsupported`] for XYZE data or rotated orientations.
+pub fn read_radiance_hdr(path: impl AsRef<Path>) -> io::Result<HdrImage> {
+    decode_radiance_hdr(&fs::read(path)?)
+}
+
+/// Reads a portable float-map `.pfm` file.
+///
+/// # Errors
+///
+/// Returns an I/O error if the file cannot be read or [`io::ErrorKind::InvalidData`] if it is
+/// malformed.
+pub fn read_pfm(path: impl AsRef<Path>) -> io::Result<HdrImage> {
+    decode_pfm(&fs::read(path)?)
+}
+
+/// Decodes Radiance RGBE data from memory.
+///
+/// # Errors
+///
+/// Returns [`io::ErrorKind::InvalidData`] if the data is malformed or
+/// [`io::ErrorKind::Unsupported`] for XYZE data or rotated orientations.
+pub fn decode_radiance_hdr(bytes: &[u8]) -> io::Result<HdrImage> {
+    let mut cursor = 0;
+    let magic = read_line(bytes, &mut cursor)?;
+    if !magic.starts_with("#?") {
+        return Err(invalid("missing `#?` Radiance signature"));
+    }
+    loop {
+        let line = read_line(bytes, &mut cursor)?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=")
+            && format.trim() != "32-bit_rle_rgbe"
+        {
+            return Err(unsupported(format!(
+                "Radiance pixel format `{}`",
+                format.trim()
+            )));
+        }
+    }
+
+    let resolution = read_line(bytes, &mut cursor)?;
+    let fields = resolution.split_whitespace().collect::<Vec<_>>();
+    let (bottom_up, height, width) = match fields.as_slice() {
+        [y_axis @ ("-Y" | "+Y"), height, "+X", width] => (
+            *y_axis == "+Y",
+            parse_dimension(height)?,
+            parse_dimension(width)?,
+        ),
+        _ => {
+            return Err(unsupported(format!(
+                "Radiance resolution line `{resolution}`"
+            )));
+        }
+    };
+    check_size(width, height)?;
+
+    let row_len = width as usize;
+    let mut pixels = Vec::with_capacity(Canvas::pixel_count(width, height));
+    let mut scanline = vec![[0_u8; 4]; row_len];
+    for _ in 0..height {
+        read_rgbe_scanline(bytes, &mut cursor, &mut scanline)?;
+        pixels.extend(scanline.iter().copied().map(decode_rgbe));
+    }
+    if bottom_up {
+        flip_rows(&mut pixels, row_len);
+    }
+    Ok(HdrImage::from_pixels(width, height, pixels))
+}
+
+/// Decodes portable float-map data from memory.
+///
+/// # Errors
+///
+/// Returns [`io::ErrorKind::InvalidData`] if the data is malformed.
+pub fn decode_pfm(bytes: &[u8]) -> io::Result<HdrImage> {
+    let mut cursor = 0;
+    let channels = match next_token(bytes, &mut cursor)? {
+        "PF" => 3,
+        "Pf" => 1,
+        other => return Err(invalid(format!("unknown PFM signature `{other}`"))),
+    };
+    let width = parse_dimension(next_token(bytes, &mut cursor)?)?;
+    let height = parse_dimension(next_token(bytes, &mut cursor)?)?;
+    let scale = next_token(bytes, &mut cursor)?;
+    let scale = scale
+        .parse::<f64>()
+        .ok()
+        .filter(|scale| scale.is_finite() && *scale != 0.0)
+        .ok_or_else(|| invalid(format!("invalid PFM scale `{scale}`")))?;
+    if !bytes.get(cursor).is_some_and(u8::is_ascii_whitespace) {
+        return Err(invalid("missing PFM data separator"));
+    }
+    cursor += 1;
+    check_size(width, height)?;
+
+    let little_endian = scale < 0.0;
+    let row_len = width as usize;
+    let pixel_count = Canvas::pixel_count(width, height);
+    let data = &bytes[cursor..];
+    if data.len() < pixel_count * channels * 4 {
+        return Err(invalid("PFM data ends early"));
+    }
+    let values = data[..pixel_count * channels * 4]
+        .chunks_exact(4)
+        .map(|chunk| {
+            let bytes = [chunk[0], chunk[1], chunk[2], chunk[3]];
+            f64::from(if little_endian {
+                f32::from_le_bytes(bytes)
+            } else {
+                f32::from_be_bytes(bytes)
+            })
+        })
+        .collect::<Vec<_>>();
+    let mut pixels = values
+        .chunks_exact(channels)
+        .map(|texel| match *texel {
+            [red, green, blue] => LinearRgb::new(red, green, blue),
+            _ => LinearRgb::new(texel[0], texel[0], texel[0]),
+        })
+        .collect::<Vec<_>>();
+    // PFM stores the bottom row first.
+    flip_rows(&mut pixels, row_len);
+    Ok(HdrImage::from_pixels(width, height, pixels))
+}
+
+fn read_rgbe_scanline(
+    bytes: &[u8],
+    cursor: &mut usize,
+    scanline: &mut [[u8; 4]],
+) -> io::Result<()> {
+    let width = scanline.len();
+    let header = bytes.get(*cursor..*cursor + 4);
+    if (8..0x8000).contains(&width)
+        && let Some(&[2, 2, high, low]) = header
+        && high & 0x80 == 0
+    {
+        if usize::from(high) << 8 | usize::from(low) != width {
+            return Err(invalid("Radiance scanline width does not match the image"));
+        }
+        *cursor += 4;
+        return read_adaptive_rle_scanline(bytes, cursor, scanline);
+    }
+
+    let mut x = 0;
+    let mut shift = 0_u32;
+    while x < width {
+        let pixel = take::<4>(bytes, cursor)?;
+        if pixel[..3] == [1, 1, 1] {
+            // Old-style run: repeat the previous pixel, with counts accumulating in base 256.
+            let previous = x
+                .checked_sub(1)
+                .map(|index| scanline[index])
+                .ok_or_else(|| invalid("Radiance run before the first pixel"))?;
+            if pixel[3] == 0 || shift >= usize::BITS {
+                return Err(invalid("Radiance run has an invalid count"));
+            }
+            let count = usize::from(pixel[3]) << shift;
+            if x + count > width {
+                return Err(invalid("Radiance run overflows the scanline"));
+            }
+            scanline[x..x + count].fill(previous);
+            x += count;
+            shift += 8;
+        } else {
+            scanline[x] = pixel;
+            x += 1;
+            shift = 0;
+        }
+    }
+    Ok(())
+}
+
+fn read_adaptive_rle_scanline(
+    bytes: &[u8],
+    cursor: &mut usize,
+    scanline: &mut [[u8; 4]],
+) -> io::Result<()> {
+    let width = scanline.len();
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let [count] = take::<1>(bytes, cursor)?;
+            if count > 128 {
+                let count = usize::from(count - 128);
+                let [value] = take::<1>(bytes, cursor)?;
+                if x + count > width {
+                    return Err(invalid("Radiance run overflows the scanline"));
+                }
+                for pixel in &mut scanline[x..x + count] {
+                    pixel[channel] = value;
+                }
+                x += count;
+            } else {
+                let count = usize::from(count);
+                if count == 0 || x + count > width {
+                    return Err(invalid("invalid Radiance literal run length"));
+                }
+                let values = bytes
+                    .get(*cursor..*cursor + count)
+                    .ok_or_else(|| invalid("Radiance data ends early"))?;
+                for (pixel, value) in scanline[x..x + count].iter_mut().zip(values) {
+                    pixel[channel] = *value;
+                }
+                *cursor += count;
+                x += count;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn decode_rgbe([red, green, blue, exponent]: [u8; 4]) -> LinearRgb {
+    if exponent == 0 {
+        return LinearRgb::new(0.0, 0.0, 0.0);
+    }
+    let scale = 2.0_f64.powi(i32::from(exponent) - (128 + 8));
+    let channel = |value: u8| (f64::from(value) + 0.5) * scale;
+    LinearRgb::new(channel(red), channel(green), channel(blue))
+}
+
+fn flip_rows(pixels: &mut [LinearRgb], row_len: usize) {
+    if row_len == 0 {
+        return;
+    }
+    let rows = pixels.len() / row_len;
+    for row in 0..rows / 2 {
+        let (top, bottom) = pixels.split_at_mut((rows - 1 - row) * row_len);
+        top[row * row_len..(row + 1) * row_len].swap_with_slice(&mut bottom[..row_len]);
+    }
+}
+
+fn read_line<'a>(bytes: &'a [u8], cursor: &mut usize) -> io::Result<&'a str> {
+    let rest = &bytes[*cursor..];
+    let end = rest
+        .iter()
+        .position(|byte| *byte == b'\n')
+        .ok_or_else(|| invalid("Radiance header ends early"))?;
+    *cursor += end + 1;
+    std::str::from_utf8(&rest[..end])
+        .map(|line| line.trim_end_matches('\r'))
+        .map_err(|_| invalid("Radiance header is not valid text"))
+}
+
+fn next_token<'a>(bytes: &'a [u8], cursor: &mut usize) -> io::Result<&'a str> {
+    while bytes.get(*cursor).is_some_and(u8::is_ascii_whitespace) {
+        *cursor += 1;
+    }
+    let start = *cursor;
+    while bytes
+        .get(*cursor)
+        .is_some_and(|byte| !byte.is_ascii_whitespace())
+    {
+        *cursor += 1;
+    }
+    if start == *cursor {
+        return Err(invalid("PFM header ends early"));
+    }
+    std::str::from_utf8(&bytes[start..*cursor]).map_err(|_| invalid("PFM header is not valid text"))
+}
+
+fn take<const N: usize>(bytes: &[u8], cursor: &mut usize) -> io::Result<[u8; N]> {
+    let value = bytes
+        .get(*cursor..*cursor + N)
+        .and_then(|slice| <[u8; N]>::try_from(slice).ok())
+        .ok_or_else(|| invalid("Radiance data ends early"))?;
+    *cursor += N;
+    Ok(value)
+}
+
+fn parse_dimension(token: &str) -> io::Result<u32> {
+    token
+        .parse::<u32>()
+        .ok()
+        .filter(|value| *value > 0)
+        .ok_or_else(|| invalid(format!("invalid image dimension `{token}`")))
+}
+
+fn check_size(width: u32, height: u32) -> io::Result<()> {
+    if u64::from(width) * u64::from(height) > MAX_PIXELS {
+        return Err(invalid(format!("image is too large ({width}x{height})")));
+    }
+    Ok(())
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn unsupported(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, message.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// RGBE shares one exponent, so error is relative to the brightest channel.
+    fn assert_rgbe_close(actual: LinearRgb, expected: LinearRgb) {
+        let tolerance = expected.red.max(expected.green).max(expected.blue) * 0.01 + 1e-6;
+        for (actual, expected) in [
+            (actual.red, expected.red),
+            (actual.green, expected.green),
+            (actual.blue, expected.blue),
+        ] {
+            assert!(
+                (actual - expected).abs() <= tolerance,
+                "{actual} vs {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn radiance_reader_round_trips_the_writer() {
+        let image = HdrImage::from_pixels(
+            2,
+            2,
+            vec![
+                LinearRgb::new(0.25, 0.5, 1.0),
+                LinearRgb::new(1200.0, 800.0, 300.0),
+                LinearRgb::new(0.0, 0.0, 0.0),
+                LinearRgb::new(3.0, 0.01, 7.5),
+            ],
+        );
+        let path =
+            std::env::temp_dir().join(format!("gartus-hdr-codec-{}.hdr", std::process::id()));
+        image
+            .save_radiance_hdr(path.to_str().expect("temp path should be utf8"))
+            .unwrap();
+        let decoded = read_radiance_hdr(&path).unwrap();
+        let _ = fs::remove_file(path);
+
+        assert_eq!((decoded.width(), decoded.height()), (2, 2));
+        for (actual, expected) in decoded.pixels().iter().zip(image.pixels()) {
+            assert_rgbe_close(*actual, *expected);
+        }
+    }
+
+    #[test]
+    fn radiance_reader_decodes_adaptive_rle_and_bottom_up_rows() {
+        let mut bytes = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\nEXPOSURE=1.0\n\n+Y 2 +X 8\n".to_vec();
+        for (red, exponent) in [(128_u8, 129_u8), (128, 131)] {
+            bytes.extend_from_slice(&[2, 2, 0, 8]);
+            // Red as one run, green and blue as zero runs, exponent as literals.
+            bytes.extend_from_slice(&[128 + 8, red, 128 + 8, 0, 128 + 8, 0, 8]);
+            bytes.extend_from_slice(&[exponent; 8]);
+        }
+
+        let image = decode_radiance_hdr(&bytes).unwrap();
+
+        // The first stored row is the bottom of the image.
+        assert_rgbe_close(image.pixels()[0], LinearRgb::new(4.0, 0.0, 0.0));
+        assert_rgbe_close(image.pixels()[8], LinearRgb::new(1.0, 0.0, 0.0));
+        assert!(image.pixels()[15].green < 0.01);
+    }
+
+    #[test]
+    fn pfm_reader_round_trips_the_writer_and_reads_big_endian_grayscale() {
+        let image = HdrImage::from_pixels(
+            1,
+            2,
+            vec![
+                LinearRgb::new(9.5, 0.25, 0.0),
+                LinearRgb::new(0.0, 1.0, 65.0),
+            ],
+        );
+        let path =
+            std::env::temp_dir().join(format!("gartus-pfm-codec-{}.pfm", std::process::id()));
+        image
+            .save_pfm(path.to_str().expect("temp path should be utf8"))
+            .unwrap();
+        let decoded = read_pfm(&path).unwrap();
+        let _ = fs::remove_file(path);
+
+        let mut gray = b"Pf\n2 1\n1.0\n".to_vec();
+        gray.extend_from_slice(&2.5_f32.to_be_bytes());
+        gray.extend_from_slice(&100.0_f32.to_be_bytes());
+        let gray = decode_pfm(&gray).unwrap();
+
+        assert_eq!(decoded, image);
+        assert_eq!(gray.pixels()[1], LinearRgb::new(100.0, 100.0, 100.0));
+    }
+
+    #[test]
+    fn readers_reject_unsupported_and_truncated_data() {
+        let xyze =
+            decode_radiance_hdr(b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n\0\0\0\0")
+                .unwrap_err();
+        let rotated = decode_radiance_hdr(b"#?RADIANCE\n\n+X 1 -Y 1\n\0\0\0\0").unwrap_err();
+        let short = decode_pfm(b"PF\n2 2\n-1.0\n\0\0\0\0").unwrap_err();
+        let mut zero_runs = b"#?RADIANCE\n\n-Y 1 +X 2\n\x80\x80\x80\x80".to_vec();
+        for _ in 0..9 {
+            zero_runs.extend_from_slice(&[1, 1, 1, 0]);
+        }
+        let zero_runs = decode_radiance_hdr(&zero_runs).unwrap_err();
+
+        assert_eq!(xyze.kind(), io::ErrorKind::Unsupported);
+        assert_eq!(rotated.kind(), io::ErrorKind::Unsupported);
+        assert_eq!(short.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(zero_runs.kind(), io::ErrorKind::InvalidData);
+    }
+}
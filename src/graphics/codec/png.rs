@@ -0,0 +1,865 @@
+//! Native PNG encoding and decoding.
+//!
+//! The writer emits 8-bit RGB or RGBA images with adaptive per-row filtering. The reader accepts
+//! every standard PNG color type and bit depth, palette transparency, and Adam7 interlacing, and
+//! expands everything to 8-bit RGBA.
+
+use std::{error::Error, fmt, fs, io, path::Path};
+
+use super::zlib::{self, Compression};
+use crate::graphics::{colors::Rgb, display::Canvas};
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+const IDAT_CHUNK_SIZE: usize = 1 << 20;
+/// Largest decoded image accepted by the reader, in pixels.
+const MAX_PIXELS: u64 = 1 << 28;
+
+/// Error returned while reading or writing PNG data.
+#[derive(Debug)]
+pub enum PngError {
+    /// Reading or writing the underlying file failed.
+    Io(io::Error),
+    /// The PNG stream is malformed or fails a checksum.
+    Invalid(String),
+    /// The PNG stream is valid but uses a feature this codec does not handle.
+    Unsupported(String),
+}
+
+impl PngError {
+    fn invalid(message: impl Into<String>) -> Self {
+        Self::Invalid(message.into())
+    }
+}
+
+impl fmt::Display for PngError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "PNG I/O error: {error}"),
+            Self::Invalid(message) => write!(f, "invalid PNG: {message}"),
+            Self::Unsupported(message) => write!(f, "unsupported PNG: {message}"),
+        }
+    }
+}
+
+impl Error for PngError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Invalid(_) | Self::Unsupported(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for PngError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<zlib::InflateError> for PngError {
+    fn from(error: zlib::InflateError) -> Self {
+        Self::Invalid(error.to_string())
+    }
+}
+
+impl From<PngError> for io::Error {
+    fn from(error: PngError) -> Self {
+        match error {
+            PngError::Io(error) => error,
+            PngError::Invalid(_) => io::Error::new(io::ErrorKind::InvalidData, error),
+            PngError::Unsupported(_) => io::Error::new(io::ErrorKind::Unsupported, error),
+        }
+    }
+}
+
+/// An 8-bit RGBA image read from or written to PNG.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PngImage {
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
+    has_alpha: bool,
+}
+
+impl PngImage {
+    /// Creates an opaque image from a canvas, using canvas storage order with row 0 at the top.
+    #[must_use]
+    pub fn from_canvas(canvas: &Canvas) -> Self {
+        let mut rgba = Vec::with_capacity(canvas.len() * 4);
+        for pixel in canvas {
+            rgba.extend_from_slice(&[pixel.red, pixel.green, pixel.blue, 255]);
+        }
+        Self {
+            width: canvas.width(),
+            height: canvas.height(),
+            rgba,
+            has_alpha: false,
+        }
+    }
+
+    /// Creates an image from tightly packed 8-bit RGBA samples.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PngError::Invalid`] if either dimension is zero or `rgba.len()` is not
+    /// `width * height * 4`.
+    pub fn from_rgba(width: u32, height: u32, rgba: Vec<u8>) -> Result<Self, PngError> {
+        let expected = rgba_len(width, height)?;
+        if rgba.len() != expected {
+            return Err(PngError::invalid(format!(
+                "expected {expected} RGBA bytes, got {}",
+                rgba.len()
+            )));
+        }
+        let has_alpha = rgba.chunks_exact(4).any(|pixel| pixel[3] != 255);
+        Ok(Self {
+            width,
+            height,
+            rgba,
+            has_alpha,
+        })
+    }
+
+    /// Decodes a PNG byte stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PngError::Invalid`] for a bad signature, chunk CRC, zlib stream, filter type,
+    /// or truncated image data, and [`PngError::Unsupported`] for unknown critical chunks.
+    pub fn decode(bytes: &[u8]) -> Result<Self, PngError> {
+        decode(bytes)
+    }
+
+    /// Reads and decodes a PNG file.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PngError::Io`] if the file cannot be read, or any error from [`Self::decode`].
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, PngError> {
+        Self::decode(&fs::read(path)?)
+    }
+
+    /// Returns the image width.
+    #[must_use]
+    pub const fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the image height.
+    #[must_use]
+    pub const fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the tightly packed 8-bit RGBA samples, top row first.
+    #[must_use]
+    pub fn rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    /// Returns whether any pixel is not fully opaque.
+    #[must_use]
+    pub const fn has_alpha(&self) -> bool {
+        self.has_alpha
+    }
+
+    /// Converts to a canvas, discarding alpha.
+    pub fn to_canvas(&self) -> Canvas {
+        Canvas::from_pixels(
+            self.width,
+            self.height,
+            self.rgba
+                .chunks_exact(4)
+                .map(|pixel| Rgb::new(pixel[0], pixel[1], pixel[2]))
+                .collect(),
+        )
+    }
+
+    /// Converts to a canvas by compositing every pixel over `background`.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn to_canvas_over(&self, background: Rgb) -> Canvas {
+        let blend = |foreground: u8, background: u8, alpha: u8| {
+            let alpha = u32::from(alpha);
+            ((u32::from(foreground) * alpha + u32::from(background) * (255 - alpha) + 127) / 255)
+                as u8
+        };
+        Canvas::from_pixels(
+            self.width,
+            self.height,
+            self.rgba
+                .chunks_exact(4)
+                .map(|pixel| {
+                    Rgb::new(
+                        blend(pixel[0], background.red, pixel[3]),
+                        blend(pixel[1], background.green, pixel[3]),
+                        blend(pixel[2], background.blue, pixel[3]),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    /// Encodes the image as PNG, writing RGB when every pixel is opaque and RGBA otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PngError::Invalid`] if either dimension is zero.
+    pub fn encode(&self) -> Result<Vec<u8>, PngError> {
+        self.encode_with(Compression::Default)
+    }
+
+    /// Encodes the image as PNG with an explicit DEFLATE effort.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PngError::Invalid`] if either dimension is zero.
+    pub fn encode_with(&self, compression: Compression) -> Result<Vec<u8>, PngError> {
+        rgba_len(self.width, self.height)?;
+        let channels = if self.has_alpha { 4 } else { 3 };
+        let color_type = if self.has_alpha { 6 } else { 2 };
+        let stride = self.width as usize * channels;
+
+        let mut raw = Vec::with_capacity((stride + 1) * self.height as usize);
+        let mut previous = vec![0_u8; stride];
+        let mut current = vec![0_u8; stride];
+        for row in self.rgba.chunks_exact(self.width as usize * 4) {
+            if self.has_alpha {
+                current.copy_from_slice(row);
+            } else {
+                for (out, pixel) in current.chunks_exact_mut(3).zip(row.chunks_exact(4)) {
+                    out.copy_from_slice(&pixel[..3]);
+                }
+            }
+            filter_row(&mut raw, &current, &previous, channels);
+            std::mem::swap(&mut previous, &mut current);
+        }
+
+        let mut out = SIGNATURE.to_vec();
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&self.width.to_be_bytes());
+        header.extend_from_slice(&self.height.to_be_bytes());
+        header.extend_from_slice(&[8, color_type, 0, 0, 0]);
+        write_chunk(&mut out, *b"IHDR", &header);
+        for chunk in zlib::compress(&raw, compression).chunks(IDAT_CHUNK_SIZE) {
+            write_chunk(&mut out, *b"IDAT", chunk);
+        }
+        write_chunk(&mut out, *b"IEND", &[]);
+        Ok(out)
+    }
+
+    /// Encodes the image and writes it to `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if encoding fails or the file cannot be written.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), PngError> {
+        fs::write(path, self.encode()?)?;
+        Ok(())
+    }
+}
+
+fn rgba_len(width: u32, height: u32) -> Result<usize, PngError> {
+    if width == 0 || height == 0 {
+        return Err(PngError::invalid("PNG dimensions must be non-zero"));
+    }
+    let pixels = u64::from(width) * u64::from(height);
+    if pixels > MAX_PIXELS {
+        return Err(PngError::Unsupported(format!(
+            "{width}x{height} exceeds the {MAX_PIXELS} pixel limit"
+        )));
+    }
+    usize::try_from(pixels * 4).map_err(|_| PngError::invalid("PNG image too large"))
+}
+
+fn write_chunk(out: &mut Vec<u8>, kind: [u8; 4], data: &[u8]) {
+    out.extend_from_slice(
+        &u32::try_from(data.len())
+            .expect("PNG chunk length fits u32")
+            .to_be_bytes(),
+    );
+    out.extend_from_slice(&kind);
+    out.extend_from_slice(data);
+    let crc = zlib::crc32_update(zlib::crc32(&kind), data);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = i16::from(left) + i16::from(up) - i16::from(up_left);
+    let distance_left = (estimate - i16::from(left)).abs();
+    let distance_up = (estimate - i16::from(up)).abs();
+    let distance_up_left = (estimate - i16::from(up_left)).abs();
+    if distance_left <= distance_up && distance_left <= distance_up_left {
+        left
+    } else if distance_up <= distance_up_left {
+        up
+    } else {
+        up_left
+    }
+}
+
+/// Appends `row` filtered with whichever PNG filter minimizes the sum of absolute residuals.
+fn filter_row(out: &mut Vec<u8>, row: &[u8], previous: &[u8], bpp: usize) {
+    let mut best = (u64::MAX, 0_u8, Vec::new());
+    let mut candidate = vec![0_u8; row.len()];
+    for filter in 0..5_u8 {
+        for index in 0..row.len() {
+            let left = if index >= bpp { row[index - bpp] } else { 0 };
+            let up = previous[index];
+            let up_left = if index >= bpp {
+                previous[index - bpp]
+            } else {
+                0
+            };
+            let predictor = match filter {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => left.midpoint(up),
+                _ => paeth(left, up, up_left),
+            };
+            candidate[index] = row[index].wrapping_sub(predictor);
+        }
+        let score = candidate
+            .iter()
+            .map(|&value| u64::from(value.cast_signed().unsigned_abs()))
+            .sum::<u64>();
+        if score < best.0 {
+            best = (score, filter, candidate.clone());
+        }
+    }
+    out.push(best.1);
+    out.extend_from_slice(&best.2);
+}
+
+fn unfilter_row(filter: u8, row: &mut [u8], previous: &[u8], bpp: usize) -> Result<(), PngError> {
+    for index in 0..row.len() {
+        let left = if index >= bpp { row[index - bpp] } else { 0 };
+        let up = previous[index];
+        let up_left = if index >= bpp {
+            previous[index - bpp]
+        } else {
+            0
+        };
+        let predictor = match filter {
+            0 => 0,
+            1 => left,
+            2 => up,
+            3 => left.midpoint(up),
+            4 => paeth(left, up, up_left),
+            other => return Err(PngError::invalid(format!("unknown filter type {other}"))),
+        };
+        row[index] = row[index].wrapping_add(predictor);
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Header {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: u8,
+    interlaced: bool,
+}
+
+impl Header {
+    fn parse(data: &[u8]) -> Result<Self, PngError> {
+        if data.len() != 13 {
+            return Err(PngError::invalid("IHDR must be 13 bytes"));
+        }
+        let width = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+        let height = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+        let bit_depth = data[8];
+        let color_type = data[9];
+        let allowed: &[u8] = match color_type {
+            0 => &[1, 2, 4, 8, 16],
+            3 => &[1, 2, 4, 8],
+            2 | 4 | 6 => &[8, 16],
+            other => return Err(PngError::invalid(format!("unknown color type {other}"))),
+        };
+        if !allowed.contains(&bit_depth) {
+            return Err(PngError::invalid(format!(
+                "bit depth {bit_depth} is not valid for color type {color_type}"
+            )));
+        }
+        if data[10] != 0 || data[11] != 0 {
+            return Err(PngError::Unsupported(
+                "unknown compression or filter method".to_string(),
+            ));
+        }
+        let interlaced = match data[12] {
+            0 => false,
+            1 => true,
+            other => {
+                return Err(PngError::invalid(format!(
+                    "unknown interlace method {other}"
+                )));
+            }
+        };
+        rgba_len(width, height)?;
+        Ok(Self {
+            width,
+            height,
+            bit_depth,
+            color_type,
+            interlaced,
+        })
+    }
+
+    const fn channels(self) -> usize {
+        match self.color_type {
+            2 => 3,
+            4 => 2,
+            6 => 4,
+            _ => 1,
+        }
+    }
+
+    fn bits_per_pixel(self) -> usize {
+        self.channels() * usize::from(self.bit_depth)
+    }
+
+    /// Filter byte distance: bytes per complete pixel, rounded up to one.
+    fn filter_bpp(self) -> usize {
+        self.bits_per_pixel().div_ceil(8)
+    }
+
+    fn stride(self, width: u32) -> usize {
+        (width as usize * self.bits_per_pixel()).div_ceil(8)
+    }
+}
+
+struct Transparency {
+    palette_alpha: Vec<u8>,
+    key: Option<[u16; 3]>,
+}
+
+/// Splits the next chunk off `bytes[*cursor..]` and verifies its CRC.
+fn next_chunk<'a>(bytes: &'a [u8], cursor: &mut usize) -> Result<(&'a [u8], &'a [u8]), PngError> {
+    let start = *cursor;
+    let length_bytes = bytes
+        .get(start..start + 4)
+        .ok_or_else(|| PngError::invalid("truncated chunk length"))?;
+    let length = u32::from_be_bytes([
+        length_bytes[0],
+        length_bytes[1],
+        length_bytes[2],
+        length_bytes[3],
+    ]) as usize;
+    let kind = bytes
+        .get(start + 4..start + 8)
+        .ok_or_else(|| PngError::invalid("truncated chunk type"))?;
+    let data = bytes
+        .get(start + 8..start + 8 + length)
+        .ok_or_else(|| PngError::invalid("truncated chunk data"))?;
+    let crc_bytes = bytes
+        .get(start + 8 + length..start + 12 + length)
+        .ok_or_else(|| PngError::invalid("truncated chunk CRC"))?;
+    let expected = u32::from_be_bytes([crc_bytes[0], crc_bytes[1], crc_bytes[2], crc_bytes[3]]);
+    if zlib::crc32_update(zlib::crc32(kind), data) != expected {
+        return Err(PngError::invalid(format!(
+            "CRC mismatch in {} chunk",
+            String::from_utf8_lossy(kind)
+        )));
+    }
+    *cursor = start + 12 + length;
+    Ok((kind, data))
+}
+
+impl Transparency {
+    fn parse(header: Header, data: &[u8]) -> Result<Self, PngError> {
+        let sample = |index: usize| {
+            data.get(index * 2..index * 2 + 2)
+                .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+                .ok_or_else(|| PngError::invalid("truncated tRNS chunk"))
+        };
+        let mut transparency = Self {
+            palette_alpha: Vec::new(),
+            key: None,
+        };
+        match header.color_type {
+            3 => transparency.palette_alpha = data.to_vec(),
+            0 => {
+                let gray = sample(0)?;
+                transparency.key = Some([gray, gray, gray]);
+            }
+            2 => transparency.key = Some([sample(0)?, sample(1)?, sample(2)?]),
+            _ => {}
+        }
+        Ok(transparency)
+    }
+}
+
+fn decode(bytes: &[u8]) -> Result<PngImage, PngError> {
+    if bytes.len() < SIGNATURE.len() || bytes[..8] != SIGNATURE {
+        return Err(PngError::invalid("missing PNG signature"));
+    }
+    let mut cursor = 8;
+    let mut header = None;
+    let mut palette = Vec::new();
+    let mut transparency = Transparency {
+        palette_alpha: Vec::new(),
+        key: None,
+    };
+    let mut compressed = Vec::new();
+    let mut ended = false;
+
+    while cursor < bytes.len() {
+        let (kind, data) = next_chunk(bytes, &mut cursor)?;
+        match (kind, header) {
+            (b"IHDR", _) => header = Some(Header::parse(data)?),
+            (_, None) => return Err(PngError::invalid("first chunk is not IHDR")),
+            (b"PLTE", _) => {
+                if data.len() % 3 != 0 || data.is_empty() || data.len() > 768 {
+                    return Err(PngError::invalid(
+                        "PLTE length must be 3..=768 and divisible by 3",
+                    ));
+                }
+                palette = data
+                    .chunks_exact(3)
+                    .map(|rgb| [rgb[0], rgb[1], rgb[2]])
+                    .collect();
+            }
+            (b"tRNS", Some(header)) => transparency = Transparency::parse(header, data)?,
+            (b"IDAT", _) => compressed.extend_from_slice(data),
+            (b"IEND", _) => {
+                ended = true;
+                break;
+            }
+            (other, _) if other[0].is_ascii_uppercase() => {
+                return Err(PngError::Unsupported(format!(
+                    "unknown critical chunk {}",
+                    String::from_utf8_lossy(other)
+                )));
+            }
+            _ => {}
+        }
+    }
+
+    let header = header.ok_or_else(|| PngError::invalid("missing IHDR chunk"))?;
+    if !ended {
+        return Err(PngError::invalid("missing IEND chunk"));
+    }
+    if header.color_type == 3 && palette.is_empty() {
+        return Err(PngError::invalid("palette image has no PLTE chunk"));
+    }
+    let raw = zlib::decompress(&compressed)?;
+    reconstruct(header, &raw, &palette, &transparency)
+}
+
+fn reconstruct(
+    header: Header,
+    raw: &[u8],
+    palette: &[[u8; 3]],
+    transparency: &Transparency,
+) -> Result<PngImage, PngError> {
+    const ADAM7_PASSES: [(u32, u32, u32, u32); 7] = [
+        (0, 0, 8, 8),
+        (4, 0, 8, 8),
+        (0, 4, 4, 8),
+        (2, 0, 4, 4),
+        (0, 2, 2, 4),
+        (1, 0, 2, 2),
+        (0, 1, 1, 2),
+    ];
+
+    let mut image = PngImage {
+        width: header.width,
+        height: header.height,
+        rgba: vec![0; rgba_len(header.width, header.height)?],
+        has_alpha: false,
+    };
+    let mut reader = ScanlineReader {
+        raw,
+        cursor: 0,
+        palette,
+        transparency,
+    };
+    if header.interlaced {
+        for (x0, y0, dx, dy) in ADAM7_PASSES {
+            let pass_width = header.width.saturating_sub(x0).div_ceil(dx);
+            let pass_height = header.height.saturating_sub(y0).div_ceil(dy);
+            if pass_width == 0 || pass_height == 0 {
+                continue;
+            }
+            reader.read_pass(header, pass_width, pass_height, |x, y, rgba| {
+                image.put(x0 + x * dx, y0 + y * dy, rgba);
+            })?;
+        }
+    } else {
+        reader.read_pass(header, header.width, header.height, |x, y, rgba| {
+            image.put(x, y, rgba);
+        })?;
+    }
+    image.has_alpha = image.rgba.chunks_exact(4).any(|pixel| pixel[3] != 255);
+    Ok(image)
+}
+
+impl PngImage {
+    fn put(&mut self, x: u32, y: u32, rgba: [u8; 4]) {
+        let index = (y as usize * self.width as usize + x as usize) * 4;
+        self.rgba[index..index + 4].copy_from_slice(&rgba);
+    }
+}
+
+struct ScanlineReader<'a> {
+    raw: &'a [u8],
+    cursor: usize,
+    palette: &'a [[u8; 3]],
+    transparency: &'a Transparency,
+}
+
+impl ScanlineReader<'_> {
+    fn read_pass(
+        &mut self,
+        header: Header,
+        width: u32,
+        height: u32,
+        mut put: impl FnMut(u32, u32, [u8; 4]),
+    ) -> Result<(), PngError> {
+        let stride = header.stride(width);
+        let bpp = header.filter_bpp();
+        let mut previous = vec![0_u8; stride];
+        for y in 0..height {
+            let filter = *self
+                .raw
+                .get(self.cursor)
+                .ok_or_else(|| PngError::invalid("image data is truncated"))?;
+            let mut row = self
+                .raw
+                .get(self.cursor + 1..self.cursor + 1 + stride)
+                .ok_or_else(|| PngError::invalid("image data is truncated"))?
+                .to_vec();
+            self.cursor += 1 + stride;
+            unfilter_row(filter, &mut row, &previous, bpp)?;
+            for x in 0..width {
+                put(
+                    x,
+                    y,
+                    expand_pixel(header, &row, x as usize, self.palette, self.transparency)?,
+                );
+            }
+            previous = row;
+        }
+        Ok(())
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn expand_pixel(
+    header: Header,
+    row: &[u8],
+    x: usize,
+    palette: &[[u8; 3]],
+    transparency: &Transparency,
+) -> Result<[u8; 4], PngError> {
+    let depth = usize::from(header.bit_depth);
+    let sample = |channel: usize| -> u16 {
+        let index = x * header.channels() + channel;
+        match depth {
+            16 => u16::from_be_bytes([row[index * 2], row[index * 2 + 1]]),
+            8 => u16::from(row[index]),
+            _ => {
+                let bit = index * depth;
+                let shift = 8 - depth - bit % 8;
+                u16::from((row[bit / 8] >> shift) & ((1 << depth) - 1))
+            }
+        }
+    };
+    let scale = |value: u16| -> u8 {
+        match depth {
+            16 => (value >> 8) as u8,
+            8 => value as u8,
+            _ => (u32::from(value) * 255 / ((1 << depth) - 1)) as u8,
+        }
+    };
+    let keyed = |samples: [u16; 3]| transparency.key == Some(samples);
+    Ok(match header.color_type {
+        0 => {
+            let gray = sample(0);
+            let alpha = if keyed([gray, gray, gray]) { 0 } else { 255 };
+            let gray = scale(gray);
+            [gray, gray, gray, alpha]
+        }
+        2 => {
+            let rgb = [sample(0), sample(1), sample(2)];
+            let alpha = if keyed(rgb) { 0 } else { 255 };
+            [scale(rgb[0]), scale(rgb[1]), scale(rgb[2]), alpha]
+        }
+        3 => {
+            let index = usize::from(sample(0));
+            let [red, green, blue] = *palette
+                .get(index)
+                .ok_or_else(|| PngError::invalid(format!("palette index {index} out of range")))?;
+            let alpha = transparency
+                .palette_alpha
+                .get(index)
+                .copied()
+                .unwrap_or(255);
+            [red, green, blue, alpha]
+        }
+        4 => {
+            let gray = scale(sample(0));
+            [gray, gray, gray, scale(sample(1))]
+        }
+        _ => [
+            scale(sample(0)),
+            scale(sample(1)),
+            scale(sample(2)),
+            scale(sample(3)),
+        ],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PngError, PngImage, SIGNATURE, write_chunk};
+    use crate::graphics::{
+        codec::zlib::{self, Compression},
+        colors::Rgb,
+        display::Canvas,
+    };
+
+    fn png_from_parts(header: [u8; 13], extra: &[([u8; 4], Vec<u8>)], raw: &[u8]) -> Vec<u8> {
+        let mut out = SIGNATURE.to_vec();
+        write_chunk(&mut out, *b"IHDR", &header);
+        for (kind, data) in extra {
+            write_chunk(&mut out, *kind, data);
+        }
+        write_chunk(&mut out, *b"IDAT", &zlib::compress(raw, Compression::Fast));
+        write_chunk(&mut out, *b"IEND", &[]);
+        out
+    }
+
+    fn header(width: u32, height: u32, bit_depth: u8, color_type: u8, interlace: u8) -> [u8; 13] {
+        let mut header = [0_u8; 13];
+        header[..4].copy_from_slice(&width.to_be_bytes());
+        header[4..8].copy_from_slice(&height.to_be_bytes());
+        header[8] = bit_depth;
+        header[9] = color_type;
+        header[12] = interlace;
+        header
+    }
+
+    #[test]
+    #[allow(clippy::cast_possible_truncation)]
+    fn canvas_round_trips_through_rgb_png() {
+        let canvas = Canvas::from_fn(37, 23, |x, y| {
+            Rgb::new((x * 7) as u8, (y * 11) as u8, ((x + y) * 3) as u8)
+        });
+
+        let bytes = PngImage::from_canvas(&canvas).encode().expect("encode");
+        let decoded = PngImage::decode(&bytes).expect("decode");
+
+        assert_eq!(bytes[25], 2, "opaque canvases are written as RGB");
+        assert!(!decoded.has_alpha());
+        assert_eq!(decoded.to_canvas().pixels(), canvas.pixels());
+    }
+
+    #[test]
+    fn rgba_round_trips_and_composites_over_background() {
+        let rgba = vec![255, 0, 0, 255, 0, 0, 255, 0, 10, 20, 30, 128, 1, 2, 3, 4];
+        let image = PngImage::from_rgba(2, 2, rgba.clone()).expect("valid rgba");
+
+        let decoded = PngImage::decode(&image.encode().expect("encode")).expect("decode");
+
+        assert!(decoded.has_alpha());
+        assert_eq!(decoded.rgba(), rgba.as_slice());
+        let flattened = decoded.to_canvas_over(Rgb::WHITE);
+        assert_eq!(flattened.pixels()[0], Rgb::new(255, 0, 0));
+        assert_eq!(flattened.pixels()[1], Rgb::WHITE);
+    }
+
+    #[test]
+    fn decodes_palette_with_transparency_and_low_bit_depth() {
+        // 2-bit palette indices 0,1,2,3 packed into one byte, no filter, with only three entries.
+        let palette = vec![0, 0, 0, 255, 0, 0, 0, 255, 0];
+        let bytes = png_from_parts(
+            header(4, 1, 2, 3, 0),
+            &[(*b"PLTE", palette), (*b"tRNS", vec![0, 255])],
+            &[0, 0b0001_1011],
+        );
+        assert!(matches!(
+            PngImage::decode(&bytes),
+            Err(PngError::Invalid(message)) if message.contains("palette index 3")
+        ));
+
+        let bytes = png_from_parts(
+            header(3, 1, 2, 3, 0),
+            &[
+                (*b"PLTE", vec![0, 0, 0, 255, 0, 0, 0, 255, 0]),
+                (*b"tRNS", vec![0]),
+            ],
+            &[0, 0b0001_1000],
+        );
+        let image = PngImage::decode(&bytes).expect("decode palette");
+        assert_eq!(image.rgba(), &[0, 0, 0, 0, 255, 0, 0, 255, 0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn decodes_sixteen_bit_grayscale_alpha_and_one_bit_gray() {
+        let bytes = png_from_parts(header(1, 1, 16, 4, 0), &[], &[0, 0x80, 0x00, 0xff, 0xff]);
+        assert_eq!(
+            PngImage::decode(&bytes).unwrap().rgba(),
+            &[128, 128, 128, 255]
+        );
+
+        let bytes = png_from_parts(header(3, 1, 1, 0, 0), &[], &[0, 0b1010_0000]);
+        assert_eq!(
+            PngImage::decode(&bytes).unwrap().rgba(),
+            &[255, 255, 255, 255, 0, 0, 0, 255, 255, 255, 255, 255]
+        );
+    }
+
+    #[test]
+    #[allow(clippy::cast_possible_truncation)]
+    fn decodes_adam7_interlaced_grayscale() {
+        let width = 5_u32;
+        let height = 3_u32;
+        let value = |x: u32, y: u32| (y * width + x) as u8 * 10;
+        let passes = [
+            (0, 0, 8, 8),
+            (4, 0, 8, 8),
+            (0, 4, 4, 8),
+            (2, 0, 4, 4),
+            (0, 2, 2, 4),
+            (1, 0, 2, 2),
+            (0, 1, 1, 2),
+        ];
+        let mut raw = Vec::new();
+        for (x0, y0, dx, dy) in passes {
+            let mut y = y0;
+            while y < height {
+                if x0 < width {
+                    raw.push(0);
+                    let mut x = x0;
+                    while x < width {
+                        raw.push(value(x, y));
+                        x += dx;
+                    }
+                }
+                y += dy;
+            }
+        }
+
+        let image = PngImage::decode(&png_from_parts(header(width, height, 8, 0, 1), &[], &raw))
+            .expect("decode interlaced");
+
+        for y in 0..height {
+            for x in 0..width {
+                let index = ((y * width + x) * 4) as usize;
+                assert_eq!(image.rgba()[index], value(x, y), "pixel {x},{y}");
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_corrupt_signature_crc_and_zero_dimensions() {
+        let canvas = Canvas::new_with_bg(2, 2, Rgb::RED);
+        let mut bytes = PngImage::from_canvas(&canvas).encode().unwrap();
+        bytes[20] ^= 1;
+        assert!(matches!(PngImage::decode(&bytes), Err(PngError::Invalid(m)) if m.contains("CRC")));
+        assert!(PngImage::decode(b"not a png").is_err());
+        assert!(PngImage::from_rgba(0, 1, Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,1044 @@
+//! Dependency-free zlib (RFC 1950) and DEFLATE (RFC 1951) streams.
+//!
+//! The compressor uses hash-chained LZ77 with one step of lazy matching and picks the cheapest of
+//! stored, fixed-Huffman, and dynamic-Huffman encodings per block. The decompressor accepts every
+//! block type.
+
+use std::{error::Error, fmt};
+
+const WINDOW_SIZE: usize = 1 << 15;
+const WINDOW_MASK: usize = WINDOW_SIZE - 1;
+const HASH_BITS: usize = 15;
+const HASH_SIZE: usize = 1 << HASH_BITS;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const BLOCK_TOKENS: usize = 1 << 16;
+const END_OF_BLOCK: usize = 256;
+const LITLEN_CODES: usize = 286;
+const DIST_CODES: usize = 30;
+const MAX_CODE_BITS: u8 = 15;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// Effort used by the LZ77 match finder.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Compression {
+    /// Short hash chains; fastest output with a modest ratio.
+    Fast,
+    /// Balanced chain depth used by the image writers.
+    #[default]
+    Default,
+    /// Long hash chains for the smallest output.
+    Best,
+}
+
+impl Compression {
+    const fn max_chain(self) -> usize {
+        match self {
+            Self::Fast => 8,
+            Self::Default => 64,
+            Self::Best => 1024,
+        }
+    }
+
+    const fn nice_length(self) -> usize {
+        match self {
+            Self::Fast => 32,
+            Self::Default => 128,
+            Self::Best => MAX_MATCH,
+        }
+    }
+
+    const fn level_flag(self) -> u8 {
+        match self {
+            Self::Fast => 0,
+            Self::Default => 2,
+            Self::Best => 3,
+        }
+    }
+}
+
+/// Error returned when a zlib or DEFLATE stream is malformed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InflateError(String);
+
+impl InflateError {
+    fn new(message: impl Into<String>) -> Self {
+        Self(message.into())
+    }
+}
+
+impl fmt::Display for InflateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid deflate stream: {}", self.0)
+    }
+}
+
+impl Error for InflateError {}
+
+/// Computes the CRC-32 (IEEE 802.3) checksum used by PNG chunks and gzip members.
+#[must_use]
+pub fn crc32(bytes: &[u8]) -> u32 {
+    crc32_update(0, bytes)
+}
+
+/// Continues a CRC-32 checksum over additional bytes.
+#[must_use]
+#[allow(clippy::cast_possible_truncation)]
+pub fn crc32_update(crc: u32, bytes: &[u8]) -> u32 {
+    static TABLE: std::sync::OnceLock<[u32; 256]> = std::sync::OnceLock::new();
+    let table = TABLE.get_or_init(|| {
+        let mut table = [0_u32; 256];
+        for (index, entry) in (0_u32..).zip(table.iter_mut()) {
+            let mut value = index;
+            for _ in 0..8 {
+                value = if value & 1 == 1 {
+                    0xedb8_8320 ^ (value >> 1)
+                } else {
+                    value >> 1
+                };
+            }
+            *entry = value;
+        }
+        table
+    });
+    let mut crc = !crc;
+    for &byte in bytes {
+        crc = table[usize::from((crc as u8) ^ byte)] ^ (crc >> 8);
+    }
+    !crc
+}
+
+/// Computes the Adler-32 checksum that trails zlib streams.
+#[must_use]
+pub fn adler32(bytes: &[u8]) -> u32 {
+    const MODULUS: u32 = 65_521;
+    let mut a = 1_u32;
+    let mut b = 0_u32;
+    for chunk in bytes.chunks(5552) {
+        for &byte in chunk {
+            a += u32::from(byte);
+            b += a;
+        }
+        a %= MODULUS;
+        b %= MODULUS;
+    }
+    (b << 16) | a
+}
+
+/// Compresses `data` into a zlib stream.
+#[must_use]
+pub fn compress(data: &[u8], compression: Compression) -> Vec<u8> {
+    let cmf = 0x78_u8;
+    let flg = compression.level_flag() << 6;
+    let check = (31 - u16::from_be_bytes([cmf, flg]) % 31) % 31;
+    let mut out = vec![cmf, flg | check.to_le_bytes()[0]];
+    out.extend_from_slice(&deflate(data, compression));
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+/// Decompresses a zlib stream and verifies its Adler-32 trailer.
+///
+/// # Errors
+///
+/// Returns an error for unsupported headers, preset dictionaries, corrupt DEFLATE data, or a
+/// checksum mismatch.
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, InflateError> {
+    if data.len() < 6 {
+        return Err(InflateError::new("zlib stream is too short"));
+    }
+    let cmf = data[0];
+    let flg = data[1];
+    if cmf & 0x0f != 8 || cmf >> 4 > 7 {
+        return Err(InflateError::new("zlib stream does not use deflate"));
+    }
+    if ((u16::from(cmf) << 8) | u16::from(flg)) % 31 != 0 {
+        return Err(InflateError::new("zlib header check failed"));
+    }
+    if flg & 0x20 != 0 {
+        return Err(InflateError::new(
+            "zlib preset dictionaries are not supported",
+        ));
+    }
+    let (out, consumed) = inflate_with_len(&data[2..])?;
+    let trailer = data
+        .get(2 + consumed..2 + consumed + 4)
+        .ok_or_else(|| InflateError::new("zlib stream is missing its Adler-32 trailer"))?;
+    let expected = u32::from_be_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    if adler32(&out) != expected {
+        return Err(InflateError::new("zlib Adler-32 checksum mismatch"));
+    }
+    Ok(out)
+}
+
+/// Decompresses a raw DEFLATE stream.
+///
+/// # Errors
+///
+/// Returns an error if the stream is truncated or contains invalid codes or distances.
+pub fn inflate(data: &[u8]) -> Result<Vec<u8>, InflateError> {
+    inflate_with_len(data).map(|(out, _)| out)
+}
+
+/// Compresses `data` into a raw DEFLATE stream.
+#[must_use]
+pub fn deflate(data: &[u8], compression: Compression) -> Vec<u8> {
+    let mut writer = BitWriter::default();
+    let tokens = Lz77::new(compression).tokenize(data);
+    if tokens.is_empty() {
+        write_block(&mut writer, &[], &[], true);
+    } else {
+        let mut consumed = 0;
+        let chunks = tokens.chunks(BLOCK_TOKENS).collect::<Vec<_>>();
+        for (index, chunk) in chunks.iter().enumerate() {
+            let span = chunk.iter().map(|token| token.span()).sum::<usize>();
+            write_block(
+                &mut writer,
+                chunk,
+                &data[consumed..consumed + span],
+                index + 1 == chunks.len(),
+            );
+            consumed += span;
+        }
+    }
+    writer.finish()
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Token {
+    Literal(u8),
+    Match { length: u16, distance: u16 },
+}
+
+impl Token {
+    fn span(self) -> usize {
+        match self {
+            Self::Literal(_) => 1,
+            Self::Match { length, .. } => usize::from(length),
+        }
+    }
+}
+
+struct Lz77 {
+    max_chain: usize,
+    nice_length: usize,
+    head: Vec<i32>,
+    prev: Vec<i32>,
+}
+
+impl Lz77 {
+    fn new(compression: Compression) -> Self {
+        Self {
+            max_chain: compression.max_chain(),
+            nice_length: compression.nice_length(),
+            head: vec![-1; HASH_SIZE],
+            prev: vec![-1; WINDOW_SIZE],
+        }
+    }
+
+    fn hash(data: &[u8], position: usize) -> usize {
+        let value = (u32::from(data[position]) << 16)
+            | (u32::from(data[position + 1]) << 8)
+            | u32::from(data[position + 2]);
+        (value.wrapping_mul(0x9e37_79b1) >> (32 - HASH_BITS)) as usize
+    }
+
+    fn insert(&mut self, data: &[u8], position: usize) {
+        if position + MIN_MATCH > data.len() {
+            return;
+        }
+        let hash = Self::hash(data, position);
+        self.prev[position & WINDOW_MASK] = self.head[hash];
+        self.head[hash] = i32::try_from(position).expect("deflate input position fits i32");
+    }
+
+    fn longest_match(&self, data: &[u8], position: usize) -> (usize, usize) {
+        if position + MIN_MATCH > data.len() {
+            return (0, 0);
+        }
+        let max_length = MAX_MATCH.min(data.len() - position);
+        let mut best_length = 0;
+        let mut best_distance = 0;
+        let mut candidate = self.head[Self::hash(data, position)];
+        let mut chain = self.max_chain;
+        while candidate >= 0 && chain > 0 {
+            let start = usize::try_from(candidate).expect("candidate is non-negative");
+            let distance = position - start;
+            if distance == 0 || distance > WINDOW_SIZE {
+                break;
+            }
+            if data[start + best_length] == data[position + best_length] {
+                let length = data[start..start + max_length]
+                    .iter()
+                    .zip(&data[position..position + max_length])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if length > best_length {
+                    best_length = length;
+                    best_distance = distance;
+                    if length >= self.nice_length || length == max_length {
+                        break;
+                    }
+                }
+            }
+            let next = self.prev[start & WINDOW_MASK];
+            if next >= candidate {
+                break;
+            }
+            candidate = next;
+            chain -= 1;
+        }
+        if best_length >= MIN_MATCH {
+            (best_length, best_distance)
+        } else {
+            (0, 0)
+        }
+    }
+
+    fn tokenize(mut self, data: &[u8]) -> Vec<Token> {
+        let mut tokens = Vec::with_capacity(data.len() / 2);
+        let mut position = 0;
+        while position < data.len() {
+            let (length, distance) = self.longest_match(data, position);
+            self.insert(data, position);
+            if length == 0 {
+                tokens.push(Token::Literal(data[position]));
+                position += 1;
+                continue;
+            }
+            if length < self.nice_length && position + 1 < data.len() {
+                let (next_length, _) = self.longest_match(data, position + 1);
+                if next_length > length {
+                    tokens.push(Token::Literal(data[position]));
+                    position += 1;
+                    continue;
+                }
+            }
+            tokens.push(Token::Match {
+                length: u16::try_from(length).expect("match length fits u16"),
+                distance: u16::try_from(distance).expect("match distance fits u16"),
+            });
+            for offset in 1..length {
+                self.insert(data, position + offset);
+            }
+            position += length;
+        }
+        tokens
+    }
+}
+
+fn length_code(length: u16) -> usize {
+    LENGTH_BASE.partition_point(|&base| base <= length) - 1
+}
+
+fn distance_code(distance: u16) -> usize {
+    DIST_BASE.partition_point(|&base| base <= distance) - 1
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u64,
+    count: u32,
+}
+
+#[allow(clippy::cast_possible_truncation)]
+impl BitWriter {
+    fn write(&mut self, value: u32, bits: u8) {
+        self.buffer |= u64::from(value) << self.count;
+        self.count += u32::from(bits);
+        while self.count >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    fn write_code(&mut self, code: u16, length: u8) {
+        self.write(u32::from(reverse_bits(code, length)), length);
+    }
+
+    fn align(&mut self) {
+        if self.count > 0 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer = 0;
+            self.count = 0;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.align();
+        self.bytes
+    }
+}
+
+fn reverse_bits(code: u16, length: u8) -> u16 {
+    if length == 0 {
+        0
+    } else {
+        code.reverse_bits() >> (16 - u32::from(length))
+    }
+}
+
+/// Builds Huffman code lengths for `frequencies`, limited to `max_bits`.
+fn huffman_lengths(frequencies: &[u32], max_bits: u8) -> Vec<u8> {
+    let mut scaled = frequencies.to_vec();
+    loop {
+        let lengths = unlimited_huffman_lengths(&scaled);
+        if lengths.iter().all(|&length| length <= max_bits) {
+            return lengths;
+        }
+        for frequency in &mut scaled {
+            if *frequency > 0 {
+                *frequency = (*frequency >> 1).max(1);
+            }
+        }
+    }
+}
+
+fn unlimited_huffman_lengths(frequencies: &[u32]) -> Vec<u8> {
+    let mut lengths = vec![0_u8; frequencies.len()];
+    let used = frequencies
+        .iter()
+        .enumerate()
+        .filter(|(_, frequency)| **frequency > 0)
+        .map(|(symbol, _)| symbol)
+        .collect::<Vec<_>>();
+    match used.len() {
+        0 => return lengths,
+        1 => {
+            lengths[used[0]] = 1;
+            return lengths;
+        }
+        _ => {}
+    }
+
+    // Nodes `0..used.len()` are leaves; internal nodes are appended with their parent links.
+    let mut parents = vec![usize::MAX; used.len()];
+    let mut heap = std::collections::BinaryHeap::new();
+    for (node, &symbol) in used.iter().enumerate() {
+        heap.push(std::cmp::Reverse((u64::from(frequencies[symbol]), node)));
+    }
+    while heap.len() > 1 {
+        let std::cmp::Reverse((left_weight, left)) = heap.pop().expect("heap has two nodes");
+        let std::cmp::Reverse((right_weight, right)) = heap.pop().expect("heap has two nodes");
+        let parent = parents.len();
+        parents.push(usize::MAX);
+        parents[left] = parent;
+        parents[right] = parent;
+        heap.push(std::cmp::Reverse((left_weight + right_weight, parent)));
+    }
+    for (node, &symbol) in used.iter().enumerate() {
+        let mut depth = 0_u8;
+        let mut current = node;
+        while parents[current] != usize::MAX {
+            current = parents[current];
+            depth = depth.saturating_add(1);
+        }
+        lengths[symbol] = depth;
+    }
+    lengths
+}
+
+/// Assigns canonical codes to code lengths.
+fn canonical_codes(lengths: &[u8]) -> Vec<u16> {
+    let mut counts = [0_u16; 16];
+    for &length in lengths {
+        counts[usize::from(length)] += 1;
+    }
+    counts[0] = 0;
+    let mut next = [0_u16; 16];
+    let mut code = 0_u16;
+    for bits in 1..16 {
+        code = (code + counts[bits - 1]) << 1;
+        next[bits] = code;
+    }
+    lengths
+        .iter()
+        .map(|&length| {
+            if length == 0 {
+                0
+            } else {
+                let code = next[usize::from(length)];
+                next[usize::from(length)] += 1;
+                code
+            }
+        })
+        .collect()
+}
+
+fn fixed_litlen_lengths() -> Vec<u8> {
+    let mut lengths = vec![8_u8; 288];
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths
+}
+
+fn fixed_distance_lengths() -> Vec<u8> {
+    vec![5_u8; 32]
+}
+
+/// Run-length encodes code lengths with symbols 16, 17 and 18.
+fn encode_code_lengths(lengths: &[u8]) -> Vec<(u8, u8)> {
+    let mut symbols = Vec::new();
+    let mut index = 0;
+    while index < lengths.len() {
+        let value = lengths[index];
+        let run = lengths[index..].iter().take_while(|&&v| v == value).count();
+        if value == 0 && run >= 3 {
+            let run = run.min(138);
+            if run <= 10 {
+                symbols.push((17, u8::try_from(run - 3).expect("short zero run fits u8")));
+            } else {
+                symbols.push((18, u8::try_from(run - 11).expect("long zero run fits u8")));
+            }
+            index += run;
+        } else if value != 0 && run >= 4 {
+            symbols.push((value, 0));
+            let repeat = (run - 1).min(6);
+            symbols.push((16, u8::try_from(repeat - 3).expect("repeat run fits u8")));
+            index += 1 + repeat;
+        } else {
+            symbols.push((value, 0));
+            index += 1;
+        }
+    }
+    symbols
+}
+
+struct DynamicHeader {
+    litlen_lengths: Vec<u8>,
+    distance_lengths: Vec<u8>,
+    code_length_lengths: Vec<u8>,
+    symbols: Vec<(u8, u8)>,
+    hclen: usize,
+}
+
+impl DynamicHeader {
+    fn new(litlen_frequencies: &[u32], distance_frequencies: &[u32]) -> Self {
+        let mut litlen_lengths = huffman_lengths(litlen_frequencies, MAX_CODE_BITS);
+        let mut distance_lengths = huffman_lengths(distance_frequencies, MAX_CODE_BITS);
+        // Some decoders reject a distance tree with a single code, so always provide two.
+        let used_distances = distance_lengths.iter().filter(|&&len| len > 0).count();
+        if used_distances < 2 {
+            let used = distance_lengths
+                .iter()
+                .position(|&len| len > 0)
+                .unwrap_or(0);
+            distance_lengths[used] = 1;
+            distance_lengths[usize::from(used == 0)] = 1;
+        }
+        let hlit = litlen_lengths
+            .iter()
+            .rposition(|&length| length > 0)
+            .map_or(257, |last| (last + 1).max(257));
+        let hdist = distance_lengths
+            .iter()
+            .rposition(|&length| length > 0)
+            .map_or(1, |last| last + 1);
+        litlen_lengths.truncate(hlit);
+        distance_lengths.truncate(hdist);
+
+        let mut combined = litlen_lengths.clone();
+        combined.extend_from_slice(&distance_lengths);
+        let symbols = encode_code_lengths(&combined);
+        let mut code_length_frequencies = [0_u32; 19];
+        for &(symbol, _) in &symbols {
+            code_length_frequencies[usize::from(symbol)] += 1;
+        }
+        let code_length_lengths = huffman_lengths(&code_length_frequencies, 7);
+        let hclen = CODE_LENGTH_ORDER
+            .iter()
+            .rposition(|&symbol| code_length_lengths[symbol] > 0)
+            .map_or(4, |last| (last + 1).max(4));
+        Self {
+            litlen_lengths,
+            distance_lengths,
+            code_length_lengths,
+            symbols,
+            hclen,
+        }
+    }
+
+    fn header_bits(&self) -> usize {
+        let mut bits = 5 + 5 + 4 + 3 * self.hclen;
+        for &(symbol, _) in &self.symbols {
+            bits += usize::from(self.code_length_lengths[usize::from(symbol)]);
+            bits += match symbol {
+                16 => 2,
+                17 => 3,
+                18 => 7,
+                _ => 0,
+            };
+        }
+        bits
+    }
+
+    fn write(&self, writer: &mut BitWriter) {
+        let hlit = self.litlen_lengths.len() - 257;
+        let hdist = self.distance_lengths.len() - 1;
+        writer.write(u32::try_from(hlit).expect("HLIT fits u32"), 5);
+        writer.write(u32::try_from(hdist).expect("HDIST fits u32"), 5);
+        writer.write(u32::try_from(self.hclen - 4).expect("HCLEN fits u32"), 4);
+        for &symbol in &CODE_LENGTH_ORDER[..self.hclen] {
+            writer.write(u32::from(self.code_length_lengths[symbol]), 3);
+        }
+        let codes = canonical_codes(&self.code_length_lengths);
+        for &(symbol, extra) in &self.symbols {
+            let symbol_index = usize::from(symbol);
+            writer.write_code(codes[symbol_index], self.code_length_lengths[symbol_index]);
+            match symbol {
+                16 => writer.write(u32::from(extra), 2),
+                17 => writer.write(u32::from(extra), 3),
+                18 => writer.write(u32::from(extra), 7),
+                _ => {}
+            }
+        }
+    }
+}
+
+fn token_bits(tokens: &[Token], litlen_lengths: &[u8], distance_lengths: &[u8]) -> usize {
+    let mut bits = usize::from(litlen_lengths[END_OF_BLOCK]);
+    for &token in tokens {
+        match token {
+            Token::Literal(byte) => bits += usize::from(litlen_lengths[usize::from(byte)]),
+            Token::Match { length, distance } => {
+                let length_index = length_code(length);
+                let distance_index = distance_code(distance);
+                bits += usize::from(litlen_lengths[257 + length_index])
+                    + usize::from(LENGTH_EXTRA[length_index])
+                    + usize::from(distance_lengths[distance_index])
+                    + usize::from(DIST_EXTRA[distance_index]);
+            }
+        }
+    }
+    bits
+}
+
+fn write_tokens(
+    writer: &mut BitWriter,
+    tokens: &[Token],
+    litlen_lengths: &[u8],
+    distance_lengths: &[u8],
+) {
+    let litlen_codes = canonical_codes(litlen_lengths);
+    let distance_codes = canonical_codes(distance_lengths);
+    for &token in tokens {
+        match token {
+            Token::Literal(byte) => {
+                let symbol = usize::from(byte);
+                writer.write_code(litlen_codes[symbol], litlen_lengths[symbol]);
+            }
+            Token::Match { length, distance } => {
+                let length_index = length_code(length);
+                let symbol = 257 + length_index;
+                writer.write_code(litlen_codes[symbol], litlen_lengths[symbol]);
+                writer.write(
+                    u32::from(length - LENGTH_BASE[length_index]),
+                    LENGTH_EXTRA[length_index],
+                );
+                let distance_index = distance_code(distance);
+                writer.write_code(
+                    distance_codes[distance_index],
+                    distance_lengths[distance_index],
+                );
+                writer.write(
+                    u32::from(distance - DIST_BASE[distance_index]),
+                    DIST_EXTRA[distance_index],
+                );
+            }
+        }
+    }
+    writer.write_code(litlen_codes[END_OF_BLOCK], litlen_lengths[END_OF_BLOCK]);
+}
+
+fn write_block(writer: &mut BitWriter, tokens: &[Token], raw: &[u8], last: bool) {
+    let mut litlen_frequencies = vec![0_u32; LITLEN_CODES];
+    let mut distance_frequencies = vec![0_u32; DIST_CODES];
+    litlen_frequencies[END_OF_BLOCK] = 1;
+    for &token in tokens {
+        match token {
+            Token::Literal(byte) => litlen_frequencies[usize::from(byte)] += 1,
+            Token::Match { length, distance } => {
+                litlen_frequencies[257 + length_code(length)] += 1;
+                distance_frequencies[distance_code(distance)] += 1;
+            }
+        }
+    }
+
+    let header = DynamicHeader::new(&litlen_frequencies, &distance_frequencies);
+    let dynamic_bits =
+        header.header_bits() + token_bits(tokens, &header.litlen_lengths, &header.distance_lengths);
+    let fixed_litlen = fixed_litlen_lengths();
+    let fixed_distance = fixed_distance_lengths();
+    let fixed_bits = token_bits(tokens, &fixed_litlen, &fixed_distance);
+    let stored_bits = (raw.len() / 0xffff + 1) * 32 + raw.len() * 8 + 7;
+
+    let final_flag = u32::from(last);
+    if stored_bits < dynamic_bits.min(fixed_bits) {
+        let chunks = raw.chunks(0xffff).collect::<Vec<_>>();
+        let chunk_count = chunks.len().max(1);
+        for index in 0..chunk_count {
+            let chunk = chunks.get(index).copied().unwrap_or(&[]);
+            let is_last = last && index + 1 == chunk_count;
+            writer.write(u32::from(is_last), 1);
+            writer.write(0, 2);
+            writer.align();
+            let length = u16::try_from(chunk.len()).expect("stored chunk fits u16");
+            writer.bytes.extend_from_slice(&length.to_le_bytes());
+            writer.bytes.extend_from_slice(&(!length).to_le_bytes());
+            writer.bytes.extend_from_slice(chunk);
+        }
+    } else if fixed_bits <= dynamic_bits {
+        writer.write(final_flag, 1);
+        writer.write(1, 2);
+        write_tokens(writer, tokens, &fixed_litlen, &fixed_distance);
+    } else {
+        writer.write(final_flag, 1);
+        writer.write(2, 2);
+        header.write(writer);
+        write_tokens(
+            writer,
+            tokens,
+            &header.litlen_lengths,
+            &header.distance_lengths,
+        );
+    }
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    buffer: u64,
+    count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            position: 0,
+            buffer: 0,
+            count: 0,
+        }
+    }
+
+    fn refill(&mut self) {
+        while self.count <= 56 {
+            let Some(&byte) = self.data.get(self.position) else {
+                break;
+            };
+            self.buffer |= u64::from(byte) << self.count;
+            self.position += 1;
+            self.count += 8;
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn peek(&mut self, bits: u8) -> u32 {
+        if self.count < u32::from(bits) {
+            self.refill();
+        }
+        (self.buffer & ((1_u64 << bits) - 1)) as u32
+    }
+
+    fn consume(&mut self, bits: u8) -> Result<(), InflateError> {
+        if self.count < u32::from(bits) {
+            return Err(InflateError::new("unexpected end of stream"));
+        }
+        self.buffer >>= bits;
+        self.count -= u32::from(bits);
+        Ok(())
+    }
+
+    fn read(&mut self, bits: u8) -> Result<u32, InflateError> {
+        if bits == 0 {
+            return Ok(0);
+        }
+        let value = self.peek(bits);
+        self.consume(bits)?;
+        Ok(value)
+    }
+
+    fn align(&mut self) {
+        let drop = self.count % 8;
+        self.buffer >>= drop;
+        self.count -= drop;
+    }
+
+    /// Number of whole input bytes consumed so far.
+    fn consumed_bytes(&self) -> usize {
+        self.position - (self.count / 8) as usize
+    }
+}
+
+/// Table-driven canonical Huffman decoder indexed by reversed code bits.
+struct HuffmanTable {
+    entries: Vec<(u16, u8)>,
+    bits: u8,
+}
+
+impl HuffmanTable {
+    fn new(lengths: &[u8]) -> Result<Self, InflateError> {
+        let bits = lengths.iter().copied().max().unwrap_or(0);
+        if bits == 0 {
+            return Ok(Self {
+                entries: Vec::new(),
+                bits: 0,
+            });
+        }
+        let mut counts = [0_u32; 16];
+        for &length in lengths {
+            counts[usize::from(length)] += 1;
+        }
+        let mut left = 1_i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - i32::try_from(count).expect("code count fits i32");
+            if left < 0 {
+                return Err(InflateError::new("over-subscribed Huffman code"));
+            }
+        }
+        let codes = canonical_codes(lengths);
+        let mut entries = vec![(0_u16, 0_u8); 1 << bits];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length == 0 {
+                continue;
+            }
+            let reversed = usize::from(reverse_bits(codes[symbol], length));
+            let step = 1 << length;
+            let mut index = reversed;
+            while index < entries.len() {
+                entries[index] = (
+                    u16::try_from(symbol).expect("Huffman symbol fits u16"),
+                    length,
+                );
+                index += step;
+            }
+        }
+        Ok(Self { entries, bits })
+    }
+
+    fn decode(&self, reader: &mut BitReader<'_>) -> Result<usize, InflateError> {
+        if self.bits == 0 {
+            return Err(InflateError::new("empty Huffman code used"));
+        }
+        let (symbol, length) = self.entries[reader.peek(self.bits) as usize];
+        if length == 0 {
+            return Err(InflateError::new("invalid Huffman code"));
+        }
+        reader.consume(length)?;
+        Ok(usize::from(symbol))
+    }
+}
+
+fn inflate_with_len(data: &[u8]) -> Result<(Vec<u8>, usize), InflateError> {
+    let mut reader = BitReader::new(data);
+    let mut out = Vec::with_capacity(data.len() * 4);
+    loop {
+        let last = reader.read(1)? == 1;
+        match reader.read(2)? {
+            0 => {
+                reader.align();
+                let length = reader.read(16)?;
+                let complement = reader.read(16)?;
+                if length != !complement & 0xffff {
+                    return Err(InflateError::new("stored block length check failed"));
+                }
+                for _ in 0..length {
+                    out.push(u8::try_from(reader.read(8)?).expect("byte read fits u8"));
+                }
+            }
+            1 => {
+                let litlen = HuffmanTable::new(&fixed_litlen_lengths())?;
+                let distance = HuffmanTable::new(&fixed_distance_lengths())?;
+                inflate_block(&mut reader, &mut out, &litlen, &distance)?;
+            }
+            2 => {
+                let (litlen, distance) = read_dynamic_tables(&mut reader)?;
+                inflate_block(&mut reader, &mut out, &litlen, &distance)?;
+            }
+            _ => return Err(InflateError::new("reserved block type")),
+        }
+        if last {
+            break;
+        }
+    }
+    reader.align();
+    Ok((out, reader.consumed_bytes()))
+}
+
+fn read_dynamic_tables(
+    reader: &mut BitReader<'_>,
+) -> Result<(HuffmanTable, HuffmanTable), InflateError> {
+    let hlit = reader.read(5)? as usize + 257;
+    let hdist = reader.read(5)? as usize + 1;
+    let hclen = reader.read(4)? as usize + 4;
+    if hlit > LITLEN_CODES || hdist > DIST_CODES {
+        return Err(InflateError::new(
+            "too many literal/length or distance codes",
+        ));
+    }
+    let mut code_length_lengths = [0_u8; 19];
+    for &symbol in &CODE_LENGTH_ORDER[..hclen] {
+        code_length_lengths[symbol] = u8::try_from(reader.read(3)?).expect("3-bit length");
+    }
+    let code_length_table = HuffmanTable::new(&code_length_lengths)?;
+    let mut lengths = Vec::with_capacity(hlit + hdist);
+    while lengths.len() < hlit + hdist {
+        let symbol = code_length_table.decode(reader)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (u8::try_from(symbol).expect("literal length fits u8"), 1),
+            16 => {
+                let previous = *lengths
+                    .last()
+                    .ok_or_else(|| InflateError::new("repeat with no previous length"))?;
+                (previous, 3 + reader.read(2)? as usize)
+            }
+            17 => (0, 3 + reader.read(3)? as usize),
+            18 => (0, 11 + reader.read(7)? as usize),
+            _ => return Err(InflateError::new("invalid code length symbol")),
+        };
+        if lengths.len() + repeat > hlit + hdist {
+            return Err(InflateError::new("code length repeat overflows table"));
+        }
+        lengths.extend(std::iter::repeat_n(value, repeat));
+    }
+    if lengths[END_OF_BLOCK] == 0 {
+        return Err(InflateError::new("missing end-of-block code"));
+    }
+    Ok((
+        HuffmanTable::new(&lengths[..hlit])?,
+        HuffmanTable::new(&lengths[hlit..])?,
+    ))
+}
+
+fn inflate_block(
+    reader: &mut BitReader<'_>,
+    out: &mut Vec<u8>,
+    litlen: &HuffmanTable,
+    distance: &HuffmanTable,
+) -> Result<(), InflateError> {
+    loop {
+        let symbol = litlen.decode(reader)?;
+        match symbol {
+            0..=255 => out.push(u8::try_from(symbol).expect("literal fits u8")),
+            END_OF_BLOCK => return Ok(()),
+            257..=285 => {
+                let length_index = symbol - 257;
+                let length = usize::from(LENGTH_BASE[length_index])
+                    + reader.read(LENGTH_EXTRA[length_index])? as usize;
+                let distance_index = distance.decode(reader)?;
+                if distance_index >= DIST_CODES {
+                    return Err(InflateError::new("invalid distance symbol"));
+                }
+                let distance = usize::from(DIST_BASE[distance_index])
+                    + reader.read(DIST_EXTRA[distance_index])? as usize;
+                if distance > out.len() {
+                    return Err(InflateError::new("distance reaches before output start"));
+                }
+                let start = out.len() - distance;
+                if distance >= length {
+                    out.extend_from_within(start..start + length);
+                } else {
+                    for offset in 0..length {
+                        out.push(out[start + offset]);
+                    }
+                }
+            }
+            _ => return Err(InflateError::new("invalid literal/length symbol")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Compression, adler32, compress, crc32, decompress, deflate, inflate};
+
+    fn sample_data() -> Vec<u8> {
+        let mut data = Vec::new();
+        for index in 0_u32..20_000 {
+            data.extend_from_slice(format!("row {} value {}\n", index % 37, index * 7).as_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn checksums_match_reference_values() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn zlib_round_trips_at_every_compression_level() {
+        let data = sample_data();
+        for compression in [Compression::Fast, Compression::Default, Compression::Best] {
+            let packed = compress(&data, compression);
+            assert!(packed.len() < data.len() / 4);
+            assert_eq!(decompress(&packed).expect("inflate"), data);
+        }
+    }
+
+    #[test]
+    #[allow(clippy::cast_possible_truncation)]
+    fn incompressible_and_empty_inputs_round_trip() {
+        let mut state = 0x1234_5678_u32;
+        let noise = (0..100_000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            inflate(&deflate(&noise, Compression::Default)).unwrap(),
+            noise
+        );
+        assert_eq!(
+            inflate(&deflate(&[], Compression::Default)).unwrap(),
+            Vec::<u8>::new()
+        );
+        assert_eq!(
+            decompress(&compress(&[7], Compression::Fast)).unwrap(),
+            vec![7]
+        );
+    }
+
+    #[test]
+    fn inflates_reference_fixed_huffman_stream() {
+        // `zlib.compress(b"hello hello hello")` from CPython.
+        let stream = [
+            0x78, 0x9c, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x90, 0x00, 0x3a, 0x2e,
+            0x06, 0x7d,
+        ];
+        assert_eq!(decompress(&stream).unwrap(), b"hello hello hello");
+    }
+
+    #[test]
+    fn corrupt_streams_return_errors() {
+        let mut packed = compress(&sample_data(), Compression::Default);
+        let last = packed.len() - 1;
+        packed[last] ^= 0xff;
+        assert!(decompress(&packed).is_err());
+        assert!(decompress(&[0x78, 0x9c]).is_err());
+        assert!(inflate(&[0xff, 0xff, 0xff]).is_err());
+    }
+}
@@ -1,4 +1,5 @@
 use crate::graphics::{
+    codec::{self, ExrCompression, ExrError, ExrImage, ExrPixelType, PngImage},
     colors::{LinearRgb, Rgb},
     lighting::Lighting,
 };
@@ -408,10 +409,89 @@ impl HdrImage {
         Ok(())
     }
 
+    /// Saves the image as a ZIP-compressed half-float `OpenEXR` file with `R`, `G`, `B` channels.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the underlying I/O fails.
+    pub fn save_exr(&self, file_name: &str) -> io::Result<()> {
+        self.save_exr_with(file_name, ExrPixelType::Half, ExrCompression::Zip)
+    }
+
+    /// Saves the image as an `OpenEXR` file with explicit channel storage and compression.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the underlying I/O fails.
+    pub fn save_exr_with(
+        &self,
+        file_name: &str,
+        pixel_type: ExrPixelType,
+        compression: ExrCompression,
+    ) -> io::Result<()> {
+        ExrImage::from_hdr_image(self, pixel_type).save(file_name, compression)?;
+        Ok(())
+    }
+
+    /// Loads the default RGB layer of an `OpenEXR` file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the file cannot be read or decoded, or has no RGB channels.
+    pub fn from_exr(path: impl AsRef<std::path::Path>) -> Result<Self, ExrError> {
+        ExrImage::open(path)?.to_hdr_image()
+    }
+
+    /// Loads a Radiance RGBE `.hdr` file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the file cannot be read, is malformed, or uses an unsupported pixel
+    /// format or orientation.
+    pub fn from_radiance_hdr(path: impl AsRef<std::path::Path>) -> io::Result<Self> {
+        codec::hdr::read_radiance_hdr(path)
+    }
+
+    /// Loads a portable float-map `.pfm` file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the file cannot be read or is malformed.
+    pub fn from_pfm(path: impl AsRef<std::path::Path>) -> io::Result<Self> {
+        codec::hdr::read_pfm(path)
+    }
+
+    /// Loads an HDR image by extension.
+    ///
+    /// `.hdr` and `.rgbe` are read as Radiance RGBE, `.pfm` as a portable float map, and `.exr`
+    /// as the default `OpenEXR` RGB layer.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`io::ErrorKind::Unsupported`] for other extensions, or any error from the
+    /// matching decoder.
+    pub fn open(path: impl AsRef<std::path::Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        match path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase)
+            .as_deref()
+        {
+            Some("hdr" | "rgbe") => Self::from_radiance_hdr(path),
+            Some("pfm") => Self::from_pfm(path),
+            Some("exr") => Ok(Self::from_exr(path)?),
+            _ => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("{} is not a supported HDR image", path.display()),
+            )),
+        }
+    }
+
     /// Saves this image by extension.
     ///
-    /// `.pfm`, `.hdr`, and `.rgbe` preserve HDR data. Other extensions are tone-mapped with
-    /// default settings and delegated to [`Canvas::save_extension`].
+    /// `.pfm`, `.hdr`, `.rgbe`, and `.exr` preserve HDR data. Other extensions are tone-mapped
+    /// with default settings and delegated to [`Canvas::save_extension`].
     ///
     /// # Errors
     ///
@@ -425,6 +505,7 @@ impl HdrImage {
         {
             Some("pfm") => self.save_pfm(file_name),
             Some("hdr" | "rgbe") => self.save_radiance_hdr(file_name),
+            Some("exr") => self.save_exr(file_name),
             _ => self.to_canvas().save_extension(file_name),
         }
     }
@@ -1781,14 +1862,40 @@ impl Canvas {
         Ok(())
     }
 
-    /// Saves the current state of an image as any format supported by `ImageMagick`.
+    /// Saves the current state of an image as an 8-bit RGB PNG file.
     ///
     /// # Arguments
     ///
     /// * `file_name` - The name of the file that will be created.
+    ///   Should end in ".png".
     ///
     /// # Errors
-    /// Returns `Err` if `magick` is not installed or the output extension is unsupported.
+    /// Returns `Err` if the canvas has a zero dimension or the underlying I/O fails.
+    ///
+    /// # Examples
+    ///
+    /// Basic usage:
+    /// ```no_run
+    /// use crate::gartus::prelude::{Canvas, Rgb};
+    /// let image = Canvas::new(500, 500, Rgb::default());
+    /// image.save_png("test.png").expect("Could not save file");
+    /// ```
+    pub fn save_png(&self, file_name: &str) -> io::Result<()> {
+        PngImage::from_canvas(self).save(file_name)?;
+        Ok(())
+    }
+
+    /// Saves the current state of an image, choosing the format from the file extension.
+    ///
+    /// `.png` and `.ppm` are written natively. Any other extension is delegated to `ImageMagick`.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_name` - The name of the file that will be created.
+    ///
+    /// # Errors
+    /// Returns `Err` if the file cannot be written, or for other extensions if `magick` is not
+    /// installed or does not support the output extension.
     ///
     /// # Examples
     ///
@@ -1799,6 +1906,19 @@ impl Canvas {
     /// image.save_extension("pics/test.png").expect("Could not save file")
     /// ```
     pub fn save_extension(&self, file_name: &str) -> io::Result<()> {
+        match std::path::Path::new(file_name)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase)
+            .as_deref()
+        {
+            Some("png") => self.save_png(file_name),
+            Some("ppm") => self.save_binary(file_name),
+            _ => self.save_with_imagemagick(file_name),
+        }
+    }
+
+    fn save_with_imagemagick(&self, file_name: &str) -> io::Result<()> {
         let mut child = Command::new("magick")
             .args(["-", file_name])
             .stdin(Stdio::piped())
@@ -2200,6 +2320,28 @@ mod tests {
         assert_eq!(&bytes[bytes.len() - 4..], &[128, 64, 32, 129]);
     }
 
+    #[test]
+    fn hdr_image_round_trips_exr_by_extension() {
+        let path =
+            std::env::temp_dir().join(format!("gartus-hdr-image-{}.exr", std::process::id()));
+        let image = HdrImage::from_pixels(
+            2,
+            1,
+            vec![
+                LinearRgb::new(16.0, 0.5, 0.25),
+                LinearRgb::new(0.0, 1.0, 2.0),
+            ],
+        );
+
+        image
+            .save_extension(path.to_str().expect("temp path should be utf8"))
+            .expect("write exr");
+        let loaded = HdrImage::from_exr(&path);
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(loaded.expect("read exr"), image);
+    }
+
     #[test]
     fn from_fn_visits_pixels_in_storage_order() {
         let canvas = Canvas::from_fn(2, 2, |x, y| match (x, y) {
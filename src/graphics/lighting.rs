@@ -346,6 +346,72 @@ impl LightAttenuation {
     }
 }
 
+/// Angular falloff for a spotlight cone.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpotCone {
+    direction: Vector,
+    cos_inner: f64,
+    cos_outer: f64,
+    falloff: f64,
+}
+
+impl SpotCone {
+    /// Creates a cone around `direction` that is fully lit inside `inner_degrees` and dark outside
+    /// `outer_degrees`, both measured from the axis. Between them, intensity follows a smoothstep
+    /// raised to `falloff`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `direction` is zero or not finite, if the angles are not
+    /// `0 <= inner_degrees <= outer_degrees <= 180`, or if `falloff` is not positive and finite.
+    #[must_use]
+    pub fn new(direction: Vector, inner_degrees: f64, outer_degrees: f64, falloff: f64) -> Self {
+        let length = direction.length();
+        assert!(
+            length.is_finite() && length > 0.0,
+            "spotlight direction must be non-zero and finite"
+        );
+        assert!(
+            0.0 <= inner_degrees && inner_degrees <= outer_degrees && outer_degrees <= 180.0,
+            "spotlight angles must satisfy 0 <= inner <= outer <= 180 degrees"
+        );
+        assert!(
+            falloff.is_finite() && falloff > 0.0,
+            "spotlight falloff must be positive and finite"
+        );
+        Self {
+            direction: direction / length,
+            cos_inner: inner_degrees.to_radians().cos(),
+            cos_outer: outer_degrees.to_radians().cos(),
+            falloff,
+        }
+    }
+
+    /// Unit cone axis, pointing the way the light travels.
+    #[must_use]
+    pub const fn direction(&self) -> Vector {
+        self.direction
+    }
+
+    /// Returns the intensity scale in `[0, 1]` for light leaving along `outgoing`.
+    #[must_use]
+    pub fn factor(&self, outgoing: Vector) -> f64 {
+        let length = outgoing.length();
+        if length < f64::EPSILON {
+            return 0.0;
+        }
+        let cos_theta = self.direction.dot(outgoing) / length;
+        if cos_theta >= self.cos_inner {
+            return 1.0;
+        }
+        if cos_theta <= self.cos_outer {
+            return 0.0;
+        }
+        let t = (cos_theta - self.cos_outer) / (self.cos_inner - self.cos_outer);
+        (t * t * (3.0 - 2.0 * t)).powf(self.falloff)
+    }
+}
+
 /// A light source with a position/direction and RGB color.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct PointLight {
@@ -357,6 +423,8 @@ pub struct PointLight {
     pub kind: LightKind,
     /// Distance falloff for positional lights.
     pub attenuation: LightAttenuation,
+    /// Optional spotlight cone for positional lights.
+    pub spot: Option<SpotCone>,
 }
 
 impl PointLight {
@@ -374,6 +442,7 @@ impl PointLight {
             color,
             kind: LightKind::Positional,
             attenuation: LightAttenuation::None,
+            spot: None,
         }
     }
 
@@ -385,6 +454,7 @@ impl PointLight {
             color,
             kind: LightKind::Directional,
             attenuation: LightAttenuation::None,
+            spot: None,
         }
     }
 
@@ -402,6 +472,13 @@ impl PointLight {
         self
     }
 
+    /// Returns this light limited to a spotlight cone. Directional lights ignore the cone.
+    #[must_use]
+    pub const fn with_spot_cone(mut self, spot: SpotCone) -> Self {
+        self.spot = Some(spot);
+        self
+    }
+
     /// Returns this light with explicit attenuation.
     #[must_use]
     pub const fn with_attenuation(mut self, attenuation: LightAttenuation) -> Self {
@@ -518,6 +595,7 @@ impl Lighting {
                     position,
                     kind: point_light.kind,
                     attenuation: point_light.attenuation,
+                    spot: point_light.spot,
                     diffuse: [
                         point[0] * diffuse_reflection[0],
                         point[1] * diffuse_reflection[1],
@@ -550,6 +628,7 @@ struct PreparedPointLight {
     position: Vector,
     kind: LightKind,
     attenuation: LightAttenuation,
+    spot: Option<SpotCone>,
     diffuse: [f64; 3],
     specular: [f64; 3],
 }
@@ -592,7 +671,10 @@ impl PreparedLighting {
                     } else {
                         light_vector / light_length
                     };
-                    (light, point_light.attenuation.factor(light_length))
+                    let cone = point_light
+                        .spot
+                        .map_or(1.0, |spot| spot.factor(-light_vector));
+                    (light, cone * point_light.attenuation.factor(light_length))
                 }
                 LightKind::Directional => (point_light.position, 1.0),
             };
@@ -695,6 +777,30 @@ mod tests {
         assert_eq!(lighting.specular_exponent, DEFAULT_SPECULAR_EXPONENT);
     }
 
+    #[test]
+    fn spot_cone_lights_only_points_inside_its_cone() {
+        let spot = SpotCone::new(Vector::new(0.0, -1.0, 0.0), 20.0, 40.0, 1.0);
+        let lighting = Lighting {
+            ambient: Rgb::BLACK,
+            point_lights: vec![
+                PointLight::positional(Vector::new(0.0, 10.0, 0.0), Rgb::WHITE)
+                    .with_spot_cone(spot),
+            ],
+            ..Lighting::default()
+        };
+        let up = Vector::new(0.0, 1.0, 0.0);
+
+        let inside = lighting.illuminate_at(up, Vector::new(0.0, 0.0, 0.0));
+        let edge =
+            lighting.illuminate_at(up, Vector::new(10.0 * 30_f64.to_radians().tan(), 0.0, 0.0));
+        let outside = lighting.illuminate_at(up, Vector::new(10.0, 0.0, 0.0));
+
+        assert!(inside.red > edge.red && edge.red > 0);
+        assert_eq!(outside, Rgb::BLACK);
+        assert!((spot.factor(Vector::new(0.0, -1.0, 0.0)) - 1.0).abs() < 1e-12);
+        assert!(spot.factor(Vector::new(0.0, 1.0, 0.0)).abs() < 1e-12);
+    }
+
     #[test]
     fn material_presets_keep_source_values() {
         assert_eq!(
@@ -16,7 +16,8 @@
 //! [`SamplingTargetList`](crate::graphics::raytracing::SamplingTargetList) with
 //! [`PathTracer::render_with_lights`](crate::graphics::raytracing::PathTracer::render_with_lights)
 //! to importance-sample real emitters or other important geometry without mixing those targets into
-//! the world intersection container.
+//! the world intersection container, or [`LightTree`](crate::graphics::raytracing::LightTree) when
+//! a scene has many emitters.
 
 #[cfg(feature = "spectral")]
 pub use crate::graphics::camera::RenderTransportMode;
@@ -28,17 +29,25 @@ pub use crate::{
     },
     graphics::display::{HdrImage, ToneMap, ToneMappingOperator},
 };
+pub(crate) mod bdpt;
 mod bvh;
+pub mod checkpoint;
+pub mod curve;
+pub mod denoise;
 pub mod environment;
+pub mod hair;
 pub mod instance;
+pub mod light_tree;
 pub mod material;
 pub mod mesh;
 pub mod object;
 pub mod pdf;
+pub mod photon;
 pub mod renderer;
 pub mod scene;
 pub mod scenes;
 pub mod sdf;
+pub mod sky;
 #[cfg(feature = "spectral")]
 pub mod spectrum;
 pub mod texture;
@@ -50,21 +59,28 @@ use crate::{
     graphics::colors::{LinearRgb, Rgb},
 };
 pub use bvh::{BvhBuildOptions, BvhTraversalStats};
+pub use checkpoint::RenderCheckpoint;
+pub use curve::CurveSet;
+pub use denoise::Denoiser;
 pub use environment::EnvironmentLight;
-pub use instance::{MatrixInstance, RotateY, Translate};
+pub use hair::HairBsdf;
+pub use instance::{MatrixInstance, MotionInstance, RotateY, Translate};
+pub use light_tree::LightTree;
 pub use material::{
-    Dielectric, DiffuseLight, GgxMicrofacet, HenyeyGreenstein, Isotropic, Lambertian,
-    LayeredDiffuseGgx, Material, MaterialRef, Metal, RayMaterial, ScatterRecord,
+    BsdfFlags, BsdfSample, Dielectric, DiffuseLight, GgxMicrofacet, HenyeyGreenstein, Isotropic,
+    Lambertian, LayeredDiffuseGgx, Material, MaterialRef, Metal, RayMaterial, ScatterRecord,
 };
 pub use mesh::{MeshTriangle, TriangleMesh};
 pub use object::{
     HitRecord, Hittable, Intersect, Interval, MovingSphere, PdfContext, Quad, RayGeometry,
-    SceneObject, Sphere, SurfaceHit, box_object, hit_sphere, hit_sphere_in_interval, hit_triangle,
+    SceneObject, Sphere, SurfaceHit, SurfaceSample, box_object, hit_sphere, hit_sphere_in_interval,
+    hit_triangle,
 };
 pub use pdf::{
     CosinePdf, GgxReflectionPdf, HenyeyGreensteinPdf, HittablePdf, MaterialPdf, MixturePdf, Pdf,
     SpherePdf,
 };
+pub use photon::PhotonMappingOptions;
 pub use renderer::{PathTracer, RenderOptions};
 #[doc(hidden)]
 pub use scene::SphereList;
@@ -74,6 +90,7 @@ pub use scene::{
     WeightedSamplingTargetList,
 };
 pub use sdf::{DistanceField, DistanceFieldRef, FnDistanceField, SdfObject};
+pub use sky::PhysicalSky;
 #[cfg(feature = "spectral")]
 pub use spectrum::{
     ConductorFresnel, ConductorOpticalConstants, DielectricFresnel, MeasuredSpectrum,
@@ -86,33 +103,40 @@ pub use texture::{
     SolidColor, TextureRef,
 };
 pub use volume::{
-    ConstantDensity, ConstantMedium, CurlNoiseField, DensityField, DensityFieldRef,
-    DomainWarpedDensityField, ExtractedSurface, FluidParticle, FnDensityField, GridBounds,
+    BlackbodyEmission, ChromaticMedium, ConstantDensity, ConstantMedium, CurlNoiseField,
+    DensityField, DensityFieldRef, DensityRange, DomainWarpedDensityField, EmissionField,
+    EmissionFieldRef, ExtractedSurface, FluidParticle, FnDensityField, GridBounds,
     GridDensityField, GridDensityMetadata, GridInterpolation, LiquidSurface, MacCellFlags,
-    MacFluidEmitter, MacFluidGrid2, MacFluidGrid3, MacProjectionStats, MacScalarAdvection,
-    MacScalarGrid3, MacStepStats, MarchingCubes, NonUniformMedium, ParticleSplatField,
-    ProceduralDensityField, ProceduralDensityPreset, SplatKernel, StableFluidEmitter,
-    StableFluidGrid2,
+    MacFlipLiquid3, MacFlipParticle, MacFluidEmitter, MacFluidGrid2, MacFluidGrid3,
+    MacParticleTransfer, MacPressurePreconditioner, MacProjectionStats, MacScalarAdvection,
+    MacScalarGrid3, MacStepStats, MajorantGrid, MarchingCubes, MediumCoefficients,
+    NonUniformMedium, ParticleSplatField, ProceduralDensityField, ProceduralDensityPreset,
+    ScalarEmission, SplatKernel, StableFluidEmitter, StableFluidGrid2, blackbody_linear_rgb,
+    blackbody_spectral_radiance,
 };
 
 /// Common ray-tracing types for `use gartus::graphics::raytracing::prelude::*`.
 pub mod prelude {
     pub use super::{
-        BvhBuildOptions, BvhNode, BvhTraversalStats, ConstantDensity, ConstantMedium,
-        CurlNoiseField, DenoisingAovs, DensityField, DensityFieldRef, Dielectric, DiffuseLight,
-        DistanceField, DistanceFieldRef, DomainWarpedDensityField, EnvironmentLight,
+        BlackbodyEmission, BvhBuildOptions, BvhNode, BvhTraversalStats, ChromaticMedium,
+        ConstantDensity, ConstantMedium, CurlNoiseField, CurveSet, Denoiser, DenoisingAovs,
+        DensityField, DensityFieldRef, DensityRange, Dielectric, DiffuseLight, DistanceField,
+        DistanceFieldRef, DomainWarpedDensityField, EmissionField, EnvironmentLight,
         ExtractedSurface, FluidParticle, FnDensityField, FnDistanceField, GgxMicrofacet,
         GgxReflectionPdf, GridBounds, GridDensityField, GridDensityMetadata, GridInterpolation,
-        HenyeyGreenstein, HenyeyGreensteinPdf, Hittable, HittableLayers, HittableList, Lambertian,
-        LayeredDiffuseGgx, LinearColor, LiquidSurface, MacCellFlags, MacFluidEmitter,
-        MacFluidGrid2, MacFluidGrid3, MacProjectionStats, MacScalarAdvection, MacScalarGrid3,
-        MacStepStats, MarchingCubes, MaterialId, MaterialRef, MatrixInstance, Metal,
-        NonUniformMedium, NormalMap, NormalMapGreenChannel, NormalMapRef, ParticleSplatField,
-        PathTracer, ProceduralDensityField, ProceduralDensityPreset, ProgressiveRenderUpdate, Quad,
-        RayGeometry, RayMaterial, RayPrimitive, RayScene, RaySceneBuilder, RenderOptions,
-        RenderProgress, RenderTile, RotateY, SamplingTargetList, SdfObject, Sphere, SplatKernel,
-        StableFluidEmitter, StableFluidGrid2, SurfaceRayMaterialMapper, SurfaceRayMaterialMode,
-        Translate, TriangleMesh, WeightedSamplingTargetList, box_object,
+        HairBsdf, HenyeyGreenstein, HenyeyGreensteinPdf, Hittable, HittableLayers, HittableList,
+        Lambertian, LayeredDiffuseGgx, LightTree, LinearColor, LiquidSurface, MacCellFlags,
+        MacFlipLiquid3, MacFlipParticle, MacFluidEmitter, MacFluidGrid2, MacFluidGrid3,
+        MacParticleTransfer, MacPressurePreconditioner, MacProjectionStats, MacScalarAdvection,
+        MacScalarGrid3, MacStepStats, MajorantGrid, MarchingCubes, MaterialId, MaterialRef,
+        MatrixInstance, MediumCoefficients, Metal, MotionInstance, NonUniformMedium, NormalMap,
+        NormalMapGreenChannel, NormalMapRef, ParticleSplatField, PathTracer, PhotonMappingOptions,
+        PhysicalSky, ProceduralDensityField, ProceduralDensityPreset, ProgressiveRenderUpdate,
+        Quad, RayGeometry, RayMaterial, RayPrimitive, RayScene, RaySceneBuilder, RenderCheckpoint,
+        RenderOptions, RenderProgress, RenderTile, RotateY, SamplingTargetList, ScalarEmission,
+        SdfObject, Sphere, SplatKernel, StableFluidEmitter, StableFluidGrid2,
+        SurfaceRayMaterialMapper, SurfaceRayMaterialMode, Translate, TriangleMesh,
+        WeightedSamplingTargetList, box_object,
     };
     #[cfg(feature = "spectral")]
     pub use super::{
@@ -184,7 +208,8 @@ mod tests {
     use super::scenes::*;
     use super::*;
     use crate::gmath::{
-        geometry::{SphereGeometry, TriangleGeometry},
+        edge_matrix::EdgeMatrix,
+        geometry::{CurveGeometry, SphereGeometry, TriangleGeometry},
         matrix::Matrix,
         polygon_matrix::PolygonMatrix,
         ray::Ray,
@@ -1062,6 +1087,157 @@ mod tests {
         );
     }
 
+    #[test]
+    fn motion_instance_orbits_through_the_bvh_at_each_ray_time() {
+        // Spin an offset child about the orbit center so the path follows the arc.
+        let planet = MotionInstance::new(
+            Translate::new(
+                Sphere::new(Point::new(0.0, 0.0, 0.0), 0.5),
+                Vector::new(2.0, 0.0, 0.0),
+            ),
+            [
+                (0.0, &Matrix::rotate_y(0.0)),
+                (0.5, &Matrix::rotate_y(45.0)),
+                (1.0, &Matrix::rotate_y(90.0)),
+            ],
+        )
+        .expect("rigid keys should decompose");
+        let bounds = planet.bounding_box().expect("motion instance is bounded");
+        assert!(bounds.max.0 >= 2.5 && bounds.min.2 <= -2.5);
+        // The whole quarter orbit stays inside the bounds, not only the key poses.
+        assert!(bounds.max.0 - bounds.min.0 < 3.2 && bounds.max.2 - bounds.min.2 < 3.2);
+
+        let mut list = HittableList::new();
+        list.add(planet);
+        list.add(Sphere::new(Point::new(-5.0, 0.0, 0.0), 0.5));
+        let bvh = list.into_bvh().expect("bounded objects build a BVH");
+
+        let halfway = 2.0 * std::f64::consts::FRAC_1_SQRT_2;
+        for (time, x, z) in [
+            (0.0, 2.0, 0.0),
+            (0.25, 1.847_759, -0.765_367),
+            (1.0, 0.0, -2.0),
+        ] {
+            let ray = Ray::with_time(Point::new(x, 3.0, z), Vector::new(0.0, -1.0, 0.0), time);
+            let record = bvh
+                .hit(&ray, Interval::new(0.0, INFINITY))
+                .expect("ray should hit the planet at its current position");
+            assert!((record.t - 2.5).abs() < 1e-5);
+            assert!((record.normal.y() - 1.0).abs() < 1e-5);
+        }
+
+        let stale = Ray::with_time(Point::new(2.0, 3.0, 0.0), Vector::new(0.0, -1.0, 0.0), 1.0);
+        assert!(bvh.hit(&stale, Interval::new(0.0, INFINITY)).is_none());
+        let midpoint = Ray::with_time(
+            Point::new(halfway, 3.0, -halfway),
+            Vector::new(0.0, -1.0, 0.0),
+            0.5,
+        );
+        assert!(bvh.hit(&midpoint, Interval::new(0.0, INFINITY)).is_some());
+    }
+
+    #[test]
+    fn rigid_motion_instance_forwards_pdfs_at_the_context_time() {
+        let moving = MotionInstance::between(
+            Sphere::new(Point::new(0.0, 0.0, 0.0), 0.5),
+            &Matrix::translate(0.0, 0.0, -2.0),
+            &Matrix::translate(0.0, 0.0, -4.0),
+        )
+        .expect("translations should decompose");
+        let fixed = Sphere::new(Point::new(0.0, 0.0, -3.0), 0.5);
+        let origin = Point::new(0.0, 0.0, 0.0);
+        let direction = Vector::new(0.05, 0.0, -1.0);
+
+        let moving_pdf = moving.pdf_value(PdfContext::new(origin, 0.5), direction);
+        let fixed_pdf = fixed.pdf_value(PdfContext::new(origin, 0.5), direction);
+        assert!(moving_pdf > 0.0);
+        assert_close(moving_pdf, fixed_pdf);
+
+        let scaled = MotionInstance::between(
+            Sphere::new(Point::new(0.0, 0.0, 0.0), 0.5),
+            &Matrix::identity_matrix(4),
+            &Matrix::scale(2.0, 2.0, 2.0),
+        )
+        .expect("scales should decompose");
+        assert_close(
+            scaled.pdf_value(PdfContext::new(origin, 0.5), direction),
+            0.0,
+        );
+    }
+
+    #[test]
+    fn curve_set_hits_per_curve_materials_through_the_bvh() {
+        let strand = |y: f64, z: f64| {
+            CurveGeometry::new(
+                [
+                    Point::new(-2.0, y, z),
+                    Point::new(-0.7, y + 0.1, z),
+                    Point::new(0.7, y - 0.1, z),
+                    Point::new(2.0, y, z),
+                ],
+                [0.08, 0.02],
+            )
+        };
+        let red: MaterialRef = Arc::new(Lambertian::new(LinearColor::new(0.9, 0.1, 0.1)));
+        let blue: MaterialRef = Arc::new(Lambertian::new(LinearColor::new(0.1, 0.1, 0.9)));
+        let curves = CurveSet::with_materials(
+            vec![
+                (strand(0.0, -2.0), 0),
+                (strand(0.0, -3.0), 1),
+                (strand(0.5, -1.0), 1),
+            ],
+            vec![red.clone(), blue.clone()],
+        )
+        .expect("material indices are in range");
+        assert_eq!(curves.len(), 3);
+        assert!(curves.segment_count() > curves.len());
+        assert!(curves.bvh_node_count().is_some());
+        assert!(CurveSet::with_materials(vec![(strand(0.0, 0.0), 2)], vec![red]).is_none());
+
+        let mut rng = SampleRng::new(1);
+        let ray = Ray::new(Point::new(-1.0, 0.0, 2.0), Vector::new(0.0, 0.0, -1.0));
+        let hit = curves
+            .hit_with_rng(&ray, Interval::new(0.001, INFINITY), &mut rng)
+            .expect("front strand hit");
+        let brute = curves
+            .hit_bruteforce(&ray, Interval::new(0.001, INFINITY))
+            .expect("brute-force hit");
+        assert_close(hit.t, brute.t);
+        assert!((hit.t - 4.0).abs() < 0.05);
+        assert!(hit.material.denoise_albedo(&hit).red > 0.5);
+        assert!(hit.tangent.is_some_and(|tangent| tangent.x().abs() > 0.9));
+
+        let behind = curves
+            .hit_with_rng(&ray, Interval::new(hit.t + 0.01, INFINITY), &mut rng)
+            .expect("back strand hit");
+        assert!(behind.material.denoise_albedo(&behind).blue > 0.5);
+    }
+
+    #[test]
+    fn curve_set_from_edge_matrix_follows_bezier_chains() {
+        let mut edges = EdgeMatrix::new();
+        edges.add_bezier3((0.0, 0.0), (1.0, 2.0), (2.0, -2.0), (3.0, 0.0));
+        edges.add_bezier3((0.0, 5.0), (1.0, 6.0), (2.0, 4.0), (3.0, 5.0));
+        let material: MaterialRef = Arc::new(HairBsdf::default());
+        let curves = CurveSet::from_edge_matrix(&edges, [0.05, 0.01], material);
+        assert!(!curves.is_empty());
+
+        let mut rng = SampleRng::new(2);
+        for start in [Point::new(0.0, 0.0, 1.0), Point::new(0.0, 5.0, 1.0)] {
+            let ray = Ray::new(start, Vector::new(0.0, 0.0, -1.0));
+            let hit = curves
+                .hit_with_rng(&ray, Interval::new(0.001, INFINITY), &mut rng)
+                .expect("chain start hit");
+            assert!((hit.t - 1.0).abs() < 0.05);
+        }
+        let between = Ray::new(Point::new(1.5, 2.5, 1.0), Vector::new(0.0, 0.0, -1.0));
+        assert!(
+            curves
+                .hit_with_rng(&between, Interval::new(0.001, INFINITY), &mut rng)
+                .is_none()
+        );
+    }
+
     #[test]
     fn constant_medium_samples_hit_inside_boundary() {
         let boundary = Sphere::new(Point::new(0.0, 0.0, -1.0), 0.5);
@@ -1534,6 +1710,66 @@ mod tests {
         assert!(canvas.upper_left_origin());
     }
 
+    #[test]
+    fn path_tracer_bidirectional_ray_scene_uses_emissive_targets() {
+        let mut scene = RayScene::new();
+        let diffuse = scene.add_material(RayMaterial::lambertian(LinearColor::new(0.6, 0.6, 0.6)));
+        let light = scene.add_material(RayMaterial::diffuse_light(LinearColor::new(4.0, 4.0, 4.0)));
+        scene.add_sphere(Point::new(0.0, 0.0, -1.0), 0.5, diffuse);
+        scene.add_quad(
+            Point::new(-0.5, 1.0, -1.5),
+            Vector::new(1.0, 0.0, 0.0),
+            Vector::new(0.0, 0.0, 1.0),
+            light,
+        );
+        let camera = RayCamera::new(6, 1.0)
+            .with_samples_per_pixel(4)
+            .with_max_depth(3)
+            .with_rng_seed(29);
+        let tracer = PathTracer::new(camera).with_options(RenderOptions::new().tile_size(4));
+
+        let canvas = tracer.render_ray_scene_bidirectional(&scene);
+        let expected = camera
+            .with_sampling_strategy(SamplingStrategy::bidirectional())
+            .render_world_with_lights_tiled(&scene, &scene.emissive_targets(), 4);
+
+        assert_eq!(canvas.pixels(), expected.pixels());
+        assert!(canvas.pixels().iter().any(|pixel| *pixel != Rgb::default()));
+    }
+
+    #[test]
+    fn path_tracer_photon_mapped_ray_scene_uses_emissive_targets() {
+        let mut scene = RayScene::new();
+        let diffuse = scene.add_material(RayMaterial::lambertian(LinearColor::new(0.6, 0.6, 0.6)));
+        let light = scene.add_material(RayMaterial::diffuse_light(LinearColor::new(4.0, 4.0, 4.0)));
+        scene.add_sphere(Point::new(0.0, 0.0, -1.0), 0.5, diffuse);
+        scene.add_quad(
+            Point::new(-0.5, 1.0, -1.5),
+            Vector::new(1.0, 0.0, 0.0),
+            Vector::new(0.0, 0.0, 1.0),
+            light,
+        );
+        let camera = RayCamera::new(6, 1.0)
+            .with_samples_per_pixel(2)
+            .with_max_depth(3)
+            .with_rng_seed(31);
+        let options = PhotonMappingOptions::new().photons_per_pass(500);
+        let tracer = PathTracer::new(camera).with_options(RenderOptions::new().tile_size(4));
+
+        let canvas = tracer.render_ray_scene_photon_mapped(&scene, options);
+        let expected = camera
+            .render_world_photon_mapped_hdr_image_tiled(
+                &scene,
+                &scene.emissive_targets(),
+                options,
+                4,
+            )
+            .to_canvas();
+
+        assert_eq!(canvas.pixels(), expected.pixels());
+        assert!(canvas.pixels().iter().any(|pixel| *pixel != Rgb::default()));
+    }
+
     #[test]
     fn path_tracer_progressive_render_matches_regular_render() {
         let world = normal_sphere_world();
@@ -1635,6 +1871,130 @@ mod tests {
         assert_eq!(canvas.height(), 2);
     }
 
+    #[test]
+    fn quad_and_sphere_surface_samples_match_their_area_pdf() {
+        let quad = Quad::new(
+            Point::new(-1.0, -1.0, -1.0),
+            Vector::new(2.0, 0.0, 0.0),
+            Vector::new(0.0, 2.0, 0.0),
+        );
+        let sphere = Sphere::new(Point::new(0.0, 0.0, -1.0), 0.5);
+        let mut rng = SampleRng::new(41);
+
+        for _ in 0..32 {
+            let sample = quad
+                .sample_surface(0.0, &mut rng)
+                .expect("quad surface sample");
+            assert_close(sample.point.z(), -1.0);
+            assert_close(sample.normal.z(), 1.0);
+            assert_close(sample.pdf, 0.25);
+            assert_close(quad.surface_pdf(sample.point, 0.0), sample.pdf);
+
+            let sample = sphere
+                .sample_surface(0.0, &mut rng)
+                .expect("sphere surface sample");
+            assert_close((sample.point - Point::new(0.0, 0.0, -1.0)).length(), 0.5);
+            assert_close(
+                sample.normal.dot(sample.point - Point::new(0.0, 0.0, -1.0)),
+                0.5,
+            );
+            assert_close(sphere.surface_pdf(sample.point, 0.0), 1.0 / PI);
+        }
+        assert_close(quad.surface_pdf(Point::new(2.0, 0.0, -1.0), 0.0), 0.0);
+        assert_close(quad.surface_pdf(Point::new(0.0, 0.0, -0.5), 0.0), 0.0);
+    }
+
+    fn analytic_shapes() -> [RayGeometry; 4] {
+        [
+            RayGeometry::disk(Point::new(0.0, 0.0, -2.0), Vector::new(0.3, 0.2, 1.0), 0.8),
+            RayGeometry::cylinder(
+                Point::new(-0.5, -0.2, -3.0),
+                Vector::new(0.6, 1.0, 0.2),
+                0.4,
+            ),
+            RayGeometry::cone(Point::new(0.3, -0.5, -2.5), Vector::new(0.0, 1.2, 0.4), 0.6),
+            RayGeometry::torus(
+                Point::new(0.0, 0.2, -3.0),
+                Vector::new(0.4, 1.0, 0.3),
+                0.9,
+                0.3,
+            ),
+        ]
+    }
+
+    #[test]
+    fn analytic_shape_surface_samples_match_their_area_pdf() {
+        let mut rng = SampleRng::new(43);
+        for shape in analytic_shapes() {
+            let area = shape.area();
+            for _ in 0..64 {
+                let sample = shape.sample_surface(0.0, &mut rng).expect("surface sample");
+                assert!((sample.pdf - 1.0 / area).abs() < 1e-12);
+                assert!((shape.surface_pdf(sample.point, 0.0) - sample.pdf).abs() < 1e-12);
+                assert!((sample.normal.length() - 1.0).abs() < 1e-10);
+
+                // Rays aimed along the sampled normal land back on the sampled point.
+                let ray = Ray::new(sample.point + 0.5 * sample.normal, -sample.normal);
+                let hit = shape
+                    .intersect(&ray, Interval::new(0.0, INFINITY))
+                    .expect("ray toward sampled point hits the shape");
+                assert!(hit.t <= 0.5 + 1e-6, "{shape:?} hit at {}", hit.t);
+                assert!(shape.surface_pdf(hit.point, 0.0) > 0.0);
+            }
+            assert!(shape.surface_pdf(Point::new(5.0, 5.0, 5.0), 0.0).abs() < f64::EPSILON);
+        }
+    }
+
+    #[test]
+    fn analytic_shape_direction_pdfs_integrate_to_one() {
+        let origin = Point::new(0.2, 0.1, 0.0);
+        let context = PdfContext::new(origin, 0.0);
+        let mut rng = SampleRng::new(47);
+        let samples = 40_000;
+        for shape in analytic_shapes() {
+            let mut integral = 0.0;
+            for _ in 0..samples {
+                let direction = rng.random_unit_vector_spherical();
+                integral += shape.pdf_value(context, direction) * 4.0 * PI;
+            }
+            integral /= f64::from(samples);
+            assert!((integral - 1.0).abs() < 0.08, "{shape:?}: {integral}");
+
+            let direction = shape.random_direction(context, &mut rng);
+            assert!(shape.pdf_value(context, direction) > 0.0);
+        }
+    }
+
+    #[test]
+    fn weighted_sampling_targets_scale_surface_pdf_by_selection_weight() {
+        let mut targets = WeightedSamplingTargetList::new();
+        targets.add_target_weighted(
+            Quad::new(
+                Point::new(-1.0, -1.0, -1.0),
+                Vector::new(2.0, 0.0, 0.0),
+                Vector::new(0.0, 2.0, 0.0),
+            ),
+            1.0,
+        );
+        targets.add_target_weighted(Sphere::new(Point::new(0.0, 3.0, 0.0), 0.5), 3.0);
+        let mut rng = SampleRng::new(43);
+
+        for _ in 0..32 {
+            let sample = targets
+                .sample_surface(0.0, &mut rng)
+                .expect("weighted surface sample");
+            assert_close(targets.surface_pdf(sample.point, 0.0), sample.pdf);
+        }
+        assert_close(
+            targets.surface_pdf(Point::new(0.0, 0.0, -1.0), 0.0),
+            0.25 * 0.25,
+        );
+        assert_close(
+            targets.surface_pdf(Point::new(0.0, 3.5, 0.0), 0.0),
+            0.75 / PI,
+        );
+    }
+
     #[test]
     fn sampling_target_list_samples_explicit_targets() {
         let mut targets = SamplingTargetList::new();
@@ -1700,6 +2060,33 @@ mod tests {
         assert!(targets.random_direction(context, &mut rng).z() < 0.0);
     }
 
+    #[test]
+    fn ray_scene_emissive_targets_weight_lights_by_emitted_power() {
+        let mut scene = RayScene::new();
+        let dim = scene.add_material(RayMaterial::diffuse_light(LinearColor::new(1.0, 1.0, 1.0)));
+        let bright =
+            scene.add_material(RayMaterial::diffuse_light(LinearColor::new(5.0, 5.0, 5.0)));
+        scene.add_sphere(Point::new(-2.0, 0.0, -1.0), 0.5, dim);
+        scene.add_quad(
+            Point::new(1.0, 1.0, -2.0),
+            Vector::new(1.0, 0.0, 0.0),
+            Vector::new(0.0, 0.0, 2.0),
+            bright,
+        );
+        let targets = scene.emissive_targets();
+        let context = PdfContext::new(Point::new(1.5, 0.0, -1.0), 0.0);
+        let mut rng = SampleRng::new(5);
+
+        assert_eq!(targets.len(), 2);
+        assert_close(targets.total_power(), PI * (PI + 10.0));
+        // The quad emits toward -y, so points above it only ever select the sphere.
+        let above = PdfContext::new(Point::new(1.5, 5.0, -1.0), 0.0);
+        for _ in 0..32 {
+            assert!(targets.random_direction(above, &mut rng).x() < 0.0);
+        }
+        assert!(targets.pdf_value(context, Vector::new(0.0, 1.0, 0.0)) > 0.0);
+    }
+
     #[test]
     fn path_tracer_render_ray_scene_auto_samples_emissive_targets() {
         let mut scene = RayScene::new();
@@ -1774,6 +2161,118 @@ mod tests {
         assert_eq!(auto_updates, 4);
     }
 
+    #[test]
+    fn path_tracer_resumed_checkpoint_matches_uninterrupted_render() {
+        let mut scene = RayScene::new();
+        let diffuse = scene.add_material(RayMaterial::lambertian(LinearColor::new(0.7, 0.7, 0.7)));
+        let light = scene.add_material(RayMaterial::diffuse_light(LinearColor::new(4.0, 4.0, 4.0)));
+        scene.add_sphere(Point::new(0.0, 0.0, -1.0), 0.5, diffuse);
+        scene.add_quad(
+            Point::new(-0.5, 1.0, -1.5),
+            Vector::new(1.0, 0.0, 0.0),
+            Vector::new(0.0, 0.0, 1.0),
+            light,
+        );
+        let lights = scene.emissive_targets();
+        let camera = RayCamera::new(4, 1.0)
+            .with_max_depth(3)
+            .with_background(LinearColor::new(0.2, 0.3, 0.5))
+            .with_rng_seed(11);
+        let options = RenderOptions::new().tile_size(3).checkpoint_interval(3);
+        let path = std::env::temp_dir().join(format!(
+            "gartus-checkpoint-resume-{}.ckpt",
+            std::process::id()
+        ));
+
+        let full = PathTracer::new(camera.with_samples_per_pixel(10))
+            .with_options(options)
+            .render_with_lights_checkpointed(&scene, &lights, &path)
+            .unwrap();
+        let expected = PathTracer::new(camera.with_samples_per_pixel(10))
+            .render_with_lights_hdr_image(&scene, &lights);
+        PathTracer::new(camera.with_samples_per_pixel(4))
+            .with_options(options)
+            .render_with_lights_checkpointed(&scene, &lights, &path)
+            .unwrap();
+        let partial = RenderCheckpoint::load(&path).unwrap();
+        let resumed = PathTracer::new(camera.with_samples_per_pixel(10))
+            .with_options(options)
+            .resume_with_lights(&scene, &lights, &path)
+            .unwrap();
+        let refined = RenderCheckpoint::load(&path).unwrap();
+        let mismatch = PathTracer::new(camera.with_samples_per_pixel(10).with_rng_seed(12))
+            .resume_with_lights(&scene, &lights, &path)
+            .unwrap_err();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(full.pixels(), expected.pixels());
+        assert_eq!(partial.samples_per_pixel(), 4);
+        assert_eq!(refined.samples_per_pixel(), 10);
+        assert_eq!(resumed.pixels(), full.pixels());
+        assert_eq!(mismatch.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn path_tracer_checkpoints_environment_renders_and_rejects_other_estimators() {
+        let mut scene = RayScene::new();
+        let diffuse = scene.add_material(RayMaterial::lambertian(LinearColor::new(0.7, 0.7, 0.7)));
+        scene.add_sphere(Point::new(0.0, 0.0, -1.0), 0.5, diffuse);
+        let lights = scene.emissive_targets();
+        let sky = HdrImage::from_pixels(
+            4,
+            2,
+            vec![
+                LinearColor::new(2.0, 1.8, 1.5),
+                LinearColor::new(0.4, 0.5, 0.9),
+                LinearColor::new(0.4, 0.5, 0.9),
+                LinearColor::new(0.4, 0.5, 0.9),
+                LinearColor::new(0.1, 0.1, 0.1),
+                LinearColor::new(0.1, 0.1, 0.1),
+                LinearColor::new(0.1, 0.1, 0.1),
+                LinearColor::new(0.1, 0.1, 0.1),
+            ],
+        );
+        let environment = EnvironmentLight::from_hdr_image(sky);
+        let camera = RayCamera::new(4, 1.0)
+            .with_max_depth(3)
+            .with_samples_per_pixel(6)
+            .with_rng_seed(5);
+        let options = RenderOptions::new().tile_size(3).checkpoint_interval(4);
+        let path = std::env::temp_dir().join(format!(
+            "gartus-checkpoint-environment-{}.ckpt",
+            std::process::id()
+        ));
+
+        let checkpointed = PathTracer::new(camera)
+            .with_options(options)
+            .render_with_environment_checkpointed(&scene, &environment, &path)
+            .unwrap();
+        let expected =
+            PathTracer::new(camera).render_with_environment_hdr_image(&scene, &environment);
+        let saved = RenderCheckpoint::load(&path).unwrap();
+        let resumed = PathTracer::new(camera)
+            .resume_with_environment(&scene, &environment, &path)
+            .unwrap();
+        let without_environment = PathTracer::new(camera)
+            .resume_with_lights(&scene, &lights, &path)
+            .unwrap_err();
+        let deeper = PathTracer::new(camera.with_max_depth(4))
+            .resume_with_environment(&scene, &environment, &path)
+            .unwrap_err();
+        let with_lights = PathTracer::new(camera)
+            .resume_with_lights_and_environment(&scene, &lights, &environment, &path)
+            .unwrap_err();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(checkpointed.pixels(), expected.pixels());
+        assert_eq!(resumed.pixels(), expected.pixels());
+        assert_eq!(saved.max_depth(), 3);
+        assert!(saved.uses_environment() && !saved.uses_lights());
+        for error in [without_environment, deeper, with_lights] {
+            assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+        }
+    }
+
     #[test]
     fn sphere_hit_flips_normal_for_inside_ray() {
         let sphere = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0);
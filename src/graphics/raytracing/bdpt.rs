@@ -0,0 +1,799 @@
+//! Bidirectional path tracing over hittable worlds.
+//!
+//! Each camera sample traces one camera subpath and one light subpath starting on a surface point
+//! drawn from the light targets, then connects every pair of subpath prefixes. A full path of `n`
+//! edges can be produced by several of these strategies, so every contribution is weighted with
+//! the power heuristic over the area-measure densities of all strategies that could have built it
+//! (Veach's formulation, with delta lobes excluded as connection points). Light-subpath vertices
+//! connected straight to the lens land in whichever pixel they project to, which is what lets
+//! caustics seen through glass converge from the light side.
+//!
+//! `max_depth` counts scattering events like the camera-only tracer, so paths have at most
+//! `max_depth + 1` edges. Emitters seen directly and camera rays that escape to the background are
+//! only estimated from the camera side, with full weight.
+
+use super::{
+    BsdfFlags, HitRecord, Hittable, INFINITY, Interval, LinearColor, PI, SHADOW_ACNE_EPSILON,
+    component_mul,
+};
+use crate::{
+    gmath::{
+        geometry::OrthonormalBasis,
+        random::SampleRng,
+        ray::Ray,
+        vector::{Point, Vector},
+    },
+    graphics::camera::{RayBackground, RayBackgroundSource},
+};
+
+/// Offset used to re-intersect a sampled light point when looking up its emitted radiance.
+const EMISSION_PROBE_OFFSET: f64 = SHADOW_ACNE_EPSILON;
+
+/// Pinhole or thin-lens film model used to generate densities and to project light vertices.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct BidirectionalCamera {
+    /// Lens center.
+    pub(crate) center: Point,
+    /// Center of pixel `(0, 0)` on the focus plane.
+    pub(crate) pixel00_loc: Point,
+    /// Horizontal pixel step on the focus plane.
+    pub(crate) pixel_delta_u: Vector,
+    /// Vertical pixel step on the focus plane.
+    pub(crate) pixel_delta_v: Vector,
+    /// Horizontal lens radius vector; zero for a pinhole.
+    pub(crate) defocus_disk_u: Vector,
+    /// Vertical lens radius vector; zero for a pinhole.
+    pub(crate) defocus_disk_v: Vector,
+    /// Image width in pixels.
+    pub(crate) image_width: u32,
+    /// Image height in pixels.
+    pub(crate) image_height: u32,
+}
+
+impl BidirectionalCamera {
+    fn forward(self) -> Vector {
+        self.pixel_delta_u.cross(self.pixel_delta_v).normalized()
+    }
+
+    fn sample_lens(self, rng: &mut SampleRng) -> Point {
+        if self.defocus_disk_u.length_squared() <= 0.0 {
+            return self.center;
+        }
+        let point = rng.random_in_unit_disk();
+        self.center + point.x() * self.defocus_disk_u + point.y() * self.defocus_disk_v
+    }
+
+    /// Solid-angle density of a camera ray leaving the lens along `direction`.
+    ///
+    /// Pixel samples are uniform over the whole film, so this is also the camera importance that
+    /// converts a light-tracing connection into a pixel estimate.
+    fn direction_density(self, direction: Vector) -> f64 {
+        let forward = self.forward();
+        let cosine = direction.normalized().dot(forward);
+        if cosine <= 0.0 {
+            return 0.0;
+        }
+
+        let focus_distance = (self.pixel00_loc - self.center).dot(forward);
+        let film_area = self.pixel_delta_u.cross(self.pixel_delta_v).length()
+            * f64::from(self.image_width)
+            * f64::from(self.image_height);
+        focus_distance * focus_distance / (film_area * cosine * cosine * cosine)
+    }
+
+    /// Returns the pixel hit by the ray from `lens` through `point`, if it lands on the film.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn raster_position(self, lens: Point, point: Point) -> Option<(u32, u32)> {
+        let forward = self.forward();
+        let direction = point - lens;
+        let cosine = direction.dot(forward);
+        if cosine <= 0.0 {
+            return None;
+        }
+
+        let plane_distance = (self.pixel00_loc - lens).dot(forward);
+        let film_point = lens + direction * (plane_distance / cosine);
+        let offset =
+            film_point - (self.pixel00_loc - 0.5 * (self.pixel_delta_u + self.pixel_delta_v));
+        let x = offset.dot(self.pixel_delta_u) / self.pixel_delta_u.length_squared();
+        let y = offset.dot(self.pixel_delta_v) / self.pixel_delta_v.length_squared();
+        if !(0.0..f64::from(self.image_width)).contains(&x)
+            || !(0.0..f64::from(self.image_height)).contains(&y)
+        {
+            return None;
+        }
+        Some((x as u32, y as u32))
+    }
+}
+
+/// Light-tracing contribution added to one pixel of the film.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct FilmSplat {
+    /// Row-major pixel index.
+    pub(crate) index: usize,
+    /// Radiance estimate, before dividing by the sample count.
+    pub(crate) color: LinearColor,
+}
+
+// Surface vertices dominate every subpath, so boxing them would only add allocations.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Copy)]
+enum VertexKind<'a> {
+    Camera,
+    Light,
+    Surface { hit: HitRecord<'a>, ray_in: Ray },
+}
+
+#[derive(Clone, Copy)]
+struct PathVertex<'a> {
+    kind: VertexKind<'a>,
+    point: Point,
+    /// Outward geometric normal, or zero for vertices without a surface cosine.
+    normal: Vector,
+    beta: LinearColor,
+    delta: bool,
+    /// Area density of this vertex under the sampling of its own subpath.
+    pdf_fwd: f64,
+    /// Area density of this vertex when sampled from the opposite subpath.
+    pdf_rev: f64,
+}
+
+impl<'a> PathVertex<'a> {
+    fn camera(point: Point) -> Self {
+        Self {
+            kind: VertexKind::Camera,
+            point,
+            normal: Vector::default(),
+            beta: LinearColor::new(1.0, 1.0, 1.0),
+            delta: false,
+            pdf_fwd: 0.0,
+            pdf_rev: 0.0,
+        }
+    }
+
+    fn light(point: Point, normal: Vector, pdf: f64) -> Self {
+        Self {
+            kind: VertexKind::Light,
+            point,
+            normal,
+            beta: LinearColor::new(1.0, 1.0, 1.0) / pdf,
+            delta: false,
+            pdf_fwd: pdf,
+            pdf_rev: 0.0,
+        }
+    }
+
+    fn surface(hit: HitRecord<'a>, ray_in: Ray, beta: LinearColor) -> Self {
+        let normal = if hit.material.bsdf_flags(&hit).contains(BsdfFlags::MEDIUM) {
+            Vector::default()
+        } else if hit.front_face {
+            hit.normal
+        } else {
+            -hit.normal
+        };
+        Self {
+            kind: VertexKind::Surface { hit, ray_in },
+            point: hit.point,
+            normal,
+            beta,
+            delta: false,
+            pdf_fwd: 0.0,
+            pdf_rev: 0.0,
+        }
+    }
+}
+
+/// MIS bookkeeping copied out of a [`PathVertex`] so connections can override it locally.
+#[derive(Clone, Copy)]
+struct VertexDensity {
+    pdf_fwd: f64,
+    pdf_rev: f64,
+    delta: bool,
+}
+
+impl From<&PathVertex<'_>> for VertexDensity {
+    fn from(vertex: &PathVertex<'_>) -> Self {
+        Self {
+            pdf_fwd: vertex.pdf_fwd,
+            pdf_rev: vertex.pdf_rev,
+            delta: vertex.delta,
+        }
+    }
+}
+
+/// Scene and camera state shared by all bidirectional samples of one render.
+pub(crate) struct BidirectionalIntegrator<'a> {
+    pub(crate) camera: BidirectionalCamera,
+    pub(crate) world: &'a dyn Hittable,
+    pub(crate) lights: &'a dyn Hittable,
+    pub(crate) background: RayBackground,
+    pub(crate) max_depth: u32,
+}
+
+impl<'a> BidirectionalIntegrator<'a> {
+    /// Estimates the radiance along `camera_ray` and pushes light-tracing splats for any pixel.
+    pub(crate) fn sample(
+        &self,
+        camera_ray: &Ray,
+        rng: &mut SampleRng,
+        splats: &mut Vec<FilmSplat>,
+    ) -> LinearColor {
+        let max_depth = usize::try_from(self.max_depth).expect("max depth should fit usize");
+        if max_depth == 0 {
+            return LinearColor::default();
+        }
+        let time = camera_ray.time();
+
+        let mut camera_path = Vec::with_capacity(max_depth + 2);
+        camera_path.push(PathVertex::camera(*camera_ray.origin()));
+        let mut color = self
+            .random_walk(
+                *camera_ray,
+                LinearColor::new(1.0, 1.0, 1.0),
+                self.camera.direction_density(*camera_ray.direction()),
+                &mut camera_path,
+                max_depth + 2,
+                rng,
+            )
+            .unwrap_or_default();
+
+        let mut light_path = Vec::with_capacity(max_depth + 1);
+        self.trace_light_subpath(time, &mut light_path, max_depth + 1, rng);
+
+        for t in 1..=camera_path.len() {
+            for s in 0..=light_path.len() {
+                if (t == 1 && s < 2) || s + t - 2 > max_depth {
+                    continue;
+                }
+                if t == 1 {
+                    if let Some(splat) =
+                        self.connect_to_camera(&camera_path, &light_path, s, time, rng)
+                    {
+                        splats.push(splat);
+                    }
+                } else {
+                    let contribution = self.connect(&camera_path, &light_path, s, t, time, rng);
+                    if contribution.is_finite() {
+                        color += contribution;
+                    }
+                }
+            }
+        }
+
+        color
+    }
+
+    /// Extends `path` by BSDF sampling and returns the background seen by an escaping ray.
+    fn random_walk(
+        &self,
+        mut ray: Ray,
+        mut beta: LinearColor,
+        mut pdf_dir: f64,
+        path: &mut Vec<PathVertex<'a>>,
+        max_vertices: usize,
+        rng: &mut SampleRng,
+    ) -> Option<LinearColor> {
+        let world: &'a dyn Hittable = self.world;
+        let time = ray.time();
+        while path.len() < max_vertices {
+            let Some(hit) =
+                world.hit_with_rng(&ray, Interval::new(SHADOW_ACNE_EPSILON, INFINITY), rng)
+            else {
+                return Some(component_mul(
+                    beta,
+                    self.background.radiance(*ray.direction()),
+                ));
+            };
+
+            if hit.material.bsdf_flags(&hit).is_index_matched() {
+                // Connections see through index-matched medium boundaries, so crossing one
+                // continues the current segment instead of adding a vertex.
+                let Some(sample) = hit.material.sample_bsdf(&ray, &hit, rng) else {
+                    break;
+                };
+                beta = component_mul(beta, sample.weight());
+                if !beta.is_finite() || beta == LinearColor::default() {
+                    break;
+                }
+                ray = Ray::with_time(hit.point, sample.direction, time);
+                continue;
+            }
+
+            let previous = path.len() - 1;
+            let mut vertex = PathVertex::surface(hit, ray, beta);
+            vertex.pdf_fwd = convert_density(pdf_dir, path[previous].point, &vertex);
+            path.push(vertex);
+            if path.len() == max_vertices {
+                break;
+            }
+
+            let Some(sample) = hit.material.sample_bsdf(&ray, &hit, rng) else {
+                break;
+            };
+            let weight = sample.weight();
+            if !weight.is_finite() || weight == LinearColor::default() {
+                break;
+            }
+            beta = component_mul(beta, weight);
+
+            let pdf_rev = if sample.is_delta() {
+                path[previous + 1].delta = true;
+                pdf_dir = 0.0;
+                0.0
+            } else {
+                pdf_dir = sample.pdf;
+                let reversed =
+                    Ray::with_time(hit.point + sample.direction, -sample.direction, time);
+                let toward_previous =
+                    Ray::with_time(hit.point, path[previous].point - hit.point, time);
+                hit.material.bsdf_pdf(&reversed, &hit, &toward_previous)
+            };
+            path[previous].pdf_rev = convert_density(pdf_rev, hit.point, &path[previous]);
+            ray = Ray::with_time(hit.point, sample.direction, time);
+        }
+
+        None
+    }
+
+    fn trace_light_subpath(
+        &self,
+        time: f64,
+        path: &mut Vec<PathVertex<'a>>,
+        max_vertices: usize,
+        rng: &mut SampleRng,
+    ) {
+        let Some(sample) = self.lights.sample_surface(time, rng) else {
+            return;
+        };
+        if !sample.pdf.is_finite() || sample.pdf <= 0.0 {
+            return;
+        }
+
+        path.push(PathVertex::light(sample.point, sample.normal, sample.pdf));
+
+        let Some(basis) = OrthonormalBasis::from_w(sample.normal) else {
+            return;
+        };
+        let local = rng.random_cosine_direction();
+        if local.z() <= 0.0 {
+            return;
+        }
+        let direction = basis.local(local);
+        let emitted = emitted_leaving(self.world, sample.point, direction, time, rng);
+        if !emitted.is_finite() || emitted == LinearColor::default() {
+            return;
+        }
+
+        let pdf_dir = local.z() / PI;
+        let beta = emitted * (local.z() / (sample.pdf * pdf_dir));
+        self.random_walk(
+            Ray::with_time(sample.point, direction, time),
+            beta,
+            pdf_dir,
+            path,
+            max_vertices,
+            rng,
+        );
+    }
+
+    /// Connects camera vertex `t - 1` (with `t >= 2`) to light vertex `s - 1`, or collects
+    /// emission at the camera vertex when `s == 0`.
+    fn connect(
+        &self,
+        camera_path: &[PathVertex<'a>],
+        light_path: &[PathVertex<'a>],
+        s: usize,
+        t: usize,
+        time: f64,
+        rng: &mut SampleRng,
+    ) -> LinearColor {
+        let camera_vertex = &camera_path[t - 1];
+        let VertexKind::Surface { hit, ray_in } = camera_vertex.kind else {
+            return LinearColor::default();
+        };
+
+        let unweighted = if s == 0 {
+            component_mul(
+                camera_vertex.beta,
+                hit.material.emitted(&ray_in, &hit, hit.u, hit.v, hit.point),
+            )
+        } else {
+            if camera_vertex.delta {
+                return LinearColor::default();
+            }
+            let light_vertex = &light_path[s - 1];
+            let toward_light = light_vertex.point - camera_vertex.point;
+            let camera_eval = hit.material.eval_bsdf(
+                &ray_in,
+                &hit,
+                &Ray::with_time(camera_vertex.point, toward_light, time),
+            );
+            if camera_eval == LinearColor::default() {
+                return LinearColor::default();
+            }
+            let Some(light_eval) =
+                self.light_vertex_eval(light_vertex, camera_vertex.point, time, rng)
+            else {
+                return LinearColor::default();
+            };
+            component_mul(
+                component_mul(camera_vertex.beta, camera_eval),
+                component_mul(light_eval, light_vertex.beta),
+            ) / toward_light.length_squared()
+        };
+
+        if unweighted == LinearColor::default() || !unweighted.is_finite() {
+            return LinearColor::default();
+        }
+        unweighted * self.mis_weight(camera_path, light_path, s, t, time, None)
+    }
+
+    /// Connects light vertex `s - 1` to a freshly sampled lens point and returns the film splat.
+    fn connect_to_camera(
+        &self,
+        camera_path: &[PathVertex<'a>],
+        light_path: &[PathVertex<'a>],
+        s: usize,
+        time: f64,
+        rng: &mut SampleRng,
+    ) -> Option<FilmSplat> {
+        let light_vertex = &light_path[s - 1];
+        if light_vertex.delta {
+            return None;
+        }
+        let lens = self.camera.sample_lens(rng);
+        let (x, y) = self.camera.raster_position(lens, light_vertex.point)?;
+        let light_eval = self.light_vertex_eval(light_vertex, lens, time, rng)?;
+
+        let toward_light = light_vertex.point - lens;
+        let importance = self.camera.direction_density(toward_light);
+        let color = component_mul(light_eval, light_vertex.beta)
+            * (importance / toward_light.length_squared());
+        if color == LinearColor::default() || !color.is_finite() {
+            return None;
+        }
+
+        let lens_vertex = PathVertex::camera(lens);
+        let weight = self.mis_weight(camera_path, light_path, s, 1, time, Some(&lens_vertex));
+        let width = usize::try_from(self.camera.image_width).expect("image width should fit usize");
+        let x = usize::try_from(x).expect("pixel x should fit usize");
+        let y = usize::try_from(y).expect("pixel y should fit usize");
+        Some(FilmSplat {
+            index: y * width + x,
+            color: color * weight,
+        })
+    }
+
+    /// Evaluates light vertex `vertex` toward `target`, including visibility.
+    ///
+    /// Light-origin vertices return emitted radiance times the emitter cosine; scattering
+    /// vertices return their BSDF times the cosine toward `target`.
+    fn light_vertex_eval(
+        &self,
+        vertex: &PathVertex<'a>,
+        target: Point,
+        time: f64,
+        rng: &mut SampleRng,
+    ) -> Option<LinearColor> {
+        let offset = vertex.point - target;
+        let distance = offset.length();
+        if distance <= 2.0 * SHADOW_ACNE_EPSILON {
+            return None;
+        }
+        let direction = offset / distance;
+        let shadow_ray = Ray::with_time(target, direction, time);
+
+        match vertex.kind {
+            VertexKind::Light => {
+                let transmittance = self.world.transmittance_with_rng(
+                    &shadow_ray,
+                    Interval::new(SHADOW_ACNE_EPSILON, distance - SHADOW_ACNE_EPSILON),
+                    rng,
+                );
+                if transmittance == LinearColor::default() {
+                    return None;
+                }
+                let hit = self.world.hit_with_rng(
+                    &shadow_ray,
+                    Interval::new(distance - SHADOW_ACNE_EPSILON, INFINITY),
+                    rng,
+                )?;
+                if (hit.t - distance).abs() > SHADOW_ACNE_EPSILON {
+                    return None;
+                }
+                let emitted = hit
+                    .material
+                    .emitted(&shadow_ray, &hit, hit.u, hit.v, hit.point);
+                Some(component_mul(emitted, transmittance) * vertex.normal.dot(direction).abs())
+            }
+            VertexKind::Surface { hit, ray_in } => {
+                let eval = hit.material.eval_bsdf(
+                    &ray_in,
+                    &hit,
+                    &Ray::with_time(vertex.point, -direction, time),
+                );
+                if eval == LinearColor::default() {
+                    return None;
+                }
+                let transmittance = self.world.transmittance_with_rng(
+                    &shadow_ray,
+                    Interval::new(SHADOW_ACNE_EPSILON, distance - SHADOW_ACNE_EPSILON),
+                    rng,
+                );
+                (transmittance != LinearColor::default())
+                    .then(|| component_mul(eval, transmittance))
+            }
+            VertexKind::Camera => None,
+        }
+    }
+
+    /// Area density at `next` of sampling the direction `vertex -> next`, given that `vertex` was
+    /// reached from `previous`.
+    fn density(
+        &self,
+        vertex: &PathVertex<'a>,
+        previous: Option<Point>,
+        next: &PathVertex<'a>,
+    ) -> f64 {
+        let direction = next.point - vertex.point;
+        let solid_angle_pdf = match vertex.kind {
+            VertexKind::Camera => self.camera.direction_density(direction),
+            VertexKind::Light => emission_direction_density(vertex.normal, direction),
+            VertexKind::Surface { hit, ray_in } => {
+                let time = ray_in.time();
+                let ray_in = previous.map_or(ray_in, |previous| {
+                    Ray::with_time(previous, vertex.point - previous, time)
+                });
+                hit.material.bsdf_pdf(
+                    &ray_in,
+                    &hit,
+                    &Ray::with_time(vertex.point, direction, time),
+                )
+            }
+        };
+        convert_density(solid_angle_pdf, vertex.point, next)
+    }
+
+    /// Power-heuristic weight of strategy `(s, t)` against every other strategy for the same path.
+    ///
+    /// `lens` replaces the camera endpoint for light-tracing connections (`t == 1`).
+    fn mis_weight(
+        &self,
+        camera_path: &[PathVertex<'a>],
+        light_path: &[PathVertex<'a>],
+        s: usize,
+        t: usize,
+        time: f64,
+        lens: Option<&PathVertex<'a>>,
+    ) -> f64 {
+        if s + t == 2 {
+            return 1.0;
+        }
+
+        let camera_vertex = lens.unwrap_or(&camera_path[t - 1]);
+        let camera_previous = (t >= 2).then(|| &camera_path[t - 2]);
+        let light_vertex = s.checked_sub(1).map(|index| &light_path[index]);
+        let light_previous = (s >= 2).then(|| &light_path[s - 2]);
+
+        let mut camera: Vec<VertexDensity> =
+            camera_path[..t].iter().map(VertexDensity::from).collect();
+        let mut light: Vec<VertexDensity> =
+            light_path[..s].iter().map(VertexDensity::from).collect();
+        camera[t - 1] = VertexDensity::from(camera_vertex);
+
+        camera[t - 1].pdf_rev = match light_vertex {
+            Some(light_vertex) => self.density(
+                light_vertex,
+                light_previous.map(|vertex| vertex.point),
+                camera_vertex,
+            ),
+            None => self.lights.surface_pdf(camera_vertex.point, time),
+        };
+        if let Some(camera_previous) = camera_previous {
+            camera[t - 2].pdf_rev = match light_vertex {
+                Some(light_vertex) => {
+                    self.density(camera_vertex, Some(light_vertex.point), camera_previous)
+                }
+                None => convert_density(
+                    emission_direction_density(
+                        camera_vertex.normal,
+                        camera_previous.point - camera_vertex.point,
+                    ),
+                    camera_vertex.point,
+                    camera_previous,
+                ),
+            };
+        }
+        if let Some(light_vertex) = light_vertex {
+            light[s - 1].pdf_rev = self.density(
+                camera_vertex,
+                camera_previous.map(|vertex| vertex.point),
+                light_vertex,
+            );
+            if let Some(light_previous) = light_previous {
+                light[s - 2].pdf_rev =
+                    self.density(light_vertex, Some(camera_vertex.point), light_previous);
+            }
+            light[s - 1].delta = false;
+        }
+        camera[t - 1].delta = false;
+
+        let mut other_strategies = 0.0;
+        let mut ratio = 1.0;
+        for index in (1..t).rev() {
+            let source_is_delta = index + 1 < t && camera[index + 1].delta;
+            if camera[index].pdf_rev <= 0.0 && !source_is_delta {
+                break;
+            }
+            ratio *= remap_zero(camera[index].pdf_rev) / remap_zero(camera[index].pdf_fwd);
+            if !camera[index].delta && !camera[index - 1].delta {
+                other_strategies += ratio * ratio;
+            }
+        }
+
+        ratio = 1.0;
+        for index in (0..s).rev() {
+            let source_is_delta = index + 1 < s && light[index + 1].delta;
+            if light[index].pdf_rev <= 0.0 && !source_is_delta {
+                break;
+            }
+            ratio *= remap_zero(light[index].pdf_rev) / remap_zero(light[index].pdf_fwd);
+            let previous_is_delta = index > 0 && light[index - 1].delta;
+            if !light[index].delta && !previous_is_delta {
+                other_strategies += ratio * ratio;
+            }
+        }
+
+        1.0 / (1.0 + other_strategies)
+    }
+}
+
+/// Looks up the radiance leaving an emitter surface point of `world` along `direction`.
+pub(super) fn emitted_leaving(
+    world: &dyn Hittable,
+    point: Point,
+    direction: Vector,
+    time: f64,
+    rng: &mut SampleRng,
+) -> LinearColor {
+    let direction = direction.normalized();
+    let probe = Ray::with_time(point + EMISSION_PROBE_OFFSET * direction, -direction, time);
+    world
+        .hit_with_rng(&probe, Interval::new(0.0, 2.0 * EMISSION_PROBE_OFFSET), rng)
+        .map_or_else(LinearColor::default, |hit| {
+            hit.material.emitted(&probe, &hit, hit.u, hit.v, hit.point)
+        })
+}
+
+/// Cosine-weighted emission density of leaving an emitter with outward `normal` along `direction`.
+fn emission_direction_density(normal: Vector, direction: Vector) -> f64 {
+    normal.dot(direction.normalized()).max(0.0) / PI
+}
+
+/// Converts a solid-angle density at `origin` to an area density at `next`.
+fn convert_density(solid_angle_pdf: f64, origin: Point, next: &PathVertex<'_>) -> f64 {
+    let offset = next.point - origin;
+    let distance_squared = offset.length_squared();
+    if distance_squared <= 0.0 || !solid_angle_pdf.is_finite() {
+        return 0.0;
+    }
+
+    let cosine = if next.normal.length_squared() > 0.0 {
+        next.normal.dot(offset).abs() / distance_squared.sqrt()
+    } else {
+        1.0
+    };
+    solid_angle_pdf * cosine / distance_squared
+}
+
+/// Maps zero densities, produced only by delta lobes, to one in MIS ratios.
+fn remap_zero(pdf: f64) -> f64 {
+    if pdf > 0.0 { pdf } else { 1.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::raytracing::{DiffuseLight, HittableList, Lambertian, Quad};
+
+    fn test_camera() -> BidirectionalCamera {
+        BidirectionalCamera {
+            center: Point::new(0.0, 1.0, 2.0),
+            pixel00_loc: Point::new(-0.875, 1.875, 0.0),
+            pixel_delta_u: Vector::new(0.25, 0.0, 0.0),
+            pixel_delta_v: Vector::new(0.0, -0.25, 0.0),
+            defocus_disk_u: Vector::default(),
+            defocus_disk_v: Vector::default(),
+            image_width: 8,
+            image_height: 8,
+        }
+    }
+
+    #[test]
+    fn raster_position_inverts_pixel_center_rays() {
+        let camera = test_camera();
+
+        for (x, y) in [(0, 0), (3, 5), (7, 7)] {
+            let pixel_center = camera.pixel00_loc
+                + f64::from(x) * camera.pixel_delta_u
+                + f64::from(y) * camera.pixel_delta_v;
+            let beyond = camera.center + 3.0 * (pixel_center - camera.center);
+
+            assert_eq!(camera.raster_position(camera.center, beyond), Some((x, y)));
+        }
+        assert_eq!(
+            camera.raster_position(camera.center, Point::new(0.0, 1.0, 4.0)),
+            None
+        );
+        assert_eq!(
+            camera.raster_position(camera.center, Point::new(5.0, 1.0, 0.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn direction_density_integrates_to_one_over_the_film() {
+        let camera = test_camera();
+        let subdivisions = 4;
+        let step_u = camera.pixel_delta_u / f64::from(subdivisions);
+        let step_v = camera.pixel_delta_v / f64::from(subdivisions);
+        let corner = camera.pixel00_loc - 0.5 * (camera.pixel_delta_u + camera.pixel_delta_v);
+        let cell_area = step_u.cross(step_v).length();
+        let mut total = 0.0;
+
+        for j in 0..camera.image_height * subdivisions {
+            for i in 0..camera.image_width * subdivisions {
+                let point = corner + (f64::from(i) + 0.5) * step_u + (f64::from(j) + 0.5) * step_v;
+                let direction = point - camera.center;
+                let cosine = direction.normalized().dot(camera.forward());
+                let solid_angle = cell_area * cosine / direction.length_squared();
+                total += camera.direction_density(direction) * solid_angle;
+            }
+        }
+
+        assert!((total - 1.0).abs() < 1e-3, "{total}");
+    }
+
+    #[test]
+    fn light_tracing_splats_land_on_the_film() {
+        let mut world = HittableList::new();
+        world.add(Quad::with_material(
+            Point::new(-2.0, 0.0, -2.0),
+            Vector::new(0.0, 0.0, 4.0),
+            Vector::new(4.0, 0.0, 0.0),
+            Lambertian::new(LinearColor::new(0.8, 0.8, 0.8)),
+        ));
+        world.add(Quad::with_material(
+            Point::new(-0.25, 3.0, -0.25),
+            Vector::new(0.5, 0.0, 0.0),
+            Vector::new(0.0, 0.0, 0.5),
+            DiffuseLight::new(LinearColor::new(4.0, 4.0, 4.0)),
+        ));
+        let mut lights = HittableList::new();
+        lights.add(Quad::new(
+            Point::new(-0.25, 3.0, -0.25),
+            Vector::new(0.5, 0.0, 0.0),
+            Vector::new(0.0, 0.0, 0.5),
+        ));
+        let camera = test_camera();
+        let integrator = BidirectionalIntegrator {
+            camera,
+            world: &world,
+            lights: &lights,
+            background: RayBackground::Constant(LinearColor::default()),
+            max_depth: 3,
+        };
+        let mut rng = SampleRng::new(7);
+        let mut splats = Vec::new();
+        let ray = Ray::new(camera.center, camera.pixel00_loc - camera.center);
+
+        for _ in 0..64 {
+            integrator.sample(&ray, &mut rng, &mut splats);
+        }
+
+        assert!(!splats.is_empty());
+        assert!(splats.iter().all(|splat| splat.index < 64
+            && splat.color.is_finite()
+            && splat.color != LinearColor::default()));
+    }
+}
@@ -303,6 +303,42 @@ impl FlatBvh {
         })
     }
 
+    /// Calls `visit_leaf` for every leaf whose bounds the ray crosses inside `ray_t`, in no
+    /// particular order, until it returns false.
+    pub(super) fn visit_leaves<F>(
+        &self,
+        ray_t: Interval,
+        traversal: RayTraversal,
+        mut visit_leaf: F,
+    ) where
+        F: FnMut(&[usize]) -> bool,
+    {
+        let Some(root_entry) = traversal.hit_bounds(self.nodes[0].bounds, ray_t.min, ray_t.max)
+        else {
+            return;
+        };
+        let mut stack = TraversalStack::new(root_entry);
+
+        while let Some(entry) = stack.pop() {
+            match self.nodes[entry.node].kind {
+                FlatBvhNodeKind::Leaf { first, count } => {
+                    if !visit_leaf(&self.indices[first..first + count]) {
+                        return;
+                    }
+                }
+                FlatBvhNodeKind::Internal { left, right } => {
+                    for node in [left, right] {
+                        if let Some(entry_t) =
+                            traversal.hit_bounds(self.nodes[node].bounds, ray_t.min, ray_t.max)
+                        {
+                            stack.push(StackEntry { node, entry_t });
+                        }
+                    }
+                }
+            }
+        }
+    }
+
     pub(super) fn hit_with_stats<H, F>(
         &self,
         ray_t: Interval,
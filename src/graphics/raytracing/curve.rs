@@ -0,0 +1,254 @@
+//! Ray-traced hair, fur, and grass curves with a curve-local BVH.
+
+use super::{
+    Aabb, HitRecord, Hittable, Interval, Material, MaterialRef, SampleRng, SurfaceHit,
+    bvh::{BvhBuildOptions, BvhPrimitiveInfo, FlatBvh, RayTraversal},
+};
+use crate::gmath::{edge_matrix::EdgeMatrix, geometry::CurveGeometry, ray::Ray, vector::Point};
+use std::{fmt, sync::Arc};
+
+/// Longest segment, in multiples of the segment's width, before a curve is split for the BVH.
+const SEGMENT_LENGTH_IN_WIDTHS: f64 = 16.0;
+const MAX_SEGMENTS_PER_CURVE: usize = 32;
+/// Distance below which consecutive edge endpoints are treated as the same chain point.
+const CHAIN_EPSILON: f64 = 1e-9;
+
+#[derive(Clone, Copy, Debug)]
+struct CurveSegment {
+    geometry: CurveGeometry,
+    material: usize,
+}
+
+/// A set of Bézier curves with per-curve materials and a monomorphic internal BVH.
+///
+/// Each curve is split into segments no longer than a fixed multiple of its width, so long thin
+/// strands get tight bounds. Hits carry the curve tangent and across-width direction as their
+/// tangent frame, and `v` runs across the width, which [`HairBsdf`](super::HairBsdf) uses to find
+/// where the ray struck the fiber.
+#[derive(Clone)]
+pub struct CurveSet {
+    segments: Vec<CurveSegment>,
+    materials: Vec<MaterialRef>,
+    curve_count: usize,
+    bounds: Option<Aabb>,
+    bvh: Option<FlatBvh>,
+}
+
+impl fmt::Debug for CurveSet {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("CurveSet")
+            .field("curves", &self.curve_count)
+            .field("segments", &self.segments.len())
+            .field("materials", &self.materials.len())
+            .field("bounds", &self.bounds)
+            .field("has_bvh", &self.bvh.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl CurveSet {
+    /// Creates a curve set that shades every curve with one concrete material.
+    #[must_use]
+    pub fn new(curves: Vec<CurveGeometry>, material: impl Material + 'static) -> Self {
+        Self::with_shared_material(curves, Arc::new(material))
+    }
+
+    /// Creates a curve set that shades every curve with one shared material handle.
+    #[must_use]
+    pub fn with_shared_material(curves: Vec<CurveGeometry>, material: MaterialRef) -> Self {
+        Self::build(
+            curves.into_iter().map(|curve| (curve, 0)).collect(),
+            vec![material],
+            BvhBuildOptions::default(),
+        )
+    }
+
+    /// Creates a curve set where each curve picks a material by index into `materials`.
+    ///
+    /// Returns `None` if any material index is out of range.
+    #[must_use]
+    pub fn with_materials(
+        curves: Vec<(CurveGeometry, usize)>,
+        materials: Vec<MaterialRef>,
+    ) -> Option<Self> {
+        Self::with_materials_and_bvh_options(curves, materials, BvhBuildOptions::default())
+    }
+
+    /// Creates a per-curve-material set with explicit BVH build options.
+    ///
+    /// Returns `None` if any material index is out of range.
+    #[must_use]
+    pub fn with_materials_and_bvh_options(
+        curves: Vec<(CurveGeometry, usize)>,
+        materials: Vec<MaterialRef>,
+        bvh_options: BvhBuildOptions,
+    ) -> Option<Self> {
+        if curves
+            .iter()
+            .any(|(_, material)| *material >= materials.len())
+        {
+            return None;
+        }
+        Some(Self::build(curves, materials, bvh_options))
+    }
+
+    fn build(
+        curves: Vec<(CurveGeometry, usize)>,
+        materials: Vec<MaterialRef>,
+        bvh_options: BvhBuildOptions,
+    ) -> Self {
+        let curve_count = curves.len();
+        let segments = curves
+            .into_iter()
+            .flat_map(|(curve, material)| {
+                curve
+                    .split(segment_count(curve))
+                    .into_iter()
+                    .map(move |geometry| CurveSegment { geometry, material })
+            })
+            .collect::<Vec<_>>();
+        let primitive_info = segments
+            .iter()
+            .enumerate()
+            .map(|(index, segment)| BvhPrimitiveInfo::new(index, segment.geometry.bounds()))
+            .collect::<Vec<_>>();
+        let bvh = FlatBvh::build(&primitive_info, bvh_options);
+        Self {
+            bounds: bvh.as_ref().map(FlatBvh::bounds),
+            segments,
+            materials,
+            curve_count,
+            bvh,
+        }
+    }
+
+    /// Creates smooth curves through the connected polylines of an edge matrix.
+    ///
+    /// Curves drawn with [`EdgeMatrix::add_bezier3`], [`EdgeMatrix::add_hermite`], or any other
+    /// chain of edges whose end points meet become one Catmull-Rom chain each. Widths taper from
+    /// `widths[0]` at the start of each chain to `widths[1]` at its end.
+    #[must_use]
+    pub fn from_edge_matrix(edges: &EdgeMatrix, widths: [f64; 2], material: MaterialRef) -> Self {
+        let curves = edge_chains(edges)
+            .iter()
+            .flat_map(|chain| CurveGeometry::catmull_rom_chain(chain, widths))
+            .collect();
+        Self::with_shared_material(curves, material)
+    }
+
+    /// Returns the number of curves the set was built from.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.curve_count
+    }
+
+    /// Returns true if the set has no curves.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.curve_count == 0
+    }
+
+    /// Returns the number of BVH segments after splitting long curves.
+    #[must_use]
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// Returns the material table.
+    #[must_use]
+    pub fn materials(&self) -> &[MaterialRef] {
+        &self.materials
+    }
+
+    /// Returns the number of flat BVH nodes when this set has a built BVH.
+    #[must_use]
+    pub fn bvh_node_count(&self) -> Option<usize> {
+        self.bvh.as_ref().map(FlatBvh::node_count)
+    }
+
+    /// Brute-force hit path used for testing and diagnostics.
+    #[must_use]
+    pub fn hit_bruteforce(&self, ray: &Ray, ray_t: Interval) -> Option<HitRecord<'_>> {
+        self.hit_segments(0..self.segments.len(), ray, ray_t)
+    }
+
+    fn hit_segments(
+        &self,
+        indices: impl IntoIterator<Item = usize>,
+        ray: &Ray,
+        ray_t: Interval,
+    ) -> Option<HitRecord<'_>> {
+        let mut closest_so_far = ray_t.max;
+        let mut closest_hit = None;
+
+        for index in indices {
+            let segment = self.segments[index];
+            let Some(hit) = segment.geometry.hit_ray(ray, ray_t.min, closest_so_far) else {
+                continue;
+            };
+            closest_so_far = hit.t;
+            let surface = SurfaceHit::with_uv(ray, ray.at(hit.t), hit.normal, hit.t, hit.u, hit.v);
+            let mut record =
+                HitRecord::from_surface(surface, self.materials[segment.material].as_ref());
+            record.set_tangent_frame(hit.tangent, hit.bitangent);
+            closest_hit = Some(record);
+        }
+
+        closest_hit
+    }
+}
+
+impl Hittable for CurveSet {
+    fn hit_with_rng(
+        &self,
+        ray: &Ray,
+        ray_t: Interval,
+        _rng: &mut SampleRng,
+    ) -> Option<HitRecord<'_>> {
+        self.bvh.as_ref().and_then(|bvh| {
+            bvh.hit_with(ray_t, RayTraversal::new(ray), |indices, ray_t| {
+                self.hit_segments(indices.iter().copied(), ray, ray_t)
+            })
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bounds
+    }
+}
+
+fn segment_count(curve: CurveGeometry) -> usize {
+    let [start, end] = curve.widths();
+    let width = start.max(end);
+    if width <= 0.0 {
+        return 1;
+    }
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let count =
+        (curve.control_polygon_length() / (SEGMENT_LENGTH_IN_WIDTHS * width)).ceil() as usize;
+    count.clamp(1, MAX_SEGMENTS_PER_CURVE)
+}
+
+/// Groups edges whose end points meet into polylines.
+fn edge_chains(edges: &EdgeMatrix) -> Vec<Vec<Point>> {
+    let mut chains: Vec<Vec<Point>> = Vec::new();
+    for (start, end) in edges.iter_edges() {
+        let start = Point::new(start[0], start[1], start[2]);
+        let end = Point::new(end[0], end[1], end[2]);
+        if (end - start).length_squared() <= CHAIN_EPSILON * CHAIN_EPSILON {
+            continue;
+        }
+        match chains.last_mut() {
+            Some(chain)
+                if chain.last().is_some_and(|last| {
+                    (start - *last).length_squared() <= CHAIN_EPSILON * CHAIN_EPSILON
+                }) =>
+            {
+                chain.push(end);
+            }
+            _ => chains.push(vec![start, end]),
+        }
+    }
+    chains
+}
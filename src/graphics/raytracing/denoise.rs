@@ -0,0 +1,526 @@
+//! Edge-avoiding à-trous denoising for path-traced [`DenoisingAovs`].
+//!
+//! [`Denoiser`] implements the spatial part of spatiotemporal variance-guided filtering (SVGF).
+//! The beauty pass is divided by the first-hit albedo, smoothed by repeated 5x5 à-trous wavelet
+//! passes with a doubling step, and multiplied by the albedo again. Each tap is weighted by
+//! luminance, normal, and albedo similarity. The luminance tolerance scales with the per-pixel
+//! variance buffer, so noisy pixels blur more than converged ones while geometry and texture
+//! edges stay sharp.
+
+use super::LinearColor;
+use crate::graphics::{camera::DenoisingAovs, display::HdrImage};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// B3-spline weights indexed by absolute tap offset.
+const ATROUS_KERNEL: [f64; 3] = [3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+/// Gaussian weights indexed by absolute tap offset for the variance prefilter.
+const VARIANCE_KERNEL: [f64; 2] = [1.0 / 2.0, 1.0 / 4.0];
+/// Albedo channels at or below this value are not demodulated.
+const MIN_DEMODULATION_ALBEDO: f64 = 1.0e-3;
+/// Tap radius used when variance has to be estimated from neighbouring pixels.
+const SPATIAL_VARIANCE_RADIUS: isize = 2;
+
+/// Edge-avoiding à-trous wavelet denoiser guided by albedo, normal, and variance AOVs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Denoiser {
+    iterations: u32,
+    luminance_sigma: f64,
+    normal_power: f64,
+    albedo_sigma: f64,
+    demodulate_albedo: bool,
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self {
+            iterations: 5,
+            luminance_sigma: 4.0,
+            normal_power: 128.0,
+            albedo_sigma: 0.1,
+            demodulate_albedo: true,
+        }
+    }
+}
+
+impl Denoiser {
+    /// Creates a denoiser with the default SVGF-style settings.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of à-trous passes. Pass `i` samples taps `2^i` pixels apart.
+    #[must_use]
+    pub const fn with_iterations(mut self, iterations: u32) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    /// Sets how many standard deviations of luminance difference still blend two pixels.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sigma` is not positive and finite.
+    #[must_use]
+    pub fn with_luminance_sigma(mut self, sigma: f64) -> Self {
+        assert!(
+            sigma.is_finite() && sigma > 0.0,
+            "denoiser luminance sigma must be positive and finite"
+        );
+        self.luminance_sigma = sigma;
+        self
+    }
+
+    /// Sets the exponent applied to the cosine between neighbouring normals.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `power` is negative or not finite.
+    #[must_use]
+    pub fn with_normal_power(mut self, power: f64) -> Self {
+        assert!(
+            power.is_finite() && power >= 0.0,
+            "denoiser normal power must be non-negative and finite"
+        );
+        self.normal_power = power;
+        self
+    }
+
+    /// Sets the albedo distance that falls off to `1/e` weight.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sigma` is not positive and finite.
+    #[must_use]
+    pub fn with_albedo_sigma(mut self, sigma: f64) -> Self {
+        assert!(
+            sigma.is_finite() && sigma > 0.0,
+            "denoiser albedo sigma must be positive and finite"
+        );
+        self.albedo_sigma = sigma;
+        self
+    }
+
+    /// Controls whether the beauty pass is divided by albedo before filtering.
+    ///
+    /// Demodulation keeps texture detail that the albedo AOV already resolves.
+    #[must_use]
+    pub const fn with_albedo_demodulation(mut self, demodulate: bool) -> Self {
+        self.demodulate_albedo = demodulate;
+        self
+    }
+
+    /// Returns the number of à-trous passes.
+    #[must_use]
+    pub const fn iterations(&self) -> u32 {
+        self.iterations
+    }
+
+    /// Denoises the linear beauty pass of `aovs`.
+    ///
+    /// Albedo, normal, or variance buffers whose length does not match the image are ignored.
+    /// Pixels without a finite variance use a variance estimated from their neighbours.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `beauty_linear` does not hold one sample per beauty canvas pixel.
+    #[must_use]
+    pub fn denoise(&self, aovs: &DenoisingAovs) -> HdrImage {
+        let width = aovs.beauty.width();
+        let height = aovs.beauty.height();
+        let guides = Guides::new(aovs);
+        assert_eq!(
+            aovs.beauty_linear.len(),
+            guides.width * guides.height,
+            "denoising beauty buffer must match the beauty canvas size"
+        );
+
+        let demodulation: Vec<LinearColor> = (0..aovs.beauty_linear.len())
+            .map(|index| self.demodulation(&guides, index))
+            .collect();
+        let mut color: Vec<LinearColor> = aovs
+            .beauty_linear
+            .iter()
+            .zip(&demodulation)
+            .map(|(beauty, factor)| finite_or_black(divide(*beauty, *factor)))
+            .collect();
+        let mut variance = self.initial_variance(aovs, &guides, &color, &demodulation);
+
+        for iteration in 0..self.iterations.min(usize::BITS - 1) {
+            let step = 1_isize << iteration;
+            if step >= isize::try_from(guides.width.max(guides.height)).unwrap_or(isize::MAX) {
+                break;
+            }
+            let blurred_variance = prefilter_variance(&guides, &variance);
+            let filtered: Vec<(LinearColor, f64)> = map_pixels(color.len(), |index| {
+                self.atrous_tap(&guides, &color, &variance, &blurred_variance, index, step)
+            });
+            (color, variance) = filtered.into_iter().unzip();
+        }
+
+        let pixels = color
+            .into_iter()
+            .zip(demodulation)
+            .map(|(color, factor)| color.component_mul(factor))
+            .collect();
+        HdrImage::from_pixels(width, height, pixels)
+    }
+
+    fn demodulation(&self, guides: &Guides<'_>, index: usize) -> LinearColor {
+        let Some(albedo) = guides.albedo.filter(|_| self.demodulate_albedo) else {
+            return LinearColor::new(1.0, 1.0, 1.0);
+        };
+        let factor = |value: f64| {
+            if value > MIN_DEMODULATION_ALBEDO {
+                value
+            } else {
+                1.0
+            }
+        };
+        let albedo = albedo[index];
+        LinearColor::new(
+            factor(albedo.red),
+            factor(albedo.green),
+            factor(albedo.blue),
+        )
+    }
+
+    fn initial_variance(
+        &self,
+        aovs: &DenoisingAovs,
+        guides: &Guides<'_>,
+        color: &[LinearColor],
+        demodulation: &[LinearColor],
+    ) -> Vec<f64> {
+        let rendered = (aovs.variance.len() == color.len()).then_some(aovs.variance.as_slice());
+        map_pixels(color.len(), |index| {
+            let variance = rendered.map_or(f64::NAN, |variance| variance[index]);
+            if variance.is_finite() && variance >= 0.0 {
+                let scale = luminance(demodulation[index]).max(MIN_DEMODULATION_ALBEDO);
+                variance / (scale * scale)
+            } else {
+                self.spatial_variance(guides, color, index)
+            }
+        })
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn spatial_variance(&self, guides: &Guides<'_>, color: &[LinearColor], index: usize) -> f64 {
+        let mut weight_sum = 0.0;
+        let mut first_moment = 0.0;
+        let mut second_moment = 0.0;
+        for offset_y in -SPATIAL_VARIANCE_RADIUS..=SPATIAL_VARIANCE_RADIUS {
+            for offset_x in -SPATIAL_VARIANCE_RADIUS..=SPATIAL_VARIANCE_RADIUS {
+                let Some(neighbour) = guides.offset(index, offset_x, offset_y) else {
+                    continue;
+                };
+                let weight = self.guide_weight(guides, index, neighbour);
+                let value = luminance(color[neighbour]);
+                weight_sum += weight;
+                first_moment += weight * value;
+                second_moment += weight * value * value;
+            }
+        }
+        let mean = first_moment / weight_sum;
+        (second_moment / weight_sum - mean * mean).max(0.0)
+    }
+
+    fn atrous_tap(
+        &self,
+        guides: &Guides<'_>,
+        color: &[LinearColor],
+        variance: &[f64],
+        blurred_variance: &[f64],
+        index: usize,
+        step: isize,
+    ) -> (LinearColor, f64) {
+        let center_luminance = luminance(color[index]);
+        let luminance_scale = self.luminance_sigma * blurred_variance[index].max(0.0).sqrt();
+        let mut weight_sum = 0.0;
+        let mut color_sum = LinearColor::default();
+        let mut variance_sum = 0.0;
+        for (offset_y, kernel_y) in kernel_taps() {
+            for (offset_x, kernel_x) in kernel_taps() {
+                let Some(neighbour) = guides.offset(index, offset_x * step, offset_y * step) else {
+                    continue;
+                };
+                let luminance_distance = (center_luminance - luminance(color[neighbour])).abs();
+                let luminance_weight = if luminance_scale > f64::EPSILON {
+                    (-luminance_distance / luminance_scale).exp()
+                } else if luminance_distance <= f64::EPSILON {
+                    1.0
+                } else {
+                    0.0
+                };
+                let weight = kernel_x
+                    * kernel_y
+                    * luminance_weight
+                    * self.guide_weight(guides, index, neighbour);
+                weight_sum += weight;
+                color_sum += color[neighbour] * weight;
+                variance_sum += weight * weight * variance[neighbour];
+            }
+        }
+        // The center tap always has full edge weight, so `weight_sum` is positive.
+        (
+            color_sum / weight_sum,
+            variance_sum / (weight_sum * weight_sum),
+        )
+    }
+
+    fn guide_weight(&self, guides: &Guides<'_>, index: usize, neighbour: usize) -> f64 {
+        let mut weight = 1.0;
+        if let Some(normals) = &guides.normals {
+            let (center, other) = (normals[index], normals[neighbour]);
+            if let (Some(center), Some(other)) = (center, other) {
+                let cosine = (center.red * other.red
+                    + center.green * other.green
+                    + center.blue * other.blue)
+                    .max(0.0);
+                weight *= cosine.powf(self.normal_power);
+            } else if center.is_some() != other.is_some() {
+                weight = 0.0;
+            }
+        }
+        if let Some(albedo) = guides.albedo {
+            let difference = albedo[index] - albedo[neighbour];
+            let distance_squared = difference.red * difference.red
+                + difference.green * difference.green
+                + difference.blue * difference.blue;
+            weight *= (-distance_squared / (self.albedo_sigma * self.albedo_sigma)).exp();
+        }
+        weight
+    }
+}
+
+/// Guide buffers shared by every pass.
+struct Guides<'a> {
+    width: usize,
+    height: usize,
+    albedo: Option<&'a [LinearColor]>,
+    /// Unit normals decoded from `[0, 1]`, or `None` where no surface was hit.
+    normals: Option<Vec<Option<LinearColor>>>,
+}
+
+impl<'a> Guides<'a> {
+    fn new(aovs: &'a DenoisingAovs) -> Self {
+        let width = aovs.beauty.width() as usize;
+        let height = aovs.beauty.height() as usize;
+        let count = width * height;
+        let albedo = (aovs.albedo_linear.len() == count).then_some(aovs.albedo_linear.as_slice());
+        let normals = (aovs.normal_linear.len() == count).then(|| {
+            aovs.normal_linear
+                .iter()
+                .map(|encoded| decode_normal(*encoded))
+                .collect()
+        });
+        Self {
+            width,
+            height,
+            albedo,
+            normals,
+        }
+    }
+
+    fn offset(&self, index: usize, offset_x: isize, offset_y: isize) -> Option<usize> {
+        let x = (index % self.width).checked_add_signed(offset_x)?;
+        let y = (index / self.width).checked_add_signed(offset_y)?;
+        (x < self.width && y < self.height).then_some(y * self.width + x)
+    }
+}
+
+fn kernel_taps() -> impl Iterator<Item = (isize, f64)> {
+    (-2_isize..=2).map(|offset| (offset, ATROUS_KERNEL[offset.unsigned_abs()]))
+}
+
+fn prefilter_variance(guides: &Guides<'_>, variance: &[f64]) -> Vec<f64> {
+    map_pixels(variance.len(), |index| {
+        let mut weight_sum = 0.0;
+        let mut sum = 0.0;
+        for offset_y in -1_isize..=1 {
+            for offset_x in -1_isize..=1 {
+                if let Some(neighbour) = guides.offset(index, offset_x, offset_y) {
+                    let weight = VARIANCE_KERNEL[offset_x.unsigned_abs()]
+                        * VARIANCE_KERNEL[offset_y.unsigned_abs()];
+                    weight_sum += weight;
+                    sum += weight * variance[neighbour];
+                }
+            }
+        }
+        sum / weight_sum
+    })
+}
+
+fn map_pixels<T, F>(count: usize, pixel: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(usize) -> T + Sync + Send,
+{
+    #[cfg(feature = "rayon")]
+    {
+        (0..count).into_par_iter().map(pixel).collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        (0..count).map(pixel).collect()
+    }
+}
+
+fn decode_normal(encoded: LinearColor) -> Option<LinearColor> {
+    let normal = LinearColor::new(
+        encoded.red.mul_add(2.0, -1.0),
+        encoded.green.mul_add(2.0, -1.0),
+        encoded.blue.mul_add(2.0, -1.0),
+    );
+    let length =
+        (normal.red * normal.red + normal.green * normal.green + normal.blue * normal.blue).sqrt();
+    // Misses encode as zero, which decodes to a vector longer than any averaged surface normal.
+    (length > 1.0e-6 && length <= 1.0 + 1.0e-6).then(|| normal / length)
+}
+
+fn divide(color: LinearColor, factor: LinearColor) -> LinearColor {
+    LinearColor::new(
+        color.red / factor.red,
+        color.green / factor.green,
+        color.blue / factor.blue,
+    )
+}
+
+fn finite_or_black(color: LinearColor) -> LinearColor {
+    if color.is_finite() {
+        color
+    } else {
+        LinearColor::default()
+    }
+}
+
+/// Rec. 709 luminance of a linear color.
+pub(crate) fn luminance(color: LinearColor) -> f64 {
+    0.2126 * color.red + 0.7152 * color.green + 0.0722 * color.blue
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        gmath::random::SampleRng,
+        graphics::{colors::Rgb, display::Canvas},
+    };
+
+    fn aovs(
+        width: u32,
+        height: u32,
+        beauty: impl Fn(usize, usize) -> LinearColor,
+        albedo: impl Fn(usize, usize) -> LinearColor,
+    ) -> DenoisingAovs {
+        let (columns, rows) = (width as usize, height as usize);
+        let buffer = |value: &dyn Fn(usize, usize) -> LinearColor| -> Vec<LinearColor> {
+            (0..columns * rows)
+                .map(|index| value(index % columns, index / columns))
+                .collect()
+        };
+        DenoisingAovs::new(
+            Canvas::new(width, height, Rgb::default()),
+            Canvas::new(width, height, Rgb::default()),
+            Canvas::new(width, height, Rgb::default()),
+            buffer(&beauty),
+            buffer(&albedo),
+            buffer(&|_, _| LinearColor::new(0.5, 1.0, 0.5)),
+        )
+    }
+
+    fn mean_squared_error(image: &[LinearColor], expected: impl Fn(usize) -> f64) -> f64 {
+        #[allow(clippy::cast_precision_loss)]
+        let count = image.len() as f64;
+        image
+            .iter()
+            .enumerate()
+            .map(|(index, pixel)| (pixel.red - expected(index)).powi(2))
+            .sum::<f64>()
+            / count
+    }
+
+    #[test]
+    fn denoiser_reduces_noise_on_flat_regions() {
+        let mut rng = SampleRng::new(3);
+        let offsets: Vec<f64> = (0..32 * 32).map(|_| rng.random_double() - 0.5).collect();
+        let noisy = aovs(
+            32,
+            32,
+            |x, y| {
+                let value = 0.5 + offsets[y * 32 + x];
+                LinearColor::new(value, value, value)
+            },
+            |_, _| LinearColor::new(0.8, 0.8, 0.8),
+        )
+        .with_variance(vec![1.0 / 12.0; 32 * 32]);
+
+        let denoised = noisy.denoise();
+
+        let before = mean_squared_error(&noisy.beauty_linear, |_| 0.5);
+        let after = mean_squared_error(denoised.pixels(), |_| 0.5);
+        assert!(after < before * 0.1, "{before} -> {after}");
+    }
+
+    #[test]
+    fn denoiser_keeps_albedo_edges() {
+        let albedo = |x: usize, _| {
+            if x < 8 {
+                LinearColor::new(0.1, 0.1, 0.1)
+            } else {
+                LinearColor::new(0.9, 0.9, 0.9)
+            }
+        };
+        let aovs = aovs(16, 8, albedo, albedo);
+
+        let denoised = Denoiser::new().denoise(&aovs);
+
+        let error = mean_squared_error(
+            denoised.pixels(),
+            |index| {
+                if index % 16 < 8 { 0.1 } else { 0.9 }
+            },
+        );
+        assert!(error < 1.0e-8, "{error}");
+    }
+
+    #[test]
+    fn denoiser_estimates_missing_variance_and_ignores_mismatched_guides() {
+        let mut rng = SampleRng::new(9);
+        let offsets: Vec<f64> = (0..16 * 16).map(|_| rng.random_double()).collect();
+        let mut noisy = aovs(
+            16,
+            16,
+            |x, y| {
+                let value = offsets[y * 16 + x];
+                LinearColor::new(value, value, value)
+            },
+            |_, _| LinearColor::new(1.0, 1.0, 1.0),
+        );
+        noisy.albedo_linear.clear();
+        noisy.normal_linear.pop();
+
+        let denoised = Denoiser::new().with_iterations(3).denoise(&noisy);
+
+        assert_eq!(denoised.width(), 16);
+        let before = mean_squared_error(&noisy.beauty_linear, |_| 0.5);
+        let after = mean_squared_error(denoised.pixels(), |_| 0.5);
+        assert!(after < before, "{before} -> {after}");
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn zero_iterations_return_the_beauty_pass() {
+        let aovs = aovs(
+            3,
+            2,
+            |x, y| LinearColor::new(x as f64, y as f64, 2.0),
+            |_, _| LinearColor::new(0.5, 0.25, 0.0),
+        );
+
+        let denoised = Denoiser::new().with_iterations(0).denoise(&aovs);
+
+        assert_eq!(denoised.pixels(), aovs.beauty_linear.as_slice());
+    }
+}
@@ -1,27 +1,39 @@
 //! Environment lighting and importance sampling.
 
-use super::LinearColor;
+use super::{LinearColor, PhysicalSky};
+#[cfg(feature = "spectral")]
+use super::{SampledWavelength, Spectrum};
 use crate::{
     gmath::{
+        geometry::OrthonormalBasis,
         random::SampleRng,
         vector::{Point, Vector},
     },
     graphics::{
+        codec::ExrError,
         colors::LinearRgb,
-        display::Canvas,
+        display::{Canvas, HdrImage},
         texture::{
-            SurfaceTexture, Texture as BitmapTexture, TextureFilter, TextureSample, TextureWrap,
+            HdrTexture, SurfaceTexture, Texture as BitmapTexture, TextureFilter, TextureSample,
+            TextureWrap,
         },
     },
 };
 
 const TAU: f64 = std::f64::consts::TAU;
 
+/// Lat-long table resolution used to importance-sample a [`PhysicalSky`].
+const SKY_TABLE_WIDTH: u32 = 256;
+const SKY_TABLE_HEIGHT: u32 = 128;
+
 /// Lat-long environment light with luminance-weighted importance sampling.
 #[derive(Clone, Debug)]
 pub struct EnvironmentLight {
     texture: BitmapTexture,
+    hdr: Option<HdrTexture>,
     constant_radiance: Option<LinearColor>,
+    sky: Option<PhysicalSky>,
+    sun_probability: f64,
     weights: Vec<f64>,
     cdf: Vec<f64>,
     total_weight: f64,
@@ -42,7 +54,10 @@ impl EnvironmentLight {
             texture: texture
                 .wrap(TextureWrap::Repeat, TextureWrap::Clamp)
                 .filter(TextureFilter::Linear),
+            hdr: None,
             constant_radiance: None,
+            sky: None,
+            sun_probability: 0.0,
             weights: Vec::new(),
             cdf: Vec::new(),
             total_weight: 0.0,
@@ -59,6 +74,88 @@ impl EnvironmentLight {
         Self::new(BitmapTexture::from_canvas(canvas))
     }
 
+    /// Creates an environment from linear HDR pixels without clamping radiance.
+    ///
+    /// [`Self::texture`] holds a tone-mapped preview; lighting and importance sampling use the
+    /// float pixels directly.
+    #[must_use]
+    pub fn from_hdr_image(image: HdrImage) -> Self {
+        Self::from_hdr_texture(HdrTexture::from(image))
+    }
+
+    /// Creates an environment from a float texture without clamping radiance.
+    ///
+    /// The base level is sampled bilinearly with texel centers at half-texel offsets, repeating
+    /// horizontally and clamping at the poles; the texture's own wrap and filter modes are reset
+    /// to match.
+    #[must_use]
+    pub fn from_hdr_texture(texture: HdrTexture) -> Self {
+        let mut environment = Self::from_canvas(texture.to_hdr_image().to_canvas());
+        environment.hdr = Some(
+            texture
+                .wrap(TextureWrap::Repeat, TextureWrap::Clamp)
+                .filter(TextureFilter::Linear),
+        );
+        environment.rebuild_distribution();
+        environment
+    }
+
+    /// Loads a `.hdr`, `.rgbe`, `.pfm`, or `.exr` lat-long environment map.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the extension is not an HDR format or the file cannot be decoded.
+    pub fn from_hdr_file(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        Ok(Self::from_hdr_texture(HdrTexture::from_path(path)?))
+    }
+
+    /// Loads the default RGB layer of an `OpenEXR` lat-long environment map.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the file cannot be read or decoded, or has no RGB channels.
+    pub fn from_exr(path: impl AsRef<std::path::Path>) -> Result<Self, ExrError> {
+        Ok(Self::from_hdr_image(HdrImage::from_exr(path)?))
+    }
+
+    /// Creates an environment from an analytic daylight sky.
+    ///
+    /// Radiance is evaluated from the model directly. Importance sampling mixes a luminance table
+    /// of the sky and ground with uniform sampling of the sun disk's cone, weighted by their
+    /// power, so both diffuse skylight and the small, bright sun converge quickly.
+    #[must_use]
+    pub fn from_physical_sky(sky: PhysicalSky) -> Self {
+        let width = f64::from(SKY_TABLE_WIDTH);
+        let height = f64::from(SKY_TABLE_HEIGHT);
+        let mut pixels = Vec::with_capacity(Canvas::pixel_count(SKY_TABLE_WIDTH, SKY_TABLE_HEIGHT));
+        for y in 0..SKY_TABLE_HEIGHT {
+            let theta = std::f64::consts::PI * (f64::from(y) + 0.5) / height;
+            for x in 0..SKY_TABLE_WIDTH {
+                let phi = TAU * (f64::from(x) + 0.5) / width;
+                pixels.push(sky.sky_radiance(Vector::new(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                )));
+            }
+        }
+        let mut environment = Self::from_hdr_image(HdrImage::from_pixels(
+            SKY_TABLE_WIDTH,
+            SKY_TABLE_HEIGHT,
+            pixels,
+        ));
+        let table_power =
+            environment.total_weight * (TAU / width) * (std::f64::consts::PI / height);
+        let sun_power = luminance(sky.sun_radiance()) * sky.sun_solid_angle();
+        environment.sun_probability = if sun_power > 0.0 {
+            sun_power / (sun_power + table_power)
+        } else {
+            0.0
+        };
+        environment.sky = Some(sky);
+        environment
+    }
+
     /// Creates a constant-color environment.
     #[must_use]
     pub fn constant(color: LinearColor) -> Self {
@@ -78,35 +175,95 @@ impl EnvironmentLight {
 
     /// Loads an environment image file.
     ///
+    /// `.hdr`, `.rgbe`, `.pfm`, and `.exr` files keep their float radiance through
+    /// [`Self::from_hdr_file`]; other formats are converted into a display canvas.
+    ///
     /// # Errors
     ///
     /// Returns an error if the image cannot be loaded or converted into a canvas.
     #[cfg(feature = "external")]
     pub fn from_file(path: impl AsRef<str>) -> Result<Self, Box<dyn std::error::Error>> {
-        let canvas = crate::external::ppmify(path.as_ref(), false)?;
+        let path = path.as_ref();
+        if is_hdr_path(path) {
+            return Ok(Self::from_hdr_file(path)?);
+        }
+        let canvas = crate::external::ppmify(path, false)?;
         Ok(Self::from_canvas(canvas))
     }
 
     /// Returns the underlying texture.
+    ///
+    /// For HDR environments this is a tone-mapped preview of [`Self::hdr_texture`].
     #[must_use]
     pub const fn texture(&self) -> &BitmapTexture {
         &self.texture
     }
 
+    /// Returns the float texture used for lighting, if the environment was built from HDR data.
+    ///
+    /// For physical skies this is the baked sky table used for importance sampling.
+    #[must_use]
+    pub const fn hdr_texture(&self) -> Option<&HdrTexture> {
+        self.hdr.as_ref()
+    }
+
+    /// Returns the analytic sky, if the environment was built from one.
+    #[must_use]
+    pub const fn physical_sky(&self) -> Option<&PhysicalSky> {
+        self.sky.as_ref()
+    }
+
     /// Returns radiance for a world-space direction.
     #[must_use]
     pub fn radiance(&self, direction: Vector) -> LinearColor {
         if let Some(color) = self.constant_radiance {
             return color;
         }
+        if let Some(sky) = &self.sky {
+            return sky.radiance(direction);
+        }
         let (u, v) = direction_to_latlong_uv(direction);
+        if let Some(texture) = &self.hdr {
+            return sample_hdr_bilinear(texture, u, v);
+        }
         self.texture
             .sample_linear(TextureSample::new(u, v, Point::default()))
     }
 
+    /// Returns sampled-wavelength radiance for a world-space direction.
+    ///
+    /// Physical skies evaluate their sun spectrally; other environments lift their RGB radiance
+    /// through [`Spectrum::from_linear_rgb`].
+    #[cfg(feature = "spectral")]
+    #[must_use]
+    pub fn spectral_radiance(&self, direction: Vector, wavelength: SampledWavelength) -> f64 {
+        match &self.sky {
+            Some(sky) if self.constant_radiance.is_none() => {
+                sky.spectral_radiance(direction, wavelength)
+            }
+            _ => Spectrum::from_linear_rgb(self.radiance(direction)).sample(wavelength),
+        }
+    }
+
     /// Samples an incident direction and its solid-angle PDF.
     #[must_use]
     pub fn sample_direction(&self, rng: &mut SampleRng) -> (Vector, f64) {
+        if let Some(sky) = &self.sky
+            && self.sun_probability > 0.0
+            && rng.random_double() < self.sun_probability
+            && let Some(basis) = OrthonormalBasis::from_w(sky.sun_direction())
+        {
+            let cos_max = sky.sun_cos_angular_radius();
+            let cos_theta = 1.0 - rng.random_double() * (1.0 - cos_max);
+            let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+            let phi = TAU * rng.random_double();
+            let direction = basis.local(Vector::new(
+                sin_theta * phi.cos(),
+                sin_theta * phi.sin(),
+                cos_theta,
+            ));
+            return (direction, self.pdf_value(direction));
+        }
         if self.total_weight <= f64::EPSILON || self.weights.is_empty() {
             return (
                 rng.random_unit_vector_spherical(),
@@ -134,6 +291,22 @@ impl EnvironmentLight {
     /// Returns the solid-angle PDF for a world-space direction.
     #[must_use]
     pub fn pdf_value(&self, direction: Vector) -> f64 {
+        let Some(sky) = self.sky.as_ref().filter(|_| self.sun_probability > 0.0) else {
+            return self.table_pdf_value(direction);
+        };
+        let cone_pdf =
+            if direction.normalized().dot(sky.sun_direction()) >= sky.sun_cos_angular_radius() {
+                1.0 / sky.sun_solid_angle()
+            } else {
+                0.0
+            };
+        (1.0 - self.sun_probability).mul_add(
+            self.table_pdf_value(direction),
+            self.sun_probability * cone_pdf,
+        )
+    }
+
+    fn table_pdf_value(&self, direction: Vector) -> f64 {
         if self.total_weight <= f64::EPSILON || self.weights.is_empty() {
             return 1.0 / (4.0 * std::f64::consts::PI);
         }
@@ -157,6 +330,7 @@ impl EnvironmentLight {
 
     fn rebuild_distribution(&mut self) {
         let image = self.texture.image();
+        let hdr = self.hdr.as_ref();
         self.width = usize::try_from(image.width()).expect("environment width should fit usize");
         self.height = usize::try_from(image.height()).expect("environment height should fit usize");
         self.weights.clear();
@@ -174,7 +348,10 @@ impl EnvironmentLight {
             let sin_theta = theta.sin().max(0.0);
             for x in 0..self.width {
                 let index = y * self.width + x;
-                let color = LinearRgb::from_rgb_srgb(image.pixels()[index]);
+                let color = hdr.map_or_else(
+                    || LinearRgb::from_rgb_srgb(image.pixels()[index]),
+                    |hdr| hdr.texel((index % self.width) as u32, (index / self.width) as u32),
+                );
                 let weight = luminance(color).max(0.0) * sin_theta;
                 self.total_weight += weight;
                 self.weights.push(weight);
@@ -197,6 +374,49 @@ fn direction_to_latlong_uv(direction: Vector) -> (f64, f64) {
     (phi / TAU, 1.0 - theta / std::f64::consts::PI)
 }
 
+/// Bilinearly samples lat-long `(u, v)`, repeating horizontally and clamping at the poles.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_possible_wrap,
+    clippy::cast_sign_loss
+)]
+fn sample_hdr_bilinear(texture: &HdrTexture, u: f64, v: f64) -> LinearColor {
+    let width = texture.width() as usize;
+    let height = texture.height() as usize;
+    if width == 0 || height == 0 {
+        return LinearColor::new(0.0, 0.0, 0.0);
+    }
+    let x = u.rem_euclid(1.0).mul_add(f64::from(texture.width()), -0.5);
+    let y = (1.0 - v).mul_add(f64::from(texture.height()), -0.5);
+    let x0 = x.floor();
+    let y0 = y.floor();
+    let tx = x - x0;
+    let ty = y - y0;
+    let column = |offset: f64| ((x0 + offset) as i64).rem_euclid(width as i64) as usize;
+    let row = |offset: f64| ((y0 + offset).max(0.0) as usize).min(height - 1);
+    let texel = |x: usize, y: usize| texture.texel(x as u32, y as u32);
+    let (left, right) = (column(0.0), column(1.0));
+    let (top, bottom) = (row(0.0), row(1.0));
+    let lerp = |a: LinearColor, b: LinearColor, t: f64| a * (1.0 - t) + b * t;
+    lerp(
+        lerp(texel(left, top), texel(right, top), tx),
+        lerp(texel(left, bottom), texel(right, bottom), tx),
+        ty,
+    )
+}
+
+#[cfg(feature = "external")]
+fn is_hdr_path(path: &str) -> bool {
+    std::path::Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            ["hdr", "rgbe", "pfm", "exr"]
+                .iter()
+                .any(|hdr| extension.eq_ignore_ascii_case(hdr))
+        })
+}
+
 fn luminance(color: LinearRgb) -> f64 {
     0.2126 * color.red + 0.7152 * color.green + 0.0722 * color.blue
 }
@@ -233,6 +453,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn environment_light_from_hdr_image_keeps_unclamped_radiance() {
+        let image = HdrImage::from_pixels(
+            2,
+            1,
+            vec![
+                LinearRgb::new(0.5, 0.5, 0.5),
+                LinearRgb::new(40.0, 20.0, 10.0),
+            ],
+        );
+        let environment = EnvironmentLight::from_hdr_image(image);
+        let sun = Vector::new(0.0, 0.0, -1.0);
+        let sky = Vector::new(0.0, 0.0, 1.0);
+
+        let radiance = environment.radiance(sun);
+        assert!(radiance.red > 39.0 && radiance.red <= 40.0, "{radiance:?}");
+        assert!(environment.pdf_value(sun) > 40.0 * environment.pdf_value(sky));
+    }
+
     #[test]
     fn environment_light_samples_brighter_texels_more_often() {
         let canvas = Canvas::from_pixels_rgb_only(2, 1, vec![Rgb::BLACK, Rgb::WHITE], true, false);
@@ -267,4 +506,85 @@ mod tests {
 
         assert!((mean_cos_theta - 0.5).abs() < 0.03, "{mean_cos_theta}");
     }
+
+    #[test]
+    fn environment_light_from_radiance_file_keeps_sun_above_one() {
+        let mut pixels = vec![LinearRgb::new(0.2, 0.3, 0.5); 8];
+        pixels[2] = LinearRgb::new(5000.0, 4800.0, 4500.0);
+        let path =
+            std::env::temp_dir().join(format!("gartus-environment-sun-{}.hdr", std::process::id()));
+        HdrImage::from_pixels(4, 2, pixels)
+            .save_radiance_hdr(path.to_str().expect("temp path should be utf8"))
+            .unwrap();
+        let environment = EnvironmentLight::from_hdr_file(&path).unwrap();
+        let _ = std::fs::remove_file(path);
+
+        // Texel (2, 0) is centered at phi = 5/8 turn in the upper hemisphere.
+        let phi = TAU * 0.625;
+        let sun = Vector::new(phi.cos(), 0.7, phi.sin());
+        let sky = Vector::new(-phi.cos(), 0.7, -phi.sin());
+        let texture = environment
+            .hdr_texture()
+            .expect("HDR file should keep float texels");
+
+        assert!(texture.texel(2, 0).red > 4900.0);
+        assert!(environment.radiance(sun).red > 1.0);
+        assert!(environment.pdf_value(sun) > 1000.0 * environment.pdf_value(sky));
+    }
+
+    #[test]
+    fn physical_sky_environment_samples_the_sun_with_a_consistent_pdf() {
+        let sky = PhysicalSky::new(40.0, 60.0);
+        let environment = EnvironmentLight::from_physical_sky(sky);
+        let mut rng = SampleRng::new(11);
+        let mut sun_hits = 0;
+        let sample_count = 4000;
+
+        for _ in 0..sample_count {
+            let (direction, pdf) = environment.sample_direction(&mut rng);
+            assert!(pdf.is_finite() && pdf > 0.0, "{pdf}");
+            assert!((environment.pdf_value(direction) - pdf).abs() <= pdf * 1.0e-9);
+            if sky.is_sun_direction(direction) {
+                sun_hits += 1;
+            }
+        }
+
+        // The tiny sun disk still carries a large share of a clear sky's power.
+        let sun_fraction = f64::from(sun_hits) / f64::from(sample_count);
+        assert!(environment.sun_probability > 0.25);
+        assert!(
+            (sun_fraction - environment.sun_probability).abs() < 0.03,
+            "{sun_fraction} vs {}",
+            environment.sun_probability
+        );
+        assert!(environment.radiance(sky.sun_direction()).red > 100.0);
+        assert_eq!(
+            environment.radiance(Vector::new(0.0, 1.0, 0.0)),
+            sky.radiance(Vector::new(0.0, 1.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn physical_sky_environment_estimates_horizontal_irradiance() {
+        let sky = PhysicalSky::new(60.0, 0.0);
+        let environment = EnvironmentLight::from_physical_sky(sky);
+        let mut rng = SampleRng::new(3);
+        let sample_count = 20_000;
+        let mut estimate = 0.0;
+
+        for _ in 0..sample_count {
+            let (direction, pdf) = environment.sample_direction(&mut rng);
+            let cosine = direction.normalized().y().max(0.0);
+            estimate += luminance(environment.radiance(direction)) * cosine / pdf;
+        }
+        estimate /= f64::from(sample_count);
+
+        let ground_albedo = luminance(sky.ground_albedo());
+        let expected = luminance(sky.radiance(Vector::new(0.0, -1.0, 0.0))) * std::f64::consts::PI
+            / ground_albedo;
+        assert!(
+            (estimate - expected).abs() < 0.05 * expected,
+            "{estimate} vs {expected}"
+        );
+    }
 }
@@ -0,0 +1,621 @@
+//! Hair fiber scattering for ray-traced curves.
+
+use super::{
+    BsdfFlags, BsdfSample, HitRecord, LinearColor, Material, PI, SampleRng, ScatterRecord,
+    denoise::luminance,
+};
+use crate::gmath::{ray::Ray, vector::Vector};
+
+/// Number of explicit azimuthal lobes: reflection (R), transmission (TT), and TRT.
+const P_MAX: usize = 3;
+const SQRT_PI_OVER_8: f64 = 0.626_657_068_657_750_1;
+const EUMELANIN_SIGMA_A: [f64; 3] = [0.419, 0.697, 1.37];
+const PHEOMELANIN_SIGMA_A: [f64; 3] = [0.187, 0.4, 1.05];
+
+/// Hair fiber BSDF with longitudinal and azimuthal lobes.
+///
+/// This is the rough dielectric cylinder model of d'Eon et al. and Chiang et al. as described in
+/// pbrt. Light scatters into an `R` lobe reflected off the cuticle, `TT` and `TRT` lobes that cross
+/// the absorbing interior, and one residual lobe for longer paths. Each lobe is a product of a
+/// longitudinal term controlled by `beta_m` and an azimuthal term controlled by `beta_n`.
+///
+/// The BSDF reads its frame from the hit: `tangent` runs along the fiber and `v` runs across its
+/// width, as produced by [`CurveSet`](super::CurveSet). Hits without a tangent fall back to a frame
+/// around the shading normal.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HairBsdf {
+    sigma_a: LinearColor,
+    eta: f64,
+    beta_m: f64,
+    beta_n: f64,
+    alpha: f64,
+}
+
+impl HairBsdf {
+    /// Creates a hair BSDF from an interior absorption coefficient and lobe roughnesses.
+    ///
+    /// `beta_m` is the longitudinal roughness and `beta_n` the azimuthal roughness, both clamped
+    /// into `[0.01, 1]`. Absorption is per unit fiber diameter.
+    #[must_use]
+    pub fn new(sigma_a: LinearColor, beta_m: f64, beta_n: f64) -> Self {
+        Self {
+            sigma_a: LinearColor::new(
+                sigma_a.red.max(0.0),
+                sigma_a.green.max(0.0),
+                sigma_a.blue.max(0.0),
+            ),
+            eta: 1.55,
+            beta_m: beta_m.clamp(0.01, 1.0),
+            beta_n: beta_n.clamp(0.01, 1.0),
+            alpha: 2.0,
+        }
+    }
+
+    /// Creates a hair BSDF whose multiple-scattered color approximates `color`.
+    #[must_use]
+    pub fn from_reflectance(color: LinearColor, beta_m: f64, beta_n: f64) -> Self {
+        let beta_n = beta_n.clamp(0.01, 1.0);
+        let scale = reflectance_scale(beta_n);
+        let sigma = |channel: f64| (channel.clamp(1e-4, 1.0).ln() / scale).powi(2);
+        Self::new(
+            LinearColor::new(sigma(color.red), sigma(color.green), sigma(color.blue)),
+            beta_m,
+            beta_n,
+        )
+    }
+
+    /// Creates a hair BSDF from eumelanin and pheomelanin concentrations.
+    ///
+    /// Eumelanin darkens toward brown and black; pheomelanin gives red and blond tints. A
+    /// concentration of about 8 eumelanin is black hair, 1.3 is brown, and 0.3 is blond.
+    #[must_use]
+    pub fn from_melanin(eumelanin: f64, pheomelanin: f64, beta_m: f64, beta_n: f64) -> Self {
+        let eumelanin = eumelanin.max(0.0);
+        let pheomelanin = pheomelanin.max(0.0);
+        let sigma = |channel: usize| {
+            eumelanin * EUMELANIN_SIGMA_A[channel] + pheomelanin * PHEOMELANIN_SIGMA_A[channel]
+        };
+        Self::new(
+            LinearColor::new(sigma(0), sigma(1), sigma(2)),
+            beta_m,
+            beta_n,
+        )
+    }
+
+    /// Sets the fiber's index of refraction.
+    #[must_use]
+    pub fn with_eta(mut self, eta: f64) -> Self {
+        self.eta = eta.max(1.0 + f64::EPSILON);
+        self
+    }
+
+    /// Sets the cuticle scale tilt in degrees, which shifts the lobes along the fiber.
+    #[must_use]
+    pub fn with_scale_angle(mut self, degrees: f64) -> Self {
+        self.alpha = degrees;
+        self
+    }
+
+    /// Returns the interior absorption coefficient.
+    #[must_use]
+    pub const fn sigma_a(&self) -> LinearColor {
+        self.sigma_a
+    }
+
+    /// Returns the fiber's index of refraction.
+    #[must_use]
+    pub const fn eta(&self) -> f64 {
+        self.eta
+    }
+
+    /// Returns the longitudinal roughness.
+    #[must_use]
+    pub const fn beta_m(&self) -> f64 {
+        self.beta_m
+    }
+
+    /// Returns the azimuthal roughness.
+    #[must_use]
+    pub const fn beta_n(&self) -> f64 {
+        self.beta_n
+    }
+
+    /// Returns the cuticle scale tilt in degrees.
+    #[must_use]
+    pub const fn scale_angle(&self) -> f64 {
+        self.alpha
+    }
+
+    fn shading(&self, ray_in: &Ray, hit: &HitRecord<'_>) -> Option<HairShading> {
+        let outgoing = -ray_in.direction().normalized();
+        let frame = HairFrame::new(hit, outgoing)?;
+        let (sin_theta_o, cos_theta_o, phi_o) = frame.spherical(outgoing);
+        if cos_theta_o <= 1e-6 {
+            return None;
+        }
+
+        let h = frame.h;
+        let etap = (self.eta * self.eta - sin_theta_o * sin_theta_o).sqrt() / cos_theta_o;
+        let sin_gamma_t = (h / etap).clamp(-1.0, 1.0);
+        let cos_gamma_t = safe_sqrt(1.0 - sin_gamma_t * sin_gamma_t);
+        let sin_theta_t = sin_theta_o / self.eta;
+        let cos_theta_t = safe_sqrt(1.0 - sin_theta_t * sin_theta_t);
+        let path = 2.0 * cos_gamma_t / cos_theta_t;
+        let transmittance = LinearColor::new(
+            (-self.sigma_a.red * path).exp(),
+            (-self.sigma_a.green * path).exp(),
+            (-self.sigma_a.blue * path).exp(),
+        );
+
+        let fresnel = fresnel_dielectric(cos_theta_o * safe_sqrt(1.0 - h * h), self.eta);
+        let mut attenuation = [LinearColor::default(); P_MAX + 1];
+        attenuation[0] = LinearColor::new(fresnel, fresnel, fresnel);
+        attenuation[1] = transmittance * ((1.0 - fresnel) * (1.0 - fresnel));
+        for p in 2..P_MAX {
+            attenuation[p] = attenuation[p - 1].component_mul(transmittance) * fresnel;
+        }
+        let residual =
+            |channel: f64, previous: f64| previous * fresnel * channel / (1.0 - channel * fresnel);
+        attenuation[P_MAX] = LinearColor::new(
+            residual(transmittance.red, attenuation[P_MAX - 1].red),
+            residual(transmittance.green, attenuation[P_MAX - 1].green),
+            residual(transmittance.blue, attenuation[P_MAX - 1].blue),
+        );
+
+        let total = attenuation.iter().copied().map(luminance).sum::<f64>();
+        let mut lobe_pdf = [0.0; P_MAX + 1];
+        if total > 0.0 {
+            for (pdf, lobe) in lobe_pdf.iter_mut().zip(attenuation) {
+                *pdf = luminance(lobe) / total;
+            }
+        } else {
+            lobe_pdf[0] = 1.0;
+        }
+
+        let v0 = (0.726 * self.beta_m + 0.812 * self.beta_m.powi(2) + 3.7 * self.beta_m.powi(20))
+            .powi(2);
+        let mut sin_2k_alpha = [self.alpha.to_radians().sin(), 0.0, 0.0];
+        let mut cos_2k_alpha = [safe_sqrt(1.0 - sin_2k_alpha[0] * sin_2k_alpha[0]), 0.0, 0.0];
+        for i in 1..3 {
+            sin_2k_alpha[i] = 2.0 * cos_2k_alpha[i - 1] * sin_2k_alpha[i - 1];
+            cos_2k_alpha[i] = cos_2k_alpha[i - 1].powi(2) - sin_2k_alpha[i - 1].powi(2);
+        }
+
+        Some(HairShading {
+            frame,
+            sin_theta_o,
+            cos_theta_o,
+            phi_o,
+            gamma_o: h.clamp(-1.0, 1.0).asin(),
+            gamma_t: sin_gamma_t.asin(),
+            attenuation,
+            lobe_pdf,
+            variance: [v0, 0.25 * v0, 4.0 * v0, 4.0 * v0],
+            logistic_scale: SQRT_PI_OVER_8
+                * (0.265 * self.beta_n
+                    + 1.194 * self.beta_n.powi(2)
+                    + 5.372 * self.beta_n.powi(22)),
+            sin_2k_alpha,
+            cos_2k_alpha,
+        })
+    }
+}
+
+impl Default for HairBsdf {
+    /// Brown hair with pbrt's default roughness.
+    fn default() -> Self {
+        Self::from_melanin(1.3, 0.0, 0.3, 0.3)
+    }
+}
+
+impl Material for HairBsdf {
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        hit: &HitRecord<'_>,
+        rng: &mut SampleRng,
+    ) -> Option<ScatterRecord> {
+        let sample = self.sample_bsdf(ray_in, hit, rng)?;
+        Some(ScatterRecord::Specular {
+            ray: Ray::with_time(hit.point, sample.direction, ray_in.time()),
+            attenuation: sample.weight(),
+        })
+    }
+
+    fn bsdf_flags(&self, _hit: &HitRecord<'_>) -> BsdfFlags {
+        BsdfFlags::GLOSSY | BsdfFlags::REFLECTION | BsdfFlags::TRANSMISSION
+    }
+
+    /// The hair model already integrates against solid angle, so no cosine is applied.
+    fn eval_bsdf(&self, ray_in: &Ray, hit: &HitRecord<'_>, scattered: &Ray) -> LinearColor {
+        self.shading(ray_in, hit)
+            .map_or_else(LinearColor::default, |shading| {
+                shading.eval(shading.frame.spherical(scattered.direction().normalized()))
+            })
+    }
+
+    fn bsdf_pdf(&self, ray_in: &Ray, hit: &HitRecord<'_>, scattered: &Ray) -> f64 {
+        self.shading(ray_in, hit).map_or(0.0, |shading| {
+            shading.pdf(shading.frame.spherical(scattered.direction().normalized()))
+        })
+    }
+
+    fn sample_bsdf(
+        &self,
+        ray_in: &Ray,
+        hit: &HitRecord<'_>,
+        rng: &mut SampleRng,
+    ) -> Option<BsdfSample> {
+        let shading = self.shading(ray_in, hit)?;
+        let u = [
+            rng.random_double(),
+            rng.random_double(),
+            rng.random_double(),
+            rng.random_double(),
+        ];
+        let incoming = shading.sample(u);
+        let pdf = shading.pdf(incoming);
+        if !pdf.is_finite() || pdf <= 0.0 {
+            return None;
+        }
+        let (sin_theta, cos_theta, phi) = incoming;
+        Some(BsdfSample {
+            direction: shading.frame.direction(sin_theta, cos_theta, phi),
+            value: shading.eval(incoming),
+            pdf,
+            flags: self.bsdf_flags(hit),
+        })
+    }
+
+    fn denoise_albedo(&self, _hit: &HitRecord<'_>) -> LinearColor {
+        let scale = reflectance_scale(self.beta_n);
+        let color = |sigma: f64| (-sigma.sqrt() * scale).exp();
+        LinearColor::new(
+            color(self.sigma_a.red),
+            color(self.sigma_a.green),
+            color(self.sigma_a.blue),
+        )
+    }
+}
+
+/// Fiber-local frame: `x` runs along the fiber and the outgoing direction lies in the `xy` plane.
+#[derive(Clone, Copy, Debug)]
+struct HairFrame {
+    x: Vector,
+    y: Vector,
+    z: Vector,
+    /// Signed offset across the fiber in `[-1, 1]`, oriented for the azimuthal lobe formulas.
+    h: f64,
+}
+
+impl HairFrame {
+    fn new(hit: &HitRecord<'_>, outgoing: Vector) -> Option<Self> {
+        let normal = hit.shading_normal;
+        let x = hit
+            .tangent
+            .filter(|tangent| tangent.length_squared() > f64::EPSILON)
+            .unwrap_or_else(|| {
+                let helper = if normal.x().abs() > 0.9 {
+                    Vector::new(0.0, 1.0, 0.0)
+                } else {
+                    Vector::new(1.0, 0.0, 0.0)
+                };
+                normal.cross(helper)
+            })
+            .normalized();
+        let mut y = outgoing - x * outgoing.dot(x);
+        if y.length_squared() <= 1e-12 {
+            y = normal - x * normal.dot(x);
+        }
+        if y.length_squared() <= 1e-12 {
+            return None;
+        }
+        let y = y.normalized();
+        let z = x.cross(y);
+
+        // The azimuthal lobes expect a positive offset to sit at -gamma_o from the outgoing
+        // direction, which is the -z side of this frame.
+        let mut h = (2.0 * hit.v - 1.0).clamp(-1.0, 1.0);
+        if hit.bitangent.is_some_and(|side| side.dot(z) > 0.0) {
+            h = -h;
+        }
+        Some(Self { x, y, z, h })
+    }
+
+    /// Returns `(sin_theta, cos_theta, phi)` of a unit direction.
+    fn spherical(self, direction: Vector) -> (f64, f64, f64) {
+        let sin_theta = direction.dot(self.x).clamp(-1.0, 1.0);
+        (
+            sin_theta,
+            safe_sqrt(1.0 - sin_theta * sin_theta),
+            direction.dot(self.z).atan2(direction.dot(self.y)),
+        )
+    }
+
+    fn direction(self, sin_theta: f64, cos_theta: f64, phi: f64) -> Vector {
+        self.x * sin_theta + self.y * (cos_theta * phi.cos()) + self.z * (cos_theta * phi.sin())
+    }
+}
+
+/// Per-hit state shared by evaluation, sampling, and density queries.
+#[derive(Clone, Copy, Debug)]
+struct HairShading {
+    frame: HairFrame,
+    sin_theta_o: f64,
+    cos_theta_o: f64,
+    phi_o: f64,
+    gamma_o: f64,
+    gamma_t: f64,
+    attenuation: [LinearColor; P_MAX + 1],
+    lobe_pdf: [f64; P_MAX + 1],
+    variance: [f64; P_MAX + 1],
+    logistic_scale: f64,
+    sin_2k_alpha: [f64; 3],
+    cos_2k_alpha: [f64; 3],
+}
+
+impl HairShading {
+    /// Returns the outgoing longitudinal angle tilted by the cuticle scales for lobe `p`.
+    fn tilted_outgoing(&self, p: usize) -> (f64, f64) {
+        let (sin_o, cos_o) = (self.sin_theta_o, self.cos_theta_o);
+        let (sin_op, cos_op) = match p {
+            0 => (
+                sin_o * self.cos_2k_alpha[1] - cos_o * self.sin_2k_alpha[1],
+                cos_o * self.cos_2k_alpha[1] + sin_o * self.sin_2k_alpha[1],
+            ),
+            1 => (
+                sin_o * self.cos_2k_alpha[0] + cos_o * self.sin_2k_alpha[0],
+                cos_o * self.cos_2k_alpha[0] - sin_o * self.sin_2k_alpha[0],
+            ),
+            2 => (
+                sin_o * self.cos_2k_alpha[2] + cos_o * self.sin_2k_alpha[2],
+                cos_o * self.cos_2k_alpha[2] - sin_o * self.sin_2k_alpha[2],
+            ),
+            _ => (sin_o, cos_o),
+        };
+        (sin_op, cos_op.abs())
+    }
+
+    fn eval(&self, (sin_theta_i, cos_theta_i, phi_i): (f64, f64, f64)) -> LinearColor {
+        let phi = phi_i - self.phi_o;
+        let mut sum = LinearColor::default();
+        for p in 0..=P_MAX {
+            let (sin_op, cos_op) = self.tilted_outgoing(p);
+            let longitudinal =
+                longitudinal(cos_theta_i, cos_op, sin_theta_i, sin_op, self.variance[p]);
+            sum += self.attenuation[p] * (longitudinal * self.azimuthal(phi, p));
+        }
+        sum
+    }
+
+    fn pdf(&self, (sin_theta_i, cos_theta_i, phi_i): (f64, f64, f64)) -> f64 {
+        let phi = phi_i - self.phi_o;
+        (0..=P_MAX)
+            .map(|p| {
+                let (sin_op, cos_op) = self.tilted_outgoing(p);
+                longitudinal(cos_theta_i, cos_op, sin_theta_i, sin_op, self.variance[p])
+                    * self.lobe_pdf[p]
+                    * self.azimuthal(phi, p)
+            })
+            .sum()
+    }
+
+    fn sample(&self, u: [f64; 4]) -> (f64, f64, f64) {
+        let mut lobe_u = u[0];
+        let mut p = P_MAX;
+        for (lobe, pdf) in self.lobe_pdf.iter().enumerate().take(P_MAX) {
+            if lobe_u < *pdf {
+                p = lobe;
+                break;
+            }
+            lobe_u -= pdf;
+        }
+
+        let (sin_op, cos_op) = self.tilted_outgoing(p);
+        let variance = self.variance[p];
+        let u_m = u[1].max(1e-5);
+        let cos_theta = 1.0 + variance * (u_m + (1.0 - u_m) * (-2.0 / variance).exp()).ln();
+        let sin_theta = safe_sqrt(1.0 - cos_theta * cos_theta);
+        let cos_phi = (2.0 * PI * u[2]).cos();
+        let sin_theta_i = (-cos_theta * sin_op + sin_theta * cos_phi * cos_op).clamp(-1.0, 1.0);
+        let cos_theta_i = safe_sqrt(1.0 - sin_theta_i * sin_theta_i);
+
+        let dphi = if p < P_MAX {
+            self.lobe_phi(p) + sample_trimmed_logistic(u[3], self.logistic_scale)
+        } else {
+            2.0 * PI * u[3]
+        };
+        (sin_theta_i, cos_theta_i, self.phi_o + dphi)
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn lobe_phi(&self, p: usize) -> f64 {
+        let p = p as f64;
+        2.0 * p * self.gamma_t - 2.0 * self.gamma_o + p * PI
+    }
+
+    fn azimuthal(&self, phi: f64, p: usize) -> f64 {
+        if p == P_MAX {
+            return 1.0 / (2.0 * PI);
+        }
+        let mut dphi = phi - self.lobe_phi(p);
+        while dphi > PI {
+            dphi -= 2.0 * PI;
+        }
+        while dphi < -PI {
+            dphi += 2.0 * PI;
+        }
+        trimmed_logistic(dphi, self.logistic_scale)
+    }
+}
+
+/// Color-to-absorption scale fitted by Chiang et al. for a given azimuthal roughness.
+fn reflectance_scale(beta_n: f64) -> f64 {
+    5.969 - 0.215 * beta_n + 2.532 * beta_n.powi(2) - 10.73 * beta_n.powi(3)
+        + 5.574 * beta_n.powi(4)
+        + 0.245 * beta_n.powi(5)
+}
+
+/// Longitudinal scattering function `M_p` for a lobe with variance `v`.
+fn longitudinal(
+    cos_theta_i: f64,
+    cos_theta_o: f64,
+    sin_theta_i: f64,
+    sin_theta_o: f64,
+    v: f64,
+) -> f64 {
+    let a = cos_theta_i * cos_theta_o / v;
+    let b = sin_theta_i * sin_theta_o / v;
+    if v <= 0.1 {
+        (log_bessel_i0(a) - b - 1.0 / v + std::f64::consts::LN_2 + (1.0 / (2.0 * v)).ln()).exp()
+    } else {
+        (-b).exp() * bessel_i0(a) / ((1.0 / v).sinh() * 2.0 * v)
+    }
+}
+
+/// Modified Bessel function of the first kind, order zero.
+fn bessel_i0(x: f64) -> f64 {
+    let mut value = 0.0;
+    let mut x2i = 1.0;
+    let mut factorial = 1.0;
+    let mut four_i = 1.0;
+    for i in 0..10 {
+        if i > 1 {
+            factorial *= f64::from(i);
+        }
+        value += x2i / (four_i * factorial * factorial);
+        x2i *= x * x;
+        four_i *= 4.0;
+    }
+    value
+}
+
+fn log_bessel_i0(x: f64) -> f64 {
+    if x > 12.0 {
+        x + 0.5 * (-(2.0 * PI).ln() + (1.0 / x).ln() + 1.0 / (8.0 * x))
+    } else {
+        bessel_i0(x).ln()
+    }
+}
+
+fn logistic(x: f64, s: f64) -> f64 {
+    let x = x.abs();
+    let e = (-x / s).exp();
+    e / (s * (1.0 + e) * (1.0 + e))
+}
+
+fn logistic_cdf(x: f64, s: f64) -> f64 {
+    1.0 / (1.0 + (-x / s).exp())
+}
+
+/// Logistic distribution renormalized over `[-PI, PI]`.
+fn trimmed_logistic(x: f64, s: f64) -> f64 {
+    logistic(x, s) / (logistic_cdf(PI, s) - logistic_cdf(-PI, s))
+}
+
+fn sample_trimmed_logistic(u: f64, s: f64) -> f64 {
+    let k = logistic_cdf(PI, s) - logistic_cdf(-PI, s);
+    let x = -s * (1.0 / (u * k + logistic_cdf(-PI, s)) - 1.0).ln();
+    x.clamp(-PI, PI)
+}
+
+/// Unpolarized Fresnel reflectance entering a dielectric of index `eta` from air.
+fn fresnel_dielectric(cos_theta_i: f64, eta: f64) -> f64 {
+    let cos_theta_i = cos_theta_i.clamp(0.0, 1.0);
+    let sin_theta_t = safe_sqrt(1.0 - cos_theta_i * cos_theta_i) / eta;
+    if sin_theta_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_theta_t = safe_sqrt(1.0 - sin_theta_t * sin_theta_t);
+    let parallel = (eta * cos_theta_i - cos_theta_t) / (eta * cos_theta_i + cos_theta_t);
+    let perpendicular = (cos_theta_i - eta * cos_theta_t) / (cos_theta_i + eta * cos_theta_t);
+    0.5 * (parallel * parallel + perpendicular * perpendicular)
+}
+
+fn safe_sqrt(value: f64) -> f64 {
+    value.max(0.0).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gmath::vector::Point;
+    use crate::graphics::raytracing::SurfaceHit;
+
+    fn fiber_hit<'a>(material: &'a HairBsdf, ray: &Ray, v: f64) -> HitRecord<'a> {
+        let h = 2.0 * v - 1.0;
+        let normal = Vector::new(0.0, h, (1.0 - h * h).sqrt());
+        let surface = SurfaceHit::with_uv(ray, Point::new(0.0, h, 0.0), normal, 1.0, 0.5, v);
+        let mut hit = HitRecord::from_surface(surface, material);
+        hit.set_tangent_frame(Vector::new(1.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0));
+        hit
+    }
+
+    #[test]
+    fn non_absorbing_hair_samples_have_unit_weight() {
+        let bsdf = HairBsdf::new(LinearColor::default(), 0.3, 0.3);
+        let mut rng = SampleRng::new(11);
+        for (v, direction) in [
+            (0.5, Vector::new(0.0, 0.0, -1.0)),
+            (0.2, Vector::new(0.4, 0.1, -1.0)),
+            (0.9, Vector::new(-0.7, -0.3, -0.5)),
+        ] {
+            let ray = Ray::new(Point::new(0.0, 0.0, 5.0), direction);
+            let hit = fiber_hit(&bsdf, &ray, v);
+            for _ in 0..200 {
+                let sample = bsdf.sample_bsdf(&ray, &hit, &mut rng).expect("hair sample");
+                let weight = sample.weight();
+                assert!((weight.green - 1.0).abs() < 1e-3, "weight {weight:?}");
+                let scattered = Ray::new(hit.point, sample.direction);
+                let pdf = bsdf.bsdf_pdf(&ray, &hit, &scattered);
+                assert!((pdf - sample.pdf).abs() <= 1e-6 * sample.pdf.max(1.0));
+            }
+        }
+    }
+
+    #[test]
+    fn non_absorbing_hair_conserves_energy_in_a_white_furnace() {
+        let bsdf = HairBsdf::new(LinearColor::default(), 0.4, 0.4);
+        let mut rng = SampleRng::new(3);
+        let ray = Ray::new(Point::new(0.0, 0.0, 5.0), Vector::new(0.3, 0.0, -1.0));
+        let hit = fiber_hit(&bsdf, &ray, 0.35);
+        let samples = 40_000;
+        let mut sum = 0.0;
+        for _ in 0..samples {
+            let direction = rng.random_unit_vector();
+            let scattered = Ray::new(hit.point, direction);
+            sum += bsdf.eval_bsdf(&ray, &hit, &scattered).green * 4.0 * PI;
+        }
+        let estimate = sum / f64::from(samples);
+        assert!((estimate - 1.0).abs() < 0.05, "furnace estimate {estimate}");
+    }
+
+    #[test]
+    fn smooth_reflection_lobe_follows_the_fiber_surface_normal() {
+        let bsdf =
+            HairBsdf::new(LinearColor::new(50.0, 50.0, 50.0), 0.02, 0.02).with_scale_angle(0.0);
+        let mut rng = SampleRng::new(5);
+        let ray = Ray::new(Point::new(0.0, 0.0, 5.0), Vector::new(0.0, 0.0, -1.0));
+        let hit = fiber_hit(&bsdf, &ray, 0.75);
+        let mirror = {
+            let direction = *ray.direction();
+            direction - hit.normal * (2.0 * direction.dot(hit.normal))
+        };
+        let best = (0..64)
+            .filter_map(|_| bsdf.sample_bsdf(&ray, &hit, &mut rng))
+            .map(|sample| sample.direction.normalized().dot(mirror))
+            .fold(-1.0, f64::max);
+        assert!(best > 0.98, "closest reflected sample {best}");
+    }
+
+    #[test]
+    fn reflectance_and_melanin_constructors_order_colors() {
+        let red = HairBsdf::from_reflectance(LinearColor::new(0.8, 0.3, 0.1), 0.3, 0.3);
+        assert!(red.sigma_a().red < red.sigma_a().green);
+        assert!(red.sigma_a().green < red.sigma_a().blue);
+
+        let blond = HairBsdf::from_melanin(0.3, 0.0, 0.3, 0.3);
+        let black = HairBsdf::from_melanin(8.0, 0.0, 0.3, 0.3);
+        assert!(blond.sigma_a().blue < black.sigma_a().blue);
+    }
+}
@@ -1,8 +1,11 @@
 //! Hittable instance transforms.
 
-use super::{Aabb, HitRecord, Hittable, Interval, PdfContext, degrees_to_radians};
+use super::{
+    Aabb, HitRecord, Hittable, Interval, LinearColor, PdfContext, SurfaceSample, degrees_to_radians,
+};
 use crate::gmath::{
     matrix::Matrix,
+    motion::AnimatedTransform,
     random::SampleRng,
     ray::Ray,
     vector::{Point, Vector},
@@ -77,6 +80,16 @@ impl Hittable for Translate {
         self.bounds
     }
 
+    fn transmittance_with_rng(
+        &self,
+        ray: &Ray,
+        ray_t: Interval,
+        rng: &mut SampleRng,
+    ) -> LinearColor {
+        let offset_ray = Ray::with_time(*ray.origin() - self.offset, *ray.direction(), ray.time());
+        self.object.transmittance_with_rng(&offset_ray, ray_t, rng)
+    }
+
     fn pdf_value(&self, context: PdfContext, direction: Vector) -> f64 {
         self.object.pdf_value(
             PdfContext::new(context.origin - self.offset, context.time),
@@ -90,6 +103,18 @@ impl Hittable for Translate {
             rng,
         )
     }
+
+    fn sample_surface(&self, time: f64, rng: &mut SampleRng) -> Option<SurfaceSample> {
+        let sample = self.object.sample_surface(time, rng)?;
+        Some(SurfaceSample {
+            point: sample.point + self.offset,
+            ..sample
+        })
+    }
+
+    fn surface_pdf(&self, point: Point, time: f64) -> f64 {
+        self.object.surface_pdf(point - self.offset, time)
+    }
 }
 
 /// A Y-axis rotated instance of a hittable object.
@@ -170,6 +195,20 @@ impl Hittable for RotateY {
         self.bounds
     }
 
+    fn transmittance_with_rng(
+        &self,
+        ray: &Ray,
+        ray_t: Interval,
+        rng: &mut SampleRng,
+    ) -> LinearColor {
+        let rotated_ray = Ray::with_time(
+            rotate_y_point_inverse(*ray.origin(), self.sin_theta, self.cos_theta),
+            rotate_y_vector_inverse(*ray.direction(), self.sin_theta, self.cos_theta),
+            ray.time(),
+        );
+        self.object.transmittance_with_rng(&rotated_ray, ray_t, rng)
+    }
+
     fn pdf_value(&self, context: PdfContext, direction: Vector) -> f64 {
         self.object.pdf_value(
             PdfContext::new(
@@ -193,6 +232,22 @@ impl Hittable for RotateY {
             self.cos_theta,
         )
     }
+
+    fn sample_surface(&self, time: f64, rng: &mut SampleRng) -> Option<SurfaceSample> {
+        let sample = self.object.sample_surface(time, rng)?;
+        Some(SurfaceSample {
+            point: rotate_y_point(sample.point, self.sin_theta, self.cos_theta),
+            normal: rotate_y_vector(sample.normal, self.sin_theta, self.cos_theta),
+            pdf: sample.pdf,
+        })
+    }
+
+    fn surface_pdf(&self, point: Point, time: f64) -> f64 {
+        self.object.surface_pdf(
+            rotate_y_point_inverse(point, self.sin_theta, self.cos_theta),
+            time,
+        )
+    }
 }
 
 /// A generic matrix-transformed instance of a hittable object.
@@ -200,8 +255,8 @@ impl Hittable for RotateY {
 /// The transform maps object space into world space. Rays are transformed by the cached inverse
 /// before hitting the child object, then hit points and normals are transformed back to world
 /// space. Normals use the inverse-transpose transform, which keeps them correct for non-uniform
-/// scales as well as rotations and translations. Direction PDFs are forwarded only for rigid
-/// transforms because non-uniform scales do not preserve solid angle.
+/// scales as well as rotations and translations. Direction PDFs and surface samples are forwarded
+/// only for rigid transforms because non-uniform scales preserve neither solid angle nor area.
 pub struct MatrixInstance {
     object: Box<dyn Hittable>,
     transform: Matrix,
@@ -293,6 +348,20 @@ impl Hittable for MatrixInstance {
         self.bounds
     }
 
+    fn transmittance_with_rng(
+        &self,
+        ray: &Ray,
+        ray_t: Interval,
+        rng: &mut SampleRng,
+    ) -> LinearColor {
+        let object_ray = Ray::with_time(
+            transform_point(*ray.origin(), &self.inverse),
+            transform_vector(*ray.direction(), &self.inverse),
+            ray.time(),
+        );
+        self.object.transmittance_with_rng(&object_ray, ray_t, rng)
+    }
+
     fn pdf_value(&self, context: PdfContext, direction: Vector) -> f64 {
         if !self.preserves_solid_angle {
             return 0.0;
@@ -317,6 +386,208 @@ impl Hittable for MatrixInstance {
             &self.transform,
         )
     }
+
+    fn sample_surface(&self, time: f64, rng: &mut SampleRng) -> Option<SurfaceSample> {
+        if !self.preserves_solid_angle {
+            return None;
+        }
+
+        let sample = self.object.sample_surface(time, rng)?;
+        Some(SurfaceSample {
+            point: transform_point(sample.point, &self.transform),
+            normal: transform_vector(sample.normal, &self.normal_transform).normalized(),
+            pdf: sample.pdf,
+        })
+    }
+
+    fn surface_pdf(&self, point: Point, time: f64) -> f64 {
+        if !self.preserves_solid_angle {
+            return 0.0;
+        }
+
+        self.object
+            .surface_pdf(transform_point(point, &self.inverse), time)
+    }
+}
+
+/// A hittable object whose transform is keyframed over the camera shutter.
+///
+/// Keys are decomposed into translation, quaternion rotation, and scale and interpolated at each
+/// ray's time, so spinning and orbiting objects blur along curved paths instead of smearing
+/// between straight-line matrix blends. Bounds cover the object over the whole animation, which
+/// keeps BVH traversal correct for any shutter time. As with [`MatrixInstance`], direction PDFs
+/// and surface samples are forwarded only when every key is rigid.
+///
+/// Translations interpolate along straight lines, so an orbit should key a rotation about the
+/// orbit center around an offset child (for example a [`Translate`]) rather than key the orbiting
+/// position itself.
+pub struct MotionInstance {
+    object: Box<dyn Hittable>,
+    animation: AnimatedTransform,
+    preserves_solid_angle: bool,
+    bounds: Option<Aabb>,
+}
+
+impl fmt::Debug for MotionInstance {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("MotionInstance")
+            .field("animation", &self.animation)
+            .field("bounds", &self.bounds)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MotionInstance {
+    /// Creates an instance keyed by `(time, object-to-world matrix)` pairs.
+    ///
+    /// Returns `None` if there are no keys, two keys share a time, or a matrix cannot be
+    /// decomposed into translation, rotation, and scale.
+    #[must_use]
+    pub fn new<'m>(
+        object: impl Hittable + 'static,
+        keys: impl IntoIterator<Item = (f64, &'m Matrix)>,
+    ) -> Option<Self> {
+        Self::from_box(Box::new(object), keys)
+    }
+
+    /// Creates a keyed instance from a boxed hittable.
+    ///
+    /// Returns `None` under the same conditions as [`Self::new`].
+    #[must_use]
+    pub fn from_box<'m>(
+        object: Box<dyn Hittable>,
+        keys: impl IntoIterator<Item = (f64, &'m Matrix)>,
+    ) -> Option<Self> {
+        Some(Self::with_animation(
+            object,
+            AnimatedTransform::from_matrices(keys)?,
+        ))
+    }
+
+    /// Creates an instance that moves from `start` at time 0 to `end` at time 1.
+    ///
+    /// This matches the default camera shutter.
+    #[must_use]
+    pub fn between(object: impl Hittable + 'static, start: &Matrix, end: &Matrix) -> Option<Self> {
+        Self::new(object, [(0.0, start), (1.0, end)])
+    }
+
+    /// Creates an instance from an already decomposed animation.
+    #[must_use]
+    pub fn with_animation(object: Box<dyn Hittable>, animation: AnimatedTransform) -> Self {
+        let preserves_solid_angle = animation.is_rigid();
+        let bounds = object
+            .bounding_box()
+            .map(|bounds| animation.motion_bounds(bounds));
+        Self {
+            object,
+            animation,
+            preserves_solid_angle,
+            bounds,
+        }
+    }
+
+    /// Returns the keyed object-to-world animation.
+    #[must_use]
+    pub const fn animation(&self) -> &AnimatedTransform {
+        &self.animation
+    }
+}
+
+impl Hittable for MotionInstance {
+    fn hit_with_rng(
+        &self,
+        ray: &Ray,
+        ray_t: Interval,
+        rng: &mut SampleRng,
+    ) -> Option<HitRecord<'_>> {
+        let pose = self.animation.at(ray.time());
+        let object_ray = Ray::with_time(
+            pose.inverse_transform_point(*ray.origin()),
+            pose.inverse_transform_vector(*ray.direction()),
+            ray.time(),
+        );
+        let mut record = self.object.hit_with_rng(&object_ray, ray_t, rng)?;
+        let object_outward_normal = if record.front_face {
+            record.normal
+        } else {
+            -record.normal
+        };
+        record.point = pose.transform_point(record.point);
+        record.set_face_normal(
+            ray,
+            pose.transform_normal(object_outward_normal).normalized(),
+        );
+        Some(record)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bounds
+    }
+
+    fn transmittance_with_rng(
+        &self,
+        ray: &Ray,
+        ray_t: Interval,
+        rng: &mut SampleRng,
+    ) -> LinearColor {
+        let pose = self.animation.at(ray.time());
+        let object_ray = Ray::with_time(
+            pose.inverse_transform_point(*ray.origin()),
+            pose.inverse_transform_vector(*ray.direction()),
+            ray.time(),
+        );
+        self.object.transmittance_with_rng(&object_ray, ray_t, rng)
+    }
+
+    fn pdf_value(&self, context: PdfContext, direction: Vector) -> f64 {
+        if !self.preserves_solid_angle {
+            return 0.0;
+        }
+
+        let pose = self.animation.at(context.time);
+        self.object.pdf_value(
+            PdfContext::new(pose.inverse_transform_point(context.origin), context.time),
+            pose.inverse_transform_vector(direction),
+        )
+    }
+
+    fn random_direction(&self, context: PdfContext, rng: &mut SampleRng) -> Vector {
+        if !self.preserves_solid_angle {
+            return Vector::new(1.0, 0.0, 0.0);
+        }
+
+        let pose = self.animation.at(context.time);
+        pose.transform_vector(self.object.random_direction(
+            PdfContext::new(pose.inverse_transform_point(context.origin), context.time),
+            rng,
+        ))
+    }
+
+    fn sample_surface(&self, time: f64, rng: &mut SampleRng) -> Option<SurfaceSample> {
+        if !self.preserves_solid_angle {
+            return None;
+        }
+
+        let pose = self.animation.at(time);
+        let sample = self.object.sample_surface(time, rng)?;
+        Some(SurfaceSample {
+            point: pose.transform_point(sample.point),
+            normal: pose.transform_normal(sample.normal).normalized(),
+            pdf: sample.pdf,
+        })
+    }
+
+    fn surface_pdf(&self, point: Point, time: f64) -> f64 {
+        if !self.preserves_solid_angle {
+            return 0.0;
+        }
+
+        let pose = self.animation.at(time);
+        self.object
+            .surface_pdf(pose.inverse_transform_point(point), time)
+    }
 }
 
 fn transform_bounds(bounds: Aabb, transform: &Matrix) -> Aabb {
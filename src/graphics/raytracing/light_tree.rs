@@ -0,0 +1,677 @@
+//! Light tree for importance-sampling scenes with many emitters.
+//!
+//! [`LightTree`] groups emitters into a binary hierarchy whose nodes store total power, spatial
+//! bounds, and a cone containing every emitting direction. Sampling descends the tree by an
+//! importance estimate at the shading point, so nearby, bright, front-facing lights are chosen far
+//! more often than distant or back-facing ones while the selection probability remains exact for
+//! MIS.
+
+use super::{
+    Aabb, HitRecord, Hittable, Interval, PdfContext, Quad, RayGeometry, SampleRng, Sphere,
+    SurfaceSample, scene::GeometrySamplingTarget,
+};
+use crate::gmath::{
+    ray::Ray,
+    vector::{Point, Vector},
+};
+use std::{f64::consts::PI, fmt, sync::OnceLock};
+
+const MAX_TRAVERSAL_DEPTH: usize = 64;
+
+/// A power-weighted light hierarchy that importance-samples one emitter per shading point.
+///
+/// Pass a tree as the `lights` argument of
+/// [`PathTracer::render_with_lights`](crate::graphics::raytracing::PathTracer::render_with_lights).
+/// [`RayScene::emissive_targets`](crate::graphics::raytracing::RayScene::emissive_targets) builds
+/// one automatically from emissive primitives. Flat lights added with [`Self::add_quad`] or
+/// [`Self::add_one_sided_target`] only emit toward their front face, so shading points behind them
+/// never select them.
+///
+/// The hierarchy is built lazily on first use and rebuilt after the tree is modified.
+#[derive(Default)]
+pub struct LightTree {
+    lights: Vec<TreeLight>,
+    total_power: f64,
+    nodes: OnceLock<Vec<LightNode>>,
+}
+
+struct TreeLight {
+    object: Box<dyn Hittable>,
+    bounds: LightBounds,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct EmissionCone {
+    axis: Vector,
+    cos_theta: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct LightBounds {
+    bounds: Aabb,
+    power: f64,
+    cone: EmissionCone,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum LightNodeKind {
+    Leaf(usize),
+    Interior { second_child: usize },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct LightNode {
+    bounds: LightBounds,
+    kind: LightNodeKind,
+}
+
+impl fmt::Debug for LightTree {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("LightTree")
+            .field("len", &self.lights.len())
+            .field("total_power", &self.total_power)
+            .finish_non_exhaustive()
+    }
+}
+
+impl LightTree {
+    /// Creates an empty light tree.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty light tree with reserved capacity.
+    #[must_use]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            lights: Vec::with_capacity(capacity),
+            total_power: 0.0,
+            nodes: OnceLock::new(),
+        }
+    }
+
+    /// Adds a spherical light that emits `power` in every direction.
+    ///
+    /// # Panics
+    /// Panics if `power` is not finite and positive.
+    pub fn add_sphere(&mut self, center: Point, radius: f64, power: f64) {
+        self.add_target(Sphere::new(center, radius), power);
+    }
+
+    /// Adds a one-sided quad light that emits `power` toward the side `u × v` faces.
+    ///
+    /// # Panics
+    /// Panics if `power` is not finite and positive.
+    pub fn add_quad(&mut self, corner: Point, u: Vector, v: Vector, power: f64) {
+        self.add_one_sided_target(Quad::new(corner, u, v), power, u.cross(v));
+    }
+
+    /// Adds a custom light that may emit `power` in every direction.
+    ///
+    /// # Panics
+    /// Panics if `object` has no bounding box or if `power` is not finite and positive.
+    pub fn add_target(&mut self, object: impl Hittable + 'static, power: f64) {
+        self.push(Box::new(object), power, EmissionCone::everywhere());
+    }
+
+    /// Adds a custom flat light that only emits `power` into the hemisphere around `normal`.
+    ///
+    /// # Panics
+    /// Panics if `object` has no bounding box, if `normal` has zero length, or if `power` is not
+    /// finite and positive.
+    pub fn add_one_sided_target(
+        &mut self,
+        object: impl Hittable + 'static,
+        power: f64,
+        normal: Vector,
+    ) {
+        assert!(
+            normal.length_squared() > 0.0 && normal.length_squared().is_finite(),
+            "light tree normal must be finite and non-zero"
+        );
+        self.push(Box::new(object), power, EmissionCone::around(normal));
+    }
+
+    pub(crate) fn add_geometry(&mut self, geometry: RayGeometry, power: f64) {
+        let cone = geometry
+            .front_normal()
+            .map_or_else(EmissionCone::everywhere, EmissionCone::around);
+        self.push(Box::new(GeometrySamplingTarget::new(geometry)), power, cone);
+    }
+
+    fn push(&mut self, object: Box<dyn Hittable>, power: f64, cone: EmissionCone) {
+        assert!(
+            power.is_finite() && power > 0.0,
+            "light tree power must be finite and positive"
+        );
+        let total_power = self.total_power + power;
+        assert!(
+            total_power.is_finite(),
+            "light tree total power must remain finite"
+        );
+        let bounds = object
+            .bounding_box()
+            .expect("light tree targets must have a bounding box");
+        self.total_power = total_power;
+        self.lights.push(TreeLight {
+            object,
+            bounds: LightBounds {
+                bounds,
+                power,
+                cone,
+            },
+        });
+        self.nodes = OnceLock::new();
+    }
+
+    /// Removes all lights.
+    pub fn clear(&mut self) {
+        self.lights.clear();
+        self.total_power = 0.0;
+        self.nodes = OnceLock::new();
+    }
+
+    /// Returns the number of lights.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.lights.len()
+    }
+
+    /// Returns true when there are no lights.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+
+    /// Returns the sum of all light powers.
+    #[must_use]
+    pub const fn total_power(&self) -> f64 {
+        self.total_power
+    }
+
+    fn nodes(&self) -> &[LightNode] {
+        self.nodes.get_or_init(|| build_nodes(&self.lights))
+    }
+
+    fn choose_light(&self, point: Point, rng: &mut SampleRng) -> Option<usize> {
+        let nodes = self.nodes();
+        let root = nodes.first()?;
+        if root.bounds.importance(point) <= 0.0 {
+            return None;
+        }
+
+        let mut index = 0;
+        loop {
+            match nodes[index].kind {
+                LightNodeKind::Leaf(light) => return Some(light),
+                LightNodeKind::Interior { second_child } => {
+                    let first = nodes[index + 1].bounds.importance(point);
+                    let second = nodes[second_child].bounds.importance(point);
+                    let total = first + second;
+                    if total <= 0.0 {
+                        return None;
+                    }
+                    index = if rng.random_double() * total < first {
+                        index + 1
+                    } else {
+                        second_child
+                    };
+                }
+            }
+        }
+    }
+
+    fn choose_light_by_power(&self, rng: &mut SampleRng) -> Option<(usize, f64)> {
+        let nodes = self.nodes();
+        nodes.first()?;
+
+        let mut index = 0;
+        let mut probability = 1.0;
+        loop {
+            match nodes[index].kind {
+                LightNodeKind::Leaf(light) => return Some((light, probability)),
+                LightNodeKind::Interior { second_child } => {
+                    let first = nodes[index + 1].bounds.power;
+                    let second = nodes[second_child].bounds.power;
+                    let first_probability = first / (first + second);
+                    if rng.random_double() < first_probability {
+                        probability *= first_probability;
+                        index += 1;
+                    } else {
+                        probability *= 1.0 - first_probability;
+                        index = second_child;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Hittable for LightTree {
+    fn hit_with_rng(
+        &self,
+        _ray: &Ray,
+        _ray_t: Interval,
+        _rng: &mut SampleRng,
+    ) -> Option<HitRecord<'_>> {
+        None
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.nodes().first().map(|root| root.bounds.bounds)
+    }
+
+    fn pdf_value(&self, context: PdfContext, direction: Vector) -> f64 {
+        let nodes = self.nodes();
+        let Some(root) = nodes.first() else {
+            return 0.0;
+        };
+        if root.bounds.importance(context.origin) <= 0.0 {
+            return 0.0;
+        }
+
+        let ray = Ray::with_time(context.origin, direction, context.time);
+        let mut stack = [(0_usize, 0.0_f64); MAX_TRAVERSAL_DEPTH + 1];
+        stack[0] = (0, 1.0);
+        let mut stack_len = 1;
+        let mut pdf = 0.0;
+        while stack_len > 0 {
+            stack_len -= 1;
+            let (index, probability) = stack[stack_len];
+            match nodes[index].kind {
+                LightNodeKind::Leaf(light) => {
+                    pdf += probability * self.lights[light].object.pdf_value(context, direction);
+                }
+                LightNodeKind::Interior { second_child } => {
+                    let first = nodes[index + 1].bounds.importance(context.origin);
+                    let second = nodes[second_child].bounds.importance(context.origin);
+                    let total = first + second;
+                    if total <= 0.0 {
+                        continue;
+                    }
+                    for (child, importance) in [(index + 1, first), (second_child, second)] {
+                        if importance > 0.0
+                            && nodes[child].bounds.bounds.hit(&ray, 0.0, f64::INFINITY)
+                        {
+                            stack[stack_len] = (child, probability * importance / total);
+                            stack_len += 1;
+                        }
+                    }
+                }
+            }
+        }
+        pdf
+    }
+
+    fn random_direction(&self, context: PdfContext, rng: &mut SampleRng) -> Vector {
+        match self.choose_light(context.origin, rng) {
+            Some(light) => self.lights[light].object.random_direction(context, rng),
+            None => Vector::new(1.0, 0.0, 0.0),
+        }
+    }
+
+    fn sample_surface(&self, time: f64, rng: &mut SampleRng) -> Option<SurfaceSample> {
+        let (light, probability) = self.choose_light_by_power(rng)?;
+        let mut sample = self.lights[light].object.sample_surface(time, rng)?;
+        sample.pdf *= probability;
+        Some(sample)
+    }
+
+    fn surface_pdf(&self, point: Point, time: f64) -> f64 {
+        let nodes = self.nodes();
+        if nodes.is_empty() {
+            return 0.0;
+        }
+
+        let mut stack = [(0_usize, 0.0_f64); MAX_TRAVERSAL_DEPTH + 1];
+        stack[0] = (0, 1.0);
+        let mut stack_len = 1;
+        let mut pdf = 0.0;
+        while stack_len > 0 {
+            stack_len -= 1;
+            let (index, probability) = stack[stack_len];
+            match nodes[index].kind {
+                LightNodeKind::Leaf(light) => {
+                    pdf += probability * self.lights[light].object.surface_pdf(point, time);
+                }
+                LightNodeKind::Interior { second_child } => {
+                    let first = nodes[index + 1].bounds.power;
+                    let second = nodes[second_child].bounds.power;
+                    let total = first + second;
+                    for (child, power) in [(index + 1, first), (second_child, second)] {
+                        if bounds_contain(nodes[child].bounds.bounds, point) {
+                            stack[stack_len] = (child, probability * power / total);
+                            stack_len += 1;
+                        }
+                    }
+                }
+            }
+        }
+        pdf
+    }
+}
+
+impl EmissionCone {
+    fn everywhere() -> Self {
+        Self {
+            axis: Vector::new(0.0, 0.0, 1.0),
+            cos_theta: -1.0,
+        }
+    }
+
+    fn around(normal: Vector) -> Self {
+        Self {
+            axis: normal.normalized(),
+            cos_theta: 1.0,
+        }
+    }
+
+    fn theta(self) -> f64 {
+        self.cos_theta.clamp(-1.0, 1.0).acos()
+    }
+
+    fn union(self, other: Self) -> Self {
+        let theta_a = self.theta();
+        let theta_b = other.theta();
+        let theta_d = self.axis.dot(other.axis).clamp(-1.0, 1.0).acos();
+        if (theta_d + theta_b).min(PI) <= theta_a {
+            return self;
+        }
+        if (theta_d + theta_a).min(PI) <= theta_b {
+            return other;
+        }
+
+        let theta = 0.5 * (theta_a + theta_d + theta_b);
+        if theta >= PI {
+            return Self::everywhere();
+        }
+
+        let rotation = theta - theta_a;
+        let rotation_axis = self.axis.cross(other.axis);
+        if rotation_axis.length_squared() <= f64::EPSILON {
+            return Self::everywhere();
+        }
+        Self {
+            axis: rotate(self.axis, rotation_axis.normalized(), rotation).normalized(),
+            cos_theta: theta.cos(),
+        }
+    }
+}
+
+impl LightBounds {
+    fn union(self, other: Self) -> Self {
+        Self {
+            bounds: self.bounds.union(other.bounds),
+            power: self.power + other.power,
+            cone: self.cone.union(other.cone),
+        }
+    }
+
+    /// Conservatively estimates the contribution of every light in these bounds at `point`.
+    fn importance(self, point: Point) -> f64 {
+        let center = self.bounds.centroid();
+        let extent = Point::new(self.bounds.max.0, self.bounds.max.1, self.bounds.max.2) - center;
+        let radius_squared = extent.length_squared();
+        let offset = point - center;
+        let distance_squared = offset.length_squared();
+
+        let cos_theta_w = if distance_squared > 0.0 {
+            self.cone.axis.dot(offset) / distance_squared.sqrt()
+        } else {
+            1.0
+        };
+        let theta_w = cos_theta_w.clamp(-1.0, 1.0).acos();
+        let theta_b = if distance_squared <= radius_squared {
+            PI
+        } else {
+            (radius_squared / distance_squared).sqrt().asin()
+        };
+        let theta_p = (theta_w - self.cone.theta() - theta_b).max(0.0);
+        if theta_p >= 0.5 * PI {
+            return 0.0;
+        }
+
+        self.power * theta_p.cos() / distance_squared.max(radius_squared)
+    }
+}
+
+fn rotate(vector: Vector, axis: Vector, angle: f64) -> Vector {
+    let (sin, cos) = angle.sin_cos();
+    vector * cos + axis.cross(vector) * sin + axis * (axis.dot(vector) * (1.0 - cos))
+}
+
+fn point_axis(point: Point, axis: usize) -> f64 {
+    match axis {
+        0 => point.x(),
+        1 => point.y(),
+        _ => point.z(),
+    }
+}
+
+fn bounds_contain(bounds: Aabb, point: Point) -> bool {
+    let extent = Point::new(bounds.max.0, bounds.max.1, bounds.max.2)
+        - Point::new(bounds.min.0, bounds.min.1, bounds.min.2);
+    let tolerance = 1.0e-6 * (1.0 + extent.length());
+    (0..3).all(|axis| {
+        let value = point_axis(point, axis);
+        value >= bounds.axis_min(axis) - tolerance && value <= bounds.axis_max(axis) + tolerance
+    })
+}
+
+fn build_nodes(lights: &[TreeLight]) -> Vec<LightNode> {
+    let mut nodes = Vec::with_capacity((2 * lights.len()).saturating_sub(1));
+    if !lights.is_empty() {
+        let mut indices: Vec<usize> = (0..lights.len()).collect();
+        build_range(lights, &mut indices, &mut nodes);
+    }
+    nodes
+}
+
+fn build_range(
+    lights: &[TreeLight],
+    indices: &mut [usize],
+    nodes: &mut Vec<LightNode>,
+) -> LightBounds {
+    if let [light] = indices {
+        let bounds = lights[*light].bounds;
+        nodes.push(LightNode {
+            bounds,
+            kind: LightNodeKind::Leaf(*light),
+        });
+        return bounds;
+    }
+
+    let first_centroid = lights[indices[0]].bounds.bounds.centroid();
+    let centroid_bounds = indices.iter().fold(
+        Aabb::from_points(first_centroid, first_centroid),
+        |bounds, &light| bounds.union_point(lights[light].bounds.bounds.centroid()),
+    );
+    let axis = centroid_bounds.largest_axis();
+    indices.sort_unstable_by(|&a, &b| {
+        let a = point_axis(lights[a].bounds.bounds.centroid(), axis);
+        let b = point_axis(lights[b].bounds.bounds.centroid(), axis);
+        a.total_cmp(&b)
+    });
+
+    let node_index = nodes.len();
+    nodes.push(LightNode {
+        bounds: lights[indices[0]].bounds,
+        kind: LightNodeKind::Leaf(indices[0]),
+    });
+    let (first, second) = indices.split_at_mut(indices.len() / 2);
+    let first_bounds = build_range(lights, first, nodes);
+    let second_child = nodes.len();
+    let second_bounds = build_range(lights, second, nodes);
+    let bounds = first_bounds.union(second_bounds);
+    nodes[node_index] = LightNode {
+        bounds,
+        kind: LightNodeKind::Interior { second_child },
+    };
+    bounds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(origin: Point) -> PdfContext {
+        PdfContext::new(origin, 0.0)
+    }
+
+    #[test]
+    fn light_tree_prefers_near_and_bright_lights() {
+        let mut tree = LightTree::new();
+        tree.add_sphere(Point::new(-1.0, 0.0, 0.0), 0.1, 1.0);
+        tree.add_sphere(Point::new(20.0, 0.0, 0.0), 0.1, 1.0);
+        tree.add_sphere(Point::new(1.0, 0.0, 0.0), 0.1, 50.0);
+        tree.add_sphere(Point::new(22.0, 0.0, 0.0), 0.1, 1.0);
+        let mut rng = SampleRng::new(7);
+        let mut counts = [0_usize; 4];
+        for _ in 0..4000 {
+            counts[tree
+                .choose_light(Point::default(), &mut rng)
+                .expect("light")] += 1;
+        }
+
+        assert!(counts[2] > counts[0] * 10);
+        assert!(counts[0] > (counts[1] + counts[3]) * 10);
+    }
+
+    #[test]
+    fn light_tree_ignores_lights_facing_away() {
+        let mut tree = LightTree::new();
+        for height in [2.0, -2.0] {
+            tree.add_quad(
+                Point::new(-0.5, height, -0.5),
+                Vector::new(1.0, 0.0, 0.0),
+                Vector::new(0.0, 0.0, 1.0),
+                10.0,
+            );
+        }
+        let origin = Point::default();
+        let mut rng = SampleRng::new(3);
+
+        for _ in 0..256 {
+            let direction = tree.random_direction(context(origin), &mut rng);
+            assert!(direction.y() > 0.0);
+        }
+        assert!(
+            tree.pdf_value(context(origin), Vector::new(0.0, -1.0, 0.0))
+                .abs()
+                < f64::EPSILON
+        );
+        assert!(tree.pdf_value(context(origin), Vector::new(0.0, 1.0, 0.0)) > 0.0);
+    }
+
+    #[test]
+    fn light_tree_direction_pdf_integrates_to_one() {
+        let mut tree = LightTree::new();
+        for index in 0..12 {
+            let angle = f64::from(index) * 0.5;
+            let center = Point::new(
+                4.0 * angle.cos(),
+                0.5 * f64::from(index % 3),
+                4.0 * angle.sin(),
+            );
+            tree.add_sphere(
+                center,
+                0.8 + 0.1 * f64::from(index % 4),
+                1.0 + f64::from(index),
+            );
+        }
+        let origin = Point::new(0.3, 0.2, -0.1);
+        let mut rng = SampleRng::new(11);
+        let samples = 200_000;
+        let mut integral = 0.0;
+        for _ in 0..samples {
+            let direction = random_unit_vector(&mut rng);
+            integral += tree.pdf_value(context(origin), direction) * 4.0 * PI;
+        }
+        integral /= f64::from(samples);
+
+        assert!((integral - 1.0).abs() < 0.03, "integral {integral}");
+    }
+
+    #[test]
+    fn light_tree_direction_pdf_matches_sampling_frequency() {
+        let mut tree = LightTree::new();
+        tree.add_sphere(Point::new(3.0, 0.0, 0.0), 0.5, 1.0);
+        tree.add_sphere(Point::new(-6.0, 0.0, 0.0), 0.5, 4.0);
+        tree.add_sphere(Point::new(0.0, 5.0, 0.0), 0.5, 2.0);
+        let origin = Point::default();
+        let mut rng = SampleRng::new(5);
+        let samples = 20_000;
+        let mut hits = 0;
+        for _ in 0..samples {
+            let direction = tree.random_direction(context(origin), &mut rng);
+            if direction.x() > 0.0 && direction.y().abs() < 0.5 {
+                hits += 1;
+            }
+        }
+        let observed = f64::from(hits) / f64::from(samples);
+
+        let mut probe = SampleRng::new(13);
+        let mut expected = 0.0;
+        let probes = 200_000;
+        for _ in 0..probes {
+            let direction = random_unit_vector(&mut probe);
+            if direction.x() > 0.0 && direction.y().abs() < 0.5 {
+                expected += tree.pdf_value(context(origin), direction) * 4.0 * PI;
+            }
+        }
+        expected /= f64::from(probes);
+
+        assert!(
+            (observed - expected).abs() < 0.03,
+            "{observed} vs {expected}"
+        );
+    }
+
+    #[test]
+    fn light_tree_surface_pdf_matches_power_selection() {
+        let mut tree = LightTree::new();
+        tree.add_sphere(Point::new(0.0, 0.0, 0.0), 1.0, 1.0);
+        tree.add_sphere(Point::new(5.0, 0.0, 0.0), 1.0, 3.0);
+        tree.add_quad(
+            Point::new(-10.0, 3.0, -1.0),
+            Vector::new(2.0, 0.0, 0.0),
+            Vector::new(0.0, 0.0, 2.0),
+            4.0,
+        );
+        let mut rng = SampleRng::new(17);
+
+        for _ in 0..64 {
+            let sample = tree.sample_surface(0.0, &mut rng).expect("surface sample");
+            let pdf = tree.surface_pdf(sample.point, 0.0);
+            assert!((pdf - sample.pdf).abs() < 1.0e-9 * sample.pdf.max(1.0));
+        }
+        let expected = 3.0 / 8.0 / (4.0 * PI);
+        assert!((tree.surface_pdf(Point::new(6.0, 0.0, 0.0), 0.0) - expected).abs() < 1.0e-12);
+    }
+
+    #[test]
+    fn light_tree_modification_rebuilds_hierarchy() {
+        let mut tree = LightTree::new();
+        tree.add_sphere(Point::new(0.0, 0.0, -3.0), 0.5, 1.0);
+        let origin = context(Point::default());
+        let toward_new = Vector::new(0.0, 0.0, 1.0);
+        assert!(tree.pdf_value(origin, toward_new).abs() < f64::EPSILON);
+
+        tree.add_sphere(Point::new(0.0, 0.0, 3.0), 0.5, 1.0);
+        assert!(tree.pdf_value(origin, toward_new) > 0.0);
+        assert!((tree.total_power() - 2.0).abs() < 1.0e-12);
+
+        tree.clear();
+        assert!(tree.is_empty());
+        assert!(tree.pdf_value(origin, toward_new).abs() < f64::EPSILON);
+    }
+
+    fn random_unit_vector(rng: &mut SampleRng) -> Vector {
+        let z = rng.random_range(-1.0, 1.0);
+        let phi = rng.random_range(0.0, 2.0 * PI);
+        let radius = (1.0 - z * z).sqrt();
+        Vector::new(radius * phi.cos(), radius * phi.sin(), z)
+    }
+}
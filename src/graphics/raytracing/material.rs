@@ -5,6 +5,7 @@ use super::{
     pdf::{CosinePdf, GgxReflectionPdf, HenyeyGreensteinPdf, Pdf},
     rgb_to_linear_color,
     texture::{CheckerTexture, NoiseTexture, NormalMapRef, SolidColor, TextureRef},
+    volume::MediumCoefficients,
 };
 use crate::{
     gmath::{
@@ -14,12 +15,16 @@ use crate::{
     },
     graphics::{
         colors::Rgb,
-        lighting::{PhongMaterial, ReflectionConstants, RefractiveIndex},
+        lighting::{PhongMaterial, ReflectionConstants, RefractiveIndex, SpotCone},
         material::SurfaceMaterial,
         texture::{SurfaceTexture, TextureSample},
     },
 };
-use std::{fmt, sync::Arc};
+use std::{
+    fmt,
+    ops::{BitOr, BitOrAssign},
+    sync::Arc,
+};
 
 #[cfg(feature = "spectral")]
 use super::spectrum::{
@@ -46,6 +51,120 @@ pub enum ScatterRecord {
     },
 }
 
+/// Set of scattering lobes a material can produce, combined with `|`.
+///
+/// Renderers use these flags to decide which estimators apply at a hit: materials with a
+/// [`Self::DIFFUSE`] or [`Self::GLOSSY`] lobe can be evaluated toward explicit light samples and
+/// weighted with multiple importance sampling, while [`Self::DELTA`] lobes are only reachable by
+/// sampling.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BsdfFlags(u8);
+
+impl BsdfFlags {
+    /// No lobes. This is also reported by materials that only implement [`Material::scatter`].
+    pub const EMPTY: Self = Self(0);
+    /// Scattering back to the side the ray arrived from.
+    pub const REFLECTION: Self = Self(0b00_0001);
+    /// Scattering through the surface, or onward through a medium.
+    pub const TRANSMISSION: Self = Self(0b00_0010);
+    /// Broad lobe such as Lambertian reflection or an isotropic phase function.
+    pub const DIFFUSE: Self = Self(0b00_0100);
+    /// Concentrated lobe with a finite density, such as GGX reflection.
+    pub const GLOSSY: Self = Self(0b00_1000);
+    /// Singular lobe such as a mirror or refraction, including implicitly sampled fuzz.
+    pub const DELTA: Self = Self(0b01_0000);
+    /// Phase-function scattering inside a participating medium, with no surface cosine.
+    ///
+    /// Together with [`Self::DELTA`], marks an index-matched medium boundary that rays cross
+    /// without changing direction.
+    pub const MEDIUM: Self = Self(0b10_0000);
+
+    /// Returns true when every lobe in `other` is also set in `self`.
+    #[must_use]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns true when no lobe is set.
+    #[must_use]
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns true when the flags describe a delta lobe.
+    #[must_use]
+    pub const fn is_delta(self) -> bool {
+        self.contains(Self::DELTA)
+    }
+
+    /// Returns true when a diffuse or glossy lobe can be evaluated for arbitrary directions.
+    #[must_use]
+    pub const fn has_non_delta_lobe(self) -> bool {
+        self.0 & (Self::DIFFUSE.0 | Self::GLOSSY.0) != 0
+    }
+
+    /// Returns true for an index-matched medium boundary.
+    ///
+    /// Shadow rays step over these boundaries and let the medium attenuate them, so path tracers
+    /// treat the crossing as part of the segment rather than as a new scattering vertex.
+    #[must_use]
+    pub const fn is_index_matched(self) -> bool {
+        self.contains(Self::DELTA) && self.contains(Self::MEDIUM)
+    }
+}
+
+impl BitOr for BsdfFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for BsdfFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// One direction drawn from a material's scattering distribution.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BsdfSample {
+    /// World-space scattered direction, not necessarily unit length.
+    pub direction: Vector,
+    /// BSDF times the incident cosine along `direction`, or albedo times the phase function for
+    /// volumes. Delta samples store the full path weight here instead.
+    pub value: LinearColor,
+    /// Solid-angle density of `direction`. Delta samples store the lobe selection probability.
+    pub pdf: f64,
+    /// Lobe that produced the sample.
+    pub flags: BsdfFlags,
+}
+
+impl BsdfSample {
+    /// Returns true when the sample came from a delta lobe.
+    #[must_use]
+    pub const fn is_delta(&self) -> bool {
+        self.flags.is_delta()
+    }
+
+    /// Returns the throughput multiplier `value / pdf`, or black for an invalid density.
+    #[must_use]
+    pub fn weight(&self) -> LinearColor {
+        if !self.pdf.is_finite() || self.pdf <= 0.0 {
+            return LinearColor::default();
+        }
+        self.value * (1.0 / self.pdf)
+    }
+
+    fn specular_record(self, ray_in: &Ray, hit: &HitRecord<'_>) -> ScatterRecord {
+        ScatterRecord::Specular {
+            ray: Ray::with_time(hit.point, self.direction, ray_in.time()),
+            attenuation: self.weight(),
+        }
+    }
+}
+
 /// A surface material that can scatter rays.
 pub trait Material: Send + Sync {
     /// Returns emitted light for this material at a surface point.
@@ -90,6 +209,61 @@ pub trait Material: Send + Sync {
         0.0
     }
 
+    /// Returns the lobes this material can scatter into at `hit`.
+    ///
+    /// The default is [`BsdfFlags::EMPTY`]: [`Self::eval_bsdf`] and [`Self::bsdf_pdf`] are not
+    /// available, so the renderer reaches the material only through [`Self::sample_bsdf`] and
+    /// skips explicit light sampling at its hits.
+    fn bsdf_flags(&self, _hit: &HitRecord<'_>) -> BsdfFlags {
+        BsdfFlags::EMPTY
+    }
+
+    /// Evaluates the BSDF times the incident cosine for light scattered along `scattered`.
+    ///
+    /// Volume phase functions return albedo times the phase function. Delta lobes are black.
+    fn eval_bsdf(&self, _ray_in: &Ray, _hit: &HitRecord<'_>, _scattered: &Ray) -> LinearColor {
+        LinearColor::default()
+    }
+
+    /// Returns the solid-angle density with which [`Self::sample_bsdf`] produces `scattered`.
+    fn bsdf_pdf(&self, _ray_in: &Ray, _hit: &HitRecord<'_>, _scattered: &Ray) -> f64 {
+        0.0
+    }
+
+    /// Samples a scattered direction with its BSDF value and density.
+    ///
+    /// The default adapts [`Self::scatter`]: specular records become delta samples, and PDF-sampled
+    /// records draw a direction from their [`MaterialPdf`] weighted by [`Self::scattering_pdf`].
+    fn sample_bsdf(
+        &self,
+        ray_in: &Ray,
+        hit: &HitRecord<'_>,
+        rng: &mut SampleRng,
+    ) -> Option<BsdfSample> {
+        match self.scatter(ray_in, hit, rng)? {
+            ScatterRecord::Specular { ray, attenuation } => Some(BsdfSample {
+                direction: *ray.direction(),
+                value: attenuation,
+                pdf: 1.0,
+                flags: BsdfFlags::DELTA,
+            }),
+            ScatterRecord::Scattering { attenuation, pdf } => {
+                let direction = pdf.generate(rng);
+                let pdf_value = pdf.value(direction);
+                if !pdf_value.is_finite() || pdf_value <= 0.0 {
+                    return None;
+                }
+                let scattered = Ray::with_time(hit.point, direction, ray_in.time());
+                Some(BsdfSample {
+                    direction,
+                    value: attenuation * self.scattering_pdf(ray_in, hit, &scattered),
+                    pdf: pdf_value,
+                    flags: BsdfFlags::EMPTY,
+                })
+            }
+        }
+    }
+
     /// Returns a first-hit albedo suitable for denoising auxiliary output.
     fn denoise_albedo(&self, _hit: &HitRecord<'_>) -> LinearColor {
         LinearColor::new(0.5, 0.5, 0.5)
@@ -531,6 +705,30 @@ impl Material for Lambertian {
         }
     }
 
+    fn bsdf_flags(&self, _hit: &HitRecord<'_>) -> BsdfFlags {
+        BsdfFlags::DIFFUSE | BsdfFlags::REFLECTION
+    }
+
+    fn eval_bsdf(&self, ray_in: &Ray, hit: &HitRecord<'_>, scattered: &Ray) -> LinearColor {
+        self.color
+            .sample(TextureSample::new(hit.u, hit.v, hit.point))
+            * self.scattering_pdf(ray_in, hit, scattered)
+    }
+
+    fn bsdf_pdf(&self, ray_in: &Ray, hit: &HitRecord<'_>, scattered: &Ray) -> f64 {
+        self.scattering_pdf(ray_in, hit, scattered)
+    }
+
+    fn sample_bsdf(
+        &self,
+        ray_in: &Ray,
+        hit: &HitRecord<'_>,
+        rng: &mut SampleRng,
+    ) -> Option<BsdfSample> {
+        let pdf = MaterialPdf::Cosine(CosinePdf::new(hit.shading_normal)?);
+        sample_material_pdf(self, ray_in, hit, pdf, self.bsdf_flags(hit), rng)
+    }
+
     fn denoise_albedo(&self, hit: &HitRecord<'_>) -> LinearColor {
         self.color
             .sample(TextureSample::new(hit.u, hit.v, hit.point))
@@ -787,6 +985,18 @@ impl LayeredDiffuseGgx {
         (self.diffuse.sample(sample), self.specular.sample(sample))
     }
 
+    fn material_pdf(&self, ray_in: &Ray, hit: &HitRecord<'_>) -> Option<MaterialPdf> {
+        let outgoing = -ray_in.direction().normalized();
+        let diffuse = CosinePdf::new(hit.shading_normal)?;
+        let specular = GgxReflectionPdf::new(hit.shading_normal, outgoing, self.roughness)?;
+        let (diffuse_color, specular_color) = self.sampled_colors(hit);
+        Some(MaterialPdf::DiffuseGgx {
+            diffuse,
+            specular,
+            specular_weight: specular_sampling_weight(diffuse_color, specular_color),
+        })
+    }
+
     #[cfg(feature = "spectral")]
     fn conductor_constants_at(
         &self,
@@ -805,19 +1015,10 @@ impl Material for LayeredDiffuseGgx {
         hit: &HitRecord<'_>,
         _rng: &mut SampleRng,
     ) -> Option<ScatterRecord> {
-        let outgoing = -ray_in.direction().normalized();
-        let diffuse_pdf = CosinePdf::new(hit.shading_normal)?;
-        let specular_pdf = GgxReflectionPdf::new(hit.shading_normal, outgoing, self.roughness)?;
         let (diffuse_color, specular_color) = self.sampled_colors(hit);
-        let specular_weight = specular_sampling_weight(diffuse_color, specular_color);
-
         Some(ScatterRecord::Scattering {
             attenuation: reflectance_sum(diffuse_color, specular_color),
-            pdf: MaterialPdf::DiffuseGgx {
-                diffuse: diffuse_pdf,
-                specular: specular_pdf,
-                specular_weight,
-            },
+            pdf: self.material_pdf(ray_in, hit)?,
         })
     }
 
@@ -845,6 +1046,33 @@ impl Material for LayeredDiffuseGgx {
         (1.0 - specular_weight) * diffuse_pdf + specular_weight * specular_pdf
     }
 
+    fn bsdf_flags(&self, _hit: &HitRecord<'_>) -> BsdfFlags {
+        BsdfFlags::DIFFUSE | BsdfFlags::GLOSSY | BsdfFlags::REFLECTION
+    }
+
+    fn eval_bsdf(&self, ray_in: &Ray, hit: &HitRecord<'_>, scattered: &Ray) -> LinearColor {
+        let (diffuse_color, specular_color) = self.sampled_colors(hit);
+        reflectance_sum(diffuse_color, specular_color) * self.scattering_pdf(ray_in, hit, scattered)
+    }
+
+    fn bsdf_pdf(&self, ray_in: &Ray, hit: &HitRecord<'_>, scattered: &Ray) -> f64 {
+        if scattered.direction().dot(hit.normal) <= 0.0 {
+            return 0.0;
+        }
+        self.material_pdf(ray_in, hit)
+            .map_or(0.0, |pdf| pdf.value(*scattered.direction()))
+    }
+
+    fn sample_bsdf(
+        &self,
+        ray_in: &Ray,
+        hit: &HitRecord<'_>,
+        rng: &mut SampleRng,
+    ) -> Option<BsdfSample> {
+        let pdf = self.material_pdf(ray_in, hit)?;
+        sample_material_pdf(self, ray_in, hit, pdf, self.bsdf_flags(hit), rng)
+    }
+
     fn denoise_albedo(&self, hit: &HitRecord<'_>) -> LinearColor {
         let (diffuse_color, specular_color) = self.sampled_colors(hit);
         reflectance_sum(diffuse_color, specular_color)
@@ -933,6 +1161,7 @@ impl Material for LayeredDiffuseGgx {
 #[derive(Clone, Debug)]
 pub struct DiffuseLight {
     color: MaterialColorSource,
+    spot: Option<SpotCone>,
 }
 
 impl DiffuseLight {
@@ -946,6 +1175,7 @@ impl DiffuseLight {
         assert!(emit.is_finite(), "diffuse light color must be finite");
         Self {
             color: MaterialColorSource::constant(emit),
+            spot: None,
         }
     }
 
@@ -954,6 +1184,7 @@ impl DiffuseLight {
     pub fn try_new(emit: LinearColor) -> Option<Self> {
         emit.is_finite().then(|| Self {
             color: MaterialColorSource::constant(emit),
+            spot: None,
         })
     }
 
@@ -962,6 +1193,7 @@ impl DiffuseLight {
     pub fn from_shared_texture(texture: TextureRef) -> Self {
         Self {
             color: MaterialColorSource::texture(texture),
+            spot: None,
         }
     }
 
@@ -971,6 +1203,7 @@ impl DiffuseLight {
     pub fn from_spectrum(spectrum: Spectrum) -> Self {
         Self {
             color: MaterialColorSource::spectrum(spectrum),
+            spot: None,
         }
     }
 
@@ -980,6 +1213,7 @@ impl DiffuseLight {
     pub fn from_measured_spectrum(spectrum: MeasuredSpectrum) -> Self {
         Self {
             color: MaterialColorSource::measured_spectrum(spectrum),
+            spot: None,
         }
     }
 
@@ -989,6 +1223,7 @@ impl DiffuseLight {
     pub fn from_shared_spectral_texture(texture: SpectralTextureRef) -> Self {
         Self {
             color: MaterialColorSource::spectral_texture(texture),
+            spot: None,
         }
     }
 
@@ -998,24 +1233,41 @@ impl DiffuseLight {
         Self::from_shared_texture(Arc::new(texture))
     }
 
+    /// Returns this light restricted to a spotlight cone around the emission direction.
+    #[must_use]
+    pub const fn with_spot_cone(mut self, spot: SpotCone) -> Self {
+        self.spot = Some(spot);
+        self
+    }
+
     /// Returns the texture sampled for emitted radiance.
     #[must_use]
     pub fn texture(&self) -> &dyn SurfaceTexture {
         self.color.surface_texture()
     }
+
+    /// Returns the radiance emitted at the middle of the texture, used to rank lights by power.
+    pub(crate) fn representative_radiance(&self, point: Point) -> LinearColor {
+        self.color.sample(TextureSample::new(0.5, 0.5, point))
+    }
+
+    fn cone_factor(&self, ray_in: &Ray) -> f64 {
+        self.spot
+            .map_or(1.0, |spot| spot.factor(-*ray_in.direction()))
+    }
 }
 
 impl Material for DiffuseLight {
     fn emitted(
         &self,
-        _ray_in: &Ray,
+        ray_in: &Ray,
         hit: &HitRecord<'_>,
         u: f64,
         v: f64,
         point: Point,
     ) -> LinearColor {
         if hit.front_face {
-            self.color.sample(TextureSample::new(u, v, point))
+            self.color.sample(TextureSample::new(u, v, point)) * self.cone_factor(ray_in)
         } else {
             LinearColor::default()
         }
@@ -1029,7 +1281,7 @@ impl Material for DiffuseLight {
     #[cfg(feature = "spectral")]
     fn spectral_emitted(
         &self,
-        _ray_in: &Ray,
+        ray_in: &Ray,
         hit: &HitRecord<'_>,
         u: f64,
         v: f64,
@@ -1039,6 +1291,7 @@ impl Material for DiffuseLight {
         if hit.front_face {
             self.color
                 .sample_spectrum(TextureSample::new(u, v, point), wavelength)
+                * self.cone_factor(ray_in)
         } else {
             0.0
         }
@@ -1140,6 +1393,30 @@ impl Material for Isotropic {
         1.0 / (4.0 * PI)
     }
 
+    fn bsdf_flags(&self, _hit: &HitRecord<'_>) -> BsdfFlags {
+        BsdfFlags::DIFFUSE | BsdfFlags::REFLECTION | BsdfFlags::TRANSMISSION | BsdfFlags::MEDIUM
+    }
+
+    fn eval_bsdf(&self, ray_in: &Ray, hit: &HitRecord<'_>, scattered: &Ray) -> LinearColor {
+        self.color
+            .sample(TextureSample::new(hit.u, hit.v, hit.point))
+            * self.scattering_pdf(ray_in, hit, scattered)
+    }
+
+    fn bsdf_pdf(&self, ray_in: &Ray, hit: &HitRecord<'_>, scattered: &Ray) -> f64 {
+        self.scattering_pdf(ray_in, hit, scattered)
+    }
+
+    fn sample_bsdf(
+        &self,
+        ray_in: &Ray,
+        hit: &HitRecord<'_>,
+        rng: &mut SampleRng,
+    ) -> Option<BsdfSample> {
+        let pdf = MaterialPdf::Sphere(SpherePdf);
+        sample_material_pdf(self, ray_in, hit, pdf, self.bsdf_flags(hit), rng)
+    }
+
     fn denoise_albedo(&self, hit: &HitRecord<'_>) -> LinearColor {
         self.color
             .sample(TextureSample::new(hit.u, hit.v, hit.point))
@@ -1301,6 +1578,31 @@ impl Material for HenyeyGreenstein {
         pdf.value(*scattered.direction())
     }
 
+    fn bsdf_flags(&self, _hit: &HitRecord<'_>) -> BsdfFlags {
+        BsdfFlags::GLOSSY | BsdfFlags::REFLECTION | BsdfFlags::TRANSMISSION | BsdfFlags::MEDIUM
+    }
+
+    fn eval_bsdf(&self, ray_in: &Ray, hit: &HitRecord<'_>, scattered: &Ray) -> LinearColor {
+        self.color
+            .sample(TextureSample::new(hit.u, hit.v, hit.point))
+            * self.scattering_pdf(ray_in, hit, scattered)
+    }
+
+    fn bsdf_pdf(&self, ray_in: &Ray, hit: &HitRecord<'_>, scattered: &Ray) -> f64 {
+        self.scattering_pdf(ray_in, hit, scattered)
+    }
+
+    fn sample_bsdf(
+        &self,
+        ray_in: &Ray,
+        hit: &HitRecord<'_>,
+        rng: &mut SampleRng,
+    ) -> Option<BsdfSample> {
+        let pdf =
+            MaterialPdf::HenyeyGreenstein(HenyeyGreensteinPdf::new(*ray_in.direction(), self.g)?);
+        sample_material_pdf(self, ray_in, hit, pdf, self.bsdf_flags(hit), rng)
+    }
+
     fn denoise_albedo(&self, hit: &HitRecord<'_>) -> LinearColor {
         self.color
             .sample(TextureSample::new(hit.u, hit.v, hit.point))
@@ -1615,6 +1917,40 @@ impl Material for GgxMicrofacet {
         )
     }
 
+    fn bsdf_flags(&self, _hit: &HitRecord<'_>) -> BsdfFlags {
+        BsdfFlags::GLOSSY | BsdfFlags::REFLECTION
+    }
+
+    fn eval_bsdf(&self, ray_in: &Ray, hit: &HitRecord<'_>, scattered: &Ray) -> LinearColor {
+        self.color
+            .sample(TextureSample::new(hit.u, hit.v, hit.point))
+            * self.scattering_pdf(ray_in, hit, scattered)
+    }
+
+    fn bsdf_pdf(&self, ray_in: &Ray, hit: &HitRecord<'_>, scattered: &Ray) -> f64 {
+        let outgoing = -ray_in.direction().normalized();
+        if scattered.direction().dot(hit.normal) <= 0.0 || outgoing.dot(hit.normal) <= 0.0 {
+            return 0.0;
+        }
+        GgxReflectionPdf::new(hit.shading_normal, outgoing, self.roughness)
+            .map_or(0.0, |pdf| pdf.value(*scattered.direction()))
+    }
+
+    fn sample_bsdf(
+        &self,
+        ray_in: &Ray,
+        hit: &HitRecord<'_>,
+        rng: &mut SampleRng,
+    ) -> Option<BsdfSample> {
+        let outgoing = -ray_in.direction().normalized();
+        let pdf = MaterialPdf::GgxReflection(GgxReflectionPdf::new(
+            hit.shading_normal,
+            outgoing,
+            self.roughness,
+        )?);
+        sample_material_pdf(self, ray_in, hit, pdf, self.bsdf_flags(hit), rng)
+    }
+
     fn denoise_albedo(&self, hit: &HitRecord<'_>) -> LinearColor {
         self.color
             .sample(TextureSample::new(hit.u, hit.v, hit.point))
@@ -1683,6 +2019,29 @@ fn normal_map_world_normal(
     })
 }
 
+/// Draws a direction from `pdf` and evaluates `material` along it.
+fn sample_material_pdf<M: Material + ?Sized>(
+    material: &M,
+    ray_in: &Ray,
+    hit: &HitRecord<'_>,
+    pdf: MaterialPdf,
+    flags: BsdfFlags,
+    rng: &mut SampleRng,
+) -> Option<BsdfSample> {
+    let direction = pdf.generate(rng);
+    let pdf_value = pdf.value(direction);
+    if !pdf_value.is_finite() || pdf_value <= 0.0 {
+        return None;
+    }
+    let scattered = Ray::with_time(hit.point, direction, ray_in.time());
+    Some(BsdfSample {
+        direction,
+        value: material.eval_bsdf(ray_in, hit, &scattered),
+        pdf: pdf_value,
+        flags,
+    })
+}
+
 fn ggx_reflection_scattering_weight(
     normal: Vector,
     outgoing: Vector,
@@ -2091,6 +2450,21 @@ impl Material for Metal {
         hit: &HitRecord<'_>,
         rng: &mut SampleRng,
     ) -> Option<ScatterRecord> {
+        self.sample_bsdf(ray_in, hit, rng)
+            .map(|sample| sample.specular_record(ray_in, hit))
+    }
+
+    /// Fuzzed reflections are sampled implicitly, so every metal reports a delta lobe.
+    fn bsdf_flags(&self, _hit: &HitRecord<'_>) -> BsdfFlags {
+        BsdfFlags::DELTA | BsdfFlags::REFLECTION
+    }
+
+    fn sample_bsdf(
+        &self,
+        ray_in: &Ray,
+        hit: &HitRecord<'_>,
+        rng: &mut SampleRng,
+    ) -> Option<BsdfSample> {
         let reflected = ray_in
             .direction()
             .normalized()
@@ -2100,9 +2474,11 @@ impl Material for Metal {
             return None;
         }
 
-        Some(ScatterRecord::Specular {
-            ray: Ray::with_time(hit.point, scattered_direction, ray_in.time()),
-            attenuation: self.albedo,
+        Some(BsdfSample {
+            direction: scattered_direction,
+            value: self.albedo,
+            pdf: 1.0,
+            flags: self.bsdf_flags(hit),
         })
     }
 
@@ -2151,6 +2527,7 @@ pub struct Dielectric {
     pub refraction_index: RefractiveIndex,
     #[cfg(feature = "spectral")]
     eta_spectrum: Option<MeasuredSpectrum>,
+    interior_medium: Option<MediumCoefficients>,
 }
 
 impl Dielectric {
@@ -2161,6 +2538,7 @@ impl Dielectric {
             refraction_index,
             #[cfg(feature = "spectral")]
             eta_spectrum: None,
+            interior_medium: None,
         }
     }
 
@@ -2180,6 +2558,7 @@ impl Dielectric {
         Self {
             refraction_index,
             eta_spectrum: Some(eta),
+            interior_medium: None,
         }
     }
 
@@ -2191,6 +2570,31 @@ impl Dielectric {
         self
     }
 
+    /// Fills the inside of the dielectric with an absorbing medium.
+    ///
+    /// Rays leaving through a back face are dimmed by the Beer–Lambert transmittance of
+    /// `medium`'s absorption over the distance they traveled inside, so thick parts of a glass
+    /// object look more deeply tinted than thin ones. Scattering coefficients are ignored here; use
+    /// a [`ChromaticMedium`](super::ChromaticMedium) to scatter inside the boundary as well.
+    #[must_use]
+    pub fn with_interior_medium(mut self, medium: MediumCoefficients) -> Self {
+        self.interior_medium = Some(medium);
+        self
+    }
+
+    /// Makes the inside of the dielectric clear again.
+    #[must_use]
+    pub fn without_interior_medium(mut self) -> Self {
+        self.interior_medium = None;
+        self
+    }
+
+    /// Returns the absorbing interior medium, if any.
+    #[must_use]
+    pub const fn interior_medium(&self) -> Option<&MediumCoefficients> {
+        self.interior_medium.as_ref()
+    }
+
     /// Returns the refractive index used at `wavelength`.
     #[cfg(feature = "spectral")]
     #[must_use]
@@ -2268,12 +2672,8 @@ impl Material for Dielectric {
         hit: &HitRecord<'_>,
         rng: &mut SampleRng,
     ) -> Option<ScatterRecord> {
-        Some(Self::scatter_with_refraction_index(
-            self.refraction_index.0,
-            ray_in,
-            hit,
-            rng,
-        ))
+        self.sample_bsdf(ray_in, hit, rng)
+            .map(|sample| sample.specular_record(ray_in, hit))
     }
 
     #[cfg(feature = "spectral")]
@@ -2284,12 +2684,31 @@ impl Material for Dielectric {
         wavelength: SampledWavelength,
         rng: &mut SampleRng,
     ) -> Option<ScatterRecord> {
-        Some(Self::scatter_with_refraction_index(
+        let mut sample = Self::sample_with_refraction_index(
             self.refraction_index_at(wavelength),
             ray_in,
             hit,
             rng,
-        ))
+        );
+        let transmittance = self.interior_transmittance_at(ray_in, hit, wavelength);
+        sample.value = LinearColor::new(transmittance, transmittance, transmittance);
+        Some(sample.specular_record(ray_in, hit))
+    }
+
+    fn bsdf_flags(&self, _hit: &HitRecord<'_>) -> BsdfFlags {
+        BsdfFlags::DELTA | BsdfFlags::REFLECTION | BsdfFlags::TRANSMISSION
+    }
+
+    fn sample_bsdf(
+        &self,
+        ray_in: &Ray,
+        hit: &HitRecord<'_>,
+        rng: &mut SampleRng,
+    ) -> Option<BsdfSample> {
+        let mut sample =
+            Self::sample_with_refraction_index(self.refraction_index.0, ray_in, hit, rng);
+        sample.value = self.interior_transmittance(ray_in, hit);
+        Some(sample)
     }
 
     fn denoise_albedo(&self, _hit: &HitRecord<'_>) -> LinearColor {
@@ -2318,13 +2737,37 @@ impl Material for Dielectric {
 }
 
 impl Dielectric {
-    fn scatter_with_refraction_index(
+    /// Absorption along the segment that ends at a back-face hit, which starts at the ray origin.
+    fn interior_transmittance(&self, ray_in: &Ray, hit: &HitRecord<'_>) -> LinearColor {
+        match &self.interior_medium {
+            Some(medium) if !hit.front_face => {
+                medium.absorption_transmittance(hit.t * ray_in.direction().length())
+            }
+            _ => LinearColor::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    #[cfg(feature = "spectral")]
+    fn interior_transmittance_at(
+        &self,
+        ray_in: &Ray,
+        hit: &HitRecord<'_>,
+        wavelength: SampledWavelength,
+    ) -> f64 {
+        match &self.interior_medium {
+            Some(medium) if !hit.front_face => {
+                medium.absorption_transmittance_at(hit.t * ray_in.direction().length(), wavelength)
+            }
+            _ => 1.0,
+        }
+    }
+
+    fn sample_with_refraction_index(
         refraction_index: f64,
         ray_in: &Ray,
         hit: &HitRecord<'_>,
         rng: &mut SampleRng,
-    ) -> ScatterRecord {
-        let attenuation = LinearColor::new(1.0, 1.0, 1.0);
+    ) -> BsdfSample {
         let refraction_ratio = if hit.front_face {
             1.0 / refraction_index
         } else {
@@ -2335,17 +2778,22 @@ impl Dielectric {
         let cos_theta = (-unit_direction).dot(hit.normal).min(1.0);
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
         let cannot_refract = refraction_ratio * sin_theta > 1.0;
-        let direction = if cannot_refract
+        let (direction, lobe) = if cannot_refract
             || Self::reflectance(cos_theta, refraction_ratio) > rng.random_double()
         {
-            unit_direction.reflected(hit.normal)
+            (unit_direction.reflected(hit.normal), BsdfFlags::REFLECTION)
         } else {
-            unit_direction.refracted(hit.normal, refraction_ratio)
+            (
+                unit_direction.refracted(hit.normal, refraction_ratio),
+                BsdfFlags::TRANSMISSION,
+            )
         };
 
-        ScatterRecord::Specular {
-            ray: Ray::with_time(hit.point, direction, ray_in.time()),
-            attenuation,
+        BsdfSample {
+            direction,
+            value: LinearColor::new(1.0, 1.0, 1.0),
+            pdf: 1.0,
+            flags: BsdfFlags::DELTA | lobe,
         }
     }
 }
@@ -2556,6 +3004,15 @@ impl RayMaterial {
         matches!(self, Self::DiffuseLight(_))
     }
 
+    /// Returns a representative emitted radiance near `point`, or black for non-emissive
+    /// materials.
+    pub(crate) fn representative_emission(&self, point: Point) -> LinearColor {
+        match self {
+            Self::DiffuseLight(material) => material.representative_radiance(point),
+            _ => LinearColor::default(),
+        }
+    }
+
     /// Returns true for materials that scatter through a deterministic specular ray.
     #[must_use]
     pub const fn is_delta(&self) -> bool {
@@ -2695,6 +3152,59 @@ impl Material for RayMaterial {
         }
     }
 
+    fn bsdf_flags(&self, hit: &HitRecord<'_>) -> BsdfFlags {
+        match self {
+            Self::Lambertian(material) => material.bsdf_flags(hit),
+            Self::DiffuseLight(material) => material.bsdf_flags(hit),
+            Self::Isotropic(material) => material.bsdf_flags(hit),
+            Self::HenyeyGreenstein(material) => material.bsdf_flags(hit),
+            Self::GgxMicrofacet(material) => material.bsdf_flags(hit),
+            Self::Metal(material) => material.bsdf_flags(hit),
+            Self::Dielectric(material) => material.bsdf_flags(hit),
+        }
+    }
+
+    fn eval_bsdf(&self, ray_in: &Ray, hit: &HitRecord<'_>, scattered: &Ray) -> LinearColor {
+        match self {
+            Self::Lambertian(material) => material.eval_bsdf(ray_in, hit, scattered),
+            Self::DiffuseLight(material) => material.eval_bsdf(ray_in, hit, scattered),
+            Self::Isotropic(material) => material.eval_bsdf(ray_in, hit, scattered),
+            Self::HenyeyGreenstein(material) => material.eval_bsdf(ray_in, hit, scattered),
+            Self::GgxMicrofacet(material) => material.eval_bsdf(ray_in, hit, scattered),
+            Self::Metal(material) => material.eval_bsdf(ray_in, hit, scattered),
+            Self::Dielectric(material) => material.eval_bsdf(ray_in, hit, scattered),
+        }
+    }
+
+    fn bsdf_pdf(&self, ray_in: &Ray, hit: &HitRecord<'_>, scattered: &Ray) -> f64 {
+        match self {
+            Self::Lambertian(material) => material.bsdf_pdf(ray_in, hit, scattered),
+            Self::DiffuseLight(material) => material.bsdf_pdf(ray_in, hit, scattered),
+            Self::Isotropic(material) => material.bsdf_pdf(ray_in, hit, scattered),
+            Self::HenyeyGreenstein(material) => material.bsdf_pdf(ray_in, hit, scattered),
+            Self::GgxMicrofacet(material) => material.bsdf_pdf(ray_in, hit, scattered),
+            Self::Metal(material) => material.bsdf_pdf(ray_in, hit, scattered),
+            Self::Dielectric(material) => material.bsdf_pdf(ray_in, hit, scattered),
+        }
+    }
+
+    fn sample_bsdf(
+        &self,
+        ray_in: &Ray,
+        hit: &HitRecord<'_>,
+        rng: &mut SampleRng,
+    ) -> Option<BsdfSample> {
+        match self {
+            Self::Lambertian(material) => material.sample_bsdf(ray_in, hit, rng),
+            Self::DiffuseLight(material) => material.sample_bsdf(ray_in, hit, rng),
+            Self::Isotropic(material) => material.sample_bsdf(ray_in, hit, rng),
+            Self::HenyeyGreenstein(material) => material.sample_bsdf(ray_in, hit, rng),
+            Self::GgxMicrofacet(material) => material.sample_bsdf(ray_in, hit, rng),
+            Self::Metal(material) => material.sample_bsdf(ray_in, hit, rng),
+            Self::Dielectric(material) => material.sample_bsdf(ray_in, hit, rng),
+        }
+    }
+
     fn denoise_albedo(&self, hit: &HitRecord<'_>) -> LinearColor {
         match self {
             Self::Lambertian(material) => material.denoise_albedo(hit),
@@ -3156,6 +3666,55 @@ mod tests {
         );
     }
 
+    #[test]
+    fn dielectric_interior_medium_tints_rays_leaving_through_back_faces() {
+        let material = Dielectric::from_ratio(1.5).with_interior_medium(
+            MediumCoefficients::from_transmittance(LinearColor::new(0.8, 0.5, 0.2), 1.0),
+        );
+        let ray = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, -1.0));
+        let exit = HitRecord {
+            point: Point::new(0.0, 0.0, -2.0),
+            normal: Vector::new(0.0, 0.0, 1.0),
+            geometric_normal: Vector::new(0.0, 0.0, 1.0),
+            shading_normal: Vector::new(0.0, 0.0, 1.0),
+            tangent: None,
+            bitangent: None,
+            tangent_handedness: 1.0,
+            t: 2.0,
+            u: 0.0,
+            v: 0.0,
+            front_face: false,
+            material: &material,
+        };
+        let entry = HitRecord {
+            front_face: true,
+            ..exit
+        };
+        let mut rng = SampleRng::new(17);
+
+        let leaving = material
+            .sample_bsdf(&ray, &exit, &mut rng)
+            .expect("dielectric should always sample");
+        let expected = [0.64, 0.25, 0.04];
+        for (actual, expected) in [leaving.value.red, leaving.value.green, leaving.value.blue]
+            .into_iter()
+            .zip(expected)
+        {
+            assert!((actual - expected).abs() < 1e-12, "{actual} vs {expected}");
+        }
+
+        let entering = material
+            .sample_bsdf(&ray, &entry, &mut rng)
+            .expect("dielectric should always sample");
+        assert_eq!(entering.value, LinearColor::new(1.0, 1.0, 1.0));
+        assert!(
+            material
+                .without_interior_medium()
+                .interior_medium()
+                .is_none()
+        );
+    }
+
     #[cfg(feature = "spectral")]
     #[test]
     fn dielectric_eta_spectrum_varies_with_wavelength() {
@@ -3573,4 +4132,152 @@ mod tests {
         assert!(!metal.has_pdf_scatter());
         assert!(!dielectric.has_pdf_scatter());
     }
+
+    fn bsdf_test_hit(material: &dyn Material) -> (Ray, HitRecord<'_>) {
+        let ray = Ray::with_time(
+            Point::new(-1.0, 1.0, 0.0),
+            Vector::new(1.0, -1.0, 0.25),
+            0.25,
+        );
+        let hit = HitRecord::new(
+            &ray,
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+            1.0,
+            material,
+        );
+        (ray, hit)
+    }
+
+    fn assert_color_near(actual: LinearColor, expected: LinearColor) {
+        let tolerance = 1.0e-9 * (1.0 + expected.red.abs() + expected.green.abs());
+        assert!(
+            (actual.red - expected.red).abs() <= tolerance
+                && (actual.green - expected.green).abs() <= tolerance
+                && (actual.blue - expected.blue).abs() <= tolerance,
+            "expected {expected:?}, got {actual:?}"
+        );
+    }
+
+    #[test]
+    fn bsdf_samples_agree_with_eval_and_pdf() {
+        let materials: [Box<dyn Material>; 5] = [
+            Box::new(Lambertian::new(LinearColor::new(0.6, 0.4, 0.2))),
+            Box::new(GgxMicrofacet::new(LinearColor::new(0.9, 0.7, 0.5), 0.35)),
+            Box::new(LayeredDiffuseGgx::new(
+                LinearColor::new(0.5, 0.3, 0.2),
+                LinearColor::new(0.04, 0.04, 0.04),
+                0.2,
+            )),
+            Box::new(Isotropic::new(LinearColor::new(0.8, 0.8, 0.8))),
+            Box::new(HenyeyGreenstein::new(LinearColor::new(0.7, 0.8, 0.9), 0.6)),
+        ];
+
+        for material in &materials {
+            let (ray, hit) = bsdf_test_hit(material.as_ref());
+            let flags = material.bsdf_flags(&hit);
+            assert!(flags.has_non_delta_lobe() && !flags.is_delta(), "{flags:?}");
+
+            let mut rng = SampleRng::new(17);
+            for _ in 0..64 {
+                let Some(sample) = material.sample_bsdf(&ray, &hit, &mut rng) else {
+                    continue;
+                };
+                let scattered = Ray::with_time(hit.point, sample.direction, ray.time());
+                let pdf = material.bsdf_pdf(&ray, &hit, &scattered);
+
+                assert!(flags.contains(sample.flags));
+                assert!((sample.pdf - pdf).abs() <= 1.0e-9 * pdf.max(1.0));
+                assert_color_near(sample.value, material.eval_bsdf(&ray, &hit, &scattered));
+            }
+        }
+    }
+
+    #[test]
+    fn lambertian_bsdf_sample_weight_is_albedo() {
+        let albedo = LinearColor::new(0.6, 0.4, 0.2);
+        let material = Lambertian::new(albedo);
+        let (ray, hit) = bsdf_test_hit(&material);
+        let mut rng = SampleRng::new(5);
+
+        for _ in 0..16 {
+            let sample = material
+                .sample_bsdf(&ray, &hit, &mut rng)
+                .expect("cosine sampling should produce a direction");
+            assert_eq!(sample.flags, BsdfFlags::DIFFUSE | BsdfFlags::REFLECTION);
+            assert_color_near(sample.weight(), albedo);
+        }
+    }
+
+    #[test]
+    fn delta_materials_are_sampled_but_never_evaluated() {
+        let metal = Metal::new(LinearColor::new(0.9, 0.8, 0.7), 0.0);
+        let glass = Dielectric::new(RefractiveIndex::GLASS);
+        let materials: [&dyn Material; 2] = [&metal, &glass];
+
+        for material in materials {
+            let (ray, hit) = bsdf_test_hit(material);
+            assert!(material.bsdf_flags(&hit).is_delta());
+            assert!(!material.bsdf_flags(&hit).has_non_delta_lobe());
+
+            let sample = material
+                .sample_bsdf(&ray, &hit, &mut SampleRng::new(9))
+                .expect("delta material should scatter");
+            let Some(ScatterRecord::Specular {
+                ray: scattered,
+                attenuation,
+            }) = material.scatter(&ray, &hit, &mut SampleRng::new(9))
+            else {
+                panic!("delta material should produce a specular record");
+            };
+
+            assert!(sample.is_delta());
+            assert!(
+                sample.flags.contains(BsdfFlags::REFLECTION)
+                    || sample.flags.contains(BsdfFlags::TRANSMISSION)
+            );
+            assert_eq!(*scattered.direction(), sample.direction);
+            assert_color_near(attenuation, sample.weight());
+            assert_eq!(
+                material.eval_bsdf(&ray, &hit, &scattered),
+                LinearColor::default()
+            );
+            assert!(material.bsdf_pdf(&ray, &hit, &scattered).abs() < f64::EPSILON);
+        }
+    }
+
+    #[test]
+    fn default_bsdf_sampling_adapts_scatter_records() {
+        struct ScatterOnly;
+
+        impl Material for ScatterOnly {
+            fn scatter(
+                &self,
+                _ray_in: &Ray,
+                hit: &HitRecord<'_>,
+                _rng: &mut SampleRng,
+            ) -> Option<ScatterRecord> {
+                Some(ScatterRecord::Scattering {
+                    attenuation: LinearColor::new(0.5, 0.25, 0.125),
+                    pdf: MaterialPdf::Cosine(CosinePdf::new(hit.shading_normal)?),
+                })
+            }
+
+            fn scattering_pdf(&self, _ray_in: &Ray, hit: &HitRecord<'_>, scattered: &Ray) -> f64 {
+                hit.shading_normal
+                    .dot(scattered.direction().normalized())
+                    .max(0.0)
+                    / PI
+            }
+        }
+
+        let (ray, hit) = bsdf_test_hit(&ScatterOnly);
+        let sample = ScatterOnly
+            .sample_bsdf(&ray, &hit, &mut SampleRng::new(3))
+            .expect("scatter record should adapt to a BSDF sample");
+
+        assert_eq!(ScatterOnly.bsdf_flags(&hit), BsdfFlags::EMPTY);
+        assert_eq!(sample.flags, BsdfFlags::EMPTY);
+        assert_color_near(sample.weight(), LinearColor::new(0.5, 0.25, 0.125));
+    }
 }
@@ -1,8 +1,8 @@
 //! Triangle mesh primitives and mesh-local acceleration.
 
 use super::{
-    Aabb, HitRecord, Hittable, Interval, Material, MaterialRef, MatrixInstance, SampleRng,
-    SurfaceHit,
+    Aabb, HitRecord, Hittable, Interval, Material, MaterialRef, MatrixInstance, MotionInstance,
+    SampleRng, SurfaceHit,
     bvh::{BvhBuildOptions, BvhPrimitiveInfo, BvhTraversalStats, FlatBvh, RayTraversal},
 };
 #[cfg(feature = "external")]
@@ -484,6 +484,17 @@ impl TriangleMesh {
     pub fn shared_instance(shared: Arc<Self>, transform: Matrix) -> Option<MatrixInstance> {
         MatrixInstance::new(shared, transform)
     }
+
+    /// Creates a keyframed motion-blur instance that shares this mesh through an [`Arc`].
+    ///
+    /// Keys are `(time, object-to-world matrix)` pairs; see [`MotionInstance::new`].
+    #[must_use]
+    pub fn shared_motion_instance<'m>(
+        shared: Arc<Self>,
+        keys: impl IntoIterator<Item = (f64, &'m Matrix)>,
+    ) -> Option<MotionInstance> {
+        MotionInstance::new(shared, keys)
+    }
 }
 
 #[cfg(feature = "external")]
@@ -1,12 +1,15 @@
 //! Core hittable objects, hit records, and analytic ray intersections.
 
 use super::{
-    Aabb, INFINITY, PI, SampleRng,
+    Aabb, INFINITY, LinearColor, PI, SampleRng,
     material::{Material, MaterialRef, default_material},
     scene::HittableList,
 };
 use crate::gmath::{
-    geometry::{MovingSphereGeometry, QuadGeometry, SphereGeometry, TriangleGeometry},
+    geometry::{
+        ConeGeometry, CylinderGeometry, DiskGeometry, MovingSphereGeometry, QuadGeometry, ShapeHit,
+        SphereGeometry, TorusGeometry, TriangleGeometry,
+    },
     ray::Ray,
     vector::{Point, Vector},
 };
@@ -94,6 +97,17 @@ impl PdfContext {
     }
 }
 
+/// A point drawn uniformly by area from an object's surface.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SurfaceSample {
+    /// Sampled surface point.
+    pub point: Point,
+    /// Unit-length outward geometric normal at the sampled point.
+    pub normal: Vector,
+    /// Area density of the sample, including any target-selection probability.
+    pub pdf: f64,
+}
+
 /// Geometry-only ray intersection information.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct SurfaceHit {
@@ -233,6 +247,50 @@ impl Intersect for QuadGeometry {
     }
 }
 
+impl Intersect for DiskGeometry {
+    fn intersect(&self, ray: &Ray, ray_t: Interval) -> Option<SurfaceHit> {
+        let hit = self.hit_ray(ray, ray_t.min, ray_t.max)?;
+        Some(shape_surface_hit(ray, hit))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.bounds())
+    }
+}
+
+impl Intersect for CylinderGeometry {
+    fn intersect(&self, ray: &Ray, ray_t: Interval) -> Option<SurfaceHit> {
+        let hit = self.hit_ray(ray, ray_t.min, ray_t.max)?;
+        Some(shape_surface_hit(ray, hit))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.bounds())
+    }
+}
+
+impl Intersect for ConeGeometry {
+    fn intersect(&self, ray: &Ray, ray_t: Interval) -> Option<SurfaceHit> {
+        let hit = self.hit_ray(ray, ray_t.min, ray_t.max)?;
+        Some(shape_surface_hit(ray, hit))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.bounds())
+    }
+}
+
+impl Intersect for TorusGeometry {
+    fn intersect(&self, ray: &Ray, ray_t: Interval) -> Option<SurfaceHit> {
+        let hit = self.hit_ray(ray, ray_t.min, ray_t.max)?;
+        Some(shape_surface_hit(ray, hit))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.bounds())
+    }
+}
+
 /// Returns `(t, u, v)` for a two-sided Möller-Trumbore triangle hit.
 #[must_use]
 pub fn hit_triangle(
@@ -258,6 +316,14 @@ pub enum RayGeometry {
     Triangle(TriangleGeometry),
     /// Analytic parallelogram geometry.
     Quad(QuadGeometry),
+    /// Analytic one-sided disk geometry.
+    Disk(DiskGeometry),
+    /// Analytic cylinder geometry, optionally capped.
+    Cylinder(CylinderGeometry),
+    /// Analytic cone geometry, optionally capped.
+    Cone(ConeGeometry),
+    /// Analytic torus geometry.
+    Torus(TorusGeometry),
 }
 
 impl RayGeometry {
@@ -285,6 +351,30 @@ impl RayGeometry {
         Self::Quad(QuadGeometry::new(corner, u, v))
     }
 
+    /// Creates a disk geometry variant facing along `normal`.
+    #[must_use]
+    pub fn disk(center: Point, normal: Vector, radius: f64) -> Self {
+        Self::Disk(DiskGeometry::new(center, normal, radius))
+    }
+
+    /// Creates a capped cylinder geometry variant from its base center and base-to-top axis.
+    #[must_use]
+    pub fn cylinder(base: Point, axis: Vector, radius: f64) -> Self {
+        Self::Cylinder(CylinderGeometry::new(base, axis, radius))
+    }
+
+    /// Creates a capped cone geometry variant from its base center and base-to-apex axis.
+    #[must_use]
+    pub fn cone(base: Point, axis: Vector, radius: f64) -> Self {
+        Self::Cone(ConeGeometry::new(base, axis, radius))
+    }
+
+    /// Creates a torus geometry variant from its center, ring axis, and ring and tube radii.
+    #[must_use]
+    pub fn torus(center: Point, axis: Vector, major_radius: f64, minor_radius: f64) -> Self {
+        Self::Torus(TorusGeometry::new(center, axis, major_radius, minor_radius))
+    }
+
     pub(crate) fn pdf_value(self, context: PdfContext, direction: Vector) -> f64 {
         match self {
             Self::Sphere(geometry) => sphere_pdf_value(geometry, context.origin, direction),
@@ -294,6 +384,10 @@ impl RayGeometry {
             }
             Self::Triangle(geometry) => triangle_pdf_value(geometry, context.origin, direction),
             Self::Quad(geometry) => quad_pdf_value(geometry, context.origin, direction),
+            Self::Disk(geometry) => disk_pdf_value(geometry, context.origin, direction),
+            Self::Cylinder(_) | Self::Cone(_) | Self::Torus(_) => {
+                crossing_area_pdf_value(self, context.origin, direction)
+            }
         }
     }
 
@@ -307,6 +401,71 @@ impl RayGeometry {
             ),
             Self::Triangle(geometry) => random_direction_to_triangle(geometry, context.origin, rng),
             Self::Quad(geometry) => random_direction_to_quad(geometry, context.origin, rng),
+            Self::Disk(_) | Self::Cylinder(_) | Self::Cone(_) | Self::Torus(_) => {
+                self.sample_surface(context.time, rng).map_or_else(
+                    || rng.random_unit_vector_spherical(),
+                    |sample| sample.point - context.origin,
+                )
+            }
+        }
+    }
+
+    pub(crate) fn sample_surface(self, time: f64, rng: &mut SampleRng) -> Option<SurfaceSample> {
+        match self {
+            Self::Sphere(geometry) => sample_sphere_surface(geometry, rng),
+            Self::MovingSphere(geometry) => {
+                sample_sphere_surface(moving_sphere_at(geometry, time), rng)
+            }
+            Self::Triangle(geometry) => sample_triangle_surface(geometry, rng),
+            Self::Quad(geometry) => sample_quad_surface(geometry, rng),
+            Self::Disk(geometry) => sample_disk_surface(geometry, rng),
+            Self::Cylinder(geometry) => sample_cylinder_surface(geometry, rng),
+            Self::Cone(geometry) => sample_cone_surface(geometry, rng),
+            Self::Torus(geometry) => sample_torus_surface(geometry, rng),
+        }
+    }
+
+    /// Returns the surface area, which is zero for degenerate geometry.
+    pub(crate) fn area(self) -> f64 {
+        match self {
+            Self::Sphere(geometry) => 4.0 * PI * geometry.radius() * geometry.radius(),
+            Self::MovingSphere(geometry) => 4.0 * PI * geometry.radius() * geometry.radius(),
+            Self::Triangle(geometry) => 0.5 * geometry.area_squared().sqrt(),
+            Self::Quad(geometry) => geometry.area_squared().sqrt(),
+            Self::Disk(geometry) => geometry.area(),
+            Self::Cylinder(geometry) => geometry.area(),
+            Self::Cone(geometry) => geometry.area(),
+            Self::Torus(geometry) => geometry.area(),
+        }
+    }
+
+    /// Returns the front-face normal of flat geometry, or `None` when every direction is a front
+    /// face.
+    pub(crate) fn front_normal(self) -> Option<Vector> {
+        match self {
+            Self::Sphere(_)
+            | Self::MovingSphere(_)
+            | Self::Cylinder(_)
+            | Self::Cone(_)
+            | Self::Torus(_) => None,
+            Self::Triangle(geometry) => Some(geometry.geometric_normal()),
+            Self::Quad(geometry) => Some(geometry.geometric_normal()),
+            Self::Disk(geometry) => Some(geometry.normal()),
+        }
+    }
+
+    pub(crate) fn surface_pdf(self, point: Point, time: f64) -> f64 {
+        match self {
+            Self::Sphere(geometry) => sphere_surface_pdf(geometry, point),
+            Self::MovingSphere(geometry) => {
+                sphere_surface_pdf(moving_sphere_at(geometry, time), point)
+            }
+            Self::Triangle(geometry) => triangle_surface_pdf(geometry, point),
+            Self::Quad(geometry) => quad_surface_pdf(geometry, point),
+            Self::Disk(geometry) => disk_surface_pdf(geometry, point),
+            Self::Cylinder(geometry) => cylinder_surface_pdf(geometry, point),
+            Self::Cone(geometry) => cone_surface_pdf(geometry, point),
+            Self::Torus(geometry) => torus_surface_pdf(geometry, point),
         }
     }
 }
@@ -335,6 +494,30 @@ impl From<QuadGeometry> for RayGeometry {
     }
 }
 
+impl From<DiskGeometry> for RayGeometry {
+    fn from(geometry: DiskGeometry) -> Self {
+        Self::Disk(geometry)
+    }
+}
+
+impl From<CylinderGeometry> for RayGeometry {
+    fn from(geometry: CylinderGeometry) -> Self {
+        Self::Cylinder(geometry)
+    }
+}
+
+impl From<ConeGeometry> for RayGeometry {
+    fn from(geometry: ConeGeometry) -> Self {
+        Self::Cone(geometry)
+    }
+}
+
+impl From<TorusGeometry> for RayGeometry {
+    fn from(geometry: TorusGeometry) -> Self {
+        Self::Torus(geometry)
+    }
+}
+
 impl Intersect for RayGeometry {
     fn intersect(&self, ray: &Ray, ray_t: Interval) -> Option<SurfaceHit> {
         match self {
@@ -342,6 +525,10 @@ impl Intersect for RayGeometry {
             Self::MovingSphere(geometry) => geometry.intersect(ray, ray_t),
             Self::Triangle(geometry) => geometry.intersect(ray, ray_t),
             Self::Quad(geometry) => geometry.intersect(ray, ray_t),
+            Self::Disk(geometry) => geometry.intersect(ray, ray_t),
+            Self::Cylinder(geometry) => geometry.intersect(ray, ray_t),
+            Self::Cone(geometry) => geometry.intersect(ray, ray_t),
+            Self::Torus(geometry) => geometry.intersect(ray, ray_t),
         }
     }
 
@@ -351,6 +538,10 @@ impl Intersect for RayGeometry {
             Self::MovingSphere(geometry) => geometry.bounding_box(),
             Self::Triangle(geometry) => geometry.bounding_box(),
             Self::Quad(geometry) => geometry.bounding_box(),
+            Self::Disk(geometry) => geometry.bounding_box(),
+            Self::Cylinder(geometry) => geometry.bounding_box(),
+            Self::Cone(geometry) => geometry.bounding_box(),
+            Self::Torus(geometry) => geometry.bounding_box(),
         }
     }
 }
@@ -588,6 +779,15 @@ impl<T: Hittable + ?Sized> Hittable for Arc<T> {
     fn bounding_box(&self) -> Option<Aabb> {
         (**self).bounding_box()
     }
+
+    fn transmittance_with_rng(
+        &self,
+        ray: &Ray,
+        ray_t: Interval,
+        rng: &mut SampleRng,
+    ) -> LinearColor {
+        (**self).transmittance_with_rng(ray, ray_t, rng)
+    }
 }
 
 /// A scene object that can be intersected by a ray.
@@ -614,6 +814,24 @@ pub trait Hittable: Send + Sync {
         None
     }
 
+    /// Returns an unbiased estimate of the light that passes through this object inside `ray_t`.
+    ///
+    /// Shadow rays use this instead of [`Self::hit_with_rng`]. The default treats any hit as fully
+    /// opaque; participating media override it to attenuate instead of occlude, and aggregates
+    /// multiply the transmittance of their children.
+    fn transmittance_with_rng(
+        &self,
+        ray: &Ray,
+        ray_t: Interval,
+        rng: &mut SampleRng,
+    ) -> LinearColor {
+        if self.hit_with_rng(ray, ray_t, rng).is_some() {
+            LinearColor::default()
+        } else {
+            LinearColor::new(1.0, 1.0, 1.0)
+        }
+    }
+
     /// Returns the probability density for sampling `direction` from `origin`.
     fn pdf_value(&self, _context: PdfContext, _direction: Vector) -> f64 {
         0.0
@@ -623,6 +841,19 @@ pub trait Hittable: Send + Sync {
     fn random_direction(&self, _context: PdfContext, _rng: &mut SampleRng) -> Vector {
         Vector::new(1.0, 0.0, 0.0)
     }
+
+    /// Samples a point on this object's surface at `time`, for starting light subpaths.
+    ///
+    /// Objects that return `None` can still be reached by camera paths, but bidirectional
+    /// renders never start light subpaths on them.
+    fn sample_surface(&self, _time: f64, _rng: &mut SampleRng) -> Option<SurfaceSample> {
+        None
+    }
+
+    /// Returns the area density with which [`Self::sample_surface`] produces `point` at `time`.
+    fn surface_pdf(&self, _point: Point, _time: f64) -> f64 {
+        0.0
+    }
 }
 
 /// A sphere hittable.
@@ -719,6 +950,14 @@ impl Hittable for Sphere {
     fn random_direction(&self, context: PdfContext, rng: &mut SampleRng) -> Vector {
         random_direction_to_sphere(self.geometry(), context.origin, rng)
     }
+
+    fn sample_surface(&self, _time: f64, rng: &mut SampleRng) -> Option<SurfaceSample> {
+        sample_sphere_surface(self.geometry(), rng)
+    }
+
+    fn surface_pdf(&self, point: Point, _time: f64) -> f64 {
+        sphere_surface_pdf(self.geometry(), point)
+    }
 }
 
 /// A linearly moving sphere hittable.
@@ -829,6 +1068,14 @@ impl Hittable for MovingSphere {
             rng,
         )
     }
+
+    fn sample_surface(&self, time: f64, rng: &mut SampleRng) -> Option<SurfaceSample> {
+        sample_sphere_surface(moving_sphere_at(self.geometry(), time), rng)
+    }
+
+    fn surface_pdf(&self, point: Point, time: f64) -> f64 {
+        sphere_surface_pdf(moving_sphere_at(self.geometry(), time), point)
+    }
 }
 
 /// A parallelogram hittable.
@@ -923,6 +1170,14 @@ impl Hittable for Quad {
     fn random_direction(&self, context: PdfContext, rng: &mut SampleRng) -> Vector {
         random_direction_to_quad(self.geometry(), context.origin, rng)
     }
+
+    fn sample_surface(&self, _time: f64, rng: &mut SampleRng) -> Option<SurfaceSample> {
+        sample_quad_surface(self.geometry(), rng)
+    }
+
+    fn surface_pdf(&self, point: Point, _time: f64) -> f64 {
+        quad_surface_pdf(self.geometry(), point)
+    }
 }
 
 /// Creates an axis-aligned box from six quad sides.
@@ -1160,7 +1415,331 @@ fn random_to_sphere(radius: f64, distance_squared: f64, rng: &mut SampleRng) ->
     Vector::new(phi.cos() * radius_at_z, phi.sin() * radius_at_z, z)
 }
 
+fn sample_sphere_surface(geometry: SphereGeometry, rng: &mut SampleRng) -> Option<SurfaceSample> {
+    let area = 4.0 * PI * geometry.radius() * geometry.radius();
+    if area <= f64::EPSILON {
+        return None;
+    }
+
+    let normal = rng.random_unit_vector_spherical();
+    Some(SurfaceSample {
+        point: geometry.center() + geometry.radius() * normal,
+        normal,
+        pdf: 1.0 / area,
+    })
+}
+
+fn sphere_surface_pdf(geometry: SphereGeometry, point: Point) -> f64 {
+    let radius = geometry.radius();
+    let area = 4.0 * PI * radius * radius;
+    let distance = (point - geometry.center()).length();
+    if area <= f64::EPSILON || (distance - radius).abs() > SURFACE_PDF_TOLERANCE * radius.max(1.0) {
+        0.0
+    } else {
+        1.0 / area
+    }
+}
+
+fn sample_quad_surface(geometry: QuadGeometry, rng: &mut SampleRng) -> Option<SurfaceSample> {
+    let area = geometry.area_squared().sqrt();
+    if area <= f64::EPSILON {
+        return None;
+    }
+
+    Some(SurfaceSample {
+        point: geometry.corner()
+            + rng.random_double() * geometry.u()
+            + rng.random_double() * geometry.v(),
+        normal: geometry.geometric_normal(),
+        pdf: 1.0 / area,
+    })
+}
+
+fn quad_surface_pdf(geometry: QuadGeometry, point: Point) -> f64 {
+    let area = geometry.area_squared().sqrt();
+    if area <= f64::EPSILON
+        || !on_plane(point, geometry.corner(), geometry.geometric_normal(), area)
+    {
+        return 0.0;
+    }
+
+    let (alpha, beta) = geometry.plane_coordinates(point);
+    let range = -SURFACE_PDF_TOLERANCE..=1.0 + SURFACE_PDF_TOLERANCE;
+    if range.contains(&alpha) && range.contains(&beta) {
+        1.0 / area
+    } else {
+        0.0
+    }
+}
+
+fn sample_triangle_surface(
+    geometry: TriangleGeometry,
+    rng: &mut SampleRng,
+) -> Option<SurfaceSample> {
+    let area = 0.5 * geometry.area_squared().sqrt();
+    if area <= f64::EPSILON {
+        return None;
+    }
+
+    let [p0, p1, p2] = geometry.vertices();
+    let mut a = rng.random_double();
+    let mut b = rng.random_double();
+    if a + b > 1.0 {
+        a = 1.0 - a;
+        b = 1.0 - b;
+    }
+    Some(SurfaceSample {
+        point: p0 + a * (p1 - p0) + b * (p2 - p0),
+        normal: geometry.geometric_normal(),
+        pdf: 1.0 / area,
+    })
+}
+
+fn triangle_surface_pdf(geometry: TriangleGeometry, point: Point) -> f64 {
+    let area = 0.5 * geometry.area_squared().sqrt();
+    let [p0, p1, p2] = geometry.vertices();
+    if area <= f64::EPSILON || !on_plane(point, p0, geometry.geometric_normal(), area) {
+        return 0.0;
+    }
+
+    let edge1 = p1 - p0;
+    let edge2 = p2 - p0;
+    let offset = point - p0;
+    let d11 = edge1.dot(edge1);
+    let d12 = edge1.dot(edge2);
+    let d22 = edge2.dot(edge2);
+    let denominator = d11 * d22 - d12 * d12;
+    let b1 = (d22 * offset.dot(edge1) - d12 * offset.dot(edge2)) / denominator;
+    let b2 = (d11 * offset.dot(edge2) - d12 * offset.dot(edge1)) / denominator;
+    if b1 >= -SURFACE_PDF_TOLERANCE
+        && b2 >= -SURFACE_PDF_TOLERANCE
+        && b1 + b2 <= 1.0 + SURFACE_PDF_TOLERANCE
+    {
+        1.0 / area
+    } else {
+        0.0
+    }
+}
+
+fn shape_surface_hit(ray: &Ray, hit: ShapeHit) -> SurfaceHit {
+    SurfaceHit::with_uv(ray, ray.at(hit.t), hit.normal, hit.t, hit.u, hit.v)
+}
+
+fn disk_pdf_value(geometry: DiskGeometry, origin: Point, direction: Vector) -> f64 {
+    let Some(hit) = geometry.hit_ray(
+        &Ray::new(origin, direction),
+        SHADOW_ACNE_PDF_EPSILON,
+        INFINITY,
+    ) else {
+        return 0.0;
+    };
+
+    area_pdf_value(direction, hit.t, geometry.normal(), geometry.area())
+}
+
+/// Returns the solid-angle density of uniform area sampling along `direction`, summed over every
+/// surface crossing so the back of a closed shape is counted as well as the front.
+fn crossing_area_pdf_value(geometry: RayGeometry, origin: Point, direction: Vector) -> f64 {
+    let area = geometry.area();
+    let ray = Ray::new(origin, direction);
+    let mut t_min = SHADOW_ACNE_PDF_EPSILON;
+    let mut pdf = 0.0;
+    for _ in 0..MAX_PDF_SURFACE_CROSSINGS {
+        let Some(hit) = geometry.intersect(&ray, Interval::new(t_min, INFINITY)) else {
+            break;
+        };
+        pdf += area_pdf_value(direction, hit.t, hit.normal, area);
+        t_min = hit.t + SHADOW_ACNE_PDF_EPSILON;
+    }
+    pdf
+}
+
+/// Returns a uniformly distributed local-frame point on a disk of `radius`.
+fn random_in_disk(radius: f64, rng: &mut SampleRng) -> (f64, f64) {
+    let distance = radius * rng.random_double().sqrt();
+    let (sin_phi, cos_phi) = (2.0 * PI * rng.random_double()).sin_cos();
+    (distance * cos_phi, distance * sin_phi)
+}
+
+fn sample_disk_surface(geometry: DiskGeometry, rng: &mut SampleRng) -> Option<SurfaceSample> {
+    let area = geometry.area();
+    if area <= f64::EPSILON {
+        return None;
+    }
+
+    let (x, y) = random_in_disk(geometry.radius(), rng);
+    Some(SurfaceSample {
+        point: geometry.center() + geometry.basis().local(Vector::new(x, y, 0.0)),
+        normal: geometry.normal(),
+        pdf: 1.0 / area,
+    })
+}
+
+fn disk_surface_pdf(geometry: DiskGeometry, point: Point) -> f64 {
+    let area = geometry.area();
+    if area <= f64::EPSILON {
+        return 0.0;
+    }
+
+    let local = geometry.basis().to_local(point - geometry.center());
+    let tolerance = SURFACE_PDF_TOLERANCE * geometry.radius().max(1.0);
+    if local.z().abs() <= tolerance && local.x().hypot(local.y()) <= geometry.radius() + tolerance {
+        1.0 / area
+    } else {
+        0.0
+    }
+}
+
+fn sample_cylinder_surface(
+    geometry: CylinderGeometry,
+    rng: &mut SampleRng,
+) -> Option<SurfaceSample> {
+    let area = geometry.area();
+    if area <= f64::EPSILON {
+        return None;
+    }
+
+    let basis = geometry.basis();
+    let region = rng.random_double() * area;
+    let (local, normal) = if region < geometry.side_area() {
+        let (sin_phi, cos_phi) = (2.0 * PI * rng.random_double()).sin_cos();
+        let radial = Vector::new(cos_phi, sin_phi, 0.0);
+        let height = geometry.height() * rng.random_double();
+        (
+            geometry.radius() * radial + Vector::new(0.0, 0.0, height),
+            basis.local(radial),
+        )
+    } else {
+        let (x, y) = random_in_disk(geometry.radius(), rng);
+        if region < geometry.side_area() + geometry.cap_area() {
+            (Vector::new(x, y, 0.0), -basis.w())
+        } else {
+            (Vector::new(x, y, geometry.height()), basis.w())
+        }
+    };
+    Some(SurfaceSample {
+        point: geometry.base() + basis.local(local),
+        normal,
+        pdf: 1.0 / area,
+    })
+}
+
+fn cylinder_surface_pdf(geometry: CylinderGeometry, point: Point) -> f64 {
+    let area = geometry.area();
+    if area <= f64::EPSILON {
+        return 0.0;
+    }
+
+    let local = geometry.basis().to_local(point - geometry.base());
+    let (radius, height) = (geometry.radius(), geometry.height());
+    let tolerance = SURFACE_PDF_TOLERANCE * radius.max(height).max(1.0);
+    let distance = local.x().hypot(local.y());
+    let on_side = (distance - radius).abs() <= tolerance
+        && (-tolerance..=height + tolerance).contains(&local.z());
+    let on_cap = geometry.is_capped()
+        && distance <= radius + tolerance
+        && (local.z().abs() <= tolerance || (local.z() - height).abs() <= tolerance);
+    if on_side || on_cap { 1.0 / area } else { 0.0 }
+}
+
+fn sample_cone_surface(geometry: ConeGeometry, rng: &mut SampleRng) -> Option<SurfaceSample> {
+    let area = geometry.area();
+    if area <= f64::EPSILON {
+        return None;
+    }
+
+    let basis = geometry.basis();
+    let (local, normal) = if rng.random_double() * area < geometry.side_area() {
+        // The side's area element grows linearly with distance from the apex.
+        let from_apex = rng.random_double().sqrt();
+        let phi = 2.0 * PI * rng.random_double();
+        let (sin_phi, cos_phi) = phi.sin_cos();
+        let radius = geometry.radius() * from_apex;
+        (
+            Vector::new(
+                radius * cos_phi,
+                radius * sin_phi,
+                geometry.height() * (1.0 - from_apex),
+            ),
+            geometry.side_normal(phi),
+        )
+    } else {
+        let (x, y) = random_in_disk(geometry.radius(), rng);
+        (Vector::new(x, y, 0.0), -basis.w())
+    };
+    Some(SurfaceSample {
+        point: geometry.base() + basis.local(local),
+        normal,
+        pdf: 1.0 / area,
+    })
+}
+
+fn cone_surface_pdf(geometry: ConeGeometry, point: Point) -> f64 {
+    let area = geometry.area();
+    if area <= f64::EPSILON {
+        return 0.0;
+    }
+
+    let local = geometry.basis().to_local(point - geometry.base());
+    let (radius, height) = (geometry.radius(), geometry.height());
+    let tolerance = SURFACE_PDF_TOLERANCE * radius.max(height).max(1.0);
+    let distance = local.x().hypot(local.y());
+    let side_radius = radius * (height - local.z()) / height;
+    let on_side = (distance - side_radius).abs() <= tolerance
+        && (-tolerance..=height + tolerance).contains(&local.z());
+    let on_cap =
+        geometry.is_capped() && distance <= radius + tolerance && local.z().abs() <= tolerance;
+    if on_side || on_cap { 1.0 / area } else { 0.0 }
+}
+
+fn sample_torus_surface(geometry: TorusGeometry, rng: &mut SampleRng) -> Option<SurfaceSample> {
+    let area = geometry.area();
+    if area <= f64::EPSILON {
+        return None;
+    }
+
+    // The area element is proportional to the distance from the ring axis, so reject tube
+    // angles against the outermost circle.
+    let (major, minor) = (geometry.major_radius(), geometry.minor_radius());
+    let mut theta = 0.0;
+    for _ in 0..TORUS_SAMPLE_ATTEMPTS {
+        theta = 2.0 * PI * rng.random_double();
+        if rng.random_double() * (major + minor) <= major + minor * theta.cos() {
+            break;
+        }
+    }
+    let (point, normal) = geometry.surface_point(2.0 * PI * rng.random_double(), theta);
+    Some(SurfaceSample {
+        point,
+        normal,
+        pdf: 1.0 / area,
+    })
+}
+
+fn torus_surface_pdf(geometry: TorusGeometry, point: Point) -> f64 {
+    let area = geometry.area();
+    if area <= f64::EPSILON {
+        return 0.0;
+    }
+
+    let local = geometry.basis().to_local(point - geometry.center());
+    let tube = (local.x().hypot(local.y()) - geometry.major_radius()).hypot(local.z());
+    let tolerance = SURFACE_PDF_TOLERANCE * geometry.major_radius().max(1.0);
+    if (tube - geometry.minor_radius()).abs() <= tolerance {
+        1.0 / area
+    } else {
+        0.0
+    }
+}
+
+fn on_plane(point: Point, origin: Point, normal: Vector, area: f64) -> bool {
+    (point - origin).dot(normal).abs() <= SURFACE_PDF_TOLERANCE * area.sqrt().max(1.0)
+}
+
 const SHADOW_ACNE_PDF_EPSILON: f64 = 0.001;
+const SURFACE_PDF_TOLERANCE: f64 = 1e-6;
+const MAX_PDF_SURFACE_CROSSINGS: usize = 4;
+const TORUS_SAMPLE_ATTEMPTS: usize = 64;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,554 @@
+//! Stochastic progressive photon mapping over hittable worlds.
+//!
+//! Every pass traces a fresh batch of photons from surface points drawn from the light targets,
+//! stores the ones that land on diffuse or glossy surfaces in a uniform hash grid, and then traces
+//! one camera ray per pixel through delta lobes until it reaches such a surface. That visible point
+//! takes a next-event estimate for direct lighting and gathers the photons inside the pixel's
+//! current radius for everything else. Pixels keep their radius, photon count, and flux across
+//! passes and shrink the radius after each gather (Hachisuka and Jensen's progressive update), so
+//! the estimate converges even for specular-diffuse-specular paths such as caustics seen through
+//! [`Dielectric`](crate::graphics::raytracing::Dielectric) objects.
+//!
+//! Photons are only emitted from light targets, so background radiance is collected by camera rays
+//! that escape but never illuminates visible points. Participating media are not gathered: camera
+//! paths that scatter in a medium keep their direct lighting only.
+
+use super::{
+    BsdfFlags, HitRecord, Hittable, HittablePdf, INFINITY, Interval, LinearColor, PI, Pdf,
+    PdfContext, SHADOW_ACNE_EPSILON, bdpt::emitted_leaving, component_mul, denoise::luminance,
+};
+use crate::{
+    gmath::{
+        geometry::OrthonormalBasis,
+        random::SampleRng,
+        ray::Ray,
+        vector::{Point, Vector},
+    },
+    graphics::camera::{RayBackground, RayBackgroundSource},
+};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::collections::HashMap;
+
+const DEFAULT_PHOTONS_PER_PASS: u32 = 100_000;
+const DEFAULT_ALPHA: f64 = 2.0 / 3.0;
+/// Initial gather radius as a fraction of the world bounding-box diagonal.
+const DEFAULT_INITIAL_RADIUS_FRACTION: f64 = 0.01;
+/// Initial gather radius for worlds without a finite bounding box.
+const DEFAULT_INITIAL_RADIUS: f64 = 0.05;
+/// Photons traced from one random stream; also the unit of parallel work.
+const PHOTON_CHUNK_SIZE: u32 = 4096;
+/// Bounce count after which photons may be terminated by Russian roulette.
+const PHOTON_RUSSIAN_ROULETTE_MIN_DEPTH: u32 = 3;
+
+/// Progressive photon mapping settings.
+///
+/// Each camera sample per pixel is one photon pass, so
+/// [`RayCamera::with_samples_per_pixel`](crate::graphics::camera::RayCamera::with_samples_per_pixel)
+/// sets the pass count and the total photon budget is that count times [`Self::photons_per_pass`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PhotonMappingOptions {
+    photons_per_pass: u32,
+    initial_radius: Option<f64>,
+    alpha: f64,
+}
+
+impl PhotonMappingOptions {
+    /// Uses 100 000 photons per pass, a scene-relative initial radius, and `alpha = 2/3`.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            photons_per_pass: DEFAULT_PHOTONS_PER_PASS,
+            initial_radius: None,
+            alpha: DEFAULT_ALPHA,
+        }
+    }
+
+    /// Sets how many photons are emitted before each camera pass.
+    #[must_use]
+    pub const fn photons_per_pass(mut self, photons_per_pass: u32) -> Self {
+        self.photons_per_pass = if photons_per_pass == 0 {
+            1
+        } else {
+            photons_per_pass
+        };
+        self
+    }
+
+    /// Returns the number of photons emitted per pass.
+    #[must_use]
+    pub const fn photons_per_pass_count(self) -> u32 {
+        self.photons_per_pass
+    }
+
+    /// Sets the gather radius every pixel starts with, in world units.
+    ///
+    /// Without an explicit radius, renders start at one percent of the world bounding-box
+    /// diagonal, which is too wide for scenes with very large ground planes.
+    #[must_use]
+    pub fn initial_radius(mut self, radius: f64) -> Self {
+        self.initial_radius = (radius.is_finite() && radius > 0.0).then_some(radius);
+        self
+    }
+
+    /// Returns the configured initial gather radius, if any.
+    #[must_use]
+    pub const fn initial_radius_override(self) -> Option<f64> {
+        self.initial_radius
+    }
+
+    /// Sets the fraction of newly gathered photons each pixel keeps, clamped to `(0, 1]`.
+    ///
+    /// Smaller values shrink the radius faster, trading noise for less blur.
+    #[must_use]
+    pub fn alpha(mut self, alpha: f64) -> Self {
+        self.alpha = if alpha.is_finite() {
+            alpha.clamp(f64::EPSILON, 1.0)
+        } else {
+            DEFAULT_ALPHA
+        };
+        self
+    }
+
+    /// Returns the radius reduction parameter.
+    #[must_use]
+    pub const fn alpha_value(self) -> f64 {
+        self.alpha
+    }
+
+    /// Returns the initial gather radius used for `world`.
+    pub(crate) fn initial_radius_for(self, world: &dyn Hittable) -> f64 {
+        self.initial_radius.unwrap_or_else(|| {
+            world
+                .bounding_box()
+                .map(|bounds| {
+                    let diagonal = (0..3)
+                        .map(|axis| bounds.axis_max(axis) - bounds.axis_min(axis))
+                        .map(|extent| extent * extent)
+                        .sum::<f64>()
+                        .sqrt();
+                    diagonal * DEFAULT_INITIAL_RADIUS_FRACTION
+                })
+                .filter(|radius| radius.is_finite() && *radius > 0.0)
+                .unwrap_or(DEFAULT_INITIAL_RADIUS)
+        })
+    }
+}
+
+impl Default for PhotonMappingOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Photon stored where it arrived at a gathering surface.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Photon {
+    point: Point,
+    /// Unit travel direction of the photon when it arrived.
+    direction: Vector,
+    /// Flux carried by the photon, before dividing by the total photon count.
+    flux: LinearColor,
+}
+
+/// Uniform hash grid of photons with cells at least as wide as the largest gather radius.
+pub(crate) struct PhotonGrid {
+    cell_size: f64,
+    cells: HashMap<[i64; 3], Vec<Photon>>,
+}
+
+impl PhotonGrid {
+    /// Buckets `photons` into cells of width `cell_size`.
+    pub(crate) fn new(photons: Vec<Photon>, cell_size: f64) -> Self {
+        let cell_size = if cell_size.is_finite() && cell_size > 0.0 {
+            cell_size
+        } else {
+            DEFAULT_INITIAL_RADIUS
+        };
+        let mut grid = Self {
+            cell_size,
+            cells: HashMap::new(),
+        };
+        for photon in photons {
+            let cell = grid.cell(photon.point);
+            grid.cells.entry(cell).or_default().push(photon);
+        }
+        grid
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn cell(&self, point: Point) -> [i64; 3] {
+        [point.x(), point.y(), point.z()]
+            .map(|coordinate| (coordinate / self.cell_size).floor() as i64)
+    }
+
+    /// Calls `visit` for every photon closer than `radius` to `point`.
+    fn for_each_within(&self, point: Point, radius: f64, mut visit: impl FnMut(&Photon)) {
+        let offset = Vector::new(radius, radius, radius);
+        let low = self.cell(point - offset);
+        let high = self.cell(point + offset);
+        let radius_squared = radius * radius;
+        for x in low[0]..=high[0] {
+            for y in low[1]..=high[1] {
+                for z in low[2]..=high[2] {
+                    let Some(photons) = self.cells.get(&[x, y, z]) else {
+                        continue;
+                    };
+                    for photon in photons {
+                        if (photon.point - point).length_squared() < radius_squared {
+                            visit(photon);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Progressive statistics of one pixel.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct PhotonPixel {
+    /// Current gather radius.
+    pub(crate) radius: f64,
+    /// Accumulated photon count after radius reduction.
+    photon_count: f64,
+    /// Accumulated camera-weighted photon flux inside the current radius.
+    flux: LinearColor,
+    /// Sum of emitted and direct radiance over all passes.
+    direct: LinearColor,
+}
+
+impl PhotonPixel {
+    pub(crate) fn new(radius: f64) -> Self {
+        Self {
+            radius,
+            ..Self::default()
+        }
+    }
+
+    /// Returns the pixel radiance after `passes` passes that emitted `emitted_photons` in total.
+    pub(crate) fn radiance(self, passes: u32, emitted_photons: f64) -> LinearColor {
+        let passes = f64::from(passes.max(1));
+        let area = PI * self.radius * self.radius;
+        let indirect = if area > 0.0 && emitted_photons > 0.0 {
+            self.flux / (emitted_photons * area)
+        } else {
+            LinearColor::default()
+        };
+        self.direct / passes + indirect
+    }
+
+    /// Adds `found` photons with camera-weighted flux `flux` and shrinks the radius.
+    fn gather(&mut self, found: u32, flux: LinearColor, alpha: f64) {
+        if found == 0 {
+            return;
+        }
+        let found = f64::from(found);
+        let photon_count = self.photon_count + alpha * found;
+        let radius = self.radius * (photon_count / (self.photon_count + found)).sqrt();
+        let shrink = (radius / self.radius).powi(2);
+        self.flux = (self.flux + flux) * shrink;
+        self.photon_count = photon_count;
+        self.radius = radius;
+    }
+}
+
+/// Scene state shared by all passes of one photon-mapped render.
+pub(crate) struct PhotonMapIntegrator<'a> {
+    pub(crate) world: &'a dyn Hittable,
+    pub(crate) lights: &'a dyn Hittable,
+    pub(crate) background: RayBackground,
+    pub(crate) max_depth: u32,
+    pub(crate) shutter: (f64, f64),
+    pub(crate) alpha: f64,
+}
+
+impl PhotonMapIntegrator<'_> {
+    /// Traces `count` photons and returns those stored on gathering surfaces.
+    ///
+    /// Photons are split into fixed-size chunks whose random streams come from `chunk_seed`, so the
+    /// result does not depend on the number of worker threads.
+    pub(crate) fn trace_photons(
+        &self,
+        count: u32,
+        chunk_seed: impl Fn(u32) -> u64 + Sync,
+    ) -> Vec<Photon> {
+        let chunk_count = count.div_ceil(PHOTON_CHUNK_SIZE);
+        let trace_chunk = |chunk: u32| {
+            let mut rng = SampleRng::new(chunk_seed(chunk));
+            let start = chunk * PHOTON_CHUNK_SIZE;
+            let end = count.min(start + PHOTON_CHUNK_SIZE);
+            let mut photons = Vec::new();
+            for _ in start..end {
+                self.trace_photon(&mut photons, &mut rng);
+            }
+            photons
+        };
+
+        #[cfg(feature = "rayon")]
+        let chunks: Vec<Vec<Photon>> = (0..chunk_count).into_par_iter().map(trace_chunk).collect();
+        #[cfg(not(feature = "rayon"))]
+        let chunks: Vec<Vec<Photon>> = (0..chunk_count).map(trace_chunk).collect();
+
+        chunks.into_iter().flatten().collect()
+    }
+
+    fn trace_photon(&self, photons: &mut Vec<Photon>, rng: &mut SampleRng) {
+        let time = rng.random_range(self.shutter.0, self.shutter.1);
+        let Some(sample) = self.lights.sample_surface(time, rng) else {
+            return;
+        };
+        if !sample.pdf.is_finite() || sample.pdf <= 0.0 {
+            return;
+        }
+        let Some(basis) = OrthonormalBasis::from_w(sample.normal) else {
+            return;
+        };
+        let local = rng.random_cosine_direction();
+        if local.z() <= 0.0 {
+            return;
+        }
+        let direction = basis.local(local);
+        let emitted = emitted_leaving(self.world, sample.point, direction, time, rng);
+        if !emitted.is_finite() || emitted == LinearColor::default() {
+            return;
+        }
+
+        // Cosine-weighted emission cancels the emitter cosine, leaving pi over the area density.
+        let mut flux = emitted * (PI / sample.pdf);
+        let mut ray = Ray::with_time(sample.point, direction, time);
+        for depth in 0..self.max_depth {
+            let Some(hit) =
+                self.world
+                    .hit_with_rng(&ray, Interval::new(SHADOW_ACNE_EPSILON, INFINITY), rng)
+            else {
+                return;
+            };
+            // The first surface a photon reaches is lit directly, which the camera pass estimates
+            // with next-event estimation instead.
+            if depth > 0 && gathers_photons(hit.material.bsdf_flags(&hit)) {
+                photons.push(Photon {
+                    point: hit.point,
+                    direction: ray.direction().normalized(),
+                    flux,
+                });
+            }
+
+            let Some(sample) = hit.material.sample_bsdf(&ray, &hit, rng) else {
+                return;
+            };
+            let scattered = component_mul(flux, sample.weight());
+            if !scattered.is_finite() || scattered == LinearColor::default() {
+                return;
+            }
+            if depth + 1 >= PHOTON_RUSSIAN_ROULETTE_MIN_DEPTH {
+                let survival = (luminance(scattered) / luminance(flux)).min(1.0);
+                if survival.is_nan() || rng.random_double() >= survival {
+                    return;
+                }
+                flux = scattered / survival;
+            } else {
+                flux = scattered;
+            }
+            ray = Ray::with_time(hit.point, sample.direction, time);
+        }
+    }
+
+    /// Traces `camera_ray` to its visible point, gathers photons there, and returns the updated
+    /// pixel statistics.
+    pub(crate) fn update_pixel(
+        &self,
+        mut pixel: PhotonPixel,
+        camera_ray: &Ray,
+        grid: &PhotonGrid,
+        rng: &mut SampleRng,
+    ) -> PhotonPixel {
+        let mut beta = LinearColor::new(1.0, 1.0, 1.0);
+        let mut direct = LinearColor::default();
+        let mut ray = *camera_ray;
+        for depth in 0..self.max_depth {
+            let Some(hit) =
+                self.world
+                    .hit_with_rng(&ray, Interval::new(SHADOW_ACNE_EPSILON, INFINITY), rng)
+            else {
+                direct += component_mul(beta, self.background.radiance(*ray.direction()));
+                break;
+            };
+            direct += component_mul(
+                beta,
+                hit.material.emitted(&ray, &hit, hit.u, hit.v, hit.point),
+            );
+
+            let flags = hit.material.bsdf_flags(&hit);
+            if flags.has_non_delta_lobe() {
+                direct += component_mul(beta, self.estimate_direct_lighting(&ray, &hit, rng));
+                if gathers_photons(flags) {
+                    let (found, flux) = Self::gather(&ray, &hit, grid, pixel.radius);
+                    pixel.gather(found, component_mul(beta, flux), self.alpha);
+                }
+                break;
+            }
+
+            if depth + 1 == self.max_depth {
+                break;
+            }
+            let Some(sample) = hit.material.sample_bsdf(&ray, &hit, rng) else {
+                break;
+            };
+            beta = component_mul(beta, sample.weight());
+            if !beta.is_finite() || beta == LinearColor::default() {
+                break;
+            }
+            ray = Ray::with_time(hit.point, sample.direction, ray.time());
+        }
+
+        if direct.is_finite() {
+            pixel.direct += direct;
+        }
+        pixel
+    }
+
+    /// Sums the BSDF-weighted flux of photons within `radius` of `hit`.
+    fn gather(
+        ray_in: &Ray,
+        hit: &HitRecord<'_>,
+        grid: &PhotonGrid,
+        radius: f64,
+    ) -> (u32, LinearColor) {
+        let mut found = 0;
+        let mut flux = LinearColor::default();
+        grid.for_each_within(hit.point, radius, |photon| {
+            found += 1;
+            let toward_light = -photon.direction;
+            let cosine = hit.shading_normal.dot(toward_light).abs();
+            if cosine <= 0.0 {
+                return;
+            }
+            // `eval_bsdf` includes the incident cosine, which the photon density already accounts
+            // for.
+            let bsdf = hit.material.eval_bsdf(
+                ray_in,
+                hit,
+                &Ray::with_time(hit.point, toward_light, ray_in.time()),
+            ) / cosine;
+            let contribution = component_mul(bsdf, photon.flux);
+            if contribution.is_finite() {
+                flux += contribution;
+            }
+        });
+        (found, flux)
+    }
+
+    /// Samples one direction toward the light targets and returns the unweighted direct radiance.
+    fn estimate_direct_lighting(
+        &self,
+        ray_in: &Ray,
+        hit: &HitRecord<'_>,
+        rng: &mut SampleRng,
+    ) -> LinearColor {
+        let light_pdf = HittablePdf::new(self.lights, PdfContext::new(hit.point, ray_in.time()));
+        let direction = light_pdf.generate(rng);
+        let pdf = light_pdf.value(direction);
+        if !pdf.is_finite() || pdf <= f64::EPSILON {
+            return LinearColor::default();
+        }
+
+        let shadow_ray = Ray::with_time(hit.point, direction, ray_in.time());
+        let bsdf = hit.material.eval_bsdf(ray_in, hit, &shadow_ray);
+        if !bsdf.is_finite() || bsdf == LinearColor::default() {
+            return LinearColor::default();
+        }
+        let Some(light_hit) = self.world.hit_with_rng(
+            &shadow_ray,
+            Interval::new(SHADOW_ACNE_EPSILON, INFINITY),
+            rng,
+        ) else {
+            return LinearColor::default();
+        };
+        let emitted = light_hit.material.emitted(
+            &shadow_ray,
+            &light_hit,
+            light_hit.u,
+            light_hit.v,
+            light_hit.point,
+        );
+        if !emitted.is_finite() {
+            return LinearColor::default();
+        }
+        component_mul(bsdf, emitted) / pdf
+    }
+}
+
+/// Returns true for surface lobes whose incident radiance is estimated from photons.
+fn gathers_photons(flags: BsdfFlags) -> bool {
+    flags.has_non_delta_lobe() && !flags.contains(BsdfFlags::MEDIUM)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn photon(point: Point) -> Photon {
+        Photon {
+            point,
+            direction: Vector::new(0.0, -1.0, 0.0),
+            flux: LinearColor::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    #[test]
+    fn photon_grid_finds_exactly_the_photons_inside_the_radius() {
+        let mut rng = SampleRng::new(3);
+        let photons: Vec<Photon> = (0..500)
+            .map(|_| photon(Point::default() + rng.random_vector_range(-1.0, 1.0)))
+            .collect();
+        let grid = PhotonGrid::new(photons.clone(), 0.3);
+
+        for _ in 0..20 {
+            let center = Point::default() + rng.random_vector_range(-1.0, 1.0);
+            let radius = rng.random_range(0.05, 0.3);
+            let expected = photons
+                .iter()
+                .filter(|photon| (photon.point - center).length_squared() < radius * radius)
+                .count();
+            let mut found = 0;
+            grid.for_each_within(center, radius, |_| found += 1);
+
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn photon_pixel_gather_shrinks_radius_and_keeps_flux_density() {
+        let mut pixel = PhotonPixel::new(0.5);
+        pixel.gather(30, LinearColor::new(3.0, 3.0, 3.0), 2.0 / 3.0);
+
+        assert!((pixel.radius - 0.5 * (20.0_f64 / 30.0).sqrt()).abs() < 1e-12);
+        assert!((pixel.photon_count - 20.0).abs() < 1e-12);
+        let density_before = 3.0 / (0.5 * 0.5);
+        let density_after = pixel.flux.red / (pixel.radius * pixel.radius);
+        assert!((density_after - density_before).abs() < 1e-9);
+
+        let radius = pixel.radius;
+        pixel.gather(0, LinearColor::new(1.0, 1.0, 1.0), 2.0 / 3.0);
+        assert!((pixel.radius - radius).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn photon_mapping_options_sanitize_inputs() {
+        let options = PhotonMappingOptions::new()
+            .photons_per_pass(0)
+            .initial_radius(-1.0)
+            .alpha(4.0);
+
+        assert_eq!(options.photons_per_pass_count(), 1);
+        assert_eq!(options.initial_radius_override(), None);
+        assert!((options.alpha_value() - 1.0).abs() < f64::EPSILON);
+        assert_eq!(
+            PhotonMappingOptions::new()
+                .initial_radius(0.25)
+                .initial_radius_override(),
+            Some(0.25)
+        );
+    }
+}
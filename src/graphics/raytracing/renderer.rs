@@ -6,28 +6,43 @@
 //! [`SurfaceScene::to_ray_scene`] and pass the resulting [`RayScene`] to
 //! [`PathTracer::render_ray_scene`], [`PathTracer::render`], or
 //! [`PathTracer::render_with_lights`]. [`PathTracer::render_with_light_connections`] forces
-//! next-event light connections for cameras configured with material-PDF path continuation.
+//! next-event light connections for cameras configured with material-PDF path continuation, and
+//! [`PathTracer::render_bidirectional`] connects camera subpaths to light subpaths instead.
+//! [`PathTracer::render_photon_mapped`] gathers progressively refined photon maps for caustics.
+//! Long renders can use [`PathTracer::render_checkpointed`] and [`PathTracer::resume`], or their
+//! lights and environment variants, to survive interruptions and to add samples to a finished
+//! image.
 
 #[cfg(feature = "spectral")]
 use super::SpectralImage;
-use super::{EnvironmentLight, Hittable, RayScene};
+use super::{EnvironmentLight, Hittable, PhotonMappingOptions, RayScene, RenderCheckpoint};
 use crate::graphics::{
-    camera::{DenoisingAovs, ProgressiveRenderUpdate, RayBackgroundSource, RayCamera},
+    camera::{
+        DenoisingAovs, ProgressiveRenderUpdate, RayBackgroundSource, RayCamera, SamplingStrategy,
+    },
     display::{Canvas, HdrImage, ToneMap},
     scene::SurfaceScene,
 };
+use std::{io, path::Path};
+
+const DEFAULT_CHECKPOINT_TILE_SIZE: u32 = 16;
+const DEFAULT_CHECKPOINT_INTERVAL: u32 = 16;
 
 /// Path-tracer image traversal options.
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
 pub struct RenderOptions {
     tile_size: Option<u32>,
+    checkpoint_interval: Option<u32>,
 }
 
 impl RenderOptions {
     /// Uses the camera's default image traversal settings.
     #[must_use]
     pub const fn new() -> Self {
-        Self { tile_size: None }
+        Self {
+            tile_size: None,
+            checkpoint_interval: None,
+        }
     }
 
     /// Sets a tile size for world renders.
@@ -42,6 +57,23 @@ impl RenderOptions {
     pub const fn tile_size_override(self) -> Option<u32> {
         self.tile_size
     }
+
+    /// Sets how many samples per pixel checkpointed renders draw between checkpoint writes.
+    #[must_use]
+    pub const fn checkpoint_interval(mut self, samples_per_pixel: u32) -> Self {
+        self.checkpoint_interval = Some(if samples_per_pixel == 0 {
+            1
+        } else {
+            samples_per_pixel
+        });
+        self
+    }
+
+    /// Returns the configured checkpoint interval, if any.
+    #[must_use]
+    pub const fn checkpoint_interval_override(self) -> Option<u32> {
+        self.checkpoint_interval
+    }
 }
 
 /// Path-tracing renderer wrapper around a ray camera.
@@ -175,8 +207,8 @@ impl PathTracer {
 
     /// Renders a compiled ray scene, automatically importance-sampling emissive primitives.
     ///
-    /// This is the simplest entry point for [`RayScene`] content. It builds a light-target list
-    /// from [`RayScene::emissive_targets`] and calls [`Self::render_with_lights`] when that list is
+    /// This is the simplest entry point for [`RayScene`] content. It builds a light tree from
+    /// [`RayScene::emissive_targets`] and calls [`Self::render_with_lights`] when that tree is
     /// non-empty; scenes without emissive primitives render through [`Self::render`].
     pub fn render_ray_scene(self, scene: &RayScene) -> Canvas {
         self.render_ray_scene_hdr_image(scene).to_canvas()
@@ -212,6 +244,47 @@ impl PathTracer {
         }
     }
 
+    /// Renders a compiled ray scene with bidirectional path tracing.
+    ///
+    /// Emissive primitives are collected as light-subpath origins. Scenes without emissive
+    /// primitives fall back to the ordinary path tracer.
+    pub fn render_ray_scene_bidirectional(self, scene: &RayScene) -> Canvas {
+        let lights = scene.emissive_targets();
+        if lights.is_empty() {
+            self.render(scene)
+        } else {
+            self.render_bidirectional(scene, &lights)
+        }
+    }
+
+    /// Renders a compiled ray scene with stochastic progressive photon mapping.
+    ///
+    /// Emissive primitives are collected as photon sources. Scenes without emissive primitives
+    /// fall back to the ordinary path tracer.
+    pub fn render_ray_scene_photon_mapped(
+        self,
+        scene: &RayScene,
+        options: PhotonMappingOptions,
+    ) -> Canvas {
+        self.render_ray_scene_photon_mapped_hdr_image(scene, options)
+            .to_canvas()
+    }
+
+    /// Renders a compiled ray scene with progressive photon mapping to linear HDR samples.
+    #[must_use]
+    pub fn render_ray_scene_photon_mapped_hdr_image(
+        self,
+        scene: &RayScene,
+        options: PhotonMappingOptions,
+    ) -> HdrImage {
+        let lights = scene.emissive_targets();
+        if lights.is_empty() {
+            self.render_hdr_image(scene)
+        } else {
+            self.render_photon_mapped_hdr_image(scene, &lights, options)
+        }
+    }
+
     /// Renders a compiled ray scene with denoising AOVs and automatic emissive-target sampling.
     #[must_use]
     pub fn render_ray_scene_denoising_aovs(self, scene: &RayScene) -> DenoisingAovs {
@@ -764,10 +837,73 @@ impl PathTracer {
         )
     }
 
+    /// Renders `world` with bidirectional path tracing.
+    ///
+    /// Each camera sample also traces a subpath from a surface point sampled on `lights`; every
+    /// camera and light vertex pair is connected and weighted with the power heuristic, and light
+    /// vertices that see the lens are splatted onto the film. This converges much faster than
+    /// next-event estimation for caustics and for lights that are mostly reached through
+    /// indirect bounces. Light targets must support [`Hittable::sample_surface`].
+    pub fn render_bidirectional(self, world: &dyn Hittable, lights: &dyn Hittable) -> Canvas {
+        self.render_bidirectional_hdr_image(world, lights)
+            .to_canvas()
+    }
+
+    /// Renders `world` with bidirectional path tracing to linear HDR samples.
+    #[must_use]
+    pub fn render_bidirectional_hdr_image(
+        self,
+        world: &dyn Hittable,
+        lights: &dyn Hittable,
+    ) -> HdrImage {
+        self.with_camera(
+            self.camera
+                .with_sampling_strategy(SamplingStrategy::bidirectional()),
+        )
+        .render_with_lights_hdr_image(world, lights)
+    }
+
+    /// Renders `world` with stochastic progressive photon mapping.
+    ///
+    /// Every sample per pixel is one pass that emits [`PhotonMappingOptions::photons_per_pass`]
+    /// photons from `lights` and gathers them where camera rays first reach a diffuse or glossy
+    /// surface, with a radius that shrinks from pass to pass. This resolves caustics cast by
+    /// dielectrics onto diffuse surfaces, which camera paths and next-event estimation barely
+    /// sample. Light targets must support [`Hittable::sample_surface`].
+    pub fn render_photon_mapped(
+        self,
+        world: &dyn Hittable,
+        lights: &dyn Hittable,
+        options: PhotonMappingOptions,
+    ) -> Canvas {
+        self.render_photon_mapped_hdr_image(world, lights, options)
+            .to_canvas()
+    }
+
+    /// Renders `world` with stochastic progressive photon mapping to linear HDR samples.
+    #[must_use]
+    pub fn render_photon_mapped_hdr_image(
+        self,
+        world: &dyn Hittable,
+        lights: &dyn Hittable,
+        options: PhotonMappingOptions,
+    ) -> HdrImage {
+        self.options.tile_size.map_or_else(
+            || {
+                self.camera
+                    .render_world_photon_mapped_hdr_image(world, lights, options)
+            },
+            |tile_size| {
+                self.camera
+                    .render_world_photon_mapped_hdr_image_tiled(world, lights, options, tile_size)
+            },
+        )
+    }
+
     /// Renders `world` with forced next-event light connections.
     ///
-    /// This is ordinary camera-subpath next-event estimation. It does not implement bidirectional
-    /// light subpaths, path-space MIS, or path mutation.
+    /// This is ordinary camera-subpath next-event estimation; use [`Self::render_bidirectional`]
+    /// to add light subpaths and path-space MIS.
     pub fn render_with_light_connections(
         self,
         world: &dyn Hittable,
@@ -868,12 +1004,250 @@ impl PathTracer {
         }
     }
 
+    /// Renders `world` to linear HDR samples, saving a [`RenderCheckpoint`] to `path` after every
+    /// checkpoint interval.
+    ///
+    /// Checkpointed renders draw the camera's `samples_per_pixel` as independent random samples
+    /// with RGB transport; stratified, adaptive, and spectral camera settings do not apply. With
+    /// the default random pixel sampling the result matches [`Self::render_hdr_image`].
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from writing the checkpoint file.
+    pub fn render_checkpointed(
+        self,
+        world: &dyn Hittable,
+        path: impl AsRef<Path>,
+    ) -> io::Result<HdrImage> {
+        self.continue_checkpoint(
+            world,
+            None,
+            None,
+            self.camera.new_render_checkpoint(false, false),
+            path.as_ref(),
+        )
+    }
+
+    /// Renders `world` with explicit light sampling, saving checkpoints to `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from writing the checkpoint file.
+    pub fn render_with_lights_checkpointed(
+        self,
+        world: &dyn Hittable,
+        lights: &dyn Hittable,
+        path: impl AsRef<Path>,
+    ) -> io::Result<HdrImage> {
+        self.continue_checkpoint(
+            world,
+            Some(lights),
+            None,
+            self.camera.new_render_checkpoint(true, false),
+            path.as_ref(),
+        )
+    }
+
+    /// Renders `world` with an importance-sampled environment light, saving checkpoints to `path`.
+    ///
+    /// With the default random pixel sampling the result matches
+    /// [`Self::render_with_environment_hdr_image`].
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from writing the checkpoint file.
+    pub fn render_with_environment_checkpointed(
+        self,
+        world: &dyn Hittable,
+        environment: &EnvironmentLight,
+        path: impl AsRef<Path>,
+    ) -> io::Result<HdrImage> {
+        self.continue_checkpoint(
+            world,
+            None,
+            Some(environment),
+            self.camera.new_render_checkpoint(false, true),
+            path.as_ref(),
+        )
+    }
+
+    /// Renders `world` with geometry lights plus an environment light, saving checkpoints to
+    /// `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from writing the checkpoint file.
+    pub fn render_with_lights_and_environment_checkpointed(
+        self,
+        world: &dyn Hittable,
+        lights: &dyn Hittable,
+        environment: &EnvironmentLight,
+        path: impl AsRef<Path>,
+    ) -> io::Result<HdrImage> {
+        self.continue_checkpoint(
+            world,
+            Some(lights),
+            Some(environment),
+            self.camera.new_render_checkpoint(true, true),
+            path.as_ref(),
+        )
+    }
+
+    /// Continues the checkpointed render saved at `path` until every pixel has the camera's
+    /// `samples_per_pixel`.
+    ///
+    /// Raise the camera's sample count to keep refining a finished render; a checkpoint that
+    /// already has enough samples is returned without tracing more rays.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from reading or writing the checkpoint, or
+    /// [`io::ErrorKind::InvalidInput`] if the checkpoint was rendered at a different resolution,
+    /// seed, or maximum depth than this tracer's camera, or by a different entry point's choice of
+    /// explicit lights and environment light.
+    pub fn resume(self, world: &dyn Hittable, path: impl AsRef<Path>) -> io::Result<HdrImage> {
+        let path = path.as_ref();
+        let checkpoint = self.load_matching_checkpoint(path, false, false)?;
+        self.continue_checkpoint(world, None, None, checkpoint, path)
+    }
+
+    /// Continues a checkpointed render with explicit light sampling.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`Self::resume`].
+    pub fn resume_with_lights(
+        self,
+        world: &dyn Hittable,
+        lights: &dyn Hittable,
+        path: impl AsRef<Path>,
+    ) -> io::Result<HdrImage> {
+        let path = path.as_ref();
+        let checkpoint = self.load_matching_checkpoint(path, true, false)?;
+        self.continue_checkpoint(world, Some(lights), None, checkpoint, path)
+    }
+
+    /// Continues a checkpointed render with an importance-sampled environment light.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`Self::resume`].
+    pub fn resume_with_environment(
+        self,
+        world: &dyn Hittable,
+        environment: &EnvironmentLight,
+        path: impl AsRef<Path>,
+    ) -> io::Result<HdrImage> {
+        let path = path.as_ref();
+        let checkpoint = self.load_matching_checkpoint(path, false, true)?;
+        self.continue_checkpoint(world, None, Some(environment), checkpoint, path)
+    }
+
+    /// Continues a checkpointed render with geometry lights plus an environment light.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`Self::resume`].
+    pub fn resume_with_lights_and_environment(
+        self,
+        world: &dyn Hittable,
+        lights: &dyn Hittable,
+        environment: &EnvironmentLight,
+        path: impl AsRef<Path>,
+    ) -> io::Result<HdrImage> {
+        let path = path.as_ref();
+        let checkpoint = self.load_matching_checkpoint(path, true, true)?;
+        self.continue_checkpoint(world, Some(lights), Some(environment), checkpoint, path)
+    }
+
+    fn load_matching_checkpoint(
+        self,
+        path: &Path,
+        lights: bool,
+        environment: bool,
+    ) -> io::Result<RenderCheckpoint> {
+        let checkpoint = RenderCheckpoint::load(path)?;
+        let expected = self.camera.new_render_checkpoint(lights, environment);
+        if (
+            checkpoint.width(),
+            checkpoint.height(),
+            checkpoint.rng_seed(),
+        ) != (expected.width(), expected.height(), expected.rng_seed())
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "checkpoint was rendered at {}x{} with seed {}, but the camera renders {}x{} with seed {}",
+                    checkpoint.width(),
+                    checkpoint.height(),
+                    checkpoint.rng_seed(),
+                    expected.width(),
+                    expected.height(),
+                    expected.rng_seed(),
+                ),
+            ));
+        }
+        if checkpoint.estimator() != expected.estimator() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "checkpoint was rendered with {}, but this resume renders with {}",
+                    describe_estimator(&checkpoint),
+                    describe_estimator(&expected),
+                ),
+            ));
+        }
+        Ok(checkpoint)
+    }
+
+    fn continue_checkpoint(
+        self,
+        world: &dyn Hittable,
+        lights: Option<&dyn Hittable>,
+        environment: Option<&EnvironmentLight>,
+        mut checkpoint: RenderCheckpoint,
+        path: &Path,
+    ) -> io::Result<HdrImage> {
+        let target = self.camera.samples_per_pixel();
+        let interval = self
+            .options
+            .checkpoint_interval
+            .unwrap_or(DEFAULT_CHECKPOINT_INTERVAL);
+        let tile_size = self
+            .options
+            .tile_size
+            .unwrap_or(DEFAULT_CHECKPOINT_TILE_SIZE);
+        while checkpoint.samples_per_pixel() < target {
+            let samples = interval.min(target - checkpoint.samples_per_pixel());
+            self.camera.accumulate_checkpoint_samples(
+                world,
+                lights,
+                environment,
+                &mut checkpoint,
+                samples,
+                tile_size,
+            );
+            checkpoint.save(path)?;
+        }
+        Ok(checkpoint.to_hdr_image())
+    }
+
     /// Renders `world` as normal-visualization colors.
     pub fn render_normals(self, world: &dyn Hittable) -> Canvas {
         self.camera.render_world_normals(world)
     }
 }
 
+fn describe_estimator(checkpoint: &RenderCheckpoint) -> String {
+    let sampled = |used| if used { "with" } else { "without" };
+    format!(
+        "max depth {}, {} explicit lights, and {} an environment light",
+        checkpoint.max_depth(),
+        sampled(checkpoint.uses_lights()),
+        sampled(checkpoint.uses_environment()),
+    )
+}
+
 impl Default for PathTracer {
     fn default() -> Self {
         Self::new(RayCamera::default())
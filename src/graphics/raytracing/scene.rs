@@ -9,9 +9,11 @@
 //! - [`SamplingTargetList`]: importance-sampling targets such as lights, windows, or caustic
 //!   objects.
 
+use super::component_mul;
+use super::denoise::luminance;
 use super::{
-    Aabb, HitRecord, Hittable, Intersect, Interval, MovingSphere, PdfContext, Quad, RayGeometry,
-    RayMaterial, SampleRng, Sphere,
+    Aabb, HitRecord, Hittable, Intersect, Interval, LightTree, LinearColor, MovingSphere,
+    PdfContext, Quad, RayGeometry, RayMaterial, SampleRng, Sphere, SurfaceSample,
     bvh::{BvhBuildOptions, BvhPrimitiveInfo, BvhTraversalStats, FlatBvh, RayTraversal},
 };
 use crate::{
@@ -23,7 +25,7 @@ use crate::{
     },
     graphics::{material::SurfaceMaterial, scene::SurfaceScene},
 };
-use std::{collections::HashMap, fmt, sync::OnceLock};
+use std::{collections::HashMap, f64::consts::PI, fmt, sync::OnceLock};
 
 /// Custom [`SurfaceMaterial`] to [`RayMaterial`] mapper used by [`SurfaceRayMaterialMode::Custom`].
 pub type SurfaceRayMaterialMapper<'a> = dyn Fn(&SurfaceMaterial) -> RayMaterial + Send + Sync + 'a;
@@ -204,6 +206,15 @@ impl Hittable for HittableList {
         }
     }
 
+    fn transmittance_with_rng(
+        &self,
+        ray: &Ray,
+        ray_t: Interval,
+        rng: &mut SampleRng,
+    ) -> LinearColor {
+        transmittance_through(self.objects.iter().map(|object| &**object), ray, ray_t, rng)
+    }
+
     fn pdf_value(&self, context: PdfContext, direction: Vector) -> f64 {
         if self.objects.is_empty() {
             return 0.0;
@@ -222,6 +233,24 @@ impl Hittable for HittableList {
             |index| self.objects[index].random_direction(context, rng),
         )
     }
+
+    fn sample_surface(&self, time: f64, rng: &mut SampleRng) -> Option<SurfaceSample> {
+        sample_uniform_surface(self.objects.len(), rng, |index, rng| {
+            self.objects[index].sample_surface(time, rng)
+        })
+    }
+
+    fn surface_pdf(&self, point: Point, time: f64) -> f64 {
+        if self.objects.is_empty() {
+            return 0.0;
+        }
+
+        let weight = reciprocal_count(self.objects.len());
+        self.objects
+            .iter()
+            .map(|object| weight * object.surface_pdf(point, time))
+            .sum()
+    }
 }
 
 /// Borrowed hittable layers for composing prebuilt scene pieces.
@@ -330,6 +359,15 @@ impl Hittable for HittableLayers<'_> {
         }
     }
 
+    fn transmittance_with_rng(
+        &self,
+        ray: &Ray,
+        ray_t: Interval,
+        rng: &mut SampleRng,
+    ) -> LinearColor {
+        transmittance_through(self.layers.iter().copied(), ray, ray_t, rng)
+    }
+
     fn pdf_value(&self, context: PdfContext, direction: Vector) -> f64 {
         if self.layers.is_empty() {
             return 0.0;
@@ -348,6 +386,24 @@ impl Hittable for HittableLayers<'_> {
             |index| self.layers[index].random_direction(context, rng),
         )
     }
+
+    fn sample_surface(&self, time: f64, rng: &mut SampleRng) -> Option<SurfaceSample> {
+        sample_uniform_surface(self.layers.len(), rng, |index, rng| {
+            self.layers[index].sample_surface(time, rng)
+        })
+    }
+
+    fn surface_pdf(&self, point: Point, time: f64) -> f64 {
+        if self.layers.is_empty() {
+            return 0.0;
+        }
+
+        let weight = reciprocal_count(self.layers.len());
+        self.layers
+            .iter()
+            .map(|layer| weight * layer.surface_pdf(point, time))
+            .sum()
+    }
 }
 
 /// Dedicated importance-sampling target list.
@@ -447,6 +503,14 @@ impl Hittable for SamplingTargetList {
     fn random_direction(&self, context: PdfContext, rng: &mut SampleRng) -> Vector {
         self.targets.random_direction(context, rng)
     }
+
+    fn sample_surface(&self, time: f64, rng: &mut SampleRng) -> Option<SurfaceSample> {
+        self.targets.sample_surface(time, rng)
+    }
+
+    fn surface_pdf(&self, point: Point, time: f64) -> f64 {
+        self.targets.surface_pdf(point, time)
+    }
 }
 
 /// Importance-sampling target list with per-target selection weights.
@@ -606,6 +670,43 @@ impl Hittable for WeightedSamplingTargetList {
             .object
             .random_direction(context, rng)
     }
+
+    fn sample_surface(&self, time: f64, rng: &mut SampleRng) -> Option<SurfaceSample> {
+        if self.objects.is_empty() || self.total_weight <= 0.0 {
+            return None;
+        }
+
+        let mut pick = rng.random_range(0.0, self.total_weight);
+        let target = self
+            .objects
+            .iter()
+            .find(|target| {
+                pick -= target.weight;
+                pick <= 0.0
+            })
+            .unwrap_or(&self.objects[self.objects.len() - 1]);
+        let selection_pdf = target.weight / self.total_weight;
+        target
+            .object
+            .sample_surface(time, rng)
+            .map(|sample| SurfaceSample {
+                pdf: sample.pdf * selection_pdf,
+                ..sample
+            })
+    }
+
+    fn surface_pdf(&self, point: Point, time: f64) -> f64 {
+        if self.objects.is_empty() || self.total_weight <= 0.0 {
+            return 0.0;
+        }
+
+        self.objects
+            .iter()
+            .map(|target| {
+                target.weight / self.total_weight * target.object.surface_pdf(point, time)
+            })
+            .sum()
+    }
 }
 
 /// Bounding-volume hierarchy over arbitrary bounded boxed hittables.
@@ -704,6 +805,15 @@ impl Hittable for BvhNode {
         Some(self.bounds)
     }
 
+    fn transmittance_with_rng(
+        &self,
+        ray: &Ray,
+        ray_t: Interval,
+        rng: &mut SampleRng,
+    ) -> LinearColor {
+        self.bvh.transmittance(&self.objects, ray, ray_t, rng)
+    }
+
     fn pdf_value(&self, context: PdfContext, direction: Vector) -> f64 {
         if self.objects.is_empty() {
             return 0.0;
@@ -722,6 +832,24 @@ impl Hittable for BvhNode {
             |index| self.objects[index].random_direction(context, rng),
         )
     }
+
+    fn sample_surface(&self, time: f64, rng: &mut SampleRng) -> Option<SurfaceSample> {
+        sample_uniform_surface(self.objects.len(), rng, |index, rng| {
+            self.objects[index].sample_surface(time, rng)
+        })
+    }
+
+    fn surface_pdf(&self, point: Point, time: f64) -> f64 {
+        if self.objects.is_empty() {
+            return 0.0;
+        }
+
+        let weight = reciprocal_count(self.objects.len());
+        self.objects
+            .iter()
+            .map(|object| weight * object.surface_pdf(point, time))
+            .sum()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -774,6 +902,30 @@ impl ObjectBvh {
             })
     }
 
+    fn transmittance(
+        &self,
+        objects: &[Box<dyn Hittable>],
+        ray: &Ray,
+        ray_t: Interval,
+        rng: &mut SampleRng,
+    ) -> LinearColor {
+        let mut transmittance = LinearColor::new(1.0, 1.0, 1.0);
+        self.bvh
+            .visit_leaves(ray_t, RayTraversal::new(ray), |indices| {
+                transmittance = component_mul(
+                    transmittance,
+                    transmittance_through(
+                        indices.iter().map(|index| &*objects[*index]),
+                        ray,
+                        ray_t,
+                        rng,
+                    ),
+                );
+                transmittance != LinearColor::default()
+            });
+        transmittance
+    }
+
     fn traversal_stats(
         &self,
         objects: &[Box<dyn Hittable>],
@@ -824,6 +976,26 @@ impl ObjectBvh {
     }
 }
 
+/// Multiplies the transmittance of `objects`, stopping early once no light gets through.
+fn transmittance_through<'a>(
+    objects: impl IntoIterator<Item = &'a dyn Hittable>,
+    ray: &Ray,
+    ray_t: Interval,
+    rng: &mut SampleRng,
+) -> LinearColor {
+    let mut transmittance = LinearColor::new(1.0, 1.0, 1.0);
+    for object in objects {
+        transmittance = component_mul(
+            transmittance,
+            object.transmittance_with_rng(ray, ray_t, rng),
+        );
+        if transmittance == LinearColor::default() {
+            break;
+        }
+    }
+    transmittance
+}
+
 fn hit_object_indices<'a>(
     objects: &'a [Box<dyn Hittable>],
     indices: impl IntoIterator<Item = usize>,
@@ -886,10 +1058,16 @@ pub struct RayPrimitive {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
-struct GeometrySamplingTarget {
+pub(super) struct GeometrySamplingTarget {
     geometry: RayGeometry,
 }
 
+impl GeometrySamplingTarget {
+    pub(super) const fn new(geometry: RayGeometry) -> Self {
+        Self { geometry }
+    }
+}
+
 impl Hittable for GeometrySamplingTarget {
     fn hit_with_rng(
         &self,
@@ -911,6 +1089,14 @@ impl Hittable for GeometrySamplingTarget {
     fn random_direction(&self, context: PdfContext, rng: &mut SampleRng) -> Vector {
         self.geometry.random_direction(context, rng)
     }
+
+    fn sample_surface(&self, time: f64, rng: &mut SampleRng) -> Option<SurfaceSample> {
+        self.geometry.sample_surface(time, rng)
+    }
+
+    fn surface_pdf(&self, point: Point, time: f64) -> f64 {
+        self.geometry.surface_pdf(point, time)
+    }
 }
 
 /// Ergonomic builder for [`RayScene`] that resolves primitives through named materials.
@@ -1040,6 +1226,80 @@ impl RaySceneBuilder {
         self
     }
 
+    /// Adds a disk facing along `normal` with a named material.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `material` has not been registered.
+    #[must_use]
+    pub fn disk(
+        mut self,
+        center: Point,
+        normal: Vector,
+        radius: f64,
+        material: impl AsRef<str>,
+    ) -> Self {
+        let material = self.material_id(material.as_ref());
+        self.scene.add_disk(center, normal, radius, material);
+        self
+    }
+
+    /// Adds a capped cylinder with a named material.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `material` has not been registered.
+    #[must_use]
+    pub fn cylinder(
+        mut self,
+        base: Point,
+        axis: Vector,
+        radius: f64,
+        material: impl AsRef<str>,
+    ) -> Self {
+        let material = self.material_id(material.as_ref());
+        self.scene.add_cylinder(base, axis, radius, material);
+        self
+    }
+
+    /// Adds a capped cone with a named material.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `material` has not been registered.
+    #[must_use]
+    pub fn cone(
+        mut self,
+        base: Point,
+        axis: Vector,
+        radius: f64,
+        material: impl AsRef<str>,
+    ) -> Self {
+        let material = self.material_id(material.as_ref());
+        self.scene.add_cone(base, axis, radius, material);
+        self
+    }
+
+    /// Adds a torus with a named material.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `material` has not been registered.
+    #[must_use]
+    pub fn torus(
+        mut self,
+        center: Point,
+        axis: Vector,
+        major_radius: f64,
+        minor_radius: f64,
+        material: impl AsRef<str>,
+    ) -> Self {
+        let material = self.material_id(material.as_ref());
+        self.scene
+            .add_torus(center, axis, major_radius, minor_radius, material);
+        self
+    }
+
     /// Adds multiple triangles with a named material.
     ///
     /// # Panics
@@ -1329,6 +1589,60 @@ impl RayScene {
         self.add_geometries(material, triangles);
     }
 
+    /// Adds a disk facing along `normal` using an existing material table index.
+    ///
+    /// Disks are two-sided for intersection but emit only from their front face.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `material` is not a valid material id for this scene.
+    pub fn add_disk(&mut self, center: Point, normal: Vector, radius: f64, material: MaterialId) {
+        self.add_primitive(RayGeometry::disk(center, normal, radius), material);
+    }
+
+    /// Adds a capped cylinder from its base center and base-to-top axis using an existing
+    /// material table index.
+    ///
+    /// Use [`Self::add_primitive`] with a [`crate::gmath::geometry::CylinderGeometry`] for an open
+    /// tube.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `material` is not a valid material id for this scene.
+    pub fn add_cylinder(&mut self, base: Point, axis: Vector, radius: f64, material: MaterialId) {
+        self.add_primitive(RayGeometry::cylinder(base, axis, radius), material);
+    }
+
+    /// Adds a capped cone from its base center and base-to-apex axis using an existing material
+    /// table index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `material` is not a valid material id for this scene.
+    pub fn add_cone(&mut self, base: Point, axis: Vector, radius: f64, material: MaterialId) {
+        self.add_primitive(RayGeometry::cone(base, axis, radius), material);
+    }
+
+    /// Adds a torus from its center, ring axis, and ring and tube radii using an existing
+    /// material table index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `material` is not a valid material id for this scene.
+    pub fn add_torus(
+        &mut self,
+        center: Point,
+        axis: Vector,
+        major_radius: f64,
+        minor_radius: f64,
+        material: MaterialId,
+    ) {
+        self.add_primitive(
+            RayGeometry::torus(center, axis, major_radius, minor_radius),
+            material,
+        );
+    }
+
     /// Adds a material and a sphere that references it.
     pub fn add_sphere_with_material(
         &mut self,
@@ -1413,13 +1727,37 @@ impl RayScene {
         targets
     }
 
-    /// Builds a sampling target list containing primitives whose material is emissive.
+    /// Builds a light tree containing primitives whose material is emissive.
     ///
-    /// Pass this to [`PathTracer::render_with_lights`](crate::graphics::raytracing::PathTracer::render_with_lights)
+    /// Each light is weighted by its area times the luminance of its emitted radiance, falling
+    /// back to unit radiance when a textured emitter is black at its center. Pass this to
+    /// [`PathTracer::render_with_lights`](crate::graphics::raytracing::PathTracer::render_with_lights)
     /// instead of using the whole scene as a light sampler.
     #[must_use]
-    pub fn emissive_targets(&self) -> SamplingTargetList {
-        self.sampling_targets_by_material(RayMaterial::is_emissive)
+    pub fn emissive_targets(&self) -> LightTree {
+        let mut lights = LightTree::new();
+        for primitive in &self.primitives {
+            let material = &self.materials[primitive.material];
+            if !material.is_emissive() {
+                continue;
+            }
+            let area = primitive.geometry.area();
+            if !area.is_finite() || area <= 0.0 {
+                continue;
+            }
+            let center = primitive
+                .geometry
+                .bounding_box()
+                .map_or_else(Point::default, Aabb::centroid);
+            let radiance = luminance(material.representative_emission(center));
+            let radiance = if radiance.is_finite() && radiance > 0.0 {
+                radiance
+            } else {
+                1.0
+            };
+            lights.add_geometry(primitive.geometry, PI * area * radiance);
+        }
+        lights
     }
 
     /// Returns true when this scene has a built primitive BVH.
@@ -1693,6 +2031,24 @@ impl Hittable for RayScene {
             },
         )
     }
+
+    fn sample_surface(&self, time: f64, rng: &mut SampleRng) -> Option<SurfaceSample> {
+        sample_uniform_surface(self.primitives.len(), rng, |index, rng| {
+            self.primitives[index].geometry.sample_surface(time, rng)
+        })
+    }
+
+    fn surface_pdf(&self, point: Point, time: f64) -> f64 {
+        if self.primitives.is_empty() {
+            return 0.0;
+        }
+
+        let weight = reciprocal_count(self.primitives.len());
+        self.primitives
+            .iter()
+            .map(|primitive| weight * primitive.geometry.surface_pdf(point, time))
+            .sum()
+    }
 }
 
 /// Compatibility sphere-only hittable list that avoids boxed geometry dispatch in hit loops.
@@ -1786,12 +2142,44 @@ impl Hittable for SphereList {
             |index| self.spheres[index].random_direction(context, rng),
         )
     }
+
+    fn sample_surface(&self, time: f64, rng: &mut SampleRng) -> Option<SurfaceSample> {
+        sample_uniform_surface(self.spheres.len(), rng, |index, rng| {
+            self.spheres[index].sample_surface(time, rng)
+        })
+    }
+
+    fn surface_pdf(&self, point: Point, time: f64) -> f64 {
+        if self.spheres.is_empty() {
+            return 0.0;
+        }
+
+        let weight = reciprocal_count(self.spheres.len());
+        self.spheres
+            .iter()
+            .map(|sphere| weight * sphere.surface_pdf(point, time))
+            .sum()
+    }
 }
 
 fn reciprocal_count(count: usize) -> f64 {
     1.0 / f64::from(u32::try_from(count).expect("scene object count should fit in u32"))
 }
 
+/// Picks one of `count` children uniformly and folds the selection probability into its sample.
+fn sample_uniform_surface(
+    count: usize,
+    rng: &mut SampleRng,
+    sample: impl FnOnce(usize, &mut SampleRng) -> Option<SurfaceSample>,
+) -> Option<SurfaceSample> {
+    let index = rng.random_index(count)?;
+    let selection_pdf = reciprocal_count(count);
+    sample(index, rng).map(|sample| SurfaceSample {
+        pdf: sample.pdf * selection_pdf,
+        ..sample
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
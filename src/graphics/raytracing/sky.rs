@@ -0,0 +1,482 @@
+//! Analytic daylight sky and sun for outdoor environment lighting.
+//!
+//! [`PhysicalSky`] evaluates the Preetham, Shirley, and Smits (1999) clear-sky model: Perez
+//! luminance and chromaticity distributions fitted against sun position and atmospheric turbidity.
+//! The sun is a disk of the real solar angular size whose radiance is the extraterrestrial sun
+//! attenuated by Rayleigh and aerosol extinction along the optical air mass, so low suns redden.
+//! Directions below the horizon see a Lambertian ground lit by the sky and sun.
+//!
+//! Wrap a sky in [`EnvironmentLight::from_physical_sky`](super::EnvironmentLight::from_physical_sky)
+//! to render it: the sky is importance-sampled from a luminance table and the sun disk is sampled
+//! analytically, so small bright suns converge without fireflies.
+
+use super::LinearColor;
+#[cfg(feature = "spectral")]
+use super::{SampledWavelength, Spectrum};
+use crate::gmath::vector::Vector;
+
+const PI: f64 = std::f64::consts::PI;
+
+/// Mean angular radius of the sun seen from Earth, in degrees.
+pub const SUN_ANGULAR_RADIUS_DEGREES: f64 = 0.2665;
+
+/// Luminance of the sun outside the atmosphere, in kcd/m².
+const EXTRATERRESTRIAL_SUN_LUMINANCE: f64 = 1.96e6;
+
+/// Representative wavelengths, in micrometers, used for the red, green, and blue sun extinction.
+const RGB_WAVELENGTHS_UM: [f64; 3] = [0.680, 0.550, 0.440];
+
+/// Zenith angle cosine floor that keeps the Perez `1 / cos(theta)` term finite at the horizon.
+const HORIZON_COSINE_FLOOR: f64 = 1.0e-3;
+
+/// Resolution of the hemisphere integration used for ground illumination.
+const GROUND_IRRADIANCE_STEPS: u32 = 32;
+
+/// Clear-sky daylight model parameterized by sun position, turbidity, and ground albedo.
+///
+/// Radiance is sky luminance in kcd/m² scaled by [`Self::intensity`]. The default intensity
+/// lights a white diffuse surface under a high sun to roughly `1.0`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PhysicalSky {
+    sun_direction: Vector,
+    turbidity: f64,
+    ground_albedo: LinearColor,
+    intensity: f64,
+    sun_angular_radius: f64,
+    perez: [[f64; 5]; 3],
+    zenith: [f64; 3],
+    sun_radiance: LinearColor,
+    ground_radiance: LinearColor,
+}
+
+impl PhysicalSky {
+    /// Default [`Self::intensity`].
+    pub const DEFAULT_INTENSITY: f64 = 0.025;
+
+    /// Creates a sky with the sun at `elevation_degrees` above the horizon.
+    ///
+    /// `azimuth_degrees` is measured around the world `+Y` axis from `+X` toward `+Z`, matching
+    /// the lat-long layout of [`EnvironmentLight`](super::EnvironmentLight). The sky starts with
+    /// turbidity `3`, a mid-gray ground albedo of `0.3`, and [`Self::DEFAULT_INTENSITY`].
+    #[must_use]
+    pub fn new(elevation_degrees: f64, azimuth_degrees: f64) -> Self {
+        let elevation = elevation_degrees.to_radians();
+        let azimuth = azimuth_degrees.to_radians();
+        Self::from_sun_direction(Vector::new(
+            elevation.cos() * azimuth.cos(),
+            elevation.sin(),
+            elevation.cos() * azimuth.sin(),
+        ))
+    }
+
+    /// Creates a sky whose sun lies along the world-space `direction`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `direction` is zero or not finite.
+    #[must_use]
+    pub fn from_sun_direction(direction: Vector) -> Self {
+        assert!(
+            direction.is_finite() && direction.length_squared() > f64::EPSILON,
+            "sun direction must be finite and non-zero"
+        );
+        let mut sky = Self {
+            sun_direction: direction.normalized(),
+            turbidity: 3.0,
+            ground_albedo: LinearColor::new(0.3, 0.3, 0.3),
+            intensity: Self::DEFAULT_INTENSITY,
+            sun_angular_radius: SUN_ANGULAR_RADIUS_DEGREES.to_radians(),
+            perez: [[0.0; 5]; 3],
+            zenith: [0.0; 3],
+            sun_radiance: LinearColor::default(),
+            ground_radiance: LinearColor::default(),
+        };
+        sky.update();
+        sky
+    }
+
+    /// Sets atmospheric turbidity, from `1` (very clear) to `10` (hazy).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `turbidity` is outside `1.0..=10.0`, where the model's fit is valid.
+    #[must_use]
+    pub fn with_turbidity(mut self, turbidity: f64) -> Self {
+        assert!(
+            (1.0..=10.0).contains(&turbidity),
+            "sky turbidity must be in 1..=10"
+        );
+        self.turbidity = turbidity;
+        self.update();
+        self
+    }
+
+    /// Sets the diffuse albedo of the ground seen below the horizon.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any channel is negative or not finite.
+    #[must_use]
+    pub fn with_ground_albedo(mut self, albedo: LinearColor) -> Self {
+        assert!(
+            [albedo.red, albedo.green, albedo.blue]
+                .iter()
+                .all(|channel| channel.is_finite() && *channel >= 0.0),
+            "ground albedo must be finite and non-negative"
+        );
+        self.ground_albedo = albedo;
+        self.update();
+        self
+    }
+
+    /// Sets the scale from luminance in kcd/m² to scene radiance.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `intensity` is negative or not finite.
+    #[must_use]
+    pub fn with_intensity(mut self, intensity: f64) -> Self {
+        assert!(
+            intensity.is_finite() && intensity >= 0.0,
+            "sky intensity must be finite and non-negative"
+        );
+        self.intensity = intensity;
+        self.update();
+        self
+    }
+
+    /// Sets the angular radius of the sun disk in degrees.
+    ///
+    /// The disk's radiance stays fixed, so a larger sun also casts proportionally more light.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `degrees` is not in `(0, 90)`.
+    #[must_use]
+    pub fn with_sun_angular_radius(mut self, degrees: f64) -> Self {
+        assert!(
+            degrees > 0.0 && degrees < 90.0,
+            "sun angular radius must be in (0, 90) degrees"
+        );
+        self.sun_angular_radius = degrees.to_radians();
+        self.update();
+        self
+    }
+
+    /// Returns the normalized direction toward the sun.
+    #[must_use]
+    pub const fn sun_direction(&self) -> Vector {
+        self.sun_direction
+    }
+
+    /// Returns the atmospheric turbidity.
+    #[must_use]
+    pub const fn turbidity(&self) -> f64 {
+        self.turbidity
+    }
+
+    /// Returns the ground albedo.
+    #[must_use]
+    pub const fn ground_albedo(&self) -> LinearColor {
+        self.ground_albedo
+    }
+
+    /// Returns the scale from luminance in kcd/m² to scene radiance.
+    #[must_use]
+    pub const fn intensity(&self) -> f64 {
+        self.intensity
+    }
+
+    /// Returns the cosine of the sun disk's angular radius.
+    #[must_use]
+    pub fn sun_cos_angular_radius(&self) -> f64 {
+        self.sun_angular_radius.cos()
+    }
+
+    /// Returns the solid angle subtended by the sun disk, in steradians.
+    #[must_use]
+    pub fn sun_solid_angle(&self) -> f64 {
+        2.0 * PI * (1.0 - self.sun_cos_angular_radius())
+    }
+
+    /// Returns the radiance of the sun disk after atmospheric extinction.
+    ///
+    /// This is black once the sun is entirely below the horizon.
+    #[must_use]
+    pub const fn sun_radiance(&self) -> LinearColor {
+        self.sun_radiance
+    }
+
+    /// Returns total radiance along `direction`: sky, sun disk, and ground.
+    #[must_use]
+    pub fn radiance(&self, direction: Vector) -> LinearColor {
+        let direction = direction.normalized();
+        let sky = self.sky_radiance(direction);
+        if self.is_sun_direction(direction) {
+            sky + self.sun_radiance
+        } else {
+            sky
+        }
+    }
+
+    /// Returns sky and ground radiance along `direction`, excluding the sun disk.
+    #[must_use]
+    pub fn sky_radiance(&self, direction: Vector) -> LinearColor {
+        let direction = direction.normalized();
+        if direction.y() < 0.0 {
+            return self.ground_radiance;
+        }
+        let cos_theta = direction.y().max(HORIZON_COSINE_FLOOR);
+        let cos_gamma = direction.dot(self.sun_direction).clamp(-1.0, 1.0);
+        let ratio = |coefficients: [f64; 5]| {
+            perez(coefficients, cos_theta, cos_gamma) / perez(coefficients, 1.0, self.sun_cos())
+        };
+        let luminance = self.zenith[0] * ratio(self.perez[0]);
+        let x = self.zenith[1] * ratio(self.perez[1]);
+        let y = self.zenith[2] * ratio(self.perez[2]);
+        xyy_to_linear_srgb(x, y, luminance) * self.intensity
+    }
+
+    /// Returns sampled-wavelength radiance along `direction`.
+    ///
+    /// The sun disk is evaluated per wavelength from its extinction spectrum, so sunsets keep
+    /// their dispersion in spectral renders. Sky and ground radiance are lifted from RGB through
+    /// [`Spectrum::from_linear_rgb`].
+    #[cfg(feature = "spectral")]
+    #[must_use]
+    pub fn spectral_radiance(&self, direction: Vector, wavelength: SampledWavelength) -> f64 {
+        let direction = direction.normalized();
+        let sky = Spectrum::from_linear_rgb(self.sky_radiance(direction)).sample(wavelength);
+        if self.is_sun_direction(direction) {
+            sky + self.sun_luminance()
+                * self.intensity
+                * sun_transmittance(
+                    self.sun_direction.y(),
+                    self.turbidity,
+                    wavelength.wavelength_nm() / 1000.0,
+                )
+        } else {
+            sky
+        }
+    }
+
+    /// Returns whether `direction` lands on the visible part of the sun disk.
+    pub(crate) fn is_sun_direction(&self, direction: Vector) -> bool {
+        direction.y() >= 0.0
+            && direction.normalized().dot(self.sun_direction) >= self.sun_cos_angular_radius()
+    }
+
+    fn sun_cos(&self) -> f64 {
+        self.sun_direction.y().clamp(HORIZON_COSINE_FLOOR, 1.0)
+    }
+
+    /// Extraterrestrial sun luminance, or zero once the disk has fully set.
+    fn sun_luminance(&self) -> f64 {
+        if self.sun_direction.y() < -self.sun_angular_radius.sin() {
+            0.0
+        } else {
+            EXTRATERRESTRIAL_SUN_LUMINANCE
+        }
+    }
+
+    fn update(&mut self) {
+        let turbidity = self.turbidity;
+        // The Preetham fit is only defined for suns at or above the horizon.
+        let theta_sun = self.sun_cos().acos();
+        self.perez = [
+            [
+                0.1787 * turbidity - 1.4630,
+                -0.3554 * turbidity + 0.4275,
+                -0.0227 * turbidity + 5.3251,
+                0.1206 * turbidity - 2.5771,
+                -0.0670 * turbidity + 0.3703,
+            ],
+            [
+                -0.0193 * turbidity - 0.2592,
+                -0.0665 * turbidity + 0.0008,
+                -0.0004 * turbidity + 0.2125,
+                -0.0641 * turbidity - 0.8989,
+                -0.0033 * turbidity + 0.0452,
+            ],
+            [
+                -0.0167 * turbidity - 0.2608,
+                -0.0950 * turbidity + 0.0092,
+                -0.0079 * turbidity + 0.2102,
+                -0.0441 * turbidity - 1.6537,
+                -0.0109 * turbidity + 0.0529,
+            ],
+        ];
+        self.zenith = zenith_xyy(turbidity, theta_sun);
+
+        let sun_scale = self.sun_luminance() * self.intensity;
+        let [red, green, blue] = RGB_WAVELENGTHS_UM
+            .map(|wavelength| sun_transmittance(self.sun_direction.y(), turbidity, wavelength));
+        self.sun_radiance = LinearColor::new(red, green, blue) * sun_scale;
+
+        let irradiance = self.horizontal_irradiance();
+        self.ground_radiance = LinearColor::new(
+            self.ground_albedo.red * irradiance.red,
+            self.ground_albedo.green * irradiance.green,
+            self.ground_albedo.blue * irradiance.blue,
+        ) * (1.0 / PI);
+    }
+
+    /// Integrates sky and sun irradiance on an upward-facing surface.
+    fn horizontal_irradiance(&self) -> LinearColor {
+        let steps = f64::from(GROUND_IRRADIANCE_STEPS);
+        let mut sky = LinearColor::default();
+        // Stratify uniformly in cos(theta)^2 so each cell has equal cosine-weighted solid angle.
+        for row in 0..GROUND_IRRADIANCE_STEPS {
+            let cos_theta = ((f64::from(row) + 0.5) / steps).sqrt();
+            let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+            for column in 0..GROUND_IRRADIANCE_STEPS * 2 {
+                let phi = 2.0 * PI * (f64::from(column) + 0.5) / (steps * 2.0);
+                sky += self.sky_radiance(Vector::new(
+                    sin_theta * phi.cos(),
+                    cos_theta,
+                    sin_theta * phi.sin(),
+                ));
+            }
+        }
+        let sky = sky * (PI / (steps * steps * 2.0));
+        let sun = self.sun_radiance * (self.sun_solid_angle() * self.sun_direction.y().max(0.0));
+        sky + sun
+    }
+}
+
+/// Perez all-weather distribution `(1 + A e^(B / cos θ)) (1 + C e^(D γ) + E cos² γ)`.
+#[allow(clippy::many_single_char_names)]
+fn perez([a, b, c, d, e]: [f64; 5], cos_theta: f64, cos_gamma: f64) -> f64 {
+    let gamma = cos_gamma.acos();
+    (1.0 + a * (b / cos_theta).exp()) * (1.0 + c * (d * gamma).exp() + e * cos_gamma * cos_gamma)
+}
+
+/// Zenith luminance (kcd/m²) and chromaticity for a sun at zenith angle `theta_sun`.
+fn zenith_xyy(turbidity: f64, theta_sun: f64) -> [f64; 3] {
+    let chi = (4.0 / 9.0 - turbidity / 120.0) * (PI - 2.0 * theta_sun);
+    let luminance =
+        ((4.0453 * turbidity - 4.9710) * chi.tan() - 0.2155 * turbidity + 2.4192).max(0.0);
+    let theta = [theta_sun.powi(3), theta_sun.powi(2), theta_sun, 1.0];
+    let turbidity = [turbidity * turbidity, turbidity, 1.0];
+    let chromaticity = |table: [[f64; 4]; 3]| {
+        table
+            .iter()
+            .zip(turbidity)
+            .map(|(row, weight)| weight * row.iter().zip(theta).map(|(c, t)| c * t).sum::<f64>())
+            .sum::<f64>()
+    };
+    let x = chromaticity([
+        [0.00166, -0.00375, 0.00209, 0.0],
+        [-0.02903, 0.06377, -0.03202, 0.00394],
+        [0.11693, -0.21196, 0.06052, 0.25886],
+    ]);
+    let y = chromaticity([
+        [0.00275, -0.00610, 0.00317, 0.0],
+        [-0.04214, 0.08970, -0.04153, 0.00516],
+        [0.15346, -0.26756, 0.06670, 0.26688],
+    ]);
+    [luminance, x, y]
+}
+
+/// Fraction of extraterrestrial sunlight at `wavelength_um` surviving Rayleigh and aerosol
+/// extinction for a sun whose direction has vertical component `sun_y`.
+fn sun_transmittance(sun_y: f64, turbidity: f64, wavelength_um: f64) -> f64 {
+    // Kasten's relative optical air mass, which stays finite slightly below the horizon.
+    let zenith_degrees = sun_y
+        .clamp(-1.0, 1.0)
+        .acos()
+        .to_degrees()
+        .min(93.885 - 1.0e-3);
+    let air_mass =
+        1.0 / (zenith_degrees.to_radians().cos() + 0.15 * (93.885 - zenith_degrees).powf(-1.253));
+    let rayleigh = 0.008_735 * wavelength_um.powf(-4.08);
+    // Angstrom turbidity with wavelength exponent 1.3.
+    let beta = (0.046_083_658_220_5 * turbidity - 0.045_860_259_285_2).max(0.0);
+    let aerosol = beta * wavelength_um.powf(-1.3);
+    (-(rayleigh + aerosol) * air_mass).exp()
+}
+
+fn xyy_to_linear_srgb(x: f64, y: f64, luminance: f64) -> LinearColor {
+    if y <= f64::EPSILON || luminance <= 0.0 {
+        return LinearColor::default();
+    }
+    let big_x = x / y * luminance;
+    let big_z = (1.0 - x - y) / y * luminance;
+    LinearColor::new(
+        (3.240_454_2 * big_x - 1.537_138_5 * luminance - 0.498_531_4 * big_z).max(0.0),
+        (-0.969_266_0 * big_x + 1.876_010_8 * luminance + 0.041_556_0 * big_z).max(0.0),
+        (0.055_643_4 * big_x - 0.204_025_9 * luminance + 1.057_225_2 * big_z).max(0.0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn luminance(color: LinearColor) -> f64 {
+        0.2126 * color.red + 0.7152 * color.green + 0.0722 * color.blue
+    }
+
+    #[test]
+    fn sky_is_blue_overhead_and_brightest_near_the_sun() {
+        let sky = PhysicalSky::new(45.0, 0.0);
+        let zenith = sky.sky_radiance(Vector::new(0.0, 1.0, 0.0));
+        let near_sun = sky.sky_radiance(Vector::new(1.0, 0.9, 0.0));
+        let away = sky.sky_radiance(Vector::new(-1.0, 0.9, 0.0));
+
+        assert!(zenith.blue > zenith.red, "{zenith:?}");
+        assert!(luminance(near_sun) > luminance(away));
+        assert!(sky.radiance(sky.sun_direction()).green > 1000.0 * luminance(zenith));
+    }
+
+    #[test]
+    fn low_sun_is_redder_and_dimmer_than_high_sun() {
+        let high = PhysicalSky::new(80.0, 0.0).sun_radiance();
+        let low = PhysicalSky::new(3.0, 0.0).sun_radiance();
+
+        assert!(low.red / low.blue > 2.0 * high.red / high.blue);
+        assert!(luminance(low) < luminance(high));
+        assert_eq!(
+            PhysicalSky::new(-5.0, 0.0).sun_radiance(),
+            LinearColor::default()
+        );
+    }
+
+    #[test]
+    fn default_intensity_lights_a_white_ground_to_about_one() {
+        let sky = PhysicalSky::new(75.0, 30.0).with_ground_albedo(LinearColor::new(1.0, 1.0, 1.0));
+        let ground = luminance(sky.radiance(Vector::new(0.0, -1.0, 0.0)));
+
+        assert!((0.5..2.0).contains(&ground), "{ground}");
+    }
+
+    #[test]
+    fn sun_disk_has_real_solar_solid_angle() {
+        let sky = PhysicalSky::new(30.0, 120.0);
+        let edge = sky.sun_angular_radius * 0.9;
+        let axis = sky.sun_direction();
+        let tangent = Vector::new(0.0, 1.0, 0.0).cross(axis).normalized();
+        let inside = axis * edge.cos() + tangent * edge.sin();
+        let outside = axis * (edge * 1.3).cos() + tangent * (edge * 1.3).sin();
+
+        assert!((sky.sun_solid_angle() - 6.8e-5).abs() < 1.0e-6);
+        assert!(sky.is_sun_direction(inside));
+        assert!(!sky.is_sun_direction(outside));
+    }
+
+    #[test]
+    #[should_panic(expected = "sky turbidity must be in 1..=10")]
+    fn turbidity_outside_model_range_is_rejected() {
+        let _ = PhysicalSky::new(45.0, 0.0).with_turbidity(12.0);
+    }
+
+    #[cfg(feature = "spectral")]
+    #[test]
+    fn spectral_sun_is_attenuated_more_at_short_wavelengths() {
+        let sky = PhysicalSky::new(5.0, 0.0);
+        let blue = sky.spectral_radiance(sky.sun_direction(), SampledWavelength::new(440.0, 1.0));
+        let red = sky.spectral_radiance(sky.sun_direction(), SampledWavelength::new(680.0, 1.0));
+
+        assert!(red > 2.0 * blue, "{red} vs {blue}");
+    }
+}
@@ -10,6 +10,7 @@ use crate::{
     gmath::random::SampleRng,
     gmath::vector::Vector,
     graphics::{
+        codec::{ExrCompression, ExrError, ExrImage, ExrPixelType},
         colors::LinearRgb,
         display::{Canvas, HdrImage, ToneMap},
     },
@@ -611,6 +612,48 @@ impl SpectralImage {
         self.to_hdr_image().save_pfm(file_name)
     }
 
+    /// Builds an `OpenEXR` image with the linear output as the default RGB layer.
+    ///
+    /// Polarized renders also get float `stokes.I`, `stokes.Q`, `stokes.U`, and `stokes.V`
+    /// channels.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if a sample buffer does not match `width * height`.
+    pub fn to_exr_image(&self, pixel_type: ExrPixelType) -> Result<ExrImage, ExrError> {
+        let mut image = ExrImage::new(self.width, self.height);
+        image.push_rgb_layer("", &self.linear_rgb, pixel_type)?;
+        if let Some(polarization) = &self.polarization {
+            #[allow(clippy::cast_possible_truncation)]
+            let component = |value: fn(&StokesVector) -> f64| -> Vec<f32> {
+                polarization
+                    .iter()
+                    .map(|stokes| value(stokes) as f32)
+                    .collect()
+            };
+            for (name, samples) in [
+                ("I", component(|stokes| stokes.i)),
+                ("Q", component(|stokes| stokes.q)),
+                ("U", component(|stokes| stokes.u)),
+                ("V", component(|stokes| stokes.v)),
+            ] {
+                image.push_channel(format!("stokes.{name}"), ExrPixelType::Float, samples)?;
+            }
+        }
+        Ok(image)
+    }
+
+    /// Saves the output as a ZIP-compressed half-float `OpenEXR` file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if a sample buffer does not match the image size or the underlying I/O fails.
+    pub fn save_exr(&self, file_name: &str) -> std::io::Result<()> {
+        self.to_exr_image(ExrPixelType::Half)?
+            .save(file_name, ExrCompression::Zip)?;
+        Ok(())
+    }
+
     /// Returns the reconstructed linear sample at `(x, y)`.
     ///
     /// # Panics
@@ -1426,4 +1469,27 @@ mod tests {
             Some(StokesVector::new(1.0, 0.25, 0.0, 0.0))
         );
     }
+
+    #[test]
+    fn spectral_image_exports_stokes_channels_to_exr() {
+        let image = SpectralImage::new_with_polarization(
+            1,
+            1,
+            vec![LinearColor::new(0.25, 0.25, 0.25)],
+            vec![StokesVector::new(1.0, 0.25, 0.0, -0.5)],
+        );
+
+        let exr = image
+            .to_exr_image(ExrPixelType::Half)
+            .expect("buffers match the image size");
+
+        assert_eq!(exr.layers(), vec!["", "stokes"]);
+        let q = exr.channel("stokes.Q").expect("stokes Q channel");
+        assert_eq!(q.pixel_type(), ExrPixelType::Float);
+        assert_eq!(q.samples()[0].to_bits(), 0.25_f32.to_bits());
+        assert_eq!(
+            exr.channel("stokes.V").expect("stokes V channel").samples()[0].to_bits(),
+            (-0.5_f32).to_bits()
+        );
+    }
 }
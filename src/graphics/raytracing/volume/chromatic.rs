@@ -0,0 +1,691 @@
+use super::super::{
+    Aabb, BsdfFlags, BsdfSample, Dielectric, HitRecord, Hittable, INFINITY, Interval, LinearColor,
+    Material, MaterialRef, ScatterRecord, component_mul,
+    material::{HenyeyGreenstein, Isotropic},
+};
+#[cfg(feature = "spectral")]
+use super::super::{
+    MeasuredSpectrum, MuellerMatrix, PolarizationFrame, SampledWavelength, Spectrum,
+};
+use super::medium::{boundary_interval, medium_hit_record};
+use crate::gmath::{random::SampleRng, ray::Ray};
+use std::{fmt, sync::Arc};
+
+/// Per-channel absorption and scattering coefficients of a homogeneous medium.
+///
+/// Coefficients are in inverse world units, like the density of a
+/// [`ConstantMedium`](super::ConstantMedium). Under the `spectral` feature, measured spectra can
+/// replace either coefficient for sampled-wavelength transport while the RGB renderer keeps using
+/// their reconstructed linear RGB.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MediumCoefficients {
+    absorption: LinearColor,
+    scattering: LinearColor,
+    #[cfg(feature = "spectral")]
+    absorption_spectrum: Option<MeasuredSpectrum>,
+    #[cfg(feature = "spectral")]
+    scattering_spectrum: Option<MeasuredSpectrum>,
+}
+
+impl MediumCoefficients {
+    /// Creates coefficients from per-channel absorption and scattering.
+    ///
+    /// Negative and non-finite channels are clamped to zero.
+    #[must_use]
+    pub fn new(absorption: LinearColor, scattering: LinearColor) -> Self {
+        Self {
+            absorption: non_negative_color(absorption),
+            scattering: non_negative_color(scattering),
+            #[cfg(feature = "spectral")]
+            absorption_spectrum: None,
+            #[cfg(feature = "spectral")]
+            scattering_spectrum: None,
+        }
+    }
+
+    /// Creates a purely absorbing medium.
+    #[must_use]
+    pub fn absorbing(absorption: LinearColor) -> Self {
+        Self::new(absorption, LinearColor::default())
+    }
+
+    /// Creates a purely absorbing medium that tints white light to `color` over `distance`.
+    ///
+    /// This is the usual way to dial in a liquid: pick the color a glass of it should have at a
+    /// typical thickness. Channels at or above one are left clear.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `distance` is not positive and finite.
+    #[must_use]
+    pub fn from_transmittance(color: LinearColor, distance: f64) -> Self {
+        assert!(
+            distance.is_finite() && distance > 0.0,
+            "transmittance distance must be positive and finite"
+        );
+        let absorption = |channel: f64| {
+            if channel >= 1.0 {
+                0.0
+            } else {
+                -channel.max(f64::MIN_POSITIVE).ln() / distance
+            }
+        };
+        Self::absorbing(LinearColor::new(
+            absorption(color.red),
+            absorption(color.green),
+            absorption(color.blue),
+        ))
+    }
+
+    /// Uses measured absorption for sampled-wavelength transport.
+    ///
+    /// The RGB absorption becomes the spectrum's reconstructed linear RGB.
+    #[cfg(feature = "spectral")]
+    #[must_use]
+    pub fn with_absorption_spectrum(mut self, absorption: MeasuredSpectrum) -> Self {
+        self.absorption = non_negative_color(absorption.to_linear_rgb());
+        self.absorption_spectrum = Some(absorption);
+        self
+    }
+
+    /// Uses measured scattering for sampled-wavelength transport.
+    ///
+    /// The RGB scattering becomes the spectrum's reconstructed linear RGB, which also drives
+    /// collision sampling.
+    #[cfg(feature = "spectral")]
+    #[must_use]
+    pub fn with_scattering_spectrum(mut self, scattering: MeasuredSpectrum) -> Self {
+        self.scattering = non_negative_color(scattering.to_linear_rgb());
+        self.scattering_spectrum = Some(scattering);
+        self
+    }
+
+    /// Returns the per-channel absorption coefficient.
+    #[must_use]
+    pub const fn absorption(&self) -> LinearColor {
+        self.absorption
+    }
+
+    /// Returns the per-channel scattering coefficient.
+    #[must_use]
+    pub const fn scattering(&self) -> LinearColor {
+        self.scattering
+    }
+
+    /// Returns the per-channel extinction coefficient, absorption plus scattering.
+    #[must_use]
+    pub fn extinction(&self) -> LinearColor {
+        self.absorption + self.scattering
+    }
+
+    /// Returns the per-channel single-scattering albedo, or black where the medium is clear.
+    #[must_use]
+    pub fn albedo(&self) -> LinearColor {
+        let extinction = channels(self.extinction());
+        let scattering = channels(self.scattering);
+        color(std::array::from_fn(|channel| {
+            if extinction[channel] > 0.0 {
+                scattering[channel] / extinction[channel]
+            } else {
+                0.0
+            }
+        }))
+    }
+
+    /// Returns the Beer–Lambert transmittance over `distance`, counting both absorption and
+    /// out-scattering.
+    #[must_use]
+    pub fn transmittance(&self, distance: f64) -> LinearColor {
+        color(channels(self.extinction()).map(|extinction| (-extinction * distance).exp()))
+    }
+
+    /// Returns the transmittance over `distance` due to absorption alone.
+    #[must_use]
+    pub fn absorption_transmittance(&self, distance: f64) -> LinearColor {
+        color(channels(self.absorption).map(|absorption| (-absorption * distance).exp()))
+    }
+
+    /// Returns the absorption coefficient at one sampled wavelength.
+    #[cfg(feature = "spectral")]
+    #[must_use]
+    pub fn absorption_at(&self, wavelength: SampledWavelength) -> f64 {
+        spectral_coefficient(
+            self.absorption_spectrum.as_ref(),
+            self.absorption,
+            wavelength,
+        )
+    }
+
+    /// Returns the scattering coefficient at one sampled wavelength.
+    #[cfg(feature = "spectral")]
+    #[must_use]
+    pub fn scattering_at(&self, wavelength: SampledWavelength) -> f64 {
+        spectral_coefficient(
+            self.scattering_spectrum.as_ref(),
+            self.scattering,
+            wavelength,
+        )
+    }
+
+    /// Returns the extinction coefficient at one sampled wavelength.
+    #[cfg(feature = "spectral")]
+    #[must_use]
+    pub fn extinction_at(&self, wavelength: SampledWavelength) -> f64 {
+        self.absorption_at(wavelength) + self.scattering_at(wavelength)
+    }
+
+    /// Returns the absorption-only transmittance over `distance` at one sampled wavelength.
+    #[cfg(feature = "spectral")]
+    #[must_use]
+    pub fn absorption_transmittance_at(&self, distance: f64, wavelength: SampledWavelength) -> f64 {
+        (-self.absorption_at(wavelength) * distance).exp()
+    }
+
+    /// Samples a collision distance with one-sample MIS over the RGB scattering coefficients.
+    ///
+    /// A hero channel is picked uniformly and the distance is drawn from its exponential, so the
+    /// combined density is the average of the three. Clear channels never collide.
+    fn sample_collision_distance(&self, rng: &mut SampleRng) -> f64 {
+        let hero = rng.random_double() * 3.0;
+        let [red, green, blue] = channels(self.scattering);
+        let scattering = if hero < 1.0 {
+            red
+        } else if hero < 2.0 {
+            green
+        } else {
+            blue
+        };
+        if scattering <= 0.0 {
+            return INFINITY;
+        }
+        -rng.random_double().max(f64::MIN_POSITIVE).ln() / scattering
+    }
+
+    /// Returns the combined collision density at `distance`, scaled by `exp(shift * distance)`.
+    fn shifted_collision_density(&self, distance: f64, shift: f64) -> f64 {
+        channels(self.scattering)
+            .into_iter()
+            .map(|scattering| scattering * (-(scattering - shift) * distance).exp())
+            .sum::<f64>()
+            / 3.0
+    }
+
+    /// Returns the probability of no collision before `distance`, scaled by
+    /// `exp(shift * distance)`.
+    fn shifted_escape_probability(&self, distance: f64, shift: f64) -> f64 {
+        channels(self.scattering)
+            .into_iter()
+            .map(|scattering| (-(scattering - shift) * distance).exp())
+            .sum::<f64>()
+            / 3.0
+    }
+
+    /// Smallest scattering coefficient, optionally skipping clear channels.
+    ///
+    /// Both weights divide a per-channel transmittance by an average of exponentials; factoring
+    /// this common rate out of numerator and denominator keeps long segments from underflowing.
+    fn shift(&self, skip_clear: bool) -> f64 {
+        channels(self.scattering)
+            .into_iter()
+            .filter(|scattering| !skip_clear || *scattering > 0.0)
+            .fold(INFINITY, f64::min)
+    }
+
+    /// Path weight of a sampled collision `distance` past the start of a segment.
+    fn collision_weight(&self, distance: f64) -> LinearColor {
+        let shift = self.shift(true);
+        if !shift.is_finite() {
+            return LinearColor::default();
+        }
+        let density = self.shifted_collision_density(distance, shift);
+        let extinction = channels(self.extinction());
+        let scattering = channels(self.scattering);
+        color(std::array::from_fn(|channel| {
+            scattering[channel] * (-(extinction[channel] - shift) * distance).exp() / density
+        }))
+    }
+
+    /// Path weight of a segment of length `distance` that ends without a sampled collision.
+    fn escape_weight(&self, distance: f64) -> LinearColor {
+        let shift = self.shift(false);
+        let probability = self.shifted_escape_probability(distance, shift);
+        color(
+            channels(self.extinction())
+                .map(|extinction| (-(extinction - shift) * distance).exp() / probability),
+        )
+    }
+
+    #[cfg(feature = "spectral")]
+    fn collision_weight_at(&self, distance: f64, wavelength: SampledWavelength) -> f64 {
+        let shift = self.shift(true);
+        if !shift.is_finite() {
+            return 0.0;
+        }
+        self.scattering_at(wavelength)
+            * (-(self.extinction_at(wavelength) - shift) * distance).exp()
+            / self.shifted_collision_density(distance, shift)
+    }
+
+    #[cfg(feature = "spectral")]
+    fn escape_weight_at(&self, distance: f64, wavelength: SampledWavelength) -> f64 {
+        let shift = self.shift(false);
+        (-(self.extinction_at(wavelength) - shift) * distance).exp()
+            / self.shifted_escape_probability(distance, shift)
+    }
+}
+
+/// A homogeneous medium with per-channel absorption and scattering inside a closed boundary.
+///
+/// The boundary is a real interface: rays cross it at path vertices, passing straight through by
+/// default or refracting when [`Self::with_interface`] sets a dielectric. Inside, collisions are
+/// sampled with one-sample MIS over the RGB scattering coefficients and absorption is applied
+/// analytically, so deeply tinted liquids stay noise-free while scattering that differs per
+/// channel still converges in every channel. Under the `spectral` feature the same samples are
+/// reweighted by the coefficients at the path's wavelength.
+///
+/// Each segment is weighted at the vertex that ends it, measured from the ray origin, so the
+/// medium assumes rays inside start at one of its own vertices, and surfaces placed inside the
+/// medium are not attenuated on the way to them. Shadow rays cross an index-matched boundary and
+/// pick up per-channel Beer–Lambert transmittance over the segment inside; a refracting boundary
+/// blocks them like glass.
+pub struct ChromaticMedium {
+    boundary: Box<dyn Hittable>,
+    phase: ChromaticPhase,
+    interface: MediumInterface,
+    bounds: Option<Aabb>,
+}
+
+impl fmt::Debug for ChromaticMedium {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("ChromaticMedium")
+            .field("coefficients", &self.phase.coefficients)
+            .field("interface", &self.interface.dielectric)
+            .field("bounds", &self.bounds)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ChromaticMedium {
+    /// Creates an isotropically scattering medium with an index-matched boundary.
+    #[must_use]
+    pub fn new(boundary: impl Hittable + 'static, coefficients: MediumCoefficients) -> Self {
+        Self::from_box(Box::new(boundary), coefficients)
+    }
+
+    /// Creates an isotropically scattering medium from a boxed boundary.
+    #[must_use]
+    pub fn from_box(boundary: Box<dyn Hittable>, coefficients: MediumCoefficients) -> Self {
+        let bounds = boundary.bounding_box();
+        Self {
+            boundary,
+            phase: ChromaticPhase {
+                coefficients: coefficients.clone(),
+                phase: Arc::new(Isotropic::new(LinearColor::new(1.0, 1.0, 1.0))),
+            },
+            interface: MediumInterface {
+                coefficients,
+                dielectric: None,
+            },
+            bounds,
+        }
+    }
+
+    /// Makes the boundary refract and reflect like `dielectric`, for liquids and tinted glass.
+    ///
+    /// Any interior medium already set on `dielectric` is dropped in favor of this medium.
+    #[must_use]
+    pub fn with_interface(mut self, dielectric: Dielectric) -> Self {
+        self.interface.dielectric = Some(dielectric.without_interior_medium());
+        self
+    }
+
+    /// Scatters with a Henyey-Greenstein phase function of asymmetry `g` instead of isotropically.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `g` is not finite or not in `(-1, 1)`.
+    #[must_use]
+    pub fn with_phase_asymmetry(mut self, g: f64) -> Self {
+        self.phase.phase = Arc::new(HenyeyGreenstein::new(LinearColor::new(1.0, 1.0, 1.0), g));
+        self
+    }
+
+    /// Returns the medium coefficients.
+    #[must_use]
+    pub const fn coefficients(&self) -> &MediumCoefficients {
+        &self.phase.coefficients
+    }
+
+    /// Returns the refracting interface, or `None` when the boundary is index-matched.
+    #[must_use]
+    pub const fn interface(&self) -> Option<&Dielectric> {
+        self.interface.dielectric.as_ref()
+    }
+
+    /// Returns the medium boundary bounds.
+    #[must_use]
+    pub const fn bounds(&self) -> Option<Aabb> {
+        self.bounds
+    }
+}
+
+impl Hittable for ChromaticMedium {
+    fn hit_with_rng(
+        &self,
+        ray: &Ray,
+        ray_t: Interval,
+        rng: &mut SampleRng,
+    ) -> Option<HitRecord<'_>> {
+        let surface = self
+            .boundary
+            .hit_with_rng(ray, Interval::new(ray_t.min, INFINITY), rng)?;
+
+        // Back faces mean the ray starts inside, so a collision may come before the boundary.
+        if !surface.front_face {
+            let ray_length = ray.direction().length();
+            let distance = self.phase.coefficients.sample_collision_distance(rng);
+            if ray_length > f64::EPSILON && distance.is_finite() {
+                let t = (distance / ray_length).max(ray_t.min);
+                if t < surface.t && t <= ray_t.max {
+                    return Some(medium_hit_record(ray, t, &self.phase));
+                }
+            }
+        }
+
+        (surface.t <= ray_t.max).then_some(HitRecord {
+            material: &self.interface,
+            ..surface
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bounds
+    }
+
+    fn transmittance_with_rng(
+        &self,
+        ray: &Ray,
+        ray_t: Interval,
+        rng: &mut SampleRng,
+    ) -> LinearColor {
+        if self.interface.dielectric.is_some() {
+            return if self.hit_with_rng(ray, ray_t, rng).is_some() {
+                LinearColor::default()
+            } else {
+                LinearColor::new(1.0, 1.0, 1.0)
+            };
+        }
+        boundary_interval(self.boundary.as_ref(), ray, ray_t, rng).map_or(
+            LinearColor::new(1.0, 1.0, 1.0),
+            |interval| {
+                self.phase
+                    .coefficients
+                    .transmittance(interval.distance_inside_boundary)
+            },
+        )
+    }
+}
+
+/// Phase function of a [`ChromaticMedium`] collision, weighted by the sampled segment.
+struct ChromaticPhase {
+    coefficients: MediumCoefficients,
+    phase: MaterialRef,
+}
+
+impl ChromaticPhase {
+    fn weight(&self, ray_in: &Ray, hit: &HitRecord<'_>) -> LinearColor {
+        self.coefficients
+            .collision_weight(hit.t * ray_in.direction().length())
+    }
+}
+
+impl Material for ChromaticPhase {
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        hit: &HitRecord<'_>,
+        rng: &mut SampleRng,
+    ) -> Option<ScatterRecord> {
+        let weight = self.weight(ray_in, hit);
+        self.phase
+            .scatter(ray_in, hit, rng)
+            .map(|record| weighted_record(record, |attenuation| component_mul(attenuation, weight)))
+    }
+
+    #[cfg(feature = "spectral")]
+    fn scatter_spectral(
+        &self,
+        ray_in: &Ray,
+        hit: &HitRecord<'_>,
+        wavelength: SampledWavelength,
+        rng: &mut SampleRng,
+    ) -> Option<ScatterRecord> {
+        let weight = self
+            .coefficients
+            .collision_weight_at(hit.t * ray_in.direction().length(), wavelength);
+        self.phase
+            .scatter_spectral(ray_in, hit, wavelength, rng)
+            .map(|record| weighted_record(record, |attenuation| attenuation * weight))
+    }
+
+    fn scattering_pdf(&self, ray_in: &Ray, hit: &HitRecord<'_>, scattered: &Ray) -> f64 {
+        self.phase.scattering_pdf(ray_in, hit, scattered)
+    }
+
+    fn bsdf_flags(&self, hit: &HitRecord<'_>) -> BsdfFlags {
+        self.phase.bsdf_flags(hit)
+    }
+
+    fn eval_bsdf(&self, ray_in: &Ray, hit: &HitRecord<'_>, scattered: &Ray) -> LinearColor {
+        component_mul(
+            self.phase.eval_bsdf(ray_in, hit, scattered),
+            self.weight(ray_in, hit),
+        )
+    }
+
+    fn bsdf_pdf(&self, ray_in: &Ray, hit: &HitRecord<'_>, scattered: &Ray) -> f64 {
+        self.phase.bsdf_pdf(ray_in, hit, scattered)
+    }
+
+    fn sample_bsdf(
+        &self,
+        ray_in: &Ray,
+        hit: &HitRecord<'_>,
+        rng: &mut SampleRng,
+    ) -> Option<BsdfSample> {
+        let mut sample = self.phase.sample_bsdf(ray_in, hit, rng)?;
+        sample.value = component_mul(sample.value, self.weight(ray_in, hit));
+        Some(sample)
+    }
+
+    fn denoise_albedo(&self, _hit: &HitRecord<'_>) -> LinearColor {
+        self.coefficients.albedo()
+    }
+
+    #[cfg(feature = "spectral")]
+    fn polarized_scatter_mueller(
+        &self,
+        ray_in: &Ray,
+        hit: &HitRecord<'_>,
+        scattered: &Ray,
+        incoming_frame: PolarizationFrame,
+        outgoing_frame: PolarizationFrame,
+        wavelength: SampledWavelength,
+    ) -> MuellerMatrix {
+        self.phase.polarized_scatter_mueller(
+            ray_in,
+            hit,
+            scattered,
+            incoming_frame,
+            outgoing_frame,
+            wavelength,
+        )
+    }
+}
+
+/// Boundary of a [`ChromaticMedium`], weighting segments that leave the interior.
+struct MediumInterface {
+    coefficients: MediumCoefficients,
+    dielectric: Option<Dielectric>,
+}
+
+impl MediumInterface {
+    fn distance_inside(ray_in: &Ray, hit: &HitRecord<'_>) -> Option<f64> {
+        (!hit.front_face).then(|| hit.t * ray_in.direction().length())
+    }
+
+    fn weight(&self, ray_in: &Ray, hit: &HitRecord<'_>) -> LinearColor {
+        Self::distance_inside(ray_in, hit).map_or(LinearColor::new(1.0, 1.0, 1.0), |distance| {
+            self.coefficients.escape_weight(distance)
+        })
+    }
+
+    fn pass_through(ray_in: &Ray) -> BsdfSample {
+        BsdfSample {
+            direction: *ray_in.direction(),
+            value: LinearColor::new(1.0, 1.0, 1.0),
+            pdf: 1.0,
+            flags: BsdfFlags::DELTA | BsdfFlags::TRANSMISSION | BsdfFlags::MEDIUM,
+        }
+    }
+}
+
+impl Material for MediumInterface {
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        hit: &HitRecord<'_>,
+        rng: &mut SampleRng,
+    ) -> Option<ScatterRecord> {
+        self.sample_bsdf(ray_in, hit, rng)
+            .map(|sample| ScatterRecord::Specular {
+                ray: Ray::with_time(hit.point, sample.direction, ray_in.time()),
+                attenuation: sample.weight(),
+            })
+    }
+
+    #[cfg(feature = "spectral")]
+    fn scatter_spectral(
+        &self,
+        ray_in: &Ray,
+        hit: &HitRecord<'_>,
+        wavelength: SampledWavelength,
+        rng: &mut SampleRng,
+    ) -> Option<ScatterRecord> {
+        let weight = Self::distance_inside(ray_in, hit).map_or(1.0, |distance| {
+            self.coefficients.escape_weight_at(distance, wavelength)
+        });
+        let record = match &self.dielectric {
+            Some(dielectric) => dielectric.scatter_spectral(ray_in, hit, wavelength, rng)?,
+            None => ScatterRecord::Specular {
+                ray: Ray::with_time(hit.point, *ray_in.direction(), ray_in.time()),
+                attenuation: LinearColor::new(1.0, 1.0, 1.0),
+            },
+        };
+        Some(weighted_record(record, |attenuation| attenuation * weight))
+    }
+
+    fn bsdf_flags(&self, hit: &HitRecord<'_>) -> BsdfFlags {
+        self.dielectric.as_ref().map_or(
+            BsdfFlags::DELTA | BsdfFlags::TRANSMISSION | BsdfFlags::MEDIUM,
+            |dielectric| dielectric.bsdf_flags(hit),
+        )
+    }
+
+    fn sample_bsdf(
+        &self,
+        ray_in: &Ray,
+        hit: &HitRecord<'_>,
+        rng: &mut SampleRng,
+    ) -> Option<BsdfSample> {
+        let mut sample = match &self.dielectric {
+            Some(dielectric) => dielectric.sample_bsdf(ray_in, hit, rng)?,
+            None => Self::pass_through(ray_in),
+        };
+        sample.value = component_mul(sample.value, self.weight(ray_in, hit));
+        Some(sample)
+    }
+
+    fn denoise_albedo(&self, _hit: &HitRecord<'_>) -> LinearColor {
+        LinearColor::new(1.0, 1.0, 1.0)
+    }
+
+    #[cfg(feature = "spectral")]
+    fn polarized_scatter_mueller(
+        &self,
+        ray_in: &Ray,
+        hit: &HitRecord<'_>,
+        scattered: &Ray,
+        incoming_frame: PolarizationFrame,
+        outgoing_frame: PolarizationFrame,
+        wavelength: SampledWavelength,
+    ) -> MuellerMatrix {
+        self.dielectric.as_ref().map_or_else(
+            || MuellerMatrix::frame_transform(incoming_frame, outgoing_frame),
+            |dielectric| {
+                dielectric.polarized_scatter_mueller(
+                    ray_in,
+                    hit,
+                    scattered,
+                    incoming_frame,
+                    outgoing_frame,
+                    wavelength,
+                )
+            },
+        )
+    }
+}
+
+fn weighted_record(
+    record: ScatterRecord,
+    weight: impl FnOnce(LinearColor) -> LinearColor,
+) -> ScatterRecord {
+    match record {
+        ScatterRecord::Specular { ray, attenuation } => ScatterRecord::Specular {
+            ray,
+            attenuation: weight(attenuation),
+        },
+        ScatterRecord::Scattering { attenuation, pdf } => ScatterRecord::Scattering {
+            attenuation: weight(attenuation),
+            pdf,
+        },
+    }
+}
+
+#[cfg(feature = "spectral")]
+fn spectral_coefficient(
+    measured: Option<&MeasuredSpectrum>,
+    rgb: LinearColor,
+    wavelength: SampledWavelength,
+) -> f64 {
+    measured
+        .map_or_else(
+            || Spectrum::from_linear_rgb(rgb).sample(wavelength),
+            |spectrum| spectrum.sample(wavelength),
+        )
+        .max(0.0)
+}
+
+const fn channels(color: LinearColor) -> [f64; 3] {
+    [color.red, color.green, color.blue]
+}
+
+const fn color([red, green, blue]: [f64; 3]) -> LinearColor {
+    LinearColor::new(red, green, blue)
+}
+
+fn non_negative_color(color: LinearColor) -> LinearColor {
+    let channel = |value: f64| {
+        if value.is_finite() && value > 0.0 {
+            value
+        } else {
+            0.0
+        }
+    };
+    LinearColor::new(
+        channel(color.red),
+        channel(color.green),
+        channel(color.blue),
+    )
+}
@@ -0,0 +1,408 @@
+use super::super::{
+    BsdfFlags, BsdfSample, HitRecord, LinearColor, Material, MaterialRef, ScatterRecord,
+};
+#[cfg(feature = "spectral")]
+use super::super::{MuellerMatrix, PolarizationFrame, SampledWavelength, Spectrum};
+use super::field::{DensityField, DensityFieldRef};
+use crate::gmath::{
+    random::SampleRng,
+    ray::Ray,
+    vector::{Point, Vector},
+};
+use std::{fmt, sync::Arc};
+
+/// Planck's first radiation constant for spectral radiance, `2hc^2`, in W m^2 / sr.
+const PLANCK_C1: f64 = 1.191_042_972e-16;
+/// Planck's second radiation constant, `hc/k`, in m K.
+const PLANCK_C2: f64 = 1.438_776_877e-2;
+const CIE_MIN_NM: f64 = 380.0;
+const CIE_STEP_NM: f64 = 5.0;
+const CIE_SAMPLES: u32 = 81;
+const BLACKBODY_TABLE_SIZE: usize = 512;
+
+/// Emitted radiance inside a participating medium.
+///
+/// Media with an emission field add [`Self::emission`] at every sampled collision, so emission is
+/// weighted by the medium's density: dense regions glow at the full emitted radiance and thin
+/// regions glow in proportion to their opacity.
+pub trait EmissionField: Send + Sync {
+    /// Returns emitted linear RGB radiance at `point` for `time`.
+    fn emission(&self, point: Point, time: f64) -> LinearColor;
+
+    /// Returns emitted radiance at one sampled wavelength.
+    ///
+    /// The default lifts [`Self::emission`] through the RGB spectrum adapter.
+    #[cfg(feature = "spectral")]
+    fn spectral_emission(&self, point: Point, time: f64, wavelength: SampledWavelength) -> f64 {
+        Spectrum::from_linear_rgb(self.emission(point, time)).sample(wavelength)
+    }
+}
+
+impl<T: EmissionField + ?Sized> EmissionField for Arc<T> {
+    fn emission(&self, point: Point, time: f64) -> LinearColor {
+        (**self).emission(point, time)
+    }
+
+    #[cfg(feature = "spectral")]
+    fn spectral_emission(&self, point: Point, time: f64, wavelength: SampledWavelength) -> f64 {
+        (**self).spectral_emission(point, time, wavelength)
+    }
+}
+
+/// Shared emission-field handle.
+pub type EmissionFieldRef = Arc<dyn EmissionField>;
+
+/// Blackbody emission driven by a temperature field in kelvin.
+///
+/// Radiance follows Planck's law in W / (sr m^2 nm) times `scale`. RGB rendering uses the CIE 1931
+/// response of the Planck spectrum scaled so a flat unit spectrum has unit luminance; spectral
+/// rendering samples Planck's law at the path wavelength directly. Because radiance rises steeply
+/// with temperature, a 1000 K region is a dull red while a 2000 K core is orders of magnitude
+/// brighter; pick `scale` like an exposure for the hottest temperatures in the scene.
+#[derive(Clone)]
+pub struct BlackbodyEmission<D = DensityFieldRef> {
+    temperature: D,
+    scale: f64,
+    table_max_kelvin: f64,
+    table: Vec<LinearColor>,
+}
+
+impl<D> fmt::Debug for BlackbodyEmission<D> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("BlackbodyEmission")
+            .field("scale", &self.scale)
+            .field("table_max_kelvin", &self.table_max_kelvin)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<D: DensityField> BlackbodyEmission<D> {
+    /// Creates blackbody emission from a kelvin temperature field and radiance scale.
+    ///
+    /// The field's `max_density` is read as its hottest temperature and bounds the precomputed
+    /// color table; hotter samples are evaluated directly.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `scale` is negative or not finite.
+    #[must_use]
+    pub fn new(temperature: D, scale: f64) -> Self {
+        assert!(
+            scale.is_finite() && scale >= 0.0,
+            "blackbody emission scale must be non-negative and finite"
+        );
+        let table_max_kelvin = temperature.max_density().max(1.0);
+        #[allow(clippy::cast_precision_loss)]
+        let table = (0..BLACKBODY_TABLE_SIZE)
+            .map(|index| {
+                blackbody_linear_rgb(
+                    table_max_kelvin * index as f64 / (BLACKBODY_TABLE_SIZE - 1) as f64,
+                )
+            })
+            .collect();
+        Self {
+            temperature,
+            scale,
+            table_max_kelvin,
+            table,
+        }
+    }
+
+    /// Returns the temperature field.
+    #[must_use]
+    pub const fn temperature_field(&self) -> &D {
+        &self.temperature
+    }
+
+    /// Returns the radiance scale.
+    #[must_use]
+    pub const fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// Returns scaled RGB radiance for a temperature in kelvin.
+    #[must_use]
+    pub fn radiance(&self, kelvin: f64) -> LinearColor {
+        if !kelvin.is_finite() || kelvin <= 0.0 {
+            return LinearColor::default();
+        }
+        if kelvin >= self.table_max_kelvin {
+            return blackbody_linear_rgb(kelvin) * self.scale;
+        }
+        #[allow(clippy::cast_precision_loss)]
+        let position = kelvin / self.table_max_kelvin * (BLACKBODY_TABLE_SIZE - 1) as f64;
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let lower = (position.floor() as usize).min(BLACKBODY_TABLE_SIZE - 2);
+        #[allow(clippy::cast_precision_loss)]
+        let t = position - lower as f64;
+        (self.table[lower] * (1.0 - t) + self.table[lower + 1] * t) * self.scale
+    }
+
+    fn kelvin_at(&self, point: Point, time: f64) -> f64 {
+        let kelvin = self.temperature.density(point, time);
+        if kelvin.is_finite() { kelvin } else { 0.0 }
+    }
+}
+
+impl<D: DensityField> EmissionField for BlackbodyEmission<D> {
+    fn emission(&self, point: Point, time: f64) -> LinearColor {
+        self.radiance(self.kelvin_at(point, time))
+    }
+
+    #[cfg(feature = "spectral")]
+    fn spectral_emission(&self, point: Point, time: f64, wavelength: SampledWavelength) -> f64 {
+        blackbody_spectral_radiance(wavelength.wavelength_nm(), self.kelvin_at(point, time))
+            * self.scale
+    }
+}
+
+/// Emission with a constant color scaled by an arbitrary scalar field.
+///
+/// Use this for artist-driven glow, such as a fuel or heat grid mapped through one color.
+#[derive(Clone)]
+pub struct ScalarEmission<D = DensityFieldRef> {
+    field: D,
+    color: LinearColor,
+}
+
+impl<D> fmt::Debug for ScalarEmission<D> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("ScalarEmission")
+            .field("color", &self.color)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<D: DensityField> ScalarEmission<D> {
+    /// Creates emission equal to `color` times the field value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any color channel is not finite.
+    #[must_use]
+    pub fn new(field: D, color: LinearColor) -> Self {
+        assert!(color.is_finite(), "scalar emission color must be finite");
+        Self { field, color }
+    }
+
+    /// Returns the scalar field.
+    #[must_use]
+    pub const fn field(&self) -> &D {
+        &self.field
+    }
+
+    /// Returns the emitted color at unit field value.
+    #[must_use]
+    pub const fn color(&self) -> LinearColor {
+        self.color
+    }
+}
+
+impl<D: DensityField> EmissionField for ScalarEmission<D> {
+    fn emission(&self, point: Point, time: f64) -> LinearColor {
+        let value = self.field.density(point, time);
+        if value.is_finite() && value > 0.0 {
+            self.color * value
+        } else {
+            LinearColor::default()
+        }
+    }
+}
+
+/// Returns blackbody spectral radiance from Planck's law, in W / (sr m^2 nm).
+#[must_use]
+pub fn blackbody_spectral_radiance(wavelength_nm: f64, kelvin: f64) -> f64 {
+    if !(wavelength_nm > 0.0 && kelvin > 0.0 && kelvin.is_finite()) {
+        return 0.0;
+    }
+    let wavelength = wavelength_nm * 1.0e-9;
+    let exponent = PLANCK_C2 / (wavelength * kelvin);
+    if exponent > 700.0 {
+        return 0.0;
+    }
+    // Per meter of wavelength to per nanometer.
+    PLANCK_C1 / (wavelength.powi(5) * exponent.exp_m1()) * 1.0e-9
+}
+
+/// Returns the linear RGB color of a blackbody at `kelvin`, in the units of
+/// [`blackbody_spectral_radiance`].
+///
+/// The spectrum is integrated against CIE 1931 color matching functions and converted to linear
+/// sRGB, scaled so a flat spectrum of value one has unit luminance. A 6500 K blackbody is close to
+/// the sRGB white point.
+#[must_use]
+pub fn blackbody_linear_rgb(kelvin: f64) -> LinearColor {
+    let color =
+        spectrum_to_linear_rgb(|wavelength| blackbody_spectral_radiance(wavelength, kelvin));
+    LinearColor::new(
+        color.red.max(0.0),
+        color.green.max(0.0),
+        color.blue.max(0.0),
+    )
+}
+
+fn spectrum_to_linear_rgb(spectrum: impl Fn(f64) -> f64) -> LinearColor {
+    let mut xyz = [0.0; 3];
+    for sample in 0..CIE_SAMPLES {
+        let wavelength = CIE_MIN_NM + f64::from(sample) * CIE_STEP_NM;
+        let value = spectrum(wavelength);
+        for (total, response) in xyz.iter_mut().zip(cie_1931(wavelength)) {
+            *total += value * response;
+        }
+    }
+    let flat_luminance = (0..CIE_SAMPLES)
+        .map(|sample| cie_1931(CIE_MIN_NM + f64::from(sample) * CIE_STEP_NM)[1])
+        .sum::<f64>();
+    let [x, y, z] = xyz.map(|value| value / flat_luminance);
+    LinearColor::new(
+        3.240_454_2 * x - 1.537_138_5 * y - 0.498_531_4 * z,
+        -0.969_266 * x + 1.876_010_8 * y + 0.041_556 * z,
+        0.055_643_4 * x - 0.204_025_9 * y + 1.057_225_2 * z,
+    )
+}
+
+/// Multi-lobe Gaussian fit of the CIE 1931 2-degree color matching functions.
+///
+/// From Wyman, Sloan, and Shirley, "Simple Analytic Approximations to the CIE XYZ Color Matching
+/// Functions", JCGT 2013.
+fn cie_1931(wavelength_nm: f64) -> [f64; 3] {
+    let lobe = |mean: f64, below: f64, above: f64| {
+        let spread = if wavelength_nm < mean { below } else { above };
+        let t = (wavelength_nm - mean) / spread;
+        (-0.5 * t * t).exp()
+    };
+    [
+        1.056 * lobe(599.8, 37.9, 31.0) + 0.362 * lobe(442.0, 16.0, 26.7)
+            - 0.065 * lobe(501.1, 20.4, 26.2),
+        0.821 * lobe(568.8, 46.9, 40.5) + 0.286 * lobe(530.9, 16.3, 31.1),
+        1.217 * lobe(437.0, 11.8, 36.0) + 0.681 * lobe(459.0, 26.0, 13.8),
+    ]
+}
+
+/// Phase-function wrapper that also emits radiance from an emission field.
+pub(super) struct EmittingPhase {
+    phase: MaterialRef,
+    emission: EmissionFieldRef,
+}
+
+impl EmittingPhase {
+    pub(super) fn new(phase: MaterialRef, emission: EmissionFieldRef) -> Self {
+        Self { phase, emission }
+    }
+}
+
+impl Material for EmittingPhase {
+    fn emitted(
+        &self,
+        ray_in: &Ray,
+        hit: &HitRecord<'_>,
+        u: f64,
+        v: f64,
+        point: Point,
+    ) -> LinearColor {
+        self.phase.emitted(ray_in, hit, u, v, point) + self.emission.emission(point, ray_in.time())
+    }
+
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        hit: &HitRecord<'_>,
+        rng: &mut SampleRng,
+    ) -> Option<ScatterRecord> {
+        self.phase.scatter(ray_in, hit, rng)
+    }
+
+    #[cfg(feature = "spectral")]
+    fn scatter_spectral(
+        &self,
+        ray_in: &Ray,
+        hit: &HitRecord<'_>,
+        wavelength: SampledWavelength,
+        rng: &mut SampleRng,
+    ) -> Option<ScatterRecord> {
+        self.phase.scatter_spectral(ray_in, hit, wavelength, rng)
+    }
+
+    fn scattering_pdf(&self, ray_in: &Ray, hit: &HitRecord<'_>, scattered: &Ray) -> f64 {
+        self.phase.scattering_pdf(ray_in, hit, scattered)
+    }
+
+    fn bsdf_flags(&self, hit: &HitRecord<'_>) -> BsdfFlags {
+        self.phase.bsdf_flags(hit)
+    }
+
+    fn eval_bsdf(&self, ray_in: &Ray, hit: &HitRecord<'_>, scattered: &Ray) -> LinearColor {
+        self.phase.eval_bsdf(ray_in, hit, scattered)
+    }
+
+    fn bsdf_pdf(&self, ray_in: &Ray, hit: &HitRecord<'_>, scattered: &Ray) -> f64 {
+        self.phase.bsdf_pdf(ray_in, hit, scattered)
+    }
+
+    fn sample_bsdf(
+        &self,
+        ray_in: &Ray,
+        hit: &HitRecord<'_>,
+        rng: &mut SampleRng,
+    ) -> Option<BsdfSample> {
+        self.phase.sample_bsdf(ray_in, hit, rng)
+    }
+
+    fn denoise_albedo(&self, hit: &HitRecord<'_>) -> LinearColor {
+        self.phase.denoise_albedo(hit)
+    }
+
+    fn normal_map_shading_normal(&self, hit: &HitRecord<'_>) -> Option<Vector> {
+        self.phase.normal_map_shading_normal(hit)
+    }
+
+    #[cfg(feature = "spectral")]
+    fn spectral_attenuation(
+        &self,
+        hit: &HitRecord<'_>,
+        attenuation: LinearColor,
+        wavelength: SampledWavelength,
+    ) -> f64 {
+        self.phase
+            .spectral_attenuation(hit, attenuation, wavelength)
+    }
+
+    #[cfg(feature = "spectral")]
+    fn spectral_emitted(
+        &self,
+        ray_in: &Ray,
+        hit: &HitRecord<'_>,
+        u: f64,
+        v: f64,
+        point: Point,
+        wavelength: SampledWavelength,
+    ) -> f64 {
+        self.phase
+            .spectral_emitted(ray_in, hit, u, v, point, wavelength)
+            + self
+                .emission
+                .spectral_emission(point, ray_in.time(), wavelength)
+    }
+
+    #[cfg(feature = "spectral")]
+    fn polarized_scatter_mueller(
+        &self,
+        ray_in: &Ray,
+        hit: &HitRecord<'_>,
+        scattered: &Ray,
+        incoming_frame: PolarizationFrame,
+        outgoing_frame: PolarizationFrame,
+        wavelength: SampledWavelength,
+    ) -> MuellerMatrix {
+        self.phase.polarized_scatter_mueller(
+            ray_in,
+            hit,
+            scattered,
+            incoming_frame,
+            outgoing_frame,
+            wavelength,
+        )
+    }
+}
@@ -1,3 +1,4 @@
+use super::{grid::GridBounds, majorant::DensityRange};
 use crate::gmath::vector::Point;
 use std::{fmt, sync::Arc};
 
@@ -13,6 +14,16 @@ pub trait DensityField: Send + Sync {
 
     /// Returns a positive finite upper bound for [`Self::density`].
     fn max_density(&self) -> f64;
+
+    /// Returns density bounds that hold everywhere inside `region` at every time.
+    ///
+    /// Media build their [`MajorantGrid`](super::MajorantGrid) from these bounds, so the field
+    /// must never exceed `max` or fall below `min` inside the region. The default
+    /// `[0, max_density]` is always valid; fields that know where they are empty or dense should
+    /// return tighter bounds.
+    fn density_range(&self, _region: GridBounds) -> DensityRange {
+        DensityRange::new(0.0, self.max_density())
+    }
 }
 
 impl<T: DensityField + ?Sized> DensityField for Arc<T> {
@@ -23,6 +34,10 @@ impl<T: DensityField + ?Sized> DensityField for Arc<T> {
     fn max_density(&self) -> f64 {
         (**self).max_density()
     }
+
+    fn density_range(&self, region: GridBounds) -> DensityRange {
+        (**self).density_range(region)
+    }
 }
 
 /// Shared density-field handle.
@@ -64,6 +79,10 @@ impl DensityField for ConstantDensity {
     fn max_density(&self) -> f64 {
         self.density
     }
+
+    fn density_range(&self, _region: GridBounds) -> DensityRange {
+        DensityRange::constant(self.density)
+    }
 }
 
 /// Closure-backed density field with an explicit majorant.
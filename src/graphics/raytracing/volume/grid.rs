@@ -1,4 +1,4 @@
-use super::field::DensityField;
+use super::{field::DensityField, majorant::DensityRange};
 use crate::gmath::vector::{Point, Vector};
 use std::{
     fs,
@@ -527,6 +527,44 @@ impl DensityField for GridDensityField {
     fn max_density(&self) -> f64 {
         self.max_density
     }
+
+    fn density_range(&self, region: GridBounds) -> DensityRange {
+        let grid_min = point_axes(self.bounds.min);
+        let grid_max = point_axes(self.bounds.max);
+        let region_min = point_axes(region.min);
+        let region_max = point_axes(region.max);
+
+        let mut cells = [0..=0, 0..=0, 0..=0];
+        for axis in 0..3 {
+            let low = region_min[axis].max(grid_min[axis]);
+            let high = region_max[axis].min(grid_max[axis]);
+            if low > high {
+                return DensityRange::constant(0.0);
+            }
+            // Trilinear lookups blend the voxels on both sides of each coordinate.
+            let dim = self.dims[axis];
+            let extent = grid_max[axis] - grid_min[axis];
+            let first = axis_grid_coordinate(low, grid_min[axis], extent, dim).floor();
+            let last = axis_grid_coordinate(high, grid_min[axis], extent, dim).ceil();
+            cells[axis] = nearest_index(first, dim)..=nearest_index(last, dim);
+        }
+
+        let mut minimum = f64::INFINITY;
+        let mut maximum = 0.0_f64;
+        let [xs, ys, zs] = cells;
+        for z in zs {
+            for y in ys.clone() {
+                for x in xs.clone() {
+                    let value = f64::from(self.density[index_for_dims(self.dims, x, y, z)]);
+                    minimum = minimum.min(value);
+                    maximum = maximum.max(value);
+                }
+            }
+        }
+
+        let covered = self.bounds.contains(region.min) && self.bounds.contains(region.max);
+        DensityRange::new(if covered { minimum } else { 0.0 }, maximum)
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -614,6 +652,10 @@ fn sanitize_density_f64(value: f64) -> f32 {
     }
 }
 
+fn point_axes(point: Point) -> [f64; 3] {
+    [point.x(), point.y(), point.z()]
+}
+
 fn usize_to_f64(value: usize) -> f64 {
     f64::from(u32::try_from(value).expect("grid dimension should fit in u32"))
 }
@@ -847,6 +889,28 @@ mod tests {
         assert_close(grid.density(Point::new(0.5, 1.01, 0.5), 0.0), 0.0);
     }
 
+    #[test]
+    fn grid_density_range_covers_interpolation_neighbors() {
+        let grid = GridDensityField::new(unit_bounds(), [4, 1, 1], vec![0.0, 1.0, 3.0, 2.0]);
+        let region = |min_x: f64, max_x: f64| {
+            GridBounds::new(Point::new(min_x, 0.0, 0.0), Point::new(max_x, 1.0, 1.0))
+        };
+
+        let left = grid.density_range(region(0.0, 0.25));
+        assert_close(left.min, 0.0);
+        assert_close(left.max, 1.0);
+        let right = grid.density_range(region(0.5, 1.0));
+        assert_close(right.min, 1.0);
+        assert_close(right.max, 3.0);
+        let overhanging = grid.density_range(region(0.8, 1.5));
+        assert_close(overhanging.min, 0.0);
+        assert_close(overhanging.max, 3.0);
+        assert_eq!(
+            grid.density_range(region(2.0, 3.0)),
+            DensityRange::constant(0.0)
+        );
+    }
+
     #[test]
     fn grid_density_raw_round_trip_preserves_samples() {
         let grid = GridDensityField::new(unit_bounds(), [2, 2, 1], vec![0.0, 0.25, 0.5, 0.75]);
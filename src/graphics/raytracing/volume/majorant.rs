@@ -0,0 +1,317 @@
+use super::{field::DensityField, grid::GridBounds};
+use crate::gmath::{ray::Ray, vector::Point};
+
+/// Lower and upper density bounds over a region of a density field.
+///
+/// `max` is the local majorant used for delta tracking. `min` is the control density that residual
+/// ratio tracking removes analytically before tracking the remaining variation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DensityRange {
+    /// Density the field never falls below inside the region.
+    pub min: f64,
+    /// Density the field never exceeds inside the region.
+    pub max: f64,
+}
+
+impl DensityRange {
+    /// Creates a density range, clamping both bounds to be finite and non-negative.
+    ///
+    /// A `min` above `max` is lowered to `max`.
+    #[must_use]
+    pub fn new(min: f64, max: f64) -> Self {
+        let max = non_negative(max);
+        Self {
+            min: non_negative(min).min(max),
+            max,
+        }
+    }
+
+    /// Returns the range of a region where the density is known exactly.
+    #[must_use]
+    pub fn constant(density: f64) -> Self {
+        Self::new(density, density)
+    }
+
+    /// Returns the smallest range containing both ranges.
+    #[must_use]
+    pub fn union(self, other: Self) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    /// Returns the range with `max` lowered to at most `majorant`.
+    #[must_use]
+    pub fn clamped_to(self, majorant: f64) -> Self {
+        Self::new(self.min, self.max.min(majorant))
+    }
+
+    /// Returns `max - min`, the majorant left for residual tracking.
+    #[must_use]
+    pub fn residual(self) -> f64 {
+        self.max - self.min
+    }
+}
+
+/// Coarse per-brick density bounds used to skip empty space while tracking through a medium.
+///
+/// Each brick stores the [`DensityRange`] the density field reports over it. Rays walk the bricks
+/// with a 3D DDA, so delta tracking takes long steps through empty or thin bricks and short steps
+/// only where the field is dense.
+#[derive(Clone, Debug)]
+pub struct MajorantGrid {
+    bounds: GridBounds,
+    dims: [usize; 3],
+    ranges: Vec<DensityRange>,
+}
+
+impl MajorantGrid {
+    /// Builds a grid of `dims` bricks over `bounds` from [`DensityField::density_range`].
+    ///
+    /// Brick maxima are clamped to the field's global [`DensityField::max_density`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if any dimension is zero or the brick count overflows.
+    #[must_use]
+    pub fn from_density_field<D: DensityField + ?Sized>(
+        field: &D,
+        bounds: GridBounds,
+        dims: [usize; 3],
+    ) -> Self {
+        assert!(
+            dims.into_iter().all(|dim| dim > 0),
+            "majorant grid dimensions must be non-zero"
+        );
+        let brick_count = dims[0]
+            .checked_mul(dims[1])
+            .and_then(|count| count.checked_mul(dims[2]))
+            .expect("majorant grid dimensions overflow");
+        let majorant = field.max_density();
+        let extent = bounds.extent();
+        let brick = [
+            extent.x() / usize_to_f64(dims[0]),
+            extent.y() / usize_to_f64(dims[1]),
+            extent.z() / usize_to_f64(dims[2]),
+        ];
+
+        let mut ranges = Vec::with_capacity(brick_count);
+        for z in 0..dims[2] {
+            for y in 0..dims[1] {
+                for x in 0..dims[0] {
+                    let corner = |offset: usize| {
+                        Point::new(
+                            bounds.min.x() + usize_to_f64(x + offset) * brick[0],
+                            bounds.min.y() + usize_to_f64(y + offset) * brick[1],
+                            bounds.min.z() + usize_to_f64(z + offset) * brick[2],
+                        )
+                    };
+                    let region = GridBounds::new(corner(0), corner(1));
+                    ranges.push(field.density_range(region).clamped_to(majorant));
+                }
+            }
+        }
+
+        Self {
+            bounds,
+            dims,
+            ranges,
+        }
+    }
+
+    /// Returns the world-space bounds covered by the bricks.
+    #[must_use]
+    pub const fn bounds(&self) -> GridBounds {
+        self.bounds
+    }
+
+    /// Returns the brick counts along x, y, and z.
+    #[must_use]
+    pub const fn dims(&self) -> [usize; 3] {
+        self.dims
+    }
+
+    /// Returns the density range of one brick.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any coordinate is outside the grid dimensions.
+    #[must_use]
+    pub fn brick(&self, x: usize, y: usize, z: usize) -> DensityRange {
+        assert!(
+            x < self.dims[0] && y < self.dims[1] && z < self.dims[2],
+            "majorant grid index out of bounds"
+        );
+        self.ranges[self.index([x, y, z])]
+    }
+
+    /// Returns the union of every brick's range.
+    #[must_use]
+    pub fn range(&self) -> DensityRange {
+        self.ranges
+            .iter()
+            .copied()
+            .reduce(DensityRange::union)
+            .unwrap_or(DensityRange::constant(0.0))
+    }
+
+    /// Returns true when every brick has the same range, so the grid cannot shorten any step.
+    #[must_use]
+    pub fn is_uniform(&self) -> bool {
+        self.ranges.windows(2).all(|pair| pair[0] == pair[1])
+    }
+
+    /// Walks the bricks crossed by `ray` between parameters `t_min` and `t_max` in order.
+    pub(super) fn segments(&self, ray: &Ray, t_min: f64, t_max: f64) -> MajorantSegments<'_> {
+        MajorantSegments::new(self, ray, t_min, t_max)
+    }
+
+    fn index(&self, [x, y, z]: [usize; 3]) -> usize {
+        x + self.dims[0] * (y + self.dims[1] * z)
+    }
+}
+
+/// One stretch of a ray with constant density bounds.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct MajorantSegment {
+    pub(super) t_min: f64,
+    pub(super) t_max: f64,
+    pub(super) range: DensityRange,
+}
+
+/// 3D DDA over the bricks of a [`MajorantGrid`].
+#[derive(Debug)]
+pub(super) struct MajorantSegments<'a> {
+    grid: &'a MajorantGrid,
+    cell: [usize; 3],
+    step: [isize; 3],
+    t: f64,
+    t_end: f64,
+    t_next: [f64; 3],
+    t_delta: [f64; 3],
+    done: bool,
+}
+
+impl<'a> MajorantSegments<'a> {
+    fn new(grid: &'a MajorantGrid, ray: &Ray, t_min: f64, t_max: f64) -> Self {
+        let origin = *ray.origin();
+        let direction = *ray.direction();
+        let origin = [origin.x(), origin.y(), origin.z()];
+        let direction = [direction.x(), direction.y(), direction.z()];
+        let minimum = [
+            grid.bounds.min.x(),
+            grid.bounds.min.y(),
+            grid.bounds.min.z(),
+        ];
+        let maximum = [
+            grid.bounds.max.x(),
+            grid.bounds.max.y(),
+            grid.bounds.max.z(),
+        ];
+
+        let mut t = t_min;
+        let mut t_end = t_max;
+        for axis in 0..3 {
+            if direction[axis].abs() <= f64::EPSILON {
+                continue;
+            }
+            let near = (minimum[axis] - origin[axis]) / direction[axis];
+            let far = (maximum[axis] - origin[axis]) / direction[axis];
+            t = t.max(near.min(far));
+            t_end = t_end.min(near.max(far));
+        }
+
+        let mut segments = Self {
+            grid,
+            cell: [0; 3],
+            step: [0; 3],
+            t,
+            t_end,
+            t_next: [f64::INFINITY; 3],
+            t_delta: [f64::INFINITY; 3],
+            done: t.is_nan() || t_end.is_nan() || t >= t_end,
+        };
+        if segments.done {
+            return segments;
+        }
+
+        for axis in 0..3 {
+            let dim = grid.dims[axis];
+            let size = (maximum[axis] - minimum[axis]) / usize_to_f64(dim);
+            let position = origin[axis] + t * direction[axis];
+            let cell = brick_index((position - minimum[axis]) / size, dim);
+            segments.cell[axis] = cell;
+            if direction[axis] > f64::EPSILON {
+                let boundary = minimum[axis] + usize_to_f64(cell + 1) * size;
+                segments.step[axis] = 1;
+                segments.t_next[axis] = (boundary - origin[axis]) / direction[axis];
+                segments.t_delta[axis] = size / direction[axis];
+            } else if direction[axis] < -f64::EPSILON {
+                let boundary = minimum[axis] + usize_to_f64(cell) * size;
+                segments.step[axis] = -1;
+                segments.t_next[axis] = (boundary - origin[axis]) / direction[axis];
+                segments.t_delta[axis] = -size / direction[axis];
+            }
+        }
+        segments
+    }
+}
+
+impl Iterator for MajorantSegments<'_> {
+    type Item = MajorantSegment;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let axis = if self.t_next[0] <= self.t_next[1] && self.t_next[0] <= self.t_next[2] {
+            0
+        } else if self.t_next[1] <= self.t_next[2] {
+            1
+        } else {
+            2
+        };
+        let t_max = self.t_next[axis].min(self.t_end).max(self.t);
+        let segment = MajorantSegment {
+            t_min: self.t,
+            t_max,
+            range: self.grid.ranges[self.grid.index(self.cell)],
+        };
+
+        self.t = t_max;
+        if t_max >= self.t_end {
+            self.done = true;
+        } else {
+            match self.cell[axis].checked_add_signed(self.step[axis]) {
+                Some(cell) if cell < self.grid.dims[axis] => {
+                    self.cell[axis] = cell;
+                    self.t_next[axis] += self.t_delta[axis];
+                }
+                _ => self.done = true,
+            }
+        }
+        Some(segment)
+    }
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn brick_index(coordinate: f64, dim: usize) -> usize {
+    if coordinate.is_nan() {
+        return 0;
+    }
+    coordinate.floor().clamp(0.0, usize_to_f64(dim - 1)) as usize
+}
+
+fn non_negative(value: f64) -> f64 {
+    if value.is_finite() && value > 0.0 {
+        value
+    } else {
+        0.0
+    }
+}
+
+fn usize_to_f64(value: usize) -> f64 {
+    f64::from(u32::try_from(value).expect("majorant grid dimension should fit in u32"))
+}
@@ -2,13 +2,23 @@ use super::super::{
     Aabb, HitRecord, Hittable, INFINITY, Interval, LinearColor, Material, MaterialRef,
     material::Isotropic,
 };
+use super::emission::{EmissionField, EmittingPhase};
 use super::field::{DensityField, DensityFieldRef};
+use super::grid::GridBounds;
+use super::majorant::{DensityRange, MajorantGrid, MajorantSegment};
 use crate::{
-    gmath::{random::SampleRng, ray::Ray, vector::Vector},
+    gmath::{
+        random::SampleRng,
+        ray::Ray,
+        vector::{Point, Vector},
+    },
     graphics::texture::SurfaceTexture,
 };
 use std::{fmt, sync::Arc};
 
+/// Brick counts of the majorant grid a [`NonUniformMedium`] builds over its boundary by default.
+const DEFAULT_MAJORANT_GRID_DIMS: [usize; 3] = [16, 16, 16];
+
 /// A constant-density participating medium bounded by another hittable object.
 pub struct ConstantMedium {
     boundary: Box<dyn Hittable>,
@@ -109,15 +119,25 @@ impl ConstantMedium {
     pub const fn bounds(&self) -> Option<Aabb> {
         self.bounds
     }
+
+    /// Makes the medium emit radiance from `emission` at every sampled collision.
+    ///
+    /// Emission is added on top of the phase function, so the medium still scatters light.
+    /// Calling this again adds another emission field.
+    #[must_use]
+    pub fn with_emission(mut self, emission: impl EmissionField + 'static) -> Self {
+        self.phase_function = Arc::new(EmittingPhase::new(self.phase_function, Arc::new(emission)));
+        self
+    }
 }
 
-struct MediumInterval {
-    entry_t: f64,
-    ray_length: f64,
-    distance_inside_boundary: f64,
+pub(super) struct MediumInterval {
+    pub(super) entry_t: f64,
+    pub(super) ray_length: f64,
+    pub(super) distance_inside_boundary: f64,
 }
 
-fn boundary_interval(
+pub(super) fn boundary_interval(
     boundary: &dyn Hittable,
     ray: &Ray,
     ray_t: Interval,
@@ -149,7 +169,11 @@ fn boundary_interval(
     })
 }
 
-fn medium_hit_record<'a>(ray: &Ray, t: f64, material: &'a dyn Material) -> HitRecord<'a> {
+pub(super) fn medium_hit_record<'a>(
+    ray: &Ray,
+    t: f64,
+    material: &'a dyn Material,
+) -> HitRecord<'a> {
     let normal = Vector::new(1.0, 0.0, 0.0);
     HitRecord {
         point: ray.at(t),
@@ -188,16 +212,34 @@ impl Hittable for ConstantMedium {
     fn bounding_box(&self) -> Option<Aabb> {
         self.bounds
     }
+
+    fn transmittance_with_rng(
+        &self,
+        ray: &Ray,
+        ray_t: Interval,
+        rng: &mut SampleRng,
+    ) -> LinearColor {
+        let transmittance = boundary_interval(self.boundary.as_ref(), ray, ray_t, rng)
+            .map_or(1.0, |interval| {
+                (interval.distance_inside_boundary / self.neg_inv_density).exp()
+            });
+        LinearColor::new(transmittance, transmittance, transmittance)
+    }
 }
 
 /// A spatially varying participating medium bounded by another hittable object.
 ///
-/// Scattering is sampled with Woodcock tracking against the density field's maximum density. This
-/// supports procedural fog, smoke, cloud, and nebula volumes without tessellating the interior.
+/// Scattering is sampled with Woodcock tracking against a [`MajorantGrid`] built over the boundary
+/// bounds, so rays take long steps through empty bricks and short ones only near dense wisps.
+/// Shadow rays estimate transmittance with residual ratio tracking instead of sampling a
+/// collision. This supports procedural fog, smoke, cloud, and nebula volumes without tessellating
+/// the interior.
 pub struct NonUniformMedium<D = DensityFieldRef> {
     boundary: Box<dyn Hittable>,
     density_field: D,
     max_density: f64,
+    density_range: DensityRange,
+    majorant_grid: Option<MajorantGrid>,
     phase_function: MaterialRef,
     bounds: Option<Aabb>,
 }
@@ -207,6 +249,11 @@ impl<D> fmt::Debug for NonUniformMedium<D> {
         formatter
             .debug_struct("NonUniformMedium")
             .field("max_density", &self.max_density)
+            .field("density_range", &self.density_range)
+            .field(
+                "majorant_grid_dims",
+                &self.majorant_grid.as_ref().map(MajorantGrid::dims),
+            )
             .field("bounds", &self.bounds)
             .finish_non_exhaustive()
     }
@@ -290,9 +337,12 @@ impl<D: DensityField> NonUniformMedium<D> {
             boundary,
             density_field,
             max_density,
+            density_range: DensityRange::new(0.0, max_density),
+            majorant_grid: None,
             phase_function,
             bounds,
         }
+        .with_majorant_grid_dims(DEFAULT_MAJORANT_GRID_DIMS)
     }
 
     /// Returns the medium boundary bounds.
@@ -301,28 +351,110 @@ impl<D: DensityField> NonUniformMedium<D> {
         self.bounds
     }
 
+    /// Makes the medium emit radiance from `emission` at every sampled collision.
+    ///
+    /// Emission is added on top of the phase function, so the medium still scatters light.
+    /// Calling this again adds another emission field.
+    #[must_use]
+    pub fn with_emission(mut self, emission: impl EmissionField + 'static) -> Self {
+        self.phase_function = Arc::new(EmittingPhase::new(self.phase_function, Arc::new(emission)));
+        self
+    }
+
     /// Returns the density field.
     #[must_use]
     pub const fn density_field(&self) -> &D {
         &self.density_field
     }
 
-    /// Returns the density majorant used for Woodcock tracking.
+    /// Returns the global density majorant reported by the density field.
     #[must_use]
     pub const fn max_density(&self) -> f64 {
         self.max_density
     }
 
-    fn clamped_density(&self, point: crate::gmath::vector::Point, time: f64) -> f64 {
+    /// Returns the density bounds used where no majorant grid brick applies.
+    #[must_use]
+    pub const fn density_range(&self) -> DensityRange {
+        self.density_range
+    }
+
+    /// Returns the majorant grid, if one tightens tracking for this field.
+    ///
+    /// Media with unbounded boundaries, and fields whose [`DensityField::density_range`] reports
+    /// the same bounds everywhere, track against [`Self::density_range`] alone.
+    #[must_use]
+    pub const fn majorant_grid(&self) -> Option<&MajorantGrid> {
+        self.majorant_grid.as_ref()
+    }
+
+    /// Rebuilds the majorant grid with `dims` bricks over the boundary bounds.
+    ///
+    /// Media start with 16 bricks per axis. Finer grids skip more empty space but cost more DDA steps per ray.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any dimension is zero or the brick count overflows.
+    #[must_use]
+    pub fn with_majorant_grid_dims(mut self, dims: [usize; 3]) -> Self {
+        let Some(bounds) = self.bounds.and_then(grid_bounds) else {
+            self.majorant_grid = None;
+            return self;
+        };
+        let grid = MajorantGrid::from_density_field(&self.density_field, bounds, dims);
+        self.density_range = grid.range();
+        self.majorant_grid = (!grid.is_uniform()).then_some(grid);
+        self
+    }
+
+    /// Drops the majorant grid and tracks against the field's global maximum density.
+    #[must_use]
+    pub fn without_majorant_grid(mut self) -> Self {
+        self.majorant_grid = None;
+        self.density_range = DensityRange::new(0.0, self.max_density);
+        self
+    }
+
+    fn majorant_segments<'a>(
+        &'a self,
+        ray: &Ray,
+        interval: &MediumInterval,
+    ) -> impl Iterator<Item = MajorantSegment> + 'a {
+        let exit_t = interval.entry_t + interval.distance_inside_boundary / interval.ray_length;
+        let grid_segments = self
+            .majorant_grid
+            .as_ref()
+            .map(|grid| grid.segments(ray, interval.entry_t, exit_t));
+        let whole_segment = grid_segments.is_none().then_some(MajorantSegment {
+            t_min: interval.entry_t,
+            t_max: exit_t,
+            range: self.density_range,
+        });
+        grid_segments.into_iter().flatten().chain(whole_segment)
+    }
+
+    fn clamped_density(&self, point: Point, time: f64, range: DensityRange) -> f64 {
         let density = self.density_field.density(point, time);
         if density.is_finite() {
-            density.clamp(0.0, self.max_density)
+            density.clamp(range.min, range.max)
         } else {
-            0.0
+            range.min
         }
     }
 }
 
+fn grid_bounds(bounds: Aabb) -> Option<GridBounds> {
+    let bounds = bounds.padded(1e-6);
+    let min = Point::new(bounds.min.0, bounds.min.1, bounds.min.2);
+    let max = Point::new(bounds.max.0, bounds.max.1, bounds.max.2);
+    (min.is_finite()
+        && max.is_finite()
+        && max.x() > min.x()
+        && max.y() > min.y()
+        && max.z() > min.z())
+    .then(|| GridBounds::new(min, max))
+}
+
 impl NonUniformMedium<DensityFieldRef> {
     /// Creates a non-uniform medium from shared boxed density field data.
     ///
@@ -347,25 +479,71 @@ impl<D: DensityField> Hittable for NonUniformMedium<D> {
         rng: &mut SampleRng,
     ) -> Option<HitRecord<'_>> {
         let interval = boundary_interval(self.boundary.as_ref(), ray, ray_t, rng)?;
-        let mut distance = 0.0;
 
-        loop {
-            let sample = rng.random_double().max(f64::MIN_POSITIVE);
-            distance += -sample.ln() / self.max_density;
-            if distance > interval.distance_inside_boundary {
-                return None;
+        for segment in self.majorant_segments(ray, &interval) {
+            let majorant = segment.range.max * interval.ray_length;
+            if majorant <= 0.0 {
+                continue;
             }
 
-            let t = interval.entry_t + distance / interval.ray_length;
-            let density = self.clamped_density(ray.at(t), ray.time());
-            let accept_probability = density / self.max_density;
-            if accept_probability.total_cmp(&rng.random_double()).is_gt() {
-                return Some(medium_hit_record(ray, t, self.phase_function.as_ref()));
+            let mut t = segment.t_min;
+            loop {
+                let sample = rng.random_double().max(f64::MIN_POSITIVE);
+                t += -sample.ln() / majorant;
+                if t > segment.t_max {
+                    break;
+                }
+
+                let density = self.clamped_density(ray.at(t), ray.time(), segment.range);
+                let accept_probability = density / segment.range.max;
+                if accept_probability.total_cmp(&rng.random_double()).is_gt() {
+                    return Some(medium_hit_record(ray, t, self.phase_function.as_ref()));
+                }
             }
         }
+        None
     }
 
     fn bounding_box(&self) -> Option<Aabb> {
         self.bounds
     }
+
+    // Residual ratio tracking: each brick's minimum density is attenuated analytically and only
+    // the residual up to the brick maximum is tracked, so thick but smooth regions need few
+    // density lookups.
+    fn transmittance_with_rng(
+        &self,
+        ray: &Ray,
+        ray_t: Interval,
+        rng: &mut SampleRng,
+    ) -> LinearColor {
+        let Some(interval) = boundary_interval(self.boundary.as_ref(), ray, ray_t, rng) else {
+            return LinearColor::new(1.0, 1.0, 1.0);
+        };
+
+        let mut transmittance = 1.0;
+        for segment in self.majorant_segments(ray, &interval) {
+            let length = (segment.t_max - segment.t_min) * interval.ray_length;
+            transmittance *= (-segment.range.min * length).exp();
+            let residual = segment.range.residual();
+            if residual <= 0.0 {
+                continue;
+            }
+
+            let mut t = segment.t_min;
+            loop {
+                let sample = rng.random_double().max(f64::MIN_POSITIVE);
+                t += -sample.ln() / (residual * interval.ray_length);
+                if t > segment.t_max {
+                    break;
+                }
+                let density = self.clamped_density(ray.at(t), ray.time(), segment.range);
+                transmittance *= 1.0 - (density - segment.range.min) / residual;
+            }
+            if transmittance <= 0.0 {
+                return LinearColor::default();
+            }
+        }
+        LinearColor::new(transmittance, transmittance, transmittance)
+    }
 }
@@ -1,7 +1,10 @@
 //! Participating media and density fields for path tracing.
 
+mod chromatic;
+mod emission;
 mod field;
 mod grid;
+mod majorant;
 mod marching_cubes;
 mod medium;
 mod particles;
@@ -9,15 +12,22 @@ mod procedural;
 mod solver;
 mod warp;
 
+pub use chromatic::{ChromaticMedium, MediumCoefficients};
+pub use emission::{
+    BlackbodyEmission, EmissionField, EmissionFieldRef, ScalarEmission, blackbody_linear_rgb,
+    blackbody_spectral_radiance,
+};
 pub use field::{ConstantDensity, DensityField, DensityFieldRef, FnDensityField};
 pub use grid::{GridBounds, GridDensityField, GridDensityMetadata, GridInterpolation};
+pub use majorant::{DensityRange, MajorantGrid};
 pub use marching_cubes::{ExtractedSurface, LiquidSurface, MarchingCubes};
 pub use medium::{ConstantMedium, NonUniformMedium};
 pub use particles::{FluidParticle, ParticleSplatField, SplatKernel};
 pub use procedural::{ProceduralDensityField, ProceduralDensityPreset};
 pub use solver::{
-    MacCellFlags, MacFluidEmitter, MacFluidGrid2, MacFluidGrid3, MacProjectionStats,
-    MacScalarAdvection, MacScalarGrid3, MacStepStats, StableFluidEmitter, StableFluidGrid2,
+    MacCellFlags, MacFlipLiquid3, MacFlipParticle, MacFluidEmitter, MacFluidGrid2, MacFluidGrid3,
+    MacParticleTransfer, MacPressurePreconditioner, MacProjectionStats, MacScalarAdvection,
+    MacScalarGrid3, MacStepStats, StableFluidEmitter, StableFluidGrid2,
 };
 pub use warp::{CurlNoiseField, DomainWarpedDensityField};
 
@@ -30,7 +40,10 @@ mod tests {
             ray::Ray,
             vector::{Point, Vector},
         },
-        graphics::raytracing::{Hittable, INFINITY, Interval, LinearColor, Sphere},
+        graphics::raytracing::{
+            BsdfFlags, BvhNode, Hittable, HittableList, INFINITY, Interval, LinearColor, Sphere,
+            Translate,
+        },
     };
 
     fn assert_close(actual: f64, expected: f64) {
@@ -212,4 +225,411 @@ mod tests {
         assert_eq!(record.normal, Vector::new(1.0, 0.0, 0.0));
         assert!(record.front_face);
     }
+
+    fn unit_cube() -> GridBounds {
+        GridBounds::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0))
+    }
+
+    /// A 32³ grid over the unit cube that is empty except for a slab with `x` in `[0.25, 0.5]`.
+    fn sparse_slab() -> GridDensityField {
+        GridDensityField::from_fn(unit_cube(), [32, 32, 32], |point| {
+            if (0.25..0.5).contains(&point.x()) {
+                4.0
+            } else {
+                0.0
+            }
+        })
+    }
+
+    #[test]
+    fn majorant_grid_brackets_sparse_density_and_walks_bricks_in_order() {
+        let field = sparse_slab();
+        let grid = MajorantGrid::from_density_field(&field, unit_cube(), [8, 8, 8]);
+
+        assert_eq!(grid.dims(), [8, 8, 8]);
+        assert_close(grid.brick(0, 3, 3).max, 0.0);
+        assert_close(grid.brick(5, 3, 3).max, 4.0);
+        assert_close(grid.brick(5, 3, 3).min, 0.0);
+        assert_close(grid.range().max, 4.0);
+        assert!(!grid.is_uniform());
+
+        let ray = Ray::new(Point::new(-3.0, -0.9, 0.3), Vector::new(1.0, 0.35, -0.1));
+        let mut previous_end = 2.0;
+        let mut count = 0;
+        for segment in grid.segments(&ray, 2.0, 4.0) {
+            assert_close(segment.t_min, previous_end);
+            assert!(segment.t_max >= segment.t_min);
+            let middle = ray.at(0.5 * (segment.t_min + segment.t_max));
+            assert!(field.density(middle, 0.0) <= segment.range.max + 1e-9);
+            previous_end = segment.t_max;
+            count += 1;
+        }
+        assert_close(previous_end, 4.0);
+        assert!(count > 4, "{count}");
+    }
+
+    #[test]
+    fn sparse_medium_tracking_and_transmittance_match_beer_lambert() {
+        let ray = Ray::new(Point::new(-3.0, 0.1, 0.05), Vector::new(1.0, 0.0, 0.0));
+        let slab = sparse_slab();
+        let steps = 20_000;
+        let optical_depth = (0..steps)
+            .map(|step| {
+                let t = 2.0 + 2.0 * (f64::from(step) + 0.5) / f64::from(steps);
+                slab.density(ray.at(t), 0.0) * 2.0 / f64::from(steps)
+            })
+            .sum::<f64>();
+        let expected = (-optical_depth).exp();
+        assert!((0.2..0.6).contains(&expected), "{expected}");
+
+        let bricked = NonUniformMedium::new(
+            Sphere::new(Point::new(0.0, 0.0, 0.0), 1.5),
+            slab.clone(),
+            LinearColor::new(1.0, 1.0, 1.0),
+        );
+        assert!(bricked.majorant_grid().is_some());
+        let global = NonUniformMedium::new(
+            Sphere::new(Point::new(0.0, 0.0, 0.0), 1.5),
+            slab,
+            LinearColor::new(1.0, 1.0, 1.0),
+        )
+        .without_majorant_grid();
+        assert!(global.majorant_grid().is_none());
+
+        let samples = 6000;
+        let mut rng = SampleRng::new(23);
+        for medium in [&bricked, &global] {
+            let mut escaped = 0_u32;
+            let mut transmittance = 0.0;
+            for _ in 0..samples {
+                if medium
+                    .hit_with_rng(&ray, Interval::new(0.0, INFINITY), &mut rng)
+                    .is_none()
+                {
+                    escaped += 1;
+                }
+                transmittance += medium
+                    .transmittance_with_rng(&ray, Interval::new(0.0, INFINITY), &mut rng)
+                    .red;
+            }
+            let escaped = f64::from(escaped) / f64::from(samples);
+            let transmittance = transmittance / f64::from(samples);
+            assert!((escaped - expected).abs() < 0.03, "{escaped} vs {expected}");
+            assert!(
+                (transmittance - expected).abs() < 0.02,
+                "{transmittance} vs {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn procedural_fog_floor_is_removed_analytically() {
+        let mist = ProceduralDensityField::mist().with_seed(5);
+        let range = mist.density_range(unit_cube());
+        assert!(range.min > 0.0 && range.min < range.max);
+        assert_close(range.max, mist.max_density());
+
+        let medium = NonUniformMedium::new(
+            Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0),
+            mist,
+            LinearColor::new(1.0, 1.0, 1.0),
+        );
+        assert!(medium.majorant_grid().is_none());
+        assert_eq!(medium.density_range(), range);
+
+        let ray = Ray::new(Point::new(-2.0, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0));
+        let floor = (-range.min * 2.0).exp();
+        let mut rng = SampleRng::new(3);
+        for _ in 0..64 {
+            let transmittance = medium
+                .transmittance_with_rng(&ray, Interval::new(0.0, INFINITY), &mut rng)
+                .red;
+            assert!(transmittance > 0.0 && transmittance <= floor + 1e-12);
+        }
+    }
+
+    #[test]
+    fn shadow_transmittance_multiplies_media_and_stops_at_surfaces() {
+        let fog = || {
+            ConstantMedium::new(
+                Sphere::new(Point::new(0.0, 0.0, -2.0), 0.5),
+                1.0,
+                LinearColor::new(1.0, 1.0, 1.0),
+            )
+        };
+        let ray = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, -1.0));
+        let before_wall = Interval::new(0.001, 3.5);
+        let through_wall = Interval::new(0.001, INFINITY);
+        let mut rng = SampleRng::new(1);
+
+        let mut scene = HittableList::new();
+        scene.add(fog());
+        scene.add(Translate::new(fog(), Vector::new(0.0, 0.0, -1.0)));
+        scene.add(Sphere::new(Point::new(0.0, 0.0, -5.0), 1.0));
+        let transmittance = scene.transmittance_with_rng(&ray, before_wall, &mut rng);
+        assert!((transmittance.green - (-2.0_f64).exp()).abs() < 1e-9);
+        assert_eq!(
+            scene.transmittance_with_rng(&ray, through_wall, &mut rng),
+            LinearColor::default()
+        );
+
+        let objects: Vec<Box<dyn Hittable>> = vec![
+            Box::new(fog()),
+            Box::new(Translate::new(fog(), Vector::new(0.0, 0.0, -1.0))),
+            Box::new(Sphere::new(Point::new(0.0, 0.0, -5.0), 1.0)),
+            Box::new(Sphere::new(Point::new(4.0, 0.0, -2.0), 1.0)),
+        ];
+        let bvh = BvhNode::from_hittables(objects).expect("bounded objects build a BVH");
+        let transmittance = bvh.transmittance_with_rng(&ray, before_wall, &mut rng);
+        assert!((transmittance.blue - (-2.0_f64).exp()).abs() < 1e-9);
+        assert_eq!(
+            bvh.transmittance_with_rng(&ray, through_wall, &mut rng),
+            LinearColor::default()
+        );
+    }
+
+    #[test]
+    fn blackbody_radiance_follows_planck_and_shifts_color_with_temperature() {
+        let sun = blackbody_spectral_radiance(500.0, 5778.0);
+        assert!((sun / 26_370.0 - 1.0).abs() < 0.01, "{sun}");
+        assert!(blackbody_spectral_radiance(550.0, 0.0) <= 0.0);
+
+        let ember = blackbody_linear_rgb(1500.0);
+        assert!(ember.red > ember.green && ember.green > ember.blue);
+        let daylight = blackbody_linear_rgb(6500.0);
+        let balance = daylight.blue / daylight.red;
+        assert!((0.8..1.25).contains(&balance), "{daylight:?}");
+        assert!(blackbody_linear_rgb(2000.0).green > 100.0 * blackbody_linear_rgb(1000.0).green);
+    }
+
+    #[test]
+    fn blackbody_emission_interpolates_its_table_and_scales() {
+        let temperature = FnDensityField::new(2000.0, |point: Point, _time| 1000.0 + point.x());
+        let emission = BlackbodyEmission::new(temperature, 4.0);
+        let direct = blackbody_linear_rgb(1250.0) * 4.0;
+        let table = emission.emission(Point::new(250.0, 0.0, 0.0), 0.0);
+        assert!((table.red / direct.red - 1.0).abs() < 1e-2);
+        assert!((table.green / direct.green - 1.0).abs() < 1e-2);
+        let hotter = emission.radiance(3000.0);
+        assert!((hotter.red / (blackbody_linear_rgb(3000.0).red * 4.0) - 1.0).abs() < 1e-12);
+        assert_eq!(emission.radiance(-5.0), LinearColor::default());
+    }
+
+    #[test]
+    fn emissive_medium_glows_at_collisions_and_still_scatters() {
+        let boundary = Sphere::new(Point::new(0.0, 0.0, -2.0), 0.5);
+        let medium = NonUniformMedium::new(
+            boundary,
+            ConstantDensity::new(50.0),
+            LinearColor::new(0.5, 0.5, 0.5),
+        )
+        .with_emission(ScalarEmission::new(
+            ConstantDensity::new(2.0),
+            LinearColor::new(1.0, 0.5, 0.25),
+        ));
+        let ray = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, -1.0));
+        let mut rng = SampleRng::new(9);
+
+        let record = medium
+            .hit_with_rng(&ray, Interval::new(0.0, INFINITY), &mut rng)
+            .expect("dense medium should collide");
+        let emitted = record
+            .material
+            .emitted(&ray, &record, record.u, record.v, record.point);
+        assert_close(emitted.red, 2.0);
+        assert_close(emitted.blue, 0.5);
+        assert!(record.material.bsdf_flags(&record).has_non_delta_lobe());
+        assert!(
+            record
+                .material
+                .sample_bsdf(&ray, &record, &mut rng)
+                .is_some()
+        );
+
+        let fire = ConstantMedium::new(
+            Sphere::new(Point::new(0.0, 0.0, -2.0), 0.5),
+            50.0,
+            LinearColor::default(),
+        )
+        .with_emission(BlackbodyEmission::new(ConstantDensity::new(1800.0), 1.0));
+        let record = fire
+            .hit_with_rng(&ray, Interval::new(0.0, INFINITY), &mut rng)
+            .expect("dense fire should collide");
+        let glow = record
+            .material
+            .emitted(&ray, &record, record.u, record.v, record.point);
+        assert!(glow.red > glow.blue && glow.red > 0.0);
+    }
+
+    #[test]
+    fn chromatic_medium_weights_match_per_channel_beer_lambert() {
+        let coefficients = MediumCoefficients::new(
+            LinearColor::new(0.5, 0.0, 1.0),
+            LinearColor::new(0.0, 1.0, 2.0),
+        );
+        let medium = ChromaticMedium::new(
+            Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0),
+            coefficients.clone(),
+        );
+        let mut rng = SampleRng::new(31);
+
+        let outside = Ray::new(Point::new(-3.0, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0));
+        let entry = medium
+            .hit_with_rng(&outside, Interval::new(0.0, INFINITY), &mut rng)
+            .expect("ray should enter the boundary");
+        let crossing = entry
+            .material
+            .sample_bsdf(&outside, &entry, &mut rng)
+            .expect("index-matched boundary should pass rays through");
+        assert!(entry.front_face);
+        assert_eq!(crossing.direction, *outside.direction());
+        assert_eq!(crossing.weight(), LinearColor::new(1.0, 1.0, 1.0));
+
+        let inside = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0));
+        let samples = 40_000;
+        let mut escaped = LinearColor::default();
+        let mut scattered = LinearColor::default();
+        for _ in 0..samples {
+            let record = medium
+                .hit_with_rng(&inside, Interval::new(0.0, INFINITY), &mut rng)
+                .expect("ray inside the boundary should collide or leave");
+            let weight = record
+                .material
+                .sample_bsdf(&inside, &record, &mut rng)
+                .expect("medium vertices should always sample")
+                .weight();
+            let flags = record.material.bsdf_flags(&record);
+            if flags.contains(BsdfFlags::MEDIUM) && !flags.is_index_matched() {
+                scattered += weight;
+            } else {
+                escaped += weight;
+            }
+        }
+        let escaped = escaped / f64::from(samples);
+        let scattered = scattered / f64::from(samples);
+
+        // Unscattered light follows Beer-Lambert per channel, and scattered light integrates
+        // sigma_s * T(s) over the unit radius.
+        let transmittance = coefficients.transmittance(1.0);
+        let albedo = coefficients.albedo();
+        for (escaped, scattered, transmittance, albedo) in [
+            (escaped.red, scattered.red, transmittance.red, albedo.red),
+            (
+                escaped.green,
+                scattered.green,
+                transmittance.green,
+                albedo.green,
+            ),
+            (
+                escaped.blue,
+                scattered.blue,
+                transmittance.blue,
+                albedo.blue,
+            ),
+        ] {
+            let expected_scattered = albedo * (1.0 - transmittance);
+            assert!(
+                (escaped - transmittance).abs() < 0.02,
+                "{escaped} vs {transmittance}"
+            );
+            assert!(
+                (scattered - expected_scattered).abs() < 0.02,
+                "{scattered} vs {expected_scattered}"
+            );
+        }
+        assert_close(scattered.red, 0.0);
+    }
+
+    #[test]
+    fn chromatic_medium_attenuates_shadow_rays_unless_the_boundary_refracts() {
+        use crate::graphics::raytracing::Dielectric;
+
+        let coefficients = MediumCoefficients::new(
+            LinearColor::new(0.5, 0.0, 1.0),
+            LinearColor::new(0.0, 1.0, 2.0),
+        );
+        let sphere = || Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0);
+        let medium = ChromaticMedium::new(sphere(), coefficients.clone());
+        let mut rng = SampleRng::new(8);
+
+        let outside = Ray::new(Point::new(-3.0, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0));
+        let entry = medium
+            .hit_with_rng(&outside, Interval::new(0.0, INFINITY), &mut rng)
+            .expect("ray should enter the boundary");
+        assert!(entry.material.bsdf_flags(&entry).is_index_matched());
+        let through = medium.transmittance_with_rng(&outside, Interval::new(0.0, 10.0), &mut rng);
+        let inside = Ray::new(Point::new(0.5, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0));
+        let from_inside =
+            medium.transmittance_with_rng(&inside, Interval::new(0.0, 10.0), &mut rng);
+        let clipped = medium.transmittance_with_rng(&outside, Interval::new(0.0, 2.5), &mut rng);
+        for (actual, expected) in [
+            (through, coefficients.transmittance(2.0)),
+            (from_inside, coefficients.transmittance(0.5)),
+            (clipped, coefficients.transmittance(0.5)),
+        ] {
+            assert_close(actual.red, expected.red);
+            assert_close(actual.green, expected.green);
+            assert_close(actual.blue, expected.blue);
+        }
+
+        let liquid = ChromaticMedium::new(sphere(), coefficients)
+            .with_interface(Dielectric::from_ratio(1.33));
+        assert_eq!(
+            liquid.transmittance_with_rng(&outside, Interval::new(0.0, 10.0), &mut rng),
+            LinearColor::default()
+        );
+    }
+
+    #[cfg(feature = "spectral")]
+    #[test]
+    fn measured_absorption_tints_spectral_exits_per_wavelength() {
+        use crate::graphics::raytracing::{
+            Dielectric, MeasuredSpectrum, SampledWavelength, ScatterRecord,
+        };
+
+        let coefficients = MediumCoefficients::absorbing(LinearColor::default())
+            .with_absorption_spectrum(MeasuredSpectrum::new(vec![(450.0, 2.0), (650.0, 0.0)]));
+        let blue = SampledWavelength::new(450.0, 1.0);
+        let red = SampledWavelength::new(650.0, 1.0);
+        assert_close(coefficients.absorption_at(blue), 2.0);
+        assert_close(coefficients.absorption_at(red), 0.0);
+
+        let medium =
+            ChromaticMedium::new(Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0), coefficients)
+                .with_interface(Dielectric::from_ratio(1.33));
+        assert!(medium.interface().is_some());
+        let ray = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0));
+        let mut rng = SampleRng::new(12);
+        for (wavelength, expected) in [(blue, (-2.0_f64).exp()), (red, 1.0)] {
+            let record = medium
+                .hit_with_rng(&ray, Interval::new(0.0, INFINITY), &mut rng)
+                .expect("absorbing medium should only stop at its boundary");
+            assert!(!record.front_face);
+            let Some(ScatterRecord::Specular { attenuation, .. }) = record
+                .material
+                .scatter_spectral(&ray, &record, wavelength, &mut rng)
+            else {
+                panic!("dielectric interface should scatter specularly");
+            };
+            assert_close(
+                record
+                    .material
+                    .spectral_attenuation(&record, attenuation, wavelength),
+                expected,
+            );
+        }
+    }
+
+    #[cfg(feature = "spectral")]
+    #[test]
+    fn blackbody_emission_samples_planck_at_the_path_wavelength() {
+        use crate::graphics::raytracing::SampledWavelength;
+
+        let emission = BlackbodyEmission::new(ConstantDensity::new(1800.0), 3.0);
+        let wavelength = SampledWavelength::new(620.0, 1.0);
+        assert_close(
+            emission.spectral_emission(Point::new(0.0, 0.0, 0.0), 0.0, wavelength),
+            3.0 * blackbody_spectral_radiance(620.0, 1800.0),
+        );
+    }
 }
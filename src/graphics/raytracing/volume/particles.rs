@@ -1,4 +1,4 @@
-use super::field::DensityField;
+use super::{field::DensityField, grid::GridBounds, majorant::DensityRange};
 use crate::gmath::vector::{Point, Vector};
 use std::collections::HashMap;
 
@@ -53,6 +53,15 @@ impl FluidParticle {
 
         self.density * kernel.evaluate(distance / self.radius)
     }
+
+    fn reaches(self, region: GridBounds) -> bool {
+        let closest = Point::new(
+            self.position.x().clamp(region.min.x(), region.max.x()),
+            self.position.y().clamp(region.min.y(), region.max.y()),
+            self.position.z().clamp(region.min.z(), region.max.z()),
+        );
+        (closest - self.position).length_squared() < self.radius * self.radius
+    }
 }
 
 /// Radial kernel used to turn particles into a smooth density field.
@@ -243,6 +252,20 @@ impl DensityField for ParticleSplatField {
     fn max_density(&self) -> f64 {
         self.max_density
     }
+
+    // Overlapping particles can at most add up their center densities.
+    fn density_range(&self, region: GridBounds) -> DensityRange {
+        let mut candidates = self.acceleration.region_candidates(region);
+        candidates.sort_unstable();
+        candidates.dedup();
+        let reaching = candidates
+            .into_iter()
+            .map(|index| self.particles[index])
+            .filter(|particle| particle.reaches(region))
+            .map(|particle| particle.density)
+            .sum::<f64>();
+        DensityRange::new(0.0, reaching.min(self.max_density))
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -299,6 +322,43 @@ impl ParticleGrid {
         };
         self.buckets.get(&key).map_or(&[], Vec::as_slice)
     }
+
+    /// Returns the particles in every bucket overlapping `region`, possibly with repeats.
+    fn region_candidates(&self, region: GridBounds) -> Vec<usize> {
+        let (Some(min_key), Some(max_key)) = (
+            cell_key(region.min, self.cell_size),
+            cell_key(region.max, self.cell_size),
+        ) else {
+            return Vec::new();
+        };
+        let in_region = |key: &[i32; 3]| {
+            (0..3).all(|axis| (min_key[axis]..=max_key[axis]).contains(&key[axis]))
+        };
+        let key_count = (0..3)
+            .map(|axis| u64::from(min_key[axis].abs_diff(max_key[axis])) + 1)
+            .product::<u64>();
+
+        if key_count > u64::try_from(self.buckets.len()).unwrap_or(u64::MAX) {
+            return self
+                .buckets
+                .iter()
+                .filter(|(key, _)| in_region(key))
+                .flat_map(|(_, indices)| indices.iter().copied())
+                .collect();
+        }
+
+        let mut candidates = Vec::new();
+        for z in min_key[2]..=max_key[2] {
+            for y in min_key[1]..=max_key[1] {
+                for x in min_key[0]..=max_key[0] {
+                    if let Some(indices) = self.buckets.get(&[x, y, z]) {
+                        candidates.extend_from_slice(indices);
+                    }
+                }
+            }
+        }
+        candidates
+    }
 }
 
 fn gaussian_kernel(radius: f64) -> f64 {
@@ -484,6 +544,26 @@ mod tests {
         assert_close(clamped.density(Point::new(0.0, 0.0, 0.0), 0.0), 1.5);
     }
 
+    #[test]
+    fn particle_splat_density_range_sums_only_reaching_particles() {
+        let field = ParticleSplatField::new(vec![
+            FluidParticle::new(Point::new(0.0, 0.0, 0.0), 0.5, 2.0),
+            FluidParticle::new(Point::new(0.2, 0.0, 0.0), 0.5, 1.0),
+            FluidParticle::new(Point::new(5.0, 0.0, 0.0), 0.5, 4.0),
+        ]);
+        let near = GridBounds::new(
+            Point::new(-0.25, -0.25, -0.25),
+            Point::new(0.25, 0.25, 0.25),
+        );
+        let empty = GridBounds::new(Point::new(2.0, -0.25, -0.25), Point::new(3.0, 0.25, 0.25));
+        let far = GridBounds::new(Point::new(4.0, -1.0, -1.0), Point::new(6.0, 1.0, 1.0));
+
+        assert_close(field.density_range(near).max, 3.0);
+        assert_close(field.density_range(empty).max, 0.0);
+        assert_close(field.density_range(far).max, 4.0);
+        assert_close(field.density_range(near).min, 0.0);
+    }
+
     #[test]
     fn empty_particle_splat_field_is_empty_with_positive_majorant() {
         let field = ParticleSplatField::new(Vec::new());
@@ -1,5 +1,7 @@
 use super::{
     field::DensityField,
+    grid::GridBounds,
+    majorant::DensityRange,
     warp::{CurlNoiseField, DomainWarpedDensityField},
 };
 use crate::gmath::{
@@ -316,6 +318,16 @@ impl ProceduralDensityField {
         point + warp
     }
 
+    /// Lowest value [`Self::preset_density`] can return anywhere, before contrast shaping.
+    const fn preset_floor(&self) -> f64 {
+        match self.preset {
+            ProceduralDensityPreset::Smoke | ProceduralDensityPreset::Nebula => 0.0,
+            ProceduralDensityPreset::Mist => 0.25,
+            ProceduralDensityPreset::Plasma => 0.05,
+            ProceduralDensityPreset::Underwater => 0.35,
+        }
+    }
+
     fn preset_density(&self, point: Point, time: f64) -> f64 {
         match self.preset {
             ProceduralDensityPreset::Smoke => {
@@ -376,4 +388,11 @@ impl DensityField for ProceduralDensityField {
     fn max_density(&self) -> f64 {
         self.max_density
     }
+
+    // Animated noise has no cheap spatial bound, but the fog-like presets never drop below a
+    // floor that residual ratio tracking can remove analytically.
+    fn density_range(&self, _region: GridBounds) -> DensityRange {
+        let floor = (self.preset_floor() * self.contrast).clamp(0.0, 1.0);
+        DensityRange::new(self.max_density * floor, self.max_density)
+    }
 }
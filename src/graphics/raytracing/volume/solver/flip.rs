@@ -0,0 +1,909 @@
+use super::{
+    finite_f32,
+    mac::MacProjectionStats,
+    mac3::{MacFluidGrid3, cell_index_for_dims3, sample_grid3},
+    usize_to_f64,
+};
+use crate::{
+    gmath::{random::SampleRng, vector::Point},
+    graphics::raytracing::volume::{
+        grid::GridBounds,
+        marching_cubes::LiquidSurface,
+        particles::{FluidParticle, ParticleSplatField},
+    },
+};
+
+const DEFAULT_PARTICLES_PER_AXIS: usize = 2;
+const DEFAULT_FLIP_RATIO: f64 = 0.95;
+const DEFAULT_GRAVITY: [f64; 3] = [0.0, -9.81, 0.0];
+// Just over half a cell diagonal, so 2x2x2 seeding keeps every interior cell center inside.
+const DEFAULT_PARTICLE_RADIUS: f64 = 0.9;
+const AIR_PHI: f64 = 3.0;
+const VELOCITY_EXTRAPOLATION_LAYERS: usize = 4;
+const TRANSFER_WEIGHT_EPSILON: f64 = 1.0e-9;
+const WALL_MARGIN: f64 = 1.0e-3;
+const SOLID_MARGIN: f64 = 1.0e-2;
+
+/// One marker particle carried by [`MacFlipLiquid3`].
+///
+/// Positions use solver cell coordinates, where cell `[x, y, z]` is centered at `[x, y, z]` and
+/// the closed domain spans `-0.5..dims - 0.5` on each axis. Velocities use the same world units
+/// per second as the MAC faces.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MacFlipParticle {
+    /// Particle center in solver cell coordinates.
+    pub position: [f64; 3],
+    /// Particle velocity.
+    pub velocity: [f64; 3],
+    /// APIC affine rows, one per velocity component, in velocity change per cell.
+    pub affine: [[f64; 3]; 3],
+}
+
+impl MacFlipParticle {
+    /// Creates a particle with zero affine velocity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `position` or `velocity` is not finite.
+    #[must_use]
+    pub fn new(position: [f64; 3], velocity: [f64; 3]) -> Self {
+        assert!(
+            position.into_iter().all(f64::is_finite),
+            "FLIP particle position must be finite"
+        );
+        assert!(
+            velocity.into_iter().all(f64::is_finite),
+            "FLIP particle velocity must be finite"
+        );
+        Self {
+            position,
+            velocity,
+            affine: [[0.0; 3]; 3],
+        }
+    }
+}
+
+/// Velocity transfer between particles and MAC faces.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum MacParticleTransfer {
+    /// FLIP/PIC blend: particles keep their own velocity plus the grid's change over the step,
+    /// blended toward the grid velocity by [`MacFlipLiquid3::flip_ratio`].
+    Flip,
+    /// Affine particle-in-cell: particles take the grid velocity and carry its local gradient,
+    /// which keeps rotation without FLIP noise.
+    #[default]
+    Apic,
+}
+
+/// Particle-based free-surface liquid solver built on [`MacFluidGrid3`].
+///
+/// Marker particles carry the liquid between steps. Each step splats particle velocity onto the
+/// MAC faces, rebuilds the grid's liquid level set from the particles, adds gravity, runs the
+/// grid's free-surface projection against its solid SDF, and transfers the result back before
+/// moving the particles through the divergence-free field. Because the particles, not an
+/// advected level set, carry the liquid, thin sheets and droplets keep their volume.
+///
+/// Export each frame with [`Self::to_fluid_particles`], [`Self::to_splat_field`], or
+/// [`Self::to_liquid_surface`].
+#[derive(Clone, Debug)]
+pub struct MacFlipLiquid3 {
+    grid: MacFluidGrid3,
+    particles: Vec<MacFlipParticle>,
+    transfer: MacParticleTransfer,
+    flip_ratio: f64,
+    gravity: [f64; 3],
+    particle_radius: f64,
+    particles_per_axis: usize,
+    rng: SampleRng,
+}
+
+impl MacFlipLiquid3 {
+    /// Creates a solver seeded with eight particles in every liquid cell of `grid`.
+    ///
+    /// The grid's liquid level set, solids, face velocities, timestep, and pressure settings are
+    /// kept. Particles start with the grid's face velocity at their position.
+    #[must_use]
+    pub fn new(grid: MacFluidGrid3) -> Self {
+        Self::seeded(grid, DEFAULT_PARTICLES_PER_AXIS, 0)
+    }
+
+    /// Creates a solver seeded with `particles_per_axis³` jittered particles per liquid cell.
+    ///
+    /// Particles are stratified inside each cell and kept only where the grid's trilinear liquid
+    /// level set is negative and outside solids. `seed` fixes the jitter.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `particles_per_axis` is zero.
+    #[must_use]
+    pub fn seeded(grid: MacFluidGrid3, particles_per_axis: usize, seed: u64) -> Self {
+        assert!(
+            particles_per_axis > 0,
+            "FLIP particles per axis must be non-zero"
+        );
+        let mut solver = Self {
+            grid,
+            particles: Vec::new(),
+            transfer: MacParticleTransfer::default(),
+            flip_ratio: DEFAULT_FLIP_RATIO,
+            gravity: DEFAULT_GRAVITY,
+            particle_radius: DEFAULT_PARTICLE_RADIUS,
+            particles_per_axis,
+            rng: SampleRng::new(seed),
+        };
+        let dims = solver.grid.dims();
+        let liquid_phi = solver.grid.liquid_phi().to_vec();
+        solver.seed_where(
+            |position| f64::from(sample_grid3(&liquid_phi, dims, position)) < 0.0,
+            None,
+        );
+        solver
+    }
+
+    /// Returns a copy with a different velocity transfer.
+    #[must_use]
+    pub const fn with_transfer(mut self, transfer: MacParticleTransfer) -> Self {
+        self.transfer = transfer;
+        self
+    }
+
+    /// Returns a copy with a different FLIP share for [`MacParticleTransfer::Flip`].
+    ///
+    /// `1.0` is pure FLIP and `0.0` is pure PIC.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `flip_ratio` is outside `0.0..=1.0`.
+    #[must_use]
+    pub fn with_flip_ratio(mut self, flip_ratio: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&flip_ratio),
+            "FLIP ratio must be in 0..=1"
+        );
+        self.flip_ratio = flip_ratio;
+        self
+    }
+
+    /// Returns a copy with a different gravity acceleration in world units per second squared.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any component is not finite.
+    #[must_use]
+    pub fn with_gravity(mut self, gravity: [f64; 3]) -> Self {
+        assert!(
+            gravity.into_iter().all(f64::is_finite),
+            "FLIP gravity must be finite"
+        );
+        self.gravity = gravity;
+        self
+    }
+
+    /// Returns a copy with a different particle radius in cells.
+    ///
+    /// The radius sets how far each particle pushes the liquid level set and the splat radius of
+    /// exported [`FluidParticle`]s.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `radius` is not positive and finite.
+    #[must_use]
+    pub fn with_particle_radius(mut self, radius: f64) -> Self {
+        assert!(
+            radius.is_finite() && radius > 0.0,
+            "FLIP particle radius must be positive and finite"
+        );
+        self.particle_radius = radius;
+        self.rebuild_liquid_phi();
+        self
+    }
+
+    /// Returns the underlying MAC grid.
+    #[must_use]
+    pub const fn grid(&self) -> &MacFluidGrid3 {
+        &self.grid
+    }
+
+    /// Returns the underlying MAC grid for changing solids or solver settings.
+    ///
+    /// The liquid level set is rebuilt from the particles every step, so liquid edits made here
+    /// are overwritten; add liquid with [`Self::seed_liquid_sdf`] instead.
+    pub const fn grid_mut(&mut self) -> &mut MacFluidGrid3 {
+        &mut self.grid
+    }
+
+    /// Returns the marker particles.
+    #[must_use]
+    pub fn particles(&self) -> &[MacFlipParticle] {
+        &self.particles
+    }
+
+    /// Returns the velocity transfer.
+    #[must_use]
+    pub const fn transfer(&self) -> MacParticleTransfer {
+        self.transfer
+    }
+
+    /// Returns the FLIP share used by [`MacParticleTransfer::Flip`].
+    #[must_use]
+    pub const fn flip_ratio(&self) -> f64 {
+        self.flip_ratio
+    }
+
+    /// Returns gravity in world units per second squared.
+    #[must_use]
+    pub const fn gravity(&self) -> [f64; 3] {
+        self.gravity
+    }
+
+    /// Returns the particle radius in cells.
+    #[must_use]
+    pub const fn particle_radius(&self) -> f64 {
+        self.particle_radius
+    }
+
+    /// Returns how many particles are seeded along each axis of a liquid cell.
+    #[must_use]
+    pub const fn particles_per_axis(&self) -> usize {
+        self.particles_per_axis
+    }
+
+    /// Adds liquid particles inside a cell-coordinate signed distance function.
+    ///
+    /// Cells that already hold particles are skipped, so repeated calls act like an emitter that
+    /// only fills the gaps. Returns the number of particles added.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `velocity` is not finite or if `sdf` returns a non-finite value.
+    pub fn seed_liquid_sdf<F>(&mut self, velocity: [f64; 3], mut sdf: F) -> usize
+    where
+        F: FnMut([f64; 3]) -> f64,
+    {
+        assert!(
+            velocity.into_iter().all(f64::is_finite),
+            "FLIP seed velocity must be finite"
+        );
+        self.seed_where(
+            |position| {
+                let value = sdf(position);
+                assert!(value.is_finite(), "FLIP seed SDF values must be finite");
+                value < 0.0
+            },
+            Some(velocity),
+        )
+    }
+
+    /// Adds a sphere of liquid particles moving at `velocity`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `center` or `velocity` is not finite or if `radius` is not positive and finite.
+    pub fn seed_liquid_sphere(
+        &mut self,
+        center: [f64; 3],
+        radius: f64,
+        velocity: [f64; 3],
+    ) -> usize {
+        assert!(
+            center.into_iter().all(f64::is_finite),
+            "FLIP seed sphere center must be finite"
+        );
+        assert!(
+            radius.is_finite() && radius > 0.0,
+            "FLIP seed sphere radius must be positive and finite"
+        );
+        self.seed_liquid_sdf(velocity, |cell| {
+            let dx = cell[0] - center[0];
+            let dy = cell[1] - center[1];
+            let dz = cell[2] - center[2];
+            (dx * dx + dy * dy + dz * dz).sqrt() - radius
+        })
+    }
+
+    /// Returns the largest particle speed.
+    #[must_use]
+    pub fn max_particle_speed(&self) -> f64 {
+        self.particles
+            .iter()
+            .map(|particle| {
+                let [x, y, z] = particle.velocity;
+                x.hypot(y).hypot(z)
+            })
+            .fold(0.0, f64::max)
+    }
+
+    /// Returns the number of substeps needed to keep particles within `cfl_number` cells per step.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cfl_number` is not positive and finite.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn cfl_substeps(&self, cfl_number: f64) -> usize {
+        assert!(
+            cfl_number.is_finite() && cfl_number > 0.0,
+            "FLIP CFL number must be positive and finite"
+        );
+        let cell_size = self.grid.cell_size();
+        let rate = self
+            .particles
+            .iter()
+            .flat_map(|particle| {
+                (0..3).map(move |axis| particle.velocity[axis].abs() / cell_size[axis])
+            })
+            .fold(0.0, f64::max);
+        (self.grid.dt() * rate / cfl_number).ceil().max(1.0) as usize
+    }
+
+    /// Advances the liquid by one grid timestep and returns the pressure projection diagnostics.
+    pub fn step(&mut self) -> MacProjectionStats {
+        let dt = self.grid.dt();
+        self.rebuild_liquid_phi();
+        let [u, v, w] = self.particles_to_faces();
+        self.grid.set_face_velocities(u, v, w);
+        self.grid
+            .extrapolate_velocity_into_air(VELOCITY_EXTRAPOLATION_LAYERS);
+        let previous = [
+            self.grid.u().to_vec(),
+            self.grid.v().to_vec(),
+            self.grid.w().to_vec(),
+        ];
+
+        self.apply_gravity(dt);
+        if self.grid.liquid_viscosity() > 0.0 {
+            self.grid
+                .apply_liquid_viscosity(self.grid.liquid_viscosity());
+        }
+        let projection = self.grid.project_liquid_velocity();
+        self.grid
+            .extrapolate_velocity_into_air(VELOCITY_EXTRAPOLATION_LAYERS);
+
+        self.faces_to_particles(&previous);
+        self.advect_particles(dt);
+        self.rebuild_liquid_phi();
+        projection
+    }
+
+    /// Advances one grid timestep in enough substeps to satisfy a particle CFL limit.
+    ///
+    /// Call once per output frame with the grid timestep set to the frame duration.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cfl_number` is not positive and finite.
+    pub fn step_cfl(&mut self, cfl_number: f64) -> Vec<MacProjectionStats> {
+        let original_dt = self.grid.dt();
+        let substeps = self.cfl_substeps(cfl_number);
+        self.grid.set_dt(original_dt / usize_to_f64(substeps));
+        let stats = (0..substeps).map(|_| self.step()).collect();
+        self.grid.set_dt(original_dt);
+        stats
+    }
+
+    /// Exports the particles as world-space splats for [`ParticleSplatField`] or
+    /// [`LiquidSurface`].
+    ///
+    /// Cell centers map to voxel centers of `bounds`, matching
+    /// [`MacFluidGrid3::to_density_grid`]. Each splat has unit density and the particle radius
+    /// scaled by the smallest voxel size.
+    #[must_use]
+    pub fn to_fluid_particles(&self, bounds: GridBounds) -> Vec<FluidParticle> {
+        let dims = self.grid.dims();
+        let extent = bounds.extent();
+        let voxel = [
+            extent.x() / usize_to_f64(dims[0]),
+            extent.y() / usize_to_f64(dims[1]),
+            extent.z() / usize_to_f64(dims[2]),
+        ];
+        let radius = self.particle_radius * voxel[0].min(voxel[1]).min(voxel[2]);
+        self.particles
+            .iter()
+            .map(|particle| {
+                let [x, y, z] = particle.position;
+                let position = Point::new(
+                    bounds.min.x() + (x + 0.5) * voxel[0],
+                    bounds.min.y() + (y + 0.5) * voxel[1],
+                    bounds.min.z() + (z + 0.5) * voxel[2],
+                );
+                FluidParticle::new(position, radius, 1.0)
+            })
+            .collect()
+    }
+
+    /// Exports the particles as a renderable density field over `bounds`.
+    #[must_use]
+    pub fn to_splat_field(&self, bounds: GridBounds) -> ParticleSplatField {
+        ParticleSplatField::new(self.to_fluid_particles(bounds))
+    }
+
+    /// Exports the particles as a liquid-surface builder over `bounds`.
+    #[must_use]
+    pub fn to_liquid_surface(&self, bounds: GridBounds) -> LiquidSurface {
+        LiquidSurface::from_particles(self.to_fluid_particles(bounds)).with_bounds(bounds)
+    }
+
+    fn grid_velocity_sampler(&self) -> FaceVelocitySampler<'_> {
+        FaceVelocitySampler {
+            grid: &self.grid,
+            u: self.grid.u(),
+            v: self.grid.v(),
+            w: self.grid.w(),
+        }
+    }
+
+    fn seed_where<I>(&mut self, mut inside: I, velocity: Option<[f64; 3]>) -> usize
+    where
+        I: FnMut([f64; 3]) -> bool,
+    {
+        let dims = self.grid.dims();
+        let mut occupied = vec![false; dims[0] * dims[1] * dims[2]];
+        for particle in &self.particles {
+            occupied[self.cell_of(particle.position)] = true;
+        }
+
+        let per_axis = usize_to_f64(self.particles_per_axis);
+        let mut positions = Vec::new();
+        for z in 0..dims[2] {
+            for y in 0..dims[1] {
+                for x in 0..dims[0] {
+                    let cell = cell_index_for_dims3(dims, x, y, z);
+                    if occupied[cell] || self.grid.flags()[cell].is_solid() {
+                        continue;
+                    }
+                    for sz in 0..self.particles_per_axis {
+                        for sy in 0..self.particles_per_axis {
+                            for sx in 0..self.particles_per_axis {
+                                let position = [
+                                    usize_to_f64(x) - 0.5
+                                        + (usize_to_f64(sx) + self.rng.random_double()) / per_axis,
+                                    usize_to_f64(y) - 0.5
+                                        + (usize_to_f64(sy) + self.rng.random_double()) / per_axis,
+                                    usize_to_f64(z) - 0.5
+                                        + (usize_to_f64(sz) + self.rng.random_double()) / per_axis,
+                                ];
+                                if inside(position) && self.solid_phi_at(position) > 0.0 {
+                                    positions.push(position);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let sampler = self.grid_velocity_sampler();
+        let seeded: Vec<_> = positions
+            .into_iter()
+            .map(|position| {
+                let velocity = velocity.unwrap_or_else(|| sampler.sample(position));
+                MacFlipParticle::new(position, velocity)
+            })
+            .collect();
+        let added = seeded.len();
+        self.particles.extend(seeded);
+        if added > 0 {
+            self.rebuild_liquid_phi();
+        }
+        added
+    }
+
+    fn rebuild_liquid_phi(&mut self) {
+        let dims = self.grid.dims();
+        let radius = self.particle_radius;
+        let mut phi = vec![AIR_PHI; dims[0] * dims[1] * dims[2]];
+        for particle in &self.particles {
+            let [px, py, pz] = particle.position;
+            let low = |value: f64, dim: usize| clamped_cell(value - radius, dim);
+            let high = |value: f64, dim: usize| clamped_cell(value + radius + 1.0, dim);
+            for z in low(pz, dims[2])..=high(pz, dims[2]) {
+                for y in low(py, dims[1])..=high(py, dims[1]) {
+                    for x in low(px, dims[0])..=high(px, dims[0]) {
+                        let dx = usize_to_f64(x) - px;
+                        let dy = usize_to_f64(y) - py;
+                        let dz = usize_to_f64(z) - pz;
+                        let distance = (dx * dx + dy * dy + dz * dz).sqrt() - radius;
+                        let cell = &mut phi[cell_index_for_dims3(dims, x, y, z)];
+                        *cell = cell.min(distance);
+                    }
+                }
+            }
+        }
+        self.grid
+            .set_liquid_phi(phi.into_iter().map(finite_f32).collect());
+    }
+
+    fn particles_to_faces(&self) -> [Vec<f32>; 3] {
+        let dims = self.grid.dims();
+        std::array::from_fn(|axis| {
+            let face_dims = face_dims(dims, axis);
+            let count = face_dims[0] * face_dims[1] * face_dims[2];
+            let mut momentum = vec![0.0; count];
+            let mut weight = vec![0.0; count];
+            for particle in &self.particles {
+                let stencil = FaceStencil::new(dims, axis, particle.position);
+                for corner in 0..8 {
+                    let mut velocity = particle.velocity[axis];
+                    if self.transfer == MacParticleTransfer::Apic {
+                        velocity += dot(particle.affine[axis], stencil.offsets[corner]);
+                    }
+                    momentum[stencil.indices[corner]] += stencil.weights[corner] * velocity;
+                    weight[stencil.indices[corner]] += stencil.weights[corner];
+                }
+            }
+            momentum
+                .into_iter()
+                .zip(weight)
+                .map(|(momentum, weight)| {
+                    if weight > TRANSFER_WEIGHT_EPSILON {
+                        finite_f32(momentum / weight)
+                    } else {
+                        0.0
+                    }
+                })
+                .collect()
+        })
+    }
+
+    fn apply_gravity(&mut self, dt: f64) {
+        let faces = [self.grid.u(), self.grid.v(), self.grid.w()];
+        let weights = [
+            self.grid.u_weights(),
+            self.grid.v_weights(),
+            self.grid.w_weights(),
+        ];
+        let [u, v, w]: [Vec<f32>; 3] = std::array::from_fn(|axis| {
+            let impulse = dt * self.gravity[axis];
+            faces[axis]
+                .iter()
+                .zip(weights[axis])
+                .map(|(velocity, weight)| {
+                    if *weight > 0.0 {
+                        finite_f32(f64::from(*velocity) + impulse)
+                    } else {
+                        0.0
+                    }
+                })
+                .collect()
+        });
+        self.grid.set_face_velocities(u, v, w);
+    }
+
+    fn faces_to_particles(&mut self, previous: &[Vec<f32>; 3]) {
+        let dims = self.grid.dims();
+        let current = [self.grid.u(), self.grid.v(), self.grid.w()];
+        for particle in &mut self.particles {
+            for axis in 0..3 {
+                let stencil = FaceStencil::new(dims, axis, particle.position);
+                let mut velocity = 0.0;
+                let mut old_velocity = 0.0;
+                let mut gradient = [0.0; 3];
+                for corner in 0..8 {
+                    let index = stencil.indices[corner];
+                    let face_velocity = f64::from(current[axis][index]);
+                    velocity += stencil.weights[corner] * face_velocity;
+                    old_velocity += stencil.weights[corner] * f64::from(previous[axis][index]);
+                    for (component, slope) in gradient.iter_mut().enumerate() {
+                        *slope += stencil.gradients[corner][component] * face_velocity;
+                    }
+                }
+                match self.transfer {
+                    MacParticleTransfer::Flip => {
+                        let flip = particle.velocity[axis] + velocity - old_velocity;
+                        particle.velocity[axis] =
+                            self.flip_ratio * flip + (1.0 - self.flip_ratio) * velocity;
+                        particle.affine[axis] = [0.0; 3];
+                    }
+                    MacParticleTransfer::Apic => {
+                        particle.velocity[axis] = velocity;
+                        particle.affine[axis] = gradient;
+                    }
+                }
+            }
+        }
+    }
+
+    fn advect_particles(&mut self, dt: f64) {
+        let cell_size = self.grid.cell_size();
+        let sampler = self.grid_velocity_sampler();
+        let mut positions: Vec<[f64; 3]> = self
+            .particles
+            .iter()
+            .map(|particle| {
+                let start = particle.position;
+                let first = sampler.sample(start);
+                let midpoint: [f64; 3] = std::array::from_fn(|axis| {
+                    start[axis] + 0.5 * dt * first[axis] / cell_size[axis]
+                });
+                let second = sampler.sample(midpoint);
+                std::array::from_fn(|axis| start[axis] + dt * second[axis] / cell_size[axis])
+            })
+            .collect();
+        for position in &mut positions {
+            *position = self.constrain_position(*position);
+        }
+        for (particle, position) in self.particles.iter_mut().zip(positions) {
+            particle.position = position;
+        }
+    }
+
+    fn constrain_position(&self, position: [f64; 3]) -> [f64; 3] {
+        let dims = self.grid.dims();
+        let clamp = |position: [f64; 3]| -> [f64; 3] {
+            std::array::from_fn(|axis| {
+                position[axis].clamp(
+                    -0.5 + WALL_MARGIN,
+                    usize_to_f64(dims[axis]) - 0.5 - WALL_MARGIN,
+                )
+            })
+        };
+        let mut position = clamp(position);
+        let phi = self.solid_phi_at(position);
+        if phi < SOLID_MARGIN {
+            let step = 0.5;
+            let gradient: [f64; 3] = std::array::from_fn(|axis| {
+                let mut ahead = position;
+                let mut behind = position;
+                ahead[axis] += step;
+                behind[axis] -= step;
+                (self.solid_phi_at(ahead) - self.solid_phi_at(behind)) / (2.0 * step)
+            });
+            let length = dot(gradient, gradient).sqrt();
+            if length > f64::EPSILON {
+                let push = (SOLID_MARGIN - phi) / length;
+                position = clamp(std::array::from_fn(|axis| {
+                    position[axis] + push * gradient[axis] / length
+                }));
+            }
+        }
+        position
+    }
+
+    fn solid_phi_at(&self, position: [f64; 3]) -> f64 {
+        f64::from(sample_grid3(
+            self.grid.solid_phi(),
+            self.grid.dims(),
+            position,
+        ))
+    }
+
+    fn cell_of(&self, position: [f64; 3]) -> usize {
+        let dims = self.grid.dims();
+        cell_index_for_dims3(
+            dims,
+            clamped_cell(position[0] + 0.5, dims[0]),
+            clamped_cell(position[1] + 0.5, dims[1]),
+            clamped_cell(position[2] + 0.5, dims[2]),
+        )
+    }
+}
+
+struct FaceVelocitySampler<'a> {
+    grid: &'a MacFluidGrid3,
+    u: &'a [f32],
+    v: &'a [f32],
+    w: &'a [f32],
+}
+
+impl FaceVelocitySampler<'_> {
+    fn sample(&self, position: [f64; 3]) -> [f64; 3] {
+        self.grid
+            .velocity_at_position_from_faces(self.u, self.v, self.w, position)
+    }
+}
+
+/// Trilinear weights from one particle to the eight surrounding faces of one velocity component.
+struct FaceStencil {
+    indices: [usize; 8],
+    weights: [f64; 8],
+    gradients: [[f64; 3]; 8],
+    offsets: [[f64; 3]; 8],
+}
+
+impl FaceStencil {
+    fn new(dims: [usize; 3], axis: usize, position: [f64; 3]) -> Self {
+        let face_dims = face_dims(dims, axis);
+        let shift: [f64; 3] = std::array::from_fn(|a| if a == axis { 0.5 } else { 0.0 });
+        let mut base = [0; 3];
+        let mut next = [0; 3];
+        let mut fraction = [0.0; 3];
+        for a in 0..3 {
+            let coordinate = (position[a] + shift[a]).clamp(0.0, usize_to_f64(face_dims[a] - 1));
+            base[a] = clamped_cell(coordinate, face_dims[a]);
+            next[a] = (base[a] + 1).min(face_dims[a] - 1);
+            fraction[a] = coordinate - usize_to_f64(base[a]);
+        }
+
+        let mut stencil = Self {
+            indices: [0; 8],
+            weights: [0.0; 8],
+            gradients: [[0.0; 3]; 8],
+            offsets: [[0.0; 3]; 8],
+        };
+        for corner in 0..8 {
+            let mut node = [0; 3];
+            let mut axis_weight = [0.0; 3];
+            let mut axis_slope = [0.0; 3];
+            for a in 0..3 {
+                if corner >> a & 1 == 0 {
+                    node[a] = base[a];
+                    axis_weight[a] = 1.0 - fraction[a];
+                    axis_slope[a] = -1.0;
+                } else {
+                    node[a] = next[a];
+                    axis_weight[a] = fraction[a];
+                    axis_slope[a] = 1.0;
+                }
+            }
+            stencil.indices[corner] = cell_index_for_dims3(face_dims, node[0], node[1], node[2]);
+            stencil.weights[corner] = axis_weight[0] * axis_weight[1] * axis_weight[2];
+            stencil.gradients[corner] = [
+                axis_slope[0] * axis_weight[1] * axis_weight[2],
+                axis_weight[0] * axis_slope[1] * axis_weight[2],
+                axis_weight[0] * axis_weight[1] * axis_slope[2],
+            ];
+            stencil.offsets[corner] =
+                std::array::from_fn(|a| usize_to_f64(node[a]) - shift[a] - position[a]);
+        }
+        stencil
+    }
+}
+
+fn face_dims(dims: [usize; 3], axis: usize) -> [usize; 3] {
+    let mut face_dims = dims;
+    face_dims[axis] += 1;
+    face_dims
+}
+
+fn dot(lhs: [f64; 3], rhs: [f64; 3]) -> f64 {
+    lhs[0] * rhs[0] + lhs[1] * rhs[1] + lhs[2] * rhs[2]
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn clamped_cell(value: f64, dim: usize) -> usize {
+    if value.is_nan() {
+        return 0;
+    }
+    value.floor().clamp(0.0, usize_to_f64(dim - 1)) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        gmath::vector::Point,
+        graphics::raytracing::volume::{field::DensityField, grid::GridBounds},
+    };
+
+    fn pool_grid() -> MacFluidGrid3 {
+        let mut grid = MacFluidGrid3::new([8, 8, 8]).with_dt(0.02);
+        grid.set_liquid_sdf(|cell| cell[1] - 3.5);
+        grid
+    }
+
+    fn particle_extent(solver: &MacFlipLiquid3, axis: usize) -> (f64, f64) {
+        solver
+            .particles()
+            .iter()
+            .map(|particle| particle.position[axis])
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), value| {
+                (low.min(value), high.max(value))
+            })
+    }
+
+    #[test]
+    fn flip_seeds_stratified_particles_in_liquid_cells() {
+        let solver = MacFlipLiquid3::new(pool_grid());
+
+        assert_eq!(solver.particles().len(), 8 * 4 * 8 * 8);
+        let (low, high) = particle_extent(&solver, 1);
+        assert!(low >= -0.5 && high < 3.5, "{low}..{high}");
+        assert!(solver.grid().is_liquid([3, 3, 3]));
+        assert!(solver.grid().is_air([3, 5, 3]));
+
+        let mut solver = solver;
+        assert_eq!(solver.seed_liquid_sdf([0.0; 3], |cell| cell[1] - 3.5), 0);
+    }
+
+    #[test]
+    fn flip_resting_pool_stays_level_under_gravity() {
+        for transfer in [MacParticleTransfer::Flip, MacParticleTransfer::Apic] {
+            let mut solver = MacFlipLiquid3::new(pool_grid()).with_transfer(transfer);
+            let count = solver.particles().len();
+            for _ in 0..20 {
+                let stats = solver.step();
+                assert!(stats.divergence_after_l2 < 1.0e-3, "{stats:?}");
+            }
+
+            assert_eq!(solver.particles().len(), count);
+            assert!(
+                solver.max_particle_speed() < 0.05,
+                "{transfer:?}: {}",
+                solver.max_particle_speed()
+            );
+            let (_, high) = particle_extent(&solver, 1);
+            assert!(high < 3.6, "{transfer:?}: {high}");
+        }
+    }
+
+    #[test]
+    fn flip_falling_drop_lands_and_avoids_solids() {
+        let mut grid = MacFluidGrid3::new([10, 12, 10]).with_dt(0.05);
+        grid.set_solid_sphere([4.5, 2.0, 4.5], 2.0);
+        let mut solver = MacFlipLiquid3::new(grid);
+        let added = solver.seed_liquid_sphere([4.5, 8.0, 4.5], 1.5, [0.0, -6.0, 0.0]);
+        assert!(added > 0);
+
+        let (start, _) = particle_extent(&solver, 1);
+        let mut substeps = 0;
+        for _ in 0..20 {
+            substeps += solver.step_cfl(0.25).len();
+        }
+        assert!(substeps > 20);
+        assert!((solver.grid().dt() - 0.05).abs() < 1.0e-12);
+
+        let (end, _) = particle_extent(&solver, 1);
+        assert!(end < start, "{start} -> {end}");
+        for particle in solver.particles() {
+            assert!(particle.position.iter().all(|value| value.is_finite()));
+            assert!(solver.solid_phi_at(particle.position) > 0.0, "{particle:?}");
+            for axis in 0..3 {
+                let upper = usize_to_f64(solver.grid().dims()[axis]) - 0.5;
+                assert!((-0.5..upper).contains(&particle.position[axis]));
+            }
+        }
+    }
+
+    #[test]
+    fn flip_apic_carries_rigid_rotation_gradient() {
+        let mut solver = MacFlipLiquid3::new(MacFluidGrid3::new([9, 9, 9])).with_gravity([0.0; 3]);
+        solver.seed_liquid_sdf([0.0; 3], |cell| {
+            let dx = cell[0] - 4.0;
+            let dy = cell[1] - 4.0;
+            (dx * dx + dy * dy).sqrt() - 3.0
+        });
+        for particle in &mut solver.particles {
+            let dx = particle.position[0] - 4.0;
+            let dy = particle.position[1] - 4.0;
+            particle.velocity = [-dy, dx, 0.0];
+        }
+        solver.step();
+
+        let near_center = solver
+            .particles()
+            .iter()
+            .find(|particle| {
+                (particle.position[0] - 4.0).abs() < 1.0 && (particle.position[1] - 4.0).abs() < 1.0
+            })
+            .expect("particle near the rotation axis");
+        assert!(
+            (near_center.affine[0][1] + 1.0).abs() < 0.2,
+            "{near_center:?}"
+        );
+        assert!(
+            (near_center.affine[1][0] - 1.0).abs() < 0.2,
+            "{near_center:?}"
+        );
+    }
+
+    #[test]
+    fn flip_exports_world_space_splats_for_rendering() {
+        let solver = MacFlipLiquid3::new(pool_grid());
+        let bounds = GridBounds::new(Point::new(0.0, 0.0, 0.0), Point::new(2.0, 2.0, 2.0));
+
+        let splats = solver.to_fluid_particles(bounds);
+        assert_eq!(splats.len(), solver.particles().len());
+        assert!((splats[0].radius - 0.9 * 0.25).abs() < 1.0e-12);
+        assert!(splats.iter().all(|splat| bounds.contains(splat.position)));
+
+        let field = solver.to_splat_field(bounds);
+        assert!(field.density(Point::new(1.0, 0.4, 1.0), 0.0) > 0.0);
+        assert!(field.density(Point::new(1.0, 1.8, 1.0), 0.0) <= 0.0);
+
+        let surface = solver
+            .to_liquid_surface(bounds)
+            .with_resolution([16, 16, 16])
+            .build_triangle_mesh();
+        assert!(!surface.is_empty());
+    }
+}
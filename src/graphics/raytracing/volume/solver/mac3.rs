@@ -1,7 +1,7 @@
-use super::{DEFAULT_DT, finite_f32, nonnegative_f32, usize_to_f64};
+use super::{DEFAULT_DT, finite_f32, multigrid::PressureMultigrid, nonnegative_f32, usize_to_f64};
 use crate::graphics::raytracing::volume::grid::{GridBounds, GridDensityField, GridInterpolation};
 
-use super::mac::{MacProjectionStats, MacStepStats};
+use super::mac::{MacPressurePreconditioner, MacProjectionStats, MacStepStats};
 
 const DEFAULT_PRESSURE_ITERATIONS: usize = 240;
 const DEFAULT_PRESSURE_TOLERANCE: f64 = 1.0e-5;
@@ -109,6 +109,31 @@ impl MacScalarGrid3 {
         );
         f64::from(self.samples[cell_index_for_dims3(self.dims, cell[0], cell[1], cell[2])])
     }
+
+    /// Maps solver temperature to an absolute temperature field in kelvin.
+    ///
+    /// The MAC solver stores temperature relative to ambient air, so each sample becomes
+    /// `ambient_kelvin + kelvin_per_unit * sample`, clamped at absolute zero. The result samples
+    /// trilinearly and can drive [`BlackbodyEmission`](crate::graphics::raytracing::BlackbodyEmission).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ambient_kelvin` or `kelvin_per_unit` is not finite.
+    #[must_use]
+    pub fn to_kelvin_field(&self, ambient_kelvin: f64, kelvin_per_unit: f64) -> GridDensityField {
+        assert!(
+            ambient_kelvin.is_finite() && kelvin_per_unit.is_finite(),
+            "temperature mapping must be finite"
+        );
+        #[allow(clippy::cast_possible_truncation)]
+        let kelvin = self
+            .samples
+            .iter()
+            .map(|sample| (ambient_kelvin + kelvin_per_unit * f64::from(*sample)).max(0.0) as f32)
+            .collect();
+        GridDensityField::new(self.bounds, self.dims, kelvin)
+            .with_interpolation(GridInterpolation::Trilinear)
+    }
 }
 
 /// Three-dimensional smoke/liquid solver using a Marker-and-Cell layout.
@@ -126,6 +151,7 @@ pub struct MacFluidGrid3 {
     cell_size: [f64; 3],
     pressure_iterations: usize,
     pressure_tolerance: f64,
+    pressure_preconditioner: MacPressurePreconditioner,
     density: Vec<f32>,
     temperature: Vec<f32>,
     fuel: Vec<f32>,
@@ -163,6 +189,7 @@ impl MacFluidGrid3 {
             cell_size: [1.0, 1.0, 1.0],
             pressure_iterations: DEFAULT_PRESSURE_ITERATIONS,
             pressure_tolerance: DEFAULT_PRESSURE_TOLERANCE,
+            pressure_preconditioner: MacPressurePreconditioner::Diagonal,
             density: vec![0.0; cell_count],
             temperature: vec![0.0; cell_count],
             fuel: vec![0.0; cell_count],
@@ -246,6 +273,16 @@ impl MacFluidGrid3 {
         self
     }
 
+    /// Returns a copy with a different pressure preconditioner for smoke and liquid projection.
+    #[must_use]
+    pub const fn with_pressure_preconditioner(
+        mut self,
+        pressure_preconditioner: MacPressurePreconditioner,
+    ) -> Self {
+        self.pressure_preconditioner = pressure_preconditioner;
+        self
+    }
+
     /// Returns a copy with explicit liquid viscosity used by [`Self::step_liquid`].
     ///
     /// # Panics
@@ -279,6 +316,12 @@ impl MacFluidGrid3 {
         self.cell_size
     }
 
+    /// Returns the pressure preconditioner.
+    #[must_use]
+    pub const fn pressure_preconditioner(&self) -> MacPressurePreconditioner {
+        self.pressure_preconditioner
+    }
+
     /// Returns explicit liquid viscosity used by [`Self::step_liquid`].
     #[must_use]
     pub const fn liquid_viscosity(&self) -> f64 {
@@ -804,6 +847,7 @@ impl MacFluidGrid3 {
             divergence_after_l2,
             pressure_residual_l2,
             iterations,
+            preconditioner: self.pressure_preconditioner,
         };
         self.last_projection
     }
@@ -825,6 +869,7 @@ impl MacFluidGrid3 {
             divergence_after_l2,
             pressure_residual_l2,
             iterations,
+            preconditioner: self.pressure_preconditioner,
         };
         self.last_liquid_projection
     }
@@ -986,6 +1031,21 @@ impl MacFluidGrid3 {
         velocities
     }
 
+    pub(super) fn set_dt(&mut self, dt: f64) {
+        debug_assert!(dt.is_finite() && dt > 0.0);
+        self.dt = dt;
+    }
+
+    pub(super) fn set_face_velocities(&mut self, u: Vec<f32>, v: Vec<f32>, w: Vec<f32>) {
+        debug_assert_eq!(u.len(), self.u.len());
+        debug_assert_eq!(v.len(), self.v.len());
+        debug_assert_eq!(w.len(), self.w.len());
+        self.u = u;
+        self.v = v;
+        self.w = w;
+        self.apply_solid_velocity_constraints();
+    }
+
     fn rebuild_flags_from_phi(&mut self) {
         for ((flag, solid_phi), liquid_phi) in self
             .flags
@@ -1228,9 +1288,10 @@ impl MacFluidGrid3 {
             &self.flags,
         );
 
+        let multigrid = self.pressure_multigrid(false);
         let mut residual = rhs.clone();
         let mut z_preconditioned = vec![0.0_f64; cell_count];
-        self.apply_pressure_preconditioner(&residual, &mut z_preconditioned);
+        self.precondition_pressure(multigrid.as_ref(), &residual, &mut z_preconditioned);
         let mut direction = z_preconditioned.clone();
         let mut rz = dot_active(&residual, &z_preconditioned, &self.flags);
         let mut residual_l2 = active_l2(&residual, &self.flags);
@@ -1260,7 +1321,7 @@ impl MacFluidGrid3 {
                 break;
             }
 
-            self.apply_pressure_preconditioner(&residual, &mut z_preconditioned);
+            self.precondition_pressure(multigrid.as_ref(), &residual, &mut z_preconditioned);
             let rz_next = dot_active(&residual, &z_preconditioned, &self.flags);
             if rz.abs() <= f64::MIN_POSITIVE {
                 break;
@@ -1310,9 +1371,10 @@ impl MacFluidGrid3 {
             );
         }
 
+        let multigrid = self.pressure_multigrid(true);
         let mut residual = rhs.clone();
         let mut z_preconditioned = vec![0.0_f64; cell_count];
-        self.apply_liquid_pressure_preconditioner(&residual, &mut z_preconditioned);
+        self.precondition_liquid_pressure(multigrid.as_ref(), &residual, &mut z_preconditioned);
         let mut direction = z_preconditioned.clone();
         let mut rz = dot_liquid(&residual, &z_preconditioned, &self.flags);
         let mut residual_l2 = liquid_l2(&residual, &self.flags);
@@ -1342,7 +1404,7 @@ impl MacFluidGrid3 {
                 break;
             }
 
-            self.apply_liquid_pressure_preconditioner(&residual, &mut z_preconditioned);
+            self.precondition_liquid_pressure(multigrid.as_ref(), &residual, &mut z_preconditioned);
             let rz_next = dot_liquid(&residual, &z_preconditioned, &self.flags);
             if rz.abs() <= f64::MIN_POSITIVE {
                 break;
@@ -1360,6 +1422,78 @@ impl MacFluidGrid3 {
         (iterations, residual_l2)
     }
 
+    fn pressure_multigrid(&self, liquid: bool) -> Option<PressureMultigrid> {
+        if self.pressure_preconditioner != MacPressurePreconditioner::Multigrid {
+            return None;
+        }
+        let active: Vec<bool> = self
+            .flags
+            .iter()
+            .map(|flag| {
+                if liquid {
+                    flag.is_liquid()
+                } else {
+                    !flag.is_solid()
+                }
+            })
+            .collect();
+        let mut diagonal = vec![0.0; active.len()];
+        for z in 0..self.dims[2] {
+            for y in 0..self.dims[1] {
+                for x in 0..self.dims[0] {
+                    let index = cell_index_for_dims3(self.dims, x, y, z);
+                    if active[index] {
+                        diagonal[index] = if liquid {
+                            self.liquid_pressure_diagonal(x, y, z)
+                        } else {
+                            self.pressure_diagonal(x, y, z)
+                        };
+                    }
+                }
+            }
+        }
+        Some(PressureMultigrid::new(
+            self.dims,
+            active,
+            diagonal,
+            |[x, y, z], axis| {
+                let weight = match axis {
+                    0 => self.u_weights[u_index_for_dims3(self.dims, x + 1, y, z)],
+                    1 => self.v_weights[v_index_for_dims3(self.dims, x, y + 1, z)],
+                    _ => self.w_weights[w_index_for_dims3(self.dims, x, y, z + 1)],
+                };
+                if liquid && weight <= FACE_WEIGHT_EPSILON {
+                    return 0.0;
+                }
+                f64::from(weight) / (self.cell_size[axis] * self.cell_size[axis])
+            },
+        ))
+    }
+
+    fn precondition_pressure(
+        &self,
+        multigrid: Option<&PressureMultigrid>,
+        residual: &[f64],
+        out: &mut [f64],
+    ) {
+        match multigrid {
+            Some(multigrid) => multigrid.apply(residual, out),
+            None => self.apply_pressure_preconditioner(residual, out),
+        }
+    }
+
+    fn precondition_liquid_pressure(
+        &self,
+        multigrid: Option<&PressureMultigrid>,
+        residual: &[f64],
+        out: &mut [f64],
+    ) {
+        match multigrid {
+            Some(multigrid) => multigrid.apply(residual, out),
+            None => self.apply_liquid_pressure_preconditioner(residual, out),
+        }
+    }
+
     fn apply_pressure_preconditioner(&self, residual: &[f64], out: &mut [f64]) {
         out.fill(0.0);
         for z in 0..self.dims[2] {
@@ -1836,7 +1970,7 @@ impl MacFluidGrid3 {
         self.apply_solid_velocity_constraints();
     }
 
-    fn extrapolate_velocity_into_air(&mut self, iterations: usize) {
+    pub(super) fn extrapolate_velocity_into_air(&mut self, iterations: usize) {
         let [width, height, depth] = self.dims;
         let mut u_known = vec![false; self.u.len()];
         for z in 0..depth {
@@ -1988,7 +2122,7 @@ impl MacFluidGrid3 {
         );
     }
 
-    fn velocity_at_position_from_faces(
+    pub(super) fn velocity_at_position_from_faces(
         &self,
         u_faces: &[f32],
         v_faces: &[f32],
@@ -2089,7 +2223,7 @@ fn w_count_for_dims3(dims: [usize; 3]) -> usize {
         .expect("3D MAC w grid dimensions overflow")
 }
 
-fn cell_index_for_dims3(dims: [usize; 3], x: usize, y: usize, z: usize) -> usize {
+pub(super) fn cell_index_for_dims3(dims: [usize; 3], x: usize, y: usize, z: usize) -> usize {
     x + dims[0] * (y + dims[1] * z)
 }
 
@@ -2371,7 +2505,7 @@ fn rescale_scalar_mass(samples: &mut [f32], flags: &[MacCellFlags], target_mass:
     }
 }
 
-fn sample_grid3(field: &[f32], dims: [usize; 3], position: [f64; 3]) -> f32 {
+pub(super) fn sample_grid3(field: &[f32], dims: [usize; 3], position: [f64; 3]) -> f32 {
     debug_assert_eq!(field.len(), cell_count_for_dims3(dims));
     let x = position[0].clamp(0.0, usize_to_f64(dims[0] - 1));
     let y = position[1].clamp(0.0, usize_to_f64(dims[1] - 1));
@@ -2752,6 +2886,84 @@ mod tests {
         assert!(!sim.is_solid([0, 0, 0]));
     }
 
+    fn swirl_grid(preconditioner: MacPressurePreconditioner) -> MacFluidGrid3 {
+        let mut sim = MacFluidGrid3::new([20, 20, 20])
+            .with_pressure_iterations(600)
+            .with_pressure_tolerance(1.0e-6)
+            .with_pressure_preconditioner(preconditioner);
+        sim.set_solid_sphere([8.0, 10.0, 11.0], 4.0);
+        for z in 1..19 {
+            for y in 1..19 {
+                for x in 1..19 {
+                    let [fx, fy, fz] = [x, y, z].map(|value| usize_to_f64(value) * 0.37);
+                    sim.set_u([x, y, z], fy.sin() + fz.cos());
+                    sim.set_v([x, y, z], (fx + fz).cos());
+                    sim.set_w([x, y, z], (fx - fy).sin());
+                }
+            }
+        }
+        sim
+    }
+
+    #[test]
+    fn mac3_multigrid_preconditioner_cuts_smoke_iterations() {
+        let mut diagonal = swirl_grid(MacPressurePreconditioner::Diagonal);
+        let mut multigrid = swirl_grid(MacPressurePreconditioner::Multigrid);
+        let diagonal_stats = diagonal.project_velocity();
+        let multigrid_stats = multigrid.project_velocity();
+
+        assert_eq!(
+            multigrid_stats.preconditioner,
+            MacPressurePreconditioner::Multigrid
+        );
+        assert!(
+            multigrid_stats.pressure_residual_l2 <= 1.0e-6,
+            "{multigrid_stats:?}"
+        );
+        assert!(
+            multigrid_stats.iterations * 4 < diagonal_stats.iterations,
+            "{multigrid_stats:?} vs {diagonal_stats:?}"
+        );
+        assert!(multigrid_stats.divergence_after_l2 < 1.0e-4);
+        for (diagonal, multigrid) in diagonal.u().iter().zip(multigrid.u()) {
+            assert!(
+                (diagonal - multigrid).abs() < 1.0e-3,
+                "{diagonal} != {multigrid}"
+            );
+        }
+    }
+
+    #[test]
+    fn mac3_multigrid_liquid_projection_keeps_free_surface_and_solids() {
+        let mut diagonal = swirl_grid(MacPressurePreconditioner::Diagonal);
+        let mut multigrid = swirl_grid(MacPressurePreconditioner::Multigrid);
+        for sim in [&mut diagonal, &mut multigrid] {
+            sim.set_liquid_sdf(|cell| cell[1] - 13.5);
+        }
+        let diagonal_stats = diagonal.project_liquid_velocity();
+        let multigrid_stats = multigrid.project_liquid_velocity();
+
+        assert!(
+            multigrid_stats.pressure_residual_l2 <= 1.0e-6,
+            "{multigrid_stats:?}"
+        );
+        assert!(
+            multigrid_stats.iterations * 4 < diagonal_stats.iterations,
+            "{multigrid_stats:?} vs {diagonal_stats:?}"
+        );
+        assert!(multigrid_stats.divergence_after_l2 < 1.0e-4);
+        for (pressure, flag) in multigrid.pressures().iter().zip(multigrid.flags()) {
+            if !flag.is_liquid() {
+                assert_close(f64::from(*pressure), 0.0);
+            }
+        }
+        for (face, weight) in multigrid.v().iter().zip(multigrid.v_weights()) {
+            if *weight <= FACE_WEIGHT_EPSILON {
+                assert_close(f64::from(*face), 0.0);
+            }
+        }
+    }
+
     #[test]
     fn mac3_free_surface_projection_reduces_liquid_divergence_and_leaves_air_pressure_zero() {
         let mut sim = MacFluidGrid3::new([8, 8, 8])
@@ -2957,6 +3169,9 @@ mod tests {
         assert_eq!(temperature.samples().len(), sim.temperatures().len());
         assert_close(temperature.sample_at([2, 1, 3]), 2.0);
         assert_close(temperature.sample_at([1, 1, 2]), -3.0);
+        let kelvin = temperature.to_kelvin_field(300.0, 500.0);
+        assert_close(kelvin.density(kelvin.cell_center(2, 1, 3), 0.0), 1300.0);
+        assert_close(kelvin.density(kelvin.cell_center(1, 1, 2), 0.0), 0.0);
         assert_close(fuel.density(fuel.cell_center(2, 1, 3), 0.0), 4.0);
         assert_eq!(velocities.len(), sim.densities().len());
     }
@@ -1,15 +1,19 @@
 //! Stable-fluid grid solvers that can export density fields for volume rendering.
 
 mod advection;
+mod flip;
 mod linear_solve;
 mod mac;
 mod mac3;
+mod multigrid;
 mod projection;
 
 use super::grid::{GridBounds, GridDensityField, GridInterpolation};
 
+pub use flip::{MacFlipLiquid3, MacFlipParticle, MacParticleTransfer};
 pub use mac::{
-    MacFluidEmitter, MacFluidGrid2, MacProjectionStats, MacScalarAdvection, MacStepStats,
+    MacFluidEmitter, MacFluidGrid2, MacPressurePreconditioner, MacProjectionStats,
+    MacScalarAdvection, MacStepStats,
 };
 pub use mac3::{MacCellFlags, MacFluidGrid3, MacScalarGrid3};
 
@@ -0,0 +1,258 @@
+//! Geometric multigrid V-cycle used to precondition MAC pressure solves.
+//!
+//! Every level stores a symmetric cell-centered 7-point operator. The finest level copies the
+//! solver's own matrix, so solid faces (zero coupling) and free-surface air cells (folded into
+//! the diagonal) are honored exactly. Coarser levels aggregate 2x2x2 blocks with the Galerkin
+//! product `Pᵀ A P` for piecewise-constant `P`, which keeps each level symmetric and diagonally
+//! dominant regardless of how irregular the active region is. Two-dimensional grids use a depth
+//! of one.
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+const COARSEST_DIM: usize = 4;
+const SMOOTHING_SWEEPS: usize = 2;
+const COARSEST_SWEEPS: usize = 32;
+const JACOBI_WEIGHT: f64 = 2.0 / 3.0;
+#[cfg(feature = "rayon")]
+const PARALLEL_CELL_THRESHOLD: usize = 4_096;
+
+/// Multigrid hierarchy for one pressure matrix.
+#[derive(Debug)]
+pub(super) struct PressureMultigrid {
+    levels: Vec<PressureLevel>,
+}
+
+#[derive(Debug)]
+struct PressureLevel {
+    dims: [usize; 3],
+    active: Vec<bool>,
+    diagonal: Vec<f64>,
+    // Coupling between each cell and its neighbor one step along +x, +y, and +z.
+    couplings: [Vec<f64>; 3],
+}
+
+impl PressureMultigrid {
+    /// Builds a hierarchy from the finest operator.
+    ///
+    /// `coupling(cell, axis)` returns the positive off-diagonal weight between an active cell and
+    /// its active `+axis` neighbor; it is only called when both cells are active.
+    pub(super) fn new<F>(
+        dims: [usize; 3],
+        active: Vec<bool>,
+        diagonal: Vec<f64>,
+        mut coupling: F,
+    ) -> Self
+    where
+        F: FnMut([usize; 3], usize) -> f64,
+    {
+        let cell_count = dims[0] * dims[1] * dims[2];
+        debug_assert_eq!(active.len(), cell_count);
+        debug_assert_eq!(diagonal.len(), cell_count);
+        let mut couplings = [
+            vec![0.0; cell_count],
+            vec![0.0; cell_count],
+            vec![0.0; cell_count],
+        ];
+        for z in 0..dims[2] {
+            for y in 0..dims[1] {
+                for x in 0..dims[0] {
+                    let cell = [x, y, z];
+                    let index = level_index(dims, cell);
+                    if !active[index] {
+                        continue;
+                    }
+                    for (axis, axis_couplings) in couplings.iter_mut().enumerate() {
+                        let mut neighbor = cell;
+                        neighbor[axis] += 1;
+                        if neighbor[axis] < dims[axis] && active[level_index(dims, neighbor)] {
+                            axis_couplings[index] = coupling(cell, axis);
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut levels = vec![PressureLevel {
+            dims,
+            active,
+            diagonal,
+            couplings,
+        }];
+        while let Some(coarse) = levels.last().and_then(PressureLevel::coarsen) {
+            levels.push(coarse);
+        }
+        Self { levels }
+    }
+
+    /// Applies one symmetric V-cycle to `residual`, writing the approximate solution to `out`.
+    pub(super) fn apply(&self, residual: &[f64], out: &mut [f64]) {
+        self.v_cycle(0, residual, out);
+    }
+
+    fn v_cycle(&self, level: usize, rhs: &[f64], solution: &mut [f64]) {
+        let grid = &self.levels[level];
+        solution.fill(0.0);
+        let Some(coarse) = self.levels.get(level + 1) else {
+            grid.smooth(rhs, solution, COARSEST_SWEEPS);
+            return;
+        };
+
+        grid.smooth(rhs, solution, SMOOTHING_SWEEPS);
+        let mut residual = vec![0.0; rhs.len()];
+        grid.residual(rhs, solution, &mut residual);
+        let coarse_rhs = coarse.restrict(grid, &residual);
+        let mut correction = vec![0.0; coarse_rhs.len()];
+        self.v_cycle(level + 1, &coarse_rhs, &mut correction);
+        grid.prolongate_add(coarse, &correction, solution);
+        grid.smooth(rhs, solution, SMOOTHING_SWEEPS);
+    }
+}
+
+impl PressureLevel {
+    fn cell_count(&self) -> usize {
+        self.active.len()
+    }
+
+    fn cell(&self, index: usize) -> [usize; 3] {
+        let x = index % self.dims[0];
+        let rest = index / self.dims[0];
+        [x, rest % self.dims[1], rest / self.dims[1]]
+    }
+
+    fn coarsen(&self) -> Option<Self> {
+        if self.dims.iter().all(|dim| *dim <= COARSEST_DIM) {
+            return None;
+        }
+        let dims = self.dims.map(|dim| dim.div_ceil(2));
+        let cell_count = dims[0] * dims[1] * dims[2];
+        let mut active = vec![false; cell_count];
+        let mut diagonal = vec![0.0; cell_count];
+        let mut couplings = [
+            vec![0.0; cell_count],
+            vec![0.0; cell_count],
+            vec![0.0; cell_count],
+        ];
+        for index in 0..self.cell_count() {
+            if !self.active[index] {
+                continue;
+            }
+            let cell = self.cell(index);
+            let parent = level_index(dims, cell.map(|coordinate| coordinate / 2));
+            active[parent] = true;
+            diagonal[parent] += self.diagonal[index];
+            for (axis, axis_couplings) in self.couplings.iter().enumerate() {
+                let coupling = axis_couplings[index];
+                if coupling == 0.0 {
+                    continue;
+                }
+                if cell[axis].is_multiple_of(2) {
+                    // Both cells share a parent, so the coupling cancels out of the aggregate.
+                    diagonal[parent] -= 2.0 * coupling;
+                } else {
+                    couplings[axis][parent] += coupling;
+                }
+            }
+        }
+        Some(Self {
+            dims,
+            active,
+            diagonal,
+            couplings,
+        })
+    }
+
+    fn neighbor_sum(&self, index: usize, values: &[f64]) -> f64 {
+        let cell = self.cell(index);
+        let mut sum = 0.0;
+        let mut stride = 1;
+        for ((coordinate, dim), couplings) in cell.into_iter().zip(self.dims).zip(&self.couplings) {
+            if coordinate + 1 < dim {
+                sum += couplings[index] * values[index + stride];
+            }
+            if coordinate > 0 {
+                sum += couplings[index - stride] * values[index - stride];
+            }
+            stride *= dim;
+        }
+        sum
+    }
+
+    fn smooth(&self, rhs: &[f64], solution: &mut [f64], sweeps: usize) {
+        let mut previous = vec![0.0; solution.len()];
+        for _ in 0..sweeps {
+            previous.copy_from_slice(solution);
+            update_cells(solution, |index, _| {
+                let diagonal = self.diagonal[index];
+                if !self.active[index] || diagonal <= f64::MIN_POSITIVE {
+                    return 0.0;
+                }
+                let jacobi = (rhs[index] + self.neighbor_sum(index, &previous)) / diagonal;
+                previous[index] + JACOBI_WEIGHT * (jacobi - previous[index])
+            });
+        }
+    }
+
+    fn residual(&self, rhs: &[f64], solution: &[f64], out: &mut [f64]) {
+        update_cells(out, |index, _| {
+            if !self.active[index] {
+                return 0.0;
+            }
+            rhs[index] - self.diagonal[index] * solution[index] + self.neighbor_sum(index, solution)
+        });
+    }
+
+    fn restrict(&self, fine: &Self, residual: &[f64]) -> Vec<f64> {
+        let mut coarse = vec![0.0; self.cell_count()];
+        update_cells(&mut coarse, |index, _| {
+            if !self.active[index] {
+                return 0.0;
+            }
+            let parent = self.cell(index);
+            let mut sum = 0.0;
+            for z in 0..2 {
+                for y in 0..2 {
+                    for x in 0..2 {
+                        let child = [2 * parent[0] + x, 2 * parent[1] + y, 2 * parent[2] + z];
+                        if (0..3).all(|axis| child[axis] < fine.dims[axis]) {
+                            sum += residual[level_index(fine.dims, child)];
+                        }
+                    }
+                }
+            }
+            sum
+        });
+        coarse
+    }
+
+    fn prolongate_add(&self, coarse: &Self, correction: &[f64], solution: &mut [f64]) {
+        update_cells(solution, |index, current| {
+            if !self.active[index] {
+                return current;
+            }
+            let parent = self.cell(index).map(|coordinate| coordinate / 2);
+            current + correction[level_index(coarse.dims, parent)]
+        });
+    }
+}
+
+fn update_cells<F>(values: &mut [f64], update: F)
+where
+    F: Fn(usize, f64) -> f64 + Sync + Send,
+{
+    #[cfg(feature = "rayon")]
+    if values.len() >= PARALLEL_CELL_THRESHOLD {
+        values
+            .par_iter_mut()
+            .enumerate()
+            .for_each(|(index, value)| *value = update(index, *value));
+        return;
+    }
+    for (index, value) in values.iter_mut().enumerate() {
+        *value = update(index, *value);
+    }
+}
+
+fn level_index(dims: [usize; 3], cell: [usize; 3]) -> usize {
+    cell[0] + dims[0] * (cell[1] + dims[1] * cell[2])
+}
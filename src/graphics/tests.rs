@@ -771,3 +771,102 @@ fn frame_recorder_can_capture_drawn_transformed_edges() {
     assert_eq!(recorder.frame_index(), 1);
     let _ = fs::remove_file(format!("anim/{prefix}00000000.ppm"));
 }
+
+fn assert_is_gif(path: &std::path::Path) {
+    let bytes = fs::read(path).expect("read gif");
+    assert!(bytes.starts_with(b"GIF89a"));
+    assert_eq!(bytes.last(), Some(&0x3b));
+}
+
+#[test]
+fn render_gif_streams_frames_without_frame_files() {
+    let dir = std::env::temp_dir().join(format!("gartus-recorder-stream-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let output = dir.join("out").join("stream.gif");
+    let options = AnimationRenderOptions::new(&dir, "stream-", 3, &output);
+
+    FrameRecorder::render_gif(options, |frame| {
+        let mut canvas = Canvas::new_with_bg(4, 3, Rgb::BLACK);
+        canvas.plot(&Rgb::RED, i64::try_from(frame).unwrap(), 1);
+        Ok(canvas)
+    })
+    .expect("stream gif");
+
+    assert_is_gif(&output);
+    assert!(!dir.join("stream-00000000.ppm").exists());
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn render_gif_can_encode_from_frame_files() {
+    let dir = std::env::temp_dir().join(format!("gartus-recorder-files-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let output = dir.join("files.gif");
+    let options = AnimationRenderOptions::new(&dir, "files-", 2, &output).stream_frames(false);
+
+    FrameRecorder::render_gif(options, |_| Ok(Canvas::new_with_bg(2, 2, Rgb::WHITE)))
+        .expect("encode gif from frame files");
+
+    assert_is_gif(&output);
+    assert!(!dir.join("files-00000000.ppm").exists());
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn streaming_recorder_removes_partial_gif_after_render_error() {
+    let dir = std::env::temp_dir().join(format!("gartus-recorder-partial-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let output = dir.join("partial.gif");
+    let options = AnimationRenderOptions::new(&dir, "partial-", 2, &output);
+    let canvas = Canvas::new_with_bg(1, 1, Rgb::WHITE);
+
+    let error = FrameRecorder::render_gif_with_recorder(options, |frame, _, recorder| {
+        if frame == 1 {
+            return Err(AnimationError::Render(std::io::Error::other(
+                "render failed",
+            )));
+        }
+        recorder.capture(&canvas).map_err(AnimationError::Io)?;
+        Ok(())
+    })
+    .unwrap_err();
+
+    assert!(matches!(error, AnimationError::Render(_)));
+    assert!(!output.exists());
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn streaming_recorder_rejects_frames_of_a_different_size() {
+    let dir = std::env::temp_dir().join(format!("gartus-recorder-size-{}", std::process::id()));
+    let output = dir.join("size.gif");
+    let mut recorder = FrameRecorder::streaming_gif(&output).with_delay(5);
+
+    let path = recorder
+        .capture(&Canvas::new_with_bg(2, 2, Rgb::WHITE))
+        .expect("first frame");
+    assert_eq!(path, output);
+    recorder
+        .capture(&Canvas::new_with_bg(3, 2, Rgb::WHITE))
+        .expect_err("mismatched frame size");
+
+    assert!(!output.exists());
+    assert!(recorder.finish_gif().is_err());
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn streaming_recorder_finishes_gif() {
+    let dir = std::env::temp_dir().join(format!("gartus-recorder-finish-{}", std::process::id()));
+    let output = dir.join("finish.gif");
+    let mut recorder = FrameRecorder::streaming_gif(&output);
+
+    recorder
+        .capture_with_delay(&Canvas::new_with_bg(2, 2, Rgb::WHITE), 10)
+        .expect("capture frame");
+
+    assert_eq!(recorder.finish_gif().expect("finish gif"), output);
+    assert_is_gif(&output);
+    assert!(recorder.encode_gif(dir.join("other.gif")).is_err());
+    let _ = fs::remove_dir_all(dir);
+}
@@ -5,12 +5,11 @@ use std::{
     path::{Path, PathBuf},
     sync::Arc,
 };
-#[cfg(not(feature = "external"))]
 use std::{fs, str::FromStr};
 
 use super::{
     colors::{LinearRgb, Rgb},
-    display::Canvas,
+    display::{Canvas, HdrImage},
     material::SurfaceMaterial,
     scene::SurfaceScene,
 };
@@ -80,6 +79,26 @@ pub struct Texture {
     filter: TextureFilter,
 }
 
+/// A 2D linear-RGB texture with 32-bit float texels, sampled like [`Texture`].
+///
+/// Texels are not limited to `0..=1`, so bright sources in HDR images keep their radiance through
+/// filtering and mipmapping. Samples are returned as raw linear values without any transfer curve.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HdrTexture {
+    levels: Vec<HdrTextureLevel>,
+    wrap_s: TextureWrap,
+    wrap_t: TextureWrap,
+    filter: TextureFilter,
+}
+
+/// One mip level of an [`HdrTexture`], stored top row first.
+#[derive(Clone, Debug, PartialEq)]
+struct HdrTextureLevel {
+    width: u32,
+    height: u32,
+    texels: Vec<[f32; 3]>,
+}
+
 /// Cache for bitmap textures loaded from filesystem paths.
 #[derive(Clone, Debug, Default)]
 pub struct TextureCache {
@@ -128,7 +147,7 @@ impl Texture {
     /// Loads a texture from an image path.
     ///
     /// With the `external` feature enabled, this delegates to the external image conversion loader.
-    /// Without that feature, it supports PPM input through the built-in parser.
+    /// Without that feature, it supports PPM and PNG input through the built-in decoders.
     ///
     /// # Errors
     ///
@@ -146,8 +165,16 @@ impl Texture {
             crate::external::ppmify(path, false)?
         };
         #[cfg(not(feature = "external"))]
-        let canvas = load_ppm_canvas(path)
-            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        let canvas = if path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("png"))
+        {
+            super::codec::PngImage::open(path)?.to_canvas()
+        } else {
+            load_ppm_canvas(path)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?
+        };
         Ok(Self::from_canvas(canvas))
     }
 
@@ -315,6 +342,234 @@ impl Texture {
     }
 }
 
+impl HdrTexture {
+    /// Creates a texture from linear HDR pixels.
+    ///
+    /// Pixels are stored as `f32`, so values beyond single precision lose detail.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn from_hdr_image(image: &HdrImage) -> Self {
+        let texels = image
+            .pixels()
+            .iter()
+            .map(|pixel| [pixel.red as f32, pixel.green as f32, pixel.blue as f32])
+            .collect();
+        Self {
+            levels: vec![HdrTextureLevel {
+                width: image.width(),
+                height: image.height(),
+                texels,
+            }],
+            wrap_s: TextureWrap::Clamp,
+            wrap_t: TextureWrap::Clamp,
+            filter: TextureFilter::Nearest,
+        }
+    }
+
+    /// Loads a texture from a `.hdr`, `.rgbe`, `.pfm`, or `.exr` file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the extension is not an HDR format or the file cannot be decoded.
+    pub fn from_path(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::from_hdr_image(&HdrImage::open(path)?))
+    }
+
+    /// Sets both texture-coordinate wrap modes.
+    #[must_use]
+    pub const fn wrap(mut self, wrap_s: TextureWrap, wrap_t: TextureWrap) -> Self {
+        self.wrap_s = wrap_s;
+        self.wrap_t = wrap_t;
+        self
+    }
+
+    /// Sets the texture filter.
+    #[must_use]
+    pub const fn filter(mut self, filter: TextureFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Generates and stores downsampled mipmap levels.
+    #[must_use]
+    pub fn mipmapped(self) -> Self {
+        self.with_mip_levels(usize::MAX)
+    }
+
+    /// Generates and stores at most `level_count` texture levels, including the base image.
+    ///
+    /// Passing `0` or `1` stores no additional mipmaps.
+    #[must_use]
+    pub fn with_mip_levels(mut self, level_count: usize) -> Self {
+        self.levels.truncate(1);
+        while self.levels.len() < level_count
+            && let Some(source) = self.levels.last()
+            && !source.texels.is_empty()
+            && (source.width > 1 || source.height > 1)
+        {
+            let next = source.downsample();
+            self.levels.push(next);
+        }
+        self
+    }
+
+    /// Returns the base level width in texels.
+    #[must_use]
+    pub fn width(&self) -> u32 {
+        self.levels[0].width
+    }
+
+    /// Returns the base level height in texels.
+    #[must_use]
+    pub fn height(&self) -> u32 {
+        self.levels[0].height
+    }
+
+    /// Returns the number of available texture levels, including the base image.
+    #[must_use]
+    pub fn level_count(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Returns the base level texel at storage position `(x, y)`, where row `0` is the top.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `(x, y)` is outside the base level.
+    #[must_use]
+    pub fn texel(&self, x: u32, y: u32) -> LinearRgb {
+        let level = &self.levels[0];
+        assert!(
+            x < level.width && y < level.height,
+            "texel ({x}, {y}) is outside a {}x{} texture",
+            level.width,
+            level.height
+        );
+        level.texel(x, y)
+    }
+
+    /// Samples the texture at normalized texture coordinate `(s, t)`.
+    ///
+    /// The `t` axis uses graphics convention: `t = 0` samples the bottom row, and `t = 1`
+    /// samples the top row.
+    #[must_use]
+    pub fn sample(&self, s: f64, t: f64) -> LinearRgb {
+        if self.levels[0].texels.is_empty() || !s.is_finite() || !t.is_finite() {
+            return LinearRgb::default();
+        }
+
+        self.levels[0].sample(s, t, self.filter, self.wrap_s, self.wrap_t)
+    }
+
+    /// Samples a mipmap level selected by `lod`, where `0.0` is the base image.
+    ///
+    /// Level selection matches [`Texture::sample_lod`].
+    #[must_use]
+    pub fn sample_lod(&self, s: f64, t: f64, lod: f64) -> LinearRgb {
+        if self.levels[0].texels.is_empty() || !s.is_finite() || !t.is_finite() || !lod.is_finite()
+        {
+            return LinearRgb::default();
+        }
+
+        let max_level = self.levels.len() - 1;
+        if self.filter == TextureFilter::Linear && max_level > 0 {
+            let (lower, upper, blend) = mip_level_pair_from_lod(lod, max_level);
+            let sample = |level: usize| {
+                self.levels[level].sample(s, t, TextureFilter::Linear, self.wrap_s, self.wrap_t)
+            };
+            lerp_linear(sample(lower), sample(upper), blend)
+        } else {
+            let level = mip_level_from_lod(lod, max_level);
+            self.levels[level].sample(s, t, self.filter, self.wrap_s, self.wrap_t)
+        }
+    }
+
+    /// Copies the base level back into an [`HdrImage`].
+    #[must_use]
+    pub fn to_hdr_image(&self) -> HdrImage {
+        let level = &self.levels[0];
+        HdrImage::from_pixels(
+            level.width,
+            level.height,
+            level
+                .texels
+                .iter()
+                .map(|texel| texel_to_linear(*texel))
+                .collect(),
+        )
+    }
+}
+
+impl From<HdrImage> for HdrTexture {
+    fn from(image: HdrImage) -> Self {
+        Self::from_hdr_image(&image)
+    }
+}
+
+impl HdrTextureLevel {
+    fn texel(&self, x: u32, y: u32) -> LinearRgb {
+        texel_to_linear(self.texels[y as usize * self.width as usize + x as usize])
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn sample(
+        &self,
+        s: f64,
+        t: f64,
+        filter: TextureFilter,
+        wrap_s: TextureWrap,
+        wrap_t: TextureWrap,
+    ) -> LinearRgb {
+        match filter {
+            TextureFilter::Nearest => {
+                let x = (apply_wrap(s, wrap_s) * f64::from(self.width.saturating_sub(1))).round();
+                let y = ((1.0 - apply_wrap(t, wrap_t)) * f64::from(self.height.saturating_sub(1)))
+                    .round();
+                self.texel(x as u32, y as u32)
+            }
+            TextureFilter::Linear => {
+                let (x, x0, x1) = linear_axis(s, self.width, wrap_s);
+                let (y, y0, y1) = linear_axis(1.0 - t, self.height, wrap_t);
+                let tx = x - f64::from(x0);
+                let ty = y - f64::from(y0);
+                let top = lerp_linear(self.texel(x0, y0), self.texel(x1, y0), tx);
+                let bottom = lerp_linear(self.texel(x0, y1), self.texel(x1, y1), tx);
+                lerp_linear(top, bottom, ty)
+            }
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn downsample(&self) -> Self {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut texels = Vec::with_capacity(width as usize * height as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = LinearRgb::default();
+                let mut count = 0.0;
+                for source_y in y * 2..=(y * 2 + 1).min(self.height - 1) {
+                    for source_x in x * 2..=(x * 2 + 1).min(self.width - 1) {
+                        sum += self.texel(source_x, source_y);
+                        count += 1.0;
+                    }
+                }
+                let average = sum * (1.0 / count);
+                texels.push([
+                    average.red as f32,
+                    average.green as f32,
+                    average.blue as f32,
+                ]);
+            }
+        }
+        Self {
+            width,
+            height,
+            texels,
+        }
+    }
+}
+
 impl TextureCache {
     /// Creates an empty texture cache.
     #[must_use]
@@ -400,7 +655,6 @@ impl TextureCache {
     }
 }
 
-#[cfg(not(feature = "external"))]
 pub(crate) fn load_ppm_canvas(path: &Path) -> Result<Canvas, String> {
     let buffer = fs::read(path).map_err(|error| error.to_string())?;
     let mut cursor = 0;
@@ -437,7 +691,6 @@ pub(crate) fn load_ppm_canvas(path: &Path) -> Result<Canvas, String> {
     Ok(Canvas::from_pixels(width, height, pixels))
 }
 
-#[cfg(not(feature = "external"))]
 fn next_ppm_token<'a>(buffer: &'a [u8], cursor: &mut usize) -> Option<&'a [u8]> {
     loop {
         while *cursor < buffer.len() && buffer[*cursor].is_ascii_whitespace() {
@@ -469,7 +722,6 @@ fn next_ppm_token<'a>(buffer: &'a [u8], cursor: &mut usize) -> Option<&'a [u8]>
     Some(&buffer[start..*cursor])
 }
 
-#[cfg(not(feature = "external"))]
 fn parse_ppm_token<T>(token: &[u8]) -> Result<T, String>
 where
     T: FromStr,
@@ -479,7 +731,6 @@ where
     token.parse::<T>().map_err(|error| error.to_string())
 }
 
-#[cfg(not(feature = "external"))]
 fn scale_ppm_channel(value: u16, maxval: u16) -> Result<u8, String> {
     if value > maxval {
         return Err(format!("PPM channel value {value} exceeds maxval {maxval}"));
@@ -491,7 +742,6 @@ fn scale_ppm_channel(value: u16, maxval: u16) -> Result<u8, String> {
     )
 }
 
-#[cfg(not(feature = "external"))]
 fn parse_p3_pixels(
     buffer: &[u8],
     cursor: &mut usize,
@@ -521,7 +771,6 @@ fn parse_p3_pixels(
     Ok(pixels)
 }
 
-#[cfg(not(feature = "external"))]
 fn parse_p6_pixels(
     buffer: &[u8],
     cursor: &mut usize,
@@ -567,7 +816,6 @@ fn parse_p6_pixels(
     Ok(pixels)
 }
 
-#[cfg(not(feature = "external"))]
 fn consume_p6_separator(buffer: &[u8], cursor: &mut usize) -> Result<(), String> {
     if *cursor >= buffer.len() || !buffer[*cursor].is_ascii_whitespace() {
         return Err("Invalid PPM file: missing binary data separator".to_string());
@@ -587,6 +835,12 @@ impl SurfaceTexture for Texture {
     }
 }
 
+impl SurfaceTexture for HdrTexture {
+    fn sample_linear(&self, sample: TextureSample) -> LinearRgb {
+        self.sample(sample.u, sample.v)
+    }
+}
+
 impl ActiveTextureSampler<'_> {
     pub(crate) const fn uses_mips(&self) -> bool {
         matches!(self, Self::NearestMip { .. } | Self::LinearMip { .. })
@@ -710,6 +964,14 @@ fn linear_axis(value: f64, size: u32, wrap: TextureWrap) -> (f64, u32, u32) {
     }
 }
 
+fn texel_to_linear([red, green, blue]: [f32; 3]) -> LinearRgb {
+    LinearRgb::new(f64::from(red), f64::from(green), f64::from(blue))
+}
+
+fn lerp_linear(a: LinearRgb, b: LinearRgb, t: f64) -> LinearRgb {
+    a * (1.0 - t) + b * t
+}
+
 fn pixel_at_storage(image: &Canvas, x: u32, y: u32) -> Rgb {
     let index = y as usize * image.width() as usize + x as usize;
     image.pixels()[index]
@@ -994,4 +1256,57 @@ mod tests {
         assert!(texture.lod_from_derivatives(0.0, 0.0, 0.0, 0.0).abs() < f64::EPSILON);
         assert!(texture.lod_from_derivatives(0.5, 0.0, 0.0, 0.5) >= 2.0);
     }
+
+    fn hdr_test_texture() -> HdrTexture {
+        HdrTexture::from_hdr_image(&HdrImage::from_pixels(
+            2,
+            2,
+            vec![
+                LinearRgb::new(40.0, 20.0, 10.0),
+                LinearRgb::new(0.0, 0.0, 0.0),
+                LinearRgb::new(0.5, 0.5, 0.5),
+                LinearRgb::new(1.5, 0.0, 3.0),
+            ],
+        ))
+    }
+
+    #[test]
+    fn hdr_texture_keeps_values_above_one_when_sampling() {
+        let texture = hdr_test_texture();
+        let linear = hdr_test_texture().filter(TextureFilter::Linear);
+
+        assert_eq!(texture.sample(0.0, 1.0), LinearRgb::new(40.0, 20.0, 10.0));
+        assert_eq!(texture.sample(1.0, 0.0), LinearRgb::new(1.5, 0.0, 3.0));
+        assert_eq!(
+            linear.sample(0.5, 1.0),
+            LinearRgb::new(20.0, 10.0, 5.0),
+            "linear filtering should blend unclamped texels"
+        );
+        assert_eq!(
+            texture.sample_linear(TextureSample::new(0.0, 1.0, Point::default())),
+            texture.sample(0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn hdr_texture_mipmaps_average_unclamped_texels() {
+        let texture = hdr_test_texture().filter(TextureFilter::Linear).mipmapped();
+
+        assert_eq!(texture.level_count(), 2);
+        assert_eq!(
+            texture.sample_lod(0.3, 0.7, 1.0),
+            LinearRgb::new(10.5, 5.125, 3.375)
+        );
+        assert_eq!(
+            HdrTexture::from_hdr_image(&HdrImage::from_pixels(
+                8,
+                8,
+                vec![LinearRgb::new(2.0, 2.0, 2.0); 64]
+            ))
+            .with_mip_levels(3)
+            .level_count(),
+            3
+        );
+        assert_eq!(texture.to_hdr_image(), hdr_test_texture().to_hdr_image());
+    }
 }
@@ -1,6 +1,5 @@
-pub fn main() {
-    println!(
-        "gartus is primarily a library crate. Try `cargo run --example raytracing_weekend` \
-         to render the Ray Tracing in One Weekend scene."
-    );
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    gartus::mdl::cli::main_from_env()
 }
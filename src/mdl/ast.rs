@@ -1,9 +1,10 @@
 //! Typed MDL command representation.
 
-use super::lexer::Span;
+use super::lexer::{Span, Token, TokenKind};
 use crate::graphics::{
     colors::LinearRgb,
     lighting::{DEFAULT_SPECULAR_EXPONENT, SurfaceMaterial},
+    raytracing::{Dielectric, GgxMicrofacet, Metal, RayMaterial},
 };
 use std::path::PathBuf;
 
@@ -77,6 +78,16 @@ pub enum Command {
     Include(String),
     /// Apply a canvas filter.
     Filter(FilterCommand),
+    /// Bind a name to an expression for later numeric arguments.
+    Let(LetCommand),
+    /// Repeat a block of commands over an integer range.
+    For(ForCommand),
+    /// A command with expression arguments that is resolved after variable expansion.
+    Deferred(DeferredCommand),
+    /// Define a reusable procedure.
+    Define(DefineCommand),
+    /// Expand a previously defined procedure.
+    Call(CallCommand),
 }
 
 impl Command {
@@ -314,6 +325,15 @@ pub enum RenderCommand {
     Shading(ShadingMode),
     /// Save a copy of the current coordinate-system stack top.
     SaveCoordSystem(String),
+    /// Define a named physically based material for `shading raytrace`.
+    RayMaterial {
+        name: String,
+        material: RayMaterialSpec,
+    },
+    /// Define a named homogeneous participating medium for `shading raytrace`.
+    Medium { name: String, medium: MediumSpec },
+    /// Define a spot, directional, or area light.
+    LightSource(LightSource),
 }
 
 /// Camera commands.
@@ -346,6 +366,207 @@ pub struct FilterCommand {
     pub value: Option<f64>,
 }
 
+/// `let name = expression` binding.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LetCommand {
+    /// Bound variable name.
+    pub name: String,
+    /// Bound expression.
+    pub value: Expr,
+}
+
+/// `for name in start..end { ... }` block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForCommand {
+    /// Loop variable name.
+    pub variable: String,
+    /// First loop value.
+    pub start: Expr,
+    /// Loop bound.
+    pub end: Expr,
+    /// Whether `end` is included (`..=`) or excluded (`..`).
+    pub inclusive: bool,
+    /// Commands repeated for each loop value.
+    pub body: Vec<Spanned<Command>>,
+}
+
+/// `define name(params) { ... }` procedure definition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DefineCommand {
+    /// Procedure name.
+    pub name: String,
+    /// Parameter names, bound like `let` variables inside the body.
+    pub params: Vec<String>,
+    /// Commands expanded by each call.
+    pub body: Vec<Spanned<Command>>,
+}
+
+/// `call name(args)` procedure call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallCommand {
+    /// Called procedure name.
+    pub name: String,
+    /// Source span of the procedure name.
+    pub name_span: Span,
+    /// Argument expressions, one per parameter.
+    pub args: Vec<Expr>,
+}
+
+/// A command line whose numeric arguments contain expressions.
+///
+/// The tokens are kept as written so the command can be parsed again once every expression has a
+/// value. Expressions that only use `let` and loop variables are folded by
+/// [`crate::mdl::semantic::compile`]; expressions that read knobs are evaluated for each frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeferredCommand {
+    /// Command tokens, including the command name.
+    pub tokens: Vec<Token>,
+    /// Expression arguments keyed by token index.
+    pub expressions: Vec<(usize, Expr)>,
+}
+
+impl DeferredCommand {
+    /// Command name token text.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        match self.tokens.first().map(|token| &token.kind) {
+            Some(TokenKind::Word(name)) => name,
+            _ => "",
+        }
+    }
+}
+
+/// Arithmetic expression accepted wherever MDL expects a number.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    /// Expression node.
+    pub kind: ExprKind,
+    /// Source span covering the whole expression.
+    pub span: Span,
+}
+
+impl Expr {
+    /// Creates an expression node.
+    #[must_use]
+    pub const fn new(kind: ExprKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    /// Returns the literal value when the expression is a plain number.
+    #[must_use]
+    pub const fn as_number(&self) -> Option<f64> {
+        match self.kind {
+            ExprKind::Number(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// Expression node kind.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    /// Numeric literal or folded constant.
+    Number(f64),
+    /// `let` variable, loop variable, or knob reference.
+    Variable(String),
+    /// Unary negation.
+    Negate(Box<Expr>),
+    /// Binary arithmetic.
+    Binary {
+        /// Operator.
+        op: BinaryOp,
+        /// Left operand.
+        lhs: Box<Expr>,
+        /// Right operand.
+        rhs: Box<Expr>,
+    },
+    /// Built-in function call.
+    Call {
+        /// Called function.
+        function: ExprFunction,
+        /// Call arguments.
+        args: Vec<Expr>,
+    },
+}
+
+/// Binary arithmetic operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    /// `+`
+    Add,
+    /// `-`
+    Sub,
+    /// `*`
+    Mul,
+    /// `/`
+    Div,
+    /// `^`, right associative.
+    Pow,
+}
+
+/// Built-in expression function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExprFunction {
+    /// Sine of an angle in radians.
+    Sin,
+    /// Cosine of an angle in radians.
+    Cos,
+    /// Tangent of an angle in radians.
+    Tan,
+    /// Square root.
+    Sqrt,
+    /// Absolute value.
+    Abs,
+    /// Largest integer less than or equal to the argument.
+    Floor,
+    /// Smaller of two values.
+    Min,
+    /// Larger of two values.
+    Max,
+}
+
+impl ExprFunction {
+    /// Looks up a function by its MDL name.
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "sin" => Self::Sin,
+            "cos" => Self::Cos,
+            "tan" => Self::Tan,
+            "sqrt" => Self::Sqrt,
+            "abs" => Self::Abs,
+            "floor" => Self::Floor,
+            "min" => Self::Min,
+            "max" => Self::Max,
+            _ => return None,
+        })
+    }
+
+    /// MDL function name.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Sin => "sin",
+            Self::Cos => "cos",
+            Self::Tan => "tan",
+            Self::Sqrt => "sqrt",
+            Self::Abs => "abs",
+            Self::Floor => "floor",
+            Self::Min => "min",
+            Self::Max => "max",
+        }
+    }
+
+    /// Number of arguments the function takes.
+    #[must_use]
+    pub const fn arity(self) -> usize {
+        match self {
+            Self::Sin | Self::Cos | Self::Tan | Self::Sqrt | Self::Abs | Self::Floor => 1,
+            Self::Min | Self::Max => 2,
+        }
+    }
+}
+
 /// Rotation axis.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Axis {
@@ -491,10 +712,164 @@ impl From<Material> for SurfaceMaterial {
     }
 }
 
+/// Physically based surface material declared with `material`.
+///
+/// Colors are linear reflectance or emission values, not the 0-255 channels used by `constants`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RayMaterialSpec {
+    /// Lambertian diffuse surface.
+    Diffuse {
+        /// Diffuse albedo.
+        color: Vec3,
+    },
+    /// Fuzzy mirror metal.
+    Metal {
+        /// Reflectance.
+        color: Vec3,
+        /// Reflection fuzz in `[0, 1]`.
+        fuzz: f64,
+    },
+    /// GGX/Trowbridge-Reitz glossy conductor.
+    Ggx {
+        /// Specular color.
+        color: Vec3,
+        /// Perceptual roughness in `[0, 1]`.
+        roughness: f64,
+    },
+    /// Clear dielectric such as glass or water.
+    Glass {
+        /// Index of refraction.
+        ior: f64,
+    },
+    /// Diffuse emitter.
+    Emissive {
+        /// Emission color.
+        color: Vec3,
+        /// Multiplier applied to `color`.
+        strength: f64,
+    },
+}
+
+impl RayMaterialSpec {
+    /// Phong coefficients used when the shape is rasterized instead of path traced.
+    #[must_use]
+    pub const fn preview_material(self) -> Material {
+        let (ambient, diffuse, specular) = match self {
+            Self::Diffuse { color } => (0.2, color, 0.0),
+            Self::Metal { color, .. } | Self::Ggx { color, .. } => (0.2, color, 0.8),
+            Self::Glass { .. } => (0.1, Vec3::new(0.2, 0.2, 0.2), 0.9),
+            Self::Emissive { color, .. } => (1.0, color, 0.0),
+        };
+        Material::new(
+            ambient * diffuse.x,
+            diffuse.x,
+            specular,
+            ambient * diffuse.y,
+            diffuse.y,
+            specular,
+            ambient * diffuse.z,
+            diffuse.z,
+            specular,
+        )
+    }
+}
+
+impl From<RayMaterialSpec> for RayMaterial {
+    fn from(spec: RayMaterialSpec) -> Self {
+        let linear = |color: Vec3| LinearRgb::new(color.x, color.y, color.z);
+        match spec {
+            RayMaterialSpec::Diffuse { color } => Self::lambertian(linear(color)),
+            RayMaterialSpec::Metal { color, fuzz } => Self::Metal(Metal::new(linear(color), fuzz)),
+            RayMaterialSpec::Ggx { color, roughness } => {
+                Self::GgxMicrofacet(GgxMicrofacet::new(linear(color), roughness))
+            }
+            RayMaterialSpec::Glass { ior } => Self::Dielectric(Dielectric::from_ratio(ior)),
+            RayMaterialSpec::Emissive { color, strength } => {
+                Self::diffuse_light(linear(color) * strength)
+            }
+        }
+    }
+}
+
+/// Homogeneous participating medium declared with `medium`.
+///
+/// A shape that names a medium becomes the boundary of a constant-density volume.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MediumSpec {
+    /// Extinction density per scene unit.
+    pub density: f64,
+    /// Single-scattering albedo.
+    pub albedo: Vec3,
+    /// Henyey-Greenstein asymmetry in `(-1, 1)`; `0` scatters isotropically.
+    pub anisotropy: f64,
+}
+
+/// Light declared with `spotlight`, `dirlight`, or `arealight`.
+///
+/// Colors use the 0-255 channels of `light`; positions are in world space like `light`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightSource {
+    /// Positional light limited to a cone.
+    Spot {
+        /// Light color.
+        color: Vec3,
+        /// Light position.
+        position: Vec3,
+        /// Direction the cone points, i.e. the way the light travels.
+        direction: Vec3,
+        /// Half-angle in degrees inside which the cone is fully lit.
+        inner_angle: f64,
+        /// Half-angle in degrees outside which the cone is dark.
+        outer_angle: f64,
+        /// Exponent applied to the smooth transition between the two angles.
+        falloff: f64,
+    },
+    /// Light arriving from infinitely far away along one direction.
+    Directional {
+        /// Light color.
+        color: Vec3,
+        /// Direction the light travels.
+        direction: Vec3,
+    },
+    /// Light emitted from the surface of a finite shape.
+    Area {
+        /// Light color.
+        color: Vec3,
+        /// Emitting shape.
+        emitter: AreaEmitter,
+        /// Multiplier applied to the linear emitted radiance.
+        strength: f64,
+    },
+}
+
+/// Emitting shape for an area light.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(missing_docs)]
+pub enum AreaEmitter {
+    /// Parallelogram spanned by `u` and `v` from `corner`, emitting on the `u x v` side.
+    Quad { corner: Vec3, u: Vec3, v: Vec3 },
+    /// Sphere emitting outward.
+    Sphere { center: Vec3, radius: f64 },
+}
+
+impl AreaEmitter {
+    /// Center of the emitting shape, used as the light position in raster shading.
+    #[must_use]
+    pub const fn center(self) -> Vec3 {
+        match self {
+            Self::Quad { corner, u, v } => Vec3::new(
+                corner.x + 0.5 * (u.x + v.x),
+                corner.y + 0.5 * (u.y + v.y),
+                corner.z + 0.5 * (u.z + v.z),
+            ),
+            Self::Sphere { center, .. } => center,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::graphics::colors::LinearRgb;
 
     #[test]
     fn mdl_material_converts_to_surface_material_channels() {
@@ -0,0 +1,683 @@
+//! Command-line front end used by the `gartus` binary.
+//!
+//! ```text
+//! gartus render script.mdl --size 1024x768 --spp 256 --max-depth 12 --out frames/ --format png
+//! gartus render spin.mdl --frames 10..20 --out frames/
+//! gartus check script.mdl
+//! ```
+//!
+//! [`run`] writes rendered paths to `stdout` and diagnostics to `stderr`, and returns an
+//! [`ExitStatus`] whose numeric code scripts can branch on.
+
+use super::{
+    RenderConfig,
+    animation::FrameOutputConfig,
+    compile_file,
+    executor::{
+        ExecutionError, execute_compiled_frame, execute_compiled_frame_range_with_options,
+        execute_compiled_gif_range_with_options, for_each_compiled_frame,
+    },
+    semantic::CompiledProgram,
+};
+use crate::graphics::animation::AnimationRenderOptions;
+use std::{
+    error::Error,
+    ffi::OsString,
+    fmt,
+    io::{self, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+const DEFAULT_SIZE: (u32, u32) = (500, 500);
+const DEFAULT_FORMAT: &str = "png";
+const DEFAULT_FRAME_DIR: &str = "frames";
+
+const USAGE: &str = "\
+Usage:
+  gartus render <script.mdl> [options]
+  gartus check <script.mdl>
+  gartus help | --help
+  gartus --version
+
+Render options:
+  --size <W>x<H>       canvas size in pixels (default 500x500)
+  --spp <N>            samples per pixel for `shading raytrace`
+  --max-depth <N>      maximum path depth for `shading raytrace`
+  --out <PATH>         output file, or directory for animation frames
+  --format <EXT>       output format such as png, ppm, or gif
+  --frames <RANGE>     animation frames to render: N, A..B, A..=B, A.., or ..B
+  --check              compile only and report diagnostics
+
+Without --out or --format, still scripts run their own `save` commands and
+animations write PNG frames to `frames/`.
+
+Exit codes:
+  0  success
+  1  rendering or output failed
+  2  invalid command-line arguments
+  3  the script could not be read or has diagnostics
+";
+
+/// Process exit status reported by [`run`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// The command completed.
+    Success,
+    /// Rendering or writing output failed.
+    RenderFailed,
+    /// The command line could not be parsed or did not match the script.
+    Usage,
+    /// The script could not be read, parsed, or compiled.
+    Diagnostics,
+}
+
+impl ExitStatus {
+    /// Returns the numeric process exit code.
+    #[must_use]
+    pub const fn code(self) -> u8 {
+        match self {
+            Self::Success => 0,
+            Self::RenderFailed => 1,
+            Self::Usage => 2,
+            Self::Diagnostics => 3,
+        }
+    }
+}
+
+impl From<ExitStatus> for ExitCode {
+    fn from(status: ExitStatus) -> Self {
+        Self::from(status.code())
+    }
+}
+
+/// A command-line argument error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsageError(String);
+
+impl UsageError {
+    fn new(message: impl Into<String>) -> Self {
+        Self(message.into())
+    }
+}
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for UsageError {}
+
+/// A parsed `gartus` invocation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CliCommand {
+    /// Compile, and unless `check` is set, render a script.
+    Render(RenderArgs),
+    /// Print usage.
+    Help,
+    /// Print the crate version.
+    Version,
+}
+
+/// Frames selected with `--frames`, as a half-open range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameSelection {
+    /// First frame to render.
+    pub start: usize,
+    /// One past the last frame to render, or `None` for the end of the animation.
+    pub end: Option<usize>,
+}
+
+impl FrameSelection {
+    /// Resolves the selection against an animation with `frames` frames.
+    #[must_use]
+    pub fn resolve(self, frames: usize) -> Range<usize> {
+        self.start..self.end.unwrap_or(frames)
+    }
+}
+
+/// Options for `gartus render`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderArgs {
+    /// MDL script to compile.
+    pub script: PathBuf,
+    /// Canvas width and height.
+    pub size: (u32, u32),
+    /// Samples per pixel for ray-traced output.
+    pub samples_per_pixel: Option<u32>,
+    /// Maximum path depth for ray-traced output.
+    pub max_depth: Option<u32>,
+    /// Output file or frame directory.
+    pub output: Option<PathBuf>,
+    /// Output format extension without a leading dot.
+    pub format: Option<String>,
+    /// Selected animation frames.
+    pub frames: Option<FrameSelection>,
+    /// Compile only.
+    pub check: bool,
+}
+
+impl RenderArgs {
+    /// Creates render arguments with default options for `script`.
+    #[must_use]
+    pub fn new(script: impl Into<PathBuf>) -> Self {
+        Self {
+            script: script.into(),
+            size: DEFAULT_SIZE,
+            samples_per_pixel: None,
+            max_depth: None,
+            output: None,
+            format: None,
+            frames: None,
+            check: false,
+        }
+    }
+
+    fn render_config(&self) -> RenderConfig {
+        let mut config = RenderConfig::new(self.size.0, self.size.1).display_enabled(false);
+        if let Some(samples_per_pixel) = self.samples_per_pixel {
+            config = config.raytrace_samples_per_pixel(samples_per_pixel);
+        }
+        if let Some(max_depth) = self.max_depth {
+            config = config.raytrace_max_depth(max_depth);
+        }
+        if let Some(parent) = self
+            .script
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            config = config.source_dir(parent);
+        }
+        config
+    }
+
+    /// Returns `--format`, falling back to the `--out` file extension.
+    fn explicit_format(&self) -> Option<String> {
+        self.format.clone().or_else(|| {
+            self.output
+                .as_deref()
+                .filter(|output| !is_directory_like(output))
+                .and_then(Path::extension)
+                .and_then(|extension| extension.to_str())
+                .map(str::to_ascii_lowercase)
+        })
+    }
+
+    /// Builds a single output file path, placing `<script stem>.<format>` in directory outputs.
+    fn output_file(&self, format: &str) -> PathBuf {
+        let file_name = || {
+            let stem = self
+                .script
+                .file_stem()
+                .map_or_else(|| "render".into(), |stem| stem.to_string_lossy());
+            PathBuf::from(format!("{stem}.{format}"))
+        };
+        match &self.output {
+            Some(output) if is_directory_like(output) => output.join(file_name()),
+            Some(output) => output.clone(),
+            None => file_name(),
+        }
+    }
+}
+
+/// Parses command-line arguments, excluding the program name.
+///
+/// # Errors
+///
+/// Returns a [`UsageError`] for unknown commands or options, missing values, or malformed
+/// sizes, counts, and frame ranges.
+pub fn parse_args<I, S>(args: I) -> Result<CliCommand, UsageError>
+where
+    I: IntoIterator<Item = S>,
+    S: Into<OsString>,
+{
+    let mut args = args
+        .into_iter()
+        .map(|arg| {
+            arg.into().into_string().map_err(|arg| {
+                UsageError::new(format!("argument `{}` is not UTF-8", arg.display()))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter();
+
+    let command = args
+        .next()
+        .ok_or_else(|| UsageError::new("missing command"))?;
+    let check = match command.as_str() {
+        "help" | "-h" | "--help" => return Ok(CliCommand::Help),
+        "-V" | "--version" => return Ok(CliCommand::Version),
+        "render" => false,
+        "check" => true,
+        other => return Err(UsageError::new(format!("unknown command `{other}`"))),
+    };
+
+    let mut script = None;
+    let mut render = RenderArgs::new(PathBuf::new());
+    render.check = check;
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| UsageError::new(format!("`{name}` needs a value")))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(CliCommand::Help),
+            "--check" => render.check = true,
+            "--size" => render.size = parse_size(&value("--size")?)?,
+            "--spp" => render.samples_per_pixel = Some(parse_count("--spp", &value("--spp")?)?),
+            "--max-depth" => {
+                render.max_depth = Some(parse_count("--max-depth", &value("--max-depth")?)?);
+            }
+            "--out" | "-o" => render.output = Some(PathBuf::from(value("--out")?)),
+            "--format" => render.format = Some(parse_format(&value("--format")?)?),
+            "--frames" => render.frames = Some(parse_frames(&value("--frames")?)?),
+            option if option.starts_with('-') && option.len() > 1 => {
+                return Err(UsageError::new(format!("unknown option `{option}`")));
+            }
+            path if script.is_none() => script = Some(PathBuf::from(path)),
+            extra => return Err(UsageError::new(format!("unexpected argument `{extra}`"))),
+        }
+    }
+
+    render.script = script.ok_or_else(|| UsageError::new("missing MDL script path"))?;
+    Ok(CliCommand::Render(render))
+}
+
+/// Runs a `gartus` invocation, excluding the program name.
+pub fn run<I, S>(args: I, stdout: &mut dyn Write, stderr: &mut dyn Write) -> ExitStatus
+where
+    I: IntoIterator<Item = S>,
+    S: Into<OsString>,
+{
+    // Reporting is best effort: a closed stdout or stderr must not change the exit status.
+    match parse_args(args) {
+        Ok(CliCommand::Help) => {
+            let _ = stdout.write_all(USAGE.as_bytes());
+            ExitStatus::Success
+        }
+        Ok(CliCommand::Version) => {
+            let _ = writeln!(stdout, "gartus {}", env!("CARGO_PKG_VERSION"));
+            ExitStatus::Success
+        }
+        Ok(CliCommand::Render(render)) => run_render(&render, stdout, stderr),
+        Err(error) => {
+            let _ = writeln!(stderr, "gartus: error: {error}\n\n{USAGE}");
+            ExitStatus::Usage
+        }
+    }
+}
+
+/// Runs the process arguments and returns the process exit code.
+#[must_use]
+pub fn main_from_env() -> ExitCode {
+    run(
+        std::env::args_os().skip(1),
+        &mut io::stdout().lock(),
+        &mut io::stderr().lock(),
+    )
+    .into()
+}
+
+fn run_render(render: &RenderArgs, stdout: &mut dyn Write, stderr: &mut dyn Write) -> ExitStatus {
+    let compiled = match compile_file(&render.script) {
+        Ok(compiled) => compiled,
+        Err(diagnostics) => {
+            for diagnostic in &diagnostics {
+                let _ = writeln!(stderr, "{diagnostic}");
+            }
+            let _ = writeln!(
+                stderr,
+                "gartus: {} diagnostic(s) in `{}`",
+                diagnostics.len(),
+                render.script.display()
+            );
+            return ExitStatus::Diagnostics;
+        }
+    };
+
+    let frame_count = compiled.animation().frames();
+    if render.check {
+        let _ = writeln!(
+            stdout,
+            "{}: ok, {} command(s), {frame_count} frame(s)",
+            render.script.display(),
+            compiled.commands().len()
+        );
+        return ExitStatus::Success;
+    }
+
+    let frames = render
+        .frames
+        .map_or(0..frame_count, |frames| frames.resolve(frame_count));
+    if frames.is_empty() || frames.end > frame_count {
+        let _ = writeln!(
+            stderr,
+            "gartus: error: frames {}..{} are outside the script's {frame_count} frame(s)",
+            frames.start, frames.end
+        );
+        return ExitStatus::Usage;
+    }
+
+    let written = if compiled.animation().is_animated() {
+        render_animation(&compiled, render, frames)
+    } else {
+        render_still(&compiled, render)
+    };
+    match written {
+        Ok(paths) => {
+            for path in paths {
+                let _ = writeln!(stdout, "{}", path.display());
+            }
+            ExitStatus::Success
+        }
+        Err(error) => {
+            let _ = writeln!(stderr, "gartus: error: {error}");
+            ExitStatus::RenderFailed
+        }
+    }
+}
+
+fn render_still(
+    compiled: &CompiledProgram,
+    render: &RenderArgs,
+) -> Result<Vec<PathBuf>, ExecutionError> {
+    let config = render.render_config();
+    if render.output.is_none() && render.format.is_none() {
+        for_each_compiled_frame(compiled, &config, |_, _| Ok(()))?;
+        return Ok(Vec::new());
+    }
+
+    let format = render
+        .explicit_format()
+        .unwrap_or_else(|| DEFAULT_FORMAT.to_string());
+    let path = render.output_file(&format);
+    execute_compiled_frame(compiled, &config.save_enabled(false), 0)?.save_to_path(&path)?;
+    Ok(vec![path])
+}
+
+fn render_animation(
+    compiled: &CompiledProgram,
+    render: &RenderArgs,
+    frames: Range<usize>,
+) -> Result<Vec<PathBuf>, ExecutionError> {
+    let config = render.render_config();
+    let format = render
+        .explicit_format()
+        .unwrap_or_else(|| DEFAULT_FORMAT.to_string());
+    if format == "gif" {
+        let path = render.output_file(&format);
+        let options = AnimationRenderOptions::new(
+            DEFAULT_FRAME_DIR,
+            format!("{}-", compiled.animation().basename()),
+            frames.len(),
+            &path,
+        );
+        execute_compiled_gif_range_with_options(compiled, config, options, frames)?;
+        return Ok(vec![path]);
+    }
+
+    let directory = match &render.output {
+        Some(output) if render.format.is_some() || is_directory_like(output) => output.clone(),
+        Some(output) => output.parent().map(Path::to_path_buf).unwrap_or_default(),
+        None => PathBuf::from(DEFAULT_FRAME_DIR),
+    };
+    let output = FrameOutputConfig::new(directory).extension(format);
+    execute_compiled_frame_range_with_options(compiled, config, output, frames)
+}
+
+/// Treats existing directories, trailing separators, and extension-less paths as directories.
+fn is_directory_like(path: &Path) -> bool {
+    path.is_dir()
+        || path.as_os_str().to_string_lossy().ends_with(['/', '\\'])
+        || path.extension().is_none()
+}
+
+fn parse_size(value: &str) -> Result<(u32, u32), UsageError> {
+    let invalid = || UsageError::new(format!("`--size` expects <W>x<H>, got `{value}`"));
+    let (width, height) = value.split_once(['x', 'X']).ok_or_else(invalid)?;
+    let width = width.parse::<u32>().map_err(|_| invalid())?;
+    let height = height.parse::<u32>().map_err(|_| invalid())?;
+    if width == 0 || height == 0 {
+        return Err(invalid());
+    }
+    Ok((width, height))
+}
+
+fn parse_count(name: &str, value: &str) -> Result<u32, UsageError> {
+    value
+        .parse::<u32>()
+        .ok()
+        .filter(|count| *count > 0)
+        .ok_or_else(|| {
+            UsageError::new(format!(
+                "`{name}` expects a positive integer, got `{value}`"
+            ))
+        })
+}
+
+fn parse_format(value: &str) -> Result<String, UsageError> {
+    let format = value.trim_start_matches('.').to_ascii_lowercase();
+    if format.is_empty() || !format.chars().all(|ch| ch.is_ascii_alphanumeric()) {
+        return Err(UsageError::new(format!(
+            "`--format` expects a file extension, got `{value}`"
+        )));
+    }
+    Ok(format)
+}
+
+fn parse_frames(value: &str) -> Result<FrameSelection, UsageError> {
+    let invalid = || {
+        UsageError::new(format!(
+            "`--frames` expects N, A..B, A..=B, A.., or ..B, got `{value}`"
+        ))
+    };
+    let bound = |text: &str| text.parse::<usize>().map_err(|_| invalid());
+    let selection = if let Some((start, end)) = value.split_once("..=") {
+        FrameSelection {
+            start: if start.is_empty() { 0 } else { bound(start)? },
+            end: Some(bound(end)?.checked_add(1).ok_or_else(invalid)?),
+        }
+    } else if let Some((start, end)) = value.split_once("..") {
+        FrameSelection {
+            start: if start.is_empty() { 0 } else { bound(start)? },
+            end: if end.is_empty() {
+                None
+            } else {
+                Some(bound(end)?)
+            },
+        }
+    } else {
+        let frame = bound(value)?;
+        FrameSelection {
+            start: frame,
+            end: Some(frame.checked_add(1).ok_or_else(invalid)?),
+        }
+    };
+    if selection.end.is_some_and(|end| end <= selection.start) {
+        return Err(invalid());
+    }
+    Ok(selection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("gartus-cli-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("create temp dir");
+        dir
+    }
+
+    fn run_args(args: &[&str]) -> (ExitStatus, String, String) {
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        let status = run(args.iter().copied(), &mut stdout, &mut stderr);
+        (
+            status,
+            String::from_utf8(stdout).expect("utf8 stdout"),
+            String::from_utf8(stderr).expect("utf8 stderr"),
+        )
+    }
+
+    #[test]
+    fn parses_render_options() {
+        let command = parse_args([
+            "render",
+            "scene.mdl",
+            "--size",
+            "1024x768",
+            "--spp",
+            "256",
+            "--max-depth",
+            "12",
+            "--out",
+            "frames/",
+            "--format",
+            ".PNG",
+            "--frames",
+            "10..=19",
+        ])
+        .expect("valid arguments");
+
+        let mut expected = RenderArgs::new("scene.mdl");
+        expected.size = (1024, 768);
+        expected.samples_per_pixel = Some(256);
+        expected.max_depth = Some(12);
+        expected.output = Some(PathBuf::from("frames/"));
+        expected.format = Some("png".to_string());
+        expected.frames = Some(FrameSelection {
+            start: 10,
+            end: Some(20),
+        });
+        assert_eq!(command, CliCommand::Render(expected));
+    }
+
+    #[test]
+    fn parses_frame_selections() {
+        let frames = |value| parse_frames(value).map(|frames| frames.resolve(30));
+
+        assert_eq!(frames("4"), Ok(4..5));
+        assert_eq!(frames("2..6"), Ok(2..6));
+        assert_eq!(frames("..3"), Ok(0..3));
+        assert_eq!(frames("25.."), Ok(25..30));
+        assert!(frames("6..6").is_err());
+        assert!(frames("a..b").is_err());
+    }
+
+    #[test]
+    fn rejects_bad_arguments_with_usage_status() {
+        for args in [
+            &[][..],
+            &["paint", "scene.mdl"],
+            &["render"],
+            &["render", "scene.mdl", "--size", "0x10"],
+            &["render", "scene.mdl", "--spp"],
+            &["render", "scene.mdl", "--bogus"],
+            &["render", "a.mdl", "b.mdl"],
+        ] {
+            let (status, _, stderr) = run_args(args);
+            assert_eq!(status, ExitStatus::Usage, "{args:?}");
+            assert!(stderr.starts_with("gartus: error:"), "{stderr}");
+        }
+        assert_eq!(run_args(&["--help"]).0, ExitStatus::Success);
+    }
+
+    #[test]
+    fn check_reports_diagnostics_with_exit_code() {
+        let dir = temp_dir("check");
+        let good = dir.join("good.mdl");
+        let bad = dir.join("bad.mdl");
+        fs::write(&good, "box 0 0 0 10 10 10\n").expect("write script");
+        fs::write(&bad, "box 0 0\n").expect("write script");
+
+        let (ok_status, ok_stdout, _) = run_args(&["check", good.to_str().expect("utf8 path")]);
+        let (bad_status, _, bad_stderr) =
+            run_args(&["render", bad.to_str().expect("utf8 path"), "--check"]);
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(ok_status, ExitStatus::Success);
+        assert!(
+            ok_stdout.contains("ok, 1 command(s), 1 frame(s)"),
+            "{ok_stdout}"
+        );
+        assert_eq!(bad_status, ExitStatus::Diagnostics);
+        assert_eq!(ExitStatus::Diagnostics.code(), 3);
+        assert!(bad_stderr.contains("line 1"), "{bad_stderr}");
+    }
+
+    #[test]
+    fn renders_still_to_output_file() {
+        let dir = temp_dir("still");
+        let script = dir.join("still.mdl");
+        fs::write(&script, "box -50 50 0 100 100 100\nsave ignored.png\n").expect("write script");
+        let output = dir.join("out");
+
+        let (status, stdout, stderr) = run_args(&[
+            "render",
+            script.to_str().expect("utf8 path"),
+            "--size",
+            "32x16",
+            "--out",
+            output.to_str().expect("utf8 path"),
+            "--format",
+            "ppm",
+        ]);
+        let written = output.join("still.ppm");
+        let image = crate::graphics::texture::load_ppm_canvas(&written);
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(status, ExitStatus::Success, "{stderr}");
+        assert_eq!(stdout.trim(), written.display().to_string());
+        let image = image.expect("rendered ppm");
+        assert_eq!((image.width(), image.height()), (32, 16));
+        assert!(!Path::new("ignored.png").exists());
+    }
+
+    #[test]
+    fn renders_selected_animation_frames() {
+        let dir = temp_dir("anim");
+        let script = dir.join("spin.mdl");
+        fs::write(
+            &script,
+            "frames 6\nbasename spin\nvary k 0 5 0 1\nrotate y 90 k\nbox -50 50 0 100 100 100\n",
+        )
+        .expect("write script");
+        let output = dir.join("frames");
+
+        let (status, stdout, stderr) = run_args(&[
+            "render",
+            script.to_str().expect("utf8 path"),
+            "--size",
+            "16x16",
+            "--frames",
+            "2..4",
+            "--out",
+            output.to_str().expect("utf8 path"),
+        ]);
+        let (range_status, _, _) = run_args(&[
+            "render",
+            script.to_str().expect("utf8 path"),
+            "--frames",
+            "5..9",
+        ]);
+        let mut files = fs::read_dir(&output)
+            .map(|entries| {
+                entries
+                    .map(|entry| entry.expect("dir entry").file_name())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        files.sort();
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(status, ExitStatus::Success, "{stderr}");
+        assert_eq!(stdout.lines().count(), 2);
+        assert_eq!(files, ["spin00000002.png", "spin00000003.png"]);
+        assert_eq!(range_status, ExitStatus::Usage);
+    }
+}
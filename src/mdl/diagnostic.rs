@@ -17,6 +17,44 @@ pub struct Diagnostic {
     pub message: String,
     /// Optional guidance for fixing the error.
     pub help: Option<String>,
+    /// Related source locations, such as the call sites that expanded a procedure body.
+    pub notes: Vec<DiagnosticNote>,
+}
+
+/// A secondary source location attached to a [`Diagnostic`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiagnosticNote {
+    /// Optional source filename.
+    pub source_name: Option<PathBuf>,
+    /// One-based source line.
+    pub line: usize,
+    /// One-based starting source column.
+    pub col_start: usize,
+    /// One-based ending source column.
+    pub col_end: usize,
+    /// Note message.
+    pub message: String,
+}
+
+impl DiagnosticNote {
+    /// Creates a note at a source span.
+    #[must_use]
+    pub fn new(line: usize, col_start: usize, col_end: usize, message: impl Into<String>) -> Self {
+        Self {
+            source_name: None,
+            line,
+            col_start,
+            col_end,
+            message: message.into(),
+        }
+    }
+
+    /// Attaches a source filename.
+    #[must_use]
+    pub fn with_source(mut self, source_name: impl Into<PathBuf>) -> Self {
+        self.source_name = Some(source_name.into());
+        self
+    }
 }
 
 impl Diagnostic {
@@ -30,6 +68,7 @@ impl Diagnostic {
             col_end,
             message: message.into(),
             help: None,
+            notes: Vec::new(),
         }
     }
 
@@ -52,6 +91,13 @@ impl Diagnostic {
         self.source_name = Some(source_name.into());
         self
     }
+
+    /// Appends a related source location.
+    #[must_use]
+    pub fn with_note(mut self, note: DiagnosticNote) -> Self {
+        self.notes.push(note);
+        self
+    }
 }
 
 impl fmt::Display for Diagnostic {
@@ -67,6 +113,17 @@ impl fmt::Display for Diagnostic {
         if let Some(help) = &self.help {
             write!(f, "\n  help: {help}")?;
         }
+        for note in &self.notes {
+            write!(f, "\n  note: ")?;
+            if let Some(source_name) = &note.source_name {
+                write!(f, "{}:", source_name.display())?;
+            }
+            write!(
+                f,
+                "line {}, col {}: {}",
+                note.line, note.col_start, note.message
+            )?;
+        }
         Ok(())
     }
 }
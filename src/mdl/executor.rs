@@ -4,42 +4,54 @@ use super::{
     animation::FrameOutputConfig,
     ast::{
         AnimationCommand, Axis, CameraCommand, ColorSpec, Command, ControlCommand, CurveCommand,
-        FilterCommand, OutputCommand, PointRef, Program, RenderCommand, ShadingMode, ShapeCommand,
-        Spanned, TransformCommand, Vec2, Vec3,
+        DeferredCommand, Expr, ExprKind, FilterCommand, OutputCommand, PointRef, Program,
+        RenderCommand, ShadingMode, ShapeCommand, Spanned, TransformCommand, Vec2, Vec3,
     },
+    diagnostic::Diagnostic,
+    expr::{evaluate, non_finite_diagnostic},
     lexer::Span,
-    runtime::{Light, RenderConfig, Runtime, rgb_from_vec3},
+    parser::resolve_expression_command,
+    runtime::{AnalyticShapeMaterial, Light, RenderConfig, Runtime, rgb_from_vec3},
     semantic::CompiledProgram,
 };
 use crate::{
-    gmath::{edge_matrix::DEFAULT_CURVE_STEP, matrix::Matrix, polygon_matrix::PolygonMatrix},
+    gmath::{
+        edge_matrix::DEFAULT_CURVE_STEP,
+        matrix::Matrix,
+        polygon_matrix::PolygonMatrix,
+        vector::{Point, Vector},
+    },
     graphics::{
         animation::{AnimationError, AnimationRenderOptions, FrameRecorder},
         colors::Rgb,
         display::{PolygonColorMode, ShadingMode as CanvasShadingMode},
+        raytracing::RayGeometry,
     },
 };
 use std::{
     error::Error,
     fmt, fs, io,
+    ops::Range,
     path::{Path, PathBuf},
 };
 
 #[cfg(feature = "external")]
 use super::{
     ast::Material,
-    runtime::{AssetCaches, MaterialConstants},
+    runtime::{AssetCaches, MaterialConstants, RayShapeMaterial},
 };
 #[cfg(feature = "external")]
 use crate::{
     external::{MaterialMeshGroup, MaterialMeshTriangle, MeshMaterial, TexturedMeshTriangle},
-    gmath::{geometry::TriangleGeometry, vector::Vector},
+    gmath::geometry::TriangleGeometry,
     graphics::draw::TexturedVertex,
 };
 #[cfg(feature = "rayon")]
 use rayon::prelude::*;
 
 const DEFAULT_3D_STEPS: usize = 100;
+/// Relative tolerance for treating an MDL transform as a rotation plus uniform scale.
+const SIMILARITY_TOLERANCE: f64 = 1e-9;
 
 /// MDL pipeline stage required before a command can be executed directly.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -48,6 +60,8 @@ pub enum RequiredPipelineStage {
     IncludeExpansion,
     /// Animation planning performed by [`crate::mdl::semantic`].
     AnimationCompilation,
+    /// `let`, `for`, `define`, and `call` expansion performed by [`crate::mdl::semantic`].
+    VariableExpansion,
 }
 
 impl fmt::Display for RequiredPipelineStage {
@@ -55,6 +69,7 @@ impl fmt::Display for RequiredPipelineStage {
         match self {
             Self::IncludeExpansion => f.write_str("include expansion"),
             Self::AnimationCompilation => f.write_str("animation compilation"),
+            Self::VariableExpansion => f.write_str("variable, loop, and procedure expansion"),
         }
     }
 }
@@ -120,6 +135,8 @@ pub enum ExecutionError {
         /// Number of available frames.
         frames: usize,
     },
+    /// An expression argument evaluated to a value its command rejects.
+    Expression(Diagnostic),
     /// GIF/file animation options do not match the compiled animation plan.
     InvalidAnimationOptions {
         /// Expected frame count from the compiled program.
@@ -176,6 +193,11 @@ impl fmt::Display for ExecutionError {
             Self::InvalidFrame { frame, frames } => {
                 write!(f, "frame {frame} is outside compiled frame count {frames}")
             }
+            Self::Expression(diagnostic) => write!(
+                f,
+                "expression at col {}: {}",
+                diagnostic.col_start, diagnostic.message
+            ),
             Self::InvalidAnimationOptions {
                 expected_frames,
                 got_frames,
@@ -192,6 +214,7 @@ impl Error for ExecutionError {
         match self {
             Self::Located { error, .. } => Some(error.as_ref()),
             Self::Io(error) => Some(error),
+            Self::Expression(diagnostic) => Some(diagnostic),
             Self::StackUnderflow
             | Self::UnknownKnob(_)
             | Self::UnknownConstants(_)
@@ -207,6 +230,12 @@ impl Error for ExecutionError {
     }
 }
 
+impl From<Diagnostic> for ExecutionError {
+    fn from(diagnostic: Diagnostic) -> Self {
+        Self::Expression(diagnostic)
+    }
+}
+
 fn with_location(error: ExecutionError, command: &Spanned<Command>) -> ExecutionError {
     if matches!(error, ExecutionError::Located { .. }) {
         return error;
@@ -297,37 +326,76 @@ pub fn execute_compiled_frames_to_files(
 ///
 /// # Errors
 /// Returns an execution error if frame rendering or file writing fails.
-#[allow(clippy::needless_pass_by_value)]
 pub fn execute_compiled_frames_with_options(
     compiled: &CompiledProgram,
     config: RenderConfig,
     output: FrameOutputConfig,
 ) -> Result<Vec<PathBuf>, ExecutionError> {
+    let frames = 0..compiled.animation().frames();
+    execute_compiled_frame_range_with_options(compiled, config, output, frames)
+}
+
+/// Executes the compiled frames in `frames` and writes files using configurable naming options.
+///
+/// Frame files keep their absolute frame numbers, so a range render can fill in part of a
+/// sequence written by an earlier run.
+///
+/// # Errors
+/// Returns [`ExecutionError::InvalidFrame`] if `frames` is empty or extends past the compiled
+/// frame count, or an execution error if frame rendering or file writing fails.
+#[allow(clippy::needless_pass_by_value)]
+pub fn execute_compiled_frame_range_with_options(
+    compiled: &CompiledProgram,
+    config: RenderConfig,
+    output: FrameOutputConfig,
+    frames: Range<usize>,
+) -> Result<Vec<PathBuf>, ExecutionError> {
+    check_frame_range(compiled, &frames)?;
     #[cfg(feature = "rayon")]
     {
-        execute_compiled_frames_with_options_parallel(compiled, config, &output)
+        execute_compiled_frames_with_options_parallel(compiled, config, &output, frames)
     }
     #[cfg(not(feature = "rayon"))]
     {
         fs::create_dir_all(output.output_dir_path()).map_err(ExecutionError::Io)?;
 
-        let mut paths = Vec::with_capacity(compiled.animation().frames());
+        let mut paths = Vec::with_capacity(frames.len());
         let config = config.save_enabled(false);
-        for_each_compiled_frame(compiled, &config, |frame, runtime| {
+        let mut runtime = Runtime::new(&config);
+        for frame in frames {
+            execute_compiled_frame_into(&mut runtime, compiled, frame)?;
             let path = output.frame_path(compiled.animation().basename(), frame);
             runtime.save_to_path(&path)?;
             paths.push(path);
-            Ok(())
-        })?;
+        }
         Ok(paths)
     }
 }
 
+fn check_frame_range(
+    compiled: &CompiledProgram,
+    frames: &Range<usize>,
+) -> Result<(), ExecutionError> {
+    let available = compiled.animation().frames();
+    if frames.is_empty() || frames.end > available {
+        return Err(ExecutionError::InvalidFrame {
+            frame: if frames.is_empty() {
+                frames.start
+            } else {
+                frames.end - 1
+            },
+            frames: available,
+        });
+    }
+    Ok(())
+}
+
 #[cfg(feature = "rayon")]
 fn execute_compiled_frames_with_options_parallel(
     compiled: &CompiledProgram,
     config: RenderConfig,
     output: &FrameOutputConfig,
+    frames: Range<usize>,
 ) -> Result<Vec<PathBuf>, ExecutionError> {
     fs::create_dir_all(output.output_dir_path()).map_err(ExecutionError::Io)?;
 
@@ -335,7 +403,7 @@ fn execute_compiled_frames_with_options_parallel(
     let basename = compiled.animation().basename().to_string();
     #[cfg(feature = "external")]
     let asset_caches = preload_compiled_assets(compiled, &config)?;
-    let mut paths = (0..compiled.animation().frames())
+    let mut paths = frames
         .into_par_iter()
         .map(|frame| {
             #[cfg(feature = "external")]
@@ -369,9 +437,11 @@ pub fn execute_compiled_frames_to_default_dir(
 
 /// Executes a compiled program and encodes its frames as a GIF.
 ///
-/// Frames are rendered through the existing [`FrameRecorder`] GIF pipeline. MDL
-/// `save` commands are disabled during GIF rendering so they do not overwrite a
-/// static filename while each frame is being generated.
+/// Frames are rendered through the existing [`FrameRecorder`] GIF pipeline and,
+/// unless the options disable streaming, encoded as they are produced without
+/// intermediate frame files. MDL `save` commands are disabled during GIF
+/// rendering so they do not overwrite a static filename while each frame is
+/// being generated.
 ///
 /// # Errors
 /// Returns an execution error if frame rendering or GIF encoding fails.
@@ -394,15 +464,34 @@ pub fn execute_compiled_gif(
 ///
 /// # Errors
 /// Returns an execution error if frame rendering or GIF encoding fails.
-#[allow(clippy::needless_pass_by_value)]
 pub fn execute_compiled_gif_with_options(
     compiled: &CompiledProgram,
     config: RenderConfig,
     options: AnimationRenderOptions,
 ) -> Result<(), ExecutionError> {
-    if options.frames() != compiled.animation().frames() {
+    let frames = 0..compiled.animation().frames();
+    execute_compiled_gif_range_with_options(compiled, config, options, frames)
+}
+
+/// Executes the compiled frames in `frames` and encodes them as a GIF with explicit options.
+///
+/// GIF frame `i` (and a preview index `i`) refers to compiled frame `frames.start + i`.
+///
+/// # Errors
+/// Returns [`ExecutionError::InvalidFrame`] if `frames` is empty or extends past the compiled
+/// frame count, [`ExecutionError::InvalidAnimationOptions`] if `options` does not request
+/// `frames.len()` frames, or an execution error if frame rendering or GIF encoding fails.
+#[allow(clippy::needless_pass_by_value)]
+pub fn execute_compiled_gif_range_with_options(
+    compiled: &CompiledProgram,
+    config: RenderConfig,
+    options: AnimationRenderOptions,
+    frames: Range<usize>,
+) -> Result<(), ExecutionError> {
+    check_frame_range(compiled, &frames)?;
+    if options.frames() != frames.len() {
         return Err(ExecutionError::InvalidAnimationOptions {
-            expected_frames: compiled.animation().frames(),
+            expected_frames: frames.len(),
             got_frames: options.frames(),
         });
     }
@@ -410,7 +499,7 @@ pub fn execute_compiled_gif_with_options(
     let config = config.save_enabled(false);
     let mut runtime = Runtime::new(&config);
     FrameRecorder::render_gif_with_recorder(options, |frame, preview_output, recorder| {
-        execute_compiled_frame_into(&mut runtime, compiled, frame)
+        execute_compiled_frame_into(&mut runtime, compiled, frames.start + frame)
             .map_err(AnimationError::Render)?;
         if let Some(preview_output) = preview_output {
             runtime
@@ -579,7 +668,13 @@ fn execute_command(
             Ok(())
         }
         Command::Output(command) => execute_output_command(runtime, command),
-        Command::Include(_) | Command::Filter(_) => execute_misc_command(runtime, command),
+        Command::Deferred(command) => execute_deferred_command(runtime, command, source_name),
+        Command::Include(_)
+        | Command::Filter(_)
+        | Command::Let(_)
+        | Command::For(_)
+        | Command::Define(_)
+        | Command::Call(_) => execute_misc_command(runtime, command),
     }
 }
 
@@ -963,9 +1058,29 @@ fn draw_torus(
     r1: f64,
     coord_system: Option<&str>,
 ) -> Result<(), ExecutionError> {
-    draw_polygons(runtime, constants, coord_system, |polygons| {
-        polygons.add_torus((center.x, center.y, center.z), r0, r1, DEFAULT_3D_STEPS);
-    })
+    draw_shape(
+        runtime,
+        constants,
+        coord_system,
+        |polygons| {
+            polygons.add_torus((center.x, center.y, center.z), r0, r1, DEFAULT_3D_STEPS);
+        },
+        |transform| {
+            // Spindle tori, whose tube is wider than the ring, stay tessellated.
+            let (minor, major) = (r0.abs(), r1.abs());
+            if minor > major {
+                return None;
+            }
+            let (center, axis, scale) =
+                similarity_frame(transform, center, Vector::new(0.0, 1.0, 0.0))?;
+            Some(RayGeometry::torus(
+                center,
+                axis,
+                major * scale,
+                minor * scale,
+            ))
+        },
+    )
 }
 
 fn draw_cylinder(
@@ -976,9 +1091,19 @@ fn draw_cylinder(
     height: f64,
     coord_system: Option<&str>,
 ) -> Result<(), ExecutionError> {
-    draw_polygons(runtime, constants, coord_system, |polygons| {
-        polygons.add_cylinder((center.x, center.y, center.z), radius, height, 24);
-    })
+    draw_shape(
+        runtime,
+        constants,
+        coord_system,
+        |polygons| {
+            polygons.add_cylinder((center.x, center.y, center.z), radius, height, 24);
+        },
+        |transform| {
+            let (base, axis, scale) =
+                similarity_frame(transform, center, Vector::new(0.0, 0.0, height))?;
+            Some(RayGeometry::cylinder(base, axis, radius.abs() * scale))
+        },
+    )
 }
 
 fn draw_cone(
@@ -989,9 +1114,19 @@ fn draw_cone(
     height: f64,
     coord_system: Option<&str>,
 ) -> Result<(), ExecutionError> {
-    draw_polygons(runtime, constants, coord_system, |polygons| {
-        polygons.add_cone((center.x, center.y, center.z), radius, height, 24);
-    })
+    draw_shape(
+        runtime,
+        constants,
+        coord_system,
+        |polygons| {
+            polygons.add_cone((center.x, center.y, center.z), radius, height, 24);
+        },
+        |transform| {
+            let (base, axis, scale) =
+                similarity_frame(transform, center, Vector::new(0.0, 0.0, height))?;
+            Some(RayGeometry::cone(base, axis, radius.abs() * scale))
+        },
+    )
 }
 
 fn draw_pyramid(
@@ -1062,6 +1197,11 @@ fn execute_render_state_command(
         } => runtime.set_constants(name.clone(), *material, *color),
         RenderCommand::Shading(mode) => set_shading(runtime, *mode),
         RenderCommand::SaveCoordSystem(name) => runtime.save_coord_system(name.clone()),
+        RenderCommand::RayMaterial { name, material } => {
+            runtime.set_ray_material(name.clone(), *material);
+        }
+        RenderCommand::Medium { name, medium } => runtime.set_medium(name.clone(), *medium),
+        RenderCommand::LightSource(source) => runtime.add_light_source(*source),
     }
     Ok(())
 }
@@ -1091,11 +1231,45 @@ fn execute_misc_command(runtime: &mut Runtime, command: &Command) -> Result<(),
             command: "include",
             stage: RequiredPipelineStage::IncludeExpansion,
         }),
+        Command::Let(_) => Err(ExecutionError::CommandRequiresStage {
+            command: "let",
+            stage: RequiredPipelineStage::VariableExpansion,
+        }),
+        Command::For(_) => Err(ExecutionError::CommandRequiresStage {
+            command: "for",
+            stage: RequiredPipelineStage::VariableExpansion,
+        }),
+        Command::Define(_) => Err(ExecutionError::CommandRequiresStage {
+            command: "define",
+            stage: RequiredPipelineStage::VariableExpansion,
+        }),
+        Command::Call(_) => Err(ExecutionError::CommandRequiresStage {
+            command: "call",
+            stage: RequiredPipelineStage::VariableExpansion,
+        }),
         Command::Filter(filter) => execute_filter_command(runtime, filter),
         _ => unreachable!("non-misc command dispatched to misc executor"),
     }
 }
 
+fn execute_deferred_command(
+    runtime: &mut Runtime,
+    command: &DeferredCommand,
+    source_name: Option<&Path>,
+) -> Result<(), ExecutionError> {
+    let mut expressions = command.expressions.clone();
+    let resolved = resolve_expression_command(&command.tokens, &mut expressions, &mut |expr| {
+        let value = evaluate(expr, &mut |name, _| runtime.knob_value(Some(name)))?;
+        if value.is_finite() {
+            Ok(Expr::new(ExprKind::Number(value), expr.span))
+        } else {
+            Err(ExecutionError::Expression(non_finite_diagnostic(expr.span)))
+        }
+    })?
+    .expect("evaluated expressions resolve every argument");
+    execute_command(runtime, &resolved, source_name)
+}
+
 fn execute_filter_command(
     runtime: &mut Runtime,
     filter: &FilterCommand,
@@ -1118,6 +1292,18 @@ fn draw_polygons(
     constants: Option<&str>,
     coord_system: Option<&str>,
     build: impl FnOnce(&mut crate::gmath::polygon_matrix::PolygonMatrix),
+) -> Result<(), ExecutionError> {
+    draw_shape(runtime, constants, coord_system, build, |_| None)
+}
+
+/// Draws a tessellated shape, capturing `analytic` geometry for ray tracing instead of the
+/// triangles when it can represent the transformed shape exactly.
+fn draw_shape(
+    runtime: &mut Runtime,
+    constants: Option<&str>,
+    coord_system: Option<&str>,
+    build: impl FnOnce(&mut crate::gmath::polygon_matrix::PolygonMatrix),
+    analytic: impl FnOnce(&Matrix) -> Option<RayGeometry>,
 ) -> Result<(), ExecutionError> {
     let transform = runtime.transform_for(coord_system)?;
     let material = runtime.material_for(constants)?;
@@ -1125,12 +1311,25 @@ fn draw_polygons(
         crate::graphics::material::SurfaceMaterial::default,
         Into::into,
     );
+    let ray_material = runtime.ray_material_for(constants);
     let previous = runtime.apply_draw_state(material);
 
     runtime.with_tmp_polygons(build);
     runtime.transform_tmp_polygons(&transform);
     if runtime.should_capture_surfaces() {
-        runtime.add_surface_mesh(runtime.tmp_polygons().clone(), surface_material);
+        if let Some(geometry) = analytic(&transform) {
+            let material = ray_material.map_or(
+                AnalyticShapeMaterial::Constants(surface_material),
+                AnalyticShapeMaterial::Ray,
+            );
+            runtime.add_ray_shape(geometry, material);
+        } else {
+            let polygons = runtime.tmp_polygons().clone();
+            match ray_material {
+                Some(ray_material) => runtime.add_ray_surface(polygons, ray_material),
+                None => runtime.add_surface_mesh(polygons, surface_material),
+            }
+        }
     }
     runtime.draw_tmp_polygons();
 
@@ -1138,6 +1337,46 @@ fn draw_polygons(
     Ok(())
 }
 
+/// Maps a shape's local origin and `axis` through an MDL transform.
+///
+/// Returns the transformed origin, the transformed axis, and the transform's uniform scale, or
+/// `None` when the transform shears, scales non-uniformly, or is projective, since analytic
+/// shapes cannot represent those exactly.
+fn similarity_frame(
+    transform: &Matrix,
+    origin: Vec3,
+    axis: Vector,
+) -> Option<(Point, Vector, f64)> {
+    let map = |x: f64, y: f64, z: f64| {
+        let [x, y, z, w] = transform.transform_homogeneous_point(&[x, y, z, 1.0]);
+        ((w - 1.0).abs() <= SIMILARITY_TOLERANCE).then(|| Point::new(x, y, z))
+    };
+    let center = map(origin.x, origin.y, origin.z)?;
+    let column = |offset: Vector| {
+        map(
+            origin.x + offset.x(),
+            origin.y + offset.y(),
+            origin.z + offset.z(),
+        )
+        .map(|point| point - center)
+    };
+    let x_axis = column(Vector::new(1.0, 0.0, 0.0))?;
+    let y_axis = column(Vector::new(0.0, 1.0, 0.0))?;
+    let z_axis = column(Vector::new(0.0, 0.0, 1.0))?;
+
+    let scale = x_axis.length();
+    let tolerance = SIMILARITY_TOLERANCE * scale.max(1.0);
+    let uniform = (y_axis.length() - scale).abs() <= tolerance
+        && (z_axis.length() - scale).abs() <= tolerance;
+    let orthogonal = [x_axis.dot(y_axis), y_axis.dot(z_axis), z_axis.dot(x_axis)]
+        .iter()
+        .all(|dot| dot.abs() <= tolerance * scale);
+    if scale <= f64::EPSILON || !uniform || !orthogonal {
+        return None;
+    }
+    Some((center, column(axis)?, scale))
+}
+
 fn draw_line(
     runtime: &mut Runtime,
     constants: Option<&str>,
@@ -1198,6 +1437,7 @@ fn draw_mesh(
 ) -> Result<(), ExecutionError> {
     let transform = runtime.transform_for(coord_system)?;
     let material = runtime.material_for(constants)?;
+    let ray_material = runtime.ray_material_for(constants);
     let path = runtime.resolve_mesh_path(filename, source_name);
 
     let mesh = runtime.load_mesh_cached(&path)?;
@@ -1224,11 +1464,12 @@ fn draw_mesh(
                 &transform,
                 reverse,
                 surface_material.clone(),
+                ray_material,
             );
             draw_textured_mesh_group(runtime, group, &texture, &transform, reverse);
         } else {
             prepare_external_mesh_group_polygons(runtime, group, &transform, reverse);
-            capture_prepared_mesh_surface(runtime, surface_material);
+            capture_prepared_mesh_surface(runtime, surface_material, ray_material);
             if reverse {
                 runtime.draw_tmp_polygons();
             } else {
@@ -1249,13 +1490,14 @@ fn capture_external_mesh_group_surface(
     transform: &Matrix,
     reverse: bool,
     material: crate::graphics::material::SurfaceMaterial,
+    ray_material: Option<RayShapeMaterial>,
 ) {
     if !runtime.should_capture_surfaces() {
         return;
     }
 
     prepare_external_mesh_group_polygons(runtime, group, transform, reverse);
-    capture_prepared_mesh_surface(runtime, material);
+    capture_prepared_mesh_surface(runtime, material, ray_material);
 }
 
 #[cfg(feature = "external")]
@@ -1278,9 +1520,14 @@ fn prepare_external_mesh_group_polygons(
 fn capture_prepared_mesh_surface(
     runtime: &mut Runtime,
     material: crate::graphics::material::SurfaceMaterial,
+    ray_material: Option<RayShapeMaterial>,
 ) {
     if runtime.should_capture_surfaces() {
-        runtime.add_surface_mesh(runtime.tmp_polygons().clone(), material);
+        let polygons = runtime.tmp_polygons().clone();
+        match ray_material {
+            Some(ray_material) => runtime.add_ray_surface(polygons, ray_material),
+            None => runtime.add_surface_mesh(polygons, material),
+        }
     }
 }
 
@@ -1658,19 +1905,20 @@ mod tests {
     #[cfg(feature = "external")]
     use crate::mdl::runtime::Runtime;
     use crate::{
-        gmath::matrix::Matrix,
+        gmath::{matrix::Matrix, vector::Vector},
         graphics::{
             colors::Rgb,
             display::{PolygonColorMode, ShadingMode as CanvasShadingMode},
+            lighting::LightKind,
         },
         mdl::{
             animation::FrameOutputConfig,
-            ast::Vec3,
+            ast::{MediumSpec, RayMaterialSpec, Vec3},
             parser::parse_script,
-            runtime::{Light, RenderConfig, Symbol},
+            runtime::{AnalyticShapeMaterial, Light, RayShapeMaterial, RenderConfig, Symbol},
             semantic::compile,
         },
-        prelude::AnimationRenderOptions,
+        prelude::{AnimationRenderOptions, RayGeometry},
     };
 
     fn execute(src: &str) -> crate::mdl::runtime::Runtime {
@@ -1742,6 +1990,65 @@ mod tests {
         }
     }
 
+    #[test]
+    fn raw_variable_loop_and_procedure_commands_require_expansion() {
+        for (source, command) in [
+            ("let n = 2", "let"),
+            ("for i in 0..2 {\n}", "for"),
+            ("define f() {\n}", "define"),
+            ("call f()", "call"),
+        ] {
+            let program = parse_script(source).unwrap();
+            let error =
+                execute_program(&program, &RenderConfig::new(10, 10).display_enabled(false))
+                    .unwrap_err();
+
+            assert!(matches!(
+                error_kind(&error),
+                ExecutionError::CommandRequiresStage {
+                    command: actual,
+                    stage: RequiredPipelineStage::VariableExpansion,
+                } if *actual == command
+            ));
+        }
+    }
+
+    #[test]
+    fn compiled_frame_evaluates_knob_expressions_per_frame() {
+        let program =
+            parse_script("frames 3\nvary k 0 2 0 1\nlet base = 4\nmove (base + k * 6) 0 0")
+                .unwrap();
+        let compiled = compile(program).unwrap();
+        let config = RenderConfig::new(10, 10).display_enabled(false);
+
+        let offsets = (0..3)
+            .map(|frame| {
+                execute_compiled_frame(&compiled, &config, frame)
+                    .unwrap()
+                    .top_transform()
+                    .get(0, 3)
+            })
+            .collect::<Vec<_>>();
+
+        assert!((offsets[0] - 4.0).abs() < 1e-9);
+        assert!((offsets[1] - 7.0).abs() < 1e-9);
+        assert!((offsets[2] - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn knob_expressions_report_runtime_range_errors() {
+        let program = parse_script("set k 0\nscale 1 (1 / k) 1").unwrap();
+        let compiled = compile(program).unwrap();
+        let error =
+            execute_compiled_program(&compiled, &RenderConfig::new(10, 10).display_enabled(false))
+                .unwrap_err();
+
+        assert!(matches!(
+            error_kind(&error),
+            ExecutionError::Expression(diagnostic) if diagnostic.col_start == 9
+        ));
+    }
+
     #[test]
     fn shading_toon_sets_canvas_toon_mode() {
         let runtime = execute("shading toon");
@@ -1807,6 +2114,201 @@ save {}
         let _ = std::fs::remove_file(path);
     }
 
+    #[test]
+    fn material_and_medium_names_capture_ray_surfaces() {
+        let runtime = execute(
+            "shading raytrace\nmaterial gold ggx color 1 0.8 0.3 roughness 0.3\nmedium fog density 0.5 albedo 0.9 0.9 0.9 anisotropy 0.4\nsphere gold 0 0 0 20\nbox fog 0 0 0 10 10 10\nsphere 0 0 0 5",
+        );
+
+        assert_eq!(runtime.captured_surface_count(), 1);
+        let materials = runtime
+            .captured_ray_surfaces()
+            .iter()
+            .map(|(polygons, material)| {
+                assert!(polygons.triangle_count() > 0);
+                *material
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            materials,
+            [
+                RayShapeMaterial::Surface(RayMaterialSpec::Ggx {
+                    color: Vec3::new(1.0, 0.8, 0.3),
+                    roughness: 0.3,
+                }),
+                RayShapeMaterial::Medium(MediumSpec {
+                    density: 0.5,
+                    albedo: Vec3::new(0.9, 0.9, 0.9),
+                    anisotropy: 0.4,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn raytrace_cylinders_cones_and_tori_capture_exact_geometry() {
+        let runtime = execute(
+            "shading raytrace\nmaterial gold ggx color 1 0.8 0.3 roughness 0.3\nrotate x 90\nscale 2 2 2\ncylinder gold 0 0 0 1 3\ncone 0 0 0 1 2\nscale 1 2 1\ntorus 0 0 0 0.5 2",
+        );
+
+        // The non-uniform scale leaves the torus tessellated.
+        assert_eq!(runtime.captured_surface_count(), 1);
+        let shapes = runtime.captured_ray_shapes();
+        assert_eq!(shapes.len(), 2);
+        let (RayGeometry::Cylinder(cylinder), material) = &shapes[0] else {
+            panic!("expected a cylinder, got {:?}", shapes[0]);
+        };
+        assert!((cylinder.height() - 6.0).abs() < 1e-9);
+        assert!((cylinder.radius() - 2.0).abs() < 1e-9);
+        assert!((cylinder.basis().w().y().abs() - 1.0).abs() < 1e-9);
+        assert_eq!(
+            *material,
+            AnalyticShapeMaterial::Ray(RayShapeMaterial::Surface(RayMaterialSpec::Ggx {
+                color: Vec3::new(1.0, 0.8, 0.3),
+                roughness: 0.3,
+            }))
+        );
+        assert!(matches!(
+            shapes[1],
+            (RayGeometry::Cone(_), AnalyticShapeMaterial::Constants(_))
+        ));
+    }
+
+    #[test]
+    fn raytrace_tori_use_radius_magnitudes_and_tessellate_spindles() {
+        let runtime =
+            execute("shading raytrace\ntorus 0 0 0 -0.5 -2\ntorus 0 0 0 0.5 -2\ntorus 0 0 0 2 0.5");
+
+        // The spindle torus has a tube wider than its ring and stays tessellated.
+        assert_eq!(runtime.captured_surface_count(), 1);
+        let shapes = runtime.captured_ray_shapes();
+        assert_eq!(shapes.len(), 2);
+        for (geometry, _) in shapes {
+            let RayGeometry::Torus(torus) = geometry else {
+                panic!("expected a torus, got {geometry:?}");
+            };
+            assert!((torus.major_radius() - 2.0).abs() < 1e-9);
+            assert!((torus.minor_radius() - 0.5).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn analytic_emissive_torus_and_fog_cylinder_render() {
+        let path =
+            std::env::temp_dir().join(format!("gartus-mdl-analytic-{}.ppm", std::process::id()));
+        let script = format!(
+            "\
+shading raytrace
+camera 0 0 -5 0 0 0
+focal 20
+material lamp emissive 1 0.9 0.8 4
+medium fog density 0.5 albedo 0.9 0.9 0.9
+rotate x 90
+torus lamp 0 0 0 0.4 1
+cylinder fog -2 0 -0.5 0.3 1
+save {}
+",
+            path.display()
+        );
+
+        let program = parse_script(&script).unwrap();
+        execute_program(
+            &program,
+            &RenderConfig::new_with_bg(16, 16, Rgb::WHITE, Rgb::BLACK).display_enabled(false),
+        )
+        .unwrap();
+        let image = crate::graphics::texture::load_ppm_canvas(&path).unwrap();
+        let _ = std::fs::remove_file(path);
+
+        // The ring glows while the hole through its middle stays dark.
+        let ring = *image.get_pixel(11, 8).unwrap();
+        let hole = *image.get_pixel(8, 8).unwrap();
+        assert!(ring.red > 150, "ring {ring:?}");
+        assert!(hole.red < ring.red / 2, "hole {hole:?}");
+    }
+
+    #[test]
+    fn emissive_material_lights_path_traced_output() {
+        let path =
+            std::env::temp_dir().join(format!("gartus-mdl-emissive-{}.ppm", std::process::id()));
+        let script = format!(
+            "\
+shading raytrace
+camera 0 0 -5 0 0 0
+focal 20
+material lamp emissive 1 0.9 0.8 4
+sphere lamp 0 0 0 1
+save {}
+",
+            path.display()
+        );
+
+        let program = parse_script(&script).unwrap();
+        execute_program(
+            &program,
+            &RenderConfig::new_with_bg(16, 16, Rgb::WHITE, Rgb::BLACK).display_enabled(false),
+        )
+        .unwrap();
+        let image = crate::graphics::texture::load_ppm_canvas(&path).unwrap();
+        let _ = std::fs::remove_file(path);
+
+        let center = *image.get_pixel(8, 8).unwrap();
+        let corner = *image.get_pixel(0, 0).unwrap();
+        assert!(center.red > 200, "center {center:?}");
+        assert!(corner.red < 10, "corner {corner:?}");
+    }
+
+    #[test]
+    fn area_light_quad_emits_toward_its_normal_side() {
+        let render = |label: &str, quad: &str| {
+            let path = std::env::temp_dir().join(format!(
+                "gartus-mdl-arealight-{label}-{}.ppm",
+                std::process::id()
+            ));
+            let script = format!(
+                "shading raytrace\ncamera 0 0 -5 0 0 0\nfocal 20\narealight quad 255 255 255 {quad} 2\nsave {}\n",
+                path.display()
+            );
+            let program = parse_script(&script).unwrap();
+            execute_program(
+                &program,
+                &RenderConfig::new_with_bg(16, 16, Rgb::WHITE, Rgb::BLACK).display_enabled(false),
+            )
+            .unwrap();
+            let image = crate::graphics::texture::load_ppm_canvas(&path).unwrap();
+            let _ = std::fs::remove_file(path);
+            *image.get_pixel(8, 8).unwrap()
+        };
+
+        let facing = render("facing", "-1 -1 0 0 2 0 2 0 0");
+        let away = render("away", "-1 -1 0 2 0 0 0 2 0");
+
+        assert!(facing.red > 200, "facing {facing:?}");
+        assert!(away.red < 10, "away {away:?}");
+    }
+
+    #[test]
+    fn light_sources_feed_raster_lighting() {
+        let runtime = execute(
+            "spotlight 255 0 0 0 10 0 0 -1 0 10 20\ndirlight 0 255 0 0 -1 0\narealight sphere 0 0 255 0 5 0 1",
+        );
+        let lighting = runtime.canvas().lighting();
+
+        assert_eq!(runtime.light_sources().len(), 3);
+        assert_eq!(lighting.point_lights.len(), 3);
+        assert!(lighting.point_lights[0].spot.is_some());
+        assert_eq!(lighting.point_lights[1].kind, LightKind::Directional);
+        assert_eq!(
+            lighting.point_lights[1].location,
+            Vector::new(0.0, 1.0, 0.0)
+        );
+        assert_eq!(
+            lighting.point_lights[2].location,
+            Vector::new(0.0, 5.0, 0.0)
+        );
+        assert_eq!(lighting.point_lights[2].color, Rgb::BLUE);
+    }
+
     #[test]
     fn runtime_errors_include_command_location() {
         let program = parse_script("move 1 0 0\nmove 1 0 0 missing").unwrap();
@@ -0,0 +1,781 @@
+//! Arithmetic expressions for MDL numeric arguments.
+//!
+//! Expressions support `+ - * / ^`, parentheses, unary minus, the constant `pi`, and the built-in
+//! functions listed by [`ExprFunction`]. Trigonometric functions take radians. Names that are not
+//! built-ins are variables: `let` bindings and loop variables are folded by
+//! [`crate::mdl::semantic::compile`], and any remaining names are knob references evaluated per
+//! frame.
+
+use super::{
+    ast::{BinaryOp, Expr, ExprFunction, ExprKind},
+    diagnostic::Diagnostic,
+    lexer::Span,
+};
+
+/// Built-in constant names that cannot be rebound by `let` or `for`.
+const BUILTIN_CONSTANTS: [(&str, f64); 1] = [("pi", std::f64::consts::PI)];
+
+/// Maximum parenthesis and operator nesting accepted by the expression parser.
+const MAX_NESTING: usize = 64;
+
+/// Parses an expression from source text that starts at `col_start` on `line`.
+///
+/// # Errors
+/// Returns a span-accurate diagnostic for unexpected characters, malformed expressions, unknown
+/// functions, or function calls with the wrong number of arguments.
+pub fn parse_expression(text: &str, line: usize, col_start: usize) -> Result<Expr, Diagnostic> {
+    let mut parser = ExprParser::new(text, line, col_start)?;
+    let expr = parser.expression()?;
+    parser.expect_end()?;
+    Ok(expr)
+}
+
+/// Parses a `start..end` or `start..=end` loop range.
+///
+/// Returns the start expression, end expression, and whether the end is inclusive.
+///
+/// # Errors
+/// Returns a diagnostic when either bound is malformed or the range operator is missing.
+pub fn parse_range(
+    text: &str,
+    line: usize,
+    col_start: usize,
+) -> Result<(Expr, Expr, bool), Diagnostic> {
+    let mut parser = ExprParser::new(text, line, col_start)?;
+    let start = parser.expression()?;
+    let inclusive = match parser.peek_kind() {
+        Some(ExprTokenKind::DotDot) => false,
+        Some(ExprTokenKind::DotDotEq) => true,
+        _ => {
+            return Err(parser
+                .error_here("expected `..` or `..=` in `for` range")
+                .with_help("for name in start..end {"));
+        }
+    };
+    parser.pos += 1;
+    let end = parser.expression()?;
+    parser.expect_end()?;
+    Ok((start, end, inclusive))
+}
+
+/// Parses a `name(param, ...)` procedure signature.
+///
+/// Returns the procedure name and its parameter names.
+///
+/// # Errors
+/// Returns a diagnostic for malformed signatures, reserved names, or repeated parameters.
+pub fn parse_signature(
+    text: &str,
+    line: usize,
+    col_start: usize,
+) -> Result<(String, Vec<String>), Diagnostic> {
+    let mut parser = ExprParser::new(text, line, col_start)?;
+    let (name, _, open) = parser.call_head("procedure name")?;
+    let mut params: Vec<String> = Vec::new();
+    if parser.peek_kind() != Some(&ExprTokenKind::RParen) {
+        loop {
+            let Some(ExprToken {
+                kind: ExprTokenKind::Ident(param),
+                start,
+                end,
+            }) = parser.tokens.get(parser.pos).cloned()
+            else {
+                return Err(parser.error_here("expected parameter name"));
+            };
+            if is_reserved_name(&param) {
+                return Err(parser.error_at(
+                    start,
+                    end,
+                    format!("`{param}` is a built-in expression name"),
+                ));
+            }
+            if params.contains(&param) {
+                return Err(parser.error_at(start, end, format!("duplicate parameter `{param}`")));
+            }
+            params.push(param);
+            parser.pos += 1;
+            if parser.peek_kind() == Some(&ExprTokenKind::Comma) {
+                parser.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+    parser.expect_close(open)?;
+    parser.expect_end()?;
+    Ok((name, params))
+}
+
+/// Parses a `name(expression, ...)` procedure call.
+///
+/// Returns the procedure name, its span, and the argument expressions.
+///
+/// # Errors
+/// Returns a diagnostic for malformed calls or argument expressions.
+pub fn parse_call(
+    text: &str,
+    line: usize,
+    col_start: usize,
+) -> Result<(String, Span, Vec<Expr>), Diagnostic> {
+    let mut parser = ExprParser::new(text, line, col_start)?;
+    let (name, name_span, open) = parser.call_head("procedure name")?;
+    let args = parser.arguments()?;
+    parser.expect_close(open)?;
+    parser.expect_end()?;
+    Ok((name, name_span, args))
+}
+
+/// Returns true for names reserved by the expression language.
+#[must_use]
+pub fn is_reserved_name(name: &str) -> bool {
+    ExprFunction::from_name(name).is_some()
+        || BUILTIN_CONSTANTS
+            .iter()
+            .any(|(constant, _)| *constant == name)
+}
+
+/// Substitutes bound variables and folds constant subexpressions.
+///
+/// Variables without a binding are left in place as knob references.
+///
+/// # Errors
+/// Returns a diagnostic at the first constant subexpression that is not finite.
+pub(crate) fn fold(
+    expr: &Expr,
+    binding: &dyn Fn(&str) -> Option<Expr>,
+) -> Result<Expr, Diagnostic> {
+    let kind = match &expr.kind {
+        ExprKind::Number(_) => return Ok(expr.clone()),
+        ExprKind::Variable(name) => {
+            return Ok(match binding(name) {
+                Some(Expr {
+                    kind: ExprKind::Number(value),
+                    ..
+                }) => Expr::new(ExprKind::Number(value), expr.span),
+                Some(bound) => bound,
+                None => expr.clone(),
+            });
+        }
+        ExprKind::Negate(inner) => {
+            let inner = fold(inner, binding)?;
+            match inner.as_number() {
+                Some(value) => ExprKind::Number(-value),
+                None => ExprKind::Negate(Box::new(inner)),
+            }
+        }
+        ExprKind::Binary { op, lhs, rhs } => {
+            let lhs = fold(lhs, binding)?;
+            let rhs = fold(rhs, binding)?;
+            match (lhs.as_number(), rhs.as_number()) {
+                (Some(lhs), Some(rhs)) => ExprKind::Number(apply_binary(*op, lhs, rhs)),
+                _ => ExprKind::Binary {
+                    op: *op,
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                },
+            }
+        }
+        ExprKind::Call { function, args } => {
+            let args = args
+                .iter()
+                .map(|arg| fold(arg, binding))
+                .collect::<Result<Vec<_>, _>>()?;
+            let values = args.iter().filter_map(Expr::as_number).collect::<Vec<_>>();
+            if values.len() == args.len() {
+                ExprKind::Number(apply_function(*function, &values))
+            } else {
+                ExprKind::Call {
+                    function: *function,
+                    args,
+                }
+            }
+        }
+    };
+
+    if let ExprKind::Number(value) = kind
+        && !value.is_finite()
+    {
+        return Err(non_finite_diagnostic(expr.span));
+    }
+    Ok(Expr::new(kind, expr.span))
+}
+
+/// Evaluates an expression, reading variables through `variable`.
+///
+/// The result may be non-finite; callers report that against the expression span.
+///
+/// # Errors
+/// Returns the first error produced by `variable`.
+pub(crate) fn evaluate<E>(
+    expr: &Expr,
+    variable: &mut dyn FnMut(&str, Span) -> Result<f64, E>,
+) -> Result<f64, E> {
+    Ok(match &expr.kind {
+        ExprKind::Number(value) => *value,
+        ExprKind::Variable(name) => variable(name, expr.span)?,
+        ExprKind::Negate(inner) => -evaluate(inner, variable)?,
+        ExprKind::Binary { op, lhs, rhs } => {
+            apply_binary(*op, evaluate(lhs, variable)?, evaluate(rhs, variable)?)
+        }
+        ExprKind::Call { function, args } => {
+            let values = args
+                .iter()
+                .map(|arg| evaluate(arg, variable))
+                .collect::<Result<Vec<_>, _>>()?;
+            apply_function(*function, &values)
+        }
+    })
+}
+
+/// Collects every variable reference left in an expression.
+pub(crate) fn variables(expr: &Expr, out: &mut Vec<(String, Span)>) {
+    match &expr.kind {
+        ExprKind::Number(_) => {}
+        ExprKind::Variable(name) => out.push((name.clone(), expr.span)),
+        ExprKind::Negate(inner) => variables(inner, out),
+        ExprKind::Binary { lhs, rhs, .. } => {
+            variables(lhs, out);
+            variables(rhs, out);
+        }
+        ExprKind::Call { args, .. } => {
+            for arg in args {
+                variables(arg, out);
+            }
+        }
+    }
+}
+
+/// Diagnostic for an expression that evaluated to infinity or NaN.
+pub(crate) fn non_finite_diagnostic(span: Span) -> Diagnostic {
+    Diagnostic::new(
+        span.line,
+        span.col_start,
+        span.col_end,
+        "expression does not evaluate to a finite number",
+    )
+    .with_help("check for division by zero or square roots of negative values")
+}
+
+fn apply_binary(op: BinaryOp, lhs: f64, rhs: f64) -> f64 {
+    match op {
+        BinaryOp::Add => lhs + rhs,
+        BinaryOp::Sub => lhs - rhs,
+        BinaryOp::Mul => lhs * rhs,
+        BinaryOp::Div => lhs / rhs,
+        BinaryOp::Pow => lhs.powf(rhs),
+    }
+}
+
+fn apply_function(function: ExprFunction, args: &[f64]) -> f64 {
+    match function {
+        ExprFunction::Sin => args[0].sin(),
+        ExprFunction::Cos => args[0].cos(),
+        ExprFunction::Tan => args[0].tan(),
+        ExprFunction::Sqrt => args[0].sqrt(),
+        ExprFunction::Abs => args[0].abs(),
+        ExprFunction::Floor => args[0].floor(),
+        ExprFunction::Min => args[0].min(args[1]),
+        ExprFunction::Max => args[0].max(args[1]),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ExprTokenKind {
+    Number(f64),
+    Ident(String),
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Caret,
+    LParen,
+    RParen,
+    Comma,
+    DotDot,
+    DotDotEq,
+}
+
+#[derive(Debug, Clone)]
+struct ExprToken {
+    kind: ExprTokenKind,
+    /// Zero-based char offset of the first char.
+    start: usize,
+    /// Zero-based char offset one past the last char.
+    end: usize,
+}
+
+struct ExprParser {
+    tokens: Vec<ExprToken>,
+    pos: usize,
+    line: usize,
+    col_start: usize,
+    text_len: usize,
+    depth: usize,
+}
+
+impl ExprParser {
+    fn new(text: &str, line: usize, col_start: usize) -> Result<Self, Diagnostic> {
+        let chars = text.chars().collect::<Vec<_>>();
+        let mut parser = Self {
+            tokens: Vec::new(),
+            pos: 0,
+            line,
+            col_start,
+            text_len: chars.len(),
+            depth: 0,
+        };
+        parser.tokens = parser.tokenize(&chars)?;
+        Ok(parser)
+    }
+
+    fn tokenize(&self, chars: &[char]) -> Result<Vec<ExprToken>, Diagnostic> {
+        let mut tokens = Vec::new();
+        let mut index = 0;
+        while index < chars.len() {
+            let ch = chars[index];
+            let start = index;
+            let kind = match ch {
+                ch if ch.is_whitespace() => {
+                    index += 1;
+                    continue;
+                }
+                '+' => ExprTokenKind::Plus,
+                '-' => ExprTokenKind::Minus,
+                '*' => ExprTokenKind::Star,
+                '/' => ExprTokenKind::Slash,
+                '^' => ExprTokenKind::Caret,
+                '(' => ExprTokenKind::LParen,
+                ')' => ExprTokenKind::RParen,
+                ',' => ExprTokenKind::Comma,
+                '.' if chars.get(index + 1) == Some(&'.') => {
+                    if chars.get(index + 2) == Some(&'=') {
+                        index += 3;
+                        tokens.push(ExprToken {
+                            kind: ExprTokenKind::DotDotEq,
+                            start,
+                            end: index,
+                        });
+                    } else {
+                        index += 2;
+                        tokens.push(ExprToken {
+                            kind: ExprTokenKind::DotDot,
+                            start,
+                            end: index,
+                        });
+                    }
+                    continue;
+                }
+                ch if ch.is_ascii_digit() || ch == '.' => {
+                    index = number_end(chars, index);
+                    let raw = chars[start..index].iter().collect::<String>();
+                    let value = raw.parse::<f64>().map_err(|_| {
+                        self.error_at(start, index, format!("invalid number `{raw}`"))
+                    })?;
+                    if !value.is_finite() {
+                        return Err(self.error_at(start, index, "number must be finite"));
+                    }
+                    tokens.push(ExprToken {
+                        kind: ExprTokenKind::Number(value),
+                        start,
+                        end: index,
+                    });
+                    continue;
+                }
+                ch if ch.is_ascii_alphabetic() || ch == '_' => {
+                    while index < chars.len()
+                        && (chars[index].is_ascii_alphanumeric() || chars[index] == '_')
+                    {
+                        index += 1;
+                    }
+                    tokens.push(ExprToken {
+                        kind: ExprTokenKind::Ident(chars[start..index].iter().collect()),
+                        start,
+                        end: index,
+                    });
+                    continue;
+                }
+                other => {
+                    return Err(self.error_at(
+                        start,
+                        start + 1,
+                        format!("unexpected character `{other}` in expression"),
+                    ));
+                }
+            };
+            index += 1;
+            tokens.push(ExprToken {
+                kind,
+                start,
+                end: index,
+            });
+        }
+        Ok(tokens)
+    }
+
+    fn expression(&mut self) -> Result<Expr, Diagnostic> {
+        self.depth += 1;
+        if self.depth > MAX_NESTING {
+            return Err(self.error_here("expression is nested too deeply"));
+        }
+        let result = self.additive();
+        self.depth -= 1;
+        result
+    }
+
+    fn additive(&mut self) -> Result<Expr, Diagnostic> {
+        let mut lhs = self.multiplicative()?;
+        loop {
+            let op = match self.peek_kind() {
+                Some(ExprTokenKind::Plus) => BinaryOp::Add,
+                Some(ExprTokenKind::Minus) => BinaryOp::Sub,
+                _ => return Ok(lhs),
+            };
+            self.pos += 1;
+            let rhs = self.multiplicative()?;
+            lhs = binary(op, lhs, rhs);
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr, Diagnostic> {
+        let mut lhs = self.unary()?;
+        loop {
+            let op = match self.peek_kind() {
+                Some(ExprTokenKind::Star) => BinaryOp::Mul,
+                Some(ExprTokenKind::Slash) => BinaryOp::Div,
+                _ => return Ok(lhs),
+            };
+            self.pos += 1;
+            let rhs = self.unary()?;
+            lhs = binary(op, lhs, rhs);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, Diagnostic> {
+        match self.peek_kind() {
+            Some(ExprTokenKind::Minus | ExprTokenKind::Plus) => {
+                let token = self.tokens[self.pos].clone();
+                self.pos += 1;
+                self.depth += 1;
+                if self.depth > MAX_NESTING {
+                    return Err(self.error_here("expression is nested too deeply"));
+                }
+                let inner = self.unary();
+                self.depth -= 1;
+                let inner = inner?;
+                if token.kind == ExprTokenKind::Plus {
+                    return Ok(inner);
+                }
+                let span = self.join(self.span(token.start, token.end), inner.span);
+                Ok(Expr::new(ExprKind::Negate(Box::new(inner)), span))
+            }
+            _ => self.power(),
+        }
+    }
+
+    fn power(&mut self) -> Result<Expr, Diagnostic> {
+        let base = self.primary()?;
+        if self.peek_kind() != Some(&ExprTokenKind::Caret) {
+            return Ok(base);
+        }
+        self.pos += 1;
+        let exponent = self.unary()?;
+        Ok(binary(BinaryOp::Pow, base, exponent))
+    }
+
+    fn primary(&mut self) -> Result<Expr, Diagnostic> {
+        let Some(token) = self.tokens.get(self.pos).cloned() else {
+            return Err(self.error_here("expected a number, name, or `(`"));
+        };
+        self.pos += 1;
+        let span = self.span(token.start, token.end);
+        match token.kind {
+            ExprTokenKind::Number(value) => Ok(Expr::new(ExprKind::Number(value), span)),
+            ExprTokenKind::Ident(name) => {
+                if self.peek_kind() == Some(&ExprTokenKind::LParen) {
+                    return self.call(&name, span);
+                }
+                if let Some((_, value)) = BUILTIN_CONSTANTS
+                    .iter()
+                    .find(|(constant, _)| *constant == name)
+                {
+                    return Ok(Expr::new(ExprKind::Number(*value), span));
+                }
+                if let Some(function) = ExprFunction::from_name(&name) {
+                    return Err(self
+                        .error_at(
+                            token.start,
+                            token.end,
+                            format!("function `{name}` must be called"),
+                        )
+                        .with_help(format!("{}(...)", function.name())));
+                }
+                Ok(Expr::new(ExprKind::Variable(name), span))
+            }
+            ExprTokenKind::LParen => {
+                let inner = self.expression()?;
+                let close = self.expect_close(token.start)?;
+                Ok(Expr::new(inner.kind, self.join(span, close)))
+            }
+            _ => Err(self.error_at(token.start, token.end, "expected a number, name, or `(`")),
+        }
+    }
+
+    fn call(&mut self, name: &str, name_span: Span) -> Result<Expr, Diagnostic> {
+        let open = self.tokens[self.pos].start;
+        self.pos += 1;
+        let Some(function) = ExprFunction::from_name(name) else {
+            return Err(Diagnostic::new(
+                name_span.line,
+                name_span.col_start,
+                name_span.col_end,
+                format!("unknown function `{name}`"),
+            )
+            .with_help(
+                "expected one of `sin`, `cos`, `tan`, `sqrt`, `abs`, `floor`, `min`, or `max`",
+            ));
+        };
+
+        let args = self.arguments()?;
+        let close = self.expect_close(open)?;
+        let span = self.join(name_span, close);
+        if args.len() != function.arity() {
+            return Err(Diagnostic::new(
+                span.line,
+                span.col_start,
+                span.col_end,
+                format!(
+                    "`{}` takes {} argument{}, got {}",
+                    function.name(),
+                    function.arity(),
+                    if function.arity() == 1 { "" } else { "s" },
+                    args.len()
+                ),
+            ));
+        }
+        Ok(Expr::new(ExprKind::Call { function, args }, span))
+    }
+
+    /// Parses comma-separated expressions up to, but not including, a closing `)`.
+    fn arguments(&mut self) -> Result<Vec<Expr>, Diagnostic> {
+        let mut args = Vec::new();
+        if self.peek_kind() != Some(&ExprTokenKind::RParen) {
+            loop {
+                args.push(self.expression()?);
+                if self.peek_kind() == Some(&ExprTokenKind::Comma) {
+                    self.pos += 1;
+                } else {
+                    break;
+                }
+            }
+        }
+        Ok(args)
+    }
+
+    /// Parses `name(` and returns the name, its span, and the `(` offset.
+    fn call_head(&mut self, role: &str) -> Result<(String, Span, usize), Diagnostic> {
+        let Some(ExprToken {
+            kind: ExprTokenKind::Ident(name),
+            start,
+            end,
+        }) = self.tokens.get(self.pos).cloned()
+        else {
+            return Err(self.error_here(format!("expected {role}")));
+        };
+        if is_reserved_name(&name) {
+            return Err(self.error_at(
+                start,
+                end,
+                format!("`{name}` is a built-in expression name"),
+            ));
+        }
+        self.pos += 1;
+        match self.tokens.get(self.pos) {
+            Some(ExprToken {
+                kind: ExprTokenKind::LParen,
+                start: open,
+                ..
+            }) => {
+                let open = *open;
+                self.pos += 1;
+                Ok((name, self.span(start, end), open))
+            }
+            _ => Err(self
+                .error_here(format!("expected `(` after `{name}`"))
+                .with_help(format!("{name}(...)"))),
+        }
+    }
+
+    fn expect_close(&mut self, open: usize) -> Result<Span, Diagnostic> {
+        match self.tokens.get(self.pos) {
+            Some(ExprToken {
+                kind: ExprTokenKind::RParen,
+                start,
+                end,
+            }) => {
+                let span = self.span(*start, *end);
+                self.pos += 1;
+                Ok(span)
+            }
+            _ => Err(self.error_at(open, open + 1, "unclosed `(` in expression")),
+        }
+    }
+
+    fn expect_end(&self) -> Result<(), Diagnostic> {
+        match self.tokens.get(self.pos) {
+            None => Ok(()),
+            Some(token) if token.kind == ExprTokenKind::RParen => {
+                Err(self.error_at(token.start, token.end, "unmatched `)` in expression"))
+            }
+            Some(token) => Err(self.error_at(
+                token.start,
+                token.end,
+                "expected an operator between expression terms",
+            )),
+        }
+    }
+
+    fn peek_kind(&self) -> Option<&ExprTokenKind> {
+        self.tokens.get(self.pos).map(|token| &token.kind)
+    }
+
+    fn span(&self, start: usize, end: usize) -> Span {
+        Span {
+            line: self.line,
+            col_start: self.col_start + start,
+            col_end: self.col_start + end.max(start + 1) - 1,
+        }
+    }
+
+    const fn join(&self, start: Span, end: Span) -> Span {
+        Span {
+            line: self.line,
+            col_start: start.col_start,
+            col_end: end.col_end,
+        }
+    }
+
+    fn error_at(&self, start: usize, end: usize, message: impl Into<String>) -> Diagnostic {
+        let span = self.span(start, end);
+        Diagnostic::new(span.line, span.col_start, span.col_end, message)
+    }
+
+    fn error_here(&self, message: impl Into<String>) -> Diagnostic {
+        match self.tokens.get(self.pos) {
+            Some(token) => self.error_at(token.start, token.end, message),
+            None => self.error_at(self.text_len, self.text_len + 1, message),
+        }
+    }
+}
+
+fn binary(op: BinaryOp, lhs: Expr, rhs: Expr) -> Expr {
+    let span = Span {
+        line: lhs.span.line,
+        col_start: lhs.span.col_start,
+        col_end: rhs.span.col_end,
+    };
+    Expr::new(
+        ExprKind::Binary {
+            op,
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        },
+        span,
+    )
+}
+
+/// Returns the end of a decimal number starting at `start`, stopping before a `..` range operator.
+fn number_end(chars: &[char], start: usize) -> usize {
+    let mut index = start;
+    let digits = |index: &mut usize| {
+        while *index < chars.len() && chars[*index].is_ascii_digit() {
+            *index += 1;
+        }
+    };
+    digits(&mut index);
+    if chars.get(index) == Some(&'.') && chars.get(index + 1) != Some(&'.') {
+        index += 1;
+        digits(&mut index);
+    }
+    if matches!(chars.get(index), Some('e' | 'E')) {
+        let mut exponent = index + 1;
+        if matches!(chars.get(exponent), Some('+' | '-')) {
+            exponent += 1;
+        }
+        if chars.get(exponent).is_some_and(char::is_ascii_digit) {
+            index = exponent;
+            digits(&mut index);
+        }
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{evaluate, fold, parse_expression, parse_range};
+    use crate::mdl::ast::{Expr, ExprKind};
+
+    fn eval(src: &str) -> f64 {
+        let expr = parse_expression(src, 1, 1).unwrap();
+        evaluate::<()>(&expr, &mut |_, _| Err(())).unwrap()
+    }
+
+    #[test]
+    fn respects_precedence_and_right_associative_powers() {
+        assert!((eval("1 + 2 * 3") - 7.0).abs() < 1e-12);
+        assert!((eval("(1 + 2) * 3") - 9.0).abs() < 1e-12);
+        assert!((eval("2 ^ 3 ^ 2") - 512.0).abs() < 1e-12);
+        assert!((eval("-2^2") + 4.0).abs() < 1e-12);
+        assert!((eval("2^-1") - 0.5).abs() < 1e-12);
+        assert!((eval("10 - 4 - 3") - 3.0).abs() < 1e-12);
+        assert!((eval("cos(pi) + sqrt(16) + max(1, 2.5e0)") - 5.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn reports_span_accurate_syntax_errors() {
+        let error = parse_expression("1 + foo(2)", 4, 10).unwrap_err();
+        assert_eq!((error.line, error.col_start, error.col_end), (4, 14, 16));
+        assert!(error.message.contains("unknown function `foo`"));
+
+        let error = parse_expression("min(1)", 1, 1).unwrap_err();
+        assert_eq!((error.col_start, error.col_end), (1, 6));
+        assert!(error.message.contains("takes 2 arguments, got 1"));
+
+        let error = parse_expression("(1 + 2", 1, 1).unwrap_err();
+        assert!(error.message.contains("unclosed"));
+
+        let error = parse_expression("3 $ 4", 1, 1).unwrap_err();
+        assert_eq!(error.col_start, 3);
+    }
+
+    #[test]
+    fn folds_bound_variables_and_keeps_knob_references() {
+        let expr = parse_expression("i * 10 + k", 1, 1).unwrap();
+        let folded = fold(&expr, &|name| {
+            (name == "i").then(|| Expr::new(ExprKind::Number(3.0), expr.span))
+        })
+        .unwrap();
+
+        let ExprKind::Binary { lhs, rhs, .. } = folded.kind else {
+            panic!("expected binary expression");
+        };
+        assert_eq!(lhs.as_number(), Some(30.0));
+        assert_eq!(rhs.kind, ExprKind::Variable("k".to_string()));
+
+        let error = fold(&parse_expression("1 / (2 - 2)", 1, 1).unwrap(), &|_| None).unwrap_err();
+        assert!(error.message.contains("finite"));
+    }
+
+    #[test]
+    fn parses_exclusive_and_inclusive_ranges() {
+        let (start, end, inclusive) = parse_range("0..n", 1, 1).unwrap();
+        assert_eq!(start.as_number(), Some(0.0));
+        assert_eq!(end.kind, ExprKind::Variable("n".to_string()));
+        assert!(!inclusive);
+
+        let (_, end, inclusive) = parse_range("1 ..= 2*3", 1, 1).unwrap();
+        assert!(inclusive);
+        assert_eq!((end.span.col_start, end.span.col_end), (7, 9));
+
+        assert!(parse_range("0 5", 1, 1).is_err());
+    }
+}
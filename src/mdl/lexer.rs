@@ -3,7 +3,7 @@
 use super::diagnostic::Diagnostic;
 
 /// A token source span.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Span {
     /// One-based source line.
     pub line: usize,
@@ -60,6 +60,11 @@ pub fn lex_line(line_no: usize, line: &str) -> Result<Vec<Token>, Diagnostic> {
             }
             index += ch.len_utf8();
         }
+        if line[start..index].contains('(')
+            && let Some(end) = grouped_token_end(line, start)
+        {
+            index = end;
+        }
 
         let raw = &line[start..index];
         if raw.starts_with("//") {
@@ -102,6 +107,23 @@ pub fn lex_line(line_no: usize, line: &str) -> Result<Vec<Token>, Diagnostic> {
     Ok(tokens)
 }
 
+/// Finds the end of a token whose parentheses may enclose whitespace, as in `(i * 10)`.
+///
+/// Returns `None` when the parentheses never balance so the caller keeps the plain
+/// whitespace-delimited token.
+fn grouped_token_end(line: &str, start: usize) -> Option<usize> {
+    let mut depth = 0_usize;
+    for (offset, ch) in line[start..].char_indices() {
+        match ch {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ch if ch.is_whitespace() && depth == 0 => return Some(start + offset),
+            _ => {}
+        }
+    }
+    (depth == 0).then_some(line.len())
+}
+
 fn char_col(line: &str, byte_index: usize) -> usize {
     line[..byte_index].chars().count() + 1
 }
@@ -145,6 +167,18 @@ mod tests {
         assert_eq!(tokens[1].kind, TokenKind::Word("out//foo.png".to_string()));
     }
 
+    #[test]
+    fn parenthesized_groups_keep_inner_whitespace() {
+        let tokens = lex_line(1, "move (i * 10) 0 sin( t ) save(").unwrap();
+
+        assert_eq!(tokens.len(), 5);
+        assert_eq!(tokens[1].kind, TokenKind::Word("(i * 10)".to_string()));
+        assert_eq!(tokens[1].span.col_start, 6);
+        assert_eq!(tokens[1].span.col_end, 13);
+        assert_eq!(tokens[3].kind, TokenKind::Word("sin( t )".to_string()));
+        assert_eq!(tokens[4].kind, TokenKind::Word("save(".to_string()));
+    }
+
     #[test]
     fn spans_count_unicode_chars_not_bytes() {
         let tokens = lex_line(1, "é move").unwrap();
@@ -210,9 +210,7 @@ impl Loader {
         })?;
 
         let mut program = parse_script(&source).map_err(|errors| tag_diagnostics(errors, path))?;
-        for command in &mut program.commands {
-            command.source_name = Some(path.to_path_buf());
-        }
+        set_source_name(&mut program.commands, path);
 
         self.active_files.push(path.to_path_buf());
         let source_dir = path.parent();
@@ -324,6 +322,17 @@ impl Loader {
     }
 }
 
+fn set_source_name(commands: &mut [Spanned<Command>], path: &Path) {
+    for command in commands {
+        command.source_name = Some(path.to_path_buf());
+        match &mut command.node {
+            Command::For(for_command) => set_source_name(&mut for_command.body, path),
+            Command::Define(define) => set_source_name(&mut define.body, path),
+            _ => {}
+        }
+    }
+}
+
 fn diagnostic_at_include(
     span: Span,
     source_name: Option<&Path>,
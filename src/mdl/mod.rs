@@ -8,8 +8,10 @@
 
 pub mod animation;
 pub mod ast;
+pub mod cli;
 pub mod diagnostic;
 pub mod executor;
+pub mod expr;
 pub mod lexer;
 pub mod loader;
 pub mod parser;
@@ -17,7 +19,7 @@ pub mod runtime;
 pub mod semantic;
 
 pub use ast::{Command, Program};
-pub use diagnostic::Diagnostic;
+pub use diagnostic::{Diagnostic, DiagnosticNote};
 pub use loader::{
     MdlError, compile_file, compile_source, parse_file, parse_source, run_file, run_file_streaming,
     run_source, run_source_streaming,
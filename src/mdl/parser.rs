@@ -2,28 +2,105 @@
 
 use super::{
     ast::{
-        AnimationCommand, Axis, CameraCommand, ColorSpec, Command, ControlCommand, CurveCommand,
-        FilterCommand, Material, OutputCommand, PointRef, Program, RenderCommand, ShadingMode,
-        ShapeCommand, Spanned, TransformCommand, VaryInterpolation, Vec2, Vec3,
+        AnimationCommand, AreaEmitter, Axis, CallCommand, CameraCommand, ColorSpec, Command,
+        ControlCommand, CurveCommand, DeferredCommand, DefineCommand, Expr, FilterCommand,
+        ForCommand, LetCommand, LightSource, Material, MediumSpec, OutputCommand, PointRef,
+        Program, RayMaterialSpec, RenderCommand, ShadingMode, ShapeCommand, Spanned,
+        TransformCommand, VaryInterpolation, Vec2, Vec3,
     },
     diagnostic::Diagnostic,
+    expr::{fold, is_reserved_name, parse_call, parse_expression, parse_range, parse_signature},
     lexer::{Span, Token, TokenKind, lex_line},
 };
 
 const MAX_BEZIERN_DEGREE: usize = 1_000;
 
+/// Message used by numeric argument checks; marks positions that may hold an expression.
+const EXPECTED_NUMBER: &str = "expected number";
+
+/// Stand-in value for expression arguments whose value is not known yet.
+const PLACEHOLDER_VALUE: f64 = 1.0;
+
 type NamedGeometryArgs<const N: usize> = (Option<String>, [f64; N], Option<String>);
 
+/// Animation-plan commands that apply to the whole script and cannot appear in procedures.
+const PLAN_ONLY_COMMANDS: [&str; 4] = ["frames", "basename", "vary", "tween"];
+
+/// Header of a block whose closing `}` has not been reached yet.
+enum BlockHeader {
+    For {
+        variable: String,
+        start: Expr,
+        end: Expr,
+        inclusive: bool,
+    },
+    Define {
+        name: String,
+        params: Vec<String>,
+    },
+    /// A header with errors; its body is parsed and then dropped.
+    Invalid {
+        define: bool,
+    },
+}
+
+impl BlockHeader {
+    const fn is_define(&self) -> bool {
+        matches!(self, Self::Define { .. } | Self::Invalid { define: true })
+    }
+}
+
+/// A `for` or `define` block whose closing `}` has not been reached yet.
+struct OpenBlock {
+    header: BlockHeader,
+    span: Span,
+    body: Vec<Spanned<Command>>,
+}
+
+impl OpenBlock {
+    /// Builds the block command, or `None` when its header had errors.
+    fn close(self) -> Option<Command> {
+        match self.header {
+            BlockHeader::For {
+                variable,
+                start,
+                end,
+                inclusive,
+            } => Some(Command::For(ForCommand {
+                variable,
+                start,
+                end,
+                inclusive,
+                body: self.body,
+            })),
+            BlockHeader::Define { name, params } => Some(Command::Define(DefineCommand {
+                name,
+                params,
+                body: self.body,
+            })),
+            BlockHeader::Invalid { .. } => None,
+        }
+    }
+}
+
 /// Parses an MDL source string into a typed command list.
 ///
 /// This parser only reads the text it is given. It does not expand `include`
 /// commands or resolve paths; use `parse_file`, `compile_file`, or `run_file`
 /// for complete MDL file execution.
 ///
+/// Numeric arguments may be arithmetic expressions, written without spaces
+/// (`i*10`) or inside parentheses (`(i * 10)`). Lines whose expressions use
+/// variables are kept as [`Command::Deferred`] until
+/// [`crate::mdl::semantic::compile`] expands `let` bindings, `for` blocks, and
+/// `define`/`call` procedures.
+///
 /// # Errors
 /// Returns all line-level diagnostics found during lexing or parsing.
+#[allow(clippy::too_many_lines)]
 pub fn parse_script(src: &str) -> Result<Program, Vec<Diagnostic>> {
     let mut commands = Vec::new();
+    let mut blocks: Vec<OpenBlock> = Vec::new();
     let mut errors = Vec::new();
 
     for (idx, line) in src.lines().enumerate() {
@@ -40,12 +117,90 @@ pub fn parse_script(src: &str) -> Result<Program, Vec<Diagnostic>> {
             continue;
         }
 
-        match parse_command(&tokens) {
-            Ok(command) => commands.push(Spanned::new(command, tokens[0].span)),
+        let in_define = blocks.iter().any(|block| block.header.is_define());
+        let parsed = match &tokens[0].kind {
+            TokenKind::Word(word) if word == "for" || word == "define" => {
+                let define = word == "define";
+                let header = if !define {
+                    parse_for_header(line, &tokens)
+                } else if blocks.is_empty() {
+                    parse_define_header(line, &tokens)
+                } else {
+                    Err(diag_at_token(
+                        &tokens[0],
+                        "`define` is only allowed at the top level",
+                    ))
+                };
+                let header = header.unwrap_or_else(|error| {
+                    errors.push(error);
+                    BlockHeader::Invalid { define }
+                });
+                blocks.push(OpenBlock {
+                    header,
+                    span: tokens[0].span,
+                    body: Vec::new(),
+                });
+                continue;
+            }
+            TokenKind::Word(word) if word == "}" => {
+                if let Some(extra) = tokens.get(1) {
+                    errors.push(diag_at_token(extra, "unexpected token after `}`"));
+                }
+                let Some(block) = blocks.pop() else {
+                    errors.push(diag_at_token(
+                        &tokens[0],
+                        "unexpected `}` without `for` or `define`",
+                    ));
+                    continue;
+                };
+                let span = block.span;
+                let Some(command) = block.close() else {
+                    continue;
+                };
+                Ok(Spanned::new(command, span))
+            }
+            TokenKind::Word(word) if word == "let" => {
+                parse_let(line, &tokens).map(|command| Spanned::new(command, tokens[0].span))
+            }
+            TokenKind::Word(word) if word == "call" => parse_call_command(line, &tokens)
+                .map(|command| Spanned::new(command, tokens[0].span)),
+            TokenKind::Word(word) if word == "include" && !blocks.is_empty() => Err(diag_at_token(
+                &tokens[0],
+                "`include` is not allowed inside a `for` or `define` block",
+            )),
+            TokenKind::Word(word) if in_define && PLAN_ONLY_COMMANDS.contains(&word.as_str()) => {
+                Err(diag_at_token(
+                    &tokens[0],
+                    format!("`{word}` is not allowed inside `define`"),
+                )
+                .with_help(
+                    "animation commands apply to the whole script; move it outside the procedure",
+                ))
+            }
+            _ => parse_command_line(&tokens).map(|command| Spanned::new(command, tokens[0].span)),
+        };
+
+        match parsed {
+            Ok(command) => match blocks.last_mut() {
+                Some(block) => block.body.push(command),
+                None => commands.push(command),
+            },
             Err(error) => errors.push(error),
         }
     }
 
+    for block in blocks {
+        let kind = if block.header.is_define() {
+            "define"
+        } else {
+            "for"
+        };
+        errors.push(
+            diag_at_span(block.span, format!("unclosed `{kind}` block"))
+                .with_help("close the block with a line containing only `}`"),
+        );
+    }
+
     if errors.is_empty() {
         Ok(Program { commands })
     } else {
@@ -53,6 +208,216 @@ pub fn parse_script(src: &str) -> Result<Program, Vec<Diagnostic>> {
     }
 }
 
+/// Parses one command line, deferring it when numeric arguments hold non-constant expressions.
+fn parse_command_line(tokens: &[Token]) -> Result<Command, Diagnostic> {
+    let mut expressions = Vec::new();
+    let command = resolve_expression_command(tokens, &mut expressions, &mut |expr: &Expr| {
+        fold(expr, &|_| None)
+    })?;
+    Ok(command.unwrap_or_else(|| {
+        Command::Deferred(DeferredCommand {
+            tokens: tokens.to_vec(),
+            expressions,
+        })
+    }))
+}
+
+/// Parses command tokens whose numeric positions may hold expressions.
+///
+/// Each expression in `expressions`, plus any found in a numeric position while parsing, is
+/// passed through `resolve` and stored back. Resolved numbers replace their tokens; other
+/// expressions are parsed with a placeholder value. Returns `Ok(None)` when some expression is
+/// still unresolved, so the command can only be checked and not yet built.
+///
+/// # Errors
+/// Returns parse diagnostics, expression syntax diagnostics, or errors from `resolve`.
+pub(crate) fn resolve_expression_command<E: From<Diagnostic>>(
+    tokens: &[Token],
+    expressions: &mut Vec<(usize, Expr)>,
+    resolve: &mut dyn FnMut(&Expr) -> Result<Expr, E>,
+) -> Result<Option<Command>, E> {
+    let mut substituted = tokens.to_vec();
+    let mut pending = Vec::new();
+    for (index, expr) in expressions.iter_mut() {
+        *expr = resolve(expr)?;
+        substitute_expression(&mut substituted[*index], expr, &mut pending);
+    }
+
+    loop {
+        let error = match parse_command(&substituted) {
+            Ok(command) => return Ok(pending.is_empty().then_some(command)),
+            Err(error) => error,
+        };
+        if pending.iter().any(|span| same_span(*span, &error)) {
+            return Ok(None);
+        }
+
+        let expression_index = (error.message == EXPECTED_NUMBER)
+            .then(|| {
+                substituted.iter().skip(1).position(|token| {
+                    same_span(token.span, &error) && matches!(token.kind, TokenKind::Word(_))
+                })
+            })
+            .flatten()
+            .map(|offset| offset + 1);
+        let Some(index) = expression_index else {
+            return Err(error.into());
+        };
+        let TokenKind::Word(text) = &substituted[index].kind else {
+            unreachable!("expression position holds a word token");
+        };
+        let span = substituted[index].span;
+        let expr = resolve(&parse_expression(text, span.line, span.col_start)?)?;
+        substitute_expression(&mut substituted[index], &expr, &mut pending);
+        expressions.push((index, expr));
+    }
+}
+
+fn substitute_expression(token: &mut Token, expr: &Expr, pending: &mut Vec<Span>) {
+    token.kind = TokenKind::Number(expr.as_number().unwrap_or_else(|| {
+        pending.push(token.span);
+        PLACEHOLDER_VALUE
+    }));
+}
+
+const fn same_span(span: Span, error: &Diagnostic) -> bool {
+    span.line == error.line && span.col_start == error.col_start && span.col_end == error.col_end
+}
+
+fn parse_let(line: &str, tokens: &[Token]) -> Result<Command, Diagnostic> {
+    const SYNTAX: &str = "let name = expression";
+    let command = &tokens[0];
+    let args = &tokens[1..];
+    let name = expect_variable_name(command, args, 0, "variable name")?;
+    if !matches!(args.get(1).map(|token| &token.kind), Some(TokenKind::Word(eq)) if eq == "=") {
+        return Err(diag_at_token(
+            args.get(1).unwrap_or(command),
+            "expected `=` after `let` name",
+        )
+        .with_help(SYNTAX));
+    }
+    let Some(first) = args.get(2) else {
+        return Err(diag_at_token(&args[1], "expected expression after `=`").with_help(SYNTAX));
+    };
+    let last = &tokens[tokens.len() - 1];
+    let value = parse_expression(
+        source_text(line, first.span, last.span),
+        first.span.line,
+        first.span.col_start,
+    )?;
+    Ok(Command::Let(LetCommand {
+        name,
+        value: fold(&value, &|_| None)?,
+    }))
+}
+
+fn parse_for_header(line: &str, tokens: &[Token]) -> Result<BlockHeader, Diagnostic> {
+    const SYNTAX: &str = "for name in start..end {";
+    let command = &tokens[0];
+    let args = &tokens[1..];
+    let variable = expect_variable_name(command, args, 0, "loop variable name")?;
+    if !matches!(args.get(1).map(|token| &token.kind), Some(TokenKind::Word(word)) if word == "in")
+    {
+        return Err(diag_at_token(
+            args.get(1).unwrap_or(command),
+            "expected `in` after loop variable",
+        )
+        .with_help(SYNTAX));
+    }
+    let last = &tokens[tokens.len() - 1];
+    if !matches!(&last.kind, TokenKind::Word(word) if word == "{") {
+        return Err(
+            diag_at_token(last, "expected `{` at the end of a `for` line").with_help(SYNTAX),
+        );
+    }
+    if args.len() < 4 {
+        return Err(diag_at_token(last, "expected a range before `{`").with_help(SYNTAX));
+    }
+    let first = &args[2];
+    let range_end = &tokens[tokens.len() - 2];
+    let (start, end, inclusive) = parse_range(
+        source_text(line, first.span, range_end.span),
+        first.span.line,
+        first.span.col_start,
+    )?;
+    Ok(BlockHeader::For {
+        variable,
+        start,
+        end,
+        inclusive,
+    })
+}
+
+fn parse_define_header(line: &str, tokens: &[Token]) -> Result<BlockHeader, Diagnostic> {
+    const SYNTAX: &str = "define name(param, ...) {";
+    let last = &tokens[tokens.len() - 1];
+    if !matches!(&last.kind, TokenKind::Word(word) if word == "{") {
+        return Err(
+            diag_at_token(last, "expected `{` at the end of a `define` line").with_help(SYNTAX),
+        );
+    }
+    if tokens.len() < 3 {
+        return Err(
+            diag_at_token(last, "expected a procedure signature before `{`").with_help(SYNTAX),
+        );
+    }
+    let first = &tokens[1];
+    let (name, params) = parse_signature(
+        source_text(line, first.span, tokens[tokens.len() - 2].span),
+        first.span.line,
+        first.span.col_start,
+    )?;
+    Ok(BlockHeader::Define { name, params })
+}
+
+fn parse_call_command(line: &str, tokens: &[Token]) -> Result<Command, Diagnostic> {
+    let Some(first) = tokens.get(1) else {
+        return Err(
+            diag_at_token(&tokens[0], "expected procedure call").with_help("call name(arg, ...)")
+        );
+    };
+    let (name, name_span, args) = parse_call(
+        source_text(line, first.span, tokens[tokens.len() - 1].span),
+        first.span.line,
+        first.span.col_start,
+    )?;
+    let args = args
+        .iter()
+        .map(|arg| fold(arg, &|_| None))
+        .collect::<Result<_, _>>()?;
+    Ok(Command::Call(CallCommand {
+        name,
+        name_span,
+        args,
+    }))
+}
+
+fn expect_variable_name(
+    command: &Token,
+    args: &[Token],
+    index: usize,
+    role: &str,
+) -> Result<String, Diagnostic> {
+    let name = expect_ident(command, args, index, role)?;
+    if is_reserved_name(&name) {
+        return Err(diag_at_token(
+            &args[index],
+            format!("`{name}` is a built-in expression name"),
+        ));
+    }
+    Ok(name)
+}
+
+/// Returns the source text from the start of `first` through the end of `last`.
+fn source_text(line: &str, first: Span, last: Span) -> &str {
+    let byte_at = |col: usize| {
+        line.char_indices()
+            .nth(col)
+            .map_or(line.len(), |(index, _)| index)
+    };
+    &line[byte_at(first.col_start - 1)..byte_at(last.col_end)]
+}
+
 fn control(command: ControlCommand) -> Command {
     Command::Control(command)
 }
@@ -85,7 +450,7 @@ fn output(command: OutputCommand) -> Command {
     Command::Output(command)
 }
 
-fn parse_command(tokens: &[Token]) -> Result<Command, Diagnostic> {
+pub(crate) fn parse_command(tokens: &[Token]) -> Result<Command, Diagnostic> {
     let command_token = &tokens[0];
     let command = expect_command_name(command_token)?;
     let args = &tokens[1..];
@@ -127,8 +492,13 @@ fn parse_command(tokens: &[Token]) -> Result<Command, Diagnostic> {
         "vary" => parse_vary(command_token, args),
         "setknobs" => parse_setknobs(command_token, args),
         "light" => parse_light(command_token, args),
+        "spotlight" => parse_spotlight(command_token, args),
+        "dirlight" => parse_dirlight(command_token, args),
+        "arealight" => parse_arealight(command_token, args),
         "ambient" => parse_ambient(command_token, args),
         "constants" => parse_constants(command_token, args),
+        "material" => parse_material(command_token, args),
+        "medium" => parse_medium(command_token, args),
         "shading" => parse_shading(command_token, args),
         "save_coord_system" | "save_coordinate_system" => {
             parse_save_coord_system(command_token, args)
@@ -700,6 +1070,148 @@ fn parse_light(command: &Token, args: &[Token]) -> Result<Command, Diagnostic> {
     }))
 }
 
+fn parse_spotlight(command: &Token, args: &[Token]) -> Result<Command, Diagnostic> {
+    expect_len(
+        command,
+        args,
+        &[11, 12],
+        "spotlight r g b x y z dx dy dz inner outer [falloff]",
+    )?;
+    let color = parse_rgb(command, args, 0)?;
+    let position = parse_vec3(command, args, 3)?;
+    let direction = parse_direction(command, args, 6)?;
+    let inner_angle = expect_number_where(
+        command,
+        args,
+        9,
+        |value| (0.0..=90.0).contains(&value),
+        "inner cone angle from 0 to 90 degrees",
+    )?;
+    let outer_angle = expect_number_where(
+        command,
+        args,
+        10,
+        |value| value > 0.0 && value >= inner_angle && value <= 90.0,
+        "outer cone angle at least the inner angle and at most 90 degrees",
+    )?;
+    let falloff = if args.len() == 12 {
+        expect_number_where(command, args, 11, |value| value > 0.0, "positive falloff")?
+    } else {
+        1.0
+    };
+    Ok(render(RenderCommand::LightSource(LightSource::Spot {
+        color,
+        position,
+        direction,
+        inner_angle,
+        outer_angle,
+        falloff,
+    })))
+}
+
+fn parse_dirlight(command: &Token, args: &[Token]) -> Result<Command, Diagnostic> {
+    expect_len(command, args, &[6], "dirlight r g b dx dy dz")?;
+    Ok(render(RenderCommand::LightSource(
+        LightSource::Directional {
+            color: parse_rgb(command, args, 0)?,
+            direction: parse_direction(command, args, 3)?,
+        },
+    )))
+}
+
+fn parse_arealight(command: &Token, args: &[Token]) -> Result<Command, Diagnostic> {
+    const SYNTAX: &str = "arealight quad r g b cx cy cz ux uy uz vx vy vz [strength] | arealight sphere r g b x y z radius [strength]";
+    let shape_token = args.first().unwrap_or(command);
+    let shape =
+        expect_ident_ref(command, args, 0, "area light shape").map_err(|e| e.with_help(SYNTAX))?;
+    let (emitter, strength_index) = match shape {
+        "quad" => {
+            expect_len(
+                command,
+                args,
+                &[13, 14],
+                "arealight quad r g b cx cy cz ux uy uz vx vy vz [strength]",
+            )?;
+            let u = parse_vec3(command, args, 7)?;
+            let v = parse_vec3(command, args, 10)?;
+            let normal = Vec3::new(
+                u.y * v.z - u.z * v.y,
+                u.z * v.x - u.x * v.z,
+                u.x * v.y - u.y * v.x,
+            );
+            if normal.x == 0.0 && normal.y == 0.0 && normal.z == 0.0 {
+                return Err(diag_at_token(
+                    &args[7],
+                    "area light edges must not be parallel or zero",
+                ));
+            }
+            (
+                AreaEmitter::Quad {
+                    corner: parse_vec3(command, args, 4)?,
+                    u,
+                    v,
+                },
+                13,
+            )
+        }
+        "sphere" => {
+            expect_len(
+                command,
+                args,
+                &[8, 9],
+                "arealight sphere r g b x y z radius [strength]",
+            )?;
+            (
+                AreaEmitter::Sphere {
+                    center: parse_vec3(command, args, 4)?,
+                    radius: expect_number_where(
+                        command,
+                        args,
+                        7,
+                        |value| value > 0.0,
+                        "positive radius",
+                    )?,
+                },
+                8,
+            )
+        }
+        other => {
+            return Err(
+                diag_at_token(shape_token, format!("unknown area light shape `{other}`"))
+                    .with_help("expected `quad` or `sphere`"),
+            );
+        }
+    };
+    let strength = if args.len() > strength_index {
+        expect_number_where(
+            command,
+            args,
+            strength_index,
+            |value| value >= 0.0,
+            "non-negative emission strength",
+        )?
+    } else {
+        1.0
+    };
+    Ok(render(RenderCommand::LightSource(LightSource::Area {
+        color: parse_rgb(command, args, 1)?,
+        emitter,
+        strength,
+    })))
+}
+
+/// Parses a non-zero `dx dy dz` direction.
+fn parse_direction(command: &Token, args: &[Token], start: usize) -> Result<Vec3, Diagnostic> {
+    let direction = parse_vec3(command, args, start)?;
+    if direction.x == 0.0 && direction.y == 0.0 && direction.z == 0.0 {
+        return Err(diag_at_token(
+            args.get(start).unwrap_or(command),
+            "light direction must be non-zero",
+        ));
+    }
+    Ok(direction)
+}
+
 fn parse_ambient(command: &Token, args: &[Token]) -> Result<Command, Diagnostic> {
     expect_len(command, args, &[3], "ambient r g b")?;
     Ok(render(RenderCommand::Ambient {
@@ -731,6 +1243,195 @@ fn parse_constants(command: &Token, args: &[Token]) -> Result<Command, Diagnosti
     }))
 }
 
+fn parse_material(command: &Token, args: &[Token]) -> Result<Command, Diagnostic> {
+    const SYNTAX: &str = "material name diffuse color r g b | material name metal color r g b [fuzz f] | material name ggx color r g b roughness a | material name glass [ior n] | material name emissive r g b strength";
+    let name = expect_ident(command, args, 0, "material name").map_err(|e| e.with_help(SYNTAX))?;
+    let model_token = args.get(1).unwrap_or(command);
+    let model = expect_ident_ref(command, args, 1, "material model")?;
+    let material = match model {
+        "emissive" => {
+            expect_len(command, args, &[6], "material name emissive r g b strength")?;
+            RayMaterialSpec::Emissive {
+                color: parse_linear_color(command, args, 2)?,
+                strength: expect_number_where(
+                    command,
+                    args,
+                    5,
+                    |value| value >= 0.0,
+                    "non-negative emission strength",
+                )?,
+            }
+        }
+        "diffuse" => {
+            let options = parse_keyword_options(command, args, 2, model, &[("color", 3)])?;
+            RayMaterialSpec::Diffuse {
+                color: parse_linear_color(
+                    command,
+                    args,
+                    required_option(model_token, &options, model, "color")?,
+                )?,
+            }
+        }
+        "metal" => {
+            let options =
+                parse_keyword_options(command, args, 2, model, &[("color", 3), ("fuzz", 1)])?;
+            RayMaterialSpec::Metal {
+                color: parse_linear_color(
+                    command,
+                    args,
+                    required_option(model_token, &options, model, "color")?,
+                )?,
+                fuzz: match option_index(&options, "fuzz") {
+                    Some(index) => expect_unit_interval(command, args, index, "fuzz")?,
+                    None => 0.0,
+                },
+            }
+        }
+        "ggx" => {
+            let options =
+                parse_keyword_options(command, args, 2, model, &[("color", 3), ("roughness", 1)])?;
+            RayMaterialSpec::Ggx {
+                color: parse_linear_color(
+                    command,
+                    args,
+                    required_option(model_token, &options, model, "color")?,
+                )?,
+                roughness: expect_unit_interval(
+                    command,
+                    args,
+                    required_option(model_token, &options, model, "roughness")?,
+                    "roughness",
+                )?,
+            }
+        }
+        "glass" => {
+            let options = parse_keyword_options(command, args, 2, model, &[("ior", 1)])?;
+            RayMaterialSpec::Glass {
+                ior: match option_index(&options, "ior") {
+                    Some(index) => expect_number_where(
+                        command,
+                        args,
+                        index,
+                        |value| value > 0.0,
+                        "positive index of refraction",
+                    )?,
+                    None => 1.5,
+                },
+            }
+        }
+        other => {
+            return Err(
+                diag_at_token(model_token, format!("unknown material model `{other}`"))
+                    .with_help("expected one of `diffuse`, `metal`, `ggx`, `glass`, or `emissive`"),
+            );
+        }
+    };
+    Ok(render(RenderCommand::RayMaterial { name, material }))
+}
+
+fn parse_medium(command: &Token, args: &[Token]) -> Result<Command, Diagnostic> {
+    const SYNTAX: &str = "medium name density d albedo r g b [anisotropy g]";
+    let name = expect_ident(command, args, 0, "medium name").map_err(|e| e.with_help(SYNTAX))?;
+    let options = parse_keyword_options(
+        command,
+        args,
+        1,
+        "medium",
+        &[("density", 1), ("albedo", 3), ("anisotropy", 1)],
+    )?;
+    let name_token = &args[0];
+    let density = expect_number_where(
+        command,
+        args,
+        required_option(name_token, &options, "medium", "density")?,
+        |value| value > 0.0,
+        "positive density",
+    )?;
+    let albedo = parse_linear_color(
+        command,
+        args,
+        required_option(name_token, &options, "medium", "albedo")?,
+    )?;
+    let anisotropy = match option_index(&options, "anisotropy") {
+        Some(index) => expect_number_where(
+            command,
+            args,
+            index,
+            |value| value > -1.0 && value < 1.0,
+            "anisotropy between -1 and 1 (exclusive)",
+        )?,
+        None => 0.0,
+    };
+    Ok(render(RenderCommand::Medium {
+        name,
+        medium: MediumSpec {
+            density,
+            albedo,
+            anisotropy,
+        },
+    }))
+}
+
+/// Parses `keyword value...` pairs from `args[start..]`, returning each keyword with the index of
+/// its first value.
+fn parse_keyword_options<'k>(
+    command: &Token,
+    args: &[Token],
+    start: usize,
+    owner: &str,
+    keywords: &[(&'k str, usize)],
+) -> Result<Vec<(&'k str, usize)>, Diagnostic> {
+    let mut options: Vec<(&str, usize)> = Vec::new();
+    let mut index = start;
+    while index < args.len() {
+        let keyword = expect_word_ref(command, args, index, "option name")?;
+        let Some(&(keyword, arity)) = keywords.iter().find(|(name, _)| *name == keyword) else {
+            let expected = keywords
+                .iter()
+                .map(|(name, _)| format!("`{name}`"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Err(diag_at_token(
+                &args[index],
+                format!("unknown `{owner}` option `{keyword}`"),
+            )
+            .with_help(format!("expected one of {expected}")));
+        };
+        if option_index(&options, keyword).is_some() {
+            return Err(diag_at_token(
+                &args[index],
+                format!("duplicate `{keyword}` option"),
+            ));
+        }
+        if index + arity >= args.len() {
+            return Err(diag_at_token(
+                &args[index],
+                format!("`{keyword}` expects {arity} value(s)"),
+            ));
+        }
+        options.push((keyword, index + 1));
+        index += arity + 1;
+    }
+    Ok(options)
+}
+
+fn option_index(options: &[(&str, usize)], keyword: &str) -> Option<usize> {
+    options
+        .iter()
+        .find(|(name, _)| *name == keyword)
+        .map(|(_, index)| *index)
+}
+
+fn required_option(
+    token: &Token,
+    options: &[(&str, usize)],
+    owner: &str,
+    keyword: &str,
+) -> Result<usize, Diagnostic> {
+    option_index(options, keyword)
+        .ok_or_else(|| diag_at_token(token, format!("`{owner}` requires `{keyword}`")))
+}
+
 fn parse_shading(command: &Token, args: &[Token]) -> Result<Command, Diagnostic> {
     expect_len(
         command,
@@ -901,6 +1602,24 @@ fn parse_rgb(command: &Token, args: &[Token], start: usize) -> Result<Vec3, Diag
     ))
 }
 
+/// Parses a linear `r g b` triple with non-negative channels, as used by physically based materials.
+fn parse_linear_color(command: &Token, args: &[Token], start: usize) -> Result<Vec3, Diagnostic> {
+    let channel = |index| {
+        expect_number_where(
+            command,
+            args,
+            index,
+            |value| value >= 0.0,
+            "non-negative linear color channel",
+        )
+    };
+    Ok(Vec3::new(
+        channel(start)?,
+        channel(start + 1)?,
+        channel(start + 2)?,
+    ))
+}
+
 fn parse_vec2(command: &Token, args: &[Token], start: usize) -> Result<Vec2, Diagnostic> {
     Ok(Vec2::new(
         expect_number(command, args, start)?,
@@ -965,7 +1684,7 @@ fn expect_number(command: &Token, args: &[Token], index: usize) -> Result<f64, D
     let token = args.get(index).unwrap_or(command);
     match token.kind {
         TokenKind::Number(value) => Ok(value),
-        TokenKind::Word(_) | TokenKind::Filename(_) => Err(diag_at_token(token, "expected number")),
+        TokenKind::Word(_) | TokenKind::Filename(_) => Err(diag_at_token(token, EXPECTED_NUMBER)),
     }
 }
 
@@ -1003,6 +1722,39 @@ fn expect_u8_number(command: &Token, args: &[Token], index: usize) -> Result<u8,
     Ok(value as u8)
 }
 
+fn expect_number_where(
+    command: &Token,
+    args: &[Token],
+    index: usize,
+    valid: impl Fn(f64) -> bool,
+    expected: &str,
+) -> Result<f64, Diagnostic> {
+    let token = args.get(index).unwrap_or(command);
+    let value = expect_number(command, args, index)?;
+    if !valid(value) {
+        return Err(diag_at_token(
+            token,
+            format!("expected {expected}, got {value}"),
+        ));
+    }
+    Ok(value)
+}
+
+fn expect_unit_interval(
+    command: &Token,
+    args: &[Token],
+    index: usize,
+    role: &str,
+) -> Result<f64, Diagnostic> {
+    expect_number_where(
+        command,
+        args,
+        index,
+        |value| (0.0..=1.0).contains(&value),
+        &format!("{role} from 0 to 1"),
+    )
+}
+
 fn expect_positive_usize(
     command: &Token,
     args: &[Token],
@@ -1124,9 +1876,9 @@ mod tests {
     use super::parse_script;
     use crate::mdl::{
         ast::{
-            AnimationCommand, Axis, ColorSpec, Command, ControlCommand, CurveCommand,
-            OutputCommand, RenderCommand, ShadingMode, ShapeCommand, TransformCommand,
-            VaryInterpolation, Vec3,
+            AnimationCommand, AreaEmitter, Axis, ColorSpec, Command, ControlCommand, CurveCommand,
+            Expr, LetCommand, LightSource, MediumSpec, OutputCommand, RayMaterialSpec,
+            RenderCommand, ShadingMode, ShapeCommand, TransformCommand, VaryInterpolation, Vec3,
         },
         lexer::lex_line,
     };
@@ -1537,6 +2289,255 @@ mod tests {
         assert!(errors[0].message.contains("beziern degree"));
     }
 
+    #[test]
+    fn parses_let_bindings_and_nested_for_blocks() {
+        let program = parse_script(
+            "let n = 2 * 3\nfor i in 0..n {\n  for j in 1..=i {\n    sphere (i * 10) j 0 5\n  }\n}",
+        )
+        .unwrap();
+
+        assert_eq!(program.commands.len(), 2);
+        assert!(matches!(
+            &program.commands[0].node,
+            Command::Let(LetCommand { name, value }) if name == "n" && value.as_number() == Some(6.0)
+        ));
+        let Command::For(outer) = &program.commands[1].node else {
+            panic!("expected for block");
+        };
+        assert_eq!(outer.variable, "i");
+        assert!(!outer.inclusive);
+        let Command::For(inner) = &outer.body[0].node else {
+            panic!("expected nested for block");
+        };
+        assert!(inner.inclusive);
+        let Command::Deferred(sphere) = &inner.body[0].node else {
+            panic!("expected deferred sphere");
+        };
+        assert_eq!(sphere.name(), "sphere");
+        assert_eq!(
+            sphere
+                .expressions
+                .iter()
+                .map(|(index, _)| *index)
+                .collect::<Vec<_>>(),
+            [1, 2]
+        );
+    }
+
+    #[test]
+    fn folds_constant_expressions_into_plain_commands() {
+        let program = parse_script("move (2 * 3) -4/2 2^3 k").unwrap();
+
+        assert_eq!(
+            program.commands[0].node,
+            Command::Transform(TransformCommand::Move {
+                x: 6.0,
+                y: -2.0,
+                z: 8.0,
+                knob: Some("k".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn reports_expression_and_block_errors_with_spans() {
+        let errors = parse_script(
+            "move (1 +) 0 0\nlet pi = 3\nfor i in 0..3\n}\n}\nfor j in 0..2 {\ninclude other.mdl",
+        )
+        .unwrap_err();
+
+        assert_eq!(errors.len(), 6);
+        assert_eq!((errors[0].line, errors[0].col_start), (1, 10));
+        assert!(errors[1].message.contains("built-in"));
+        assert!(errors[2].message.contains("expected `{`"));
+        assert!(errors[3].message.contains("unexpected `}`"));
+        assert!(errors[4].message.contains("`include` is not allowed"));
+        assert_eq!(errors[5].line, 6);
+        assert!(errors[5].message.contains("unclosed `for` block"));
+    }
+
+    #[test]
+    fn parses_define_blocks_and_calls() {
+        let program =
+            parse_script("define leg(len, angle) {\n  rotate x angle\n  box 0 0 0 1 len 1\n}\ncall leg(2 * 3, 45)")
+                .unwrap();
+
+        assert_eq!(program.commands.len(), 2);
+        let Command::Define(define) = &program.commands[0].node else {
+            panic!("expected define block");
+        };
+        assert_eq!(define.name, "leg");
+        assert_eq!(define.params, ["len", "angle"]);
+        assert_eq!(define.body.len(), 2);
+        let Command::Call(call) = &program.commands[1].node else {
+            panic!("expected call");
+        };
+        assert_eq!(call.name, "leg");
+        assert_eq!((call.name_span.line, call.name_span.col_start), (5, 6));
+        assert_eq!(
+            call.args.iter().map(Expr::as_number).collect::<Vec<_>>(),
+            [Some(6.0), Some(45.0)]
+        );
+    }
+
+    #[test]
+    fn reports_define_block_errors() {
+        let errors = parse_script(
+            "define a(x, x) {\n}\nfor i in 0..2 {\n  define b() {\n  }\n}\ndefine c() {\n  frames 3\n}\ncall\ndefine d() {",
+        )
+        .unwrap_err();
+
+        assert_eq!(errors.len(), 5);
+        assert!(errors[0].message.contains("duplicate parameter"));
+        assert!(errors[1].message.contains("only allowed at the top level"));
+        assert!(
+            errors[2]
+                .message
+                .contains("`frames` is not allowed inside `define`")
+        );
+        assert!(errors[3].message.contains("expected procedure call"));
+        assert_eq!(errors[4].line, 11);
+        assert!(errors[4].message.contains("unclosed `define` block"));
+    }
+
+    #[test]
+    fn parses_physically_based_material_and_medium_commands() {
+        let program = parse_script(
+            "material glassy glass\nmaterial brushed metal fuzz 0.2 color 0.9 0.9 0.9\nmaterial lamp emissive 1 1 1 8\nmedium smoke density 0.3 albedo 0.8 0.8 0.8\nsphere glassy 0 0 0 10",
+        )
+        .unwrap();
+
+        let materials = program.commands[..3]
+            .iter()
+            .map(|command| match &command.node {
+                Command::Render(RenderCommand::RayMaterial { material, .. }) => *material,
+                other => panic!("expected material, got {other:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            materials,
+            [
+                RayMaterialSpec::Glass { ior: 1.5 },
+                RayMaterialSpec::Metal {
+                    color: Vec3::new(0.9, 0.9, 0.9),
+                    fuzz: 0.2,
+                },
+                RayMaterialSpec::Emissive {
+                    color: Vec3::new(1.0, 1.0, 1.0),
+                    strength: 8.0,
+                },
+            ]
+        );
+        assert_eq!(
+            program.commands[3].node,
+            Command::Render(RenderCommand::Medium {
+                name: "smoke".to_string(),
+                medium: MediumSpec {
+                    density: 0.3,
+                    albedo: Vec3::new(0.8, 0.8, 0.8),
+                    anisotropy: 0.0,
+                },
+            })
+        );
+        assert!(matches!(
+            &program.commands[4].node,
+            Command::Shape(ShapeCommand::Sphere { constants: Some(name), .. }) if name == "glassy"
+        ));
+    }
+
+    #[test]
+    fn reports_invalid_material_options_at_their_tokens() {
+        let errors = parse_script(
+            "material a ggx color 1 1 1\nmaterial b ggx color 1 1 1 roughness 2\nmaterial c plastic\nmaterial d metal color 1 1 1 shine 3\nmedium e density 0 albedo 1 1 1\nmedium f albedo 1 1",
+        )
+        .unwrap_err();
+
+        assert_eq!(errors.len(), 6);
+        assert!(errors[0].message.contains("`ggx` requires `roughness`"));
+        assert_eq!((errors[1].line, errors[1].col_start), (2, 38));
+        assert!(errors[1].message.contains("roughness from 0 to 1"));
+        assert!(
+            errors[2]
+                .message
+                .contains("unknown material model `plastic`")
+        );
+        assert!(errors[3].message.contains("unknown `metal` option `shine`"));
+        assert!(errors[4].message.contains("positive density"));
+        assert!(errors[5].message.contains("`albedo` expects 3 value(s)"));
+    }
+
+    #[test]
+    fn parses_spot_directional_and_area_light_commands() {
+        let program = parse_script(
+            "spotlight 255 255 255 0 10 0 0 -1 0 15 30 2\ndirlight 255 200 100 0 -1 -1\narealight quad 255 255 255 -1 5 -1 2 0 0 0 0 2 4\narealight sphere 10 20 30 0 0 0 0.5",
+        )
+        .unwrap();
+
+        let sources = program
+            .commands
+            .iter()
+            .map(|command| match &command.node {
+                Command::Render(RenderCommand::LightSource(source)) => *source,
+                other => panic!("expected light source, got {other:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sources,
+            [
+                LightSource::Spot {
+                    color: Vec3::new(255.0, 255.0, 255.0),
+                    position: Vec3::new(0.0, 10.0, 0.0),
+                    direction: Vec3::new(0.0, -1.0, 0.0),
+                    inner_angle: 15.0,
+                    outer_angle: 30.0,
+                    falloff: 2.0,
+                },
+                LightSource::Directional {
+                    color: Vec3::new(255.0, 200.0, 100.0),
+                    direction: Vec3::new(0.0, -1.0, -1.0),
+                },
+                LightSource::Area {
+                    color: Vec3::new(255.0, 255.0, 255.0),
+                    emitter: AreaEmitter::Quad {
+                        corner: Vec3::new(-1.0, 5.0, -1.0),
+                        u: Vec3::new(2.0, 0.0, 0.0),
+                        v: Vec3::new(0.0, 0.0, 2.0),
+                    },
+                    strength: 4.0,
+                },
+                LightSource::Area {
+                    color: Vec3::new(10.0, 20.0, 30.0),
+                    emitter: AreaEmitter::Sphere {
+                        center: Vec3::new(0.0, 0.0, 0.0),
+                        radius: 0.5,
+                    },
+                    strength: 1.0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_invalid_light_source_arguments() {
+        let errors = parse_script(
+            "spotlight 255 255 255 0 0 0 0 0 0 10 20\nspotlight 255 255 255 0 0 0 0 -1 0 30 20\ndirlight 255 255 255 0 -1\narealight quad 255 255 255 0 0 0 1 0 0 2 0 0\narealight sphere 255 255 255 0 0 0 0\narealight disk 255 255 255",
+        )
+        .unwrap_err();
+
+        assert_eq!(errors.len(), 6);
+        assert!(errors[0].message.contains("direction must be non-zero"));
+        assert_eq!((errors[0].line, errors[0].col_start), (1, 29));
+        assert!(errors[1].message.contains("outer cone angle"));
+        assert_eq!(errors[2].help.as_deref(), Some("dirlight r g b dx dy dz"));
+        assert!(errors[3].message.contains("must not be parallel"));
+        assert!(errors[4].message.contains("positive radius"));
+        assert!(
+            errors[5]
+                .message
+                .contains("unknown area light shape `disk`")
+        );
+    }
+
     #[test]
     fn parses_bezier_surface_extension_command() {
         let mut source = String::from("bezier_surface 4");
@@ -2,7 +2,7 @@
 
 use super::{
     animation::KnobMap,
-    ast::{Material, Vec3},
+    ast::{AreaEmitter, LightSource, Material, MediumSpec, RayMaterialSpec, Vec3},
     executor::ExecutionError,
 };
 use crate::{
@@ -17,8 +17,11 @@ use crate::{
         camera::RayCamera,
         colors::{LinearRgb, Rgb},
         display::{Canvas, PolygonColorMode, ShadingMode as CanvasShadingMode},
-        lighting::{Lighting, PointLight, ReflectionConstants, SurfaceMaterial},
-        raytracing::{DiffuseLight, PathTracer, SamplingTargetList},
+        lighting::{Lighting, PointLight, ReflectionConstants, SpotCone, SurfaceMaterial},
+        raytracing::{
+            ConstantMedium, DiffuseLight, HenyeyGreenstein, Hittable, HittableLayers, MaterialId,
+            PathTracer, RayGeometry, RayMaterial, RayScene,
+        },
         scene::SurfaceScene,
         texture::{Texture, TextureFilter},
     },
@@ -26,13 +29,11 @@ use crate::{
 use std::{
     collections::HashMap,
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
 #[cfg(feature = "external")]
-use {
-    crate::{external::MaterialMesh, graphics::texture::TextureWrap},
-    std::sync::Arc,
-};
+use crate::{external::MaterialMesh, graphics::texture::TextureWrap};
 
 #[cfg(feature = "external")]
 #[derive(Debug, Clone, Default)]
@@ -44,6 +45,10 @@ pub(crate) struct AssetCaches {
 const DEFAULT_RAYTRACE_SAMPLES_PER_PIXEL: u32 = 16;
 const DEFAULT_RAYTRACE_MAX_DEPTH: u32 = 8;
 const DEFAULT_RAYTRACE_LIGHT_RADIUS: f64 = 10.0;
+/// Distance of the emitting disc that stands in for a `dirlight` when path tracing.
+const DIRECTIONAL_LIGHT_DISTANCE: f64 = 1.0e6;
+/// Angular radius of the `dirlight` disc, roughly that of the sun.
+const DIRECTIONAL_LIGHT_ANGULAR_RADIUS_DEGREES: f64 = 0.5;
 
 /// Rendering configuration for one MDL execution.
 #[derive(Debug, Clone)]
@@ -243,6 +248,28 @@ pub enum Symbol {
     Light(Light),
     /// Saved coordinate-system matrix.
     CoordSystem(Matrix),
+    /// Physically based ray-tracing material.
+    RayMaterial(RayMaterialSpec),
+    /// Homogeneous participating medium.
+    Medium(MediumSpec),
+}
+
+/// Ray-tracing material named by a shape in place of `constants`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum RayShapeMaterial {
+    /// The shape is a surface with a physically based material.
+    Surface(RayMaterialSpec),
+    /// The shape bounds a homogeneous medium.
+    Medium(MediumSpec),
+}
+
+/// Material for an analytic shape captured in place of its tessellated triangles.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum AnalyticShapeMaterial {
+    /// The shape uses `constants`, converted like captured surface meshes.
+    Constants(SurfaceMaterial),
+    /// The shape names a ray material or medium.
+    Ray(RayShapeMaterial),
 }
 
 /// Mutable state used while executing one MDL program.
@@ -274,9 +301,14 @@ struct SceneState {
     symbols: HashMap<String, Symbol>,
     frame_knobs: HashMap<String, f64>,
     lights: Vec<Light>,
+    light_sources: Vec<LightSource>,
     ambient: Vec3,
     camera: Option<Camera>,
     surface_scene: SurfaceScene,
+    /// Captured shapes that name a `material` or `medium` instead of `constants`.
+    ray_surfaces: Vec<(PolygonMatrix, RayShapeMaterial)>,
+    /// Captured cylinders, cones, and tori kept as exact ray geometry.
+    ray_shapes: Vec<(RayGeometry, AnalyticShapeMaterial)>,
     raytrace_enabled: bool,
     surface_capture_enabled: bool,
 }
@@ -411,6 +443,12 @@ impl Runtime {
         &self.scene.lights
     }
 
+    /// Returns declared spot, directional, and area lights.
+    #[must_use]
+    pub fn light_sources(&self) -> &[LightSource] {
+        &self.scene.light_sources
+    }
+
     /// Returns the ambient light color.
     #[must_use]
     pub fn ambient(&self) -> Vec3 {
@@ -532,7 +570,9 @@ impl Runtime {
                 Symbol::KnobList(_)
                 | Symbol::Constants(_)
                 | Symbol::Light(_)
-                | Symbol::CoordSystem(_) => None,
+                | Symbol::CoordSystem(_)
+                | Symbol::RayMaterial(_)
+                | Symbol::Medium(_) => None,
             })
             .collect::<KnobMap>();
         knobs.extend(
@@ -557,25 +597,68 @@ impl Runtime {
         );
     }
 
+    pub(crate) fn set_ray_material(&mut self, name: String, material: RayMaterialSpec) {
+        self.scene
+            .symbols
+            .insert(name, Symbol::RayMaterial(material));
+    }
+
+    pub(crate) fn set_medium(&mut self, name: String, medium: MediumSpec) {
+        self.scene.symbols.insert(name, Symbol::Medium(medium));
+    }
+
     pub(crate) fn set_ambient(&mut self, color: Vec3) {
         self.scene.ambient = color;
         self.canvas.lighting_mut().ambient = rgb_from_vec3(color);
     }
 
     pub(crate) fn add_light(&mut self, name: Option<String>, light: Light) {
-        let first_user_light = self.scene.lights.is_empty();
-        let point_light =
-            PointLight::positional(vec3_to_vector(light.position), rgb_from_vec3(light.color));
+        self.push_raster_light(PointLight::positional(
+            vec3_to_vector(light.position),
+            rgb_from_vec3(light.color),
+        ));
+        if let Some(name) = name {
+            self.scene.symbols.insert(name, Symbol::Light(light));
+        }
+        self.scene.lights.push(light);
+    }
+
+    pub(crate) fn add_light_source(&mut self, source: LightSource) {
+        let point_light = match source {
+            LightSource::Spot {
+                color,
+                position,
+                direction,
+                inner_angle,
+                outer_angle,
+                falloff,
+            } => PointLight::positional(vec3_to_vector(position), rgb_from_vec3(color))
+                .with_spot_cone(SpotCone::new(
+                    vec3_to_vector(direction),
+                    inner_angle,
+                    outer_angle,
+                    falloff,
+                )),
+            LightSource::Directional { color, direction } => {
+                PointLight::directional(-vec3_to_vector(direction), rgb_from_vec3(color))
+            }
+            LightSource::Area { color, emitter, .. } => {
+                PointLight::positional(vec3_to_vector(emitter.center()), rgb_from_vec3(color))
+            }
+        };
+        self.push_raster_light(point_light);
+        self.scene.light_sources.push(source);
+    }
+
+    /// Adds a light to raster shading, replacing the default light on the first declaration.
+    fn push_raster_light(&mut self, point_light: PointLight) {
+        let first_user_light = self.scene.lights.is_empty() && self.scene.light_sources.is_empty();
         let lighting = self.canvas.lighting_mut();
         lighting.point_light = point_light;
         if first_user_light {
             lighting.point_lights.clear();
         }
         lighting.point_lights.push(point_light);
-        if let Some(name) = name {
-            self.scene.symbols.insert(name, Symbol::Light(light));
-        }
-        self.scene.lights.push(light);
     }
 
     pub(crate) fn set_camera(&mut self, eye: Vec3, aim: Vec3) {
@@ -616,6 +699,16 @@ impl Runtime {
         self.scene.surface_scene.len()
     }
 
+    #[cfg(test)]
+    pub(crate) fn captured_ray_surfaces(&self) -> &[(PolygonMatrix, RayShapeMaterial)] {
+        &self.scene.ray_surfaces
+    }
+
+    #[cfg(test)]
+    pub(crate) fn captured_ray_shapes(&self) -> &[(RayGeometry, AnalyticShapeMaterial)] {
+        &self.scene.ray_shapes
+    }
+
     pub(crate) fn set_basename(&mut self, basename: String) {
         self.output.basename = basename;
     }
@@ -650,10 +743,40 @@ impl Runtime {
         };
         match self.scene.symbols.get(name) {
             Some(Symbol::Constants(constants)) => Ok(Some(*constants)),
+            Some(Symbol::RayMaterial(material)) => Ok(Some(MaterialConstants {
+                material: material.preview_material(),
+                color: Vec3::new(0.0, 0.0, 0.0),
+            })),
+            Some(Symbol::Medium(medium)) => {
+                let albedo = medium.albedo;
+                Ok(Some(MaterialConstants {
+                    material: Material::new(
+                        0.2 * albedo.x,
+                        0.5 * albedo.x,
+                        0.0,
+                        0.2 * albedo.y,
+                        0.5 * albedo.y,
+                        0.0,
+                        0.2 * albedo.z,
+                        0.5 * albedo.z,
+                        0.0,
+                    ),
+                    color: Vec3::new(0.0, 0.0, 0.0),
+                }))
+            }
             _ => Err(ExecutionError::UnknownConstants(name.to_string())),
         }
     }
 
+    /// Returns the ray-tracing material when `constants` names a `material` or `medium`.
+    pub(crate) fn ray_material_for(&self, constants: Option<&str>) -> Option<RayShapeMaterial> {
+        match self.scene.symbols.get(constants?) {
+            Some(Symbol::RayMaterial(material)) => Some(RayShapeMaterial::Surface(*material)),
+            Some(Symbol::Medium(medium)) => Some(RayShapeMaterial::Medium(*medium)),
+            _ => None,
+        }
+    }
+
     pub(crate) fn apply_draw_state(&mut self, constants: Option<MaterialConstants>) -> DrawState {
         let lighting = self.canvas.lighting_ref();
         let previous = DrawState {
@@ -730,6 +853,18 @@ impl Runtime {
         }
     }
 
+    pub(crate) fn add_ray_surface(&mut self, polygons: PolygonMatrix, material: RayShapeMaterial) {
+        if self.scene.surface_capture_enabled {
+            self.scene.ray_surfaces.push((polygons, material));
+        }
+    }
+
+    pub(crate) fn add_ray_shape(&mut self, geometry: RayGeometry, material: AnalyticShapeMaterial) {
+        if self.scene.surface_capture_enabled {
+            self.scene.ray_shapes.push((geometry, material));
+        }
+    }
+
     #[cfg(feature = "external")]
     pub(crate) fn draw_tmp_polygons_with_vertex_normal_plan(
         &mut self,
@@ -818,22 +953,120 @@ impl Runtime {
         }
 
         let mut ray_scene = self.scene.surface_scene.to_ray_scene();
-        let mut sampling_targets = SamplingTargetList::with_capacity(self.scene.lights.len());
+        let mut media = Vec::new();
+        for (polygons, material) in &self.scene.ray_surfaces {
+            match material {
+                RayShapeMaterial::Surface(material) => {
+                    let material = ray_scene.add_material(RayMaterial::from(*material));
+                    add_polygon_triangles(&mut ray_scene, polygons, material);
+                }
+                RayShapeMaterial::Medium(medium) => {
+                    media.push(constant_medium(medium, |boundary, material| {
+                        add_polygon_triangles(boundary, polygons, material);
+                    }));
+                }
+            }
+        }
+        for (geometry, material) in &self.scene.ray_shapes {
+            let material = match material {
+                AnalyticShapeMaterial::Constants(material) => {
+                    RayMaterial::from_surface_lambertian(material)
+                }
+                AnalyticShapeMaterial::Ray(RayShapeMaterial::Surface(material)) => {
+                    RayMaterial::from(*material)
+                }
+                AnalyticShapeMaterial::Ray(RayShapeMaterial::Medium(medium)) => {
+                    media.push(constant_medium(medium, |boundary, material| {
+                        boundary.add_primitive(*geometry, material);
+                    }));
+                    continue;
+                }
+            };
+            let material = ray_scene.add_material(material);
+            ray_scene.add_primitive(*geometry, material);
+        }
         for light in &self.scene.lights {
             let center = vec3_to_point(light.position);
             let radius = self.output.raytrace_light_radius;
             let emit = LinearRgb::from_rgb_linear_units(rgb_from_vec3(light.color)) * 12.0;
             let material = ray_scene.add_material(DiffuseLight::new(emit));
             ray_scene.add_sphere(center, radius, material);
-            sampling_targets.add_sphere(center, radius);
+        }
+        for source in &self.scene.light_sources {
+            self.add_ray_light_source(&mut ray_scene, source);
         }
         ray_scene.build_bvh();
+        // Emissive MDL surfaces and every light added above share one light tree.
+        let sampling_targets = ray_scene.emissive_targets();
+
+        let mut layers = HittableLayers::with_capacity(1 + media.len());
+        layers.add(&ray_scene);
+        for medium in &media {
+            layers.add(medium);
+        }
+        let world: &dyn Hittable = if media.is_empty() {
+            &ray_scene
+        } else {
+            &layers
+        };
 
         let tracer = PathTracer::new(camera);
         if sampling_targets.is_empty() {
-            tracer.render(&ray_scene)
+            tracer.render(world)
         } else {
-            tracer.render_with_lights(&ray_scene, &sampling_targets)
+            tracer.render_with_lights(world, &sampling_targets)
+        }
+    }
+
+    /// Adds the emitter geometry for a spot, directional, or area light.
+    fn add_ray_light_source(&self, ray_scene: &mut RayScene, source: &LightSource) {
+        match *source {
+            LightSource::Spot {
+                color,
+                position,
+                direction,
+                inner_angle,
+                outer_angle,
+                falloff,
+            } => {
+                let center = vec3_to_point(position);
+                let radius = self.output.raytrace_light_radius;
+                let emit = LinearRgb::from_rgb_linear_units(rgb_from_vec3(color)) * 12.0;
+                let cone =
+                    SpotCone::new(vec3_to_vector(direction), inner_angle, outer_angle, falloff);
+                let material = ray_scene.add_material(DiffuseLight::new(emit).with_spot_cone(cone));
+                ray_scene.add_sphere(center, radius, material);
+            }
+            LightSource::Directional { color, direction } => {
+                // A small, distant disc: radiance / sin^2 keeps the irradiance at pi * color.
+                let sin_angle = DIRECTIONAL_LIGHT_ANGULAR_RADIUS_DEGREES.to_radians().sin();
+                let center = Point::new(0.0, 0.0, 0.0)
+                    - vec3_to_vector(direction).normalized() * DIRECTIONAL_LIGHT_DISTANCE;
+                let radius = DIRECTIONAL_LIGHT_DISTANCE * sin_angle;
+                let emit = LinearRgb::from_rgb_linear_units(rgb_from_vec3(color))
+                    / (sin_angle * sin_angle);
+                let material = ray_scene.add_material(DiffuseLight::new(emit));
+                ray_scene.add_sphere(center, radius, material);
+            }
+            LightSource::Area {
+                color,
+                emitter,
+                strength,
+            } => {
+                let emit = LinearRgb::from_rgb_linear_units(rgb_from_vec3(color)) * strength;
+                let material = ray_scene.add_material(DiffuseLight::new(emit));
+                match emitter {
+                    AreaEmitter::Quad { corner, u, v } => {
+                        let (corner, u, v) =
+                            (vec3_to_point(corner), vec3_to_vector(u), vec3_to_vector(v));
+                        ray_scene.add_quad(corner, u, v, material);
+                    }
+                    AreaEmitter::Sphere { center, radius } => {
+                        let center = vec3_to_point(center);
+                        ray_scene.add_sphere(center, radius, material);
+                    }
+                }
+            }
         }
     }
 
@@ -922,9 +1155,12 @@ impl SceneState {
             symbols: HashMap::new(),
             frame_knobs: HashMap::new(),
             lights: Vec::new(),
+            light_sources: Vec::new(),
             ambient: default_ambient(),
             camera: None,
             surface_scene: SurfaceScene::new(),
+            ray_surfaces: Vec::new(),
+            ray_shapes: Vec::new(),
             raytrace_enabled: false,
             surface_capture_enabled: false,
         }
@@ -934,15 +1170,20 @@ impl SceneState {
         self.stack = MatrixStack::new();
         self.symbols.clear();
         self.lights.clear();
+        self.light_sources.clear();
         self.ambient = default_ambient();
         self.camera = None;
         self.surface_scene.clear();
+        self.ray_surfaces.clear();
+        self.ray_shapes.clear();
         self.raytrace_enabled = false;
         self.surface_capture_enabled = false;
     }
 
     fn clear_geometry(&mut self) {
         self.surface_scene.clear();
+        self.ray_surfaces.clear();
+        self.ray_shapes.clear();
     }
 }
 
@@ -1024,6 +1265,38 @@ pub(crate) fn rgb_from_vec3(color: Vec3) -> Rgb {
     )
 }
 
+fn add_polygon_triangles(ray_scene: &mut RayScene, polygons: &PolygonMatrix, material: MaterialId) {
+    for (p0, p1, p2) in polygons.triangles() {
+        ray_scene.add_triangle(
+            Point::new(p0[0], p0[1], p0[2]),
+            Point::new(p1[0], p1[1], p1[2]),
+            Point::new(p2[0], p2[1], p2[2]),
+            material,
+        );
+    }
+}
+
+/// Builds a constant-density volume bounded by the closed MDL shape that `add_boundary` adds.
+fn constant_medium(
+    medium: &MediumSpec,
+    add_boundary: impl FnOnce(&mut RayScene, MaterialId),
+) -> ConstantMedium {
+    let mut boundary = RayScene::new();
+    let material = boundary.add_material(RayMaterial::lambertian(LinearRgb::new(0.0, 0.0, 0.0)));
+    add_boundary(&mut boundary, material);
+    boundary.build_bvh();
+    let albedo = LinearRgb::new(medium.albedo.x, medium.albedo.y, medium.albedo.z);
+    if medium.anisotropy == 0.0 {
+        ConstantMedium::new(boundary, medium.density, albedo)
+    } else {
+        ConstantMedium::with_phase_function(
+            boundary,
+            medium.density,
+            Arc::new(HenyeyGreenstein::new(albedo, medium.anisotropy)),
+        )
+    }
+}
+
 fn vec3_to_point(point: Vec3) -> Point {
     Point::new(point.x, point.y, point.z)
 }
@@ -3,20 +3,42 @@
 use super::{
     animation::{AnimationPlan, KnobMap},
     ast::{
-        AnimationCommand, Command, ControlCommand, Program, RenderCommand, Spanned,
-        VaryInterpolation,
+        AnimationCommand, CallCommand, Command, ControlCommand, DeferredCommand, DefineCommand,
+        Expr, ExprKind, ForCommand, LetCommand, PointRef, Program, RenderCommand, ShapeCommand,
+        Spanned, VaryInterpolation,
     },
-    diagnostic::Diagnostic,
-    lexer::Span,
+    diagnostic::{Diagnostic, DiagnosticNote},
+    expr::{fold, variables},
+    lexer::{Span, TokenKind},
+    parser::resolve_expression_command,
 };
 use std::{
-    collections::{BTreeSet, HashMap},
+    collections::{BTreeSet, HashMap, HashSet},
     path::PathBuf,
 };
 
 /// Maximum animation frames precomputed by the semantic pass.
 pub const MAX_FRAMES: usize = 10_000;
 
+/// Maximum commands produced by expanding `for` blocks.
+pub const MAX_EXPANDED_COMMANDS: usize = 1_000_000;
+
+/// Maximum `for` iterations and procedure calls expanded across the whole program, including
+/// nested loops and calls whose bodies produce no commands.
+pub const MAX_EXPANSION_STEPS: usize = 2_000_000;
+
+/// Largest loop bound magnitude; keeps every loop value exactly representable.
+const MAX_LOOP_BOUND: f64 = 9_007_199_254_740_992.0;
+
+/// Deepest chain of nested `call` expansions.
+pub const MAX_CALL_DEPTH: usize = 64;
+
+/// Most "in call to" notes attached to one diagnostic raised inside a procedure body.
+const MAX_CALL_NOTES: usize = 8;
+
+/// Commands whose arguments feed the animation plan and therefore cannot read knobs.
+const PLAN_COMMANDS: [&str; 5] = ["frames", "set", "setknobs", "vary", "tween"];
+
 /// A semantically compiled MDL program.
 #[derive(Debug, Clone, PartialEq)]
 pub struct CompiledProgram {
@@ -88,9 +110,16 @@ enum SemanticSymbol {
 
 /// Compiles a parsed program into commands plus a frame-knob table.
 ///
+/// `let` bindings, `for` blocks, and `call`s of `define`d procedures are expanded first, and
+/// expression arguments that only use `let`, loop, or parameter variables are folded into plain
+/// commands. Expressions that read knobs stay [`Command::Deferred`] and are evaluated for each
+/// frame.
+///
 /// # Errors
 /// Returns semantic diagnostics for invalid animation ranges, zero frame counts,
-/// or missing tween knob lists.
+/// missing tween knob lists, unknown variables, invalid `for` ranges, unknown or
+/// redefined procedures, wrong call arities, calls nested deeper than [`MAX_CALL_DEPTH`], or
+/// expression arguments that are out of range for their command.
 #[allow(clippy::too_many_lines)]
 pub fn compile(program: Program) -> Result<CompiledProgram, Vec<Diagnostic>> {
     let mut basename = "frame".to_string();
@@ -101,9 +130,16 @@ pub fn compile(program: Program) -> Result<CompiledProgram, Vec<Diagnostic>> {
     let mut frames_location = None;
     let mut saw_frames = false;
     let mut animation_range_location = None;
-    let mut errors = Vec::new();
+    let mut knob_names = HashSet::new();
+    let mut non_numeric_names = HashMap::new();
+    let mut knob_references = Vec::new();
+
+    let mut expansion = Expansion::default();
+    expansion.expand(program.commands);
+    let mut errors = expansion.errors;
+    let mut call_notes = expansion.call_notes;
 
-    for command in program.commands {
+    for (index, command) in expansion.commands.into_iter().enumerate() {
         let location = SourceLocation {
             span: command.span,
             source_name: command.source_name.clone(),
@@ -130,6 +166,7 @@ pub fn compile(program: Program) -> Result<CompiledProgram, Vec<Diagnostic>> {
                 saw_frames = true;
             }
             Command::Animation(AnimationCommand::Set { knob, value }) => {
+                knob_names.insert(knob.clone());
                 symbols.insert(knob.clone(), SemanticSymbol::Knob(value));
                 runtime_commands.push(Spanned {
                     node: Command::Animation(AnimationCommand::Set { knob, value }),
@@ -151,6 +188,7 @@ pub fn compile(program: Program) -> Result<CompiledProgram, Vec<Diagnostic>> {
             }
             Command::Animation(AnimationCommand::SaveKnobs(name)) => {
                 let snapshot = current_knobs(&symbols);
+                non_numeric_names.insert(name.clone(), "a knob list");
                 symbols.insert(name.clone(), SemanticSymbol::KnobList(snapshot));
                 runtime_commands.push(Spanned {
                     node: Command::Animation(AnimationCommand::SaveKnobs(name)),
@@ -163,6 +201,7 @@ pub fn compile(program: Program) -> Result<CompiledProgram, Vec<Diagnostic>> {
                 material,
                 color,
             }) => {
+                non_numeric_names.insert(name.clone(), "material constants");
                 symbols.insert(name.clone(), SemanticSymbol::Constants);
                 runtime_commands.push(Spanned {
                     node: Command::Render(RenderCommand::Constants {
@@ -174,7 +213,26 @@ pub fn compile(program: Program) -> Result<CompiledProgram, Vec<Diagnostic>> {
                     source_name,
                 });
             }
+            Command::Render(RenderCommand::RayMaterial { name, material }) => {
+                non_numeric_names.insert(name.clone(), "a material");
+                symbols.insert(name.clone(), SemanticSymbol::Constants);
+                runtime_commands.push(Spanned {
+                    node: Command::Render(RenderCommand::RayMaterial { name, material }),
+                    span,
+                    source_name,
+                });
+            }
+            Command::Render(RenderCommand::Medium { name, medium }) => {
+                non_numeric_names.insert(name.clone(), "a medium");
+                symbols.insert(name.clone(), SemanticSymbol::Constants);
+                runtime_commands.push(Spanned {
+                    node: Command::Render(RenderCommand::Medium { name, medium }),
+                    span,
+                    source_name,
+                });
+            }
             Command::Render(RenderCommand::SaveCoordSystem(name)) => {
+                non_numeric_names.insert(name.clone(), "a coordinate system");
                 symbols.insert(name.clone(), SemanticSymbol::CoordSystem);
                 runtime_commands.push(Spanned {
                     node: Command::Render(RenderCommand::SaveCoordSystem(name)),
@@ -189,6 +247,7 @@ pub fn compile(program: Program) -> Result<CompiledProgram, Vec<Diagnostic>> {
                 knob,
             }) => {
                 if let Some(name) = &name {
+                    non_numeric_names.insert(name.clone(), "a light");
                     symbols.insert(name.clone(), SemanticSymbol::Light);
                 }
                 runtime_commands.push(Spanned {
@@ -211,6 +270,7 @@ pub fn compile(program: Program) -> Result<CompiledProgram, Vec<Diagnostic>> {
                 interpolation,
             }) => {
                 animation_range_location.get_or_insert_with(|| location.clone());
+                knob_names.insert(knob.clone());
                 animation_ops.push(AnimationOp::Vary(VarySpec {
                     knob,
                     start_frame,
@@ -266,6 +326,40 @@ pub fn compile(program: Program) -> Result<CompiledProgram, Vec<Diagnostic>> {
                     source_name,
                 });
             }
+            Command::Deferred(deferred) => {
+                let unresolved = deferred
+                    .expressions
+                    .iter()
+                    .map(|(_, expr)| expr)
+                    .filter(|expr| expr.as_number().is_none());
+                if PLAN_COMMANDS.contains(&deferred.name())
+                    && let Some(expr) = unresolved.clone().next()
+                {
+                    errors.push(
+                        diagnostic_at_span(
+                            expr.span,
+                            &location,
+                            format!("`{}` arguments cannot read knobs", deferred.name()),
+                        )
+                        .with_help("use numbers, `let` variables, or loop variables"),
+                    );
+                }
+                for expr in unresolved {
+                    let mut names = Vec::new();
+                    variables(expr, &mut names);
+                    let notes = call_notes.remove(&index).unwrap_or_default();
+                    knob_references.extend(
+                        names
+                            .into_iter()
+                            .map(|(name, span)| (name, span, location.clone(), notes.clone())),
+                    );
+                }
+                runtime_commands.push(Spanned {
+                    node: Command::Deferred(deferred),
+                    span,
+                    source_name,
+                });
+            }
             command => runtime_commands.push(Spanned {
                 node: command,
                 span,
@@ -274,6 +368,24 @@ pub fn compile(program: Program) -> Result<CompiledProgram, Vec<Diagnostic>> {
         }
     }
 
+    let mut reported = HashSet::new();
+    for (name, span, location, notes) in knob_references {
+        // Loop and call expansion can repeat one source expression many times.
+        if knob_names.contains(&name) || !reported.insert((span, location.source_name.clone())) {
+            continue;
+        }
+        let diagnostic = match non_numeric_names.get(name.as_str()) {
+            Some(kind) => diagnostic_at_span(
+                span,
+                &location,
+                format!("`{name}` names {kind}, not a number"),
+            ),
+            None => diagnostic_at_span(span, &location, format!("unknown variable `{name}`"))
+                .with_help("define it with `let`, a `for` loop, or as a knob with `set` or `vary`"),
+        };
+        errors.push(notes.into_iter().fold(diagnostic, Diagnostic::with_note));
+    }
+
     if frames == 0 {
         errors.push(diagnostic_at(
             frames_location.as_ref(),
@@ -355,6 +467,456 @@ pub fn compile(program: Program) -> Result<CompiledProgram, Vec<Diagnostic>> {
     })
 }
 
+/// Expands `let` bindings, `for` blocks, and procedure calls into a flat command list.
+#[derive(Default)]
+struct Expansion {
+    /// Visible bindings, innermost last.
+    scope: Vec<(String, Expr)>,
+    procedures: HashMap<String, Procedure>,
+    /// Procedure calls being expanded, innermost last.
+    calls: Vec<CallFrame>,
+    /// Number of calls expanded so far; numbers call-local coordinate-system names.
+    call_count: usize,
+    /// Loop iterations and calls expanded so far, bounded by [`MAX_EXPANSION_STEPS`].
+    steps: usize,
+    commands: Vec<Spanned<Command>>,
+    /// Call-site notes for deferred commands expanded inside procedures, by command index.
+    call_notes: HashMap<usize, Vec<DiagnosticNote>>,
+    errors: Vec<Diagnostic>,
+}
+
+/// A `define`d procedure.
+struct Procedure {
+    params: Vec<String>,
+    body: Vec<Spanned<Command>>,
+    /// Bindings visible at the definition; the body does not see the caller's bindings.
+    scope: Vec<(String, Expr)>,
+    location: SourceLocation,
+}
+
+/// One procedure call being expanded.
+struct CallFrame {
+    /// Note pointing at the `call` command.
+    note: DiagnosticNote,
+    /// Coordinate systems saved by the body, mapped to their call-local names.
+    coord_systems: HashMap<String, String>,
+}
+
+impl Expansion {
+    /// Expands commands in order. Returns false once `quit` or an expansion limit stops expansion.
+    fn expand(&mut self, commands: Vec<Spanned<Command>>) -> bool {
+        for command in commands {
+            let Spanned {
+                node,
+                span,
+                source_name,
+            } = command;
+            let location = SourceLocation { span, source_name };
+            match node {
+                Command::Let(LetCommand { name, value }) => {
+                    let value = self.fold(&value, &location).unwrap_or_else(|error| {
+                        self.errors.push(error);
+                        Expr::new(ExprKind::Number(1.0), value.span)
+                    });
+                    self.scope.push((name, value));
+                }
+                Command::For(command) => {
+                    if !self.expand_for(&command, &location) {
+                        return false;
+                    }
+                }
+                Command::Deferred(deferred) => {
+                    if !self.expand_deferred(deferred, location) {
+                        return false;
+                    }
+                }
+                Command::Define(command) => self.define(command, location),
+                Command::Call(command) => {
+                    if !self.expand_call(&command, &location) {
+                        return false;
+                    }
+                }
+                node => {
+                    let quit = node.is_quit();
+                    if !self.push(node, location) || quit {
+                        return false;
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    fn expand_for(&mut self, command: &ForCommand, location: &SourceLocation) -> bool {
+        let start = self.loop_bound(&command.start, "start", location);
+        let end = self.loop_bound(&command.end, "end", location);
+        let (Some(start), Some(end)) = (start, end) else {
+            return true;
+        };
+        let stop = if command.inclusive { end + 1 } else { end };
+        if stop < start {
+            self.errors.push(diagnostic_at_span(
+                join_spans(command.start.span, command.end.span),
+                location,
+                format!("`for` range start {start} is greater than its end {end}"),
+            ));
+            return true;
+        }
+        if usize::try_from(stop - start).map_or(true, |count| count > MAX_EXPANDED_COMMANDS) {
+            self.errors.push(diagnostic_at_span(
+                join_spans(command.start.span, command.end.span),
+                location,
+                format!("`for` range must have at most {MAX_EXPANDED_COMMANDS} iterations"),
+            ));
+            return true;
+        }
+
+        for value in start..stop {
+            if !self.step(location) {
+                return false;
+            }
+            let depth = self.scope.len();
+            let errors_before = self.errors.len();
+            #[allow(clippy::cast_precision_loss)]
+            let value = Expr::new(ExprKind::Number(value as f64), command.start.span);
+            self.scope.push((command.variable.clone(), value));
+            let keep_going = self.expand(command.body.clone());
+            self.scope.truncate(depth);
+            if !keep_going {
+                return false;
+            }
+            // Report a body error once rather than once per iteration.
+            if self.errors.len() > errors_before {
+                break;
+            }
+        }
+        true
+    }
+
+    fn define(&mut self, command: DefineCommand, location: SourceLocation) {
+        let DefineCommand { name, params, body } = command;
+        if let Some(previous) = self.procedures.get(&name) {
+            self.errors.push(
+                diagnostic_at(
+                    Some(&location),
+                    format!("procedure `{name}` is already defined"),
+                )
+                .with_note(note_at(&previous.location, "previous definition is here")),
+            );
+            return;
+        }
+        self.procedures.insert(
+            name,
+            Procedure {
+                params,
+                body,
+                scope: self.scope.clone(),
+                location,
+            },
+        );
+    }
+
+    /// Expands one call inside an implicit `push`/`pop` pair.
+    fn expand_call(&mut self, command: &CallCommand, location: &SourceLocation) -> bool {
+        let Some(procedure) = self.procedures.get(&command.name) else {
+            self.errors.push(
+                diagnostic_at_span(
+                    command.name_span,
+                    location,
+                    format!("unknown procedure `{}`", command.name),
+                )
+                .with_help("procedures must be defined with `define` before they are called"),
+            );
+            return true;
+        };
+        let definition = note_at(
+            &procedure.location,
+            format!("`{}` is defined here", command.name),
+        );
+        if command.args.len() != procedure.params.len() {
+            self.errors.push(
+                diagnostic_at(
+                    Some(location),
+                    format!(
+                        "`{}` expects {} argument(s), got {}",
+                        command.name,
+                        procedure.params.len(),
+                        command.args.len()
+                    ),
+                )
+                .with_note(definition),
+            );
+            return true;
+        }
+        if self.calls.len() >= MAX_CALL_DEPTH {
+            self.errors.push(
+                diagnostic_at(
+                    Some(location),
+                    format!("procedure calls are nested more than {MAX_CALL_DEPTH} deep"),
+                )
+                .with_help("recursive procedures need a loop bound that reaches zero")
+                .with_note(definition),
+            );
+            return true;
+        }
+
+        let mut args = Vec::with_capacity(command.args.len());
+        for arg in &command.args {
+            match self.fold(arg, location) {
+                Ok(arg) => args.push(arg),
+                Err(error) => {
+                    self.errors.push(error);
+                    return true;
+                }
+            }
+        }
+        let mut scope = procedure.scope.clone();
+        scope.extend(procedure.params.iter().cloned().zip(args));
+        let body = procedure.body.clone();
+        if !self.step(location) {
+            return false;
+        }
+
+        self.call_count += 1;
+        let mut coord_systems = HashMap::new();
+        saved_coord_systems(&body, &mut |name| {
+            coord_systems
+                .entry(name.to_string())
+                .or_insert_with(|| format!("{name}@{}#{}", command.name, self.call_count));
+        });
+        let caller_scope = std::mem::replace(&mut self.scope, scope);
+        let errors_before = self.errors.len();
+        let mut keep_going = self.push(Command::Control(ControlCommand::Push), location.clone());
+        self.calls.push(CallFrame {
+            note: note_at(location, format!("in call to `{}`", command.name)),
+            coord_systems,
+        });
+        keep_going = keep_going && self.expand(body);
+        self.calls.pop();
+        keep_going =
+            keep_going && self.push(Command::Control(ControlCommand::Pop), location.clone());
+        self.scope = caller_scope;
+
+        let note = note_at(location, format!("in call to `{}`", command.name));
+        for error in &mut self.errors[errors_before..] {
+            if error.notes.len() < MAX_CALL_NOTES {
+                error.notes.push(note.clone());
+            }
+        }
+        keep_going
+    }
+
+    /// Counts one loop iteration or call. Returns false once the program's budget is spent.
+    fn step(&mut self, location: &SourceLocation) -> bool {
+        if self.steps >= MAX_EXPANSION_STEPS {
+            self.errors.push(
+                diagnostic_at(
+                    Some(location),
+                    format!(
+                        "expansion exceeds {MAX_EXPANSION_STEPS} loop iterations and procedure calls"
+                    ),
+                )
+                .with_help("nested loops multiply their iteration counts"),
+            );
+            return false;
+        }
+        self.steps += 1;
+        true
+    }
+
+    fn loop_bound(&mut self, expr: &Expr, role: &str, location: &SourceLocation) -> Option<i64> {
+        let value = match self.fold(expr, location) {
+            Ok(folded) => folded.as_number(),
+            Err(error) => {
+                self.errors.push(error);
+                return None;
+            }
+        };
+        let Some(value) = value else {
+            self.errors.push(
+                diagnostic_at_span(
+                    expr.span,
+                    location,
+                    format!("`for` range {role} must be a constant"),
+                )
+                .with_help("loop ranges can use numbers, `let` variables, and outer loop variables, but not knobs"),
+            );
+            return None;
+        };
+        if value.fract() != 0.0 {
+            self.errors.push(diagnostic_at_span(
+                expr.span,
+                location,
+                format!("`for` range {role} must be an integer, got {value}"),
+            ));
+            return None;
+        }
+        if value.abs() > MAX_LOOP_BOUND {
+            self.errors.push(diagnostic_at_span(
+                expr.span,
+                location,
+                format!("`for` range {role} {value} is too large"),
+            ));
+            return None;
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        Some(value as i64)
+    }
+
+    fn expand_deferred(&mut self, deferred: DeferredCommand, location: SourceLocation) -> bool {
+        let DeferredCommand {
+            tokens,
+            mut expressions,
+        } = deferred;
+        let scope = &self.scope;
+        let resolved = resolve_expression_command(&tokens, &mut expressions, &mut |expr| {
+            fold(expr, &|name| lookup(scope, name))
+        });
+        match resolved {
+            Ok(Some(node)) => {
+                let quit = node.is_quit();
+                self.push(node, location) && !quit
+            }
+            Ok(None) => self.push(
+                Command::Deferred(DeferredCommand {
+                    tokens,
+                    expressions,
+                }),
+                location,
+            ),
+            Err(error) => {
+                self.errors.push(with_location_source(error, &location));
+                true
+            }
+        }
+    }
+
+    fn fold(&self, expr: &Expr, location: &SourceLocation) -> Result<Expr, Diagnostic> {
+        fold(expr, &|name| lookup(&self.scope, name))
+            .map_err(|error| with_location_source(error, location))
+    }
+
+    fn push(&mut self, mut node: Command, location: SourceLocation) -> bool {
+        if let Some(frame) = self.calls.last() {
+            rename_coord_systems(&mut node, &frame.coord_systems);
+            if matches!(node, Command::Deferred(_)) {
+                let notes = self
+                    .calls
+                    .iter()
+                    .rev()
+                    .take(MAX_CALL_NOTES)
+                    .map(|frame| frame.note.clone())
+                    .collect();
+                self.call_notes.insert(self.commands.len(), notes);
+            }
+        }
+        if self.commands.len() >= MAX_EXPANDED_COMMANDS {
+            self.errors.push(diagnostic_at(
+                Some(&location),
+                format!("expanded program exceeds {MAX_EXPANDED_COMMANDS} commands"),
+            ));
+            return false;
+        }
+        self.commands.push(Spanned {
+            node,
+            span: location.span,
+            source_name: location.source_name,
+        });
+        true
+    }
+}
+
+/// Reports every coordinate system saved by `commands`, including inside `for` bodies.
+fn saved_coord_systems(commands: &[Spanned<Command>], saved: &mut dyn FnMut(&str)) {
+    for command in commands {
+        match &command.node {
+            Command::Render(RenderCommand::SaveCoordSystem(name)) => saved(name),
+            Command::For(command) => saved_coord_systems(&command.body, saved),
+            _ => {}
+        }
+    }
+}
+
+/// Rewrites coordinate-system names saved or referenced by `command` through `names`.
+fn rename_coord_systems(command: &mut Command, names: &HashMap<String, String>) {
+    let rename = |name: &mut String| {
+        if let Some(renamed) = names.get(name) {
+            renamed.clone_into(name);
+        }
+    };
+    match command {
+        Command::Render(RenderCommand::SaveCoordSystem(name)) => rename(name),
+        Command::Shape(
+            ShapeCommand::Sphere { coord_system, .. }
+            | ShapeCommand::Torus { coord_system, .. }
+            | ShapeCommand::Box { coord_system, .. }
+            | ShapeCommand::Mesh { coord_system, .. }
+            | ShapeCommand::MeshReverse { coord_system, .. }
+            | ShapeCommand::Cylinder { coord_system, .. }
+            | ShapeCommand::Cone { coord_system, .. }
+            | ShapeCommand::Pyramid { coord_system, .. },
+        ) => {
+            if let Some(name) = coord_system {
+                rename(name);
+            }
+        }
+        Command::Shape(ShapeCommand::Line {
+            p0: PointRef {
+                coord_system: c0, ..
+            },
+            p1: PointRef {
+                coord_system: c1, ..
+            },
+            ..
+        }) => {
+            for name in [c0, c1].into_iter().flatten() {
+                rename(name);
+            }
+        }
+        // Deferred commands are parsed again later, so rename the plain words they will read.
+        Command::Deferred(deferred) => {
+            for (index, token) in deferred.tokens.iter_mut().enumerate().skip(1) {
+                let is_expression = deferred.expressions.iter().any(|(at, _)| *at == index);
+                if let TokenKind::Word(word) = &mut token.kind
+                    && !is_expression
+                {
+                    rename(word);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn note_at(location: &SourceLocation, message: impl Into<String>) -> DiagnosticNote {
+    let note = DiagnosticNote::new(
+        location.span.line,
+        location.span.col_start,
+        location.span.col_end,
+        message,
+    );
+    match &location.source_name {
+        Some(source_name) => note.with_source(source_name),
+        None => note,
+    }
+}
+
+fn lookup(scope: &[(String, Expr)], name: &str) -> Option<Expr> {
+    scope
+        .iter()
+        .rev()
+        .find(|(bound, _)| bound == name)
+        .map(|(_, value)| value.clone())
+}
+
+const fn join_spans(start: Span, end: Span) -> Span {
+    Span {
+        line: start.line,
+        col_start: start.col_start,
+        col_end: end.col_end,
+    }
+}
+
 fn current_knobs(symbols: &HashMap<String, SemanticSymbol>) -> KnobMap {
     symbols
         .iter()
@@ -492,6 +1054,24 @@ fn diagnostic_at(location: Option<&SourceLocation>, message: impl Into<String>)
     }
 }
 
+fn diagnostic_at_span(
+    span: Span,
+    location: &SourceLocation,
+    message: impl Into<String>,
+) -> Diagnostic {
+    with_location_source(
+        Diagnostic::new(span.line, span.col_start, span.col_end, message),
+        location,
+    )
+}
+
+fn with_location_source(diagnostic: Diagnostic, location: &SourceLocation) -> Diagnostic {
+    match (&location.source_name, &diagnostic.source_name) {
+        (Some(source_name), None) => diagnostic.with_source(source_name),
+        _ => diagnostic,
+    }
+}
+
 fn interpolation_t(frame: usize, start: usize, end: usize) -> f64 {
     let elapsed = u32::try_from(frame - start).expect("frame range is capped by MAX_FRAMES");
     let span = u32::try_from(end - start).expect("frame range is capped by MAX_FRAMES");
@@ -511,9 +1091,9 @@ fn knob_union(list0: &KnobMap, list1: &KnobMap) -> Vec<String> {
 
 #[cfg(test)]
 mod tests {
-    use super::{MAX_FRAMES, compile};
+    use super::{MAX_CALL_DEPTH, MAX_EXPANSION_STEPS, MAX_FRAMES, compile};
     use crate::mdl::{
-        ast::{Command, TransformCommand},
+        ast::{Command, ControlCommand, RenderCommand, ShapeCommand, TransformCommand},
         parser::parse_script,
     };
 
@@ -775,6 +1355,220 @@ mod tests {
         assert!(errors[0].message.contains("require a `frames` command"));
     }
 
+    #[test]
+    fn compile_expands_loops_and_folds_let_variables() {
+        let program = parse_script(
+            "let step = 10\nfor i in 0..3 {\n  let x = i * step\n  move x (x + 1) 0\n}\nmove step 0 0",
+        )
+        .unwrap();
+        let compiled = compile(program).unwrap();
+
+        let moves = compiled
+            .commands()
+            .iter()
+            .map(|command| match &command.node {
+                Command::Transform(TransformCommand::Move { x, y, .. }) => (*x, *y),
+                other => panic!("expected folded move, got {other:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(moves, [(0.0, 1.0), (10.0, 11.0), (20.0, 21.0), (10.0, 0.0)]);
+        assert_eq!(compiled.commands()[1].span.line, 4);
+    }
+
+    #[test]
+    fn compile_keeps_knob_expressions_for_runtime() {
+        let program =
+            parse_script("frames 2\nvary k 0 1 0 1\nlet r = 5\nsphere 0 0 0 (r * k)").unwrap();
+        let compiled = compile(program).unwrap();
+
+        let Command::Deferred(sphere) = &compiled.commands()[0].node else {
+            panic!("expected deferred sphere");
+        };
+        assert_eq!(sphere.expressions.len(), 1);
+        assert!(sphere.expressions[0].1.as_number().is_none());
+    }
+
+    #[test]
+    fn compile_reports_unknown_variables_and_non_numeric_names_at_their_spans() {
+        let program = parse_script(
+            "constants shiny 1 1 1 1 1 1 1 1 1\nmove 1 (2 + missing) 0\nmove shiny 0 0",
+        )
+        .unwrap();
+        let errors = compile(program).unwrap_err();
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            (errors[0].line, errors[0].col_start, errors[0].col_end),
+            (2, 13, 19)
+        );
+        assert!(errors[0].message.contains("unknown variable `missing`"));
+        assert_eq!((errors[1].line, errors[1].col_start), (3, 6));
+        assert!(errors[1].message.contains("material constants"));
+    }
+
+    #[test]
+    fn compile_rejects_invalid_loop_ranges() {
+        let program = parse_script(
+            "set k 1\nfor i in 0..2.5 {\n}\nfor i in 5..2 {\n}\nfor i in 0..k {\n}\nfor i in 0..20000000 {\n}",
+        )
+        .unwrap();
+        let errors = compile(program).unwrap_err();
+
+        assert_eq!(errors.len(), 4);
+        assert!(errors[0].message.contains("must be an integer, got 2.5"));
+        assert_eq!((errors[0].line, errors[0].col_start), (2, 13));
+        assert!(errors[1].message.contains("greater than its end"));
+        assert!(errors[2].message.contains("must be a constant"));
+        assert!(errors[3].message.contains("iterations"));
+    }
+
+    #[test]
+    fn compile_limits_total_iterations_of_nested_empty_loops() {
+        let program = parse_script(
+            "for i in 0..1000000 {\n  for j in 0..1000000 {\n    let x = i + j\n  }\n}",
+        )
+        .unwrap();
+        let errors = compile(program).unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].message.contains(&MAX_EXPANSION_STEPS.to_string()));
+        assert_eq!(errors[0].line, 2);
+    }
+
+    #[test]
+    fn compile_reports_out_of_range_expression_arguments_once_per_loop() {
+        let program = parse_script("for i in 0..4 {\n  color (i * 100) 0 0\n}").unwrap();
+        let errors = compile(program).unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!((errors[0].line, errors[0].col_start), (2, 9));
+        assert!(errors[0].message.contains("0 to 255"));
+    }
+
+    #[test]
+    fn compile_rejects_knob_dependent_animation_plan_arguments() {
+        let program = parse_script("frames 4\nvary k 0 1 0 1\nvary j 0 (k*3) 0 1").unwrap();
+        let errors = compile(program).unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0]
+                .message
+                .contains("`vary` arguments cannot read knobs")
+        );
+        assert_eq!(errors[0].col_start, 10);
+    }
+
+    #[test]
+    fn compile_uses_loop_values_in_animation_commands() {
+        let program = parse_script("let n = 3\nframes n+1\nvary k 0 n 0 (n*10)").unwrap();
+        let compiled = compile(program).unwrap();
+
+        assert_eq!(compiled.animation().frames(), 4);
+        assert_approx_eq(compiled.animation().frame_knobs()[3]["k"], 30.0);
+    }
+
+    #[test]
+    fn compile_expands_recursive_procedures_inside_push_pop() {
+        let program = parse_script(
+            "define tree(depth, size) {\n  sphere 0 0 0 size\n  for k in 0..min(depth, 1) {\n    move 0 size 0\n    call tree(depth - 1, size / 2)\n    call tree(depth - 1, size / 2)\n  }\n}\ncall tree(3, 8)",
+        )
+        .unwrap();
+        let compiled = compile(program).unwrap();
+
+        let radii = compiled
+            .commands()
+            .iter()
+            .filter_map(|command| match &command.node {
+                Command::Shape(ShapeCommand::Sphere { radius, .. }) => Some(*radius),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(radii.len(), 15);
+        assert_eq!(radii[..4], [8.0, 4.0, 2.0, 1.0]);
+        assert_eq!(
+            compiled.commands()[0].node,
+            Command::Control(ControlCommand::Push)
+        );
+        assert_eq!(
+            compiled.commands().last().unwrap().node,
+            Command::Control(ControlCommand::Pop)
+        );
+    }
+
+    #[test]
+    fn compile_scopes_procedure_bindings_and_coordinate_systems() {
+        let program = parse_script(
+            "set k 1\nsave_coord_system world\nlet k2 = 2\ndefine part(k) {\n  save_coord_system joint\n  sphere 0 0 0 (k * k2) joint\n  box 0 0 0 1 1 1 world\n}\ncall part(3)\ncall part(4)",
+        )
+        .unwrap();
+        let compiled = compile(program).unwrap();
+
+        let mut saved = Vec::new();
+        let mut spheres = Vec::new();
+        for command in compiled.commands() {
+            match &command.node {
+                Command::Render(RenderCommand::SaveCoordSystem(name)) => saved.push(name.clone()),
+                Command::Shape(ShapeCommand::Sphere {
+                    radius,
+                    coord_system,
+                    ..
+                }) => spheres.push((*radius, coord_system.clone().unwrap())),
+                Command::Shape(ShapeCommand::Box { coord_system, .. }) => {
+                    assert_eq!(coord_system.as_deref(), Some("world"));
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(saved, ["world", "joint@part#1", "joint@part#2"]);
+        assert_eq!(
+            spheres,
+            [
+                (6.0, "joint@part#1".to_string()),
+                (8.0, "joint@part#2".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn compile_reports_procedure_errors_at_call_and_definition() {
+        let program = parse_script(
+            "define leg(len) {\n  move 0 (len + missing) 0\n}\ndefine leg() {\n}\ncall leg(1, 2)\ncall arm()\ncall leg(1)",
+        )
+        .unwrap();
+        let errors = compile(program).unwrap_err();
+
+        assert_eq!(errors.len(), 4);
+        assert!(errors[0].message.contains("already defined"));
+        assert_eq!(errors[0].notes[0].line, 1);
+        assert!(errors[1].message.contains("expects 1 argument(s), got 2"));
+        assert_eq!((errors[1].line, errors[1].notes[0].line), (6, 1));
+        assert_eq!((errors[2].line, errors[2].col_start), (7, 6));
+        assert!(errors[2].message.contains("unknown procedure `arm`"));
+        assert_eq!(errors[3].line, 2);
+        assert!(errors[3].message.contains("unknown variable `missing`"));
+        assert_eq!(errors[3].notes[0].line, 8);
+        assert!(errors[3].notes[0].message.contains("in call to `leg`"));
+    }
+
+    #[test]
+    fn compile_limits_procedure_call_depth() {
+        let program =
+            parse_script("define forever() {\n  call forever()\n}\ncall forever()").unwrap();
+        let errors = compile(program).unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0]
+                .message
+                .contains(&format!("more than {MAX_CALL_DEPTH} deep"))
+        );
+        assert_eq!(errors[0].line, 2);
+        assert_eq!(errors[0].notes[0].line, 1);
+        assert!(errors[0].notes.len() > 1);
+        assert_eq!(errors[0].notes.last().unwrap().line, 2);
+    }
+
     #[test]
     fn compile_rejects_tween_without_frames() {
         let program =
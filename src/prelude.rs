@@ -20,10 +20,13 @@ pub use crate::{
     gmath::{
         edge_matrix::EdgeMatrix,
         geometry::{
-            CameraBasis, CameraFrame, CameraPose, MovingSphereGeometry, OrthonormalBasis,
-            QuadGeometry, SphereGeometry, TriangleGeometry,
+            CameraBasis, CameraFrame, CameraPose, ConeGeometry, CurveGeometry, CurveHit, CurveKind,
+            CylinderGeometry, DiskGeometry, MovingSphereGeometry, OrthonormalBasis, QuadGeometry,
+            ShapeHit, SphereGeometry, TorusGeometry, TriangleGeometry,
         },
+        low_discrepancy::LowDiscrepancySequence,
         matrix::{Matrix, MatrixShapeError},
+        motion::{AnimatedTransform, DecomposedTransform},
         perlin::{Perlin, scale_point},
         polygon_matrix::{Bounds3, HeightMapOptions, PolygonMatrix},
         procedural::{TAU, hash01, hash01_2d, lerp, smootherstep, smoothstep},
@@ -38,6 +41,10 @@ pub use crate::{
             ProjectedSegment, RayBackground, RayBackgroundSource, RenderProgress, RenderTile,
             SamplingStrategy, ScreenPoint, sort_segments_back_to_front,
         },
+        codec::{
+            ExrChannel, ExrCompression, ExrError, ExrImage, ExrPixelType, GifDithering, GifEncoder,
+            GifOptions, GifQuantizer, PngError, PngImage,
+        },
         colors::{ColorRamp, ColorSpace, Hsl, Hsv, LinearRgb, Rgb},
         display::{
             Canvas, CanvasBuildError, Domain2D, HdrImage, PolygonColorMode, RgbImage, ShadingMode,
@@ -46,13 +53,13 @@ pub use crate::{
         draw::TexturedVertex,
         lighting::{
             LightAttenuation, Lighting, PhongMaterial, PointLight, ReflectionConstants,
-            RefractiveIndex,
+            RefractiveIndex, SpotCone,
         },
         material::SurfaceMaterial,
         scene::{SurfaceMesh, SurfaceScene},
         texture::{
-            SurfaceTexture, SurfaceTextureRef, Texture, TextureCache, TextureFilter, TextureSample,
-            TextureWrap,
+            HdrTexture, SurfaceTexture, SurfaceTextureRef, Texture, TextureCache, TextureFilter,
+            TextureSample, TextureWrap,
         },
     },
     mdl::ast::VaryInterpolation,
@@ -62,18 +69,21 @@ pub use crate::{
     gmath::ray::Ray,
     graphics::camera::RayCamera,
     graphics::raytracing::{
-        BvhBuildOptions, BvhTraversalStats, ConstantDensity, ConstantMedium, CurlNoiseField,
-        DensityField, DensityFieldRef, Dielectric, DiffuseLight, DirectLightingMode, DistanceField,
-        DistanceFieldRef, DomainWarpedDensityField, EnvironmentLight, FluidParticle,
+        BlackbodyEmission, BvhBuildOptions, BvhTraversalStats, ChromaticMedium, ConstantDensity,
+        ConstantMedium, CurlNoiseField, CurveSet, Denoiser, DensityField, DensityFieldRef,
+        DensityRange, Dielectric, DiffuseLight, DirectLightingMode, DistanceField,
+        DistanceFieldRef, DomainWarpedDensityField, EmissionField, EnvironmentLight, FluidParticle,
         FnDensityField, FnDistanceField, GgxMicrofacet, GgxReflectionPdf, GridBounds,
-        GridDensityField, GridDensityMetadata, GridInterpolation, HenyeyGreenstein,
+        GridDensityField, GridDensityMetadata, GridInterpolation, HairBsdf, HenyeyGreenstein,
         HenyeyGreensteinPdf, Hittable, HittableLayers, HittableList, Lambertian, LayeredDiffuseGgx,
-        LinearColor, LiquidSurface, MacCellFlags, MacFluidEmitter, MacFluidGrid2, MacFluidGrid3,
-        MacProjectionStats, MacScalarAdvection, MacScalarGrid3, MacStepStats, MarchingCubes,
-        MaterialRef, MatrixInstance, Metal, NonUniformMedium, NormalMap, NormalMapGreenChannel,
-        NormalMapRef, ParticleSplatField, PathTracer, ProceduralDensityField,
-        ProceduralDensityPreset, Quad, RayGeometry, RayMaterial, RayScene, RaySceneBuilder,
-        RenderOptions, RotateY, SamplingTargetList, SdfObject, Sphere, SplatKernel,
+        LinearColor, LiquidSurface, MacCellFlags, MacFlipLiquid3, MacFlipParticle, MacFluidEmitter,
+        MacFluidGrid2, MacFluidGrid3, MacParticleTransfer, MacPressurePreconditioner,
+        MacProjectionStats, MacScalarAdvection, MacScalarGrid3, MacStepStats, MajorantGrid,
+        MarchingCubes, MaterialRef, MatrixInstance, MediumCoefficients, Metal, MotionInstance,
+        NonUniformMedium, NormalMap, NormalMapGreenChannel, NormalMapRef, ParticleSplatField,
+        PathTracer, PhysicalSky, ProceduralDensityField, ProceduralDensityPreset, Quad,
+        RayGeometry, RayMaterial, RayScene, RaySceneBuilder, RenderCheckpoint, RenderOptions,
+        RotateY, SamplingTargetList, ScalarEmission, SdfObject, Sphere, SplatKernel,
         StableFluidEmitter, StableFluidGrid2, SurfaceRayMaterialMapper, SurfaceRayMaterialMode,
         Translate, TriangleMesh, WeightedSamplingTargetList, box_object,
     },
@@ -100,10 +110,12 @@ pub use crate::graphics::turtle::{Turtle, TurtleState};
 /// Math types commonly used by raster and ray renderers.
 pub mod math {
     pub use super::{
-        Bounds3, CameraBasis, CameraFrame, CameraPose, EdgeMatrix, HeightMapOptions, Matrix,
-        MatrixShapeError, MatrixStack, MovingSphereGeometry, OrthonormalBasis, Perlin, Point,
-        PolygonMatrix, QuadGeometry, Ray, SampleRng, SphereGeometry, TAU, TriangleGeometry, Vector,
-        hash01, hash01_2d, lerp, scale_point, smootherstep, smoothstep,
+        AnimatedTransform, Bounds3, CameraBasis, CameraFrame, CameraPose, ConeGeometry,
+        CurveGeometry, CurveHit, CurveKind, CylinderGeometry, DecomposedTransform, DiskGeometry,
+        EdgeMatrix, HeightMapOptions, Matrix, MatrixShapeError, MatrixStack, MovingSphereGeometry,
+        OrthonormalBasis, Perlin, Point, PolygonMatrix, QuadGeometry, Ray, SampleRng, ShapeHit,
+        SphereGeometry, TAU, TorusGeometry, TriangleGeometry, Vector, hash01, hash01_2d, lerp,
+        scale_point, smootherstep, smoothstep,
     };
 }
 
@@ -111,35 +123,40 @@ pub mod math {
 pub mod raster {
     pub use super::{
         AnimationRenderOptions, Bounds3, Camera3D, Canvas, CanvasBuildError, ColorRamp, ColorSpace,
-        Domain2D, EdgeMatrix, FrameRecorder, HdrImage, HeightMapOptions, Hsl, Hsv, Lighting,
-        LinearRgb, Matrix, MatrixShapeError, MatrixStack, PhongMaterial, PixelSampleMode, Point,
-        PointLight, PolygonColorMode, PolygonMatrix, ProgressiveRenderUpdate, ProjectedSegment,
-        ReflectionConstants, RefractiveIndex, RenderProgress, RenderTile, Rgb, RgbImage,
-        ScreenPoint, ShadingMode, SurfaceMaterial, SurfaceMesh, SurfaceScene, SurfaceTexture,
-        SurfaceTextureRef, Texture, TextureCache, TextureFilter, TextureSample, TextureWrap,
-        TexturedVertex, ToneMap, ToneMappingOperator, Vector, sort_segments_back_to_front,
+        Domain2D, EdgeMatrix, FrameRecorder, HdrImage, HdrTexture, HeightMapOptions, Hsl, Hsv,
+        Lighting, LinearRgb, Matrix, MatrixShapeError, MatrixStack, PhongMaterial, PixelSampleMode,
+        Point, PointLight, PolygonColorMode, PolygonMatrix, ProgressiveRenderUpdate,
+        ProjectedSegment, ReflectionConstants, RefractiveIndex, RenderProgress, RenderTile, Rgb,
+        RgbImage, ScreenPoint, ShadingMode, SurfaceMaterial, SurfaceMesh, SurfaceScene,
+        SurfaceTexture, SurfaceTextureRef, Texture, TextureCache, TextureFilter, TextureSample,
+        TextureWrap, TexturedVertex, ToneMap, ToneMappingOperator, Vector,
+        sort_segments_back_to_front,
     };
 }
 
 /// Path-tracing cameras, materials, primitives, scenes, volumes, SDFs, and sampling targets.
 pub mod ray {
     pub use super::{
-        AdaptiveSampling, BvhBuildOptions, BvhTraversalStats, ConstantDensity, ConstantMedium,
-        CurlNoiseField, DenoisingAovs, DensityField, DensityFieldRef, Dielectric, DiffuseLight,
-        DirectLightingMode, DistanceField, DistanceFieldRef, DomainWarpedDensityField,
-        EnvironmentLight, FluidParticle, FnDensityField, FnDistanceField, GgxMicrofacet,
-        GgxReflectionPdf, GridBounds, GridDensityField, GridDensityMetadata, GridInterpolation,
-        HdrImage, HenyeyGreenstein, HenyeyGreensteinPdf, Hittable, HittableLayers, HittableList,
-        Lambertian, LayeredDiffuseGgx, LinearColor, LiquidSurface, MacCellFlags, MacFluidEmitter,
-        MacFluidGrid2, MacFluidGrid3, MacProjectionStats, MacScalarAdvection, MacScalarGrid3,
-        MacStepStats, MarchingCubes, MaterialRef, MatrixInstance, Metal, NonUniformMedium,
-        NormalMap, NormalMapGreenChannel, NormalMapRef, ParticleSplatField, PathTracer,
-        PixelSampleMode, ProceduralDensityField, ProceduralDensityPreset, ProgressiveRenderUpdate,
-        Quad, Ray, RayBackground, RayBackgroundSource, RayCamera, RayGeometry, RayMaterial,
-        RayScene, RaySceneBuilder, RenderOptions, RenderProgress, RenderTile, RotateY, SampleRng,
-        SamplingStrategy, SamplingTargetList, SdfObject, Sphere, SplatKernel, StableFluidEmitter,
-        StableFluidGrid2, SurfaceRayMaterialMapper, SurfaceRayMaterialMode, ToneMap,
-        ToneMappingOperator, Translate, TriangleMesh, WeightedSamplingTargetList, box_object,
+        AdaptiveSampling, BlackbodyEmission, BvhBuildOptions, BvhTraversalStats, ChromaticMedium,
+        ConstantDensity, ConstantMedium, CurlNoiseField, CurveSet, Denoiser, DenoisingAovs,
+        DensityField, DensityFieldRef, DensityRange, Dielectric, DiffuseLight, DirectLightingMode,
+        DistanceField, DistanceFieldRef, DomainWarpedDensityField, EmissionField, EnvironmentLight,
+        FluidParticle, FnDensityField, FnDistanceField, GgxMicrofacet, GgxReflectionPdf,
+        GridBounds, GridDensityField, GridDensityMetadata, GridInterpolation, HairBsdf, HdrImage,
+        HdrTexture, HenyeyGreenstein, HenyeyGreensteinPdf, Hittable, HittableLayers, HittableList,
+        Lambertian, LayeredDiffuseGgx, LinearColor, LiquidSurface, MacCellFlags, MacFlipLiquid3,
+        MacFlipParticle, MacFluidEmitter, MacFluidGrid2, MacFluidGrid3, MacParticleTransfer,
+        MacPressurePreconditioner, MacProjectionStats, MacScalarAdvection, MacScalarGrid3,
+        MacStepStats, MajorantGrid, MarchingCubes, MaterialRef, MatrixInstance, MediumCoefficients,
+        Metal, MotionInstance, NonUniformMedium, NormalMap, NormalMapGreenChannel, NormalMapRef,
+        ParticleSplatField, PathTracer, PhysicalSky, PixelSampleMode, ProceduralDensityField,
+        ProceduralDensityPreset, ProgressiveRenderUpdate, Quad, Ray, RayBackground,
+        RayBackgroundSource, RayCamera, RayGeometry, RayMaterial, RayScene, RaySceneBuilder,
+        RenderCheckpoint, RenderOptions, RenderProgress, RenderTile, RotateY, SampleRng,
+        SamplingStrategy, SamplingTargetList, ScalarEmission, SdfObject, Sphere, SplatKernel,
+        StableFluidEmitter, StableFluidGrid2, SurfaceRayMaterialMapper, SurfaceRayMaterialMode,
+        ToneMap, ToneMappingOperator, Translate, TriangleMesh, WeightedSamplingTargetList,
+        box_object,
     };
 
     #[cfg(feature = "spectral")]
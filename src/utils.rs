@@ -1,6 +1,9 @@
 use std::{io, path::Path, process::Command};
 
-use crate::graphics::animation::FrameRecorder;
+use crate::graphics::{
+    animation::{FrameRecorder, encode_frame_files},
+    codec::GifOptions,
+};
 /// Returns a new animation given a file name prefix.
 ///
 /// # Arguments
@@ -9,7 +12,7 @@ use crate::graphics::animation::FrameRecorder;
 /// * `output` - The final name of the animation
 ///
 /// # Errors
-/// Returns an error if `ImageMagick` fails or no matching frames exist.
+/// Returns an error if a frame cannot be read, the GIF cannot be written, or no matching frames exist.
 pub fn animation_from_prefix(frame_prefix: &str, output: &str) -> io::Result<()> {
     println!("Making a new animation: {output}");
     encode_existing_frames(frame_prefix, output, 2)?;
@@ -20,7 +23,7 @@ pub fn animation_from_prefix(frame_prefix: &str, output: &str) -> io::Result<()>
 /// Encodes an explicit recorder's frames.
 ///
 /// # Errors
-/// Returns an error if `ImageMagick` fails.
+/// Returns an error if a frame cannot be read or the GIF cannot be written.
 pub fn animation(recorder: &FrameRecorder, output: &str) -> io::Result<()> {
     println!("Making a new animation: {output}");
     recorder.encode_gif(output)?;
@@ -45,27 +48,11 @@ fn encode_existing_frames(frame_prefix: &str, output: &str, delay_cs: u16) -> io
         ));
     }
 
-    let mut command = Command::new("magick");
-    command.arg("-delay").arg(delay_cs.to_string());
-    for frame in frames {
-        command.arg(frame);
-    }
-    command.arg(Path::new(output));
-
-    let status = command.status().map_err(|err| {
-        io::Error::new(
-            err.kind(),
-            format!(
-                "failed to run ImageMagick `magick`; is ImageMagick installed and in PATH? {err}"
-            ),
-        )
-    })?;
-    if !status.success() {
-        return Err(io::Error::other(format!(
-            "ImageMagick `magick` failed with status {status} while encoding animation"
-        )));
-    }
-    Ok(())
+    encode_frame_files(
+        frames.iter().map(|frame| (frame, delay_cs)),
+        GifOptions::default(),
+        Path::new(output),
+    )
 }
 
 /// Open's a given animation